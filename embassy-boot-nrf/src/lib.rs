@@ -5,7 +5,7 @@ mod fmt;
 
 pub use embassy_boot::{
     AlignedBuffer, BlockingFirmwareState, BlockingFirmwareUpdater, BootError, BootLoaderConfig, FirmwareState,
-    FirmwareUpdater, FirmwareUpdaterConfig,
+    FirmwareUpdater, FirmwareUpdaterConfig, PartitionRange, PartitionTable,
 };
 use embassy_nrf::nvmc::PAGE_SIZE;
 use embassy_nrf::peripherals::WDT;
@@ -35,11 +35,20 @@ impl<const BUFFER_SIZE: usize> BootLoader<BUFFER_SIZE> {
 
     /// Boots the application without softdevice mechanisms.
     ///
+    /// If the active partition starts with an image header, the vector table is expected at
+    /// [`embassy_boot::IMAGE_VECTOR_TABLE_OFFSET`] from `start` instead.
+    ///
     /// # Safety
     ///
     /// This modifies the stack pointer and reset vector and will run code placed in the active partition.
     #[cfg(not(feature = "softdevice"))]
     pub unsafe fn load(self, start: u32) -> ! {
+        // Images with a header have their vector table after it
+        let start = if core::ptr::read_volatile(start as *const u32) == embassy_boot::IMAGE_HEADER_MAGIC {
+            start + embassy_boot::IMAGE_VECTOR_TABLE_OFFSET as u32
+        } else {
+            start
+        };
         let mut p = cortex_m::Peripherals::steal();
         p.SCB.invalidate_icache();
         p.SCB.vtor.write(start);
@@ -48,11 +57,16 @@ impl<const BUFFER_SIZE: usize> BootLoader<BUFFER_SIZE> {
 
     /// Boots the application assuming softdevice is present.
     ///
+    /// If the active partition starts with an image header, the vector table is expected at
+    /// [`embassy_boot::IMAGE_VECTOR_TABLE_OFFSET`] from `app` instead. As the softdevice starts the
+    /// application at the start of the active partition, such images are started directly, and
+    /// must point the softdevice interrupt forwarding at their vector table before enabling it.
+    ///
     /// # Safety
     ///
     /// This modifies the stack pointer and reset vector and will run code placed in the active partition.
     #[cfg(feature = "softdevice")]
-    pub unsafe fn load(self, _app: u32) -> ! {
+    pub unsafe fn load(self, app: u32) -> ! {
         use nrf_softdevice_mbr as mbr;
         const NRF_SUCCESS: u32 = 0;
 
@@ -67,8 +81,14 @@ impl<const BUFFER_SIZE: usize> BootLoader<BUFFER_SIZE> {
         let ret = mbr::sd_mbr_command(&mut cmd);
         assert_eq!(ret, NRF_SUCCESS);
 
-        let msp = *(addr as *const u32);
-        let rv = *((addr + 4) as *const u32);
+        // Images with a header have their vector table after it
+        let start = if core::ptr::read_volatile(app as *const u32) == embassy_boot::IMAGE_HEADER_MAGIC {
+            app + embassy_boot::IMAGE_VECTOR_TABLE_OFFSET as u32
+        } else {
+            addr
+        };
+        let msp = *(start as *const u32);
+        let rv = *((start + 4) as *const u32);
 
         trace!("msp = {=u32:x}, rv = {=u32:x}", msp, rv);
 
@@ -78,7 +98,7 @@ impl<const BUFFER_SIZE: usize> BootLoader<BUFFER_SIZE> {
         // * Synchronize instruction barrier
         // * Initialize stack pointer (0x1000)
         // * Set link register to not return (0xFF)
-        // * Jump to softdevice reset vector
+        // * Jump to the softdevice or application reset vector
         core::arch::asm!(
             "mrs {tmp}, CONTROL",
             "bics {tmp}, {spsel}",
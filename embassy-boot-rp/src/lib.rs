@@ -5,7 +5,7 @@ mod fmt;
 
 pub use embassy_boot::{
     AlignedBuffer, BlockingFirmwareState, BlockingFirmwareUpdater, BootError, BootLoaderConfig, FirmwareState,
-    FirmwareUpdater, FirmwareUpdaterConfig, State,
+    FirmwareUpdater, FirmwareUpdaterConfig, PartitionRange, PartitionTable, State,
 };
 use embassy_rp::flash::{Blocking, Flash, ERASE_SIZE};
 use embassy_rp::peripherals::{FLASH, WATCHDOG};
@@ -36,10 +36,19 @@ impl<const BUFFER_SIZE: usize> BootLoader<BUFFER_SIZE> {
 
     /// Boots the application.
     ///
+    /// If the active partition starts with an image header, the vector table is expected at
+    /// [`embassy_boot::IMAGE_VECTOR_TABLE_OFFSET`] from `start` instead.
+    ///
     /// # Safety
     ///
     /// This modifies the stack pointer and reset vector and will run code placed in the active partition.
     pub unsafe fn load(self, start: u32) -> ! {
+        // Images with a header have their vector table after it
+        let start = if core::ptr::read_volatile(start as *const u32) == embassy_boot::IMAGE_HEADER_MAGIC {
+            start + embassy_boot::IMAGE_VECTOR_TABLE_OFFSET as u32
+        } else {
+            start
+        };
         trace!("Loading app at 0x{:x}", start);
         #[allow(unused_mut)]
         let mut p = cortex_m::Peripherals::steal();
@@ -4,14 +4,19 @@
 mod fmt;
 
 pub use embassy_boot::{
-    AlignedBuffer, BlockingFirmwareState, BlockingFirmwareUpdater, BootError, BootLoaderConfig, FirmwareState,
-    FirmwareUpdater, FirmwareUpdaterConfig, State,
+    AlignedBuffer, BlockingFirmwareState, BlockingFirmwareUpdater, BootError, BootLoaderConfig, FailedBoots,
+    FirmwareState, FirmwareUpdater, FirmwareUpdaterConfig, HoldButton, MagicValue, PartitionRange, PartitionTable,
+    RecoveryPolicy, State,
 };
 use embedded_storage::nor_flash::NorFlash;
 
 /// A bootloader for STM32 devices.
 pub struct BootLoader {
     /// The reported state of the bootloader after preparing for boot
+    ///
+    /// After reverting an update, this is `State::Revert` instead of `State::Boot` until the
+    /// application marks the previous firmware booted. `State` is non-exhaustive, so matches on
+    /// it need a wildcard arm.
     pub state: State,
 }
 
@@ -33,12 +38,33 @@ impl BootLoader {
         Ok(Self { state })
     }
 
+    /// Inspect the bootloader state and perform actions required before booting, then report
+    /// [`State::DfuDetach`] if `policy` forces recovery.
+    pub fn try_prepare_with_recovery<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash, const BUFFER_SIZE: usize>(
+        config: BootLoaderConfig<ACTIVE, DFU, STATE>,
+        policy: &mut impl RecoveryPolicy,
+    ) -> Result<Self, BootError> {
+        let mut aligned_buf = AlignedBuffer([0; BUFFER_SIZE]);
+        let mut boot = embassy_boot::BootLoader::new(config);
+        let state = boot.prepare_boot_with_recovery(aligned_buf.as_mut(), policy)?;
+        Ok(Self { state })
+    }
+
     /// Boots the application.
     ///
+    /// If the active partition starts with an image header, the vector table is expected at
+    /// [`embassy_boot::IMAGE_VECTOR_TABLE_OFFSET`] from `start` instead.
+    ///
     /// # Safety
     ///
     /// This modifies the stack pointer and reset vector and will run code placed in the active partition.
     pub unsafe fn load(self, start: u32) -> ! {
+        // Images with a header have their vector table after it
+        let start = if core::ptr::read_volatile(start as *const u32) == embassy_boot::IMAGE_HEADER_MAGIC {
+            start + embassy_boot::IMAGE_VECTOR_TABLE_OFFSET as u32
+        } else {
+            start
+        };
         trace!("Loading app at 0x{:x}", start);
         #[allow(unused_mut)]
         let mut p = cortex_m::Peripherals::steal();
@@ -5,7 +5,12 @@ use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embassy_sync::blocking_mutex::Mutex;
 use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
 
-use crate::{State, BOOT_MAGIC, DFU_DETACH_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC};
+use crate::cipher::{apply_to_image, Plaintext};
+use crate::image::{read_header, ImageInfo};
+#[cfg(feature = "robust-state")]
+use crate::state_record::{state_record_offsets, StateRecord, STATE_RECORD_LEN};
+use crate::{CipherStream, ImageCipher, PartitionTable, Progress, RecoveryPolicy};
+use crate::{State, BOOT_MAGIC, DFU_DETACH_MAGIC, REVERT_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC};
 
 /// Errors returned by bootloader
 #[derive(PartialEq, Eq, Debug)]
@@ -14,6 +19,8 @@ pub enum BootError {
     Flash(NorFlashErrorKind),
     /// Invalid bootloader magic
     BadMagic,
+    /// No slot holds a bootable image.
+    NoValidImage,
 }
 
 #[cfg(feature = "defmt")]
@@ -22,6 +29,7 @@ impl defmt::Format for BootError {
         match self {
             BootError::Flash(_) => defmt::write!(fmt, "BootError::Flash(_)"),
             BootError::BadMagic => defmt::write!(fmt, "BootError::BadMagic"),
+            BootError::NoValidImage => defmt::write!(fmt, "BootError::NoValidImage"),
         }
     }
 }
@@ -127,6 +135,37 @@ impl<'a, ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash>
 
         Self { active, dfu, state }
     }
+
+    /// Constructs a `BootLoaderConfig` instance from flash memory and a partition table read from flash,
+    /// as an alternative to [`BootLoaderConfig::from_linkerfile_blocking`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// let layout = Flash::new_blocking(p.FLASH).into_blocking_regions();
+    /// let flash = Mutex::new(RefCell::new(layout.bank1_region));
+    ///
+    /// let table = flash
+    ///     .lock(|f| PartitionTable::read_blocking(&mut *f.borrow_mut(), PARTITION_TABLE_OFFSET))
+    ///     .unwrap()
+    ///     .expect("no partition table");
+    /// let config = BootLoaderConfig::from_partition_table_blocking(&flash, &flash, &flash, &table);
+    /// ```
+    pub fn from_partition_table_blocking(
+        active_flash: &'a Mutex<NoopRawMutex, RefCell<ACTIVE>>,
+        dfu_flash: &'a Mutex<NoopRawMutex, RefCell<DFU>>,
+        state_flash: &'a Mutex<NoopRawMutex, RefCell<STATE>>,
+        table: &PartitionTable,
+    ) -> Self {
+        trace!("ACTIVE: 0x{:x} - 0x{:x}", table.active.start, table.active.end);
+        trace!("DFU: 0x{:x} - 0x{:x}", table.dfu.start, table.dfu.end);
+        trace!("STATE: 0x{:x} - 0x{:x}", table.state.start, table.state.end);
+
+        Self {
+            active: BlockingPartition::new(active_flash, table.active.start, table.active.size()),
+            dfu: BlockingPartition::new(dfu_flash, table.dfu.start, table.dfu.size()),
+            state: BlockingPartition::new(state_flash, table.state.start, table.state.size()),
+        }
+    }
 }
 
 /// BootLoader works with any flash implementing embedded_storage.
@@ -136,19 +175,26 @@ pub struct BootLoader<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> {
     /// The state partition has the following format:
     /// All ranges are in multiples of WRITE_SIZE bytes.
     /// | Range    | Description                                                                      |
-    /// | 0..1     | Magic indicating bootloader state. BOOT_MAGIC means boot, SWAP_MAGIC means swap. |
+    /// | 0..1     | Magic indicating bootloader state. BOOT_MAGIC means boot, SWAP_MAGIC means swap, |
+    /// |          | REVERT_MAGIC means boot after reverting an update.                               |
     /// | 1..2     | Progress validity. ERASE_VALUE means valid, !ERASE_VALUE means invalid.          |
     /// | 2..2 + N | Progress index used while swapping or reverting      
+    ///
+    /// With the `robust-state` feature, the magic is unused and the state is stored in two copies
+    /// of a checksummed record instead, each in its own erase sector following the progress.
+    ///
+    /// With the `resumable-dfu` feature, an erase sector following them is reserved for the DFU
+    /// download progress, and with the `rollback-protection` feature, the last erase sector of the
+    /// partition is reserved for the rollback counter.
     state: STATE,
 }
 
 impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, STATE> {
     /// Get the page size which is the "unit of operation" within the bootloader.
-    const PAGE_SIZE: u32 = if ACTIVE::ERASE_SIZE > DFU::ERASE_SIZE {
-        ACTIVE::ERASE_SIZE as u32
-    } else {
-        DFU::ERASE_SIZE as u32
-    };
+    ///
+    /// This is the lowest common multiple of the ACTIVE and DFU erase sizes, so that a page always
+    /// covers whole erase sectors in both partitions.
+    const PAGE_SIZE: u32 = lcm(ACTIVE::ERASE_SIZE as u32, DFU::ERASE_SIZE as u32);
 
     /// Create a new instance of a bootloader with the flash partitions.
     ///
@@ -235,6 +281,76 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
     /// |       DFU |            3 |      4 |      5 |      6 |      3 |
     ///
     pub fn prepare_boot(&mut self, aligned_buf: &mut [u8]) -> Result<State, BootError> {
+        self.prepare_boot_with_progress(aligned_buf, |_| {})
+    }
+
+    /// Perform necessary boot preparations like swapping images, with an encrypted DFU partition.
+    ///
+    /// Same as [`BootLoader::prepare_boot`], except that the update in the DFU partition is
+    /// decrypted with `cipher` while copying it to the active partition, and the previous firmware
+    /// is encrypted while copying it to the DFU partition, with the nonce from the header of the
+    /// update. Updates without an image header are cancelled. See [`ImageCipher`].
+    pub fn prepare_boot_with_cipher<C: ImageCipher>(
+        &mut self,
+        aligned_buf: &mut [u8],
+        cipher: &mut C,
+    ) -> Result<State, BootError> {
+        self.prepare_boot_with_cipher_and_progress(aligned_buf, cipher, |_| {})
+    }
+
+    /// Perform necessary boot preparations like swapping images, reporting progress.
+    ///
+    /// Same as [`BootLoader::prepare_boot`], except that `progress` is called after each page is
+    /// swapped or reverted, with the number of pages done and the total number of pages, for
+    /// example to show the progress on a display.
+    pub fn prepare_boot_with_progress(
+        &mut self,
+        aligned_buf: &mut [u8],
+        progress: impl FnMut(Progress),
+    ) -> Result<State, BootError> {
+        self.prepare_boot_inner(aligned_buf, &mut Plaintext, false, progress)
+    }
+
+    /// Perform necessary boot preparations like swapping images, then check whether `policy` forces
+    /// recovery.
+    ///
+    /// Same as [`BootLoader::prepare_boot`], except that [`State::DfuDetach`] is returned when
+    /// `policy` forces recovery, for the bootloader to enter its DFU or serial recovery mode. The
+    /// policy is only checked once any pending swap or revert has completed, so that the active
+    /// partition is never left half swapped.
+    pub fn prepare_boot_with_recovery(
+        &mut self,
+        aligned_buf: &mut [u8],
+        policy: &mut impl RecoveryPolicy,
+    ) -> Result<State, BootError> {
+        let state = self.prepare_boot(aligned_buf)?;
+        if policy.should_recover() {
+            Ok(State::DfuDetach)
+        } else {
+            Ok(state)
+        }
+    }
+
+    /// Perform necessary boot preparations like swapping images, with an encrypted DFU partition and
+    /// reporting progress.
+    ///
+    /// See [`BootLoader::prepare_boot_with_cipher`] and [`BootLoader::prepare_boot_with_progress`].
+    pub fn prepare_boot_with_cipher_and_progress<C: ImageCipher>(
+        &mut self,
+        aligned_buf: &mut [u8],
+        cipher: &mut C,
+        progress: impl FnMut(Progress),
+    ) -> Result<State, BootError> {
+        self.prepare_boot_inner(aligned_buf, cipher, true, progress)
+    }
+
+    fn prepare_boot_inner<C: ImageCipher>(
+        &mut self,
+        aligned_buf: &mut [u8],
+        cipher: &mut C,
+        encrypted: bool,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<State, BootError> {
         // Ensure we have enough progress pages to store copy progress
         assert_eq!(0, Self::PAGE_SIZE % aligned_buf.len() as u32);
         assert_eq!(0, Self::PAGE_SIZE % ACTIVE::WRITE_SIZE as u32);
@@ -242,6 +358,8 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
         assert_eq!(0, Self::PAGE_SIZE % DFU::WRITE_SIZE as u32);
         assert_eq!(0, Self::PAGE_SIZE % DFU::ERASE_SIZE as u32);
         assert!(aligned_buf.len() >= STATE::WRITE_SIZE);
+        #[cfg(feature = "robust-state")]
+        assert!(aligned_buf.len() >= Self::STATE_RECORD_SIZE);
         assert_eq!(0, aligned_buf.len() % ACTIVE::WRITE_SIZE);
         assert_eq!(0, aligned_buf.len() % DFU::WRITE_SIZE);
 
@@ -256,28 +374,119 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
             // since the app has failed to mark boot as successful
             //
             if !self.is_swapped(aligned_buf)? {
+                if !self.dfu_image_fits(aligned_buf)? {
+                    warn!("Update does not fit in the active partition, cancelling it");
+                    self.set_state(aligned_buf, BOOT_MAGIC)?;
+                    return Ok(State::Boot);
+                }
+                let Some(nonce) = self.update_nonce(aligned_buf, encrypted)? else {
+                    warn!("Encrypted update has no image header, cancelling it");
+                    self.set_state(aligned_buf, BOOT_MAGIC)?;
+                    return Ok(State::Boot);
+                };
+
                 trace!("Swapping");
-                self.swap(aligned_buf)?;
+                self.swap(aligned_buf, cipher, &nonce, &mut progress)?;
                 trace!("Swapping done");
             } else {
+                // The header was checked before swapping.
+                let nonce = self.update_nonce(aligned_buf, encrypted)?.ok_or(BootError::BadMagic)?;
+
                 trace!("Reverting");
-                self.revert(aligned_buf)?;
+                self.revert(aligned_buf, cipher, &nonce, &mut progress)?;
+                self.set_state(aligned_buf, REVERT_MAGIC)?;
+            }
+        }
+        Ok(state)
+    }
 
-                let state_word = &mut aligned_buf[..STATE::WRITE_SIZE];
+    // Invalidate and clear the swap progress, and go back to booting the active partition with
+    // `magic`, either BOOT_MAGIC or REVERT_MAGIC.
+    fn set_state(&mut self, aligned_buf: &mut [u8], magic: u8) -> Result<(), BootError> {
+        let state_word = &mut aligned_buf[..STATE::WRITE_SIZE];
 
-                // Invalidate progress
-                state_word.fill(!STATE_ERASE_VALUE);
-                self.state.write(STATE::WRITE_SIZE as u32, state_word)?;
+        // Invalidate progress
+        state_word.fill(!STATE_ERASE_VALUE);
+        self.state.write(STATE::WRITE_SIZE as u32, state_word)?;
+
+        // The record is written before clearing the progress, so that a power loss in between
+        // leaves the new state with invalid progress, which is ignored. Clearing the progress first
+        // would leave the swap state with no page swapped, which would swap the update in again.
+        #[cfg(feature = "robust-state")]
+        {
+            let current = self.read_state_record(aligned_buf)?;
+            self.write_state_record(StateRecord::next(current, magic), aligned_buf)?;
+        }
 
-                // Clear magic and progress
-                self.state.erase(0, self.state.capacity() as u32)?;
+        // Clear magic and progress
+        let state_len = crate::state_len(self.state.capacity(), STATE::ERASE_SIZE);
+        self.state.erase(0, state_len as u32)?;
 
-                // Set magic
-                state_word.fill(BOOT_MAGIC);
-                self.state.write(0, state_word)?;
-            }
+        // Set magic
+        #[cfg(not(feature = "robust-state"))]
+        {
+            let state_word = &mut aligned_buf[..STATE::WRITE_SIZE];
+            state_word.fill(magic);
+            self.state.write(0, state_word)?;
         }
-        Ok(state)
+        Ok(())
+    }
+
+    /// Read the bootloader state, without acting on it.
+    ///
+    /// The `aligned_buf` requirements are the same as for `prepare_boot`.
+    pub fn get_state(&mut self, aligned_buf: &mut [u8]) -> Result<State, BootError> {
+        self.read_state(aligned_buf)
+    }
+
+    /// Number of pages of the active partition swapped so far, for an update whose swap has not
+    /// completed.
+    ///
+    /// Unlike the application, the bootloader knows how much of a swap is done. This returns `None`
+    /// unless the state is [`State::Swap`] and the swap was interrupted by a reset, or has not
+    /// started yet, in which case `prepare_boot` resumes it. The `aligned_buf` requirements are the
+    /// same as for `prepare_boot`.
+    pub fn swap_progress(&mut self, aligned_buf: &mut [u8]) -> Result<Option<usize>, BootError> {
+        if self.read_state(aligned_buf)? != State::Swap {
+            return Ok(None);
+        }
+
+        let page_count = self.active.capacity() / Self::PAGE_SIZE as usize;
+        let progress = self.current_progress(aligned_buf)?;
+        Ok((progress < 2 * page_count).then_some(progress / 2))
+    }
+
+    // If the update has an image header, check that the image fits in the active partition. This is
+    // only done before swapping starts, as the header is moved around while swapping.
+    fn dfu_image_fits(&mut self, aligned_buf: &mut [u8]) -> Result<bool, BootError> {
+        if self.current_progress(aligned_buf)? != 0 {
+            return Ok(true);
+        }
+
+        let header = read_header(&mut self.dfu, aligned_buf)?;
+        Ok(match ImageInfo::parse(&header) {
+            Some(info) => info.update_len() as usize <= self.active.capacity(),
+            None => true,
+        })
+    }
+
+    // Read the nonce from the header of an encrypted update, returning `None` if it has no header.
+    //
+    // Swapping copies the first page last and leaves the first DFU page as is, so the header of the
+    // update stays at the start of the DFU partition. Reverting copies the first page back to DFU
+    // first, while the header is still at the start of the active partition.
+    fn update_nonce(&mut self, aligned_buf: &mut [u8], encrypted: bool) -> Result<Option<[u8; 16]>, BootError> {
+        if !encrypted {
+            return Ok(Some([0; 16]));
+        }
+
+        let page_count = self.active.capacity() / Self::PAGE_SIZE as usize;
+        let header = if self.current_progress(aligned_buf)? == page_count * 2 {
+            read_header(&mut self.active, aligned_buf)?
+        } else {
+            read_header(&mut self.dfu, aligned_buf)?
+        };
+        Ok(ImageInfo::parse(&header).map(|info| info.nonce))
     }
 
     fn is_swapped(&mut self, aligned_buf: &mut [u8]) -> Result<bool, BootError> {
@@ -289,7 +498,8 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
 
     fn current_progress(&mut self, aligned_buf: &mut [u8]) -> Result<usize, BootError> {
         let write_size = STATE::WRITE_SIZE as u32;
-        let max_index = ((self.state.capacity() - STATE::WRITE_SIZE) / STATE::WRITE_SIZE) - 2;
+        let state_len = crate::state_len(self.state.capacity(), STATE::ERASE_SIZE);
+        let max_index = ((state_len - STATE::WRITE_SIZE) / STATE::WRITE_SIZE) - 2;
         let state_word = &mut aligned_buf[..write_size as usize];
 
         self.state.read(write_size, state_word)?;
@@ -316,21 +526,26 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
         Ok(())
     }
 
-    fn copy_page_once_to_active(
+    #[allow(clippy::too_many_arguments)]
+    fn copy_page_once_to_active<C: ImageCipher>(
         &mut self,
         progress_index: usize,
         from_offset: u32,
         to_offset: u32,
         aligned_buf: &mut [u8],
+        cipher: &mut C,
+        stream: CipherStream,
+        nonce: &[u8; 16],
     ) -> Result<(), BootError> {
         if self.current_progress(aligned_buf)? <= progress_index {
-            let page_size = Self::PAGE_SIZE as u32;
+            let page_size = Self::PAGE_SIZE;
 
             self.active.erase(to_offset, to_offset + page_size)?;
 
             for offset_in_page in (0..page_size).step_by(aligned_buf.len()) {
-                self.dfu.read(from_offset + offset_in_page as u32, aligned_buf)?;
-                self.active.write(to_offset + offset_in_page as u32, aligned_buf)?;
+                self.dfu.read(from_offset + offset_in_page, aligned_buf)?;
+                apply_to_image(cipher, stream, nonce, to_offset + offset_in_page, aligned_buf);
+                self.active.write(to_offset + offset_in_page, aligned_buf)?;
             }
 
             self.update_progress(progress_index, aligned_buf)?;
@@ -338,21 +553,26 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
         Ok(())
     }
 
-    fn copy_page_once_to_dfu(
+    #[allow(clippy::too_many_arguments)]
+    fn copy_page_once_to_dfu<C: ImageCipher>(
         &mut self,
         progress_index: usize,
         from_offset: u32,
         to_offset: u32,
         aligned_buf: &mut [u8],
+        cipher: &mut C,
+        stream: CipherStream,
+        nonce: &[u8; 16],
     ) -> Result<(), BootError> {
         if self.current_progress(aligned_buf)? <= progress_index {
-            let page_size = Self::PAGE_SIZE as u32;
+            let page_size = Self::PAGE_SIZE;
 
-            self.dfu.erase(to_offset as u32, to_offset + page_size)?;
+            self.dfu.erase(to_offset, to_offset + page_size)?;
 
             for offset_in_page in (0..page_size).step_by(aligned_buf.len()) {
-                self.active.read(from_offset + offset_in_page as u32, aligned_buf)?;
-                self.dfu.write(to_offset + offset_in_page as u32, aligned_buf)?;
+                self.active.read(from_offset + offset_in_page, aligned_buf)?;
+                apply_to_image(cipher, stream, nonce, from_offset + offset_in_page, aligned_buf);
+                self.dfu.write(to_offset + offset_in_page, aligned_buf)?;
             }
 
             self.update_progress(progress_index, aligned_buf)?;
@@ -360,7 +580,13 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
         Ok(())
     }
 
-    fn swap(&mut self, aligned_buf: &mut [u8]) -> Result<(), BootError> {
+    fn swap<C: ImageCipher>(
+        &mut self,
+        aligned_buf: &mut [u8],
+        cipher: &mut C,
+        nonce: &[u8; 16],
+        progress: &mut impl FnMut(Progress),
+    ) -> Result<(), BootError> {
         let page_count = self.active.capacity() as u32 / Self::PAGE_SIZE;
         for page_num in 0..page_count {
             let progress_index = (page_num * 2) as usize;
@@ -369,19 +595,46 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
             let active_from_offset = (page_count - 1 - page_num) * Self::PAGE_SIZE;
             let dfu_to_offset = (page_count - page_num) * Self::PAGE_SIZE;
             //trace!("Copy active {} to dfu {}", active_from_offset, dfu_to_offset);
-            self.copy_page_once_to_dfu(progress_index, active_from_offset, dfu_to_offset, aligned_buf)?;
+            self.copy_page_once_to_dfu(
+                progress_index,
+                active_from_offset,
+                dfu_to_offset,
+                aligned_buf,
+                cipher,
+                CipherStream::Backup,
+                nonce,
+            )?;
 
             // Copy DFU page to the active page
             let active_to_offset = (page_count - 1 - page_num) * Self::PAGE_SIZE;
             let dfu_from_offset = (page_count - 1 - page_num) * Self::PAGE_SIZE;
             //trace!("Copy dfy {} to active {}", dfu_from_offset, active_to_offset);
-            self.copy_page_once_to_active(progress_index + 1, dfu_from_offset, active_to_offset, aligned_buf)?;
+            self.copy_page_once_to_active(
+                progress_index + 1,
+                dfu_from_offset,
+                active_to_offset,
+                aligned_buf,
+                cipher,
+                CipherStream::Update,
+                nonce,
+            )?;
+
+            progress(Progress {
+                done: page_num as usize + 1,
+                total: page_count as usize,
+            });
         }
 
         Ok(())
     }
 
-    fn revert(&mut self, aligned_buf: &mut [u8]) -> Result<(), BootError> {
+    fn revert<C: ImageCipher>(
+        &mut self,
+        aligned_buf: &mut [u8],
+        cipher: &mut C,
+        nonce: &[u8; 16],
+        progress: &mut impl FnMut(Progress),
+    ) -> Result<(), BootError> {
         let page_count = self.active.capacity() as u32 / Self::PAGE_SIZE;
         for page_num in 0..page_count {
             let progress_index = (page_count * 2 + page_num * 2) as usize;
@@ -389,17 +642,39 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
             // Copy the bad active page to the DFU page
             let active_from_offset = page_num * Self::PAGE_SIZE;
             let dfu_to_offset = page_num * Self::PAGE_SIZE;
-            self.copy_page_once_to_dfu(progress_index, active_from_offset, dfu_to_offset, aligned_buf)?;
+            self.copy_page_once_to_dfu(
+                progress_index,
+                active_from_offset,
+                dfu_to_offset,
+                aligned_buf,
+                cipher,
+                CipherStream::Update,
+                nonce,
+            )?;
 
             // Copy the DFU page back to the active page
             let active_to_offset = page_num * Self::PAGE_SIZE;
             let dfu_from_offset = (page_num + 1) * Self::PAGE_SIZE;
-            self.copy_page_once_to_active(progress_index + 1, dfu_from_offset, active_to_offset, aligned_buf)?;
+            self.copy_page_once_to_active(
+                progress_index + 1,
+                dfu_from_offset,
+                active_to_offset,
+                aligned_buf,
+                cipher,
+                CipherStream::Backup,
+                nonce,
+            )?;
+
+            progress(Progress {
+                done: page_num as usize + 1,
+                total: page_count as usize,
+            });
         }
 
         Ok(())
     }
 
+    #[cfg(not(feature = "robust-state"))]
     fn read_state(&mut self, aligned_buf: &mut [u8]) -> Result<State, BootError> {
         let state_word = &mut aligned_buf[..STATE::WRITE_SIZE];
         self.state.read(0, state_word)?;
@@ -408,10 +683,73 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
             Ok(State::Swap)
         } else if !state_word.iter().any(|&b| b != DFU_DETACH_MAGIC) {
             Ok(State::DfuDetach)
+        } else if !state_word.iter().any(|&b| b != REVERT_MAGIC) {
+            Ok(State::Revert)
         } else {
             Ok(State::Boot)
         }
     }
+
+    #[cfg(feature = "robust-state")]
+    fn read_state(&mut self, aligned_buf: &mut [u8]) -> Result<State, BootError> {
+        Ok(match self.read_state_record(aligned_buf)?.map(|r| r.magic) {
+            Some(SWAP_MAGIC) => State::Swap,
+            Some(DFU_DETACH_MAGIC) => State::DfuDetach,
+            Some(REVERT_MAGIC) => State::Revert,
+            _ => State::Boot,
+        })
+    }
+
+    // Size of a state record, padded to the state partition read and write sizes.
+    #[cfg(feature = "robust-state")]
+    const STATE_RECORD_SIZE: usize = STATE_RECORD_LEN.next_multiple_of(if STATE::READ_SIZE > STATE::WRITE_SIZE {
+        STATE::READ_SIZE
+    } else {
+        STATE::WRITE_SIZE
+    });
+
+    #[cfg(feature = "robust-state")]
+    fn read_state_record(&mut self, aligned_buf: &mut [u8]) -> Result<Option<StateRecord>, BootError> {
+        let mut copies = [None; 2];
+        for (copy, offset) in copies
+            .iter_mut()
+            .zip(state_record_offsets(self.state.capacity(), STATE::ERASE_SIZE))
+        {
+            let record = &mut aligned_buf[..Self::STATE_RECORD_SIZE];
+            self.state.read(offset as u32, record)?;
+            *copy = StateRecord::decode(record[..STATE_RECORD_LEN].try_into().unwrap());
+        }
+        Ok(StateRecord::newest(copies[0], copies[1]))
+    }
+
+    // Write both copies of the state record, one after the other.
+    #[cfg(feature = "robust-state")]
+    fn write_state_record(&mut self, record: StateRecord, aligned_buf: &mut [u8]) -> Result<(), BootError> {
+        let buf = &mut aligned_buf[..Self::STATE_RECORD_SIZE];
+        buf.fill(STATE_ERASE_VALUE);
+        buf[..STATE_RECORD_LEN].copy_from_slice(&record.encode());
+        for offset in state_record_offsets(self.state.capacity(), STATE::ERASE_SIZE) {
+            self.state.erase(offset as u32, (offset + STATE::ERASE_SIZE) as u32)?;
+            self.state.write(offset as u32, buf)?;
+        }
+        Ok(())
+    }
+}
+
+const fn gcd(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+const fn lcm(a: u32, b: u32) -> u32 {
+    if a == 0 || b == 0 {
+        return a | b;
+    }
+    a / gcd(a, b) * b
 }
 
 fn assert_partitions<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash>(
@@ -424,7 +762,8 @@ fn assert_partitions<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash>(
     assert_eq!(dfu.capacity() as u32 % page_size, 0);
     // DFU partition has to be bigger than ACTIVE partition to handle swap algorithm
     assert!(dfu.capacity() as u32 - active.capacity() as u32 >= page_size);
-    assert!(2 + 2 * (active.capacity() as u32 / page_size) <= state.capacity() as u32 / STATE::WRITE_SIZE as u32);
+    let state_len = crate::state_len(state.capacity(), STATE::ERASE_SIZE);
+    assert!(2 + 2 * (active.capacity() as u32 / page_size) <= state_len as u32 / STATE::WRITE_SIZE as u32);
 }
 
 #[cfg(test)]
@@ -443,4 +782,14 @@ mod tests {
         static STATE: MemFlash<STATE_SIZE, 4, 4> = MemFlash::new(0xFF);
         assert_partitions(&ACTIVE, &DFU, &STATE, 4096);
     }
+
+    #[test]
+    fn test_page_size_covers_both_erase_sizes() {
+        type Loader<const A: usize, const D: usize> =
+            BootLoader<MemFlash<0, A, 4>, MemFlash<0, D, 4>, MemFlash<0, 4, 4>>;
+        assert_eq!(4096, Loader::<4096, 4096>::PAGE_SIZE);
+        assert_eq!(131072, Loader::<131072, 4096>::PAGE_SIZE);
+        assert_eq!(4096, Loader::<2048, 4096>::PAGE_SIZE);
+        assert_eq!(12288, Loader::<6144, 4096>::PAGE_SIZE);
+    }
 }
@@ -0,0 +1,172 @@
+use crate::IMAGE_HEADER_SIZE;
+
+/// Image a keystream is applied to, see [`ImageCipher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CipherStream {
+    /// The update, as written to the DFU partition by the application.
+    Update,
+    /// The previous firmware, moved to the DFU partition while swapping, to be able to revert.
+    Backup,
+}
+
+/// Stream cipher keeping the firmware images in the DFU partition encrypted.
+///
+/// The application writes the encrypted update to the DFU partition as received. The bootloader
+/// decrypts it while copying it to the active partition, and encrypts the previous firmware while
+/// copying it to the DFU partition, so the DFU partition, usually in external flash, never holds
+/// plaintext firmware.
+///
+/// Encrypted updates must start with an [image header](crate::ImageInfo), holding the nonce the
+/// update is encrypted with, which must be unique for each update encrypted with the same key. The
+/// first [`IMAGE_HEADER_SIZE`] bytes of the images are never encrypted, so that the header can be
+/// read by the application and the bootloader. Encrypted updates without a header are cancelled.
+///
+/// Implement this to keep the key in a secure element or in key registers, or use one of the
+/// software implementations.
+pub trait ImageCipher {
+    /// Encrypt or decrypt `data` in place, by XOR-ing it with the keystream of `stream` starting at
+    /// `offset` bytes.
+    ///
+    /// `nonce` is the nonce from the header of the update, for both streams. `offset` is the offset
+    /// of the data in the active partition, whatever partition it is read from or written to. The
+    /// two streams must use different keystreams, for example by deriving different nonces from
+    /// `nonce`, so the backup doesn't leak information about the update.
+    fn apply_keystream(&mut self, stream: CipherStream, nonce: &[u8; 16], offset: u32, data: &mut [u8]);
+}
+
+// Cipher used when images are not encrypted.
+pub(crate) struct Plaintext;
+
+impl ImageCipher for Plaintext {
+    fn apply_keystream(&mut self, _stream: CipherStream, _nonce: &[u8; 16], _offset: u32, _data: &mut [u8]) {}
+}
+
+// Apply the keystream to `data` at `offset` in an image, leaving the image header unencrypted.
+pub(crate) fn apply_to_image<C: ImageCipher>(
+    cipher: &mut C,
+    stream: CipherStream,
+    nonce: &[u8; 16],
+    offset: u32,
+    data: &mut [u8],
+) {
+    let skip = core::cmp::min((IMAGE_HEADER_SIZE as u32).saturating_sub(offset) as usize, data.len());
+    if skip < data.len() {
+        cipher.apply_keystream(stream, nonce, offset + skip as u32, &mut data[skip..]);
+    }
+}
+
+/// AES-128 in counter mode, with a 128-bit big-endian counter starting at the nonce of the update.
+///
+/// The backup is encrypted with the most significant bit of the nonce flipped.
+#[cfg(feature = "aes-ctr")]
+pub struct Aes128CtrCipher {
+    key: [u8; 16],
+}
+
+#[cfg(feature = "aes-ctr")]
+impl Aes128CtrCipher {
+    /// Create a cipher with the key the updates are encrypted with.
+    pub fn new(key: [u8; 16]) -> Self {
+        Self { key }
+    }
+}
+
+#[cfg(feature = "aes-ctr")]
+impl ImageCipher for Aes128CtrCipher {
+    fn apply_keystream(&mut self, stream: CipherStream, nonce: &[u8; 16], offset: u32, data: &mut [u8]) {
+        use ctr::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+
+        let mut nonce = *nonce;
+        if stream == CipherStream::Backup {
+            nonce[0] ^= 0x80;
+        }
+        let mut cipher = ctr::Ctr128BE::<aes::Aes128>::new(&self.key.into(), &nonce.into());
+        cipher.seek(offset);
+        cipher.apply_keystream(data);
+    }
+}
+
+/// ChaCha20 with a 96-bit nonce and 32-bit block counter (RFC 8439).
+///
+/// The nonce is the first 12 bytes of the nonce of the update. The backup is encrypted with the most
+/// significant bit of the nonce flipped.
+#[cfg(feature = "chacha20")]
+pub struct ChaCha20Cipher {
+    key: [u8; 32],
+}
+
+#[cfg(feature = "chacha20")]
+impl ChaCha20Cipher {
+    /// Create a cipher with the key the updates are encrypted with.
+    pub fn new(key: [u8; 32]) -> Self {
+        Self { key }
+    }
+}
+
+#[cfg(feature = "chacha20")]
+impl ImageCipher for ChaCha20Cipher {
+    fn apply_keystream(&mut self, stream: CipherStream, nonce: &[u8; 16], offset: u32, data: &mut [u8]) {
+        use chacha20::cipher::{KeyIvInit, StreamCipher, StreamCipherSeek};
+
+        let mut nonce = {
+            let mut short = [0; 12];
+            short.copy_from_slice(&nonce[..12]);
+            short
+        };
+        if stream == CipherStream::Backup {
+            nonce[0] ^= 0x80;
+        }
+        let mut cipher = chacha20::ChaCha20::new(&self.key.into(), &nonce.into());
+        cipher.seek(offset);
+        cipher.apply_keystream(data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #![allow(unused_imports)]
+
+    use super::*;
+
+    #[allow(dead_code)]
+    fn check_cipher(cipher: &mut impl ImageCipher) {
+        let plaintext = [0x42; 256];
+        let nonce = [2; 16];
+
+        let mut whole = plaintext;
+        cipher.apply_keystream(CipherStream::Update, &nonce, 4096, &mut whole);
+        assert_ne!(plaintext, whole);
+
+        // Chunks at any offset use the same keystream
+        let mut chunked = plaintext;
+        for (i, chunk) in chunked.chunks_mut(48).enumerate() {
+            cipher.apply_keystream(CipherStream::Update, &nonce, 4096 + i as u32 * 48, chunk);
+        }
+        assert_eq!(whole, chunked);
+
+        let mut backup = plaintext;
+        cipher.apply_keystream(CipherStream::Backup, &nonce, 4096, &mut backup);
+        assert_ne!(whole, backup);
+
+        // Updates with different nonces use different keystreams
+        let mut other = plaintext;
+        cipher.apply_keystream(CipherStream::Update, &[3; 16], 4096, &mut other);
+        assert_ne!(whole, other);
+
+        cipher.apply_keystream(CipherStream::Update, &nonce, 4096, &mut whole);
+        assert_eq!(plaintext, whole);
+    }
+
+    #[test]
+    #[cfg(feature = "aes-ctr")]
+    fn aes_ctr() {
+        check_cipher(&mut Aes128CtrCipher::new([1; 16]));
+    }
+
+    #[test]
+    #[cfg(feature = "chacha20")]
+    fn chacha20() {
+        check_cipher(&mut ChaCha20Cipher::new([1; 32]));
+    }
+}
@@ -0,0 +1,45 @@
+use digest::typenum::U4;
+use digest::{FixedOutput, HashMarker, OutputSizeUser, Update};
+
+/// CRC-32 (IEEE 802.3), output as 4 little-endian bytes.
+pub struct Crc32(u32);
+
+impl Default for Crc32 {
+    fn default() -> Self {
+        Self(0xffff_ffff)
+    }
+}
+
+impl Update for Crc32 {
+    fn update(&mut self, data: &[u8]) {
+        for &b in data {
+            self.0 ^= b as u32;
+            for _ in 0..8 {
+                self.0 = if self.0 & 1 != 0 {
+                    (self.0 >> 1) ^ 0xedb8_8320
+                } else {
+                    self.0 >> 1
+                };
+            }
+        }
+    }
+}
+
+impl FixedOutput for Crc32 {
+    fn finalize_into(self, out: &mut digest::Output<Self>) {
+        out.as_mut_slice().copy_from_slice(&(!self.0).to_le_bytes())
+    }
+}
+
+impl OutputSizeUser for Crc32 {
+    type OutputSize = U4;
+}
+
+impl HashMarker for Crc32 {}
+
+/// CRC-32 of `data`, for the checksummed records stored in flash.
+pub(crate) fn checksum(data: &[u8]) -> [u8; 4] {
+    let mut crc = Crc32::default();
+    crc.update(data);
+    crc.finalize_fixed().into()
+}
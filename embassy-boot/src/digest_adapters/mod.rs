@@ -1,3 +1,5 @@
+pub(crate) mod crc32;
+
 #[cfg(feature = "ed25519-dalek")]
 pub(crate) mod ed25519_dalek;
 
@@ -0,0 +1,489 @@
+use embedded_storage::nor_flash::NorFlash;
+use embedded_storage_async::nor_flash::NorFlash as AsyncNorFlash;
+
+use crate::image::read_header;
+use crate::{BootError, FirmwareUpdaterError, ImageInfo, State, IMAGE_HEADER_SIZE, STATE_ERASE_VALUE};
+
+/// Application slot in direct-XIP mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Slot {
+    /// First slot.
+    A,
+    /// Second slot.
+    B,
+}
+
+impl Slot {
+    /// Get the other slot.
+    pub fn other(self) -> Self {
+        match self {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        }
+    }
+
+    fn index(self) -> usize {
+        match self {
+            Slot::A => 0,
+            Slot::B => 1,
+        }
+    }
+}
+
+/// Flash configuration for direct-XIP mode, holding the two application slots.
+pub struct DirectXipConfig<F> {
+    /// The flash partition of slot A.
+    pub slot_a: F,
+    /// The flash partition of slot B.
+    pub slot_b: F,
+}
+
+// Markers at the end of each slot, one aligned buffer long each, numbered from the end of the
+// slot. They are written in this order over the life of an image, and cleared when the slot is
+// erased for the next update.
+const READY: usize = 3;
+const TRIED: usize = 2;
+const CONFIRMED: usize = 1;
+
+fn marker_offset(capacity: usize, marker: usize, len: usize) -> u32 {
+    (capacity - marker * len) as u32
+}
+
+// Largest update that fits in a slot, before the markers.
+fn max_update_len(capacity: usize, len: usize) -> u32 {
+    marker_offset(capacity, READY, len)
+}
+
+struct SlotStatus {
+    version: u32,
+    tried: bool,
+    confirmed: bool,
+}
+
+/// Bootloader for direct-XIP mode, where both slots are executable.
+///
+/// Instead of swapping the update into a single active partition, the application writes the
+/// update to the slot it doesn't run from, and the bootloader boots the slot with the newest image
+/// directly, halving the flash writes of an update. Each slot must be large enough for the whole
+/// firmware, and the firmware must be able to execute from either slot, for example by building it
+/// for each slot.
+///
+/// Images start with an [`ImageInfo`] header, whose version decides which slot is the newest. Like
+/// with the swap, a new image is booted once on trial: if it doesn't mark itself booted before the
+/// next reset, the bootloader falls back to the other slot.
+pub struct DirectXipBootLoader<F: NorFlash> {
+    slots: [F; 2],
+}
+
+impl<F: NorFlash> DirectXipBootLoader<F> {
+    /// Create a new instance of a direct-XIP bootloader with the flash partitions of both slots.
+    pub fn new(config: DirectXipConfig<F>) -> Self {
+        Self {
+            slots: [config.slot_a, config.slot_b],
+        }
+    }
+
+    /// Select the slot to boot.
+    ///
+    /// The newest image marked updated is booted, if it is marked booted or hasn't been tried yet.
+    /// Otherwise, the other image is booted if it is valid.
+    ///
+    /// The `aligned_buf` must be `F::WRITE_SIZE.max(F::READ_SIZE)` long, and follow the alignment
+    /// rules for the flash being read from and written to.
+    pub fn prepare_boot(&mut self, aligned_buf: &mut [u8]) -> Result<Slot, BootError> {
+        let status = [
+            self.read_status(Slot::A, aligned_buf)?,
+            self.read_status(Slot::B, aligned_buf)?,
+        ];
+
+        // Newest first, slot A first for equal versions
+        let order = match &status {
+            [Some(a), Some(b)] if b.version > a.version => [Slot::B, Slot::A],
+            _ => [Slot::A, Slot::B],
+        };
+
+        for slot in order {
+            let Some(status) = &status[slot.index()] else {
+                continue;
+            };
+            if status.confirmed {
+                return Ok(slot);
+            }
+            if !status.tried {
+                trace!("Trying image version {} in slot {:?}", status.version, slot);
+                let flash = &mut self.slots[slot.index()];
+                aligned_buf.fill(!STATE_ERASE_VALUE);
+                flash.write(marker_offset(flash.capacity(), TRIED, aligned_buf.len()), aligned_buf)?;
+                return Ok(slot);
+            }
+            warn!("Image version {} in slot {:?} failed to boot", status.version, slot);
+        }
+        Err(BootError::NoValidImage)
+    }
+
+    fn read_status(&mut self, slot: Slot, aligned_buf: &mut [u8]) -> Result<Option<SlotStatus>, BootError> {
+        let flash = &mut self.slots[slot.index()];
+        let capacity = flash.capacity();
+        let mut is_set = |flash: &mut F, marker| -> Result<bool, BootError> {
+            flash.read(marker_offset(capacity, marker, aligned_buf.len()), aligned_buf)?;
+            Ok(aligned_buf.iter().any(|&b| b != STATE_ERASE_VALUE))
+        };
+
+        if !is_set(flash, READY)? {
+            return Ok(None);
+        }
+        let tried = is_set(flash, TRIED)?;
+        let confirmed = is_set(flash, CONFIRMED)?;
+
+        let header = read_header(flash, aligned_buf)?;
+        Ok(ImageInfo::parse(&header)
+            .filter(|info| info.update_len() <= max_update_len(capacity, aligned_buf.len()))
+            .map(|info| SlotStatus {
+                version: info.version,
+                tried,
+                confirmed,
+            }))
+    }
+}
+
+/// Application API for updating the firmware in direct-XIP mode, see [`DirectXipBootLoader`].
+///
+/// Updates are written to the slot the application isn't running from, and must start with an
+/// [`ImageInfo`] header.
+pub struct DirectXipUpdater<'d, F> {
+    slots: [F; 2],
+    running: Slot,
+    aligned: &'d mut [u8],
+    last_erased_sector: Option<usize>,
+}
+
+impl<'d, F: AsyncNorFlash> DirectXipUpdater<'d, F> {
+    /// Create an updater for an application running from slot `running`.
+    ///
+    /// The `aligned` buffer must have a size of `F::WRITE_SIZE.max(F::READ_SIZE)`, and follow the
+    /// alignment rules for the flash being read from and written to.
+    pub fn new(config: DirectXipConfig<F>, running: Slot, aligned: &'d mut [u8]) -> Self {
+        assert_eq!(aligned.len(), F::WRITE_SIZE.max(F::READ_SIZE));
+        Self {
+            slots: [config.slot_a, config.slot_b],
+            running,
+            aligned,
+            last_erased_sector: None,
+        }
+    }
+
+    /// Get the slot updates are written to.
+    pub fn update_slot(&self) -> Slot {
+        self.running.other()
+    }
+
+    /// Obtain the current state.
+    ///
+    /// Returns [`State::Swap`] if the running firmware is booted on trial, and must be marked booted
+    /// to be booted again, or [`State::Boot`] otherwise.
+    pub async fn get_state(&mut self) -> Result<State, FirmwareUpdaterError> {
+        if self.is_set(self.running, CONFIRMED).await? {
+            Ok(State::Boot)
+        } else {
+            Ok(State::Swap)
+        }
+    }
+
+    /// Write update data to the update slot at `offset`.
+    ///
+    /// Sectors are erased before being written. Writing at offset 0 starts a new update, and clears
+    /// the markers of the image previously in the slot.
+    pub async fn write_firmware(&mut self, offset: usize, data: &[u8]) -> Result<(), FirmwareUpdaterError> {
+        assert!(offset + data.len() <= max_update_len(self.capacity(), self.aligned.len()) as usize);
+        let flash = &mut self.slots[self.running.other().index()];
+
+        let mut offset = offset;
+        let mut data = data;
+        while !data.is_empty() {
+            let sector = offset / F::ERASE_SIZE;
+            let sector_start = sector * F::ERASE_SIZE;
+            let sector_end = sector_start + F::ERASE_SIZE;
+
+            if self.last_erased_sector != Some(sector) {
+                if sector == 0 {
+                    // Clear the markers first, so the image isn't considered ready while written
+                    let capacity = flash.capacity() as u32;
+                    flash.erase(capacity - F::ERASE_SIZE as u32, capacity).await?;
+                }
+                flash.erase(sector_start as u32, sector_end as u32).await?;
+                self.last_erased_sector = Some(sector);
+            }
+
+            let len = core::cmp::min(data.len(), sector_end - offset);
+            let (chunk, rest) = data.split_at(len);
+            flash.write(offset as u32, chunk).await?;
+            data = rest;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    /// Mark the update slot to be booted on trial on next boot, if it holds a newer image than the
+    /// running one.
+    ///
+    /// Returns [`FirmwareUpdaterError::InvalidImage`] if the update doesn't start with a valid
+    /// [`ImageInfo`] header.
+    pub async fn mark_updated(&mut self) -> Result<(), FirmwareUpdaterError> {
+        let max_len = max_update_len(self.capacity(), self.aligned.len());
+        let flash = &mut self.slots[self.running.other().index()];
+
+        let mut header = [0; IMAGE_HEADER_SIZE];
+        flash.read(0, &mut header).await?;
+        match ImageInfo::parse(&header) {
+            Some(info) if info.update_len() <= max_len => {}
+            _ => return Err(FirmwareUpdaterError::InvalidImage),
+        }
+
+        self.set(self.running.other(), READY).await
+    }
+
+    /// Mark the running firmware boot successful, so it keeps being booted.
+    pub async fn mark_booted(&mut self) -> Result<(), FirmwareUpdaterError> {
+        if !self.is_set(self.running, CONFIRMED).await? {
+            self.set(self.running, CONFIRMED).await?;
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots[0].capacity()
+    }
+
+    async fn is_set(&mut self, slot: Slot, marker: usize) -> Result<bool, FirmwareUpdaterError> {
+        let flash = &mut self.slots[slot.index()];
+        let offset = marker_offset(flash.capacity(), marker, self.aligned.len());
+        flash.read(offset, self.aligned).await?;
+        Ok(self.aligned.iter().any(|&b| b != STATE_ERASE_VALUE))
+    }
+
+    async fn set(&mut self, slot: Slot, marker: usize) -> Result<(), FirmwareUpdaterError> {
+        let flash = &mut self.slots[slot.index()];
+        let offset = marker_offset(flash.capacity(), marker, self.aligned.len());
+        self.aligned.fill(!STATE_ERASE_VALUE);
+        flash.write(offset, self.aligned).await?;
+        Ok(())
+    }
+}
+
+/// Blocking application API for updating the firmware in direct-XIP mode, see [`DirectXipUpdater`].
+pub struct BlockingDirectXipUpdater<'d, F> {
+    slots: [F; 2],
+    running: Slot,
+    aligned: &'d mut [u8],
+    last_erased_sector: Option<usize>,
+}
+
+impl<'d, F: NorFlash> BlockingDirectXipUpdater<'d, F> {
+    /// Create an updater for an application running from slot `running`.
+    ///
+    /// The `aligned` buffer must have a size of `F::WRITE_SIZE.max(F::READ_SIZE)`, and follow the
+    /// alignment rules for the flash being read from and written to.
+    pub fn new(config: DirectXipConfig<F>, running: Slot, aligned: &'d mut [u8]) -> Self {
+        assert_eq!(aligned.len(), F::WRITE_SIZE.max(F::READ_SIZE));
+        Self {
+            slots: [config.slot_a, config.slot_b],
+            running,
+            aligned,
+            last_erased_sector: None,
+        }
+    }
+
+    /// Get the slot updates are written to.
+    pub fn update_slot(&self) -> Slot {
+        self.running.other()
+    }
+
+    /// Obtain the current state, see [`DirectXipUpdater::get_state`].
+    pub fn get_state(&mut self) -> Result<State, FirmwareUpdaterError> {
+        if self.is_set(self.running, CONFIRMED)? {
+            Ok(State::Boot)
+        } else {
+            Ok(State::Swap)
+        }
+    }
+
+    /// Write update data to the update slot, see [`DirectXipUpdater::write_firmware`].
+    pub fn write_firmware(&mut self, offset: usize, data: &[u8]) -> Result<(), FirmwareUpdaterError> {
+        assert!(offset + data.len() <= max_update_len(self.capacity(), self.aligned.len()) as usize);
+        let flash = &mut self.slots[self.running.other().index()];
+
+        let mut offset = offset;
+        let mut data = data;
+        while !data.is_empty() {
+            let sector = offset / F::ERASE_SIZE;
+            let sector_start = sector * F::ERASE_SIZE;
+            let sector_end = sector_start + F::ERASE_SIZE;
+
+            if self.last_erased_sector != Some(sector) {
+                if sector == 0 {
+                    // Clear the markers first, so the image isn't considered ready while written
+                    let capacity = flash.capacity() as u32;
+                    flash.erase(capacity - F::ERASE_SIZE as u32, capacity)?;
+                }
+                flash.erase(sector_start as u32, sector_end as u32)?;
+                self.last_erased_sector = Some(sector);
+            }
+
+            let len = core::cmp::min(data.len(), sector_end - offset);
+            let (chunk, rest) = data.split_at(len);
+            flash.write(offset as u32, chunk)?;
+            data = rest;
+            offset += len;
+        }
+        Ok(())
+    }
+
+    /// Mark the update slot to be booted on trial, see [`DirectXipUpdater::mark_updated`].
+    pub fn mark_updated(&mut self) -> Result<(), FirmwareUpdaterError> {
+        let max_len = max_update_len(self.capacity(), self.aligned.len());
+        let flash = &mut self.slots[self.running.other().index()];
+
+        let mut header = [0; IMAGE_HEADER_SIZE];
+        flash.read(0, &mut header)?;
+        match ImageInfo::parse(&header) {
+            Some(info) if info.update_len() <= max_len => {}
+            _ => return Err(FirmwareUpdaterError::InvalidImage),
+        }
+
+        self.set(self.running.other(), READY)
+    }
+
+    /// Mark the running firmware boot successful, so it keeps being booted.
+    pub fn mark_booted(&mut self) -> Result<(), FirmwareUpdaterError> {
+        if !self.is_set(self.running, CONFIRMED)? {
+            self.set(self.running, CONFIRMED)?;
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.slots[0].capacity()
+    }
+
+    fn is_set(&mut self, slot: Slot, marker: usize) -> Result<bool, FirmwareUpdaterError> {
+        let flash = &mut self.slots[slot.index()];
+        let offset = marker_offset(flash.capacity(), marker, self.aligned.len());
+        flash.read(offset, self.aligned)?;
+        Ok(self.aligned.iter().any(|&b| b != STATE_ERASE_VALUE))
+    }
+
+    fn set(&mut self, slot: Slot, marker: usize) -> Result<(), FirmwareUpdaterError> {
+        let flash = &mut self.slots[slot.index()];
+        let offset = marker_offset(flash.capacity(), marker, self.aligned.len());
+        self.aligned.fill(!STATE_ERASE_VALUE);
+        flash.write(offset, self.aligned)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::mem_flash::MemFlash;
+
+    fn image(version: u32) -> [u8; 4096] {
+        let mut image = [version as u8; 4096];
+        let info = ImageInfo {
+            version,
+            length: 4096 - IMAGE_HEADER_SIZE as u32,
+            flags: 0,
+            digest: [0; 32],
+            nonce: [0; 16],
+        };
+        image[..IMAGE_HEADER_SIZE].copy_from_slice(&info.to_bytes());
+        image
+    }
+
+    #[test]
+    fn boots_newest_confirmed_or_untried_slot() {
+        let mut slot_a = MemFlash::<16384, 4096, 4>::default();
+        let mut slot_b = MemFlash::<16384, 4096, 4>::default();
+        let mut aligned = [0; 4];
+
+        let boot = |slot_a: &mut MemFlash<16384, 4096, 4>, slot_b: &mut MemFlash<16384, 4096, 4>| {
+            let mut bootloader = DirectXipBootLoader::new(DirectXipConfig { slot_a, slot_b });
+            bootloader.prepare_boot(&mut [0; 4])
+        };
+        assert_eq!(Err(BootError::NoValidImage), boot(&mut slot_a, &mut slot_b));
+
+        // Version 1 installed in slot A
+        let mut updater = DirectXipUpdater::new(
+            DirectXipConfig {
+                slot_a: &mut slot_a,
+                slot_b: &mut slot_b,
+            },
+            Slot::B,
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &image(1))).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+        assert_eq!(Ok(Slot::A), boot(&mut slot_a, &mut slot_b));
+
+        let mut updater = DirectXipUpdater::new(
+            DirectXipConfig {
+                slot_a: &mut slot_a,
+                slot_b: &mut slot_b,
+            },
+            Slot::A,
+            &mut aligned,
+        );
+        assert_eq!(State::Swap, block_on(updater.get_state()).unwrap());
+        block_on(updater.mark_booted()).unwrap();
+        assert_eq!(State::Boot, block_on(updater.get_state()).unwrap());
+
+        // Version 2 in slot B fails to mark itself booted
+        block_on(updater.write_firmware(0, &image(2))).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+        assert_eq!(Ok(Slot::B), boot(&mut slot_a, &mut slot_b));
+        assert_eq!(Ok(Slot::A), boot(&mut slot_a, &mut slot_b));
+
+        // Version 3 in slot B works
+        let mut updater = DirectXipUpdater::new(
+            DirectXipConfig {
+                slot_a: &mut slot_a,
+                slot_b: &mut slot_b,
+            },
+            Slot::A,
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &image(3))).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+        assert_eq!(Ok(Slot::B), boot(&mut slot_a, &mut slot_b));
+
+        let mut updater = BlockingDirectXipUpdater::new(
+            DirectXipConfig {
+                slot_a: &mut slot_a,
+                slot_b: &mut slot_b,
+            },
+            Slot::B,
+            &mut aligned,
+        );
+        updater.mark_booted().unwrap();
+        assert_eq!(Ok(Slot::B), boot(&mut slot_a, &mut slot_b));
+        assert_eq!(Ok(Slot::B), boot(&mut slot_a, &mut slot_b));
+
+        // An update without image header isn't booted
+        let mut updater = BlockingDirectXipUpdater::new(
+            DirectXipConfig {
+                slot_a: &mut slot_a,
+                slot_b: &mut slot_b,
+            },
+            Slot::B,
+            &mut aligned,
+        );
+        updater.write_firmware(0, &[0; 4096]).unwrap();
+        assert!(matches!(
+            updater.mark_updated(),
+            Err(FirmwareUpdaterError::InvalidImage)
+        ));
+        assert_eq!(Ok(Slot::B), boot(&mut slot_a, &mut slot_b));
+    }
+}
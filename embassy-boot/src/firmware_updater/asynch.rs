@@ -1,12 +1,17 @@
 use digest::Digest;
-#[cfg(target_os = "none")]
 use embassy_embedded_hal::flash::partition::Partition;
-#[cfg(target_os = "none")]
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embedded_storage_async::nor_flash::NorFlash;
 
 use super::FirmwareUpdaterConfig;
-use crate::{FirmwareUpdaterError, State, BOOT_MAGIC, DFU_DETACH_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC};
+#[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+use super::{decode_slot, encode_slot, SLOT_LEN};
+#[cfg(feature = "robust-state")]
+use crate::state_record::{state_record_offsets, StateRecord, STATE_RECORD_LEN};
+use crate::{
+    Digester, FirmwareUpdaterError, FirmwareVerifier, ImageInfo, PartitionTable, Progress, State, BOOT_MAGIC,
+    DFU_DETACH_MAGIC, IMAGE_HEADER_SIZE, REVERT_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC,
+};
 
 /// FirmwareUpdater is an application API for interacting with the BootLoader without the ability to
 /// 'mess up' the internal bootloader state
@@ -51,6 +56,34 @@ impl<'a, DFU: NorFlash, STATE: NorFlash>
     }
 }
 
+impl<'a, DFU: NorFlash, STATE: NorFlash>
+    FirmwareUpdaterConfig<Partition<'a, NoopRawMutex, DFU>, Partition<'a, NoopRawMutex, STATE>>
+{
+    /// Create a firmware updater config from the flash and a partition table read from flash, as an
+    /// alternative to [`FirmwareUpdaterConfig::from_linkerfile`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// let table = PartitionTable::read(&mut *flash.lock().await, PARTITION_TABLE_OFFSET).await
+    ///     .unwrap()
+    ///     .expect("no partition table");
+    /// let config = FirmwareUpdaterConfig::from_partition_table(&flash, &flash, &table);
+    /// ```
+    pub fn from_partition_table(
+        dfu_flash: &'a embassy_sync::mutex::Mutex<NoopRawMutex, DFU>,
+        state_flash: &'a embassy_sync::mutex::Mutex<NoopRawMutex, STATE>,
+        table: &PartitionTable,
+    ) -> Self {
+        trace!("DFU: 0x{:x} - 0x{:x}", table.dfu.start, table.dfu.end);
+        trace!("STATE: 0x{:x} - 0x{:x}", table.state.start, table.state.end);
+
+        Self {
+            dfu: Partition::new(dfu_flash, table.dfu.start, table.dfu.size()),
+            state: Partition::new(state_flash, table.state.start, table.state.size()),
+        }
+    }
+}
+
 impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
     /// Create a firmware updater instance with partition ranges for the update and state partitions.
     pub fn new(config: FirmwareUpdaterConfig<DFU, STATE>, aligned: &'d mut [u8]) -> Self {
@@ -76,71 +109,213 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
     ///
     /// Mark to trigger firmware swap on next boot if verify succeeds.
     ///
-    /// If the "ed25519-salty" feature is set (or another similar feature) then the signature is expected to have
-    /// been generated from a SHA-512 digest of the firmware bytes.
-    ///
-    /// If no signature feature is set then this method will always return a
-    /// signature error.
+    /// The signature is verified with the [`FirmwareVerifier`] selected by the signature feature, see
+    /// `Ed25519Dalek`, `Ed25519Salty` and `EcdsaP256` for the expected key and signature formats. To use another
+    /// algorithm or a hardware accelerator, use [`FirmwareUpdater::verify_and_mark_updated_with`] instead.
     #[cfg(feature = "_verify")]
     pub async fn verify_and_mark_updated(
         &mut self,
-        _public_key: &[u8; 32],
-        _signature: &[u8; 64],
-        _update_len: u32,
+        public_key: &[u8],
+        signature: &[u8; 64],
+        update_len: u32,
     ) -> Result<(), FirmwareUpdaterError> {
-        assert!(_update_len <= self.dfu.capacity() as u32);
+        #[cfg(feature = "ed25519-dalek")]
+        let mut verifier = crate::Ed25519Dalek::new(public_key).map_err(FirmwareUpdaterError::Signature)?;
+        #[cfg(feature = "ed25519-salty")]
+        let mut verifier = crate::Ed25519Salty::new(public_key).map_err(FirmwareUpdaterError::Signature)?;
+        #[cfg(feature = "ecdsa-p256")]
+        let mut verifier = crate::EcdsaP256::new(public_key).map_err(FirmwareUpdaterError::Signature)?;
+
+        self.verify_and_mark_updated_with(&mut verifier, signature, update_len)
+            .await
+    }
+
+    /// Verify the DFU with a [`FirmwareVerifier`]. If there is an error then DO NOT
+    /// proceed with updating the firmware (otherwise it could be malicious firmware).
+    ///
+    /// Mark to trigger firmware swap on next boot if verify succeeds.
+    ///
+    /// If the "rollback-protection" feature is set, the signed image must start with an image header,
+    /// and the update is rejected if the [`ImageInfo::version`] is lower than the rollback counter.
+    pub async fn verify_and_mark_updated_with<V: FirmwareVerifier>(
+        &mut self,
+        verifier: &mut V,
+        signature: &[u8],
+        update_len: u32,
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(update_len <= self.dfu.capacity() as u32);
 
         self.state.verify_booted().await?;
 
-        #[cfg(feature = "ed25519-dalek")]
+        let mut digest = digest::Output::<V::Digest>::default();
+        let mut chunk_buf = [0; 32];
+        self.hash::<V::Digest>(update_len, &mut chunk_buf, &mut digest).await?;
+        verifier
+            .verify(&digest, signature)
+            .map_err(FirmwareUpdaterError::Signature)?;
+
+        self.check_rollback(update_len).await?;
+        self.state.mark_updated().await
+    }
+
+    #[cfg_attr(not(feature = "rollback-protection"), allow(unused_variables))]
+    async fn check_rollback(&mut self, update_len: u32) -> Result<(), FirmwareUpdaterError> {
+        #[cfg(feature = "rollback-protection")]
         {
-            use ed25519_dalek::{Signature, SignatureError, Verifier, VerifyingKey};
+            // The header must be covered by the signature
+            if (update_len as usize) < IMAGE_HEADER_SIZE {
+                return Err(FirmwareUpdaterError::InvalidImage);
+            }
+            let info = self.get_dfu_image_info().await?;
+            if info.version < self.state.rollback_counter().await? {
+                return Err(FirmwareUpdaterError::Rollback);
+            }
+        }
+        Ok(())
+    }
 
-            use crate::digest_adapters::ed25519_dalek::Sha512;
+    /// Verify the DFU with a [`FirmwareVerifier`], computing the digest with a [`Digester`] instead of the
+    /// verifier's digest, and mark to trigger firmware swap on next boot if verify succeeds.
+    ///
+    /// The DFU is read in chunks of `chunk_buf.len()` bytes, larger chunks let the digester process
+    /// more data at once.
+    pub async fn verify_and_mark_updated_with_digester<V: FirmwareVerifier, G: Digester>(
+        &mut self,
+        verifier: &mut V,
+        digester: &mut G,
+        chunk_buf: &mut [u8],
+        signature: &[u8],
+        update_len: u32,
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(update_len <= self.dfu.capacity() as u32);
+
+        self.state.verify_booted().await?;
 
-            let into_signature_error = |e: SignatureError| FirmwareUpdaterError::Signature(e.into());
+        let mut digest = [0; 64];
+        let digest = &mut digest[..digester.output_size()];
+        self.hash_with(update_len, chunk_buf, digester, digest).await?;
+        verifier
+            .verify(digest, signature)
+            .map_err(FirmwareUpdaterError::Signature)?;
 
-            let public_key = VerifyingKey::from_bytes(_public_key).map_err(into_signature_error)?;
-            let signature = Signature::from_bytes(_signature);
+        self.check_rollback(update_len).await?;
+        self.state.mark_updated().await
+    }
 
-            let mut chunk_buf = [0; 2];
-            let mut message = [0; 64];
-            self.hash::<Sha512>(_update_len, &mut chunk_buf, &mut message).await?;
+    /// Verify the CRC32 trailer of the update, and mark to trigger firmware swap on next boot if it matches.
+    ///
+    /// The update must end with the CRC32 (IEEE 802.3) of the preceding bytes, as 4 little-endian
+    /// bytes, and `update_len` includes them. This detects corrupted updates, but unlike a signature,
+    /// not malicious ones.
+    #[cfg(all(feature = "crc32", not(feature = "_verify")))]
+    pub async fn verify_crc32_and_mark_updated(&mut self, update_len: u32) -> Result<(), FirmwareUpdaterError> {
+        let mut trailer = [0; 4];
+        let mut digest = [0; 4];
+        self.verify_trailer::<crate::digest_adapters::crc32::Crc32>(update_len, &mut trailer, &mut digest)
+            .await?;
+        self.state.mark_updated().await
+    }
 
-            public_key.verify(&message, &signature).map_err(into_signature_error)?
-        }
-        #[cfg(feature = "ed25519-salty")]
-        {
-            use salty::{PublicKey, Signature};
+    /// Verify the SHA-256 trailer of the update, and mark to trigger firmware swap on next boot if it matches.
+    ///
+    /// The update must end with the SHA-256 digest of the preceding bytes, and `update_len` includes
+    /// it. This detects corrupted updates, but unlike a signature, not malicious ones.
+    #[cfg(all(feature = "sha256", not(feature = "_verify")))]
+    pub async fn verify_sha256_and_mark_updated(&mut self, update_len: u32) -> Result<(), FirmwareUpdaterError> {
+        let mut trailer = [0; 32];
+        let mut digest = [0; 32];
+        self.verify_trailer::<sha2::Sha256>(update_len, &mut trailer, &mut digest)
+            .await?;
+        self.state.mark_updated().await
+    }
 
-            use crate::digest_adapters::salty::Sha512;
+    #[cfg(all(any(feature = "crc32", feature = "sha256"), not(feature = "_verify")))]
+    async fn verify_trailer<D: Digest>(
+        &mut self,
+        update_len: u32,
+        trailer: &mut [u8],
+        digest: &mut [u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(update_len <= self.dfu.capacity() as u32);
 
-            fn into_signature_error<E>(_: E) -> FirmwareUpdaterError {
-                FirmwareUpdaterError::Signature(signature::Error::default())
-            }
+        self.state.verify_booted().await?;
 
-            let public_key = PublicKey::try_from(_public_key).map_err(into_signature_error)?;
-            let signature = Signature::try_from(_signature).map_err(into_signature_error)?;
+        let image_len = update_len
+            .checked_sub(trailer.len() as u32)
+            .ok_or(FirmwareUpdaterError::DigestMismatch)?;
+        self.read_unaligned(image_len, trailer).await?;
 
-            let mut message = [0; 64];
-            let mut chunk_buf = [0; 2];
-            self.hash::<Sha512>(_update_len, &mut chunk_buf, &mut message).await?;
+        let mut chunk_buf = crate::AlignedBuffer([0; 32]);
+        self.hash::<D>(image_len, &mut chunk_buf.0, digest).await?;
+        if trailer != digest {
+            return Err(FirmwareUpdaterError::DigestMismatch);
+        }
+        Ok(())
+    }
 
-            let r = public_key.verify(&message, &signature);
-            trace!(
-                "Verifying with public key {}, signature {} and message {} yields ok: {}",
-                public_key.to_bytes(),
-                signature.to_bytes(),
-                message,
-                r.is_ok()
-            );
-            r.map_err(into_signature_error)?
+    /// Verify the image in DFU against the SHA-256 digest in its header, and mark to trigger
+    /// firmware swap on next boot if it matches.
+    ///
+    /// This detects corrupted updates, but unlike a signature, not malicious ones.
+    #[cfg(all(feature = "sha256", not(feature = "_verify")))]
+    pub async fn verify_image_and_mark_updated(&mut self) -> Result<(), FirmwareUpdaterError> {
+        self.state.verify_booted().await?;
+        let info = self.get_dfu_image_info().await?;
+
+        let mut digest = sha2::Sha256::new();
+        let mut chunk_buf = crate::AlignedBuffer([0; 32]);
+        let end = info.update_len();
+        for offset in (IMAGE_HEADER_SIZE as u32..end).step_by(chunk_buf.0.len()) {
+            let len = core::cmp::min((end - offset) as usize, chunk_buf.0.len());
+            // Don't read past the image, except to complete a read unit
+            let chunk = &mut chunk_buf.0[..len.next_multiple_of(DFU::READ_SIZE)];
+            self.dfu.read(offset, chunk).await?;
+            digest.update(&chunk[..len]);
+        }
+        if digest.finalize().as_slice() != info.digest {
+            return Err(FirmwareUpdaterError::DigestMismatch);
         }
 
         self.state.mark_updated().await
     }
 
-    /// Verify the update in DFU with any digest.
+    /// Read the header of the image in DFU.
+    ///
+    /// Returns [`FirmwareUpdaterError::InvalidImage`] if DFU doesn't start with an image header,
+    /// or if the image doesn't fit in DFU.
+    pub async fn get_dfu_image_info(&mut self) -> Result<ImageInfo, FirmwareUpdaterError> {
+        let mut header = [0; IMAGE_HEADER_SIZE];
+        self.read_unaligned(0, &mut header).await?;
+        let info = ImageInfo::parse(&header).ok_or(FirmwareUpdaterError::InvalidImage)?;
+        if info.update_len() as usize > self.dfu.capacity() {
+            return Err(FirmwareUpdaterError::InvalidImage);
+        }
+        Ok(info)
+    }
+
+    // Read `buf.len()` bytes at any `offset` of DFU, through read units that respect the DFU alignment.
+    async fn read_unaligned(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), FirmwareUpdaterError> {
+        assert!(DFU::READ_SIZE <= 32);
+        let mut unit = crate::AlignedBuffer([0; 32]);
+        let unit = &mut unit.0[..DFU::READ_SIZE];
+
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset as usize + done;
+            let start = pos % DFU::READ_SIZE;
+            self.dfu.read((pos - start) as u32, unit).await?;
+            let len = core::cmp::min(DFU::READ_SIZE - start, buf.len() - done);
+            buf[done..done + len].copy_from_slice(&unit[start..start + len]);
+            done += len;
+        }
+        Ok(())
+    }
+
+    /// Compute the digest of the first `update_len` bytes of DFU with any digest, reading them in
+    /// chunks of `chunk_buf.len()` bytes.
+    ///
+    /// This can be used to compare the update against a checksum provided by the server, or to show
+    /// a fingerprint of the image. `output` must be the size of the digest output.
     pub async fn hash<D: Digest>(
         &mut self,
         update_len: u32,
@@ -149,20 +324,53 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
     ) -> Result<(), FirmwareUpdaterError> {
         let mut digest = D::new();
         for offset in (0..update_len).step_by(chunk_buf.len()) {
-            self.dfu.read(offset, chunk_buf).await?;
             let len = core::cmp::min((update_len - offset) as usize, chunk_buf.len());
-            digest.update(&chunk_buf[..len]);
+            // Don't read past the update, except to complete a read unit
+            let read_len = core::cmp::min(len.next_multiple_of(DFU::READ_SIZE), chunk_buf.len());
+            let chunk = &mut chunk_buf[..read_len];
+            self.dfu.read(offset, chunk).await?;
+            digest.update(&chunk[..len]);
         }
         output.copy_from_slice(digest.finalize().as_slice());
         Ok(())
     }
 
+    /// Compute the digest of the update in DFU with a [`Digester`].
+    pub async fn hash_with<G: Digester>(
+        &mut self,
+        update_len: u32,
+        chunk_buf: &mut [u8],
+        digester: &mut G,
+        output: &mut [u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        digester.reset().await;
+        for offset in (0..update_len).step_by(chunk_buf.len()) {
+            self.dfu.read(offset, chunk_buf).await?;
+            let len = core::cmp::min((update_len - offset) as usize, chunk_buf.len());
+            digester.update(&chunk_buf[..len]).await;
+        }
+        digester.finalize(output).await;
+        Ok(())
+    }
+
     /// Mark to trigger firmware swap on next boot.
     #[cfg(not(feature = "_verify"))]
     pub async fn mark_updated(&mut self) -> Result<(), FirmwareUpdaterError> {
         self.state.mark_updated().await
     }
 
+    /// Read the rollback counter, see [`FirmwareState::rollback_counter`].
+    #[cfg(feature = "rollback-protection")]
+    pub async fn rollback_counter(&mut self) -> Result<u32, FirmwareUpdaterError> {
+        self.state.rollback_counter().await
+    }
+
+    /// Raise the rollback counter, see [`FirmwareState::update_rollback_counter`].
+    #[cfg(feature = "rollback-protection")]
+    pub async fn update_rollback_counter(&mut self, version: u32) -> Result<(), FirmwareUpdaterError> {
+        self.state.update_rollback_counter(version).await
+    }
+
     /// Mark to trigger USB DFU on next boot.
     pub async fn mark_dfu(&mut self) -> Result<(), FirmwareUpdaterError> {
         self.state.verify_booted().await?;
@@ -174,13 +382,22 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
         self.state.mark_booted().await
     }
 
+    /// Mark the update as failed, to run the previous firmware again on reset.
+    ///
+    /// See [`FirmwareState::mark_reverted`].
+    pub async fn mark_reverted(&mut self) -> Result<(), FirmwareUpdaterError> {
+        self.state.mark_reverted().await
+    }
+
     /// Writes firmware data to the device.
     ///
     /// This function writes the given data to the firmware area starting at the specified offset.
     /// It handles sector erasures and data writes while verifying the device is in a proper state
     /// for firmware updates. The function ensures that only unerased sectors are erased before
     /// writing and efficiently handles the writing process across sector boundaries and in
-    /// various configurations (data size, sector size, etc.).
+    /// various configurations (data size, sector size, etc.). A chunk covering a whole sector that
+    /// already holds the same data is neither erased nor written, which makes flashing a mostly
+    /// unchanged image faster and spares the flash.
     ///
     /// # Arguments
     ///
@@ -200,6 +417,19 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
     /// - There is a failure erasing a sector before writing.
     /// - There is a failure writing data to the device.
     pub async fn write_firmware(&mut self, offset: usize, data: &[u8]) -> Result<(), FirmwareUpdaterError> {
+        self.write_firmware_with_progress(offset, data, |_| {}).await
+    }
+
+    /// Write data to a flash page, reporting progress.
+    ///
+    /// Same as [`write_firmware`](Self::write_firmware), except that `progress` is called after
+    /// each sector sized chunk is written, with the number of bytes of `data` written so far.
+    pub async fn write_firmware_with_progress(
+        &mut self,
+        offset: usize,
+        data: &[u8],
+        mut progress: impl FnMut(Progress),
+    ) -> Result<(), FirmwareUpdaterError> {
         // Make sure we are running a booted firmware to avoid reverting to a bad state.
         self.state.verify_booted().await?;
 
@@ -218,28 +448,68 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
                 .last_erased_dfu_sector_index
                 .map_or(true, |last_erased_sector| current_sector != last_erased_sector);
 
-            // If the sector needs to be erased, erase it and update the last erased sector index.
-            if need_erase {
-                self.dfu.erase(sector_start as u32, sector_end as u32).await?;
-                self.last_erased_dfu_sector_index = Some(current_sector);
-            }
-
             // Calculate the size of the data chunk that can be written in the current iteration.
             let write_size = core::cmp::min(remaining_data.len(), sector_end - offset);
             // Split the data to get the current chunk to be written and the remaining data.
             let (data_chunk, rest) = remaining_data.split_at(write_size);
 
+            // If the sector needs to be erased, erase it and update the last erased sector index.
+            let mut unchanged = false;
+            if need_erase {
+                // Writing the first sector starts a new download.
+                #[cfg(feature = "resumable-dfu")]
+                if current_sector == 0 {
+                    self.state.clear_dfu_progress().await?;
+                }
+                // A whole sector that already holds the same data, as when flashing a mostly
+                // unchanged image again, is left as is.
+                unchanged = write_size == DFU::ERASE_SIZE && self.dfu_matches(offset, data_chunk).await?;
+                if !unchanged {
+                    self.dfu.erase(sector_start as u32, sector_end as u32).await?;
+                }
+                self.last_erased_dfu_sector_index = Some(current_sector);
+            }
+
             // Write the current data chunk.
-            self.dfu.write(offset as u32, data_chunk).await?;
+            if !unchanged {
+                self.dfu.write(offset as u32, data_chunk).await?;
+            }
 
             // Update the offset and remaining data for the next iteration.
             remaining_data = rest;
             offset += write_size;
+
+            // Once a sector is complete, record it so that the download can be resumed from there.
+            #[cfg(feature = "resumable-dfu")]
+            if offset == sector_end {
+                self.state.record_dfu_progress(offset).await?;
+            }
+
+            progress(Progress {
+                done: data.len() - remaining_data.len(),
+                total: data.len(),
+            });
         }
 
         Ok(())
     }
 
+    // Whether DFU at `offset` already holds `data`.
+    async fn dfu_matches(&mut self, offset: usize, data: &[u8]) -> Result<bool, FirmwareUpdaterError> {
+        let mut buf = [0; 64];
+        if buf.len() % DFU::READ_SIZE != 0 || data.len() % DFU::READ_SIZE != 0 {
+            return Ok(false);
+        }
+        for (chunk_offset, chunk) in (offset..).step_by(buf.len()).zip(data.chunks(buf.len())) {
+            let buf = &mut buf[..chunk.len()];
+            self.dfu.read(chunk_offset as u32, buf).await?;
+            if buf != chunk {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     /// Prepare for an incoming DFU update by erasing the entire DFU area and
     /// returning its `Partition`.
     ///
@@ -247,10 +517,55 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
     /// exchange for added complexity.
     pub async fn prepare_update(&mut self) -> Result<&mut DFU, FirmwareUpdaterError> {
         self.state.verify_booted().await?;
+        #[cfg(feature = "resumable-dfu")]
+        self.state.clear_dfu_progress().await?;
         self.dfu.erase(0, self.dfu.capacity() as u32).await?;
 
         Ok(&mut self.dfu)
     }
+
+    /// Find the offset to resume an interrupted DFU download from.
+    ///
+    /// With the `resumable-dfu` feature, each time `write_firmware` completes a DFU sector, the end
+    /// of the sector is recorded in an erase sector of the state partition reserved for it. After a
+    /// reset, the download can continue from the returned offset instead of starting over, by
+    /// passing it to `write_firmware` along with the rest of the firmware.
+    ///
+    /// This assumes the firmware is written in order. Returns 0 if no download is in progress, and
+    /// the recorded progress is forgotten when starting a new download at offset 0, when calling
+    /// `prepare_update`, and when the state changes.
+    #[cfg(feature = "resumable-dfu")]
+    pub async fn resume_update(&mut self) -> Result<usize, FirmwareUpdaterError> {
+        self.state.verify_booted().await?;
+        self.last_erased_dfu_sector_index = None;
+        Ok(self.state.find_dfu_progress().await?.0)
+    }
+
+    /// Prepare for an incoming DFU update, reporting progress.
+    ///
+    /// Same as [`prepare_update`](Self::prepare_update), except that the DFU area is erased one
+    /// sector at a time and `progress` is called after each sector with the number of bytes erased.
+    pub async fn prepare_update_with_progress(
+        &mut self,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<&mut DFU, FirmwareUpdaterError> {
+        self.state.verify_booted().await?;
+        #[cfg(feature = "resumable-dfu")]
+        self.state.clear_dfu_progress().await?;
+
+        let capacity = self.dfu.capacity();
+        for sector_start in (0..capacity).step_by(DFU::ERASE_SIZE) {
+            self.dfu
+                .erase(sector_start as u32, (sector_start + DFU::ERASE_SIZE) as u32)
+                .await?;
+            progress(Progress {
+                done: sector_start + DFU::ERASE_SIZE,
+                total: capacity,
+            });
+        }
+
+        Ok(&mut self.dfu)
+    }
 }
 
 /// Manages the state partition of the firmware update.
@@ -285,7 +600,7 @@ impl<'d, STATE: NorFlash> FirmwareState<'d, STATE> {
 
     // Make sure we are running a booted firmware to avoid reverting to a bad state.
     async fn verify_booted(&mut self) -> Result<(), FirmwareUpdaterError> {
-        if self.get_state().await? == State::Boot {
+        if matches!(self.get_state().await?, State::Boot | State::Revert) {
             Ok(())
         } else {
             Err(FirmwareUpdaterError::BadState)
@@ -297,13 +612,24 @@ impl<'d, STATE: NorFlash> FirmwareState<'d, STATE> {
     /// This is useful to check if the bootloader has just done a swap, in order
     /// to do verifications and self-tests of the new image before calling
     /// `mark_booted`.
+    ///
+    /// Returns `Swap` if an update is marked to be swapped in on the next boot, or was swapped in
+    /// and awaits validation, and `Revert` if the bootloader reverted a failed update and the
+    /// previous firmware has not been marked booted since.
     pub async fn get_state(&mut self) -> Result<State, FirmwareUpdaterError> {
-        self.state.read(0, &mut self.aligned).await?;
+        #[cfg(feature = "robust-state")]
+        let magic = self.read_state_record().await?.map(|r| r.magic);
+        #[cfg(not(feature = "robust-state"))]
+        let magic = {
+            self.state.read(0, &mut self.aligned).await?;
+            let magic = self.aligned[0];
+            (!self.aligned.iter().any(|&b| b != magic)).then_some(magic)
+        };
 
-        if !self.aligned.iter().any(|&b| b != SWAP_MAGIC) {
-            Ok(State::Swap)
-        } else {
-            Ok(State::Boot)
+        match magic {
+            Some(SWAP_MAGIC) => Ok(State::Swap),
+            Some(REVERT_MAGIC) => Ok(State::Revert),
+            _ => Ok(State::Boot),
         }
     }
 
@@ -322,10 +648,223 @@ impl<'d, STATE: NorFlash> FirmwareState<'d, STATE> {
         self.set_magic(BOOT_MAGIC).await
     }
 
+    /// Mark the update as failed, to run the previous firmware again on reset.
+    ///
+    /// Call this instead of `mark_booted` when the self-tests of an update fail, then reset. If
+    /// the update was swapped in, the bootloader reverts it on the next boot. If it was only
+    /// marked updated, it is cancelled instead.
+    ///
+    /// Returns `BadState` if there is no update to revert.
+    pub async fn mark_reverted(&mut self) -> Result<(), FirmwareUpdaterError> {
+        match self.get_state().await? {
+            State::Swap if self.is_swapped_in().await? => Ok(()),
+            State::Swap => self.set_magic(BOOT_MAGIC).await,
+            _ => Err(FirmwareUpdaterError::BadState),
+        }
+    }
+
+    // Whether the bootloader has started swapping in the update, going by the first swap progress
+    // index.
+    async fn is_swapped_in(&mut self) -> Result<bool, FirmwareUpdaterError> {
+        let index = 2 * STATE::WRITE_SIZE;
+        if self.aligned.len() > index {
+            self.state.read(0, self.aligned).await?;
+            Ok(self.aligned[index..index + STATE::WRITE_SIZE]
+                .iter()
+                .any(|&b| b != STATE_ERASE_VALUE))
+        } else {
+            self.state.read(index as u32, self.aligned).await?;
+            Ok(self.aligned[..STATE::WRITE_SIZE]
+                .iter()
+                .any(|&b| b != STATE_ERASE_VALUE))
+        }
+    }
+
+    /// Read the rollback counter.
+    ///
+    /// This is the lowest firmware version accepted by `verify_and_mark_updated`, 0 if the counter
+    /// was never updated.
+    #[cfg(feature = "rollback-protection")]
+    pub async fn rollback_counter(&mut self) -> Result<u32, FirmwareUpdaterError> {
+        Ok(self.find_rollback_counter().await?.0)
+    }
+
+    /// Raise the rollback counter to `version`, to reject updates to lower versions.
+    ///
+    /// Call this with the version of the running firmware once it is marked booted, so that an
+    /// update that fails and is reverted doesn't prevent installing the previous version again.
+    /// The counter never decreases: a `version` lower than the current counter is ignored.
+    #[cfg(feature = "rollback-protection")]
+    pub async fn update_rollback_counter(&mut self, version: u32) -> Result<(), FirmwareUpdaterError> {
+        let (counter, free_slot) = self.find_rollback_counter().await?;
+        if version <= counter {
+            return Ok(());
+        }
+
+        let start = crate::rollback_counter_start(self.state.capacity(), STATE::ERASE_SIZE);
+        let slot = match free_slot {
+            Some(slot) => slot,
+            None => {
+                // All slots are used, start over from the beginning of the sector.
+                self.state.erase(start as u32, self.state.capacity() as u32).await?;
+                0
+            }
+        };
+
+        let offset = start + slot * self.slot_size();
+        self.write_slot(offset, version).await
+    }
+
+    // Returns the highest version stored in the rollback counter sector, and the first free slot.
+    #[cfg(feature = "rollback-protection")]
+    async fn find_rollback_counter(&mut self) -> Result<(u32, Option<usize>), FirmwareUpdaterError> {
+        let start = crate::rollback_counter_start(self.state.capacity(), STATE::ERASE_SIZE);
+        let slot_size = self.slot_size();
+        let mut counter = 0;
+        for slot in 0..STATE::ERASE_SIZE / slot_size {
+            let encoded = self.read_slot(start + slot * slot_size).await?;
+            if encoded.iter().all(|&b| b == STATE_ERASE_VALUE) {
+                return Ok((counter, Some(slot)));
+            }
+            if let Some(version) = decode_slot(&encoded) {
+                counter = counter.max(version);
+            }
+        }
+        Ok((counter, None))
+    }
+
+    // The DFU progress records are in their own erase sector, so that they are kept across state
+    // changes, and written without wearing out the sector holding the state.
+    #[cfg(feature = "resumable-dfu")]
+    fn dfu_progress_slots(&self) -> usize {
+        STATE::ERASE_SIZE / self.slot_size()
+    }
+
+    // Returns the last DFU offset recorded, and the first free record slot.
+    #[cfg(feature = "resumable-dfu")]
+    async fn find_dfu_progress(&mut self) -> Result<(usize, Option<usize>), FirmwareUpdaterError> {
+        let start = crate::dfu_progress_start(self.state.capacity(), STATE::ERASE_SIZE);
+        let slot_size = self.slot_size();
+        let mut progress = 0;
+        for slot in 0..self.dfu_progress_slots() {
+            let encoded = self.read_slot(start + slot * slot_size).await?;
+            if encoded.iter().all(|&b| b == STATE_ERASE_VALUE) {
+                return Ok((progress, Some(slot)));
+            }
+            if let Some(offset) = decode_slot(&encoded) {
+                progress = offset as usize;
+            }
+        }
+        Ok((progress, None))
+    }
+
+    // Record that the DFU partition is written up to `offset`.
+    #[cfg(feature = "resumable-dfu")]
+    async fn record_dfu_progress(&mut self, offset: usize) -> Result<(), FirmwareUpdaterError> {
+        let slot = match self.find_dfu_progress().await?.1 {
+            Some(slot) => slot,
+            None => {
+                // All slots are used, start over from the first one.
+                self.clear_dfu_progress().await?;
+                0
+            }
+        };
+
+        let start = crate::dfu_progress_start(self.state.capacity(), STATE::ERASE_SIZE);
+        self.write_slot(start + slot * self.slot_size(), offset as u32).await
+    }
+
+    // Forget the recorded DFU progress.
+    #[cfg(feature = "resumable-dfu")]
+    async fn clear_dfu_progress(&mut self) -> Result<(), FirmwareUpdaterError> {
+        if self.find_dfu_progress().await?.1 == Some(0) {
+            return Ok(());
+        }
+
+        let start = crate::dfu_progress_start(self.state.capacity(), STATE::ERASE_SIZE);
+        self.state
+            .erase(start as u32, (start + STATE::ERASE_SIZE) as u32)
+            .await?;
+        Ok(())
+    }
+
+    #[cfg(feature = "robust-state")]
+    async fn read_state_record(&mut self) -> Result<Option<StateRecord>, FirmwareUpdaterError> {
+        let mut copies = [None; 2];
+        for (copy, offset) in copies
+            .iter_mut()
+            .zip(state_record_offsets(self.state.capacity(), STATE::ERASE_SIZE))
+        {
+            let mut record = [0; STATE_RECORD_LEN];
+            self.read_bytes(offset, &mut record).await?;
+            *copy = StateRecord::decode(&record);
+        }
+        Ok(StateRecord::newest(copies[0], copies[1]))
+    }
+
+    // Write both copies of the state record, one after the other.
+    #[cfg(feature = "robust-state")]
+    async fn write_state_record(&mut self, record: StateRecord) -> Result<(), FirmwareUpdaterError> {
+        for offset in state_record_offsets(self.state.capacity(), STATE::ERASE_SIZE) {
+            self.state
+                .erase(offset as u32, (offset + STATE::ERASE_SIZE) as u32)
+                .await?;
+            self.write_bytes(offset, &record.encode()).await?;
+        }
+        Ok(())
+    }
+
+    #[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+    fn slot_size(&self) -> usize {
+        SLOT_LEN.next_multiple_of(self.aligned.len())
+    }
+
+    #[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+    async fn read_slot(&mut self, offset: usize) -> Result<[u8; SLOT_LEN], FirmwareUpdaterError> {
+        let mut encoded = [0; SLOT_LEN];
+        self.read_bytes(offset, &mut encoded).await?;
+        Ok(encoded)
+    }
+
+    #[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+    async fn write_slot(&mut self, offset: usize, value: u32) -> Result<(), FirmwareUpdaterError> {
+        self.write_bytes(offset, &encode_slot(value)).await
+    }
+
+    // Read `buf.len()` bytes at `offset`, one aligned chunk at a time.
+    #[cfg(any(feature = "robust-state", feature = "resumable-dfu", feature = "rollback-protection"))]
+    async fn read_bytes(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), FirmwareUpdaterError> {
+        for (i, chunk) in buf.chunks_mut(self.aligned.len()).enumerate() {
+            let chunk_offset = offset + i * self.aligned.len();
+            self.state.read(chunk_offset as u32, self.aligned).await?;
+            chunk.copy_from_slice(&self.aligned[..chunk.len()]);
+        }
+        Ok(())
+    }
+
+    // Write `data` at `offset`, one aligned chunk at a time, padding the last one.
+    #[cfg(any(feature = "robust-state", feature = "resumable-dfu", feature = "rollback-protection"))]
+    async fn write_bytes(&mut self, offset: usize, data: &[u8]) -> Result<(), FirmwareUpdaterError> {
+        for (i, chunk) in data.chunks(self.aligned.len()).enumerate() {
+            self.aligned.fill(STATE_ERASE_VALUE);
+            self.aligned[..chunk.len()].copy_from_slice(chunk);
+            let chunk_offset = offset + i * self.aligned.len();
+            self.state.write(chunk_offset as u32, self.aligned).await?;
+        }
+        Ok(())
+    }
+
     async fn set_magic(&mut self, magic: u8) -> Result<(), FirmwareUpdaterError> {
+        #[cfg(feature = "robust-state")]
+        let current = self.read_state_record().await?;
+        #[cfg(feature = "robust-state")]
+        let changed = current.map(|r| r.magic) != Some(magic);
+
         self.state.read(0, &mut self.aligned).await?;
+        #[cfg(not(feature = "robust-state"))]
+        let changed = self.aligned[..STATE::WRITE_SIZE].iter().any(|&b| b != magic);
 
-        if self.aligned[..STATE::WRITE_SIZE].iter().any(|&b| b != magic) {
+        if changed {
             // Read progress validity
             if STATE::READ_SIZE <= 2 * STATE::WRITE_SIZE {
                 self.state.read(STATE::WRITE_SIZE as u32, &mut self.aligned).await?;
@@ -346,12 +885,37 @@ impl<'d, STATE: NorFlash> FirmwareState<'d, STATE> {
                     .await?;
             }
 
+            // Leaving the swap state, the record is written before clearing the progress, so that a
+            // power loss in between leaves the new state with invalid progress, which is ignored.
+            // Entering it, the record is written after clearing the progress, so that a power loss in
+            // between leaves the previous state, instead of a swap state with invalid progress that
+            // the bootloader would take for a swap to revert.
+            #[cfg(feature = "robust-state")]
+            let record = StateRecord::next(current, magic);
+            #[cfg(feature = "robust-state")]
+            if magic != SWAP_MAGIC {
+                self.write_state_record(record).await?;
+            }
+
             // Clear magic and progress
-            self.state.erase(0, self.state.capacity() as u32).await?;
+            let state_len = crate::state_len(self.state.capacity(), STATE::ERASE_SIZE);
+            self.state.erase(0, state_len as u32).await?;
+
+            #[cfg(feature = "robust-state")]
+            if magic == SWAP_MAGIC {
+                self.write_state_record(record).await?;
+            }
 
             // Set magic
-            self.aligned.fill(magic);
-            self.state.write(0, &self.aligned[..STATE::WRITE_SIZE]).await?;
+            #[cfg(not(feature = "robust-state"))]
+            {
+                self.aligned.fill(magic);
+                self.state.write(0, &self.aligned[..STATE::WRITE_SIZE]).await?;
+            }
+
+            // A new download starts once the state changes.
+            #[cfg(feature = "resumable-dfu")]
+            self.clear_dfu_progress().await?;
         }
         Ok(())
     }
@@ -371,7 +935,7 @@ mod tests {
     #[test]
     fn can_verify_sha1() {
         let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
-        let state = Partition::new(&flash, 0, 4096);
+        let state = Partition::new(&flash, 0, 16384);
         let dfu = Partition::new(&flash, 65536, 65536);
         let mut aligned = [0; 8];
 
@@ -391,7 +955,7 @@ mod tests {
     #[test]
     fn can_verify_sha1_sector_bigger_than_chunk() {
         let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
-        let state = Partition::new(&flash, 0, 4096);
+        let state = Partition::new(&flash, 0, 16384);
         let dfu = Partition::new(&flash, 65536, 65536);
         let mut aligned = [0; 8];
 
@@ -415,7 +979,7 @@ mod tests {
     #[test]
     fn can_verify_sha1_sector_smaller_than_chunk() {
         let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 1024, 8>::default());
-        let state = Partition::new(&flash, 0, 4096);
+        let state = Partition::new(&flash, 0, 16384);
         let dfu = Partition::new(&flash, 65536, 65536);
         let mut aligned = [0; 8];
 
@@ -439,7 +1003,7 @@ mod tests {
     #[test]
     fn can_verify_sha1_cross_sector_boundary() {
         let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 1024, 8>::default());
-        let state = Partition::new(&flash, 0, 4096);
+        let state = Partition::new(&flash, 0, 16384);
         let dfu = Partition::new(&flash, 65536, 65536);
         let mut aligned = [0; 8];
 
@@ -459,4 +1023,255 @@ mod tests {
 
         assert_eq!(Sha1::digest(update).as_slice(), hash);
     }
+
+    #[test]
+    #[cfg(feature = "crc32")]
+    fn can_verify_crc32_trailer() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 8];
+
+        // CRC32 of "123456789" is 0xcbf43926.
+        let mut update = [0; 16];
+        update[..9].copy_from_slice(b"123456789");
+        update[9..13].copy_from_slice(&0xcbf4_3926u32.to_le_bytes());
+
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+        block_on(updater.write_firmware(0, &update)).unwrap();
+        assert!(matches!(
+            block_on(updater.verify_crc32_and_mark_updated(12)),
+            Err(FirmwareUpdaterError::DigestMismatch)
+        ));
+        block_on(updater.verify_crc32_and_mark_updated(13)).unwrap();
+        assert_eq!(State::Swap, block_on(updater.get_state()).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "sha256")]
+    fn can_verify_sha256_trailer() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 8];
+
+        let mut update = [0; 4096];
+        update[..100].fill(0x42);
+        let digest = sha2::Sha256::digest(&update[..100]);
+        update[100..132].copy_from_slice(&digest);
+
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+        block_on(updater.write_firmware(0, &update)).unwrap();
+        block_on(updater.verify_sha256_and_mark_updated(132)).unwrap();
+        assert_eq!(State::Swap, block_on(updater.get_state()).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "sha256")]
+    fn can_verify_image_header() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 8];
+
+        let mut update = [0; 4096];
+        update[IMAGE_HEADER_SIZE..IMAGE_HEADER_SIZE + 100].fill(0x42);
+        let info = ImageInfo {
+            version: 7,
+            length: 100,
+            flags: 0,
+            digest: sha2::Sha256::digest(&update[IMAGE_HEADER_SIZE..IMAGE_HEADER_SIZE + 100]).into(),
+            nonce: [0; 16],
+        };
+        update[..IMAGE_HEADER_SIZE].copy_from_slice(&info.to_bytes());
+
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+        assert!(matches!(
+            block_on(updater.get_dfu_image_info()),
+            Err(FirmwareUpdaterError::InvalidImage)
+        ));
+
+        block_on(updater.write_firmware(0, &update)).unwrap();
+        assert_eq!(info, block_on(updater.get_dfu_image_info()).unwrap());
+        block_on(updater.verify_image_and_mark_updated()).unwrap();
+        assert_eq!(State::Swap, block_on(updater.get_state()).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "sha256")]
+    fn can_verify_image_ending_at_dfu_end() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        // The image ends in the middle of the last 32 byte chunk read to verify it
+        let dfu = MemFlash::<4112, 16, 8>::default();
+        let mut aligned = [0; 8];
+
+        let mut update = [0x42; 4112];
+        let info = ImageInfo {
+            version: 7,
+            length: (update.len() - IMAGE_HEADER_SIZE) as u32,
+            flags: 0,
+            digest: sha2::Sha256::digest(&update[IMAGE_HEADER_SIZE..]).into(),
+            nonce: [0; 16],
+        };
+        update[..IMAGE_HEADER_SIZE].copy_from_slice(&info.to_bytes());
+
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+        block_on(updater.write_firmware(0, &update)).unwrap();
+        block_on(updater.verify_image_and_mark_updated()).unwrap();
+        assert_eq!(State::Swap, block_on(updater.get_state()).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "resumable-dfu")]
+    fn can_resume_update() {
+        use embedded_storage_async::nor_flash::ReadNorFlash;
+
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 8];
+
+        let mut update = [0; 16384];
+        for (i, b) in update.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+        assert_eq!(0, block_on(updater.resume_update()).unwrap());
+        for offset in (0..10240).step_by(1024) {
+            block_on(updater.write_firmware(offset, &update[offset..offset + 1024])).unwrap();
+        }
+
+        // Only complete sectors are recorded
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+        let offset = block_on(updater.resume_update()).unwrap();
+        assert_eq!(8192, offset);
+        block_on(updater.write_firmware(offset, &update[offset..])).unwrap();
+        assert_eq!(16384, block_on(updater.resume_update()).unwrap());
+
+        let mut written = [0; 16384];
+        block_on(updater.dfu.read(0, &mut written)).unwrap();
+        assert_eq!(update, written);
+
+        // More sectors than slots in the state partition
+        for _ in 0..150 {
+            block_on(updater.write_firmware(4096, &[0; 61440])).unwrap();
+        }
+        assert_eq!(65536, block_on(updater.resume_update()).unwrap());
+
+        // Writing the first sector starts a new download
+        block_on(updater.write_firmware(0, &update[..4096])).unwrap();
+        assert_eq!(4096, block_on(updater.resume_update()).unwrap());
+
+        block_on(updater.state.mark_updated()).unwrap();
+        block_on(updater.mark_booted()).unwrap();
+        assert_eq!(0, block_on(updater.resume_update()).unwrap());
+    }
+
+    #[test]
+    #[cfg(not(feature = "resumable-dfu"))]
+    fn write_firmware_only_writes_dfu() {
+        let state_flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<16384, 4096, 8>::default());
+        let dfu_flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<65536, 4096, 8>::default());
+        let mut aligned = [0; 8];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: Partition::new(&dfu_flash, 0, 65536),
+                state: Partition::new(&state_flash, 0, 16384),
+            },
+            &mut aligned,
+        );
+
+        block_on(state_flash.lock()).pending_write_successes = Some(0);
+        block_on(updater.write_firmware(0, &[0xAA; 12288])).unwrap();
+    }
+
+    #[test]
+    fn skips_unchanged_sectors() {
+        use embedded_storage_async::nor_flash::ReadNorFlash;
+
+        let state_flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<16384, 4096, 8>::default());
+        let dfu_flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<65536, 4096, 8>::default());
+        let mut aligned = [0; 8];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: Partition::new(&dfu_flash, 0, 65536),
+                state: Partition::new(&state_flash, 0, 16384),
+            },
+            &mut aligned,
+        );
+
+        let mut update = [0xAA; 12288];
+        block_on(updater.write_firmware(0, &update)).unwrap();
+
+        // Writing the same image again doesn't touch DFU
+        block_on(dfu_flash.lock()).pending_write_successes = Some(0);
+        block_on(updater.write_firmware(0, &update)).unwrap();
+        #[cfg(feature = "resumable-dfu")]
+        assert_eq!(12288, block_on(updater.resume_update()).unwrap());
+
+        // Only the changed sector is written
+        update[5000] = 0x55;
+        block_on(dfu_flash.lock()).pending_write_successes = Some(1);
+        block_on(updater.write_firmware(0, &update)).unwrap();
+
+        let mut written = [0; 12288];
+        block_on(updater.dfu.read(0, &mut written)).unwrap();
+        assert_eq!(update, written);
+    }
+
+    #[test]
+    #[cfg(feature = "robust-state")]
+    fn state_survives_torn_record_write() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 32768);
+        let mut aligned = [0; 8];
+        let mut state = FirmwareState::new(state, &mut aligned);
+
+        assert_eq!(State::Boot, block_on(state.get_state()).unwrap());
+        block_on(state.mark_updated()).unwrap();
+        assert_eq!(State::Swap, block_on(state.get_state()).unwrap());
+
+        // Power loss while writing the first copy of the record, after invalidating the progress
+        block_on(flash.lock()).pending_write_successes = Some(2);
+        assert!(block_on(state.mark_booted()).is_err());
+        block_on(flash.lock()).pending_write_successes = None;
+        assert_eq!(State::Swap, block_on(state.get_state()).unwrap());
+
+        // Power loss while writing the second copy
+        block_on(flash.lock()).pending_write_successes = Some(3);
+        assert!(block_on(state.mark_booted()).is_err());
+        block_on(flash.lock()).pending_write_successes = None;
+        assert_eq!(State::Boot, block_on(state.get_state()).unwrap());
+
+        block_on(state.mark_updated()).unwrap();
+        assert_eq!(State::Swap, block_on(state.get_state()).unwrap());
+    }
+
+    #[test]
+    #[cfg(feature = "rollback-protection")]
+    fn rollback_counter_survives_state_changes() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let mut aligned = [0; 8];
+        let mut state = FirmwareState::new(state, &mut aligned);
+
+        assert_eq!(0, block_on(state.rollback_counter()).unwrap());
+        block_on(state.update_rollback_counter(3)).unwrap();
+        block_on(state.update_rollback_counter(2)).unwrap();
+        assert_eq!(3, block_on(state.rollback_counter()).unwrap());
+
+        block_on(state.mark_updated()).unwrap();
+        block_on(state.mark_booted()).unwrap();
+        assert_eq!(3, block_on(state.rollback_counter()).unwrap());
+
+        // More updates than slots in the sector
+        for version in 4..1000 {
+            block_on(state.update_rollback_counter(version)).unwrap();
+        }
+        assert_eq!(999, block_on(state.rollback_counter()).unwrap());
+    }
 }
@@ -1,12 +1,17 @@
 use digest::Digest;
-#[cfg(target_os = "none")]
 use embassy_embedded_hal::flash::partition::BlockingPartition;
-#[cfg(target_os = "none")]
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embedded_storage::nor_flash::NorFlash;
 
 use super::FirmwareUpdaterConfig;
-use crate::{FirmwareUpdaterError, State, BOOT_MAGIC, DFU_DETACH_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC};
+#[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+use super::{decode_slot, encode_slot, SLOT_LEN};
+#[cfg(feature = "robust-state")]
+use crate::state_record::{state_record_offsets, StateRecord, STATE_RECORD_LEN};
+use crate::{
+    FirmwareUpdaterError, FirmwareVerifier, ImageInfo, PartitionTable, Progress, State, BOOT_MAGIC, DFU_DETACH_MAGIC,
+    REVERT_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC,
+};
 
 /// Blocking FirmwareUpdater is an application API for interacting with the BootLoader without the ability to
 /// 'mess up' the internal bootloader state
@@ -81,6 +86,34 @@ impl<'a, DFU: NorFlash, STATE: NorFlash>
     }
 }
 
+impl<'a, DFU: NorFlash, STATE: NorFlash>
+    FirmwareUpdaterConfig<BlockingPartition<'a, NoopRawMutex, DFU>, BlockingPartition<'a, NoopRawMutex, STATE>>
+{
+    /// Create a firmware updater config from the flash and a partition table read from flash, as an
+    /// alternative to [`FirmwareUpdaterConfig::from_linkerfile_blocking`].
+    ///
+    /// # Example
+    /// ```ignore
+    /// let table = flash.lock(|f| PartitionTable::read_blocking(&mut *f.borrow_mut(), PARTITION_TABLE_OFFSET))
+    ///     .unwrap()
+    ///     .expect("no partition table");
+    /// let config = FirmwareUpdaterConfig::from_partition_table_blocking(&flash, &flash, &table);
+    /// ```
+    pub fn from_partition_table_blocking(
+        dfu_flash: &'a embassy_sync::blocking_mutex::Mutex<NoopRawMutex, core::cell::RefCell<DFU>>,
+        state_flash: &'a embassy_sync::blocking_mutex::Mutex<NoopRawMutex, core::cell::RefCell<STATE>>,
+        table: &PartitionTable,
+    ) -> Self {
+        trace!("DFU: 0x{:x} - 0x{:x}", table.dfu.start, table.dfu.end);
+        trace!("STATE: 0x{:x} - 0x{:x}", table.state.start, table.state.end);
+
+        Self {
+            dfu: BlockingPartition::new(dfu_flash, table.dfu.start, table.dfu.size()),
+            state: BlockingPartition::new(state_flash, table.state.start, table.state.size()),
+        }
+    }
+}
+
 impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE> {
     /// Create a firmware updater instance with partition ranges for the update and state partitions.
     ///
@@ -111,71 +144,182 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
     ///
     /// Mark to trigger firmware swap on next boot if verify succeeds.
     ///
-    /// If the "ed25519-salty" feature is set (or another similar feature) then the signature is expected to have
-    /// been generated from a SHA-512 digest of the firmware bytes.
-    ///
-    /// If no signature feature is set then this method will always return a
-    /// signature error.
+    /// The signature is verified with the [`FirmwareVerifier`] selected by the signature feature, see
+    /// `Ed25519Dalek`, `Ed25519Salty` and `EcdsaP256` for the expected key and signature formats. To use another
+    /// algorithm or a hardware accelerator, use [`BlockingFirmwareUpdater::verify_and_mark_updated_with`] instead.
     #[cfg(feature = "_verify")]
     pub fn verify_and_mark_updated(
         &mut self,
-        _public_key: &[u8; 32],
-        _signature: &[u8; 64],
-        _update_len: u32,
+        public_key: &[u8],
+        signature: &[u8; 64],
+        update_len: u32,
+    ) -> Result<(), FirmwareUpdaterError> {
+        #[cfg(feature = "ed25519-dalek")]
+        let mut verifier = crate::Ed25519Dalek::new(public_key).map_err(FirmwareUpdaterError::Signature)?;
+        #[cfg(feature = "ed25519-salty")]
+        let mut verifier = crate::Ed25519Salty::new(public_key).map_err(FirmwareUpdaterError::Signature)?;
+        #[cfg(feature = "ecdsa-p256")]
+        let mut verifier = crate::EcdsaP256::new(public_key).map_err(FirmwareUpdaterError::Signature)?;
+
+        self.verify_and_mark_updated_with(&mut verifier, signature, update_len)
+    }
+
+    /// Verify the DFU with a [`FirmwareVerifier`]. If there is an error then DO NOT
+    /// proceed with updating the firmware (otherwise it could be malicious firmware).
+    ///
+    /// Mark to trigger firmware swap on next boot if verify succeeds.
+    ///
+    /// If the "rollback-protection" feature is set, the signed image must start with an image header,
+    /// and the update is rejected if the [`ImageInfo::version`] is lower than the rollback counter.
+    pub fn verify_and_mark_updated_with<V: FirmwareVerifier>(
+        &mut self,
+        verifier: &mut V,
+        signature: &[u8],
+        update_len: u32,
     ) -> Result<(), FirmwareUpdaterError> {
-        assert!(_update_len <= self.dfu.capacity() as u32);
+        assert!(update_len <= self.dfu.capacity() as u32);
 
         self.state.verify_booted()?;
 
-        #[cfg(feature = "ed25519-dalek")]
+        let mut digest = digest::Output::<V::Digest>::default();
+        let mut chunk_buf = [0; 32];
+        self.hash::<V::Digest>(update_len, &mut chunk_buf, &mut digest)?;
+        verifier
+            .verify(&digest, signature)
+            .map_err(FirmwareUpdaterError::Signature)?;
+
+        self.check_rollback(update_len)?;
+        self.state.mark_updated()
+    }
+
+    #[cfg_attr(not(feature = "rollback-protection"), allow(unused_variables))]
+    fn check_rollback(&mut self, update_len: u32) -> Result<(), FirmwareUpdaterError> {
+        #[cfg(feature = "rollback-protection")]
         {
-            use ed25519_dalek::{Signature, SignatureError, Verifier, VerifyingKey};
+            // The header must be covered by the signature
+            if (update_len as usize) < crate::IMAGE_HEADER_SIZE {
+                return Err(FirmwareUpdaterError::InvalidImage);
+            }
+            let info = self.get_dfu_image_info()?;
+            if info.version < self.state.rollback_counter()? {
+                return Err(FirmwareUpdaterError::Rollback);
+            }
+        }
+        Ok(())
+    }
 
-            use crate::digest_adapters::ed25519_dalek::Sha512;
+    /// Verify the CRC32 trailer of the update, and mark to trigger firmware swap on next boot if it matches.
+    ///
+    /// The update must end with the CRC32 (IEEE 802.3) of the preceding bytes, as 4 little-endian
+    /// bytes, and `update_len` includes them. This detects corrupted updates, but unlike a signature,
+    /// not malicious ones.
+    #[cfg(all(feature = "crc32", not(feature = "_verify")))]
+    pub fn verify_crc32_and_mark_updated(&mut self, update_len: u32) -> Result<(), FirmwareUpdaterError> {
+        let mut trailer = [0; 4];
+        let mut digest = [0; 4];
+        self.verify_trailer::<crate::digest_adapters::crc32::Crc32>(update_len, &mut trailer, &mut digest)?;
+        self.state.mark_updated()
+    }
 
-            let into_signature_error = |e: SignatureError| FirmwareUpdaterError::Signature(e.into());
+    /// Verify the SHA-256 trailer of the update, and mark to trigger firmware swap on next boot if it matches.
+    ///
+    /// The update must end with the SHA-256 digest of the preceding bytes, and `update_len` includes
+    /// it. This detects corrupted updates, but unlike a signature, not malicious ones.
+    #[cfg(all(feature = "sha256", not(feature = "_verify")))]
+    pub fn verify_sha256_and_mark_updated(&mut self, update_len: u32) -> Result<(), FirmwareUpdaterError> {
+        let mut trailer = [0; 32];
+        let mut digest = [0; 32];
+        self.verify_trailer::<sha2::Sha256>(update_len, &mut trailer, &mut digest)?;
+        self.state.mark_updated()
+    }
 
-            let public_key = VerifyingKey::from_bytes(_public_key).map_err(into_signature_error)?;
-            let signature = Signature::from_bytes(_signature);
+    #[cfg(all(any(feature = "crc32", feature = "sha256"), not(feature = "_verify")))]
+    fn verify_trailer<D: Digest>(
+        &mut self,
+        update_len: u32,
+        trailer: &mut [u8],
+        digest: &mut [u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(update_len <= self.dfu.capacity() as u32);
 
-            let mut message = [0; 64];
-            let mut chunk_buf = [0; 2];
-            self.hash::<Sha512>(_update_len, &mut chunk_buf, &mut message)?;
+        self.state.verify_booted()?;
+
+        let image_len = update_len
+            .checked_sub(trailer.len() as u32)
+            .ok_or(FirmwareUpdaterError::DigestMismatch)?;
+        self.read_unaligned(image_len, trailer)?;
 
-            public_key.verify(&message, &signature).map_err(into_signature_error)?
+        let mut chunk_buf = crate::AlignedBuffer([0; 32]);
+        self.hash::<D>(image_len, &mut chunk_buf.0, digest)?;
+        if trailer != digest {
+            return Err(FirmwareUpdaterError::DigestMismatch);
         }
-        #[cfg(feature = "ed25519-salty")]
-        {
-            use salty::{PublicKey, Signature};
+        Ok(())
+    }
 
-            use crate::digest_adapters::salty::Sha512;
+    /// Verify the image in DFU against the SHA-256 digest in its header, and mark to trigger
+    /// firmware swap on next boot if it matches.
+    ///
+    /// This detects corrupted updates, but unlike a signature, not malicious ones.
+    #[cfg(all(feature = "sha256", not(feature = "_verify")))]
+    pub fn verify_image_and_mark_updated(&mut self) -> Result<(), FirmwareUpdaterError> {
+        self.state.verify_booted()?;
+        let info = self.get_dfu_image_info()?;
+
+        let mut digest = sha2::Sha256::new();
+        let mut chunk_buf = crate::AlignedBuffer([0; 32]);
+        let end = info.update_len();
+        for offset in (crate::IMAGE_HEADER_SIZE as u32..end).step_by(chunk_buf.0.len()) {
+            let len = core::cmp::min((end - offset) as usize, chunk_buf.0.len());
+            // Don't read past the image, except to complete a read unit
+            let chunk = &mut chunk_buf.0[..len.next_multiple_of(DFU::READ_SIZE)];
+            self.dfu.read(offset, chunk)?;
+            digest.update(&chunk[..len]);
+        }
+        if digest.finalize().as_slice() != info.digest {
+            return Err(FirmwareUpdaterError::DigestMismatch);
+        }
 
-            fn into_signature_error<E>(_: E) -> FirmwareUpdaterError {
-                FirmwareUpdaterError::Signature(signature::Error::default())
-            }
+        self.state.mark_updated()
+    }
 
-            let public_key = PublicKey::try_from(_public_key).map_err(into_signature_error)?;
-            let signature = Signature::try_from(_signature).map_err(into_signature_error)?;
-
-            let mut message = [0; 64];
-            let mut chunk_buf = [0; 2];
-            self.hash::<Sha512>(_update_len, &mut chunk_buf, &mut message)?;
-
-            let r = public_key.verify(&message, &signature);
-            trace!(
-                "Verifying with public key {}, signature {} and message {} yields ok: {}",
-                public_key.to_bytes(),
-                signature.to_bytes(),
-                message,
-                r.is_ok()
-            );
-            r.map_err(into_signature_error)?
+    /// Read the header of the image in DFU.
+    ///
+    /// Returns [`FirmwareUpdaterError::InvalidImage`] if DFU doesn't start with an image header,
+    /// or if the image doesn't fit in DFU.
+    pub fn get_dfu_image_info(&mut self) -> Result<ImageInfo, FirmwareUpdaterError> {
+        let mut header = [0; crate::IMAGE_HEADER_SIZE];
+        self.read_unaligned(0, &mut header)?;
+        let info = ImageInfo::parse(&header).ok_or(FirmwareUpdaterError::InvalidImage)?;
+        if info.update_len() as usize > self.dfu.capacity() {
+            return Err(FirmwareUpdaterError::InvalidImage);
         }
+        Ok(info)
+    }
 
-        self.state.mark_updated()
+    // Read `buf.len()` bytes at any `offset` of DFU, through read units that respect the DFU alignment.
+    fn read_unaligned(&mut self, offset: u32, buf: &mut [u8]) -> Result<(), FirmwareUpdaterError> {
+        assert!(DFU::READ_SIZE <= 32);
+        let mut unit = crate::AlignedBuffer([0; 32]);
+        let unit = &mut unit.0[..DFU::READ_SIZE];
+
+        let mut done = 0;
+        while done < buf.len() {
+            let pos = offset as usize + done;
+            let start = pos % DFU::READ_SIZE;
+            self.dfu.read((pos - start) as u32, unit)?;
+            let len = core::cmp::min(DFU::READ_SIZE - start, buf.len() - done);
+            buf[done..done + len].copy_from_slice(&unit[start..start + len]);
+            done += len;
+        }
+        Ok(())
     }
 
-    /// Verify the update in DFU with any digest.
+    /// Compute the digest of the first `update_len` bytes of DFU with any digest, reading them in
+    /// chunks of `chunk_buf.len()` bytes.
+    ///
+    /// This can be used to compare the update against a checksum provided by the server, or to show
+    /// a fingerprint of the image. `output` must be the size of the digest output.
     pub fn hash<D: Digest>(
         &mut self,
         update_len: u32,
@@ -184,9 +328,12 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
     ) -> Result<(), FirmwareUpdaterError> {
         let mut digest = D::new();
         for offset in (0..update_len).step_by(chunk_buf.len()) {
-            self.dfu.read(offset, chunk_buf)?;
             let len = core::cmp::min((update_len - offset) as usize, chunk_buf.len());
-            digest.update(&chunk_buf[..len]);
+            // Don't read past the update, except to complete a read unit
+            let read_len = core::cmp::min(len.next_multiple_of(DFU::READ_SIZE), chunk_buf.len());
+            let chunk = &mut chunk_buf[..read_len];
+            self.dfu.read(offset, chunk)?;
+            digest.update(&chunk[..len]);
         }
         output.copy_from_slice(digest.finalize().as_slice());
         Ok(())
@@ -198,6 +345,18 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
         self.state.mark_updated()
     }
 
+    /// Read the rollback counter, see [`BlockingFirmwareState::rollback_counter`].
+    #[cfg(feature = "rollback-protection")]
+    pub fn rollback_counter(&mut self) -> Result<u32, FirmwareUpdaterError> {
+        self.state.rollback_counter()
+    }
+
+    /// Raise the rollback counter, see [`BlockingFirmwareState::update_rollback_counter`].
+    #[cfg(feature = "rollback-protection")]
+    pub fn update_rollback_counter(&mut self, version: u32) -> Result<(), FirmwareUpdaterError> {
+        self.state.update_rollback_counter(version)
+    }
+
     /// Mark to trigger USB DFU device on next boot.
     pub fn mark_dfu(&mut self) -> Result<(), FirmwareUpdaterError> {
         self.state.verify_booted()?;
@@ -209,13 +368,22 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
         self.state.mark_booted()
     }
 
+    /// Mark the update as failed, to run the previous firmware again on reset.
+    ///
+    /// See [`BlockingFirmwareState::mark_reverted`].
+    pub fn mark_reverted(&mut self) -> Result<(), FirmwareUpdaterError> {
+        self.state.mark_reverted()
+    }
+
     /// Writes firmware data to the device.
     ///
     /// This function writes the given data to the firmware area starting at the specified offset.
     /// It handles sector erasures and data writes while verifying the device is in a proper state
     /// for firmware updates. The function ensures that only unerased sectors are erased before
     /// writing and efficiently handles the writing process across sector boundaries and in
-    /// various configurations (data size, sector size, etc.).
+    /// various configurations (data size, sector size, etc.). A chunk covering a whole sector that
+    /// already holds the same data is neither erased nor written, which makes flashing a mostly
+    /// unchanged image faster and spares the flash.
     ///
     /// # Arguments
     ///
@@ -235,6 +403,19 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
     /// - There is a failure erasing a sector before writing.
     /// - There is a failure writing data to the device.
     pub fn write_firmware(&mut self, offset: usize, data: &[u8]) -> Result<(), FirmwareUpdaterError> {
+        self.write_firmware_with_progress(offset, data, |_| {})
+    }
+
+    /// Write data to a flash page, reporting progress.
+    ///
+    /// Same as [`write_firmware`](Self::write_firmware), except that `progress` is called after
+    /// each sector sized chunk is written, with the number of bytes of `data` written so far.
+    pub fn write_firmware_with_progress(
+        &mut self,
+        offset: usize,
+        data: &[u8],
+        mut progress: impl FnMut(Progress),
+    ) -> Result<(), FirmwareUpdaterError> {
         // Make sure we are running a booted firmware to avoid reverting to a bad state.
         self.state.verify_booted()?;
 
@@ -253,28 +434,68 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
                 .last_erased_dfu_sector_index
                 .map_or(true, |last_erased_sector| current_sector != last_erased_sector);
 
-            // If the sector needs to be erased, erase it and update the last erased sector index.
-            if need_erase {
-                self.dfu.erase(sector_start as u32, sector_end as u32)?;
-                self.last_erased_dfu_sector_index = Some(current_sector);
-            }
-
             // Calculate the size of the data chunk that can be written in the current iteration.
             let write_size = core::cmp::min(remaining_data.len(), sector_end - offset);
             // Split the data to get the current chunk to be written and the remaining data.
             let (data_chunk, rest) = remaining_data.split_at(write_size);
 
+            // If the sector needs to be erased, erase it and update the last erased sector index.
+            let mut unchanged = false;
+            if need_erase {
+                // Writing the first sector starts a new download.
+                #[cfg(feature = "resumable-dfu")]
+                if current_sector == 0 {
+                    self.state.clear_dfu_progress()?;
+                }
+                // A whole sector that already holds the same data, as when flashing a mostly
+                // unchanged image again, is left as is.
+                unchanged = write_size == DFU::ERASE_SIZE && self.dfu_matches(offset, data_chunk)?;
+                if !unchanged {
+                    self.dfu.erase(sector_start as u32, sector_end as u32)?;
+                }
+                self.last_erased_dfu_sector_index = Some(current_sector);
+            }
+
             // Write the current data chunk.
-            self.dfu.write(offset as u32, data_chunk)?;
+            if !unchanged {
+                self.dfu.write(offset as u32, data_chunk)?;
+            }
 
             // Update the offset and remaining data for the next iteration.
             remaining_data = rest;
             offset += write_size;
+
+            // Once a sector is complete, record it so that the download can be resumed from there.
+            #[cfg(feature = "resumable-dfu")]
+            if offset == sector_end {
+                self.state.record_dfu_progress(offset)?;
+            }
+
+            progress(Progress {
+                done: data.len() - remaining_data.len(),
+                total: data.len(),
+            });
         }
 
         Ok(())
     }
 
+    // Whether DFU at `offset` already holds `data`.
+    fn dfu_matches(&mut self, offset: usize, data: &[u8]) -> Result<bool, FirmwareUpdaterError> {
+        let mut buf = [0; 64];
+        if buf.len() % DFU::READ_SIZE != 0 || data.len() % DFU::READ_SIZE != 0 {
+            return Ok(false);
+        }
+        for (chunk_offset, chunk) in (offset..).step_by(buf.len()).zip(data.chunks(buf.len())) {
+            let buf = &mut buf[..chunk.len()];
+            self.dfu.read(chunk_offset as u32, buf)?;
+            if buf != chunk {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
     /// Prepare for an incoming DFU update by erasing the entire DFU area and
     /// returning its `Partition`.
     ///
@@ -282,10 +503,54 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
     /// exchange for added complexity.
     pub fn prepare_update(&mut self) -> Result<&mut DFU, FirmwareUpdaterError> {
         self.state.verify_booted()?;
+        #[cfg(feature = "resumable-dfu")]
+        self.state.clear_dfu_progress()?;
         self.dfu.erase(0, self.dfu.capacity() as u32)?;
 
         Ok(&mut self.dfu)
     }
+
+    /// Find the offset to resume an interrupted DFU download from.
+    ///
+    /// With the `resumable-dfu` feature, each time `write_firmware` completes a DFU sector, the end
+    /// of the sector is recorded in an erase sector of the state partition reserved for it. After a
+    /// reset, the download can continue from the returned offset instead of starting over, by
+    /// passing it to `write_firmware` along with the rest of the firmware.
+    ///
+    /// This assumes the firmware is written in order. Returns 0 if no download is in progress, and
+    /// the recorded progress is forgotten when starting a new download at offset 0, when calling
+    /// `prepare_update`, and when the state changes.
+    #[cfg(feature = "resumable-dfu")]
+    pub fn resume_update(&mut self) -> Result<usize, FirmwareUpdaterError> {
+        self.state.verify_booted()?;
+        self.last_erased_dfu_sector_index = None;
+        Ok(self.state.find_dfu_progress()?.0)
+    }
+
+    /// Prepare for an incoming DFU update, reporting progress.
+    ///
+    /// Same as [`prepare_update`](Self::prepare_update), except that the DFU area is erased one
+    /// sector at a time and `progress` is called after each sector with the number of bytes erased.
+    pub fn prepare_update_with_progress(
+        &mut self,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<&mut DFU, FirmwareUpdaterError> {
+        self.state.verify_booted()?;
+        #[cfg(feature = "resumable-dfu")]
+        self.state.clear_dfu_progress()?;
+
+        let capacity = self.dfu.capacity();
+        for sector_start in (0..capacity).step_by(DFU::ERASE_SIZE) {
+            self.dfu
+                .erase(sector_start as u32, (sector_start + DFU::ERASE_SIZE) as u32)?;
+            progress(Progress {
+                done: sector_start + DFU::ERASE_SIZE,
+                total: capacity,
+            });
+        }
+
+        Ok(&mut self.dfu)
+    }
 }
 
 /// Manages the state partition of the firmware update.
@@ -320,7 +585,7 @@ impl<'d, STATE: NorFlash> BlockingFirmwareState<'d, STATE> {
 
     // Make sure we are running a booted firmware to avoid reverting to a bad state.
     fn verify_booted(&mut self) -> Result<(), FirmwareUpdaterError> {
-        if self.get_state()? == State::Boot || self.get_state()? == State::DfuDetach {
+        if matches!(self.get_state()?, State::Boot | State::DfuDetach | State::Revert) {
             Ok(())
         } else {
             Err(FirmwareUpdaterError::BadState)
@@ -332,15 +597,25 @@ impl<'d, STATE: NorFlash> BlockingFirmwareState<'d, STATE> {
     /// This is useful to check if the bootloader has just done a swap, in order
     /// to do verifications and self-tests of the new image before calling
     /// `mark_booted`.
+    ///
+    /// Returns `Swap` if an update is marked to be swapped in on the next boot, or was swapped in
+    /// and awaits validation, and `Revert` if the bootloader reverted a failed update and the
+    /// previous firmware has not been marked booted since.
     pub fn get_state(&mut self) -> Result<State, FirmwareUpdaterError> {
-        self.state.read(0, &mut self.aligned)?;
+        #[cfg(feature = "robust-state")]
+        let magic = self.read_state_record()?.map(|r| r.magic);
+        #[cfg(not(feature = "robust-state"))]
+        let magic = {
+            self.state.read(0, &mut self.aligned)?;
+            let magic = self.aligned[0];
+            (!self.aligned.iter().any(|&b| b != magic)).then_some(magic)
+        };
 
-        if !self.aligned.iter().any(|&b| b != SWAP_MAGIC) {
-            Ok(State::Swap)
-        } else if !self.aligned.iter().any(|&b| b != DFU_DETACH_MAGIC) {
-            Ok(State::DfuDetach)
-        } else {
-            Ok(State::Boot)
+        match magic {
+            Some(SWAP_MAGIC) => Ok(State::Swap),
+            Some(DFU_DETACH_MAGIC) => Ok(State::DfuDetach),
+            Some(REVERT_MAGIC) => Ok(State::Revert),
+            _ => Ok(State::Boot),
         }
     }
 
@@ -359,10 +634,220 @@ impl<'d, STATE: NorFlash> BlockingFirmwareState<'d, STATE> {
         self.set_magic(BOOT_MAGIC)
     }
 
+    /// Mark the update as failed, to run the previous firmware again on reset.
+    ///
+    /// Call this instead of `mark_booted` when the self-tests of an update fail, then reset. If
+    /// the update was swapped in, the bootloader reverts it on the next boot. If it was only
+    /// marked updated, it is cancelled instead.
+    ///
+    /// Returns `BadState` if there is no update to revert.
+    pub fn mark_reverted(&mut self) -> Result<(), FirmwareUpdaterError> {
+        match self.get_state()? {
+            State::Swap if self.is_swapped_in()? => Ok(()),
+            State::Swap => self.set_magic(BOOT_MAGIC),
+            _ => Err(FirmwareUpdaterError::BadState),
+        }
+    }
+
+    // Whether the bootloader has started swapping in the update, going by the first swap progress
+    // index.
+    fn is_swapped_in(&mut self) -> Result<bool, FirmwareUpdaterError> {
+        let index = 2 * STATE::WRITE_SIZE;
+        if self.aligned.len() > index {
+            self.state.read(0, self.aligned)?;
+            Ok(self.aligned[index..index + STATE::WRITE_SIZE]
+                .iter()
+                .any(|&b| b != STATE_ERASE_VALUE))
+        } else {
+            self.state.read(index as u32, self.aligned)?;
+            Ok(self.aligned[..STATE::WRITE_SIZE]
+                .iter()
+                .any(|&b| b != STATE_ERASE_VALUE))
+        }
+    }
+
+    /// Read the rollback counter.
+    ///
+    /// This is the lowest firmware version accepted by `verify_and_mark_updated`, 0 if the counter
+    /// was never updated.
+    #[cfg(feature = "rollback-protection")]
+    pub fn rollback_counter(&mut self) -> Result<u32, FirmwareUpdaterError> {
+        Ok(self.find_rollback_counter()?.0)
+    }
+
+    /// Raise the rollback counter to `version`, to reject updates to lower versions.
+    ///
+    /// Call this with the version of the running firmware once it is marked booted, so that an
+    /// update that fails and is reverted doesn't prevent installing the previous version again.
+    /// The counter never decreases: a `version` lower than the current counter is ignored.
+    #[cfg(feature = "rollback-protection")]
+    pub fn update_rollback_counter(&mut self, version: u32) -> Result<(), FirmwareUpdaterError> {
+        let (counter, free_slot) = self.find_rollback_counter()?;
+        if version <= counter {
+            return Ok(());
+        }
+
+        let start = crate::rollback_counter_start(self.state.capacity(), STATE::ERASE_SIZE);
+        let slot = match free_slot {
+            Some(slot) => slot,
+            None => {
+                // All slots are used, start over from the beginning of the sector.
+                self.state.erase(start as u32, self.state.capacity() as u32)?;
+                0
+            }
+        };
+
+        let offset = start + slot * self.slot_size();
+        self.write_slot(offset, version)
+    }
+
+    // Returns the highest version stored in the rollback counter sector, and the first free slot.
+    #[cfg(feature = "rollback-protection")]
+    fn find_rollback_counter(&mut self) -> Result<(u32, Option<usize>), FirmwareUpdaterError> {
+        let start = crate::rollback_counter_start(self.state.capacity(), STATE::ERASE_SIZE);
+        let slot_size = self.slot_size();
+        let mut counter = 0;
+        for slot in 0..STATE::ERASE_SIZE / slot_size {
+            let encoded = self.read_slot(start + slot * slot_size)?;
+            if encoded.iter().all(|&b| b == STATE_ERASE_VALUE) {
+                return Ok((counter, Some(slot)));
+            }
+            if let Some(version) = decode_slot(&encoded) {
+                counter = counter.max(version);
+            }
+        }
+        Ok((counter, None))
+    }
+
+    // The DFU progress records are in their own erase sector, so that they are kept across state
+    // changes, and written without wearing out the sector holding the state.
+    #[cfg(feature = "resumable-dfu")]
+    fn dfu_progress_slots(&self) -> usize {
+        STATE::ERASE_SIZE / self.slot_size()
+    }
+
+    // Returns the last DFU offset recorded, and the first free record slot.
+    #[cfg(feature = "resumable-dfu")]
+    fn find_dfu_progress(&mut self) -> Result<(usize, Option<usize>), FirmwareUpdaterError> {
+        let start = crate::dfu_progress_start(self.state.capacity(), STATE::ERASE_SIZE);
+        let slot_size = self.slot_size();
+        let mut progress = 0;
+        for slot in 0..self.dfu_progress_slots() {
+            let encoded = self.read_slot(start + slot * slot_size)?;
+            if encoded.iter().all(|&b| b == STATE_ERASE_VALUE) {
+                return Ok((progress, Some(slot)));
+            }
+            if let Some(offset) = decode_slot(&encoded) {
+                progress = offset as usize;
+            }
+        }
+        Ok((progress, None))
+    }
+
+    // Record that the DFU partition is written up to `offset`.
+    #[cfg(feature = "resumable-dfu")]
+    fn record_dfu_progress(&mut self, offset: usize) -> Result<(), FirmwareUpdaterError> {
+        let slot = match self.find_dfu_progress()?.1 {
+            Some(slot) => slot,
+            None => {
+                // All slots are used, start over from the first one.
+                self.clear_dfu_progress()?;
+                0
+            }
+        };
+
+        let start = crate::dfu_progress_start(self.state.capacity(), STATE::ERASE_SIZE);
+        self.write_slot(start + slot * self.slot_size(), offset as u32)
+    }
+
+    // Forget the recorded DFU progress.
+    #[cfg(feature = "resumable-dfu")]
+    fn clear_dfu_progress(&mut self) -> Result<(), FirmwareUpdaterError> {
+        if self.find_dfu_progress()?.1 == Some(0) {
+            return Ok(());
+        }
+
+        let start = crate::dfu_progress_start(self.state.capacity(), STATE::ERASE_SIZE);
+        self.state.erase(start as u32, (start + STATE::ERASE_SIZE) as u32)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "robust-state")]
+    fn read_state_record(&mut self) -> Result<Option<StateRecord>, FirmwareUpdaterError> {
+        let mut copies = [None; 2];
+        for (copy, offset) in copies
+            .iter_mut()
+            .zip(state_record_offsets(self.state.capacity(), STATE::ERASE_SIZE))
+        {
+            let mut record = [0; STATE_RECORD_LEN];
+            self.read_bytes(offset, &mut record)?;
+            *copy = StateRecord::decode(&record);
+        }
+        Ok(StateRecord::newest(copies[0], copies[1]))
+    }
+
+    // Write both copies of the state record, one after the other.
+    #[cfg(feature = "robust-state")]
+    fn write_state_record(&mut self, record: StateRecord) -> Result<(), FirmwareUpdaterError> {
+        for offset in state_record_offsets(self.state.capacity(), STATE::ERASE_SIZE) {
+            self.state.erase(offset as u32, (offset + STATE::ERASE_SIZE) as u32)?;
+            self.write_bytes(offset, &record.encode())?;
+        }
+        Ok(())
+    }
+
+    #[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+    fn slot_size(&self) -> usize {
+        SLOT_LEN.next_multiple_of(self.aligned.len())
+    }
+
+    #[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+    fn read_slot(&mut self, offset: usize) -> Result<[u8; SLOT_LEN], FirmwareUpdaterError> {
+        let mut encoded = [0; SLOT_LEN];
+        self.read_bytes(offset, &mut encoded)?;
+        Ok(encoded)
+    }
+
+    #[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+    fn write_slot(&mut self, offset: usize, value: u32) -> Result<(), FirmwareUpdaterError> {
+        self.write_bytes(offset, &encode_slot(value))
+    }
+
+    // Read `buf.len()` bytes at `offset`, one aligned chunk at a time.
+    #[cfg(any(feature = "robust-state", feature = "resumable-dfu", feature = "rollback-protection"))]
+    fn read_bytes(&mut self, offset: usize, buf: &mut [u8]) -> Result<(), FirmwareUpdaterError> {
+        for (i, chunk) in buf.chunks_mut(self.aligned.len()).enumerate() {
+            let chunk_offset = offset + i * self.aligned.len();
+            self.state.read(chunk_offset as u32, self.aligned)?;
+            chunk.copy_from_slice(&self.aligned[..chunk.len()]);
+        }
+        Ok(())
+    }
+
+    // Write `data` at `offset`, one aligned chunk at a time, padding the last one.
+    #[cfg(any(feature = "robust-state", feature = "resumable-dfu", feature = "rollback-protection"))]
+    fn write_bytes(&mut self, offset: usize, data: &[u8]) -> Result<(), FirmwareUpdaterError> {
+        for (i, chunk) in data.chunks(self.aligned.len()).enumerate() {
+            self.aligned.fill(STATE_ERASE_VALUE);
+            self.aligned[..chunk.len()].copy_from_slice(chunk);
+            let chunk_offset = offset + i * self.aligned.len();
+            self.state.write(chunk_offset as u32, self.aligned)?;
+        }
+        Ok(())
+    }
+
     fn set_magic(&mut self, magic: u8) -> Result<(), FirmwareUpdaterError> {
-        self.state.read(0, &mut self.aligned)?;
+        #[cfg(feature = "robust-state")]
+        let current = self.read_state_record()?;
+        #[cfg(feature = "robust-state")]
+        let changed = current.map(|r| r.magic) != Some(magic);
+        #[cfg(not(feature = "robust-state"))]
+        let changed = {
+            self.state.read(0, &mut self.aligned)?;
+            self.aligned.iter().any(|&b| b != magic)
+        };
 
-        if self.aligned.iter().any(|&b| b != magic) {
+        if changed {
             // Read progress validity
             self.state.read(STATE::WRITE_SIZE as u32, &mut self.aligned)?;
 
@@ -374,12 +859,37 @@ impl<'d, STATE: NorFlash> BlockingFirmwareState<'d, STATE> {
                 self.state.write(STATE::WRITE_SIZE as u32, &self.aligned)?;
             }
 
+            // Leaving the swap state, the record is written before clearing the progress, so that a
+            // power loss in between leaves the new state with invalid progress, which is ignored.
+            // Entering it, the record is written after clearing the progress, so that a power loss in
+            // between leaves the previous state, instead of a swap state with invalid progress that
+            // the bootloader would take for a swap to revert.
+            #[cfg(feature = "robust-state")]
+            let record = StateRecord::next(current, magic);
+            #[cfg(feature = "robust-state")]
+            if magic != SWAP_MAGIC {
+                self.write_state_record(record)?;
+            }
+
             // Clear magic and progress
-            self.state.erase(0, self.state.capacity() as u32)?;
+            let state_len = crate::state_len(self.state.capacity(), STATE::ERASE_SIZE);
+            self.state.erase(0, state_len as u32)?;
+
+            #[cfg(feature = "robust-state")]
+            if magic == SWAP_MAGIC {
+                self.write_state_record(record)?;
+            }
 
             // Set magic
-            self.aligned.fill(magic);
-            self.state.write(0, &self.aligned)?;
+            #[cfg(not(feature = "robust-state"))]
+            {
+                self.aligned.fill(magic);
+                self.state.write(0, &self.aligned)?;
+            }
+
+            // A new download starts once the state changes.
+            #[cfg(feature = "resumable-dfu")]
+            self.clear_dfu_progress()?;
         }
         Ok(())
     }
@@ -392,6 +902,7 @@ mod tests {
     use embassy_embedded_hal::flash::partition::BlockingPartition;
     use embassy_sync::blocking_mutex::raw::NoopRawMutex;
     use embassy_sync::blocking_mutex::Mutex;
+    use embedded_storage::nor_flash::ReadNorFlash;
     use sha1::{Digest, Sha1};
 
     use super::*;
@@ -400,7 +911,7 @@ mod tests {
     #[test]
     fn can_verify_sha1() {
         let flash = Mutex::<NoopRawMutex, _>::new(RefCell::new(MemFlash::<131072, 4096, 8>::default()));
-        let state = BlockingPartition::new(&flash, 0, 4096);
+        let state = BlockingPartition::new(&flash, 0, 16384);
         let dfu = BlockingPartition::new(&flash, 65536, 65536);
         let mut aligned = [0; 8];
 
@@ -422,7 +933,7 @@ mod tests {
     #[test]
     fn can_verify_sha1_sector_bigger_than_chunk() {
         let flash = Mutex::<NoopRawMutex, _>::new(RefCell::new(MemFlash::<131072, 4096, 8>::default()));
-        let state = BlockingPartition::new(&flash, 0, 4096);
+        let state = BlockingPartition::new(&flash, 0, 16384);
         let dfu = BlockingPartition::new(&flash, 65536, 65536);
         let mut aligned = [0; 8];
 
@@ -448,7 +959,7 @@ mod tests {
     #[test]
     fn can_verify_sha1_sector_smaller_than_chunk() {
         let flash = Mutex::<NoopRawMutex, _>::new(RefCell::new(MemFlash::<131072, 1024, 8>::default()));
-        let state = BlockingPartition::new(&flash, 0, 4096);
+        let state = BlockingPartition::new(&flash, 0, 16384);
         let dfu = BlockingPartition::new(&flash, 65536, 65536);
         let mut aligned = [0; 8];
 
@@ -474,7 +985,7 @@ mod tests {
     #[test]
     fn can_verify_sha1_cross_sector_boundary() {
         let flash = Mutex::<NoopRawMutex, _>::new(RefCell::new(MemFlash::<131072, 1024, 8>::default()));
-        let state = BlockingPartition::new(&flash, 0, 4096);
+        let state = BlockingPartition::new(&flash, 0, 16384);
         let dfu = BlockingPartition::new(&flash, 65536, 65536);
         let mut aligned = [0; 8];
 
@@ -496,4 +1007,36 @@ mod tests {
 
         assert_eq!(Sha1::digest(update).as_slice(), hash);
     }
+
+    #[test]
+    fn can_configure_from_partition_table() {
+        let flash = Mutex::<NoopRawMutex, _>::new(RefCell::new(MemFlash::<131072, 4096, 8>::default()));
+        let table = PartitionTable {
+            active: crate::PartitionRange {
+                start: 20480,
+                end: 65536,
+            },
+            dfu: crate::PartitionRange {
+                start: 65536,
+                end: 131072,
+            },
+            state: crate::PartitionRange { start: 0, end: 16384 },
+        };
+        flash.lock(|f| f.borrow_mut().write(16384, &table.to_bytes())).unwrap();
+
+        let table = flash
+            .lock(|f| PartitionTable::read_blocking(&mut *f.borrow_mut(), 16384))
+            .unwrap()
+            .unwrap();
+        let config = FirmwareUpdaterConfig::from_partition_table_blocking(&flash, &flash, &table);
+        let mut aligned = [0; 8];
+        let mut updater = BlockingFirmwareUpdater::new(config, &mut aligned);
+        updater.write_firmware(0, &[0xAA; 8]).unwrap();
+
+        let mut read_buf = [0; 8];
+        flash
+            .lock(|f| ReadNorFlash::read(&mut *f.borrow_mut(), 65536, &mut read_buf))
+            .unwrap();
+        assert_eq!([0xAA; 8], read_buf);
+    }
 }
@@ -0,0 +1,418 @@
+use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
+use embedded_storage_async::nor_flash::{NorFlash as AsyncNorFlash, ReadNorFlash as AsyncReadNorFlash};
+
+use crate::{BlockingFirmwareUpdater, FirmwareUpdater, FirmwareUpdaterError};
+
+const OP_COPY: u8 = 0x00;
+const OP_INSERT: u8 = 0x01;
+
+enum Action<'p> {
+    Copy { offset: u32, len: u32 },
+    Insert(&'p [u8]),
+}
+
+/// Incremental patch parser, accepting the patch in chunks of any size.
+struct PatchDecoder {
+    header: [u8; 9],
+    header_len: usize,
+    insert_remaining: u32,
+}
+
+impl PatchDecoder {
+    const fn new() -> Self {
+        Self {
+            header: [0; 9],
+            header_len: 0,
+            insert_remaining: 0,
+        }
+    }
+
+    fn header_size(op: u8) -> Result<usize, FirmwareUpdaterError> {
+        match op {
+            OP_COPY => Ok(9),
+            OP_INSERT => Ok(5),
+            _ => Err(FirmwareUpdaterError::InvalidPatch),
+        }
+    }
+
+    /// Decode the next action from `patch`, consuming the bytes used.
+    ///
+    /// Returns `None` when `patch` has been fully consumed.
+    fn next<'p>(&mut self, patch: &mut &'p [u8]) -> Result<Option<Action<'p>>, FirmwareUpdaterError> {
+        loop {
+            if patch.is_empty() {
+                return Ok(None);
+            }
+
+            if self.insert_remaining > 0 {
+                let n = core::cmp::min(self.insert_remaining as usize, patch.len());
+                let (data, rest) = patch.split_at(n);
+                *patch = rest;
+                self.insert_remaining -= n as u32;
+                return Ok(Some(Action::Insert(data)));
+            }
+
+            if self.header_len == 0 {
+                self.header[0] = patch[0];
+                self.header_len = 1;
+                *patch = &patch[1..];
+            }
+            let size = Self::header_size(self.header[0])?;
+            let n = core::cmp::min(size - self.header_len, patch.len());
+            self.header[self.header_len..self.header_len + n].copy_from_slice(&patch[..n]);
+            self.header_len += n;
+            *patch = &patch[n..];
+            if self.header_len < size {
+                return Ok(None);
+            }
+
+            self.header_len = 0;
+            let arg = u32::from_le_bytes(self.header[1..5].try_into().unwrap());
+            if self.header[0] == OP_COPY {
+                let len = u32::from_le_bytes(self.header[5..9].try_into().unwrap());
+                return Ok(Some(Action::Copy { offset: arg, len }));
+            }
+            self.insert_remaining = arg;
+        }
+    }
+
+    fn is_idle(&self) -> bool {
+        self.header_len == 0 && self.insert_remaining == 0
+    }
+}
+
+/// Applies a delta patch to the active firmware, writing the result with a [`FirmwareUpdater`].
+///
+/// A patch is a sequence of operations, with little-endian fields:
+///
+/// - `0x00 offset:u32 len:u32`: copy `len` bytes of the active firmware, starting at `offset`.
+/// - `0x01 len:u32 data:[u8; len]`: insert `len` new bytes.
+///
+/// The new firmware is written to the DFU partition as usual, so the bootloader swaps it in
+/// the same way as a full image, and the active firmware stays untouched until then. A patch
+/// usually only inserts the changed functions and data, making it much smaller than the image.
+///
+/// The patch can be fed in chunks of any size with [`write`](Self::write), as it's received.
+/// The output is staged in `buf`, which must be a multiple of the DFU partition's write size;
+/// a larger buffer means fewer flash writes. The active partition is read at arbitrary offsets,
+/// so it must have a read size of 1, which is the case for internal flash.
+pub struct DeltaWriter<'a, ACTIVE> {
+    active: ACTIVE,
+    decoder: PatchDecoder,
+    buf: &'a mut [u8],
+    buf_len: usize,
+    offset: usize,
+}
+
+impl<'a, ACTIVE: AsyncReadNorFlash> DeltaWriter<'a, ACTIVE> {
+    /// Create a new delta writer, reading the old firmware from `active`.
+    pub fn new(active: ACTIVE, buf: &'a mut [u8]) -> Self {
+        Self {
+            active,
+            decoder: PatchDecoder::new(),
+            buf,
+            buf_len: 0,
+            offset: 0,
+        }
+    }
+
+    /// Apply the next chunk of the patch.
+    pub async fn write<DFU: AsyncNorFlash, STATE: AsyncNorFlash>(
+        &mut self,
+        updater: &mut FirmwareUpdater<'_, DFU, STATE>,
+        mut patch: &[u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(!self.buf.is_empty() && self.buf.len() % DFU::WRITE_SIZE == 0);
+
+        while let Some(action) = self.decoder.next(&mut patch)? {
+            match action {
+                Action::Insert(mut data) => {
+                    while !data.is_empty() {
+                        let n = core::cmp::min(data.len(), self.buf.len() - self.buf_len);
+                        self.buf[self.buf_len..self.buf_len + n].copy_from_slice(&data[..n]);
+                        self.buf_len += n;
+                        data = &data[n..];
+                        self.flush_full(updater).await?;
+                    }
+                }
+                Action::Copy { mut offset, len } => {
+                    let end = offset
+                        .checked_add(len)
+                        .filter(|&end| end as usize <= self.active.capacity())
+                        .ok_or(FirmwareUpdaterError::InvalidPatch)?;
+                    while offset < end {
+                        let n = core::cmp::min((end - offset) as usize, self.buf.len() - self.buf_len);
+                        self.active
+                            .read(offset, &mut self.buf[self.buf_len..self.buf_len + n])
+                            .await?;
+                        self.buf_len += n;
+                        offset += n as u32;
+                        self.flush_full(updater).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the remaining data, and return the size of the new firmware.
+    ///
+    /// The size is the one to verify with [`FirmwareUpdater::verify_and_mark_updated`] or
+    /// [`FirmwareUpdater::hash`].
+    pub async fn finish<DFU: AsyncNorFlash, STATE: AsyncNorFlash>(
+        self,
+        updater: &mut FirmwareUpdater<'_, DFU, STATE>,
+    ) -> Result<usize, FirmwareUpdaterError> {
+        if !self.decoder.is_idle() {
+            return Err(FirmwareUpdaterError::InvalidPatch);
+        }
+
+        let size = self.offset + self.buf_len;
+        if self.buf_len > 0 {
+            let padded = self.buf_len.next_multiple_of(DFU::WRITE_SIZE);
+            self.buf[self.buf_len..padded].fill(0xFF);
+            updater.write_firmware(self.offset, &self.buf[..padded]).await?;
+        }
+        Ok(size)
+    }
+
+    async fn flush_full<DFU: AsyncNorFlash, STATE: AsyncNorFlash>(
+        &mut self,
+        updater: &mut FirmwareUpdater<'_, DFU, STATE>,
+    ) -> Result<(), FirmwareUpdaterError> {
+        if self.buf_len == self.buf.len() {
+            updater.write_firmware(self.offset, self.buf).await?;
+            self.offset += self.buf_len;
+            self.buf_len = 0;
+        }
+        Ok(())
+    }
+}
+
+/// Applies a delta patch to the active firmware, writing the result with a [`BlockingFirmwareUpdater`].
+///
+/// See [`DeltaWriter`] for details.
+pub struct BlockingDeltaWriter<'a, ACTIVE> {
+    active: ACTIVE,
+    decoder: PatchDecoder,
+    buf: &'a mut [u8],
+    buf_len: usize,
+    offset: usize,
+}
+
+impl<'a, ACTIVE: ReadNorFlash> BlockingDeltaWriter<'a, ACTIVE> {
+    /// Create a new delta writer, reading the old firmware from `active`.
+    pub fn new(active: ACTIVE, buf: &'a mut [u8]) -> Self {
+        Self {
+            active,
+            decoder: PatchDecoder::new(),
+            buf,
+            buf_len: 0,
+            offset: 0,
+        }
+    }
+
+    /// Apply the next chunk of the patch.
+    pub fn write<DFU: NorFlash, STATE: NorFlash>(
+        &mut self,
+        updater: &mut BlockingFirmwareUpdater<'_, DFU, STATE>,
+        mut patch: &[u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(!self.buf.is_empty() && self.buf.len() % DFU::WRITE_SIZE == 0);
+
+        while let Some(action) = self.decoder.next(&mut patch)? {
+            match action {
+                Action::Insert(mut data) => {
+                    while !data.is_empty() {
+                        let n = core::cmp::min(data.len(), self.buf.len() - self.buf_len);
+                        self.buf[self.buf_len..self.buf_len + n].copy_from_slice(&data[..n]);
+                        self.buf_len += n;
+                        data = &data[n..];
+                        self.flush_full(updater)?;
+                    }
+                }
+                Action::Copy { mut offset, len } => {
+                    let end = offset
+                        .checked_add(len)
+                        .filter(|&end| end as usize <= self.active.capacity())
+                        .ok_or(FirmwareUpdaterError::InvalidPatch)?;
+                    while offset < end {
+                        let n = core::cmp::min((end - offset) as usize, self.buf.len() - self.buf_len);
+                        self.active
+                            .read(offset, &mut self.buf[self.buf_len..self.buf_len + n])?;
+                        self.buf_len += n;
+                        offset += n as u32;
+                        self.flush_full(updater)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the remaining data, and return the size of the new firmware.
+    pub fn finish<DFU: NorFlash, STATE: NorFlash>(
+        self,
+        updater: &mut BlockingFirmwareUpdater<'_, DFU, STATE>,
+    ) -> Result<usize, FirmwareUpdaterError> {
+        if !self.decoder.is_idle() {
+            return Err(FirmwareUpdaterError::InvalidPatch);
+        }
+
+        let size = self.offset + self.buf_len;
+        if self.buf_len > 0 {
+            let padded = self.buf_len.next_multiple_of(DFU::WRITE_SIZE);
+            self.buf[self.buf_len..padded].fill(0xFF);
+            updater.write_firmware(self.offset, &self.buf[..padded])?;
+        }
+        Ok(size)
+    }
+
+    fn flush_full<DFU: NorFlash, STATE: NorFlash>(
+        &mut self,
+        updater: &mut BlockingFirmwareUpdater<'_, DFU, STATE>,
+    ) -> Result<(), FirmwareUpdaterError> {
+        if self.buf_len == self.buf.len() {
+            updater.write_firmware(self.offset, self.buf)?;
+            self.offset += self.buf_len;
+            self.buf_len = 0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+
+    use embassy_embedded_hal::flash::partition::{BlockingPartition, Partition};
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::mutex::Mutex;
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::mem_flash::MemFlash;
+    use crate::FirmwareUpdaterConfig;
+
+    fn patch() -> ([u8; 8192], Vec<u8>, Vec<u8>) {
+        let mut old = [0; 8192];
+        for (i, b) in old.iter_mut().enumerate() {
+            *b = (i * 7) as u8;
+        }
+
+        // Keep the start, replace a few bytes, move a block and append new data.
+        let mut new = Vec::new();
+        new.extend_from_slice(&old[..3000]);
+        new.extend_from_slice(b"changed");
+        new.extend_from_slice(&old[5000..8192]);
+        new.extend_from_slice(&old[3007..5000]);
+        new.extend_from_slice(b"end");
+
+        let mut patch = Vec::new();
+        patch.push(OP_COPY);
+        patch.extend_from_slice(&0u32.to_le_bytes());
+        patch.extend_from_slice(&3000u32.to_le_bytes());
+        patch.push(OP_INSERT);
+        patch.extend_from_slice(&7u32.to_le_bytes());
+        patch.extend_from_slice(b"changed");
+        for (offset, len) in [(5000u32, 3192u32), (3007, 1993)] {
+            patch.push(OP_COPY);
+            patch.extend_from_slice(&offset.to_le_bytes());
+            patch.extend_from_slice(&len.to_le_bytes());
+        }
+        patch.push(OP_INSERT);
+        patch.extend_from_slice(&3u32.to_le_bytes());
+        patch.extend_from_slice(b"end");
+
+        (old, new, patch)
+    }
+
+    #[test]
+    fn can_apply_patch() {
+        let (old, new, patch) = patch();
+        let mut active = MemFlash::<8192, 4096, 4>::new(0xFF);
+        active.mem.copy_from_slice(&old);
+
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 4>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+
+        let mut buf = [0; 256];
+        let mut writer = DeltaWriter::new(&mut active, &mut buf);
+        // Chunks not aligned to the operations.
+        for chunk in patch.chunks(5) {
+            block_on(writer.write(&mut updater, chunk)).unwrap();
+        }
+        let size = block_on(writer.finish(&mut updater)).unwrap();
+
+        assert_eq!(new.len(), size);
+        let flash = flash.try_lock().unwrap();
+        assert_eq!(&new[..], &flash.mem[65536..65536 + size]);
+    }
+
+    #[test]
+    fn can_apply_patch_blocking() {
+        let (old, new, patch) = patch();
+        let mut active = MemFlash::<8192, 4096, 4>::new(0xFF);
+        active.mem.copy_from_slice(&old);
+
+        let flash = embassy_sync::blocking_mutex::Mutex::<NoopRawMutex, _>::new(core::cell::RefCell::new(MemFlash::<
+            131072,
+            4096,
+            4,
+        >::default(
+        )));
+        let state = BlockingPartition::new(&flash, 0, 16384);
+        let dfu = BlockingPartition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 4];
+        let mut updater = BlockingFirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+
+        let mut buf = [0; 512];
+        let mut writer = BlockingDeltaWriter::new(&mut active, &mut buf);
+        writer.write(&mut updater, &patch).unwrap();
+        let size = writer.finish(&mut updater).unwrap();
+
+        assert_eq!(new.len(), size);
+        let flash = flash.into_inner().into_inner();
+        assert_eq!(&new[..], &flash.mem[65536..65536 + size]);
+    }
+
+    #[test]
+    fn rejects_invalid_patch() {
+        let mut active = MemFlash::<8192, 4096, 4>::new(0xFF);
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 4>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+        let mut buf = [0; 256];
+
+        // Copy past the end of the active partition.
+        let mut writer = DeltaWriter::new(&mut active, &mut buf);
+        let mut patch = [OP_COPY, 0, 0, 0, 0, 0, 0, 0, 0];
+        patch[5..9].copy_from_slice(&8193u32.to_le_bytes());
+        assert!(matches!(
+            block_on(writer.write(&mut updater, &patch)),
+            Err(FirmwareUpdaterError::InvalidPatch)
+        ));
+
+        // Unknown operation.
+        let mut writer = DeltaWriter::new(&mut active, &mut buf);
+        assert!(matches!(
+            block_on(writer.write(&mut updater, &[0x42])),
+            Err(FirmwareUpdaterError::InvalidPatch)
+        ));
+
+        // Truncated insert.
+        let mut writer = DeltaWriter::new(&mut active, &mut buf);
+        block_on(writer.write(&mut updater, &[OP_INSERT, 4, 0, 0, 0, 1])).unwrap();
+        assert!(matches!(
+            block_on(writer.finish(&mut updater)),
+            Err(FirmwareUpdaterError::InvalidPatch)
+        ));
+    }
+}
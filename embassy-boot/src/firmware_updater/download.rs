@@ -0,0 +1,171 @@
+use digest::Update;
+use embedded_io_async::{Read, ReadExactError};
+use embedded_storage_async::nor_flash::NorFlash;
+
+use crate::{FirmwareUpdater, FirmwareUpdaterError, Progress};
+
+/// Errors returned by [`FirmwareUpdater::download_firmware`].
+#[derive(Debug)]
+pub enum DownloadError<E> {
+    /// Error from the reader.
+    Read(E),
+    /// The reader ended before the whole image was read.
+    UnexpectedEof,
+    /// Error writing the image to the DFU partition.
+    Updater(FirmwareUpdaterError),
+}
+
+#[cfg(feature = "defmt")]
+impl<E> defmt::Format for DownloadError<E> {
+    fn format(&self, fmt: defmt::Formatter) {
+        match self {
+            DownloadError::Read(_) => defmt::write!(fmt, "DownloadError::Read(_)"),
+            DownloadError::UnexpectedEof => defmt::write!(fmt, "DownloadError::UnexpectedEof"),
+            DownloadError::Updater(_) => defmt::write!(fmt, "DownloadError::Updater(_)"),
+        }
+    }
+}
+
+impl<E> From<FirmwareUpdaterError> for DownloadError<E> {
+    fn from(error: FirmwareUpdaterError) -> Self {
+        DownloadError::Updater(error)
+    }
+}
+
+impl<E> From<ReadExactError<E>> for DownloadError<E> {
+    fn from(error: ReadExactError<E>) -> Self {
+        match error {
+            ReadExactError::UnexpectedEof => DownloadError::UnexpectedEof,
+            ReadExactError::Other(e) => DownloadError::Read(e),
+        }
+    }
+}
+
+// Digest used when the caller doesn't need one.
+struct NoDigest;
+
+impl Update for NoDigest {
+    fn update(&mut self, _data: &[u8]) {}
+}
+
+impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
+    /// Download an image of `len` bytes from `reader`, such as a TCP socket or an HTTP response
+    /// body, into the DFU partition.
+    ///
+    /// The image is read in chunks of `chunk_buf.len()` bytes, which must be a multiple of the DFU
+    /// write size, and each chunk is written with [`write_firmware`](Self::write_firmware). The last
+    /// chunk is padded with 0xFF up to the write size. `progress` is called after each chunk, with
+    /// the number of bytes of the image downloaded so far.
+    ///
+    /// This doesn't mark the update as ready to be swapped, which is left to the caller once the
+    /// image has been verified.
+    pub async fn download_firmware<R: Read>(
+        &mut self,
+        reader: &mut R,
+        len: usize,
+        chunk_buf: &mut [u8],
+        progress: impl FnMut(Progress),
+    ) -> Result<(), DownloadError<R::Error>> {
+        self.download_firmware_with_digest(reader, len, chunk_buf, &mut NoDigest, progress)
+            .await
+    }
+
+    /// Download an image into the DFU partition, feeding it to `digest` on the way.
+    ///
+    /// Same as [`download_firmware`](Self::download_firmware), except that the image, without the
+    /// padding of the last chunk, is also fed to `digest`, so that it can be compared against a
+    /// checksum provided by the server without reading the DFU partition again.
+    pub async fn download_firmware_with_digest<R: Read>(
+        &mut self,
+        reader: &mut R,
+        len: usize,
+        chunk_buf: &mut [u8],
+        digest: &mut impl Update,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<(), DownloadError<R::Error>> {
+        assert_eq!(0, chunk_buf.len() % DFU::WRITE_SIZE);
+
+        let mut offset = 0;
+        while offset < len {
+            let n = core::cmp::min(len - offset, chunk_buf.len());
+            reader.read_exact(&mut chunk_buf[..n]).await?;
+            digest.update(&chunk_buf[..n]);
+
+            let padded = n.div_ceil(DFU::WRITE_SIZE) * DFU::WRITE_SIZE;
+            chunk_buf[n..padded].fill(0xFF);
+            self.write_firmware(offset, &chunk_buf[..padded]).await?;
+
+            offset += n;
+            progress(Progress {
+                done: offset,
+                total: len,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_embedded_hal::flash::partition::Partition;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::mutex::Mutex;
+    use embedded_storage_async::nor_flash::ReadNorFlash;
+    use futures::executor::block_on;
+    use sha1::{Digest, Sha1};
+
+    use super::*;
+    use crate::mem_flash::MemFlash;
+    use crate::FirmwareUpdaterConfig;
+
+    #[test]
+    fn can_download_firmware() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 8];
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+
+        let mut update = [0; 5003];
+        for (i, b) in update.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+
+        let mut reader = &update[..];
+        let mut chunk_buf = [0; 1024];
+        let mut digest = Sha1::new();
+        let mut done = [0; 5];
+        let mut chunks = 0;
+        block_on(
+            updater.download_firmware_with_digest(&mut reader, update.len(), &mut chunk_buf, &mut digest, |p| {
+                assert_eq!(update.len(), p.total);
+                done[chunks] = p.done;
+                chunks += 1;
+            }),
+        )
+        .unwrap();
+
+        assert_eq!([1024, 2048, 3072, 4096, 5003], done);
+        assert_eq!(Sha1::digest(update), digest.finalize());
+        let mut written = [0; 5008];
+        block_on(Partition::new(&flash, 65536, 65536).read(0, &mut written)).unwrap();
+        assert_eq!(update, written[..5003]);
+        assert_eq!([0xFF; 5], written[5003..]);
+    }
+
+    #[test]
+    fn fails_on_truncated_image() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 8>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 8];
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+
+        let mut reader = &[0xAA; 2000][..];
+        let mut chunk_buf = [0; 1024];
+        assert!(matches!(
+            block_on(updater.download_firmware(&mut reader, 4096, &mut chunk_buf, |_| {})),
+            Err(DownloadError::UnexpectedEof)
+        ));
+    }
+}
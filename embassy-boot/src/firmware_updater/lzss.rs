@@ -0,0 +1,358 @@
+use embedded_storage::nor_flash::NorFlash;
+use embedded_storage_async::nor_flash::NorFlash as AsyncNorFlash;
+
+use crate::{BlockingFirmwareUpdater, FirmwareUpdater, FirmwareUpdaterError};
+
+/// Size of the window holding the last decompressed bytes, which back references point into.
+pub const LZSS_WINDOW_SIZE: usize = 4096;
+
+/// Incremental LZSS decoder, accepting the compressed data in chunks of any size.
+struct LzssDecoder<'a> {
+    window: &'a mut [u8; LZSS_WINDOW_SIZE],
+    pos: usize,
+    flags: u8,
+    bits: u8,
+    low: Option<u8>,
+    copy_distance: usize,
+    copy_len: usize,
+}
+
+impl<'a> LzssDecoder<'a> {
+    fn new(window: &'a mut [u8; LZSS_WINDOW_SIZE]) -> Self {
+        Self {
+            window,
+            pos: 0,
+            flags: 0,
+            bits: 0,
+            low: None,
+            copy_distance: 0,
+            copy_len: 0,
+        }
+    }
+
+    fn push(&mut self, b: u8) {
+        self.window[self.pos % LZSS_WINDOW_SIZE] = b;
+        self.pos += 1;
+    }
+
+    /// Decode from `input` into `out`, consuming the bytes used.
+    ///
+    /// Returns the number of bytes decoded, which is less than `out.len()` only when `input` has
+    /// been fully consumed.
+    fn decode(&mut self, input: &mut &[u8], out: &mut [u8]) -> Result<usize, FirmwareUpdaterError> {
+        let mut n = 0;
+        while n < out.len() {
+            if self.copy_len > 0 {
+                let b = self.window[(self.pos - self.copy_distance) % LZSS_WINDOW_SIZE];
+                self.push(b);
+                out[n] = b;
+                n += 1;
+                self.copy_len -= 1;
+                continue;
+            }
+
+            let Some((&byte, rest)) = input.split_first() else {
+                break;
+            };
+            *input = rest;
+
+            if self.bits == 0 {
+                self.flags = byte;
+                self.bits = 8;
+            } else if self.flags & 1 == 1 {
+                self.push(byte);
+                out[n] = byte;
+                n += 1;
+                self.flags >>= 1;
+                self.bits -= 1;
+            } else if let Some(low) = self.low.take() {
+                let reference = u16::from_le_bytes([low, byte]) as usize;
+                self.copy_distance = (reference & 0xfff) + 1;
+                self.copy_len = (reference >> 12) + 3;
+                if self.copy_distance > self.pos {
+                    return Err(FirmwareUpdaterError::InvalidImage);
+                }
+                self.flags >>= 1;
+                self.bits -= 1;
+            } else {
+                self.low = Some(byte);
+            }
+        }
+        Ok(n)
+    }
+
+    fn is_idle(&self) -> bool {
+        self.low.is_none() && self.copy_len == 0
+    }
+}
+
+/// Decompresses an LZSS compressed firmware image, writing it with a [`FirmwareUpdater`].
+///
+/// The compressed data is a sequence of groups, each made of a flag byte followed by up to 8
+/// items. Each bit of the flag byte, starting from the least significant one, gives the kind of
+/// the next item:
+///
+/// - `1`: a literal byte, copied to the output.
+/// - `0`: a back reference, as a little-endian `u16`. The low 12 bits are the distance minus 1,
+///   and the high 4 bits the length minus 3. `length` bytes are copied from `distance` bytes
+///   back in the output, where the copy may overlap the bytes it produces.
+///
+/// This is the classic LZSS format with a 4 kB window, which compresses firmware images by about
+/// a third, and decompresses with little code and RAM. The image is decompressed as it's received,
+/// so the DFU partition holds the plain image, and the bootloader swaps it in as usual.
+///
+/// This shortens transfers, but doesn't shrink the DFU partition: swapping moves the previous
+/// firmware to the DFU partition to be able to revert, so it must be one page larger than the active
+/// partition whether the update is compressed or not.
+///
+/// The compressed data can be fed in chunks of any size with [`write`](Self::write). The output is
+/// staged in `buf`, which must be a multiple of the DFU partition's write size.
+pub struct LzssWriter<'a> {
+    decoder: LzssDecoder<'a>,
+    buf: &'a mut [u8],
+    buf_len: usize,
+    offset: usize,
+}
+
+impl<'a> LzssWriter<'a> {
+    /// Create a new decompressing writer.
+    pub fn new(window: &'a mut [u8; LZSS_WINDOW_SIZE], buf: &'a mut [u8]) -> Self {
+        Self {
+            decoder: LzssDecoder::new(window),
+            buf,
+            buf_len: 0,
+            offset: 0,
+        }
+    }
+
+    /// Decompress and write the next chunk of the compressed image.
+    pub async fn write<DFU: AsyncNorFlash, STATE: AsyncNorFlash>(
+        &mut self,
+        updater: &mut FirmwareUpdater<'_, DFU, STATE>,
+        mut data: &[u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(!self.buf.is_empty() && self.buf.len() % DFU::WRITE_SIZE == 0);
+
+        loop {
+            self.buf_len += self.decoder.decode(&mut data, &mut self.buf[self.buf_len..])?;
+            if self.buf_len < self.buf.len() {
+                return Ok(());
+            }
+            updater.write_firmware(self.offset, self.buf).await?;
+            self.offset += self.buf_len;
+            self.buf_len = 0;
+        }
+    }
+
+    /// Write the remaining data, and return the size of the decompressed firmware.
+    ///
+    /// The size is the one to verify with [`FirmwareUpdater::verify_and_mark_updated`] or
+    /// [`FirmwareUpdater::hash`].
+    pub async fn finish<DFU: AsyncNorFlash, STATE: AsyncNorFlash>(
+        self,
+        updater: &mut FirmwareUpdater<'_, DFU, STATE>,
+    ) -> Result<usize, FirmwareUpdaterError> {
+        if !self.decoder.is_idle() {
+            return Err(FirmwareUpdaterError::InvalidImage);
+        }
+
+        let size = self.offset + self.buf_len;
+        if self.buf_len > 0 {
+            let padded = self.buf_len.next_multiple_of(DFU::WRITE_SIZE);
+            self.buf[self.buf_len..padded].fill(0xFF);
+            updater.write_firmware(self.offset, &self.buf[..padded]).await?;
+        }
+        Ok(size)
+    }
+}
+
+/// Decompresses an LZSS compressed firmware image, writing it with a [`BlockingFirmwareUpdater`].
+///
+/// See [`LzssWriter`] for details.
+pub struct BlockingLzssWriter<'a> {
+    decoder: LzssDecoder<'a>,
+    buf: &'a mut [u8],
+    buf_len: usize,
+    offset: usize,
+}
+
+impl<'a> BlockingLzssWriter<'a> {
+    /// Create a new decompressing writer.
+    pub fn new(window: &'a mut [u8; LZSS_WINDOW_SIZE], buf: &'a mut [u8]) -> Self {
+        Self {
+            decoder: LzssDecoder::new(window),
+            buf,
+            buf_len: 0,
+            offset: 0,
+        }
+    }
+
+    /// Decompress and write the next chunk of the compressed image.
+    pub fn write<DFU: NorFlash, STATE: NorFlash>(
+        &mut self,
+        updater: &mut BlockingFirmwareUpdater<'_, DFU, STATE>,
+        mut data: &[u8],
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(!self.buf.is_empty() && self.buf.len() % DFU::WRITE_SIZE == 0);
+
+        loop {
+            self.buf_len += self.decoder.decode(&mut data, &mut self.buf[self.buf_len..])?;
+            if self.buf_len < self.buf.len() {
+                return Ok(());
+            }
+            updater.write_firmware(self.offset, self.buf)?;
+            self.offset += self.buf_len;
+            self.buf_len = 0;
+        }
+    }
+
+    /// Write the remaining data, and return the size of the decompressed firmware.
+    pub fn finish<DFU: NorFlash, STATE: NorFlash>(
+        self,
+        updater: &mut BlockingFirmwareUpdater<'_, DFU, STATE>,
+    ) -> Result<usize, FirmwareUpdaterError> {
+        if !self.decoder.is_idle() {
+            return Err(FirmwareUpdaterError::InvalidImage);
+        }
+
+        let size = self.offset + self.buf_len;
+        if self.buf_len > 0 {
+            let padded = self.buf_len.next_multiple_of(DFU::WRITE_SIZE);
+            self.buf[self.buf_len..padded].fill(0xFF);
+            updater.write_firmware(self.offset, &self.buf[..padded])?;
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+
+    use embassy_embedded_hal::flash::partition::{BlockingPartition, Partition};
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::mutex::Mutex;
+    use futures::executor::block_on;
+
+    use super::*;
+    use crate::mem_flash::MemFlash;
+    use crate::FirmwareUpdaterConfig;
+
+    /// Greedy compressor, producing the format expected by the decoder.
+    fn compress(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut flags_pos = 0;
+        let mut items = 8;
+        let mut pos = 0;
+        while pos < data.len() {
+            if items == 8 {
+                flags_pos = out.len();
+                out.push(0);
+                items = 0;
+            }
+
+            let mut best = (0, 0);
+            for distance in 1..=core::cmp::min(pos, LZSS_WINDOW_SIZE) {
+                let len = (0..core::cmp::min(18, data.len() - pos))
+                    .take_while(|&i| data[pos + i] == data[pos - distance + i])
+                    .count();
+                if len > best.1 {
+                    best = (distance, len);
+                }
+            }
+
+            if best.1 >= 3 {
+                let reference = (best.0 - 1) as u16 | ((best.1 - 3) as u16) << 12;
+                out.extend_from_slice(&reference.to_le_bytes());
+                pos += best.1;
+            } else {
+                out[flags_pos] |= 1 << items;
+                out.push(data[pos]);
+                pos += 1;
+            }
+            items += 1;
+        }
+        out
+    }
+
+    fn image() -> Vec<u8> {
+        let mut image = Vec::new();
+        for i in 0..3000u32 {
+            image.extend_from_slice(&(i / 7).to_le_bytes());
+        }
+        image.extend_from_slice(b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa");
+        image
+    }
+
+    #[test]
+    fn can_decompress() {
+        let image = image();
+        let compressed = compress(&image);
+        assert!(compressed.len() < image.len() / 2);
+
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 4>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+
+        let mut window = [0; LZSS_WINDOW_SIZE];
+        let mut buf = [0; 256];
+        let mut writer = LzssWriter::new(&mut window, &mut buf);
+        for chunk in compressed.chunks(7) {
+            block_on(writer.write(&mut updater, chunk)).unwrap();
+        }
+        let size = block_on(writer.finish(&mut updater)).unwrap();
+
+        assert_eq!(image.len(), size);
+        let flash = flash.try_lock().unwrap();
+        assert_eq!(&image[..], &flash.mem[65536..65536 + size]);
+    }
+
+    #[test]
+    fn can_decompress_blocking() {
+        let image = image();
+        let compressed = compress(&image);
+
+        let flash = embassy_sync::blocking_mutex::Mutex::<NoopRawMutex, _>::new(core::cell::RefCell::new(MemFlash::<
+            131072,
+            4096,
+            4,
+        >::default(
+        )));
+        let state = BlockingPartition::new(&flash, 0, 16384);
+        let dfu = BlockingPartition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 4];
+        let mut updater = BlockingFirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+
+        let mut window = [0; LZSS_WINDOW_SIZE];
+        let mut buf = [0; 512];
+        let mut writer = BlockingLzssWriter::new(&mut window, &mut buf);
+        writer.write(&mut updater, &compressed).unwrap();
+        let size = writer.finish(&mut updater).unwrap();
+
+        assert_eq!(image.len(), size);
+        let flash = flash.into_inner().into_inner();
+        assert_eq!(&image[..], &flash.mem[65536..65536 + size]);
+    }
+
+    #[test]
+    fn rejects_reference_before_start() {
+        let flash = Mutex::<NoopRawMutex, _>::new(MemFlash::<131072, 4096, 4>::default());
+        let state = Partition::new(&flash, 0, 16384);
+        let dfu = Partition::new(&flash, 65536, 65536);
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+
+        let mut window = [0; LZSS_WINDOW_SIZE];
+        let mut buf = [0; 256];
+        let mut writer = LzssWriter::new(&mut window, &mut buf);
+        // One literal, then a reference 2 bytes back.
+        assert!(matches!(
+            block_on(writer.write(&mut updater, &[0b01, b'a', 0x01, 0x00])),
+            Err(FirmwareUpdaterError::InvalidImage)
+        ));
+    }
+}
@@ -1,9 +1,17 @@
 mod asynch;
 mod blocking;
+mod delta;
+#[cfg(feature = "download")]
+mod download;
+mod lzss;
 
 pub use asynch::{FirmwareState, FirmwareUpdater};
 pub use blocking::{BlockingFirmwareState, BlockingFirmwareUpdater};
+pub use delta::{BlockingDeltaWriter, DeltaWriter};
+#[cfg(feature = "download")]
+pub use download::DownloadError;
 use embedded_storage::nor_flash::{NorFlashError, NorFlashErrorKind};
+pub use lzss::{BlockingLzssWriter, LzssWriter, LZSS_WINDOW_SIZE};
 
 /// Firmware updater flash configuration holding the two flashes used by the updater
 ///
@@ -26,6 +34,14 @@ pub enum FirmwareUpdaterError {
     Signature(signature::Error),
     /// Bad state.
     BadState,
+    /// The delta patch is malformed, or doesn't apply to the active firmware.
+    InvalidPatch,
+    /// The compressed firmware image, or the image header, is malformed.
+    InvalidImage,
+    /// The digest in the trailer of the update doesn't match its content.
+    DigestMismatch,
+    /// The version of the update is lower than the rollback counter.
+    Rollback,
 }
 
 #[cfg(feature = "defmt")]
@@ -35,6 +51,10 @@ impl defmt::Format for FirmwareUpdaterError {
             FirmwareUpdaterError::Flash(_) => defmt::write!(fmt, "FirmwareUpdaterError::Flash(_)"),
             FirmwareUpdaterError::Signature(_) => defmt::write!(fmt, "FirmwareUpdaterError::Signature(_)"),
             FirmwareUpdaterError::BadState => defmt::write!(fmt, "FirmwareUpdaterError::BadState"),
+            FirmwareUpdaterError::InvalidPatch => defmt::write!(fmt, "FirmwareUpdaterError::InvalidPatch"),
+            FirmwareUpdaterError::InvalidImage => defmt::write!(fmt, "FirmwareUpdaterError::InvalidImage"),
+            FirmwareUpdaterError::DigestMismatch => defmt::write!(fmt, "FirmwareUpdaterError::DigestMismatch"),
+            FirmwareUpdaterError::Rollback => defmt::write!(fmt, "FirmwareUpdaterError::Rollback"),
         }
     }
 }
@@ -47,3 +67,24 @@ where
         FirmwareUpdaterError::Flash(error.kind())
     }
 }
+
+// Each slot of the rollback counter and of the DFU progress records holds a value and its
+// complement, as little-endian u32s, so that a slot torn by a power loss is never mistaken for a
+// valid value.
+#[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+pub(crate) const SLOT_LEN: usize = 8;
+
+#[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+pub(crate) fn encode_slot(value: u32) -> [u8; SLOT_LEN] {
+    let mut slot = [0; SLOT_LEN];
+    slot[..4].copy_from_slice(&value.to_le_bytes());
+    slot[4..].copy_from_slice(&(!value).to_le_bytes());
+    slot
+}
+
+#[cfg(any(feature = "resumable-dfu", feature = "rollback-protection"))]
+pub(crate) fn decode_slot(slot: &[u8; SLOT_LEN]) -> Option<u32> {
+    let value = u32::from_le_bytes([slot[0], slot[1], slot[2], slot[3]]);
+    let complement = u32::from_le_bytes([slot[4], slot[5], slot[6], slot[7]]);
+    (value == !complement).then_some(value)
+}
@@ -0,0 +1,107 @@
+use embedded_storage::nor_flash::ReadNorFlash;
+
+/// Magic at the start of an image header, "EMBI" in little-endian.
+pub const IMAGE_HEADER_MAGIC: u32 = 0x4942_4d45;
+
+/// Size of the image header.
+///
+/// The header is part of the firmware image: the application must be linked to leave room for it
+/// at the start of the active partition, with the vector table and code following at
+/// [`IMAGE_VECTOR_TABLE_OFFSET`].
+pub const IMAGE_HEADER_SIZE: usize = 64;
+
+/// Offset of the vector table from the start of an image with a header.
+///
+/// The header is padded to this size, which meets the vector table alignment of Cortex-M chips
+/// with up to 240 interrupts. The application is linked to start at this offset in the active
+/// partition, and the `load` functions of the HAL specific bootloaders jump past the header when
+/// the active partition starts with one.
+pub const IMAGE_VECTOR_TABLE_OFFSET: usize = 1024;
+
+/// Information from the header at the start of a firmware image.
+///
+/// The header has the following format, with all integers in little-endian:
+///
+/// | Range  | Description                                                  |
+/// |--------|--------------------------------------------------------------|
+/// | 0..4   | [`IMAGE_HEADER_MAGIC`]                                       |
+/// | 4..8   | Firmware version                                             |
+/// | 8..12  | Length of the image following the header, padding included   |
+/// | 12..16 | Flags, free for the application to use                       |
+/// | 16..48 | SHA-256 digest of the image following the header             |
+/// | 48..64 | Nonce the image is encrypted with                            |
+///
+/// The header itself is never encrypted, so the application can read it from the DFU partition.
+/// The digest is of the image as written to the DFU partition, that is encrypted if a cipher is
+/// used, see [`ImageCipher`](crate::ImageCipher).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ImageInfo {
+    /// Firmware version.
+    pub version: u32,
+    /// Length of the image following the header.
+    pub length: u32,
+    /// Flags, free for the application to use.
+    pub flags: u32,
+    /// SHA-256 digest of the image following the header.
+    pub digest: [u8; 32],
+    /// Nonce the image is encrypted with, which must be unique for each image encrypted with the
+    /// same key. Unused by unencrypted images.
+    pub nonce: [u8; 16],
+}
+
+impl ImageInfo {
+    /// Parse an image header, returning `None` if it doesn't start with [`IMAGE_HEADER_MAGIC`].
+    pub fn parse(header: &[u8; IMAGE_HEADER_SIZE]) -> Option<Self> {
+        let word = |offset: usize| {
+            u32::from_le_bytes([
+                header[offset],
+                header[offset + 1],
+                header[offset + 2],
+                header[offset + 3],
+            ])
+        };
+
+        if word(0) != IMAGE_HEADER_MAGIC {
+            return None;
+        }
+        let mut digest = [0; 32];
+        digest.copy_from_slice(&header[16..48]);
+        let mut nonce = [0; 16];
+        nonce.copy_from_slice(&header[48..64]);
+        Some(Self {
+            version: word(4),
+            length: word(8),
+            flags: word(12),
+            digest,
+            nonce,
+        })
+    }
+
+    /// Serialize the image header, for tools building images.
+    pub fn to_bytes(&self) -> [u8; IMAGE_HEADER_SIZE] {
+        let mut header = [0; IMAGE_HEADER_SIZE];
+        header[0..4].copy_from_slice(&IMAGE_HEADER_MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&self.version.to_le_bytes());
+        header[8..12].copy_from_slice(&self.length.to_le_bytes());
+        header[12..16].copy_from_slice(&self.flags.to_le_bytes());
+        header[16..48].copy_from_slice(&self.digest);
+        header[48..64].copy_from_slice(&self.nonce);
+        header
+    }
+
+    /// Length of the whole update, header included.
+    pub fn update_len(&self) -> u32 {
+        IMAGE_HEADER_SIZE as u32 + self.length
+    }
+}
+
+// Read the image header at the start of `flash`, using `buf` as the read buffer.
+pub(crate) fn read_header<F: ReadNorFlash>(flash: &mut F, buf: &mut [u8]) -> Result<[u8; IMAGE_HEADER_SIZE], F::Error> {
+    let mut header = [0; IMAGE_HEADER_SIZE];
+    for (i, chunk) in header.chunks_mut(buf.len()).enumerate() {
+        flash.read((i * buf.len()) as u32, buf)?;
+        chunk.copy_from_slice(&buf[..chunk.len()]);
+    }
+    Ok(header)
+}
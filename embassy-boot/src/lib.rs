@@ -5,29 +5,99 @@
 mod fmt;
 
 mod boot_loader;
+mod cipher;
 mod digest_adapters;
+mod direct_xip;
 mod firmware_updater;
+mod image;
 #[cfg(test)]
 mod mem_flash;
+mod partition_table;
+#[cfg(feature = "serial-recovery")]
+mod recovery;
+mod recovery_policy;
+#[cfg(feature = "robust-state")]
+mod state_record;
 #[cfg(test)]
 mod test_flash;
+mod verifier;
 
 // The expected value of the flash after an erase
 // TODO: Use the value provided by NorFlash when available
 pub(crate) const STATE_ERASE_VALUE: u8 = 0xFF;
+
+// Length of the part of the state partition holding the magic and the swap progress. With the
+// `robust-state` feature, the two erase sectors that follow hold the copies of the state record.
+// With the `resumable-dfu` feature, the next erase sector holds the DFU download progress. With the
+// `rollback-protection` feature, the last erase sector holds the rollback counter. None of them is
+// erased along with the rest.
+pub(crate) const fn state_len(capacity: usize, erase_size: usize) -> usize {
+    let mut reserved = 0;
+    if cfg!(feature = "robust-state") {
+        reserved += 2;
+    }
+    if cfg!(feature = "resumable-dfu") {
+        reserved += 1;
+    }
+    if cfg!(feature = "rollback-protection") {
+        reserved += 1;
+    }
+    capacity - reserved * erase_size
+}
+
+// Offset of the DFU progress sector in the state partition.
+#[cfg(feature = "resumable-dfu")]
+pub(crate) const fn dfu_progress_start(capacity: usize, erase_size: usize) -> usize {
+    let mut end = capacity;
+    if cfg!(feature = "rollback-protection") {
+        end -= erase_size;
+    }
+    end - erase_size
+}
+
+// Offset of the rollback counter sector in the state partition.
+#[cfg(feature = "rollback-protection")]
+pub(crate) const fn rollback_counter_start(capacity: usize, erase_size: usize) -> usize {
+    capacity - erase_size
+}
+
 pub use boot_loader::{BootError, BootLoader, BootLoaderConfig};
+#[cfg(feature = "aes-ctr")]
+pub use cipher::Aes128CtrCipher;
+#[cfg(feature = "chacha20")]
+pub use cipher::ChaCha20Cipher;
+pub use cipher::{CipherStream, ImageCipher};
+pub use direct_xip::{BlockingDirectXipUpdater, DirectXipBootLoader, DirectXipConfig, DirectXipUpdater, Slot};
+#[cfg(feature = "download")]
+pub use firmware_updater::DownloadError;
 pub use firmware_updater::{
-    BlockingFirmwareState, BlockingFirmwareUpdater, FirmwareState, FirmwareUpdater, FirmwareUpdaterConfig,
-    FirmwareUpdaterError,
+    BlockingDeltaWriter, BlockingFirmwareState, BlockingFirmwareUpdater, BlockingLzssWriter, DeltaWriter,
+    FirmwareState, FirmwareUpdater, FirmwareUpdaterConfig, FirmwareUpdaterError, LzssWriter, LZSS_WINDOW_SIZE,
 };
+pub use image::{ImageInfo, IMAGE_HEADER_MAGIC, IMAGE_HEADER_SIZE, IMAGE_VECTOR_TABLE_OFFSET};
+pub use partition_table::{PartitionRange, PartitionTable, PARTITION_TABLE_MAGIC, PARTITION_TABLE_SIZE};
+#[cfg(feature = "serial-recovery")]
+pub use recovery::{RecoveryError, XmodemReceiver};
+pub use recovery_policy::{FailedBoots, HoldButton, MagicValue, RecoveryPolicy};
+#[cfg(feature = "ecdsa-p256")]
+pub use verifier::EcdsaP256;
+#[cfg(feature = "ed25519-dalek")]
+pub use verifier::Ed25519Dalek;
+#[cfg(feature = "ed25519-salty")]
+pub use verifier::Ed25519Salty;
+pub use verifier::{Digester, FirmwareVerifier};
 
 pub(crate) const BOOT_MAGIC: u8 = 0xD0;
 pub(crate) const SWAP_MAGIC: u8 = 0xF0;
 pub(crate) const DFU_DETACH_MAGIC: u8 = 0xE0;
+pub(crate) const REVERT_MAGIC: u8 = 0xC0;
 
 /// The state of the bootloader after running prepare.
+///
+/// New states may be added in minor releases, so matches on it need a wildcard arm.
 #[derive(PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum State {
     /// Bootloader is ready to boot the active partition.
     Boot,
@@ -35,6 +105,21 @@ pub enum State {
     Swap,
     /// Application has received a request to reboot into DFU mode to apply an update.
     DfuDetach,
+    /// Bootloader has reverted an update that was not marked booted, and boots the previous
+    /// firmware until it is marked booted again.
+    ///
+    /// Until then, `prepare_boot` returns this on every boot where it used to return `Boot`.
+    Revert,
+}
+
+/// Progress of a long running flash operation, reported to progress callbacks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Progress {
+    /// Amount of work done.
+    pub done: usize,
+    /// Total amount of work.
+    pub total: usize,
 }
 
 /// Buffer aligned to 32 byte boundary, largest known alignment requirement for embassy-boot.
@@ -67,6 +152,36 @@ mod tests {
     use crate::mem_flash::MemFlash;
     use crate::test_flash::{AsyncTestFlash, BlockingTestFlash};
 
+    // Size of a state partition with `size` bytes for the bootloader state, plus the sectors
+    // reserved for the state record copies and the rollback counter when enabled.
+    const fn state_size(size: usize, erase_size: usize) -> usize {
+        size + (usize::MAX - state_len(usize::MAX, erase_size))
+    }
+
+    // Toy cipher, XOR-ing with the nonce, the offset and the stream
+    struct XorCipher;
+
+    impl ImageCipher for XorCipher {
+        fn apply_keystream(&mut self, stream: CipherStream, nonce: &[u8; 16], offset: u32, data: &mut [u8]) {
+            for (i, b) in data.iter_mut().enumerate() {
+                *b ^= (offset as usize + i) as u8 ^ nonce[0] ^ if stream == CipherStream::Backup { 0x5A } else { 0 };
+            }
+        }
+    }
+
+    // Header of an image of `length` bytes after the header, which rollback protection reads the
+    // version from.
+    fn image_header(version: u32, length: usize) -> [u8; IMAGE_HEADER_SIZE] {
+        ImageInfo {
+            version,
+            length: length as u32,
+            flags: 0,
+            digest: [0; 32],
+            nonce: [0; 16],
+        }
+        .to_bytes()
+    }
+
     /*
     #[test]
     fn test_bad_magic() {
@@ -87,7 +202,7 @@ mod tests {
         let flash = BlockingTestFlash::new(BootLoaderConfig {
             active: MemFlash::<57344, 4096, 4>::default(),
             dfu: MemFlash::<61440, 4096, 4>::default(),
-            state: MemFlash::<4096, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
         });
 
         flash.state().write(0, &[BOOT_MAGIC; 4]).unwrap();
@@ -109,7 +224,7 @@ mod tests {
         let flash = AsyncTestFlash::new(BootLoaderConfig {
             active: MemFlash::<FIRMWARE_SIZE, 4096, 4>::default(),
             dfu: MemFlash::<61440, 4096, 4>::default(),
-            state: MemFlash::<4096, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
         });
 
         const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
@@ -187,7 +302,7 @@ mod tests {
         let flash = AsyncTestFlash::new(BootLoaderConfig {
             active: MemFlash::<12288, 4096, 8>::random(),
             dfu: MemFlash::<16384, 2048, 8>::random(),
-            state: MemFlash::<2048, 128, 4>::random(),
+            state: MemFlash::<{ state_size(2048, 128) }, 128, 4>::random(),
         });
 
         const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
@@ -232,7 +347,7 @@ mod tests {
         let flash = AsyncTestFlash::new(BootLoaderConfig {
             active: MemFlash::<FIRMWARE_SIZE, 2048, 4>::random(),
             dfu: MemFlash::<16384, 4096, 8>::random(),
-            state: MemFlash::<2048, 128, 4>::random(),
+            state: MemFlash::<{ state_size(2048, 128) }, 128, 4>::random(),
         });
 
         const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
@@ -270,7 +385,684 @@ mod tests {
     }
 
     #[test]
-    #[cfg(feature = "_verify")]
+    #[cfg(not(feature = "_verify"))]
+    fn test_swap_state_erase_sizes_not_multiples() {
+        const FIRMWARE_SIZE: usize = 24576;
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 6144, 4>::random(),
+            dfu: MemFlash::<36864, 4096, 4>::random(),
+            state: MemFlash::<{ state_size(2048, 128) }, 128, 4>::random(),
+        });
+
+        const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
+        const UPDATE: [u8; FIRMWARE_SIZE] = [0xAA; FIRMWARE_SIZE];
+        let mut aligned = [0; 4];
+
+        block_on(flash.active().erase(0, ORIGINAL.len() as u32)).unwrap();
+        block_on(flash.active().write(0, &ORIGINAL)).unwrap();
+
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &UPDATE)).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+        let mut page = [0; 4096];
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+
+        let mut read_buf = [0; FIRMWARE_SIZE];
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(UPDATE, read_buf);
+        // The original firmware is kept one 12288 byte page further for a revert
+        flash.dfu().read(12288, &mut read_buf).unwrap();
+        assert_eq!(ORIGINAL, read_buf);
+
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(ORIGINAL, read_buf);
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_swap_reports_progress() {
+        const FIRMWARE_SIZE: usize = 8192;
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 4096, 4>::default(),
+            dfu: MemFlash::<12288, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+
+        const UPDATE: [u8; FIRMWARE_SIZE] = [0xAA; FIRMWARE_SIZE];
+        let mut aligned = [0; 4];
+
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        let mut erased = Progress { done: 0, total: 0 };
+        block_on(updater.prepare_update_with_progress(|p| erased = p)).unwrap();
+        assert_eq!(
+            Progress {
+                done: 12288,
+                total: 12288
+            },
+            erased
+        );
+
+        let mut written = [Progress { done: 0, total: 0 }; 2];
+        let mut calls = 0;
+        block_on(updater.write_firmware_with_progress(0, &UPDATE, |p| {
+            written[calls] = p;
+            calls += 1;
+        }))
+        .unwrap();
+        assert_eq!(
+            [
+                Progress {
+                    done: 4096,
+                    total: 8192
+                },
+                Progress {
+                    done: 8192,
+                    total: 8192
+                }
+            ],
+            written
+        );
+        block_on(updater.mark_updated()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+        let mut page = [0; 4096];
+        let mut swapped = [Progress { done: 0, total: 0 }; 2];
+        let mut calls = 0;
+        assert_eq!(
+            State::Swap,
+            bootloader
+                .prepare_boot_with_progress(&mut page, |p| {
+                    swapped[calls] = p;
+                    calls += 1;
+                })
+                .unwrap()
+        );
+        assert_eq!(
+            [Progress { done: 1, total: 2 }, Progress { done: 2, total: 2 }],
+            swapped
+        );
+
+        // Reverting reports progress for every page as well
+        let mut reverted = 0;
+        assert_eq!(
+            State::Swap,
+            bootloader
+                .prepare_boot_with_progress(&mut page, |_| reverted += 1)
+                .unwrap()
+        );
+        assert_eq!(2, reverted);
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_mark_reverted() {
+        const FIRMWARE_SIZE: usize = 8192;
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 4096, 4>::default(),
+            dfu: MemFlash::<12288, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+
+        const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
+        const UPDATE: [u8; FIRMWARE_SIZE] = [0xAA; FIRMWARE_SIZE];
+        let mut aligned = [0; 4];
+
+        block_on(flash.active().erase(0, ORIGINAL.len() as u32)).unwrap();
+        block_on(flash.active().write(0, &ORIGINAL)).unwrap();
+
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+
+        // Nothing to revert
+        assert!(matches!(
+            block_on(updater.mark_reverted()),
+            Err(FirmwareUpdaterError::BadState)
+        ));
+
+        // An update that was not swapped in yet is cancelled
+        block_on(updater.write_firmware(0, &UPDATE)).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+        block_on(updater.mark_reverted()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+        let mut page = [0; 4096];
+        assert_eq!(State::Boot, bootloader.prepare_boot(&mut page).unwrap());
+
+        let mut read_buf = [0; FIRMWARE_SIZE];
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(ORIGINAL, read_buf);
+
+        // An update that was swapped in is reverted on the next boot
+        let flash = flash.into_async();
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &UPDATE)).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(UPDATE, read_buf);
+
+        let flash = flash.into_async();
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.mark_reverted()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(ORIGINAL, read_buf);
+        assert_eq!(State::Revert, bootloader.prepare_boot(&mut page).unwrap());
+
+        // The previous firmware sees the revert until it is marked booted
+        let flash = flash.into_async();
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        assert_eq!(State::Revert, block_on(updater.get_state()).unwrap());
+        block_on(updater.mark_booted()).unwrap();
+        assert_eq!(State::Boot, block_on(updater.get_state()).unwrap());
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_swap_progress_reports_interrupted_swap() {
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<8192, 4096, 4>::default(),
+            dfu: MemFlash::<12288, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &[0xAA; 8192])).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+        let mut page = [0; 4096];
+        assert_eq!(State::Swap, bootloader.get_state(&mut page).unwrap());
+        assert_eq!(Some(0), bootloader.swap_progress(&mut page).unwrap());
+
+        // Reset after swapping the first page and backing up the second one
+        for index in 0..3 {
+            flash.state().write(4 * (2 + index), &[0; 4]).unwrap();
+        }
+        assert_eq!(Some(1), bootloader.swap_progress(&mut page).unwrap());
+
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+        assert_eq!(State::Swap, bootloader.get_state(&mut page).unwrap());
+        assert_eq!(None, bootloader.swap_progress(&mut page).unwrap());
+    }
+
+    #[test]
+    #[cfg(all(feature = "robust-state", not(feature = "_verify")))]
+    fn test_power_loss_while_marking_updated() {
+        use embassy_embedded_hal::flash::partition::Partition;
+        use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+        use embassy_sync::mutex::Mutex;
+
+        const FIRMWARE_SIZE: usize = 57344;
+        const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
+        const UPDATE: [u8; FIRMWARE_SIZE] = [0xAA; FIRMWARE_SIZE];
+
+        // Power loss after each write of `mark_updated`, until it completes.
+        for writes in 0.. {
+            let mut active = MemFlash::<FIRMWARE_SIZE, 4096, 4>::default();
+            active.program(0, &ORIGINAL).unwrap();
+            let mut dfu = MemFlash::<61440, 4096, 4>::default();
+            dfu.program(0, &UPDATE).unwrap();
+            let state = MemFlash::<{ state_size(4096, 4096) }, 4096, 4> {
+                pending_write_successes: Some(writes),
+                ..Default::default()
+            };
+
+            let state = Mutex::<NoopRawMutex, _>::new(state);
+            let mut aligned = [0; 4];
+            let mut firmware_state =
+                FirmwareState::new(Partition::new(&state, 0, state_size(4096, 4096) as u32), &mut aligned);
+            let completed = block_on(firmware_state.mark_updated()).is_ok();
+            let mut state = state.into_inner();
+            state.pending_write_successes = None;
+
+            let flash = BlockingTestFlash::new(BootLoaderConfig { active, dfu, state });
+            let mut bootloader = BootLoader::new(BootLoaderConfig {
+                active: flash.active(),
+                dfu: flash.dfu(),
+                state: flash.state(),
+            });
+
+            // The update is either swapped in, or not marked at all, but never taken for a failed
+            // update to revert.
+            let mut page = [0; 1024];
+            let state = bootloader.prepare_boot(&mut page).unwrap();
+            let mut read_buf = [0; FIRMWARE_SIZE];
+            flash.active().read(0, &mut read_buf).unwrap();
+            match state {
+                State::Boot => assert_eq!(ORIGINAL, read_buf),
+                State::Swap => assert_eq!(UPDATE, read_buf),
+                state => panic!("unexpected state {:?} after {} writes", state, writes),
+            }
+
+            if completed {
+                assert_eq!(State::Swap, state);
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn test_recovery_policy_forces_dfu_detach() {
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<8192, 4096, 4>::default(),
+            dfu: MemFlash::<12288, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+        let register = core::cell::Cell::new(0);
+        let mut policy = MagicValue::new(|| register.get(), || register.set(0), 0xB007_DF00);
+        let mut page = [0; 4096];
+        assert_eq!(
+            State::Boot,
+            bootloader.prepare_boot_with_recovery(&mut page, &mut policy).unwrap()
+        );
+
+        register.set(0xB007_DF00);
+        assert_eq!(
+            State::DfuDetach,
+            bootloader.prepare_boot_with_recovery(&mut page, &mut policy).unwrap()
+        );
+        assert_eq!(
+            State::Boot,
+            bootloader.prepare_boot_with_recovery(&mut page, &mut policy).unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_swap_state_encrypted() {
+        const FIRMWARE_SIZE: usize = 57344;
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 4096, 4>::default(),
+            dfu: MemFlash::<61440, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+
+        const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
+        let mut update = [0xAA; FIRMWARE_SIZE];
+        let info = ImageInfo {
+            version: 1,
+            length: (FIRMWARE_SIZE - IMAGE_HEADER_SIZE) as u32,
+            flags: 0,
+            digest: [0; 32],
+            nonce: [0x17; 16],
+        };
+        update[..IMAGE_HEADER_SIZE].copy_from_slice(&info.to_bytes());
+        // The header is left unencrypted
+        let mut encrypted = update;
+        XorCipher.apply_keystream(
+            CipherStream::Update,
+            &info.nonce,
+            IMAGE_HEADER_SIZE as u32,
+            &mut encrypted[IMAGE_HEADER_SIZE..],
+        );
+        let mut aligned = [0; 4];
+
+        block_on(flash.active().erase(0, ORIGINAL.len() as u32)).unwrap();
+        block_on(flash.active().write(0, &ORIGINAL)).unwrap();
+
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &encrypted)).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+
+        let mut page = [0; 1024];
+        assert_eq!(
+            State::Swap,
+            bootloader.prepare_boot_with_cipher(&mut page, &mut XorCipher).unwrap()
+        );
+
+        let mut read_buf = [0; FIRMWARE_SIZE];
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(update, read_buf);
+        // The previous firmware is encrypted in DFU, with the nonce of the update
+        flash.dfu().read(4096, &mut read_buf).unwrap();
+        XorCipher.apply_keystream(
+            CipherStream::Backup,
+            &info.nonce,
+            IMAGE_HEADER_SIZE as u32,
+            &mut read_buf[IMAGE_HEADER_SIZE..],
+        );
+        assert_eq!(ORIGINAL, read_buf);
+
+        // Running again should cause a revert
+        assert_eq!(
+            State::Swap,
+            bootloader.prepare_boot_with_cipher(&mut page, &mut XorCipher).unwrap()
+        );
+
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(ORIGINAL, read_buf);
+        flash.dfu().read(0, &mut read_buf).unwrap();
+        assert_eq!(encrypted, read_buf);
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_swap_cancelled_if_encrypted_image_has_no_header() {
+        const FIRMWARE_SIZE: usize = 57344;
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 4096, 4>::default(),
+            dfu: MemFlash::<61440, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+
+        const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
+        const UPDATE: [u8; FIRMWARE_SIZE] = [0xAA; FIRMWARE_SIZE];
+        let mut aligned = [0; 4];
+
+        block_on(flash.active().erase(0, ORIGINAL.len() as u32)).unwrap();
+        block_on(flash.active().write(0, &ORIGINAL)).unwrap();
+
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &UPDATE)).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+
+        let mut page = [0; 1024];
+        assert_eq!(
+            State::Boot,
+            bootloader.prepare_boot_with_cipher(&mut page, &mut XorCipher).unwrap()
+        );
+
+        let mut read_buf = [0; FIRMWARE_SIZE];
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(ORIGINAL, read_buf);
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_swap_cancelled_if_image_too_large() {
+        const FIRMWARE_SIZE: usize = 57344;
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 4096, 4>::default(),
+            dfu: MemFlash::<61440, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+
+        let info = ImageInfo {
+            version: 1,
+            length: FIRMWARE_SIZE as u32,
+            flags: 0,
+            digest: [0; 32],
+            nonce: [0; 16],
+        };
+        let mut update = [0xAA; 4096];
+        update[..IMAGE_HEADER_SIZE].copy_from_slice(&info.to_bytes());
+
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &update)).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+
+        let mut page = [0; 1024];
+        assert_eq!(State::Boot, bootloader.prepare_boot(&mut page).unwrap());
+
+        let mut read_buf = [0; 4];
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!([0xFF; 4], read_buf);
+        assert_eq!(State::Boot, bootloader.prepare_boot(&mut page).unwrap());
+    }
+
+    #[test]
+    fn test_verify_with_custom_verifier() {
+        // Accepts the firmware if the signature is its SHA-1 digest
+        struct Sha1Verifier;
+
+        impl FirmwareVerifier for Sha1Verifier {
+            type Digest = sha1::Sha1;
+
+            fn verify(&mut self, digest: &[u8], signature: &[u8]) -> Result<(), signature::Error> {
+                if digest == signature {
+                    Ok(())
+                } else {
+                    Err(signature::Error::new())
+                }
+            }
+        }
+
+        use sha1::Digest;
+
+        let payload = b"This are bytes that would otherwise be firmware bytes for DFU.";
+        let mut firmware = [0; IMAGE_HEADER_SIZE + 62];
+        firmware[..IMAGE_HEADER_SIZE].copy_from_slice(&image_header(1, payload.len()));
+        firmware[IMAGE_HEADER_SIZE..].copy_from_slice(payload);
+        let signature = sha1::Sha1::digest(firmware);
+
+        let flash = BlockingTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<0, 0, 0>::default(),
+            dfu: MemFlash::<4096, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+        let mut write_buf = [0; 4096];
+        write_buf[..firmware.len()].copy_from_slice(&firmware);
+        flash.dfu().write(0, &write_buf).unwrap();
+
+        let flash = flash.into_async();
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+
+        assert!(matches!(
+            block_on(updater.verify_and_mark_updated_with(&mut Sha1Verifier, &[0; 20], firmware.len() as u32)),
+            Err(FirmwareUpdaterError::Signature(_))
+        ));
+        block_on(updater.verify_and_mark_updated_with(&mut Sha1Verifier, &signature, firmware.len() as u32)).unwrap();
+        assert_eq!(State::Swap, block_on(updater.get_state()).unwrap());
+    }
+
+    #[test]
+    fn test_verify_with_digester() {
+        // Accepts the firmware if the signature is its digest
+        struct DigestVerifier;
+
+        impl FirmwareVerifier for DigestVerifier {
+            type Digest = sha1::Sha1;
+
+            fn verify(&mut self, digest: &[u8], signature: &[u8]) -> Result<(), signature::Error> {
+                if digest == signature {
+                    Ok(())
+                } else {
+                    Err(signature::Error::new())
+                }
+            }
+        }
+
+        // Stands in for a hardware hash peripheral
+        struct Sha1Digester {
+            sha1: sha1::Sha1,
+            updates: usize,
+        }
+
+        impl Digester for Sha1Digester {
+            fn output_size(&self) -> usize {
+                20
+            }
+
+            async fn reset(&mut self) {
+                self.sha1 = Default::default();
+            }
+
+            async fn update(&mut self, data: &[u8]) {
+                sha1::Digest::update(&mut self.sha1, data);
+                self.updates += 1;
+            }
+
+            async fn finalize(&mut self, output: &mut [u8]) {
+                output.copy_from_slice(&sha1::Digest::finalize_reset(&mut self.sha1));
+            }
+        }
+
+        let mut firmware = [0x42; 1000];
+        firmware[..IMAGE_HEADER_SIZE].copy_from_slice(&image_header(1, 1000 - IMAGE_HEADER_SIZE));
+        let signature = <sha1::Sha1 as sha1::Digest>::digest(firmware);
+
+        let flash = BlockingTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<0, 0, 0>::default(),
+            dfu: MemFlash::<4096, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+        let mut write_buf = [0; 4096];
+        write_buf[..firmware.len()].copy_from_slice(&firmware);
+        flash.dfu().write(0, &write_buf).unwrap();
+
+        let flash = flash.into_async();
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+
+        let mut digester = Sha1Digester {
+            sha1: Default::default(),
+            updates: 0,
+        };
+        let mut chunk_buf = [0; 256];
+        block_on(updater.verify_and_mark_updated_with_digester(
+            &mut DigestVerifier,
+            &mut digester,
+            &mut chunk_buf,
+            &signature,
+            firmware.len() as u32,
+        ))
+        .unwrap();
+        assert_eq!(4, digester.updates);
+        assert_eq!(State::Swap, block_on(updater.get_state()).unwrap());
+    }
+
+    #[test]
+    #[cfg(any(feature = "ed25519-dalek", feature = "ed25519-salty"))]
     fn test_verify() {
         // The following key setup is based on:
         // https://docs.rs/ed25519-dalek/latest/ed25519_dalek/#example
@@ -281,7 +1073,10 @@ mod tests {
         let mut csprng = OsRng {};
         let keypair = SigningKey::generate(&mut csprng);
 
-        let firmware: &[u8] = b"This are bytes that would otherwise be firmware bytes for DFU.";
+        let payload = b"This are bytes that would otherwise be firmware bytes for DFU.";
+        let mut firmware = [0; IMAGE_HEADER_SIZE + 62];
+        firmware[..IMAGE_HEADER_SIZE].copy_from_slice(&image_header(1, payload.len()));
+        firmware[IMAGE_HEADER_SIZE..].copy_from_slice(payload);
         let mut digest = Sha512::new();
         digest.update(&firmware);
         let message = digest.finalize();
@@ -293,13 +1088,13 @@ mod tests {
         let flash = BlockingTestFlash::new(BootLoaderConfig {
             active: MemFlash::<0, 0, 0>::default(),
             dfu: MemFlash::<4096, 4096, 4>::default(),
-            state: MemFlash::<4096, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
         });
 
         let firmware_len = firmware.len();
 
         let mut write_buf = [0; 4096];
-        write_buf[0..firmware_len].copy_from_slice(firmware);
+        write_buf[0..firmware_len].copy_from_slice(&firmware);
         flash.dfu().write(0, &write_buf).unwrap();
 
         // On with the test
@@ -320,4 +1115,103 @@ mod tests {
         ))
         .is_ok());
     }
+
+    #[test]
+    #[cfg(feature = "ecdsa-p256")]
+    fn test_verify_p256() {
+        use p256::ecdsa::signature::Signer;
+        use p256::ecdsa::{Signature, SigningKey};
+
+        let keypair = SigningKey::from_slice(&[0x42; 32]).unwrap();
+
+        let payload = b"This are bytes that would otherwise be firmware bytes for DFU.";
+        let mut firmware = [0; IMAGE_HEADER_SIZE + 62];
+        firmware[..IMAGE_HEADER_SIZE].copy_from_slice(&image_header(1, payload.len()));
+        firmware[IMAGE_HEADER_SIZE..].copy_from_slice(payload);
+        let signature: Signature = keypair.sign(&firmware);
+        let public_key = keypair.verifying_key().to_encoded_point(true);
+
+        let flash = BlockingTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<0, 0, 0>::default(),
+            dfu: MemFlash::<4096, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+        let mut write_buf = [0; 4096];
+        write_buf[..firmware.len()].copy_from_slice(&firmware);
+        flash.dfu().write(0, &write_buf).unwrap();
+
+        let flash = flash.into_async();
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+
+        let mut tampered = signature.to_bytes();
+        tampered[0] ^= 1;
+        assert!(block_on(updater.verify_and_mark_updated(
+            public_key.as_bytes(),
+            tampered.as_slice().try_into().unwrap(),
+            firmware.len() as u32,
+        ))
+        .is_err());
+        assert!(block_on(updater.verify_and_mark_updated(
+            public_key.as_bytes(),
+            signature.to_bytes().as_slice().try_into().unwrap(),
+            firmware.len() as u32,
+        ))
+        .is_ok());
+    }
+
+    #[test]
+    #[cfg(all(feature = "ed25519-dalek", feature = "rollback-protection"))]
+    fn test_verify_rejects_rollback() {
+        use ed25519_dalek::{Digest, Sha512, Signature, Signer, SigningKey};
+        use rand::rngs::OsRng;
+
+        let keypair = SigningKey::generate(&mut OsRng {});
+
+        // Firmware version 2, in the image header
+        let mut firmware = [0xAA; 64];
+        firmware[..IMAGE_HEADER_SIZE].copy_from_slice(&image_header(2, 64 - IMAGE_HEADER_SIZE));
+        let mut digest = Sha512::new();
+        digest.update(firmware);
+        let signature: Signature = keypair.sign(&digest.finalize());
+
+        let flash = BlockingTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<0, 0, 0>::default(),
+            dfu: MemFlash::<4096, 4096, 4>::default(),
+            state: MemFlash::<{ state_size(4096, 4096) }, 4096, 4>::default(),
+        });
+        let mut write_buf = [0; 4096];
+        write_buf[..firmware.len()].copy_from_slice(&firmware);
+        flash.dfu().write(0, &write_buf).unwrap();
+
+        let flash = flash.into_async();
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+
+        block_on(updater.update_rollback_counter(3)).unwrap();
+        assert!(matches!(
+            block_on(updater.verify_and_mark_updated(
+                &keypair.verifying_key().to_bytes(),
+                &signature.to_bytes(),
+                firmware.len() as u32,
+            )),
+            Err(FirmwareUpdaterError::Rollback)
+        ));
+
+        block_on(updater.update_rollback_counter(2)).unwrap();
+        // The counter doesn't decrease
+        assert_eq!(3, block_on(updater.rollback_counter()).unwrap());
+    }
 }
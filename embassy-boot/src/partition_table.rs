@@ -0,0 +1,158 @@
+use embedded_storage::nor_flash::ReadNorFlash;
+
+use crate::digest_adapters::crc32;
+
+/// Magic at the start of a partition table, "EBPT" in little-endian.
+pub const PARTITION_TABLE_MAGIC: u32 = 0x5450_4245;
+
+/// Size of a partition table.
+pub const PARTITION_TABLE_SIZE: usize = 32;
+
+/// Range of flash addresses of a partition, with an exclusive end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PartitionRange {
+    /// Address of the start of the partition.
+    pub start: u32,
+    /// Address of the end of the partition.
+    pub end: u32,
+}
+
+impl PartitionRange {
+    /// Size of the partition.
+    pub fn size(&self) -> u32 {
+        self.end - self.start
+    }
+}
+
+/// Layout of the bootloader partitions, stored in flash as an alternative to the `__bootloader_*`
+/// linker symbols.
+///
+/// Storing the table at an address known to the bootloader and the application, for instance in
+/// a sector written when provisioning the device, lets the same binaries run on hardware variants
+/// with different flash layouts.
+///
+/// The table has the following format, with all integers in little-endian:
+///
+/// | Range  | Description                           |
+/// |--------|---------------------------------------|
+/// | 0..4   | [`PARTITION_TABLE_MAGIC`]             |
+/// | 4..12  | Start and end of the ACTIVE partition |
+/// | 12..20 | Start and end of the DFU partition    |
+/// | 20..28 | Start and end of the STATE partition  |
+/// | 28..32 | CRC-32 of bytes 0..28                 |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PartitionTable {
+    /// The active partition.
+    pub active: PartitionRange,
+    /// The dfu partition.
+    pub dfu: PartitionRange,
+    /// The state partition.
+    pub state: PartitionRange,
+}
+
+impl PartitionTable {
+    /// Parse a partition table, returning `None` if its magic or CRC is wrong, or if a partition
+    /// ends before it starts.
+    pub fn parse(table: &[u8; PARTITION_TABLE_SIZE]) -> Option<Self> {
+        let word = |offset: usize| {
+            u32::from_le_bytes([table[offset], table[offset + 1], table[offset + 2], table[offset + 3]])
+        };
+        let range = |offset: usize| PartitionRange {
+            start: word(offset),
+            end: word(offset + 4),
+        };
+
+        if word(0) != PARTITION_TABLE_MAGIC || table[28..32] != crc32::checksum(&table[..28]) {
+            return None;
+        }
+        let table = Self {
+            active: range(4),
+            dfu: range(12),
+            state: range(20),
+        };
+        if [table.active, table.dfu, table.state].iter().any(|r| r.end < r.start) {
+            return None;
+        }
+        Some(table)
+    }
+
+    /// Serialize the partition table, for tools provisioning devices.
+    pub fn to_bytes(&self) -> [u8; PARTITION_TABLE_SIZE] {
+        let mut table = [0; PARTITION_TABLE_SIZE];
+        table[0..4].copy_from_slice(&PARTITION_TABLE_MAGIC.to_le_bytes());
+        for (i, range) in [self.active, self.dfu, self.state].iter().enumerate() {
+            let offset = 4 + 8 * i;
+            table[offset..offset + 4].copy_from_slice(&range.start.to_le_bytes());
+            table[offset + 4..offset + 8].copy_from_slice(&range.end.to_le_bytes());
+        }
+        let crc = crc32::checksum(&table[..28]);
+        table[28..32].copy_from_slice(&crc);
+        table
+    }
+
+    /// Read the partition table at `offset` in `flash`, returning `None` if there is no valid table.
+    pub fn read_blocking<F: ReadNorFlash>(flash: &mut F, offset: u32) -> Result<Option<Self>, F::Error> {
+        let mut table = [0; PARTITION_TABLE_SIZE];
+        flash.read(offset, &mut table)?;
+        Ok(Self::parse(&table))
+    }
+
+    /// Read the partition table at `offset` in `flash`, returning `None` if there is no valid table.
+    pub async fn read<F: embedded_storage_async::nor_flash::ReadNorFlash>(
+        flash: &mut F,
+        offset: u32,
+    ) -> Result<Option<Self>, F::Error> {
+        let mut table = [0; PARTITION_TABLE_SIZE];
+        flash.read(offset, &mut table).await?;
+        Ok(Self::parse(&table))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mem_flash::MemFlash;
+
+    const TABLE: PartitionTable = PartitionTable {
+        active: PartitionRange {
+            start: 0x8000,
+            end: 0x2_0000,
+        },
+        dfu: PartitionRange {
+            start: 0x2_0000,
+            end: 0x3_9000,
+        },
+        state: PartitionRange {
+            start: 0x6000,
+            end: 0x7000,
+        },
+    };
+
+    #[test]
+    fn can_serialize_and_parse() {
+        assert_eq!(Some(TABLE), PartitionTable::parse(&TABLE.to_bytes()));
+
+        let mut corrupted = TABLE.to_bytes();
+        corrupted[5] ^= 0x10;
+        assert_eq!(None, PartitionTable::parse(&corrupted));
+        assert_eq!(None, PartitionTable::parse(&[0xFF; PARTITION_TABLE_SIZE]));
+
+        let mut reversed = TABLE;
+        reversed.state = PartitionRange {
+            start: 0x7000,
+            end: 0x6000,
+        };
+        assert_eq!(None, PartitionTable::parse(&reversed.to_bytes()));
+    }
+
+    #[test]
+    fn can_read_from_flash() {
+        let mut flash = MemFlash::<8192, 4096, 4>::default();
+        assert_eq!(None, PartitionTable::read_blocking(&mut flash, 4096).unwrap());
+
+        embedded_storage::nor_flash::NorFlash::write(&mut flash, 4096, &TABLE.to_bytes()).unwrap();
+        assert_eq!(Some(TABLE), PartitionTable::read_blocking(&mut flash, 4096).unwrap());
+    }
+}
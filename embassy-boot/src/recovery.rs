@@ -0,0 +1,390 @@
+use embedded_hal::delay::DelayNs;
+use embedded_io::{Read, ReadReady, Write};
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{BlockingFirmwareUpdater, FirmwareUpdaterError};
+
+const SOH: u8 = 0x01;
+const STX: u8 = 0x02;
+const EOT: u8 = 0x04;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+const CAN: u8 = 0x18;
+const CRC_MODE: u8 = b'C';
+
+// Timeouts from the XMODEM specification: the sender has 10 seconds to start a packet, and 1
+// second between the bytes of a packet.
+const PACKET_TIMEOUT_MS: u32 = 10_000;
+const BYTE_TIMEOUT_MS: u32 = 1_000;
+// Number of consecutive errors before giving up on a transfer.
+const MAX_ERRORS: usize = 10;
+
+/// Errors returned by [`XmodemReceiver`].
+#[derive(Debug)]
+pub enum RecoveryError<E> {
+    /// Error from the serial port.
+    Serial(E),
+    /// Error writing the image to the DFU partition.
+    Updater(FirmwareUpdaterError),
+    /// The sender didn't respond in time, or too many packets were corrupted.
+    Timeout,
+    /// The sender cancelled the transfer.
+    Cancelled,
+    /// The sender skipped a packet.
+    OutOfSync,
+}
+
+#[cfg(feature = "defmt")]
+impl<E> defmt::Format for RecoveryError<E> {
+    fn format(&self, fmt: defmt::Formatter) {
+        match self {
+            RecoveryError::Serial(_) => defmt::write!(fmt, "RecoveryError::Serial(_)"),
+            RecoveryError::Updater(_) => defmt::write!(fmt, "RecoveryError::Updater(_)"),
+            RecoveryError::Timeout => defmt::write!(fmt, "RecoveryError::Timeout"),
+            RecoveryError::Cancelled => defmt::write!(fmt, "RecoveryError::Cancelled"),
+            RecoveryError::OutOfSync => defmt::write!(fmt, "RecoveryError::OutOfSync"),
+        }
+    }
+}
+
+impl<E> From<FirmwareUpdaterError> for RecoveryError<E> {
+    fn from(error: FirmwareUpdaterError) -> Self {
+        RecoveryError::Updater(error)
+    }
+}
+
+/// Receiver for firmware images sent over a serial port with XMODEM, as a last resort way to
+/// recover a device without a debugger.
+///
+/// The bootloader runs the receiver when its recovery condition holds, such as a pressed button,
+/// writing the image to the DFU partition with a [`BlockingFirmwareUpdater`]. Once the transfer
+/// completes, the image can be verified and marked updated as if the application had written it,
+/// and swapped in by [`BootLoader::prepare_boot`](crate::BootLoader::prepare_boot).
+///
+/// Both XMODEM-CRC with 128 byte packets and XMODEM-1K with 1024 byte packets are supported, as
+/// sent by `sx` from lrzsz or most terminal programs. The sender pads the last packet, so the DFU
+/// partition holds the image followed by up to 1023 padding bytes, and the packet sizes must be
+/// multiples of the DFU write size.
+pub struct XmodemReceiver<S, D> {
+    serial: S,
+    delay: D,
+}
+
+impl<S: Read + ReadReady + Write, D: DelayNs> XmodemReceiver<S, D> {
+    /// Create a receiver using `serial` for the transfer and `delay` for its timeouts.
+    pub fn new(serial: S, delay: D) -> Self {
+        Self { serial, delay }
+    }
+
+    /// Release the serial port and the delay.
+    pub fn into_inner(self) -> (S, D) {
+        (self.serial, self.delay)
+    }
+
+    /// Receive an image into the DFU partition of `updater`, returning the number of bytes written.
+    pub fn receive<DFU: NorFlash, STATE: NorFlash>(
+        &mut self,
+        updater: &mut BlockingFirmwareUpdater<'_, DFU, STATE>,
+    ) -> Result<usize, RecoveryError<S::Error>> {
+        let mut packet = [0; 1024 + 4];
+        let mut expected: u8 = 1;
+        let mut offset = 0;
+        let mut errors = 0;
+        // Ask for CRC mode until the first packet arrives, then acknowledge each packet
+        let mut response = CRC_MODE;
+
+        loop {
+            if errors >= MAX_ERRORS {
+                self.cancel()?;
+                return Err(RecoveryError::Timeout);
+            }
+            self.send(response)?;
+
+            let len = match self.read_byte(PACKET_TIMEOUT_MS)? {
+                Some(SOH) => 128,
+                Some(STX) => 1024,
+                Some(EOT) if offset > 0 => {
+                    self.send(ACK)?;
+                    return Ok(offset);
+                }
+                Some(CAN) => return Err(RecoveryError::Cancelled),
+                other => {
+                    // Skip the rest of a garbled packet, so the NAK isn't mistaken for a response to it
+                    if other.is_some() {
+                        self.purge()?;
+                    }
+                    // Until the first packet, keep asking for CRC mode instead
+                    if offset > 0 {
+                        response = NAK;
+                    }
+                    errors += 1;
+                    continue;
+                }
+            };
+
+            let packet = &mut packet[..len + 4];
+            if !self.read_packet(packet)? {
+                self.purge()?;
+                response = NAK;
+                errors += 1;
+                continue;
+            }
+
+            let (block, data) = (packet[0], &packet[2..len + 2]);
+            if block == expected {
+                if let Err(e) = updater.write_firmware(offset, data) {
+                    self.cancel()?;
+                    return Err(e.into());
+                }
+                offset += len;
+                expected = expected.wrapping_add(1);
+            } else if block != expected.wrapping_sub(1) || offset == 0 {
+                self.cancel()?;
+                return Err(RecoveryError::OutOfSync);
+            }
+            // A retransmission of the previous packet, after our ACK got lost, is acknowledged again
+            response = ACK;
+            errors = 0;
+        }
+    }
+
+    /// Read the rest of a packet after its header byte, returning whether it is valid.
+    fn read_packet(&mut self, packet: &mut [u8]) -> Result<bool, RecoveryError<S::Error>> {
+        for b in packet.iter_mut() {
+            match self.read_byte(BYTE_TIMEOUT_MS)? {
+                Some(byte) => *b = byte,
+                None => return Ok(false),
+            }
+        }
+        let data_end = packet.len() - 2;
+        let crc = u16::from_be_bytes([packet[data_end], packet[data_end + 1]]);
+        Ok(packet[0] == !packet[1] && crc == crc16(&packet[2..data_end]))
+    }
+
+    fn read_byte(&mut self, timeout_ms: u32) -> Result<Option<u8>, RecoveryError<S::Error>> {
+        for _ in 0..timeout_ms * 10 {
+            if self.serial.read_ready().map_err(RecoveryError::Serial)? {
+                let mut byte = [0];
+                self.serial.read_exact(&mut byte).map_err(|e| match e {
+                    embedded_io::ReadExactError::Other(e) => RecoveryError::Serial(e),
+                    embedded_io::ReadExactError::UnexpectedEof => RecoveryError::Timeout,
+                })?;
+                return Ok(Some(byte[0]));
+            }
+            self.delay.delay_us(100);
+        }
+        Ok(None)
+    }
+
+    // Discard incoming bytes until the line is idle for a byte timeout.
+    fn purge(&mut self) -> Result<(), RecoveryError<S::Error>> {
+        while self.read_byte(BYTE_TIMEOUT_MS)?.is_some() {}
+        Ok(())
+    }
+
+    fn send(&mut self, byte: u8) -> Result<(), RecoveryError<S::Error>> {
+        self.serial.write_all(&[byte]).map_err(RecoveryError::Serial)?;
+        self.serial.flush().map_err(RecoveryError::Serial)
+    }
+
+    fn cancel(&mut self) -> Result<(), RecoveryError<S::Error>> {
+        self.send(CAN)?;
+        self.send(CAN)
+    }
+}
+
+// CRC-16/XMODEM, as used to check XMODEM-CRC packets.
+fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &b in data {
+        crc ^= (b as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use std::collections::VecDeque;
+    use std::vec::Vec;
+
+    use embassy_embedded_hal::flash::partition::BlockingPartition;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::blocking_mutex::Mutex;
+    use embedded_storage::nor_flash::ReadNorFlash;
+
+    use super::*;
+    use crate::mem_flash::MemFlash;
+    use crate::FirmwareUpdaterConfig;
+
+    // Serial port replaying the bytes of a sender, each batch becoming readable after one of our
+    // responses.
+    struct ScriptedSerial {
+        batches: VecDeque<Vec<u8>>,
+        rx: VecDeque<u8>,
+        tx: Vec<u8>,
+    }
+
+    impl ScriptedSerial {
+        fn new(batches: impl IntoIterator<Item = Vec<u8>>) -> Self {
+            Self {
+                batches: batches.into_iter().collect(),
+                rx: VecDeque::new(),
+                tx: Vec::new(),
+            }
+        }
+    }
+
+    impl embedded_io::ErrorType for ScriptedSerial {
+        type Error = Infallible;
+    }
+
+    impl Read for ScriptedSerial {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+            let n = buf.len().min(self.rx.len());
+            for b in buf[..n].iter_mut() {
+                *b = self.rx.pop_front().unwrap();
+            }
+            Ok(n)
+        }
+    }
+
+    impl ReadReady for ScriptedSerial {
+        fn read_ready(&mut self) -> Result<bool, Self::Error> {
+            Ok(!self.rx.is_empty())
+        }
+    }
+
+    impl Write for ScriptedSerial {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+            self.tx.extend_from_slice(buf);
+            if let Some(batch) = self.batches.pop_front() {
+                self.rx.extend(batch);
+            }
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<(), Self::Error> {
+            Ok(())
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    fn packet(block: u8, data: &[u8]) -> Vec<u8> {
+        let mut packet = Vec::new();
+        packet.push(if data.len() == 1024 { STX } else { SOH });
+        packet.extend_from_slice(&[block, !block]);
+        packet.extend_from_slice(data);
+        packet.extend_from_slice(&crc16(data).to_be_bytes());
+        packet
+    }
+
+    fn receive(
+        batches: impl IntoIterator<Item = Vec<u8>>,
+    ) -> (Result<usize, RecoveryError<Infallible>>, Vec<u8>, Vec<u8>) {
+        let flash = Mutex::<NoopRawMutex, _>::new(RefCell::new(MemFlash::<65536, 4096, 4>::default()));
+        let state = BlockingPartition::new(&flash, 0, 16384);
+        let dfu = BlockingPartition::new(&flash, 16384, 49152);
+        let mut aligned = [0; 4];
+        let mut updater = BlockingFirmwareUpdater::new(FirmwareUpdaterConfig { dfu, state }, &mut aligned);
+
+        let mut receiver = XmodemReceiver::new(ScriptedSerial::new(batches), NoDelay);
+        let res = receiver.receive(&mut updater);
+
+        let mut dfu = [0; 2048];
+        flash
+            .lock(|f| ReadNorFlash::read(&mut *f.borrow_mut(), 16384, &mut dfu))
+            .unwrap();
+        (res, receiver.into_inner().0.tx, dfu.to_vec())
+    }
+
+    #[test]
+    fn can_receive_image() {
+        let (res, tx, dfu) = receive([packet(1, &[0xAA; 128]), packet(2, &[0xBB; 1024]), [EOT].to_vec()]);
+
+        assert_eq!(1152, res.unwrap());
+        assert_eq!([CRC_MODE, ACK, ACK, ACK].as_slice(), tx);
+        assert_eq!([0xAA; 128].as_slice(), &dfu[..128]);
+        assert_eq!([0xBB; 1024].as_slice(), &dfu[128..1152]);
+    }
+
+    #[test]
+    fn retransmits_corrupted_and_duplicate_packets() {
+        let mut corrupted = packet(2, &[0xBB; 128]);
+        corrupted[10] ^= 0x01;
+        let (res, tx, dfu) = receive([
+            packet(1, &[0xAA; 128]),
+            corrupted,
+            packet(2, &[0xBB; 128]),
+            packet(2, &[0xBB; 128]),
+            [EOT].to_vec(),
+        ]);
+
+        assert_eq!(256, res.unwrap());
+        assert_eq!([CRC_MODE, ACK, NAK, ACK, ACK, ACK].as_slice(), tx);
+        assert_eq!([0xAA; 128].as_slice(), &dfu[..128]);
+        assert_eq!([0xBB; 128].as_slice(), &dfu[128..256]);
+    }
+
+    #[test]
+    fn naks_packets_with_corrupted_header() {
+        let mut corrupted = packet(2, &[0xBB; 128]);
+        corrupted[0] = 0x55;
+        let (res, tx, dfu) = receive([
+            packet(1, &[0xAA; 128]),
+            corrupted,
+            packet(2, &[0xBB; 128]),
+            [EOT].to_vec(),
+        ]);
+
+        assert_eq!(256, res.unwrap());
+        assert_eq!([CRC_MODE, ACK, NAK, ACK, ACK].as_slice(), tx);
+        assert_eq!([0xAA; 128].as_slice(), &dfu[..128]);
+        assert_eq!([0xBB; 128].as_slice(), &dfu[128..256]);
+    }
+
+    #[test]
+    fn naks_after_timeout() {
+        let (res, tx, _) = receive([
+            packet(1, &[0xAA; 128]),
+            Vec::new(),
+            packet(2, &[0xBB; 128]),
+            [EOT].to_vec(),
+        ]);
+
+        assert_eq!(256, res.unwrap());
+        assert_eq!([CRC_MODE, ACK, NAK, ACK, ACK].as_slice(), tx);
+    }
+
+    #[test]
+    fn cancels_on_skipped_packet() {
+        let (res, tx, _) = receive([packet(1, &[0xAA; 128]), packet(3, &[0xBB; 128])]);
+
+        assert!(matches!(res, Err(RecoveryError::OutOfSync)));
+        assert_eq!([CRC_MODE, ACK, CAN, CAN].as_slice(), tx);
+    }
+
+    #[test]
+    fn gives_up_without_sender() {
+        let (res, tx, _) = receive([]);
+
+        assert!(matches!(res, Err(RecoveryError::Timeout)));
+        assert_eq!(MAX_ERRORS + 2, tx.len());
+        assert!(tx[..MAX_ERRORS].iter().all(|&b| b == CRC_MODE));
+    }
+}
@@ -0,0 +1,230 @@
+use embedded_hal::delay::DelayNs;
+use embedded_hal::digital::{InputPin, PinState};
+
+/// Condition forcing the bootloader into recovery instead of booting the active firmware.
+///
+/// The policy is checked by [`BootLoader::prepare_boot_with_recovery`](crate::BootLoader::prepare_boot_with_recovery)
+/// once any pending swap or revert has completed, which then reports [`State::DfuDetach`](crate::State::DfuDetach)
+/// so that the bootloader enters its DFU or serial recovery mode.
+///
+/// Policies can be combined with a tuple, which forces recovery if any of them does. All of them are
+/// checked, so that a [`FailedBoots`] counter keeps counting even when a button is held.
+pub trait RecoveryPolicy {
+    /// Check whether to enter recovery.
+    fn should_recover(&mut self) -> bool;
+}
+
+/// Enter recovery when a button is held for some time at reset.
+pub struct HoldButton<P, D> {
+    pin: P,
+    delay: D,
+    pressed: PinState,
+    hold_ms: u32,
+}
+
+impl<P: InputPin, D: DelayNs> HoldButton<P, D> {
+    /// Create a policy entering recovery when `pin` is at the `pressed` level for `hold_ms`
+    /// milliseconds, sampled every 10 milliseconds.
+    pub fn new(pin: P, delay: D, pressed: PinState, hold_ms: u32) -> Self {
+        Self {
+            pin,
+            delay,
+            pressed,
+            hold_ms,
+        }
+    }
+
+    fn is_pressed(&mut self) -> bool {
+        match self.pressed {
+            PinState::High => self.pin.is_high().unwrap_or(false),
+            PinState::Low => self.pin.is_low().unwrap_or(false),
+        }
+    }
+}
+
+impl<P: InputPin, D: DelayNs> RecoveryPolicy for HoldButton<P, D> {
+    fn should_recover(&mut self) -> bool {
+        let mut held_ms = 0;
+        while self.is_pressed() {
+            if held_ms >= self.hold_ms {
+                return true;
+            }
+            self.delay.delay_ms(10);
+            held_ms += 10;
+        }
+        false
+    }
+}
+
+/// Enter recovery when a magic value was left in a register that survives a reset, such as a
+/// backup register or GPREGRET, by the application before resetting.
+///
+/// The value is cleared when found, so that the next reset boots normally.
+pub struct MagicValue<R, C> {
+    read: R,
+    clear: C,
+    magic: u32,
+}
+
+impl<R: FnMut() -> u32, C: FnMut()> MagicValue<R, C> {
+    /// Create a policy entering recovery when `read` returns `magic`, calling `clear` to reset it.
+    pub fn new(read: R, clear: C, magic: u32) -> Self {
+        Self { read, clear, magic }
+    }
+}
+
+impl<R: FnMut() -> u32, C: FnMut()> RecoveryPolicy for MagicValue<R, C> {
+    fn should_recover(&mut self) -> bool {
+        if (self.read)() == self.magic {
+            (self.clear)();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Enter recovery after a number of consecutive boots that didn't reach a healthy application.
+///
+/// Each check increments a counter kept in a register that survives a reset, and the application
+/// clears it once it is running fine. When the counter exceeds the limit, it is cleared and
+/// recovery is entered.
+pub struct FailedBoots<R, W> {
+    read: R,
+    write: W,
+    max_failed_boots: u32,
+}
+
+impl<R: FnMut() -> u32, W: FnMut(u32)> FailedBoots<R, W> {
+    /// Create a policy entering recovery after `max_failed_boots` boots, counted with `read` and
+    /// `write`, without the application clearing the counter.
+    pub fn new(read: R, write: W, max_failed_boots: u32) -> Self {
+        Self {
+            read,
+            write,
+            max_failed_boots,
+        }
+    }
+}
+
+impl<R: FnMut() -> u32, W: FnMut(u32)> RecoveryPolicy for FailedBoots<R, W> {
+    fn should_recover(&mut self) -> bool {
+        let failed_boots = (self.read)();
+        if failed_boots >= self.max_failed_boots {
+            (self.write)(0);
+            true
+        } else {
+            (self.write)(failed_boots + 1);
+            false
+        }
+    }
+}
+
+impl<A: RecoveryPolicy, B: RecoveryPolicy> RecoveryPolicy for (A, B) {
+    fn should_recover(&mut self) -> bool {
+        self.0.should_recover() | self.1.should_recover()
+    }
+}
+
+impl<A: RecoveryPolicy, B: RecoveryPolicy, C: RecoveryPolicy> RecoveryPolicy for (A, B, C) {
+    fn should_recover(&mut self) -> bool {
+        self.0.should_recover() | self.1.should_recover() | self.2.should_recover()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    use super::*;
+
+    struct Button<'a> {
+        // Number of samples for which the button reads as pressed
+        pressed_samples: &'a Cell<u32>,
+    }
+
+    impl embedded_hal::digital::ErrorType for Button<'_> {
+        type Error = Infallible;
+    }
+
+    impl InputPin for Button<'_> {
+        fn is_high(&mut self) -> Result<bool, Self::Error> {
+            self.is_low().map(|low| !low)
+        }
+
+        fn is_low(&mut self) -> Result<bool, Self::Error> {
+            let n = self.pressed_samples.get();
+            self.pressed_samples.set(n.saturating_sub(1));
+            Ok(n > 0)
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    #[test]
+    fn hold_button_requires_hold_time() {
+        let samples = Cell::new(0);
+        let mut policy = HoldButton::new(
+            Button {
+                pressed_samples: &samples,
+            },
+            NoDelay,
+            PinState::Low,
+            1000,
+        );
+
+        assert!(!policy.should_recover());
+        samples.set(50);
+        assert!(!policy.should_recover());
+        samples.set(101);
+        assert!(policy.should_recover());
+    }
+
+    #[test]
+    fn magic_value_is_cleared() {
+        let register = Cell::new(0xB007_DF00);
+        let mut policy = MagicValue::new(|| register.get(), || register.set(0), 0xB007_DF00);
+
+        assert!(policy.should_recover());
+        assert_eq!(0, register.get());
+        assert!(!policy.should_recover());
+    }
+
+    #[test]
+    fn failed_boots_are_counted() {
+        let register = Cell::new(0);
+        let mut policy = FailedBoots::new(|| register.get(), |v| register.set(v), 3);
+
+        for _ in 0..3 {
+            assert!(!policy.should_recover());
+        }
+        assert!(policy.should_recover());
+        assert_eq!(0, register.get());
+
+        // The application clears the counter once it is healthy
+        assert!(!policy.should_recover());
+        register.set(0);
+        assert!(!policy.should_recover());
+        assert_eq!(1, register.get());
+    }
+
+    #[test]
+    fn combined_policies_are_all_checked() {
+        let magic = Cell::new(0xB007_DF00);
+        let failed_boots = Cell::new(0);
+        let mut policy = (
+            MagicValue::new(|| magic.get(), || magic.set(0), 0xB007_DF00),
+            FailedBoots::new(|| failed_boots.get(), |v| failed_boots.set(v), 3),
+        );
+
+        assert!(policy.should_recover());
+        assert_eq!(1, failed_boots.get());
+        assert!(!policy.should_recover());
+        assert_eq!(2, failed_boots.get());
+    }
+}
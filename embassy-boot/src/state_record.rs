@@ -0,0 +1,106 @@
+use crate::digest_adapters::crc32;
+
+/// Magic at the start of a state record, "EBST" in little-endian.
+pub(crate) const STATE_RECORD_MAGIC: u32 = 0x5453_4245;
+
+/// Size of a state record.
+pub(crate) const STATE_RECORD_LEN: usize = 16;
+
+/// The bootloader state, as stored with the `robust-state` feature.
+///
+/// Each record is written to two copies, in the two erase sectors following the magic and swap
+/// progress area of the state partition. Changing the state rewrites both copies one after the
+/// other with an incremented sequence number, and the newest valid copy wins, so that a write torn
+/// by a power loss or a corrupted copy falls back to the other one.
+///
+/// The record has the following format, with all integers in little-endian:
+///
+/// | Range  | Description                                               |
+/// |--------|-----------------------------------------------------------|
+/// | 0..4   | `STATE_RECORD_MAGIC`                                      |
+/// | 4..8   | Sequence number, incremented on each state change         |
+/// | 8..9   | State magic: BOOT_MAGIC, SWAP_MAGIC, REVERT_MAGIC, ...    |
+/// | 9..12  | Reserved, 0                                               |
+/// | 12..16 | CRC-32 of bytes 0..12                                     |
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StateRecord {
+    pub magic: u8,
+    pub seq: u32,
+}
+
+impl StateRecord {
+    /// The record following this one, holding `magic`.
+    pub fn next(previous: Option<Self>, magic: u8) -> Self {
+        Self {
+            magic,
+            seq: previous.map_or(0, |r| r.seq.wrapping_add(1)),
+        }
+    }
+
+    pub fn encode(&self) -> [u8; STATE_RECORD_LEN] {
+        let mut record = [0; STATE_RECORD_LEN];
+        record[0..4].copy_from_slice(&STATE_RECORD_MAGIC.to_le_bytes());
+        record[4..8].copy_from_slice(&self.seq.to_le_bytes());
+        record[8] = self.magic;
+        let crc = crc32::checksum(&record[..12]);
+        record[12..16].copy_from_slice(&crc);
+        record
+    }
+
+    /// Decode a record, returning `None` if its magic or CRC is wrong.
+    pub fn decode(record: &[u8; STATE_RECORD_LEN]) -> Option<Self> {
+        if record[0..4] != STATE_RECORD_MAGIC.to_le_bytes() || record[12..16] != crc32::checksum(&record[..12]) {
+            return None;
+        }
+        Some(Self {
+            magic: record[8],
+            seq: u32::from_le_bytes([record[4], record[5], record[6], record[7]]),
+        })
+    }
+
+    /// The newest of two copies, ignoring invalid ones.
+    pub fn newest(a: Option<Self>, b: Option<Self>) -> Option<Self> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(if (b.seq.wrapping_sub(a.seq) as i32) > 0 { b } else { a }),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// Offsets of the two copies of the state record in a state partition of `capacity` bytes.
+pub(crate) const fn state_record_offsets(capacity: usize, erase_size: usize) -> [usize; 2] {
+    let start = crate::state_len(capacity, erase_size);
+    [start, start + erase_size]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_encode_and_decode() {
+        let record = StateRecord { magic: 0xF0, seq: 7 };
+        assert_eq!(Some(record), StateRecord::decode(&record.encode()));
+
+        let mut corrupted = record.encode();
+        corrupted[8] = 0xD0;
+        assert_eq!(None, StateRecord::decode(&corrupted));
+        assert_eq!(None, StateRecord::decode(&[0xFF; STATE_RECORD_LEN]));
+    }
+
+    #[test]
+    fn picks_newest_valid_copy() {
+        let old = StateRecord {
+            magic: 0xD0,
+            seq: u32::MAX,
+        };
+        let new = StateRecord::next(Some(old), 0xF0);
+        assert_eq!(0, new.seq);
+
+        assert_eq!(Some(new), StateRecord::newest(Some(old), Some(new)));
+        assert_eq!(Some(new), StateRecord::newest(Some(new), Some(old)));
+        assert_eq!(Some(old), StateRecord::newest(Some(old), None));
+        assert_eq!(Some(new), StateRecord::newest(None, Some(new)));
+        assert_eq!(None, StateRecord::newest(None, None));
+    }
+}
@@ -1,3 +1,7 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
+
 use embassy_embedded_hal::flash::partition::Partition;
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embassy_sync::mutex::Mutex;
@@ -11,9 +15,9 @@ where
     DFU: NorFlash,
     STATE: NorFlash,
 {
-    active: Mutex<NoopRawMutex, ACTIVE>,
-    dfu: Mutex<NoopRawMutex, DFU>,
-    state: Mutex<NoopRawMutex, STATE>,
+    active: Box<Mutex<NoopRawMutex, ACTIVE>>,
+    dfu: Box<Mutex<NoopRawMutex, DFU>>,
+    state: Box<Mutex<NoopRawMutex, STATE>>,
 }
 
 impl<ACTIVE, DFU, STATE> AsyncTestFlash<ACTIVE, DFU, STATE>
@@ -24,9 +28,9 @@ where
 {
     pub fn new(config: BootLoaderConfig<ACTIVE, DFU, STATE>) -> Self {
         Self {
-            active: Mutex::new(config.active),
-            dfu: Mutex::new(config.dfu),
-            state: Mutex::new(config.state),
+            active: Box::new(Mutex::new(config.active)),
+            dfu: Box::new(Mutex::new(config.dfu)),
+            state: Box::new(Mutex::new(config.state)),
         }
     }
 
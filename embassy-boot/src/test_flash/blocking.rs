@@ -1,3 +1,6 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
 use core::cell::RefCell;
 
 use embassy_embedded_hal::flash::partition::BlockingPartition;
@@ -13,9 +16,9 @@ where
     DFU: NorFlash,
     STATE: NorFlash,
 {
-    active: Mutex<NoopRawMutex, RefCell<ACTIVE>>,
-    dfu: Mutex<NoopRawMutex, RefCell<DFU>>,
-    state: Mutex<NoopRawMutex, RefCell<STATE>>,
+    active: Box<Mutex<NoopRawMutex, RefCell<ACTIVE>>>,
+    dfu: Box<Mutex<NoopRawMutex, RefCell<DFU>>>,
+    state: Box<Mutex<NoopRawMutex, RefCell<STATE>>>,
 }
 
 impl<ACTIVE, DFU, STATE> BlockingTestFlash<ACTIVE, DFU, STATE>
@@ -26,9 +29,9 @@ where
 {
     pub fn new(config: BootLoaderConfig<ACTIVE, DFU, STATE>) -> Self {
         Self {
-            active: Mutex::new(RefCell::new(config.active)),
-            dfu: Mutex::new(RefCell::new(config.dfu)),
-            state: Mutex::new(RefCell::new(config.state)),
+            active: Box::new(Mutex::new(RefCell::new(config.active))),
+            dfu: Box::new(Mutex::new(RefCell::new(config.dfu))),
+            state: Box::new(Mutex::new(RefCell::new(config.state))),
         }
     }
 
@@ -0,0 +1,133 @@
+use digest::Digest;
+
+/// Verifies the signature of firmware updates.
+///
+/// Implement this to verify updates with algorithms not built into this crate, or with a hardware
+/// crypto accelerator, and pass it to `FirmwareUpdater::verify_and_mark_updated_with`.
+pub trait FirmwareVerifier {
+    /// Digest computed over the firmware bytes before verifying the signature.
+    type Digest: Digest;
+
+    /// Verify that `signature` is a valid signature of the firmware, given its `digest`.
+    fn verify(&mut self, digest: &[u8], signature: &[u8]) -> Result<(), signature::Error>;
+}
+
+/// Computes the digest of firmware updates asynchronously.
+///
+/// Implement this to compute the digest with a hardware hash peripheral, for example using DMA, and
+/// pass it to `FirmwareUpdater::verify_and_mark_updated_with_digester`. It must compute the same
+/// digest as the [`FirmwareVerifier::Digest`] of the verifier it is used with.
+pub trait Digester {
+    /// Size of the digest, in bytes. At most 64.
+    fn output_size(&self) -> usize;
+
+    /// Start a new digest, discarding any data fed to the previous one.
+    async fn reset(&mut self);
+
+    /// Feed data to the digest.
+    async fn update(&mut self, data: &[u8]);
+
+    /// Finish the digest, writing it to `output`, which is `output_size()` bytes long.
+    async fn finalize(&mut self, output: &mut [u8]);
+}
+
+/// Ed25519 verifier using the `ed25519-dalek` crate.
+///
+/// The signature is expected to have been generated from a SHA-512 digest of the firmware bytes.
+#[cfg(feature = "ed25519-dalek")]
+pub struct Ed25519Dalek {
+    public_key: ed25519_dalek::VerifyingKey,
+}
+
+#[cfg(feature = "ed25519-dalek")]
+impl Ed25519Dalek {
+    /// Create a verifier for a public key.
+    pub fn new(public_key: &[u8]) -> Result<Self, signature::Error> {
+        let public_key = public_key.try_into().map_err(|_| signature::Error::new())?;
+        Ok(Self {
+            public_key: ed25519_dalek::VerifyingKey::from_bytes(public_key)?,
+        })
+    }
+}
+
+#[cfg(feature = "ed25519-dalek")]
+impl FirmwareVerifier for Ed25519Dalek {
+    type Digest = crate::digest_adapters::ed25519_dalek::Sha512;
+
+    fn verify(&mut self, digest: &[u8], signature: &[u8]) -> Result<(), signature::Error> {
+        use ed25519_dalek::Verifier;
+
+        let signature = ed25519_dalek::Signature::from_slice(signature)?;
+        self.public_key.verify(digest, &signature)
+    }
+}
+
+/// Ed25519 verifier using the `salty` crate.
+///
+/// The signature is expected to have been generated from a SHA-512 digest of the firmware bytes.
+#[cfg(feature = "ed25519-salty")]
+pub struct Ed25519Salty {
+    public_key: salty::PublicKey,
+}
+
+#[cfg(feature = "ed25519-salty")]
+impl Ed25519Salty {
+    /// Create a verifier for a public key.
+    pub fn new(public_key: &[u8]) -> Result<Self, signature::Error> {
+        let public_key: &[u8; 32] = public_key.try_into().map_err(|_| signature::Error::new())?;
+        Ok(Self {
+            public_key: salty::PublicKey::try_from(public_key).map_err(|_| signature::Error::new())?,
+        })
+    }
+}
+
+#[cfg(feature = "ed25519-salty")]
+impl FirmwareVerifier for Ed25519Salty {
+    type Digest = crate::digest_adapters::salty::Sha512;
+
+    fn verify(&mut self, digest: &[u8], signature: &[u8]) -> Result<(), signature::Error> {
+        let signature: &[u8; 64] = signature.try_into().map_err(|_| signature::Error::new())?;
+        let signature = salty::Signature::from(signature);
+
+        let r = self.public_key.verify(digest, &signature);
+        trace!(
+            "Verifying with public key {}, signature {} and message {} yields ok: {}",
+            self.public_key.to_bytes(),
+            signature.to_bytes(),
+            digest,
+            r.is_ok()
+        );
+        r.map_err(|_| signature::Error::new())
+    }
+}
+
+/// ECDSA P-256 verifier using the `p256` crate.
+///
+/// The public key is a SEC1 encoded point, and the signature is the 64 bytes `r || s` of an ECDSA
+/// signature with SHA-256 of the firmware bytes.
+#[cfg(feature = "ecdsa-p256")]
+pub struct EcdsaP256 {
+    public_key: p256::ecdsa::VerifyingKey,
+}
+
+#[cfg(feature = "ecdsa-p256")]
+impl EcdsaP256 {
+    /// Create a verifier for a public key.
+    pub fn new(public_key: &[u8]) -> Result<Self, signature::Error> {
+        Ok(Self {
+            public_key: p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key)?,
+        })
+    }
+}
+
+#[cfg(feature = "ecdsa-p256")]
+impl FirmwareVerifier for EcdsaP256 {
+    type Digest = sha2::Sha256;
+
+    fn verify(&mut self, digest: &[u8], signature: &[u8]) -> Result<(), signature::Error> {
+        use p256::ecdsa::signature::hazmat::PrehashVerifier;
+
+        let signature = p256::ecdsa::Signature::from_slice(signature)?;
+        self.public_key.verify_prehash(digest, &signature)
+    }
+}
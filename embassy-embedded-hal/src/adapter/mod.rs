@@ -1,7 +1,11 @@
 //! Adapters between embedded-hal traits.
 
 mod blocking_async;
+#[cfg(feature = "time")]
+mod tracing;
 mod yielding_async;
 
 pub use blocking_async::BlockingAsync;
+#[cfg(feature = "time")]
+pub use tracing::Tracing;
 pub use yielding_async::YieldingAsync;
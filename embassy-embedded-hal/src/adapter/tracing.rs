@@ -0,0 +1,206 @@
+use embassy_time::{Duration, Instant};
+use embedded_hal_1::i2c;
+use embedded_hal_1::spi;
+
+/// Wrapper that logs the transactions on an I2C or SPI bus.
+///
+/// Each transaction is logged at `trace` level, with the I2C address, the bytes written and
+/// read, and how long it took. Failed transactions are logged at `warn` level with the error.
+/// Enable the `defmt` or `log` feature of this crate to see them.
+///
+/// To keep logging from disturbing a busy bus, at most `max_per_second` transactions are logged
+/// each second. The number of transactions that weren't logged is reported when the next second
+/// starts.
+pub struct Tracing<T> {
+    wrapped: T,
+    name: &'static str,
+    max_per_second: u32,
+    window_start: Instant,
+    logged: u32,
+    skipped: u32,
+}
+
+impl<T> Tracing<T> {
+    /// Create a new wrapper, with `name` prefixed to each log line.
+    pub fn new(wrapped: T, name: &'static str, max_per_second: u32) -> Self {
+        Self {
+            wrapped,
+            name,
+            max_per_second,
+            window_start: Instant::now(),
+            logged: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Unwrap the inner instance.
+    pub fn into_inner(self) -> T {
+        self.wrapped
+    }
+
+    fn should_log(&mut self) -> bool {
+        let now = Instant::now();
+        if now - self.window_start >= Duration::from_secs(1) {
+            if self.skipped > 0 {
+                debug!("{}: {} transactions not logged", self.name, self.skipped);
+            }
+            self.window_start = now;
+            self.logged = 0;
+            self.skipped = 0;
+        }
+
+        if self.logged < self.max_per_second {
+            self.logged += 1;
+            true
+        } else {
+            self.skipped += 1;
+            false
+        }
+    }
+
+    fn trace_i2c<E: i2c::Error>(
+        &mut self,
+        address: u8,
+        operations: &[i2c::Operation<'_>],
+        start: Instant,
+        res: &Result<(), E>,
+    ) {
+        if !self.should_log() {
+            return;
+        }
+        for op in operations {
+            match op {
+                i2c::Operation::Read(buf) => trace!("{}: i2c {:02x} read {:?}", self.name, address, buf),
+                i2c::Operation::Write(buf) => trace!("{}: i2c {:02x} write {:?}", self.name, address, buf),
+            }
+        }
+        let elapsed = start.elapsed().as_micros();
+        match res {
+            Ok(()) => trace!("{}: done in {}us", self.name, elapsed),
+            Err(e) => warn!("{}: failed in {}us: {:?}", self.name, elapsed, e.kind()),
+        }
+    }
+
+    fn trace_spi_operations<E: spi::Error>(
+        &mut self,
+        operations: &[spi::Operation<'_, u8>],
+        start: Instant,
+        res: &Result<(), E>,
+    ) {
+        if !self.should_log() {
+            return;
+        }
+        for op in operations {
+            match op {
+                spi::Operation::Read(buf) => trace!("{}: spi read {:?}", self.name, buf),
+                spi::Operation::Write(buf) => trace!("{}: spi write {:?}", self.name, buf),
+                spi::Operation::Transfer(read, write) => {
+                    trace!("{}: spi transfer {:?} -> {:?}", self.name, write, read)
+                }
+                spi::Operation::TransferInPlace(buf) => trace!("{}: spi transfer in place -> {:?}", self.name, buf),
+                spi::Operation::DelayNs(ns) => trace!("{}: spi delay {}ns", self.name, ns),
+            }
+        }
+        self.trace_spi_result(start, res);
+    }
+
+    fn trace_spi<E: spi::Error>(&mut self, op: &str, write: &[u8], read: &[u8], start: Instant, res: &Result<(), E>) {
+        if !self.should_log() {
+            return;
+        }
+        trace!("{}: spi {} {:?} -> {:?}", self.name, op, write, read);
+        self.trace_spi_result(start, res);
+    }
+
+    fn trace_spi_result<E: spi::Error>(&self, start: Instant, res: &Result<(), E>) {
+        let elapsed = start.elapsed().as_micros();
+        match res {
+            Ok(()) => trace!("{}: done in {}us", self.name, elapsed),
+            Err(e) => warn!("{}: failed in {}us: {:?}", self.name, elapsed, e.kind()),
+        }
+    }
+}
+
+//
+// I2C implementations
+//
+impl<T: i2c::ErrorType> i2c::ErrorType for Tracing<T> {
+    type Error = T::Error;
+}
+
+impl<T: i2c::I2c> i2c::I2c for Tracing<T> {
+    fn transaction(&mut self, address: u8, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let res = self.wrapped.transaction(address, operations);
+        self.trace_i2c(address, operations, start, &res);
+        res
+    }
+}
+
+impl<T: embedded_hal_async::i2c::I2c> embedded_hal_async::i2c::I2c for Tracing<T> {
+    async fn transaction(&mut self, address: u8, operations: &mut [i2c::Operation<'_>]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let res = self.wrapped.transaction(address, operations).await;
+        self.trace_i2c(address, operations, start, &res);
+        res
+    }
+}
+
+//
+// SPI implementations
+//
+impl<T: spi::ErrorType> spi::ErrorType for Tracing<T> {
+    type Error = T::Error;
+}
+
+impl<T: spi::SpiDevice> spi::SpiDevice for Tracing<T> {
+    fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let res = self.wrapped.transaction(operations);
+        self.trace_spi_operations(operations, start, &res);
+        res
+    }
+}
+
+impl<T: embedded_hal_async::spi::SpiDevice> embedded_hal_async::spi::SpiDevice for Tracing<T> {
+    async fn transaction(&mut self, operations: &mut [spi::Operation<'_, u8>]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let res = self.wrapped.transaction(operations).await;
+        self.trace_spi_operations(operations, start, &res);
+        res
+    }
+}
+
+impl<T: embedded_hal_async::spi::SpiBus> embedded_hal_async::spi::SpiBus for Tracing<T> {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let res = self.wrapped.read(words).await;
+        self.trace_spi("read", &[], words, start, &res);
+        res
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let res = self.wrapped.write(words).await;
+        self.trace_spi("write", words, &[], start, &res);
+        res
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let res = self.wrapped.transfer(read, write).await;
+        self.trace_spi("transfer", write, read, start, &res);
+        res
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let start = Instant::now();
+        let res = self.wrapped.transfer_in_place(words).await;
+        self.trace_spi("transfer in place", &[], words, start, &res);
+        res
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.wrapped.flush().await
+    }
+}
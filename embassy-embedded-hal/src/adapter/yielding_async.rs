@@ -4,14 +4,42 @@ use embassy_futures::yield_now;
 ///
 /// This can be used in combination with BlockingAsync<T> to enforce yields
 /// between long running blocking operations.
+///
+/// Long SPI transfers and flash reads and writes can additionally be split into chunks,
+/// yielding between each chunk, so a single large operation doesn't block the executor
+/// for its whole duration. See [`YieldingAsync::new_chunked`].
 pub struct YieldingAsync<T> {
     wrapped: T,
+    chunk_size: usize,
 }
 
 impl<T> YieldingAsync<T> {
     /// Create a new instance of a wrapper that yields after each operation.
     pub fn new(wrapped: T) -> Self {
-        Self { wrapped }
+        Self {
+            wrapped,
+            chunk_size: usize::MAX,
+        }
+    }
+
+    /// Create a new instance of a wrapper that splits operations into chunks of at most
+    /// `chunk_size` words, and yields after each chunk.
+    ///
+    /// For flash, the chunk size is rounded down to a multiple of the read or write size,
+    /// but never below it. I2C operations are not split, since that would change what
+    /// is sent on the bus.
+    pub fn new_chunked(wrapped: T, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0);
+        Self { wrapped, chunk_size }
+    }
+
+    /// Unwrap the inner instance.
+    pub fn into_inner(self) -> T {
+        self.wrapped
+    }
+
+    fn aligned_chunk_size(&self, align: usize) -> usize {
+        core::cmp::max(self.chunk_size - self.chunk_size % align, align)
     }
 }
 
@@ -80,26 +108,41 @@ where
     }
 
     async fn write(&mut self, data: &[Word]) -> Result<(), Self::Error> {
-        self.wrapped.write(data).await?;
-        yield_now().await;
+        for chunk in data.chunks(self.chunk_size) {
+            self.wrapped.write(chunk).await?;
+            yield_now().await;
+        }
         Ok(())
     }
 
     async fn read(&mut self, data: &mut [Word]) -> Result<(), Self::Error> {
-        self.wrapped.read(data).await?;
-        yield_now().await;
+        for chunk in data.chunks_mut(self.chunk_size) {
+            self.wrapped.read(chunk).await?;
+            yield_now().await;
+        }
         Ok(())
     }
 
     async fn transfer(&mut self, read: &mut [Word], write: &[Word]) -> Result<(), Self::Error> {
-        self.wrapped.transfer(read, write).await?;
-        yield_now().await;
+        let len = core::cmp::max(read.len(), write.len());
+        let mut pos = 0;
+        while pos < len {
+            let end = pos.saturating_add(self.chunk_size);
+            let read_len = read.len();
+            let read = &mut read[pos.min(read_len)..end.min(read_len)];
+            let write = &write[pos.min(write.len())..end.min(write.len())];
+            self.wrapped.transfer(read, write).await?;
+            yield_now().await;
+            pos = end;
+        }
         Ok(())
     }
 
     async fn transfer_in_place(&mut self, words: &mut [Word]) -> Result<(), Self::Error> {
-        self.wrapped.transfer_in_place(words).await?;
-        yield_now().await;
+        for chunk in words.chunks_mut(self.chunk_size) {
+            self.wrapped.transfer_in_place(chunk).await?;
+            yield_now().await;
+        }
         Ok(())
     }
 }
@@ -117,7 +160,15 @@ impl<T: embedded_storage_async::nor_flash::ReadNorFlash> embedded_storage_async:
     const READ_SIZE: usize = T::READ_SIZE;
 
     async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
-        self.wrapped.read(offset, bytes).await?;
+        if self.chunk_size >= bytes.len() {
+            return self.wrapped.read(offset, bytes).await;
+        }
+
+        let chunk_size = self.aligned_chunk_size(T::READ_SIZE);
+        for (i, chunk) in bytes.chunks_mut(chunk_size).enumerate() {
+            self.wrapped.read(offset + (i * chunk_size) as u32, chunk).await?;
+            yield_now().await;
+        }
         Ok(())
     }
 
@@ -131,8 +182,11 @@ impl<T: embedded_storage_async::nor_flash::NorFlash> embedded_storage_async::nor
     const ERASE_SIZE: usize = T::ERASE_SIZE;
 
     async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
-        self.wrapped.write(offset, bytes).await?;
-        yield_now().await;
+        let chunk_size = self.aligned_chunk_size(T::WRITE_SIZE);
+        for (i, chunk) in bytes.chunks(chunk_size).enumerate() {
+            self.wrapped.write(offset + (i * chunk_size) as u32, chunk).await?;
+            yield_now().await;
+        }
         Ok(())
     }
 
@@ -166,4 +220,18 @@ mod tests {
         assert_eq!((0, 128), flash.erases[0]);
         assert_eq!((128, 256), flash.erases[1]);
     }
+
+    #[futures_test::test]
+    async fn can_write_chunked() {
+        let flash = MemFlash::<1024, 128, 4>::new(0xFF);
+        let mut yielding = YieldingAsync::new_chunked(flash, 30);
+
+        let data = [0xAA; 64];
+        yielding.write(0, &data).await.unwrap();
+
+        let flash = yielding.into_inner();
+        assert_eq!(&[(0, 28), (28, 28), (56, 8)], flash.writes.as_slice());
+        assert!(flash.mem[..64].iter().all(|&x| x == 0xAA));
+        assert!(flash.mem[64..].iter().all(|&x| x == 0xFF));
+    }
 }
@@ -0,0 +1,84 @@
+//! Block devices, like SD cards and eMMC.
+//!
+//! [`BlockDevice`] is the integration point between storage drivers and filesystem crates,
+//! so neither side has to know about the other.
+
+use core::ops::{Deref, DerefMut};
+
+/// Size of a block, in bytes.
+pub const BLOCK_SIZE: usize = 512;
+
+/// A block of data.
+///
+/// This is a 512-byte array, aligned to 4 bytes to satisfy DMA requirements.
+#[repr(align(4))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Block(pub [u8; BLOCK_SIZE]);
+
+impl Block {
+    /// Create a new block filled with zeros.
+    pub const fn new() -> Self {
+        Self([0; BLOCK_SIZE])
+    }
+}
+
+impl Default for Block {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Deref for Block {
+    type Target = [u8; BLOCK_SIZE];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Block {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// A device storing data in blocks of [`BLOCK_SIZE`] bytes.
+///
+/// Blocks are addressed by index, regardless of how the underlying device is addressed.
+pub trait BlockDevice {
+    /// The error type returned by the device.
+    type Error: core::fmt::Debug;
+
+    /// Read consecutive blocks, starting at `block_idx`.
+    async fn read(&mut self, block_idx: u32, blocks: &mut [Block]) -> Result<(), Self::Error>;
+
+    /// Write consecutive blocks, starting at `block_idx`.
+    async fn write(&mut self, block_idx: u32, blocks: &[Block]) -> Result<(), Self::Error>;
+
+    /// Wait until all written blocks are stored on the device.
+    async fn flush(&mut self) -> Result<(), Self::Error>;
+
+    /// Get the number of blocks of the device.
+    async fn block_count(&mut self) -> Result<u32, Self::Error>;
+}
+
+impl<T: BlockDevice + ?Sized> BlockDevice for &mut T {
+    type Error = T::Error;
+
+    async fn read(&mut self, block_idx: u32, blocks: &mut [Block]) -> Result<(), Self::Error> {
+        T::read(self, block_idx, blocks).await
+    }
+
+    async fn write(&mut self, block_idx: u32, blocks: &[Block]) -> Result<(), Self::Error> {
+        T::write(self, block_idx, blocks).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        T::flush(self).await
+    }
+
+    async fn block_count(&mut self) -> Result<u32, Self::Error> {
+        T::block_count(self).await
+    }
+}
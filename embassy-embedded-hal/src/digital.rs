@@ -0,0 +1,274 @@
+//! Digital input helpers, like debounced buttons and rotary encoders.
+
+use embassy_futures::select::{select, Either};
+#[cfg(feature = "time")]
+use embassy_time::{Duration, Instant, Timer};
+use embedded_hal_1::digital::InputPin;
+use embedded_hal_async::digital::Wait;
+
+/// Level at which an input is considered active.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Polarity {
+    /// Active when the pin is high.
+    ActiveHigh,
+    /// Active when the pin is low, like a button with a pull-up.
+    ActiveLow,
+}
+
+/// Debounced input pin.
+///
+/// A level change is only reported once the pin has stayed at the new level for the debounce time,
+/// so the bounces of a mechanical contact are filtered out.
+#[cfg(feature = "time")]
+pub struct Debouncer<P> {
+    pin: P,
+    debounce: Duration,
+    polarity: Polarity,
+    level: Option<bool>,
+}
+
+#[cfg(feature = "time")]
+impl<P: InputPin + Wait> Debouncer<P> {
+    /// Create a new debouncer.
+    pub fn new(pin: P, debounce: Duration, polarity: Polarity) -> Self {
+        Self {
+            pin,
+            debounce,
+            polarity,
+            level: None,
+        }
+    }
+
+    /// Release the pin.
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+
+    /// Get the debounced level of the pin, `true` when high.
+    pub fn is_high(&mut self) -> Result<bool, P::Error> {
+        match self.level {
+            Some(level) => Ok(level),
+            None => {
+                let level = self.pin.is_high()?;
+                self.level = Some(level);
+                Ok(level)
+            }
+        }
+    }
+
+    /// Get whether the input is active, according to its polarity.
+    pub fn is_active(&mut self) -> Result<bool, P::Error> {
+        Ok(self.is_high()? == (self.polarity == Polarity::ActiveHigh))
+    }
+
+    /// Wait for the debounced level to change, and return the new level.
+    pub async fn wait_for_change(&mut self) -> Result<bool, P::Error> {
+        let level = self.is_high()?;
+        loop {
+            self.pin.wait_for_any_edge().await?;
+            Timer::after(self.debounce).await;
+            let new_level = self.pin.is_high()?;
+            if new_level != level {
+                self.level = Some(new_level);
+                return Ok(new_level);
+            }
+        }
+    }
+
+    /// Wait for the input to become active.
+    pub async fn wait_for_press(&mut self) -> Result<(), P::Error> {
+        while !self.is_active()? {
+            self.wait_for_change().await?;
+        }
+        Ok(())
+    }
+
+    /// Wait for the input to become inactive.
+    pub async fn wait_for_release(&mut self) -> Result<(), P::Error> {
+        while self.is_active()? {
+            self.wait_for_change().await?;
+        }
+        Ok(())
+    }
+
+    /// Wait for the input to be pressed and released, and return how long it was pressed.
+    pub async fn wait_for_click(&mut self) -> Result<Duration, P::Error> {
+        self.wait_for_release().await?;
+        self.wait_for_press().await?;
+        let start = Instant::now();
+        self.wait_for_release().await?;
+        Ok(start.elapsed())
+    }
+
+    /// Wait for the input to be held active for at least `duration`.
+    ///
+    /// This returns while the input is still held, so an action can be taken right away.
+    /// Shorter presses are ignored.
+    pub async fn wait_for_long_press(&mut self, duration: Duration) -> Result<(), P::Error> {
+        loop {
+            self.wait_for_release().await?;
+            self.wait_for_press().await?;
+            match select(self.wait_for_release(), Timer::after(duration)).await {
+                Either::First(res) => res?,
+                Either::Second(()) => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Direction of a rotary encoder step.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    /// Clockwise, A leads B.
+    Clockwise,
+    /// Counter-clockwise, B leads A.
+    CounterClockwise,
+}
+
+/// Quadrature rotary encoder from two input pins.
+///
+/// Invalid transitions, caused by contact bounce or missed edges, are ignored, so no debouncing
+/// is needed. A step is reported every full quadrature cycle, which is one detent for most encoders.
+pub struct RotaryEncoder<A, B> {
+    a: A,
+    b: B,
+    state: Option<u8>,
+    count: i8,
+}
+
+impl<A, B, E> RotaryEncoder<A, B>
+where
+    A: InputPin<Error = E> + Wait<Error = E>,
+    B: InputPin<Error = E> + Wait<Error = E>,
+{
+    /// Create a new rotary encoder.
+    pub fn new(a: A, b: B) -> Self {
+        Self {
+            a,
+            b,
+            state: None,
+            count: 0,
+        }
+    }
+
+    /// Release the pins.
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+
+    /// Wait for the encoder to be turned by one step.
+    pub async fn wait_for_step(&mut self) -> Result<Direction, E> {
+        let mut state = match self.state {
+            Some(state) => state,
+            None => self.read()?,
+        };
+
+        loop {
+            match select(self.a.wait_for_any_edge(), self.b.wait_for_any_edge()).await {
+                Either::First(res) | Either::Second(res) => res?,
+            }
+            let new_state = self.read()?;
+            self.state = Some(new_state);
+
+            // Gray code sequence clockwise is 00 -> 01 -> 11 -> 10 -> 00, with A as the low bit.
+            self.count += match (state, new_state) {
+                (0b00, 0b01) | (0b01, 0b11) | (0b11, 0b10) | (0b10, 0b00) => 1,
+                (0b00, 0b10) | (0b10, 0b11) | (0b11, 0b01) | (0b01, 0b00) => -1,
+                _ => 0,
+            };
+            state = new_state;
+
+            if self.count >= 4 {
+                self.count = 0;
+                return Ok(Direction::Clockwise);
+            }
+            if self.count <= -4 {
+                self.count = 0;
+                return Ok(Direction::CounterClockwise);
+            }
+        }
+    }
+
+    fn read(&mut self) -> Result<u8, E> {
+        Ok(self.a.is_high()? as u8 | (self.b.is_high()? as u8) << 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    use embedded_hal_1::digital::ErrorType;
+
+    use super::*;
+
+    /// Pins replaying a sequence of (A, B) levels, advancing on each edge wait.
+    struct Script<'a> {
+        states: &'a [(bool, bool)],
+        pos: Cell<usize>,
+    }
+
+    struct Pin<'a>(&'a Script<'a>, bool);
+
+    impl ErrorType for Pin<'_> {
+        type Error = Infallible;
+    }
+
+    impl InputPin for Pin<'_> {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            let (a, b) = self.0.states[self.0.pos.get()];
+            Ok(if self.1 { b } else { a })
+        }
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.is_high()?)
+        }
+    }
+
+    impl Wait for Pin<'_> {
+        async fn wait_for_high(&mut self) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+        async fn wait_for_low(&mut self) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+        async fn wait_for_rising_edge(&mut self) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+        async fn wait_for_falling_edge(&mut self) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+        async fn wait_for_any_edge(&mut self) -> Result<(), Infallible> {
+            self.0.pos.set(self.0.pos.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[futures_test::test]
+    async fn can_decode_steps() {
+        let script = Script {
+            states: &[
+                // Clockwise, with a bounce on A.
+                (true, true),
+                (false, true),
+                (true, true),
+                (false, true),
+                (false, false),
+                (true, false),
+                (true, true),
+                // Counter-clockwise.
+                (true, false),
+                (false, false),
+                (false, true),
+                (true, true),
+            ],
+            pos: Cell::new(0),
+        };
+        let mut encoder = RotaryEncoder::new(Pin(&script, false), Pin(&script, true));
+
+        assert_eq!(Ok(Direction::Clockwise), encoder.wait_for_step().await);
+        assert_eq!(Ok(Direction::CounterClockwise), encoder.wait_for_step().await);
+    }
+}
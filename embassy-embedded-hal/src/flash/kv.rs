@@ -0,0 +1,486 @@
+//! Power-fail safe key-value storage on top of a NOR flash.
+//!
+//! This is intended for small amounts of data that are updated now and then, like device
+//! configuration, counters and calibration data.
+//!
+//! The flash is split in two banks, each made of one or more erase blocks. Records are appended
+//! to the active bank, and every record carries a CRC, so a record torn by a power loss is
+//! detected and ignored. When the active bank is full, the latest value of each key is copied
+//! to the other bank, and only then is the other bank marked as active. The banks alternate,
+//! so erases are spread over the whole flash.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use embassy_embedded_hal::flash::kv::KeyValueStore;
+//! use embassy_embedded_hal::flash::partition::Partition;
+//!
+//! let partition = Partition::new(&flash, 0x8000, 0x2000);
+//! let mut buf = [0; 64];
+//! let mut store = KeyValueStore::new(partition, &mut buf);
+//!
+//! store.write(1, &42u32.to_le_bytes()).await?;
+//!
+//! let mut value = [0; 4];
+//! if let Some(len) = store.read(1, &mut value).await? {
+//!     // ...
+//! }
+//! ```
+
+use embedded_storage_async::nor_flash::NorFlash;
+
+use super::crc32_update;
+
+const MAGIC: u32 = 0x4b56_5331;
+const BANK_HEADER_SIZE: usize = 12;
+const RECORD_HEADER_SIZE: usize = 8;
+const ERASED_KEY: u16 = 0xffff;
+const DELETED: u16 = 0x8000;
+
+/// Maximum length of a value.
+pub const MAX_VALUE_LEN: usize = 0x7fff;
+
+/// Key-value store error
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<T> {
+    /// The buffer is too small to hold the record.
+    BufferTooSmall,
+    /// There is no space left for the record, even after compaction.
+    Full,
+    /// Underlying flash error
+    Flash(T),
+}
+
+enum Next {
+    Record {
+        key: u16,
+        deleted: bool,
+        len: usize,
+        next: u32,
+    },
+    End,
+    Corrupt,
+}
+
+/// A key-value store on a NOR flash.
+///
+/// Keys are 16-bit, and `0xFFFF` is reserved. The scratch buffer passed in `new` must hold
+/// the largest record, which is the value length plus 8 bytes, rounded up to the read and
+/// write size of the flash.
+pub struct KeyValueStore<'a, F: NorFlash> {
+    flash: F,
+    buf: &'a mut [u8],
+    bank_size: u32,
+    mounted: bool,
+    active: u32,
+    seq: u32,
+    write_pos: u32,
+}
+
+impl<'a, F: NorFlash> KeyValueStore<'a, F> {
+    const ALIGN: usize = if F::READ_SIZE > F::WRITE_SIZE {
+        F::READ_SIZE
+    } else {
+        F::WRITE_SIZE
+    };
+
+    /// Create a new key-value store, using the whole flash.
+    ///
+    /// The flash is mounted on first use, and formatted if it doesn't contain a store yet.
+    pub fn new(flash: F, buf: &'a mut [u8]) -> Self {
+        let bank_size = (flash.capacity() / 2 / F::ERASE_SIZE * F::ERASE_SIZE) as u32;
+        assert!(bank_size > 0, "flash must have at least two erase blocks");
+        assert!(buf.len() >= align_up(BANK_HEADER_SIZE, Self::ALIGN), "buffer too small");
+        Self {
+            flash,
+            buf,
+            bank_size,
+            mounted: false,
+            active: 0,
+            seq: 0,
+            write_pos: 0,
+        }
+    }
+
+    /// Release the underlying flash.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// Read the value of `key` into `value`.
+    ///
+    /// Returns the length of the value, or `None` if the key is not present.
+    pub async fn read(&mut self, key: u16, value: &mut [u8]) -> Result<Option<usize>, Error<F::Error>> {
+        self.mount().await?;
+        let Some(pos) = self.find(key).await? else {
+            return Ok(None);
+        };
+        let Next::Record { len, .. } = self.next_record(self.active, pos).await? else {
+            return Ok(None);
+        };
+        if value.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+        value[..len].copy_from_slice(&self.buf[RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + len]);
+        Ok(Some(len))
+    }
+
+    /// Write the value of `key`, replacing the previous value.
+    ///
+    /// Panics if `key` is `0xFFFF` or `value` is longer than [`MAX_VALUE_LEN`].
+    pub async fn write(&mut self, key: u16, value: &[u8]) -> Result<(), Error<F::Error>> {
+        self.mount().await?;
+        self.append(key, value, false).await
+    }
+
+    /// Remove `key` from the store.
+    pub async fn remove(&mut self, key: u16) -> Result<(), Error<F::Error>> {
+        self.mount().await?;
+        if self.find(key).await?.is_none() {
+            return Ok(());
+        }
+        self.append(key, &[], true).await
+    }
+
+    /// Erase all keys.
+    pub async fn format(&mut self) -> Result<(), Error<F::Error>> {
+        self.erase_bank(1).await?;
+        self.erase_bank(0).await?;
+        self.write_bank_header(0, 0).await?;
+        self.active = 0;
+        self.seq = 0;
+        self.write_pos = self.bank_header_size();
+        self.mounted = true;
+        Ok(())
+    }
+
+    async fn mount(&mut self) -> Result<(), Error<F::Error>> {
+        if self.mounted {
+            return Ok(());
+        }
+
+        let (active, seq) = match (self.read_bank_header(0).await?, self.read_bank_header(1).await?) {
+            (Some(a), Some(b)) if b.wrapping_sub(a) as i32 > 0 => (1, b),
+            (Some(a), _) => (0, a),
+            (None, Some(b)) => (1, b),
+            (None, None) => return self.format().await,
+        };
+        self.active = active;
+        self.seq = seq;
+
+        let mut pos = self.bank_header_size();
+        let clean = loop {
+            match self.next_record(active, pos).await? {
+                Next::Record { next, .. } => pos = next,
+                Next::End => break true,
+                Next::Corrupt => break false,
+            }
+        };
+
+        // Space after a torn write can't be programmed again until it's erased, so force a
+        // compaction on the next write.
+        self.write_pos = if clean && self.is_erased(active, pos).await? {
+            pos
+        } else {
+            self.bank_size
+        };
+        self.mounted = true;
+        Ok(())
+    }
+
+    async fn find(&mut self, key: u16) -> Result<Option<u32>, Error<F::Error>> {
+        let mut found = None;
+        let mut pos = self.bank_header_size();
+        while let Next::Record {
+            key: k, deleted, next, ..
+        } = self.next_record(self.active, pos).await?
+        {
+            if k == key {
+                found = (!deleted).then_some(pos);
+            }
+            pos = next;
+        }
+        Ok(found)
+    }
+
+    async fn is_latest(&mut self, bank: u32, key: u16, mut pos: u32) -> Result<bool, Error<F::Error>> {
+        while let Next::Record { key: k, next, .. } = self.next_record(bank, pos).await? {
+            if k == key {
+                return Ok(false);
+            }
+            pos = next;
+        }
+        Ok(true)
+    }
+
+    async fn append(&mut self, key: u16, value: &[u8], deleted: bool) -> Result<(), Error<F::Error>> {
+        assert!(key != ERASED_KEY, "key 0xFFFF is reserved");
+        assert!(value.len() <= MAX_VALUE_LEN, "value too long");
+
+        let len = value.len();
+        let total = align_up(RECORD_HEADER_SIZE + len, Self::ALIGN);
+        if total > self.buf.len() {
+            return Err(Error::BufferTooSmall);
+        }
+        if self.write_pos as usize + total > self.bank_size as usize {
+            self.compact().await?;
+            if self.write_pos as usize + total > self.bank_size as usize {
+                return Err(Error::Full);
+            }
+        }
+
+        let len_flags = len as u16 | if deleted { DELETED } else { 0 };
+        self.buf[0..2].copy_from_slice(&key.to_le_bytes());
+        self.buf[2..4].copy_from_slice(&len_flags.to_le_bytes());
+        self.buf[RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + len].copy_from_slice(value);
+        self.buf[RECORD_HEADER_SIZE + len..total].fill(0xff);
+        let crc = crc32(&self.buf[0..4], value);
+        self.buf[4..8].copy_from_slice(&crc.to_le_bytes());
+
+        // If the write fails, the space may be partially programmed and can't be used again.
+        let pos = self.write_pos;
+        self.write_pos = self.bank_size;
+        self.flash
+            .write(self.active * self.bank_size + pos, &self.buf[..total])
+            .await
+            .map_err(Error::Flash)?;
+        self.write_pos = pos + total as u32;
+        Ok(())
+    }
+
+    async fn compact(&mut self) -> Result<(), Error<F::Error>> {
+        let from = self.active;
+        let to = 1 - from;
+        self.erase_bank(to).await?;
+
+        let mut out = self.bank_header_size();
+        let mut pos = self.bank_header_size();
+        while let Next::Record { key, deleted, next, .. } = self.next_record(from, pos).await? {
+            if !deleted && self.is_latest(from, key, next).await? {
+                // Read the record again, since the lookup reused the buffer.
+                if let Next::Record { len, .. } = self.next_record(from, pos).await? {
+                    let total = align_up(RECORD_HEADER_SIZE + len, Self::ALIGN);
+                    self.flash
+                        .write(to * self.bank_size + out, &self.buf[..total])
+                        .await
+                        .map_err(Error::Flash)?;
+                    out += total as u32;
+                }
+            }
+            pos = next;
+        }
+
+        // The new bank only becomes valid once all records have been copied.
+        let seq = self.seq.wrapping_add(1);
+        self.write_bank_header(to, seq).await?;
+        self.active = to;
+        self.seq = seq;
+        self.write_pos = out;
+        Ok(())
+    }
+
+    async fn next_record(&mut self, bank: u32, pos: u32) -> Result<Next, Error<F::Error>> {
+        let header_size = align_up(RECORD_HEADER_SIZE, Self::ALIGN);
+        if pos as usize + header_size > self.bank_size as usize {
+            return Ok(Next::End);
+        }
+
+        let offset = bank * self.bank_size + pos;
+        self.flash
+            .read(offset, &mut self.buf[..header_size])
+            .await
+            .map_err(Error::Flash)?;
+        if self.buf[..RECORD_HEADER_SIZE].iter().all(|&b| b == 0xff) {
+            return Ok(Next::End);
+        }
+
+        let key = u16::from_le_bytes([self.buf[0], self.buf[1]]);
+        let len_flags = u16::from_le_bytes([self.buf[2], self.buf[3]]);
+        let crc = u32::from_le_bytes([self.buf[4], self.buf[5], self.buf[6], self.buf[7]]);
+        let len = (len_flags & !DELETED) as usize;
+        let total = align_up(RECORD_HEADER_SIZE + len, Self::ALIGN);
+        if key == ERASED_KEY || total > self.buf.len() || pos as usize + total > self.bank_size as usize {
+            return Ok(Next::Corrupt);
+        }
+
+        if total > header_size {
+            self.flash
+                .read(offset + header_size as u32, &mut self.buf[header_size..total])
+                .await
+                .map_err(Error::Flash)?;
+        }
+        if crc32(&self.buf[0..4], &self.buf[RECORD_HEADER_SIZE..RECORD_HEADER_SIZE + len]) != crc {
+            return Ok(Next::Corrupt);
+        }
+
+        Ok(Next::Record {
+            key,
+            deleted: len_flags & DELETED != 0,
+            len,
+            next: pos + total as u32,
+        })
+    }
+
+    async fn is_erased(&mut self, bank: u32, mut pos: u32) -> Result<bool, Error<F::Error>> {
+        let chunk_size = self.buf.len() / Self::ALIGN * Self::ALIGN;
+        while pos < self.bank_size {
+            let len = core::cmp::min(chunk_size, (self.bank_size - pos) as usize);
+            self.flash
+                .read(bank * self.bank_size + pos, &mut self.buf[..len])
+                .await
+                .map_err(Error::Flash)?;
+            if self.buf[..len].iter().any(|&b| b != 0xff) {
+                return Ok(false);
+            }
+            pos += len as u32;
+        }
+        Ok(true)
+    }
+
+    async fn read_bank_header(&mut self, bank: u32) -> Result<Option<u32>, Error<F::Error>> {
+        let size = self.bank_header_size() as usize;
+        self.flash
+            .read(bank * self.bank_size, &mut self.buf[..size])
+            .await
+            .map_err(Error::Flash)?;
+        let magic = u32::from_le_bytes([self.buf[0], self.buf[1], self.buf[2], self.buf[3]]);
+        let seq = u32::from_le_bytes([self.buf[4], self.buf[5], self.buf[6], self.buf[7]]);
+        let crc = u32::from_le_bytes([self.buf[8], self.buf[9], self.buf[10], self.buf[11]]);
+        if magic == MAGIC && crc32(&self.buf[..8], &[]) == crc {
+            Ok(Some(seq))
+        } else {
+            Ok(None)
+        }
+    }
+
+    async fn write_bank_header(&mut self, bank: u32, seq: u32) -> Result<(), Error<F::Error>> {
+        let size = self.bank_header_size() as usize;
+        self.buf[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        self.buf[4..8].copy_from_slice(&seq.to_le_bytes());
+        let crc = crc32(&self.buf[..8], &[]);
+        self.buf[8..12].copy_from_slice(&crc.to_le_bytes());
+        self.buf[BANK_HEADER_SIZE..size].fill(0xff);
+        self.flash
+            .write(bank * self.bank_size, &self.buf[..size])
+            .await
+            .map_err(Error::Flash)
+    }
+
+    async fn erase_bank(&mut self, bank: u32) -> Result<(), Error<F::Error>> {
+        self.flash
+            .erase(bank * self.bank_size, (bank + 1) * self.bank_size)
+            .await
+            .map_err(Error::Flash)
+    }
+
+    fn bank_header_size(&self) -> u32 {
+        align_up(BANK_HEADER_SIZE, Self::ALIGN) as u32
+    }
+}
+
+const fn align_up(len: usize, align: usize) -> usize {
+    (len + align - 1) / align * align
+}
+
+fn crc32(header: &[u8], data: &[u8]) -> u32 {
+    !crc32_update(crc32_update(0xffff_ffff, header), data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::mem_flash::MemFlash;
+
+    #[futures_test::test]
+    async fn can_write_and_read() {
+        let mut buf = [0; 64];
+        let mut store = KeyValueStore::new(MemFlash::<1024, 128, 4>::default(), &mut buf);
+
+        store.write(1, b"hello").await.unwrap();
+
+        let mut value = [0; 16];
+        assert_eq!(Some(5), store.read(1, &mut value).await.unwrap());
+        assert_eq!(b"hello", &value[..5]);
+        assert_eq!(None, store.read(2, &mut value).await.unwrap());
+    }
+
+    #[futures_test::test]
+    async fn can_overwrite_and_remove() {
+        let mut buf = [0; 64];
+        let mut store = KeyValueStore::new(MemFlash::<1024, 128, 4>::default(), &mut buf);
+
+        store.write(1, b"a").await.unwrap();
+        store.write(1, b"bb").await.unwrap();
+
+        let mut value = [0; 16];
+        assert_eq!(Some(2), store.read(1, &mut value).await.unwrap());
+        assert_eq!(b"bb", &value[..2]);
+
+        store.remove(1).await.unwrap();
+        assert_eq!(None, store.read(1, &mut value).await.unwrap());
+    }
+
+    #[futures_test::test]
+    async fn compacts_when_full() {
+        let mut buf = [0; 64];
+        let mut store = KeyValueStore::new(MemFlash::<1024, 128, 4>::default(), &mut buf);
+
+        for i in 0..100u8 {
+            store.write((i % 3) as u16, &[i; 16]).await.unwrap();
+        }
+
+        let mut value = [0; 16];
+        for (key, last) in [(0, 99), (1, 97), (2, 98)] {
+            assert_eq!(Some(16), store.read(key, &mut value).await.unwrap());
+            assert_eq!([last; 16], value);
+        }
+
+        let flash = store.into_inner();
+        assert!(flash.erases.len() > 2);
+    }
+
+    #[futures_test::test]
+    async fn survives_remount() {
+        let mut buf = [0; 64];
+        let mut store = KeyValueStore::new(MemFlash::<1024, 128, 4>::default(), &mut buf);
+        for i in 0..40u8 {
+            store.write((i % 2) as u16, &[i; 16]).await.unwrap();
+        }
+        let flash = store.into_inner();
+
+        let mut buf = [0; 64];
+        let mut store = KeyValueStore::new(flash, &mut buf);
+        let mut value = [0; 16];
+        assert_eq!(Some(16), store.read(0, &mut value).await.unwrap());
+        assert_eq!([38; 16], value);
+        assert_eq!(Some(16), store.read(1, &mut value).await.unwrap());
+        assert_eq!([39; 16], value);
+    }
+
+    #[futures_test::test]
+    async fn ignores_torn_write() {
+        let mut buf = [0; 64];
+        let mut store = KeyValueStore::new(MemFlash::<1024, 128, 4>::default(), &mut buf);
+        store.write(1, b"v1").await.unwrap();
+        store.write(1, b"v2").await.unwrap();
+        let mut flash = store.into_inner();
+
+        // Bank header is 12 bytes and the first record 12 bytes, so the second record's value is at 32.
+        flash.mem[32] = 0;
+
+        let mut buf = [0; 64];
+        let mut store = KeyValueStore::new(flash, &mut buf);
+        let mut value = [0; 16];
+        assert_eq!(Some(2), store.read(1, &mut value).await.unwrap());
+        assert_eq!(b"v1", &value[..2]);
+
+        store.write(1, b"v3").await.unwrap();
+        let flash = store.into_inner();
+
+        let mut buf = [0; 64];
+        let mut store = KeyValueStore::new(flash, &mut buf);
+        assert_eq!(Some(2), store.read(1, &mut value).await.unwrap());
+        assert_eq!(b"v3", &value[..2]);
+    }
+}
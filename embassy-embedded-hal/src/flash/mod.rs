@@ -1,8 +1,27 @@
 //! Utilities related to flash.
 
 mod concat_flash;
+pub mod kv;
 #[cfg(test)]
 pub(crate) mod mem_flash;
 pub mod partition;
+pub mod provisioning;
 
 pub use concat_flash::ConcatFlash;
+
+/// Update a CRC-32 (IEEE 802.3) with `data`.
+///
+/// The CRC must start at `0xffff_ffff`, and be inverted once all the data is added.
+pub(crate) fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
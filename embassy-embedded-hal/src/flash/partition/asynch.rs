@@ -3,7 +3,7 @@ use embassy_sync::mutex::Mutex;
 use embedded_storage::nor_flash::ErrorType;
 use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
 
-use super::Error;
+use super::{check_erase, check_read_write, Error};
 
 /// A logical partition of an underlying shared flash
 ///
@@ -50,9 +50,7 @@ impl<M: RawMutex, T: NorFlash> ReadNorFlash for Partition<'_, M, T> {
     const READ_SIZE: usize = T::READ_SIZE;
 
     async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
-        if offset + bytes.len() as u32 > self.size {
-            return Err(Error::OutOfBounds);
-        }
+        check_read_write(self.size, offset, bytes.len())?;
 
         let mut flash = self.flash.lock().await;
         flash.read(self.offset + offset, bytes).await.map_err(Error::Flash)
@@ -68,18 +66,14 @@ impl<M: RawMutex, T: NorFlash> NorFlash for Partition<'_, M, T> {
     const ERASE_SIZE: usize = T::ERASE_SIZE;
 
     async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
-        if offset + bytes.len() as u32 > self.size {
-            return Err(Error::OutOfBounds);
-        }
+        check_read_write(self.size, offset, bytes.len())?;
 
         let mut flash = self.flash.lock().await;
         flash.write(self.offset + offset, bytes).await.map_err(Error::Flash)
     }
 
     async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
-        if to > self.size {
-            return Err(Error::OutOfBounds);
-        }
+        check_erase(self.size, from, to)?;
 
         let mut flash = self.flash.lock().await;
         flash
@@ -136,4 +130,26 @@ mod tests {
         let flash = flash.try_lock().unwrap();
         assert!(flash.mem[128..256].iter().position(|&x| x != 0xFF).is_none());
     }
+
+    #[futures_test::test]
+    async fn rejects_out_of_bounds() {
+        let flash = MemFlash::<1024, 128, 4>::new(0x00);
+
+        let flash = Mutex::<NoopRawMutex, _>::new(flash);
+        let mut partition = Partition::new(&flash, 128, 256);
+
+        let mut read_buf = [0; 8];
+        assert_eq!(Err(Error::OutOfBounds), partition.read(252, &mut read_buf).await);
+        assert_eq!(
+            Err(Error::OutOfBounds),
+            partition.read(u32::MAX - 3, &mut read_buf).await
+        );
+        assert_eq!(Err(Error::OutOfBounds), partition.write(u32::MAX - 3, &read_buf).await);
+        assert_eq!(Err(Error::OutOfBounds), partition.erase(128, 384).await);
+        assert_eq!(Err(Error::OutOfBounds), partition.erase(128, 0).await);
+
+        let flash = flash.try_lock().unwrap();
+        assert!(flash.writes.is_empty());
+        assert!(flash.erases.is_empty());
+    }
 }
@@ -4,7 +4,7 @@ use embassy_sync::blocking_mutex::raw::RawMutex;
 use embassy_sync::blocking_mutex::Mutex;
 use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
 
-use super::Error;
+use super::{check_erase, check_read_write, Error};
 
 /// A logical partition of an underlying shared flash
 ///
@@ -51,9 +51,7 @@ impl<M: RawMutex, T: NorFlash> ReadNorFlash for BlockingPartition<'_, M, T> {
     const READ_SIZE: usize = T::READ_SIZE;
 
     fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
-        if offset + bytes.len() as u32 > self.size {
-            return Err(Error::OutOfBounds);
-        }
+        check_read_write(self.size, offset, bytes.len())?;
 
         self.flash.lock(|flash| {
             flash
@@ -73,9 +71,7 @@ impl<M: RawMutex, T: NorFlash> NorFlash for BlockingPartition<'_, M, T> {
     const ERASE_SIZE: usize = T::ERASE_SIZE;
 
     fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
-        if offset + bytes.len() as u32 > self.size {
-            return Err(Error::OutOfBounds);
-        }
+        check_read_write(self.size, offset, bytes.len())?;
 
         self.flash.lock(|flash| {
             flash
@@ -86,9 +82,7 @@ impl<M: RawMutex, T: NorFlash> NorFlash for BlockingPartition<'_, M, T> {
     }
 
     fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
-        if to > self.size {
-            return Err(Error::OutOfBounds);
-        }
+        check_erase(self.size, from, to)?;
 
         self.flash.lock(|flash| {
             flash
@@ -146,4 +140,23 @@ mod tests {
         let flash = flash.into_inner().take();
         assert!(flash.mem[128..256].iter().position(|&x| x != 0xFF).is_none());
     }
+
+    #[test]
+    fn rejects_out_of_bounds() {
+        let flash = MemFlash::<1024, 128, 4>::new(0x00);
+
+        let flash = Mutex::<NoopRawMutex, _>::new(RefCell::new(flash));
+        let mut partition = BlockingPartition::new(&flash, 128, 256);
+
+        let mut read_buf = [0; 8];
+        assert_eq!(Err(Error::OutOfBounds), partition.read(252, &mut read_buf));
+        assert_eq!(Err(Error::OutOfBounds), partition.read(u32::MAX - 3, &mut read_buf));
+        assert_eq!(Err(Error::OutOfBounds), partition.write(u32::MAX - 3, &read_buf));
+        assert_eq!(Err(Error::OutOfBounds), partition.erase(128, 384));
+        assert_eq!(Err(Error::OutOfBounds), partition.erase(128, 0));
+
+        let flash = flash.into_inner().take();
+        assert!(flash.writes.is_empty());
+        assert!(flash.erases.is_empty());
+    }
 }
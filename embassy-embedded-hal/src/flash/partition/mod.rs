@@ -26,3 +26,19 @@ impl<T: NorFlashError> NorFlashError for Error<T> {
         }
     }
 }
+
+/// Check that `len` bytes at `offset` are within a partition of `size` bytes.
+fn check_read_write<T>(size: u32, offset: u32, len: usize) -> Result<(), Error<T>> {
+    match u32::try_from(len).ok().and_then(|len| offset.checked_add(len)) {
+        Some(end) if end <= size => Ok(()),
+        _ => Err(Error::OutOfBounds),
+    }
+}
+
+/// Check that the range `from..to` is within a partition of `size` bytes.
+fn check_erase<T>(size: u32, from: u32, to: u32) -> Result<(), Error<T>> {
+    if from > to || to > size {
+        return Err(Error::OutOfBounds);
+    }
+    Ok(())
+}
@@ -0,0 +1,376 @@
+//! One-time provisioning of per-device identity in a dedicated flash region.
+//!
+//! The identity, like the serial number, the device key and certificate, or the public key
+//! used to verify firmware updates, is written once in the factory, and read by the
+//! application. Each item is stored under a tag, see the [`tag`] module for the standard ones.
+//!
+//! The region holds a header with a CRC of all the items, written last, so a provisioning
+//! interrupted by a power loss leaves the region unprovisioned, and it can be tried again.
+//! Once provisioned, the region can't be provisioned again, unless it's erased. Use a region
+//! protected against writes by the application, like OTP or a write-protected flash sector,
+//! when the hardware provides one.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use embassy_embedded_hal::flash::provisioning::{tag, Provisioning};
+//!
+//! let mut buf = [0; 64];
+//! let mut identity = Provisioning::new(partition, &mut buf);
+//!
+//! // In the factory
+//! identity
+//!     .provision(&[(tag::SERIAL_NUMBER, b"SN0001"), (tag::DEVICE_KEY, &key)])
+//!     .await?;
+//!
+//! // In the application
+//! let mut serial = [0; 16];
+//! let serial = identity.read_str(tag::SERIAL_NUMBER, &mut serial).await?;
+//! let key: Option<[u8; 32]> = identity.read_array(tag::DEVICE_KEY).await?;
+//! ```
+
+use embedded_storage_async::nor_flash::NorFlash;
+
+use super::crc32_update;
+
+const MAGIC: u32 = 0x5052_4f56;
+const HEADER_SIZE: usize = 12;
+const ITEM_HEADER_SIZE: usize = 4;
+
+/// Standard tags.
+///
+/// Tags from `0x8000` to `0xfffe` are free for the application. `0xffff` is reserved.
+pub mod tag {
+    /// Serial number, as UTF-8.
+    pub const SERIAL_NUMBER: u16 = 0x0001;
+    /// Hardware revision, as UTF-8.
+    pub const HARDWARE_REVISION: u16 = 0x0002;
+    /// Private key of the device, like an ed25519 or P-256 key, for TLS client authentication.
+    pub const DEVICE_KEY: u16 = 0x0010;
+    /// Certificate of the device, DER encoded.
+    pub const DEVICE_CERTIFICATE: u16 = 0x0011;
+    /// Certificate of the authority the device trusts, DER encoded.
+    pub const CA_CERTIFICATE: u16 = 0x0012;
+    /// Public key verifying the signature of firmware updates.
+    pub const FIRMWARE_PUBLIC_KEY: u16 = 0x0020;
+    /// First tag free for the application.
+    pub const APPLICATION: u16 = 0x8000;
+}
+
+/// Provisioning error
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<T> {
+    /// The region has not been provisioned.
+    NotProvisioned,
+    /// The region has already been provisioned.
+    AlreadyProvisioned,
+    /// The provisioned data doesn't match its CRC.
+    Corrupt,
+    /// The items don't fit in the region, or an item is longer than 65535 bytes.
+    TooLarge,
+    /// The buffer is too small to hold the item.
+    BufferTooSmall,
+    /// The item is not valid UTF-8.
+    InvalidUtf8,
+    /// Underlying flash error
+    Flash(T),
+}
+
+/// Per-device identity, stored in a flash region.
+///
+/// The scratch buffer passed in `new` must be a multiple of the read and write size of the
+/// flash, and hold at least 12 bytes. A larger buffer makes provisioning and lookups faster.
+pub struct Provisioning<'a, F: NorFlash> {
+    flash: F,
+    buf: &'a mut [u8],
+    len: Option<u32>,
+}
+
+impl<'a, F: NorFlash> Provisioning<'a, F> {
+    const ALIGN: usize = if F::READ_SIZE > F::WRITE_SIZE {
+        F::READ_SIZE
+    } else {
+        F::WRITE_SIZE
+    };
+    const DATA_START: u32 = HEADER_SIZE.next_multiple_of(Self::ALIGN) as u32;
+
+    /// Create a new provisioning region, using the whole flash.
+    pub fn new(flash: F, buf: &'a mut [u8]) -> Self {
+        assert!(
+            buf.len() >= HEADER_SIZE && buf.len() % Self::ALIGN == 0,
+            "invalid buffer size"
+        );
+        Self { flash, buf, len: None }
+    }
+
+    /// Release the underlying flash.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// Get whether the region has been provisioned.
+    pub async fn is_provisioned(&mut self) -> Result<bool, Error<F::Error>> {
+        match self.mount().await {
+            Ok(()) => Ok(true),
+            Err(Error::NotProvisioned) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Provision the region with `items`, as `(tag, value)` pairs.
+    ///
+    /// Fails with [`Error::AlreadyProvisioned`] if the region already holds an identity, even an
+    /// identical one. Panics if a tag is `0xFFFF`.
+    pub async fn provision(&mut self, items: &[(u16, &[u8])]) -> Result<(), Error<F::Error>> {
+        if self.is_provisioned().await? {
+            return Err(Error::AlreadyProvisioned);
+        }
+
+        let mut len = 0usize;
+        for (tag, value) in items {
+            assert!(*tag != 0xffff, "tag 0xFFFF is reserved");
+            if value.len() > u16::MAX as usize {
+                return Err(Error::TooLarge);
+            }
+            len += ITEM_HEADER_SIZE + value.len();
+        }
+        let capacity = self.flash.capacity() / F::ERASE_SIZE * F::ERASE_SIZE;
+        if Self::DATA_START as usize + len > capacity {
+            return Err(Error::TooLarge);
+        }
+
+        // The header is erased, but items from an interrupted provisioning may remain.
+        self.flash.erase(0, capacity as u32).await.map_err(Error::Flash)?;
+
+        let mut crc = 0xffff_ffff;
+        let mut offset = Self::DATA_START;
+        let mut fill = 0;
+        for (tag, value) in items {
+            let [t0, t1] = tag.to_le_bytes();
+            let [l0, l1] = (value.len() as u16).to_le_bytes();
+            for part in [&[t0, t1, l0, l1][..], value] {
+                crc = crc32_update(crc, part);
+                let mut part: &[u8] = part;
+                while !part.is_empty() {
+                    let n = core::cmp::min(part.len(), self.buf.len() - fill);
+                    self.buf[fill..fill + n].copy_from_slice(&part[..n]);
+                    fill += n;
+                    part = &part[n..];
+                    if fill == self.buf.len() {
+                        self.flash.write(offset, self.buf).await.map_err(Error::Flash)?;
+                        offset += fill as u32;
+                        fill = 0;
+                    }
+                }
+            }
+        }
+        if fill > 0 {
+            let padded = fill.next_multiple_of(F::WRITE_SIZE);
+            self.buf[fill..padded].fill(0xff);
+            self.flash
+                .write(offset, &self.buf[..padded])
+                .await
+                .map_err(Error::Flash)?;
+        }
+
+        let size = Self::DATA_START as usize;
+        self.buf[..size].fill(0xff);
+        self.buf[0..4].copy_from_slice(&MAGIC.to_le_bytes());
+        self.buf[4..8].copy_from_slice(&(len as u32).to_le_bytes());
+        self.buf[8..12].copy_from_slice(&(!crc).to_le_bytes());
+        self.flash.write(0, &self.buf[..size]).await.map_err(Error::Flash)?;
+
+        self.len = Some(len as u32);
+        Ok(())
+    }
+
+    /// Read the value of `tag` into `value`.
+    ///
+    /// Returns the length of the value, or `None` if the tag is not present.
+    pub async fn read(&mut self, tag: u16, value: &mut [u8]) -> Result<Option<usize>, Error<F::Error>> {
+        let Some((offset, len)) = self.find(tag).await? else {
+            return Ok(None);
+        };
+        if value.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+        self.read_at(offset, &mut value[..len]).await?;
+        Ok(Some(len))
+    }
+
+    /// Read the value of `tag` as a fixed size array, like a key.
+    ///
+    /// Returns `None` if the tag is not present, or if its value doesn't have a length of `N`.
+    pub async fn read_array<const N: usize>(&mut self, tag: u16) -> Result<Option<[u8; N]>, Error<F::Error>> {
+        let Some((offset, len)) = self.find(tag).await? else {
+            return Ok(None);
+        };
+        if len != N {
+            return Ok(None);
+        }
+        let mut value = [0; N];
+        self.read_at(offset, &mut value).await?;
+        Ok(Some(value))
+    }
+
+    /// Read the value of `tag` as a string, like the serial number.
+    pub async fn read_str<'b>(&mut self, tag: u16, value: &'b mut [u8]) -> Result<Option<&'b str>, Error<F::Error>> {
+        let Some(len) = self.read(tag, value).await? else {
+            return Ok(None);
+        };
+        core::str::from_utf8(&value[..len])
+            .map(Some)
+            .map_err(|_| Error::InvalidUtf8)
+    }
+
+    async fn mount(&mut self) -> Result<(), Error<F::Error>> {
+        if self.len.is_some() {
+            return Ok(());
+        }
+
+        let mut header = [0; HEADER_SIZE];
+        self.read_at(0, &mut header).await?;
+        let magic = u32::from_le_bytes(header[0..4].try_into().unwrap());
+        let len = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        let crc = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        if magic != MAGIC {
+            return Err(Error::NotProvisioned);
+        }
+        if Self::DATA_START as usize + len as usize > self.flash.capacity() {
+            return Err(Error::Corrupt);
+        }
+
+        let mut actual = 0xffff_ffff;
+        let mut offset = Self::DATA_START;
+        let end = Self::DATA_START + len;
+        while offset < end {
+            let n = core::cmp::min(self.buf.len(), (end - offset) as usize);
+            let read = n.next_multiple_of(F::READ_SIZE);
+            self.flash
+                .read(offset, &mut self.buf[..read])
+                .await
+                .map_err(Error::Flash)?;
+            actual = crc32_update(actual, &self.buf[..n]);
+            offset += n as u32;
+        }
+        if !actual != crc {
+            return Err(Error::Corrupt);
+        }
+
+        self.len = Some(len);
+        Ok(())
+    }
+
+    /// Find `tag`, returning the offset and length of its value.
+    async fn find(&mut self, tag: u16) -> Result<Option<(u32, usize)>, Error<F::Error>> {
+        self.mount().await?;
+        let end = Self::DATA_START + self.len.unwrap();
+        let mut offset = Self::DATA_START;
+        while offset < end {
+            let mut header = [0; ITEM_HEADER_SIZE];
+            self.read_at(offset, &mut header).await?;
+            let t = u16::from_le_bytes([header[0], header[1]]);
+            let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+            offset += ITEM_HEADER_SIZE as u32;
+            if t == tag {
+                return Ok(Some((offset, len)));
+            }
+            offset += len as u32;
+        }
+        Ok(None)
+    }
+
+    /// Read at any offset, going through the buffer to meet the read size of the flash.
+    async fn read_at(&mut self, mut offset: u32, mut out: &mut [u8]) -> Result<(), Error<F::Error>> {
+        while !out.is_empty() {
+            let start = offset - offset % F::READ_SIZE as u32;
+            let skip = (offset - start) as usize;
+            let n = core::cmp::min(out.len(), self.buf.len() - skip);
+            let read = (skip + n).next_multiple_of(F::READ_SIZE);
+            self.flash
+                .read(start, &mut self.buf[..read])
+                .await
+                .map_err(Error::Flash)?;
+            out[..n].copy_from_slice(&self.buf[skip..skip + n]);
+            out = &mut out[n..];
+            offset += n as u32;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::mem_flash::MemFlash;
+
+    type Flash = MemFlash<4096, 1024, 4>;
+
+    #[futures_test::test]
+    async fn can_provision_and_read() {
+        let mut flash = Flash::default();
+        let mut buf = [0; 16];
+        let mut identity = Provisioning::new(&mut flash, &mut buf);
+        assert_eq!(Ok(false), identity.is_provisioned().await);
+        assert_eq!(
+            Err(Error::NotProvisioned),
+            identity.read(tag::SERIAL_NUMBER, &mut []).await
+        );
+
+        let key = [0x42; 32];
+        identity
+            .provision(&[
+                (tag::SERIAL_NUMBER, b"SN0001"),
+                (tag::DEVICE_KEY, &key),
+                (tag::APPLICATION, b"x"),
+            ])
+            .await
+            .unwrap();
+
+        // Read back from a fresh instance.
+        let mut identity = Provisioning::new(&mut flash, &mut buf);
+        assert_eq!(Ok(true), identity.is_provisioned().await);
+        let mut serial = [0; 16];
+        assert_eq!(
+            Ok(Some("SN0001")),
+            identity.read_str(tag::SERIAL_NUMBER, &mut serial).await
+        );
+        assert_eq!(Ok(Some(key)), identity.read_array(tag::DEVICE_KEY).await);
+        assert_eq!(Ok(None), identity.read_array::<16>(tag::DEVICE_KEY).await);
+        let mut value = [0; 1];
+        assert_eq!(Ok(Some(1)), identity.read(tag::APPLICATION, &mut value).await);
+        assert_eq!(b"x", &value);
+        assert_eq!(Ok(None), identity.read(tag::DEVICE_CERTIFICATE, &mut value).await);
+
+        assert_eq!(
+            Err(Error::AlreadyProvisioned),
+            identity.provision(&[(tag::SERIAL_NUMBER, b"SN0002")]).await
+        );
+    }
+
+    #[futures_test::test]
+    async fn detects_corruption() {
+        let mut flash = Flash::default();
+        let mut buf = [0; 16];
+        let mut identity = Provisioning::new(&mut flash, &mut buf);
+        identity.provision(&[(tag::SERIAL_NUMBER, b"SN0001")]).await.unwrap();
+
+        flash.mem[20] ^= 1;
+        let mut identity = Provisioning::new(&mut flash, &mut buf);
+        assert_eq!(Err(Error::Corrupt), identity.is_provisioned().await);
+    }
+
+    #[futures_test::test]
+    async fn rejects_too_large() {
+        let mut flash = Flash::default();
+        let mut buf = [0; 16];
+        let mut identity = Provisioning::new(&mut flash, &mut buf);
+        let value = [0; 4096];
+        assert_eq!(
+            Err(Error::TooLarge),
+            identity.provision(&[(tag::DEVICE_CERTIFICATE, &value)]).await
+        );
+        assert_eq!(Ok(false), identity.is_provisioned().await);
+    }
+}
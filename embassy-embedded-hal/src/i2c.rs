@@ -0,0 +1,174 @@
+//! I2C utilities.
+
+use embedded_hal_1::digital::{InputPin, OutputPin};
+use embedded_hal_async::delay::DelayNs;
+
+/// Error returned by [`recover_bus`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BusRecoveryError<E> {
+    /// Setting or reading a pin failed.
+    Pin(E),
+    /// SCL is held low by a device on the bus, and can't be clocked.
+    SclStuck,
+    /// SDA is still held low by a device on the bus after 9 clock pulses.
+    SdaStuck,
+}
+
+/// Recover an I2C bus that's stuck because a device holds SDA low.
+///
+/// This happens when a transfer is interrupted, for example by a reset of the controller,
+/// while a device is sending a 0 bit. The device keeps SDA low until it gets enough clock
+/// pulses to finish the byte, which blocks all other transfers on the bus.
+///
+/// This clocks SCL up to 9 times until SDA is released, then generates a STOP condition so
+/// all devices return to idle. Both pins must be configured as open-drain outputs with their
+/// input enabled, so their level can be read back. `half_period_us` sets the clock speed, 5us
+/// gives 100kHz which all devices support.
+///
+/// The I2C peripheral must not drive the pins while recovering. Usually this means dropping
+/// the I2C driver, recovering with the pins in GPIO mode, and creating the driver again.
+pub async fn recover_bus<SCL, SDA, E>(
+    scl: &mut SCL,
+    sda: &mut SDA,
+    delay: &mut impl DelayNs,
+    half_period_us: u32,
+) -> Result<(), BusRecoveryError<E>>
+where
+    SCL: OutputPin<Error = E> + InputPin<Error = E>,
+    SDA: OutputPin<Error = E> + InputPin<Error = E>,
+{
+    sda.set_high().map_err(BusRecoveryError::Pin)?;
+    scl.set_high().map_err(BusRecoveryError::Pin)?;
+    delay.delay_us(half_period_us).await;
+
+    if scl.is_low().map_err(BusRecoveryError::Pin)? {
+        return Err(BusRecoveryError::SclStuck);
+    }
+
+    for _ in 0..9 {
+        if sda.is_high().map_err(BusRecoveryError::Pin)? {
+            break;
+        }
+        scl.set_low().map_err(BusRecoveryError::Pin)?;
+        delay.delay_us(half_period_us).await;
+        scl.set_high().map_err(BusRecoveryError::Pin)?;
+        delay.delay_us(half_period_us).await;
+    }
+
+    // STOP condition: SDA rising while SCL is high.
+    scl.set_low().map_err(BusRecoveryError::Pin)?;
+    delay.delay_us(half_period_us).await;
+    sda.set_low().map_err(BusRecoveryError::Pin)?;
+    delay.delay_us(half_period_us).await;
+    scl.set_high().map_err(BusRecoveryError::Pin)?;
+    delay.delay_us(half_period_us).await;
+    sda.set_high().map_err(BusRecoveryError::Pin)?;
+    delay.delay_us(half_period_us).await;
+
+    if sda.is_low().map_err(BusRecoveryError::Pin)? {
+        return Err(BusRecoveryError::SdaStuck);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    use embedded_hal_1::digital::ErrorType;
+
+    use super::*;
+
+    /// A device holding SDA low for a number of SCL falling edges.
+    struct Bus {
+        scl: Cell<bool>,
+        sda: Cell<bool>,
+        hold_clocks: Cell<u32>,
+    }
+
+    impl Bus {
+        fn sda_level(&self) -> bool {
+            self.sda.get() && self.hold_clocks.get() == 0
+        }
+    }
+
+    struct Scl<'a>(&'a Bus);
+    struct Sda<'a>(&'a Bus);
+
+    impl ErrorType for Scl<'_> {
+        type Error = Infallible;
+    }
+    impl ErrorType for Sda<'_> {
+        type Error = Infallible;
+    }
+
+    impl OutputPin for Scl<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            if self.0.scl.get() && self.0.hold_clocks.get() > 0 {
+                self.0.hold_clocks.set(self.0.hold_clocks.get() - 1);
+            }
+            self.0.scl.set(false);
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.scl.set(true);
+            Ok(())
+        }
+    }
+    impl InputPin for Scl<'_> {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(self.0.scl.get())
+        }
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.0.scl.get())
+        }
+    }
+
+    impl OutputPin for Sda<'_> {
+        fn set_low(&mut self) -> Result<(), Infallible> {
+            self.0.sda.set(false);
+            Ok(())
+        }
+        fn set_high(&mut self) -> Result<(), Infallible> {
+            self.0.sda.set(true);
+            Ok(())
+        }
+    }
+    impl InputPin for Sda<'_> {
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            Ok(self.0.sda_level())
+        }
+        fn is_low(&mut self) -> Result<bool, Infallible> {
+            Ok(!self.0.sda_level())
+        }
+    }
+
+    struct NoDelay;
+
+    impl DelayNs for NoDelay {
+        async fn delay_ns(&mut self, _ns: u32) {}
+    }
+
+    async fn recover(hold_clocks: u32) -> Result<(), BusRecoveryError<Infallible>> {
+        let bus = Bus {
+            scl: Cell::new(true),
+            sda: Cell::new(true),
+            hold_clocks: Cell::new(hold_clocks),
+        };
+        recover_bus(&mut Scl(&bus), &mut Sda(&bus), &mut NoDelay, 5).await
+    }
+
+    #[futures_test::test]
+    async fn can_recover() {
+        assert_eq!(Ok(()), recover(0).await);
+        assert_eq!(Ok(()), recover(3).await);
+        assert_eq!(Ok(()), recover(9).await);
+    }
+
+    #[futures_test::test]
+    async fn reports_stuck_sda() {
+        assert_eq!(Err(BusRecoveryError::SdaStuck), recover(20).await);
+    }
+}
@@ -3,9 +3,17 @@
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
 
+// This mod MUST go first, so that the others see its macros.
+pub(crate) mod fmt;
+
 pub mod adapter;
+pub mod block;
+pub mod digital;
 pub mod flash;
+pub mod i2c;
+pub mod onewire;
 pub mod shared_bus;
+pub mod smart_led;
 
 /// Set the configuration of a peripheral driver.
 ///
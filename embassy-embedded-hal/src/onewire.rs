@@ -0,0 +1,471 @@
+//! 1-Wire bus master.
+//!
+//! [`OneWire`] implements the protocol, like ROM commands, search and CRC checks, on top of a
+//! [`OneWireBus`] that generates the time slots. Two buses are provided: [`GpioOneWireBus`] bit-bangs
+//! an open-drain pin, and [`UartOneWireBus`] uses a UART with TX and RX tied together, which keeps
+//! the timing exact regardless of interrupts.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use embassy_embedded_hal::onewire::{GpioOneWireBus, OneWire, SearchState};
+//!
+//! let pin = Flex::new(p.PIN_2); // configured as open-drain output, with input enabled
+//! let mut onewire = OneWire::new(GpioOneWireBus::new(pin));
+//!
+//! let mut search = SearchState::new();
+//! while let Some(rom) = onewire.search(&mut search).await? {
+//!     info!("found device {:x}", rom);
+//! }
+//! ```
+
+#[cfg(feature = "time")]
+use embedded_hal_1::digital::{InputPin, OutputPin};
+use embedded_io_async::{Read, Write};
+
+use crate::SetConfig;
+
+/// Search ROM command.
+pub const SEARCH_ROM: u8 = 0xf0;
+/// Search ROM command, only finding devices with an active alarm.
+pub const ALARM_SEARCH: u8 = 0xec;
+/// Read ROM command, only valid with a single device on the bus.
+pub const READ_ROM: u8 = 0x33;
+/// Match ROM command, selecting a single device.
+pub const MATCH_ROM: u8 = 0x55;
+/// Skip ROM command, selecting all devices.
+pub const SKIP_ROM: u8 = 0xcc;
+
+/// 1-Wire error
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// No device answered the reset pulse.
+    NoPresence,
+    /// The CRC of the received data doesn't match.
+    Crc,
+    /// Error of the underlying bus.
+    Bus(E),
+}
+
+/// Time slot generator of a 1-Wire bus.
+pub trait OneWireBus {
+    /// Error type
+    type Error;
+
+    /// Send a reset pulse, and return whether a device answered with a presence pulse.
+    async fn reset(&mut self) -> Result<bool, Self::Error>;
+
+    /// Write a bit.
+    async fn write_bit(&mut self, bit: bool) -> Result<(), Self::Error>;
+
+    /// Read a bit.
+    async fn read_bit(&mut self) -> Result<bool, Self::Error>;
+}
+
+/// State of a ROM search, see [`OneWire::search`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SearchState {
+    rom: u64,
+    last_discrepancy: u8,
+    done: bool,
+}
+
+impl SearchState {
+    /// Create a new search state, starting with the first device.
+    pub const fn new() -> Self {
+        Self {
+            rom: 0,
+            last_discrepancy: 0,
+            done: false,
+        }
+    }
+}
+
+/// 1-Wire bus master.
+pub struct OneWire<B> {
+    bus: B,
+}
+
+impl<B: OneWireBus> OneWire<B> {
+    /// Create a new 1-Wire bus master.
+    pub fn new(bus: B) -> Self {
+        Self { bus }
+    }
+
+    /// Release the bus.
+    pub fn into_inner(self) -> B {
+        self.bus
+    }
+
+    /// Send a reset pulse.
+    ///
+    /// Returns [`Error::NoPresence`] if no device is on the bus.
+    pub async fn reset(&mut self) -> Result<(), Error<B::Error>> {
+        match self.bus.reset().await.map_err(Error::Bus)? {
+            true => Ok(()),
+            false => Err(Error::NoPresence),
+        }
+    }
+
+    /// Write bytes, least significant bit first.
+    pub async fn write(&mut self, bytes: &[u8]) -> Result<(), Error<B::Error>> {
+        for &byte in bytes {
+            for i in 0..8 {
+                self.bus.write_bit(byte & (1 << i) != 0).await.map_err(Error::Bus)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read bytes, least significant bit first.
+    pub async fn read(&mut self, bytes: &mut [u8]) -> Result<(), Error<B::Error>> {
+        for byte in bytes {
+            *byte = 0;
+            for i in 0..8 {
+                if self.bus.read_bit().await.map_err(Error::Bus)? {
+                    *byte |= 1 << i;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reset the bus and select the device with the given ROM code, or all devices if `None`.
+    pub async fn select(&mut self, rom: Option<u64>) -> Result<(), Error<B::Error>> {
+        self.reset().await?;
+        match rom {
+            Some(rom) => {
+                self.write(&[MATCH_ROM]).await?;
+                self.write(&rom.to_le_bytes()).await
+            }
+            None => self.write(&[SKIP_ROM]).await,
+        }
+    }
+
+    /// Read the ROM code of the only device on the bus.
+    pub async fn read_rom(&mut self) -> Result<u64, Error<B::Error>> {
+        self.reset().await?;
+        self.write(&[READ_ROM]).await?;
+        let mut rom = [0; 8];
+        self.read(&mut rom).await?;
+        if crc8(&rom) != 0 {
+            return Err(Error::Crc);
+        }
+        Ok(u64::from_le_bytes(rom))
+    }
+
+    /// Find the next device on the bus, and return its ROM code.
+    ///
+    /// Call this repeatedly with the same `state` to find all devices. Returns `None` once
+    /// all devices have been found.
+    pub async fn search(&mut self, state: &mut SearchState) -> Result<Option<u64>, Error<B::Error>> {
+        self.search_with_command(state, SEARCH_ROM).await
+    }
+
+    /// Find the next device on the bus with an active alarm, like [`search`](Self::search).
+    pub async fn alarm_search(&mut self, state: &mut SearchState) -> Result<Option<u64>, Error<B::Error>> {
+        self.search_with_command(state, ALARM_SEARCH).await
+    }
+
+    async fn search_with_command(
+        &mut self,
+        state: &mut SearchState,
+        command: u8,
+    ) -> Result<Option<u64>, Error<B::Error>> {
+        if state.done {
+            return Ok(None);
+        }
+        if !self.bus.reset().await.map_err(Error::Bus)? {
+            *state = SearchState::new();
+            return Ok(None);
+        }
+        self.write(&[command]).await?;
+
+        let mut last_zero = 0;
+        for bit in 1..=64u8 {
+            let id_bit = self.bus.read_bit().await.map_err(Error::Bus)?;
+            let cmp_id_bit = self.bus.read_bit().await.map_err(Error::Bus)?;
+
+            let dir = match (id_bit, cmp_id_bit) {
+                // No device answered.
+                (true, true) => return Ok(None),
+                // All remaining devices have the same bit.
+                (a, b) if a != b => a,
+                // Discrepancy, take the other branch than last time.
+                _ => {
+                    let dir = if bit < state.last_discrepancy {
+                        state.rom & (1 << (bit - 1)) != 0
+                    } else {
+                        bit == state.last_discrepancy
+                    };
+                    if !dir {
+                        last_zero = bit;
+                    }
+                    dir
+                }
+            };
+
+            if dir {
+                state.rom |= 1 << (bit - 1);
+            } else {
+                state.rom &= !(1 << (bit - 1));
+            }
+            self.bus.write_bit(dir).await.map_err(Error::Bus)?;
+        }
+
+        state.last_discrepancy = last_zero;
+        state.done = last_zero == 0;
+
+        if crc8(&state.rom.to_le_bytes()) != 0 {
+            return Err(Error::Crc);
+        }
+        Ok(Some(state.rom))
+    }
+}
+
+/// Compute the Dallas/Maxim CRC-8 of `data`.
+///
+/// The CRC of data followed by its CRC is 0.
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        crc ^= byte;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0x8c } else { crc >> 1 };
+        }
+    }
+    crc
+}
+
+/// 1-Wire bus on an open-drain GPIO pin.
+///
+/// The pin must be configured as an open-drain output with its input enabled, and have a pull-up,
+/// usually an external 4.7k resistor. Time slots are generated by busy-waiting, so interrupts
+/// longer than a few microseconds during a transfer will corrupt it.
+#[cfg(feature = "time")]
+pub struct GpioOneWireBus<P> {
+    pin: P,
+}
+
+#[cfg(feature = "time")]
+impl<P: InputPin + OutputPin> GpioOneWireBus<P> {
+    /// Create a new 1-Wire bus.
+    pub fn new(pin: P) -> Self {
+        Self { pin }
+    }
+
+    /// Release the pin.
+    pub fn into_inner(self) -> P {
+        self.pin
+    }
+}
+
+#[cfg(feature = "time")]
+impl<P: InputPin + OutputPin> OneWireBus for GpioOneWireBus<P> {
+    type Error = P::Error;
+
+    async fn reset(&mut self) -> Result<bool, Self::Error> {
+        use embassy_time::{block_for, Duration, Timer};
+
+        self.pin.set_low()?;
+        Timer::after_micros(480).await;
+        self.pin.set_high()?;
+        block_for(Duration::from_micros(70));
+        let presence = self.pin.is_low()?;
+        Timer::after_micros(410).await;
+        Ok(presence)
+    }
+
+    async fn write_bit(&mut self, bit: bool) -> Result<(), Self::Error> {
+        use embassy_time::{block_for, Duration};
+
+        let (low, high) = if bit { (6, 64) } else { (60, 10) };
+        self.pin.set_low()?;
+        block_for(Duration::from_micros(low));
+        self.pin.set_high()?;
+        block_for(Duration::from_micros(high));
+        Ok(())
+    }
+
+    async fn read_bit(&mut self) -> Result<bool, Self::Error> {
+        use embassy_time::{block_for, Duration};
+
+        self.pin.set_low()?;
+        block_for(Duration::from_micros(6));
+        self.pin.set_high()?;
+        block_for(Duration::from_micros(9));
+        let bit = self.pin.is_high()?;
+        block_for(Duration::from_micros(55));
+        Ok(bit)
+    }
+}
+
+/// 1-Wire bus on a UART.
+///
+/// TX must be open-drain, or connected through a diode, and tied to RX, so every byte sent is
+/// also received. A reset is a 0xF0 byte at 9600 baud, and each bit is a byte at 115200 baud:
+/// 0xFF for a 1, 0x00 for a 0. Devices pull the echoed byte low to answer.
+///
+/// `reset_config` and `data_config` must set 9600 and 115200 baud, 8 data bits, no parity and
+/// 1 stop bit.
+pub struct UartOneWireBus<U: SetConfig> {
+    uart: U,
+    reset_config: U::Config,
+    data_config: U::Config,
+}
+
+/// Error returned by [`UartOneWireBus`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UartError<E> {
+    /// Setting the baud rate failed.
+    Config,
+    /// Error of the UART.
+    Uart(E),
+}
+
+impl<U: Read + Write + SetConfig> UartOneWireBus<U> {
+    /// Create a new 1-Wire bus.
+    pub fn new(uart: U, reset_config: U::Config, data_config: U::Config) -> Self {
+        Self {
+            uart,
+            reset_config,
+            data_config,
+        }
+    }
+
+    /// Release the UART.
+    pub fn into_inner(self) -> U {
+        self.uart
+    }
+
+    async fn transfer(&mut self, byte: u8) -> Result<u8, UartError<U::Error>> {
+        self.uart.write_all(&[byte]).await.map_err(UartError::Uart)?;
+        let mut buf = [0];
+        self.uart.read_exact(&mut buf).await.map_err(|e| match e {
+            embedded_io_async::ReadExactError::Other(e) => UartError::Uart(e),
+            embedded_io_async::ReadExactError::UnexpectedEof => UartError::Config,
+        })?;
+        Ok(buf[0])
+    }
+}
+
+impl<U: Read + Write + SetConfig> OneWireBus for UartOneWireBus<U> {
+    type Error = UartError<U::Error>;
+
+    async fn reset(&mut self) -> Result<bool, Self::Error> {
+        self.uart
+            .set_config(&self.reset_config)
+            .map_err(|_| UartError::Config)?;
+        let echo = self.transfer(0xf0).await;
+        self.uart.set_config(&self.data_config).map_err(|_| UartError::Config)?;
+        Ok(echo? != 0xf0)
+    }
+
+    async fn write_bit(&mut self, bit: bool) -> Result<(), Self::Error> {
+        self.transfer(if bit { 0xff } else { 0x00 }).await?;
+        Ok(())
+    }
+
+    async fn read_bit(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.transfer(0xff).await? == 0xff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+    use core::convert::Infallible;
+
+    use super::*;
+
+    /// Devices on a simulated bus, answering a search.
+    struct Bus<'a> {
+        roms: &'a [u64],
+        active: [bool; 3],
+        bit: u8,
+        step: u8,
+    }
+
+    impl OneWireBus for Bus<'_> {
+        type Error = Infallible;
+
+        async fn reset(&mut self) -> Result<bool, Infallible> {
+            self.active = [true; 3];
+            self.bit = 0;
+            // The command byte is written first, skip its 8 bits.
+            self.step = 0;
+            Ok(!self.roms.is_empty())
+        }
+
+        async fn write_bit(&mut self, bit: bool) -> Result<(), Infallible> {
+            if self.step < 8 {
+                self.step += 1;
+                return Ok(());
+            }
+            for (i, rom) in self.roms.iter().enumerate() {
+                if (rom >> self.bit) & 1 != bit as u64 {
+                    self.active[i] = false;
+                }
+            }
+            self.bit += 1;
+            self.step = 8;
+            Ok(())
+        }
+
+        async fn read_bit(&mut self) -> Result<bool, Infallible> {
+            // Wired-AND of the bit, then of its complement.
+            let cmp = self.step == 9;
+            self.step = if cmp { 8 } else { 9 };
+            Ok(self
+                .roms
+                .iter()
+                .zip(self.active)
+                .filter(|(_, active)| *active)
+                .all(|(rom, _)| ((rom >> self.bit) & 1 != 0) != cmp))
+        }
+    }
+
+    fn with_crc(rom: u64) -> u64 {
+        let bytes = rom.to_le_bytes();
+        rom | (crc8(&bytes[..7]) as u64) << 56
+    }
+
+    #[test]
+    fn can_compute_crc() {
+        // Example from Maxim application note 27.
+        let rom = [0x02, 0x1c, 0xb8, 0x01, 0x00, 0x00, 0x00, 0xa2];
+        assert_eq!(0xa2, crc8(&rom[..7]));
+        assert_eq!(0, crc8(&rom));
+    }
+
+    #[futures_test::test]
+    async fn can_search() {
+        let roms = [
+            with_crc(0x0000_0000_0012_3428),
+            with_crc(0x0000_0000_0045_6728),
+            with_crc(0x0000_0000_0012_3528),
+        ];
+
+        let mut onewire = OneWire::new(Bus {
+            roms: &roms,
+            active: [true; 3],
+            bit: 0,
+            step: 0,
+        });
+
+        let mut found = Vec::new();
+        let mut state = SearchState::new();
+        while let Some(rom) = onewire.search(&mut state).await.unwrap() {
+            found.push(rom);
+        }
+
+        found.sort();
+        let mut expected = roms.to_vec();
+        expected.sort();
+        assert_eq!(expected, found);
+    }
+}
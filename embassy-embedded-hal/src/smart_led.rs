@@ -0,0 +1,207 @@
+//! Addressable RGB LEDs, like the WS2812.
+//!
+//! [`SmartLedWrite`] is implemented by the LED drivers in the HALs, so LED effects can be written
+//! once for all of them. [`SpiWs2812`] drives WS2812 LEDs from any SPI bus, by stretching each data
+//! bit to 4 SPI bits.
+
+use embedded_hal_async::spi::SpiBus;
+
+/// A color with 8 bits per channel.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rgb8 {
+    /// Red
+    pub r: u8,
+    /// Green
+    pub g: u8,
+    /// Blue
+    pub b: u8,
+}
+
+impl Rgb8 {
+    /// Create a new color.
+    pub const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    /// Apply gamma correction, so that brightness steps look even to the eye.
+    pub fn gamma(self) -> Self {
+        Self {
+            r: gamma(self.r),
+            g: gamma(self.g),
+            b: gamma(self.b),
+        }
+    }
+}
+
+impl From<(u8, u8, u8)> for Rgb8 {
+    fn from((r, g, b): (u8, u8, u8)) -> Self {
+        Self { r, g, b }
+    }
+}
+
+/// Gamma correct a single channel, with a gamma of 2.8.
+pub fn gamma(value: u8) -> u8 {
+    GAMMA[value as usize]
+}
+
+const GAMMA: [u8; 256] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+    1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5, 5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8,
+    9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14, 14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21,
+    21, 22, 22, 23, 24, 24, 25, 25, 26, 27, 27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41,
+    42, 43, 44, 45, 46, 47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
+    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104, 105, 107, 109, 110,
+    112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137, 138, 140, 142, 144, 146, 148, 150, 152,
+    154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175, 177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203,
+    205, 208, 210, 213, 215, 218, 220, 223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
+];
+
+/// Write colors to a chain of addressable LEDs.
+pub trait SmartLedWrite {
+    /// Error type
+    type Error;
+
+    /// Write the colors of the LEDs, starting at the first LED of the chain.
+    ///
+    /// This returns once the LEDs have latched the colors, so the next frame can be written
+    /// right away.
+    async fn write(&mut self, colors: &[Rgb8]) -> Result<(), Self::Error>;
+}
+
+/// Error returned by [`SpiWs2812`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<E> {
+    /// The buffer is too small for the number of LEDs.
+    BufferTooSmall,
+    /// An SPI error occurred.
+    Spi(E),
+}
+
+/// Number of SPI bytes per LED for [`SpiWs2812`].
+pub const SPI_BYTES_PER_LED: usize = 12;
+
+/// WS2812 driver on an SPI bus.
+///
+/// The SPI bus must be clocked at 3.2MHz (2.8-3.6MHz works), in mode 0, and only MOSI is used.
+/// Each data bit becomes 4 SPI bits, so the buffer must hold [`SPI_BYTES_PER_LED`] bytes per LED.
+///
+/// The colors are encoded into the buffer before the transfer starts, so the caller's frame can be
+/// updated as soon as `write` is called again.
+pub struct SpiWs2812<'a, SPI> {
+    spi: SPI,
+    buf: &'a mut [u8],
+}
+
+impl<'a, SPI: SpiBus> SpiWs2812<'a, SPI> {
+    /// Create a new WS2812 driver.
+    pub fn new(spi: SPI, buf: &'a mut [u8]) -> Self {
+        Self { spi, buf }
+    }
+
+    /// Release the SPI bus.
+    pub fn into_inner(self) -> SPI {
+        self.spi
+    }
+}
+
+impl<'a, SPI: SpiBus> SmartLedWrite for SpiWs2812<'a, SPI> {
+    type Error = Error<SPI::Error>;
+
+    async fn write(&mut self, colors: &[Rgb8]) -> Result<(), Self::Error> {
+        let len = colors.len() * SPI_BYTES_PER_LED;
+        if self.buf.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+
+        for (color, out) in colors.iter().zip(self.buf.chunks_exact_mut(SPI_BYTES_PER_LED)) {
+            // WS2812 expects green first.
+            for (byte, out) in [color.g, color.r, color.b].into_iter().zip(out.chunks_exact_mut(4)) {
+                for (i, out) in out.iter_mut().enumerate() {
+                    // 0 is 1000, 1 is 1110, two data bits per byte.
+                    let hi = if byte & (0x80 >> (2 * i)) != 0 { 0xe0 } else { 0x80 };
+                    let lo = if byte & (0x40 >> (2 * i)) != 0 { 0x0e } else { 0x08 };
+                    *out = hi | lo;
+                }
+            }
+        }
+
+        self.spi.write(&self.buf[..len]).await.map_err(Error::Spi)?;
+        // Keep the line low for at least 280us, so the LEDs latch the colors.
+        self.spi.write(&[0; 112]).await.map_err(Error::Spi)?;
+        self.spi.flush().await.map_err(Error::Spi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+    use core::convert::Infallible;
+
+    use embedded_hal_1::spi::ErrorType;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Spi(Vec<u8>);
+
+    impl ErrorType for Spi {
+        type Error = Infallible;
+    }
+
+    impl SpiBus for Spi {
+        async fn read(&mut self, _words: &mut [u8]) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+        async fn write(&mut self, words: &[u8]) -> Result<(), Infallible> {
+            self.0.extend_from_slice(words);
+            Ok(())
+        }
+        async fn transfer(&mut self, _read: &mut [u8], _write: &[u8]) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+        async fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), Infallible> {
+            unimplemented!()
+        }
+        async fn flush(&mut self) -> Result<(), Infallible> {
+            Ok(())
+        }
+    }
+
+    #[futures_test::test]
+    async fn can_encode_for_spi() {
+        let mut buf = [0; 2 * SPI_BYTES_PER_LED];
+        let mut leds = SpiWs2812::new(Spi::default(), &mut buf);
+
+        let colors = [Rgb8::new(0xff, 0x00, 0x80), Rgb8::new(0x00, 0x01, 0x00)];
+        leds.write(&colors).await.unwrap();
+
+        let spi = leds.into_inner();
+        assert_eq!(
+            [
+                0x88, 0x88, 0x88, 0x88, 0xee, 0xee, 0xee, 0xee, 0xe8, 0x88, 0x88, 0x88, // LED 0
+                0x88, 0x88, 0x88, 0x8e, 0x88, 0x88, 0x88, 0x88, 0x88, 0x88, 0x88, 0x88, // LED 1
+            ],
+            spi.0[..24]
+        );
+        assert!(spi.0[24..].iter().all(|&b| b == 0));
+        assert_eq!(24 + 112, spi.0.len());
+    }
+
+    #[futures_test::test]
+    async fn rejects_small_buffer() {
+        let mut buf = [0; SPI_BYTES_PER_LED];
+        let mut leds = SpiWs2812::new(Spi::default(), &mut buf);
+        assert_eq!(Err(Error::BufferTooSmall), leds.write(&[Rgb8::default(); 2]).await);
+    }
+
+    #[test]
+    fn gamma_keeps_range() {
+        assert_eq!(0, gamma(0));
+        assert_eq!(255, gamma(255));
+        assert!(gamma(128) < 128);
+    }
+}
@@ -25,7 +25,8 @@ impl Parse for Args {
 }
 
 /// Declares an async task that can be run by `embassy-executor`. The optional `pool_size` parameter can be used to specify how
-/// many concurrent tasks can be spawned (default is 1) for the function.
+/// many concurrent tasks can be spawned (default is 1) for the function. The optional `name` parameter sets the name reported
+/// by the `task-introspection` feature of `embassy-executor` (default is the name of the function).
 ///
 ///
 /// The following restrictions apply:
@@ -54,6 +55,15 @@ impl Parse for Args {
 ///     // Function body
 /// }
 /// ```
+///
+/// Declaring a task with a given name:
+///
+/// ``` rust
+/// #[embassy_executor::task(name = "blinky")]
+/// async fn mytask() {
+///     // Function body
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn task(args: TokenStream, item: TokenStream) -> TokenStream {
     let args = syn::parse_macro_input!(args as Args);
@@ -131,6 +141,43 @@ pub fn main_riscv(args: TokenStream, item: TokenStream) -> TokenStream {
         .into()
 }
 
+/// Creates a new `executor` instance and declares an application entry point for Xtensa spawning the corresponding function body as an async task.
+///
+/// The following restrictions apply:
+///
+/// * The function must accept exactly 1 parameter, an `embassy_executor::Spawner` handle that it can use to spawn additional tasks.
+/// * The function must be declared `async`.
+/// * The function must not use generics.
+/// * Only a single `main` task may be declared.
+///
+/// A user-defined entry macro can be optionally provided via the `entry` argument to override the default of `xtensa_lx_rt::entry`.
+///
+/// ## Examples
+/// Spawning a task:
+///
+/// ``` rust
+/// #[embassy_executor::main]
+/// async fn main(_s: embassy_executor::Spawner) {
+///     // Function body
+/// }
+/// ```
+///
+/// Spawning a task using a custom entry macro:
+/// ``` rust
+/// #[embassy_executor::main(entry = "esp_hal::entry")]
+/// async fn main(_s: embassy_executor::Spawner) {
+///     // Function body
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn main_xtensa(args: TokenStream, item: TokenStream) -> TokenStream {
+    let args = syn::parse_macro_input!(args as Args);
+    let f = syn::parse_macro_input!(item as syn::ItemFn);
+    main::run(&args.meta, f, main::xtensa(&args.meta))
+        .unwrap_or_else(|x| x)
+        .into()
+}
+
 /// Creates a new `executor` instance and declares an application entry point for STD spawning the corresponding function body as an async task.
 ///
 /// The following restrictions apply:
@@ -7,6 +7,7 @@ static CONFIGS: &[(&str, usize)] = &[
     // BEGIN AUTOGENERATED CONFIG FEATURES
     // Generated by gen_config.py. DO NOT EDIT.
     ("TASK_ARENA_SIZE", 4096),
+    ("POLL_BUDGET", 8),
     // END AUTOGENERATED CONFIG FEATURES
 ];
 
@@ -7,8 +7,11 @@ os.chdir(dname)
 features = []
 
 
-def feature(name, default, min=None, max=None, pow2=None, vals=None, factors=[]):
-    if vals is None:
+def feature(name, default, min=None, max=None, pow2=None, vals=None, factors=[], env_only=False):
+    if env_only:
+        # Only configurable with an environment variable, no Cargo features.
+        vals = []
+    elif vals is None:
         assert min is not None
         assert max is not None
 
@@ -38,11 +41,14 @@ def feature(name, default, min=None, max=None, pow2=None, vals=None, factors=[])
 feature(
     "task_arena_size", default=4096, min=64, max=1024 * 1024, pow2=True, factors=[3, 5]
 )
+feature("poll_budget", default=8, env_only=True)
 
 # ========= Update Cargo.toml
 
 things = ""
 for f in features:
+    if not f["vals"]:
+        continue
     name = f["name"].replace("_", "-")
     for val in f["vals"]:
         things += f"## {val}"
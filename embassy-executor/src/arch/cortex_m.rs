@@ -53,7 +53,16 @@ mod thread {
 
     pub use embassy_executor_macros::main_cortex_m as main;
 
-    use crate::{raw, Spawner};
+    use crate::{raw, LowPowerStrategy, Spawner};
+
+    /// Default [`LowPowerStrategy`] of the thread mode executor, sleeping with `WFE`.
+    pub struct Wfe;
+
+    impl LowPowerStrategy for Wfe {
+        fn sleep(&mut self, _next_wakeup: Option<u64>) {
+            unsafe { asm!("wfe") };
+        }
+    }
 
     /// Thread mode executor, using WFE/SEV.
     ///
@@ -64,7 +73,8 @@ mod thread {
     ///
     /// This executor allows for ultra low power consumption for chips where `WFE`
     /// triggers low-power sleep without extra steps. If your chip requires extra steps,
-    /// you may use [`raw::Executor`] directly to program custom behavior.
+    /// you may run the executor with a custom [`LowPowerStrategy`], or use [`raw::Executor`]
+    /// directly to program custom behavior.
     pub struct Executor {
         inner: raw::Executor,
         not_send: PhantomData<*mut ()>,
@@ -98,13 +108,28 @@ mod thread {
         ///
         /// This function never returns.
         pub fn run(&'static mut self, init: impl FnOnce(Spawner)) -> ! {
+            self.run_with_strategy(Wfe, init)
+        }
+
+        /// Run the executor, sleeping with `strategy` when there is no work to do.
+        ///
+        /// See [`run()`](Self::run) for details.
+        pub fn run_with_strategy(
+            &'static mut self,
+            mut strategy: impl LowPowerStrategy,
+            init: impl FnOnce(Spawner),
+        ) -> ! {
             init(self.inner.spawner());
 
             loop {
-                unsafe {
-                    self.inner.poll();
-                    asm!("wfe");
-                };
+                unsafe { self.inner.poll() };
+
+                #[cfg(feature = "integrated-timers")]
+                let next_wakeup = unsafe { self.inner.next_expiration() };
+                #[cfg(not(feature = "integrated-timers"))]
+                let next_wakeup = None;
+
+                strategy.sleep(next_wakeup);
             }
         }
     }
@@ -10,7 +10,7 @@ mod thread {
 
     pub use embassy_executor_macros::main_riscv as main;
 
-    use crate::{raw, Spawner};
+    use crate::{raw, LowPowerStrategy, Spawner};
 
     /// global atomic used to keep track of whether there is work to do since sev() is not available on RISCV
     static SIGNAL_WORK_THREAD_MODE: AtomicBool = AtomicBool::new(false);
@@ -20,6 +20,15 @@ mod thread {
         SIGNAL_WORK_THREAD_MODE.store(true, Ordering::SeqCst);
     }
 
+    /// Default [`LowPowerStrategy`] of the executor, sleeping with `WFI`.
+    pub struct Wfi;
+
+    impl LowPowerStrategy for Wfi {
+        fn sleep(&mut self, _next_wakeup: Option<u64>) {
+            unsafe { core::arch::asm!("wfi") };
+        }
+    }
+
     /// RISCV32 Executor
     pub struct Executor {
         inner: raw::Executor,
@@ -54,11 +63,28 @@ mod thread {
         ///
         /// This function never returns.
         pub fn run(&'static mut self, init: impl FnOnce(Spawner)) -> ! {
+            self.run_with_strategy(Wfi, init)
+        }
+
+        /// Run the executor, sleeping with `strategy` when there is no work to do.
+        ///
+        /// See [`run()`](Self::run) for details.
+        pub fn run_with_strategy(
+            &'static mut self,
+            mut strategy: impl LowPowerStrategy,
+            init: impl FnOnce(Spawner),
+        ) -> ! {
             init(self.inner.spawner());
 
             loop {
                 unsafe {
                     self.inner.poll();
+
+                    #[cfg(feature = "integrated-timers")]
+                    let next_wakeup = self.inner.next_expiration();
+                    #[cfg(not(feature = "integrated-timers"))]
+                    let next_wakeup = None;
+
                     // we do not care about race conditions between the load and store operations, interrupts
                     //will only set this value to true.
                     critical_section::with(|_| {
@@ -69,7 +95,7 @@ mod thread {
                         }
                         // if not, wait for interrupt
                         else {
-                            core::arch::asm!("wfi");
+                            strategy.sleep(next_wakeup);
                         }
                     });
                     // if an interrupt occurred while waiting, it will be serviced here
@@ -92,3 +92,128 @@ mod thread {
         }
     }
 }
+
+pub use blocking::*;
+mod blocking {
+    use std::collections::VecDeque;
+    use std::future::Future;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::pin::Pin;
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+
+    use crate::Spawner;
+
+    type Job = Box<dyn FnOnce() + Send>;
+
+    /// Threads running blocking closures, started when all the existing ones are busy.
+    struct Pool {
+        state: Mutex<PoolState>,
+        condvar: Condvar,
+    }
+
+    struct PoolState {
+        jobs: VecDeque<Job>,
+        /// Number of threads waiting for a job.
+        idle: usize,
+    }
+
+    static POOL: Pool = Pool {
+        state: Mutex::new(PoolState {
+            jobs: VecDeque::new(),
+            idle: 0,
+        }),
+        condvar: Condvar::new(),
+    };
+
+    impl Pool {
+        fn run(&'static self, job: Job) {
+            let mut state = self.state.lock().unwrap();
+            state.jobs.push_back(job);
+            if state.jobs.len() > state.idle {
+                thread::Builder::new()
+                    .name("embassy-blocking".into())
+                    .spawn(|| self.work())
+                    .unwrap();
+            } else {
+                self.condvar.notify_one();
+            }
+        }
+
+        fn work(&self) {
+            let mut state = self.state.lock().unwrap();
+            loop {
+                match state.jobs.pop_front() {
+                    Some(job) => {
+                        drop(state);
+                        job();
+                        state = self.state.lock().unwrap();
+                    }
+                    None => {
+                        state.idle += 1;
+                        state = self.condvar.wait(state).unwrap();
+                        state.idle -= 1;
+                    }
+                }
+            }
+        }
+    }
+
+    struct Shared<T> {
+        result: Option<thread::Result<T>>,
+        waker: Option<Waker>,
+    }
+
+    /// Result of a closure running on a thread, returned by [`Spawner::spawn_blocking()`].
+    ///
+    /// Awaiting it returns the value returned by the closure. If the closure panicked, the panic is
+    /// resumed in the awaiting task.
+    ///
+    /// Dropping the handle doesn't stop the closure, which keeps running until it returns.
+    pub struct BlockingHandle<T> {
+        shared: Arc<Mutex<Shared<T>>>,
+    }
+
+    impl<T> Future for BlockingHandle<T> {
+        type Output = T;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            let mut shared = self.shared.lock().unwrap();
+            match shared.result.take() {
+                Some(Ok(value)) => Poll::Ready(value),
+                Some(Err(payload)) => panic::resume_unwind(payload),
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    impl Spawner {
+        /// Run a blocking closure on a thread pool, returning a handle to await its result.
+        ///
+        /// This allows calling blocking APIs, such as filesystem or network APIs, without stalling
+        /// the executor and the other tasks running in it. Threads are started when all the
+        /// existing ones are busy, and are reused for the next closures.
+        pub fn spawn_blocking<T: Send + 'static>(&self, f: impl FnOnce() -> T + Send + 'static) -> BlockingHandle<T> {
+            let shared = Arc::new(Mutex::new(Shared {
+                result: None,
+                waker: None,
+            }));
+
+            let job_shared = shared.clone();
+            POOL.run(Box::new(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(f));
+                let mut shared = job_shared.lock().unwrap();
+                shared.result = Some(result);
+                if let Some(waker) = shared.waker.take() {
+                    waker.wake();
+                }
+            }));
+
+            BlockingHandle { shared }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+#[cfg(any(feature = "executor-thread", feature = "executor-interrupt"))]
+use core::arch::asm;
+#[cfg(feature = "executor-thread")]
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Pender context of the thread mode executor running on `core`.
+#[cfg(feature = "executor-thread")]
+const fn thread_pender(core: usize) -> usize {
+    usize::MAX - core
+}
+
+/// Whether the thread mode executor of each core has work to do, since WAITI only wakes on interrupts.
+#[cfg(feature = "executor-thread")]
+static SIGNAL_WORK_THREAD_MODE: [AtomicBool; 2] = [AtomicBool::new(false), AtomicBool::new(false)];
+
+/// Index of the current core, 0 or 1 on dual-core chips such as the ESP32 and ESP32-S3.
+#[cfg(feature = "executor-thread")]
+fn current_core() -> usize {
+    let prid: u32;
+    unsafe { asm!("rsr.prid {0}", out(reg) prid, options(nomem, nostack)) };
+    ((prid >> 13) & 1) as usize
+}
+
+#[export_name = "__pender"]
+#[cfg(any(feature = "executor-thread", feature = "executor-interrupt"))]
+fn __pender(context: *mut ()) {
+    // Safety: `context` is either created by `Executor::run` with `thread_pender`, or a valid
+    // software interrupt number given to `InterruptExecutor::start`.
+
+    let context = context as usize;
+
+    #[cfg(feature = "executor-thread")]
+    // Try to make Rust optimize the branching away if we only use thread mode.
+    if !cfg!(feature = "executor-interrupt") || context >= thread_pender(1) {
+        SIGNAL_WORK_THREAD_MODE[usize::MAX - context].store(true, Ordering::SeqCst);
+        return;
+    }
+
+    #[cfg(feature = "executor-interrupt")]
+    unsafe {
+        asm!("wsr.intset {0}", "rsync", in(reg) 1u32 << context, options(nostack));
+    }
+}
+
+#[cfg(feature = "executor-thread")]
+pub use thread::*;
+#[cfg(feature = "executor-thread")]
+mod thread {
+    use core::arch::asm;
+    use core::marker::PhantomData;
+    use core::sync::atomic::Ordering;
+
+    pub use embassy_executor_macros::main_xtensa as main;
+
+    use super::{current_core, thread_pender, SIGNAL_WORK_THREAD_MODE};
+    use crate::{raw, LowPowerStrategy, Spawner};
+
+    /// Default [`LowPowerStrategy`] of the thread mode executor, sleeping with `WAITI 0`.
+    pub struct Waiti;
+
+    impl LowPowerStrategy for Waiti {
+        fn sleep(&mut self, _next_wakeup: Option<u64>) {
+            unsafe { asm!("waiti 0") };
+        }
+    }
+
+    /// Thread mode executor, using WAITI.
+    ///
+    /// The executor sleeps with the `WAITI` instruction when it has no more work to do, which
+    /// unmasks interrupts and waits for one. When a task is woken, a flag is set for the core the
+    /// executor runs on, so that it polls its tasks once the interrupt that woke the task returns.
+    ///
+    /// One executor can run on each core. Since `WAITI` only wakes up on interrupts, waking a task
+    /// of the executor running on the other core only takes effect at its next interrupt: the HAL
+    /// or application must raise an interrupt on that core, such as a cross-core interrupt, for
+    /// the task to run without delay.
+    pub struct Executor {
+        inner: raw::Executor,
+        core: usize,
+        not_send: PhantomData<*mut ()>,
+    }
+
+    impl Executor {
+        /// Create a new Executor, to run on the current core.
+        pub fn new() -> Self {
+            let core = current_core();
+            Self {
+                inner: raw::Executor::new(thread_pender(core) as *mut ()),
+                core,
+                not_send: PhantomData,
+            }
+        }
+
+        /// Run the executor.
+        ///
+        /// The `init` closure is called with a [`Spawner`] that spawns tasks on
+        /// this executor. Use it to spawn the initial task(s). After `init` returns,
+        /// the executor starts running the tasks.
+        ///
+        /// To spawn more tasks later, you may keep copies of the [`Spawner`] (it is `Copy`),
+        /// for example by passing it as an argument to the initial tasks.
+        ///
+        /// This function requires `&'static mut self`. This means you have to store the
+        /// Executor instance in a place where it'll live forever and grants you mutable
+        /// access. There's a few ways to do this:
+        ///
+        /// - a [StaticCell](https://docs.rs/static_cell/latest/static_cell/) (safe)
+        /// - a `static mut` (unsafe)
+        /// - a local variable in a function you know never returns (like `fn main() -> !`), upgrading its lifetime with `transmute`. (unsafe)
+        ///
+        /// This function never returns.
+        pub fn run(&'static mut self, init: impl FnOnce(Spawner)) -> ! {
+            self.run_with_strategy(Waiti, init)
+        }
+
+        /// Run the executor, sleeping with `strategy` when there is no work to do.
+        ///
+        /// See [`run()`](Self::run) for details.
+        pub fn run_with_strategy(
+            &'static mut self,
+            mut strategy: impl LowPowerStrategy,
+            init: impl FnOnce(Spawner),
+        ) -> ! {
+            init(self.inner.spawner());
+
+            let signal = &SIGNAL_WORK_THREAD_MODE[self.core];
+            loop {
+                unsafe {
+                    self.inner.poll();
+
+                    #[cfg(feature = "integrated-timers")]
+                    let next_wakeup = self.inner.next_expiration();
+                    #[cfg(not(feature = "integrated-timers"))]
+                    let next_wakeup = None;
+
+                    // WAITI unmasks interrupts while waiting, so checking the flag with interrupts
+                    // masked can't miss a wakeup.
+                    critical_section::with(|_| {
+                        // if there is work to do, loop back to polling
+                        if signal.load(Ordering::SeqCst) {
+                            signal.store(false, Ordering::SeqCst);
+                        }
+                        // if not, wait for interrupt
+                        else {
+                            strategy.sleep(next_wakeup);
+                        }
+                    });
+                    // if an interrupt occurred while waiting, it will be serviced here
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "executor-interrupt")]
+pub use interrupt::*;
+#[cfg(feature = "executor-interrupt")]
+mod interrupt {
+    use core::arch::asm;
+    use core::cell::{Cell, UnsafeCell};
+    use core::mem::MaybeUninit;
+
+    use critical_section::Mutex;
+
+    use crate::raw;
+
+    /// Interrupt mode executor.
+    ///
+    /// This executor runs tasks in interrupt mode. The interrupt handler is set up
+    /// to poll tasks, and when a task is woken the interrupt is raised from software.
+    ///
+    /// This allows running async tasks at a priority higher than thread mode. One
+    /// use case is to leave thread mode free for non-async tasks. Another use case is
+    /// to run multiple executors: one in thread mode for low priority tasks and another in
+    /// interrupt mode for higher priority tasks. Higher priority tasks will preempt lower
+    /// priority ones.
+    ///
+    /// To use it, you have to pick a software interrupt of the Xtensa core, whose level sets the
+    /// priority of the executor. For example, on the ESP32 and ESP32-S3, core interrupt 7 is a
+    /// level 1 software interrupt, and 29 a level 3 one. Multiple interrupt mode executors can
+    /// run at different priorities on software interrupts of different levels.
+    ///
+    /// The executor runs on the core that started it, and must only be woken from that core.
+    ///
+    /// It is somewhat more complex to use, it's recommended to use the thread-mode
+    /// [`Executor`](crate::Executor) instead, if it works for your use case.
+    pub struct InterruptExecutor {
+        started: Mutex<Cell<bool>>,
+        irq: Mutex<Cell<u32>>,
+        executor: UnsafeCell<MaybeUninit<raw::Executor>>,
+    }
+
+    unsafe impl Send for InterruptExecutor {}
+    unsafe impl Sync for InterruptExecutor {}
+
+    impl InterruptExecutor {
+        /// Create a new, not started `InterruptExecutor`.
+        #[inline]
+        pub const fn new() -> Self {
+            Self {
+                started: Mutex::new(Cell::new(false)),
+                irq: Mutex::new(Cell::new(0)),
+                executor: UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }
+
+        /// Executor interrupt callback.
+        ///
+        /// # Safety
+        ///
+        /// - You MUST call this from the interrupt handler, and from nowhere else.
+        /// - You must not call this before calling `start()`.
+        pub unsafe fn on_interrupt(&'static self) {
+            let irq = critical_section::with(|cs| self.irq.borrow(cs).get());
+            asm!("wsr.intclear {0}", "rsync", in(reg) 1u32 << irq, options(nostack));
+
+            let executor = unsafe { (&*self.executor.get()).assume_init_ref() };
+            executor.poll();
+        }
+
+        /// Start the executor.
+        ///
+        /// This initializes the executor, enables the interrupt, and returns.
+        /// The executor keeps running in the background through the interrupt.
+        ///
+        /// This returns a [`SendSpawner`] you can use to spawn tasks on it. A [`SendSpawner`]
+        /// is returned instead of a [`Spawner`](embassy_executor::Spawner) because the executor effectively runs in a
+        /// different "thread" (the interrupt), so spawning tasks on it is effectively
+        /// sending them.
+        ///
+        /// To obtain a [`Spawner`](embassy_executor::Spawner) for this executor, use [`Spawner::for_current_executor()`](embassy_executor::Spawner::for_current_executor()) from
+        /// a task running in it.
+        ///
+        /// # Interrupt requirements
+        ///
+        /// You must write the interrupt handler yourself, and make it call [`on_interrupt()`](Self::on_interrupt).
+        ///
+        /// `irq` is the number of a software interrupt of the Xtensa core. This method already
+        /// enables it in the `INTENABLE` register of the current core, you must NOT do it yourself.
+        pub fn start(&'static self, irq: u32) -> crate::SendSpawner {
+            if critical_section::with(|cs| self.started.borrow(cs).replace(true)) {
+                panic!("InterruptExecutor::start() called multiple times on the same executor.");
+            }
+
+            critical_section::with(|cs| self.irq.borrow(cs).set(irq));
+
+            unsafe {
+                (&mut *self.executor.get())
+                    .as_mut_ptr()
+                    .write(raw::Executor::new(irq as usize as *mut ()))
+            }
+
+            let executor = unsafe { (&*self.executor.get()).assume_init_ref() };
+
+            critical_section::with(|_| unsafe {
+                let mut enabled: u32;
+                asm!("rsr.intenable {0}", out(reg) enabled, options(nostack));
+                enabled |= 1 << irq;
+                asm!("wsr.intenable {0}", "rsync", in(reg) enabled, options(nostack));
+            });
+
+            executor.spawner().make_send()
+        }
+
+        /// Get a SendSpawner for this executor
+        ///
+        /// This returns a [`SendSpawner`] you can use to spawn tasks on this
+        /// executor.
+        ///
+        /// This MUST only be called on an executor that has already been started.
+        /// The function will panic otherwise.
+        pub fn spawner(&'static self) -> crate::SendSpawner {
+            if !critical_section::with(|cs| self.started.borrow(cs).get()) {
+                panic!("InterruptExecutor::spawner() called on uninitialized executor.");
+            }
+            let executor = unsafe { (&*self.executor.get()).assume_init_ref() };
+            executor.spawner().make_send()
+        }
+    }
+}
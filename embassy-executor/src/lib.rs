@@ -1,4 +1,5 @@
 #![cfg_attr(not(any(feature = "arch-std", feature = "arch-wasm")), no_std)]
+#![cfg_attr(feature = "arch-xtensa", feature(asm_experimental_arch))]
 #![allow(clippy::new_without_default)]
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
@@ -23,7 +24,14 @@ macro_rules! check_at_most_one {
         check_at_most_one!(@amo [$($f)*] [$($f)*] []);
     };
 }
-check_at_most_one!("arch-avr", "arch-cortex-m", "arch-riscv32", "arch-std", "arch-wasm",);
+check_at_most_one!(
+    "arch-avr",
+    "arch-cortex-m",
+    "arch-riscv32",
+    "arch-std",
+    "arch-wasm",
+    "arch-xtensa",
+);
 
 #[cfg(feature = "_arch")]
 #[cfg_attr(feature = "arch-avr", path = "arch/avr.rs")]
@@ -31,6 +39,7 @@ check_at_most_one!("arch-avr", "arch-cortex-m", "arch-riscv32", "arch-std", "arc
 #[cfg_attr(feature = "arch-riscv32", path = "arch/riscv32.rs")]
 #[cfg_attr(feature = "arch-std", path = "arch/std.rs")]
 #[cfg_attr(feature = "arch-wasm", path = "arch/wasm.rs")]
+#[cfg_attr(feature = "arch-xtensa", path = "arch/xtensa.rs")]
 mod arch;
 
 #[cfg(feature = "_arch")]
@@ -42,6 +51,25 @@ pub mod raw;
 mod spawner;
 pub use spawner::*;
 
+#[cfg(all(
+    feature = "executor-thread",
+    any(feature = "arch-cortex-m", feature = "arch-riscv32", feature = "arch-xtensa")
+))]
+mod low_power;
+#[cfg(all(
+    feature = "executor-thread",
+    any(feature = "arch-cortex-m", feature = "arch-riscv32", feature = "arch-xtensa")
+))]
+pub use low_power::*;
+
+#[cfg(feature = "fair-scheduling")]
+pub use raw::fairness::yield_now;
+
+#[cfg(feature = "supervisor")]
+mod supervisor;
+#[cfg(feature = "supervisor")]
+pub use supervisor::*;
+
 mod config {
     #![allow(unused)]
     include!(concat!(env!("OUT_DIR"), "/config.rs"));
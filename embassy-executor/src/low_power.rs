@@ -0,0 +1,24 @@
+/// Puts the core to sleep while a thread-mode executor has no work to do.
+///
+/// By default, the thread-mode executor sleeps with `WFE` on Cortex-M, `WFI` on RISC-V and `WAITI`
+/// on Xtensa. Pass a strategy to `Executor::run_with_strategy` to enter deeper low-power states
+/// instead, for example when the next timer expiration is far enough away to be worth the wakeup
+/// latency.
+pub trait LowPowerStrategy {
+    /// Sleep until the executor has work to do.
+    ///
+    /// `next_wakeup` is the time at which the next timer expires, in `embassy-time` ticks, when the
+    /// `integrated-timers` feature is enabled and a timer is pending. The time driver alarm is already
+    /// set for it, so it must be kept running, or the strategy must wake up by that time itself.
+    ///
+    /// The executor must not miss a wakeup while going to sleep:
+    ///
+    /// - On Cortex-M, the executor is woken with `SEV`, so the core must sleep with `WFE`, for example
+    ///   with `SLEEPDEEP` set to enter a stop mode.
+    /// - On RISC-V, this is called with interrupts disabled, and the core must sleep with `WFI`.
+    /// - On Xtensa, this is called with interrupts disabled, and the core must sleep with `WAITI 0`.
+    ///
+    /// Before returning, the strategy must restore anything it changed to enter a deeper low-power
+    /// state, such as clocks, so that tasks can run normally.
+    fn sleep(&mut self, next_wakeup: Option<u64>);
+}
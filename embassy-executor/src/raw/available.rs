@@ -0,0 +1,111 @@
+use core::cell::RefCell;
+use core::future::poll_fn;
+use core::mem;
+#[cfg(target_has_atomic = "ptr")]
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::task::{Poll, Waker};
+
+use critical_section::Mutex;
+
+/// Maximum number of tasks waiting for a task to finish. When more tasks wait, all of them are
+/// woken to make room, and the ones still waiting register again.
+const MAX_WAITERS: usize = 4;
+
+struct Waiters {
+    /// Number of tasks that have finished, so that a waiter doesn't miss a task finishing between
+    /// a failed spawn and registering its waker.
+    #[cfg(not(target_has_atomic = "ptr"))]
+    finished: u32,
+    wakers: [Option<Waker>; MAX_WAITERS],
+}
+
+/// Number of tasks that have finished, see [`Waiters`].
+#[cfg(target_has_atomic = "ptr")]
+static FINISHED: AtomicU32 = AtomicU32::new(0);
+
+/// Set while wakers are registered, so that finishing tasks only take a critical section when
+/// tasks wait for them.
+#[cfg(target_has_atomic = "ptr")]
+static WAITING: AtomicBool = AtomicBool::new(false);
+
+const NO_WAKER: Option<Waker> = None;
+
+static WAITERS: Mutex<RefCell<Waiters>> = Mutex::new(RefCell::new(Waiters {
+    #[cfg(not(target_has_atomic = "ptr"))]
+    finished: 0,
+    wakers: [NO_WAKER; MAX_WAITERS],
+}));
+
+/// Number of tasks that have finished so far, to pass to [`wait_task_finished`].
+pub(crate) fn finished_count() -> u32 {
+    #[cfg(target_has_atomic = "ptr")]
+    return FINISHED.load(Ordering::SeqCst);
+    #[cfg(not(target_has_atomic = "ptr"))]
+    return critical_section::with(|cs| WAITERS.borrow_ref(cs).finished);
+}
+
+/// Record that a task finished, freeing its storage, and wake the tasks waiting for it.
+pub(crate) fn task_finished() {
+    // Either a waiter sees the new count after registering, or this sees the waiter registered.
+    #[cfg(target_has_atomic = "ptr")]
+    {
+        FINISHED.fetch_add(1, Ordering::SeqCst);
+        if !WAITING.load(Ordering::SeqCst) {
+            return;
+        }
+    }
+
+    let mut wakers = critical_section::with(|cs| {
+        let mut waiters = WAITERS.borrow_ref_mut(cs);
+        #[cfg(target_has_atomic = "ptr")]
+        WAITING.store(false, Ordering::SeqCst);
+        #[cfg(not(target_has_atomic = "ptr"))]
+        {
+            waiters.finished = waiters.finished.wrapping_add(1);
+        }
+        mem::take(&mut waiters.wakers)
+    });
+    for waker in wakers.iter_mut().filter_map(Option::take) {
+        waker.wake();
+    }
+}
+
+/// Wait until a task finishes, if none has finished since `finished_count()` returned `seen`.
+pub(crate) async fn wait_task_finished(seen: u32) {
+    poll_fn(|cx| {
+        let mut evicted: [Option<Waker>; MAX_WAITERS] = Default::default();
+        let finished = critical_section::with(|cs| {
+            let mut waiters = WAITERS.borrow_ref_mut(cs);
+            #[cfg(not(target_has_atomic = "ptr"))]
+            let finished = |waiters: &Waiters| waiters.finished != seen;
+            #[cfg(target_has_atomic = "ptr")]
+            let finished = |_: &Waiters| FINISHED.load(Ordering::SeqCst) != seen;
+
+            if finished(&waiters) {
+                return true;
+            }
+            if !waiters.wakers.iter().flatten().any(|w| w.will_wake(cx.waker())) {
+                if waiters.wakers.iter().all(Option::is_some) {
+                    evicted = mem::take(&mut waiters.wakers);
+                }
+                if let Some(slot) = waiters.wakers.iter_mut().find(|w| w.is_none()) {
+                    *slot = Some(cx.waker().clone());
+                }
+            }
+            #[cfg(target_has_atomic = "ptr")]
+            WAITING.store(true, Ordering::SeqCst);
+
+            // A task may have finished before the waker was registered.
+            finished(&waiters)
+        });
+        for waker in evicted.iter_mut().filter_map(Option::take) {
+            waker.wake();
+        }
+        if finished {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
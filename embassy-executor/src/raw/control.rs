@@ -0,0 +1,78 @@
+use core::cell::RefCell;
+use core::task::{Context, Poll, Waker};
+
+use critical_section::Mutex;
+
+/// State shared between a task and its [`TaskHandle`](crate::TaskHandle)s.
+pub(crate) struct TaskControl {
+    inner: Mutex<RefCell<Inner>>,
+}
+
+struct Inner {
+    /// Incremented each time the task finishes, so that handles to a previous instance
+    /// of the task don't affect the next one spawned in the same storage.
+    generation: u32,
+    /// Task must be dropped instead of polled.
+    abort: bool,
+    /// Woken when the task finishes.
+    join_waker: Option<Waker>,
+}
+
+impl TaskControl {
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(Inner {
+                generation: 0,
+                abort: false,
+                join_waker: None,
+            })),
+        }
+    }
+
+    /// Generation of the currently spawned instance of the task.
+    pub fn generation(&self) -> u32 {
+        critical_section::with(|cs| self.inner.borrow_ref(cs).generation)
+    }
+
+    /// Request the instance `generation` of the task to be aborted. Return whether it is still running.
+    pub fn abort(&self, generation: u32) -> bool {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            let running = inner.generation == generation;
+            if running {
+                inner.abort = true;
+            }
+            running
+        })
+    }
+
+    /// Return whether the task must be aborted instead of polled.
+    pub fn aborted(&self) -> bool {
+        critical_section::with(|cs| self.inner.borrow_ref(cs).abort)
+    }
+
+    /// Mark the current instance of the task as finished, returning the waker of its joiner.
+    pub fn finish(&self) -> Option<Waker> {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            inner.generation = inner.generation.wrapping_add(1);
+            inner.abort = false;
+            inner.join_waker.take()
+        })
+    }
+
+    /// Poll for the instance `generation` of the task to finish.
+    pub fn poll_join(&self, generation: u32, cx: &mut Context) -> Poll<()> {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            if inner.generation != generation {
+                return Poll::Ready(());
+            }
+            match &inner.join_waker {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                _ => inner.join_waker = Some(cx.waker().clone()),
+            }
+            Poll::Pending
+        })
+    }
+}
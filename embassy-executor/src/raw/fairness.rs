@@ -0,0 +1,97 @@
+use core::cell::Cell;
+use core::future::poll_fn;
+use core::task::Poll;
+
+use critical_section::Mutex;
+
+use super::{task_from_waker, TaskRef};
+
+/// Number of consecutive polls after which a task is deprioritized.
+const POLL_BUDGET: u32 = crate::config::POLL_BUDGET as u32;
+
+/// How much a task has run since its executor last drained its run queue.
+pub(crate) struct TaskFairness {
+    inner: Mutex<Cell<Inner>>,
+}
+
+#[derive(Clone, Copy)]
+struct Inner {
+    /// Round of the executor in which `polls` were counted.
+    round: u32,
+    polls: u32,
+    yielded: bool,
+}
+
+impl TaskFairness {
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(Cell::new(Inner {
+                round: 0,
+                polls: 0,
+                yielded: false,
+            })),
+        }
+    }
+
+    /// Record that the task is polled in `round` of its executor.
+    ///
+    /// A round lasts until the executor drains its run queue.
+    pub fn polled(&self, round: u32) {
+        critical_section::with(|cs| {
+            let cell = self.inner.borrow(cs);
+            let mut inner = cell.get();
+            if inner.round != round {
+                inner.round = round;
+                inner.polls = 0;
+            }
+            inner.polls = inner.polls.saturating_add(1);
+            inner.yielded = false;
+            cell.set(inner);
+        })
+    }
+
+    fn set_yielded(&self) {
+        critical_section::with(|cs| {
+            let cell = self.inner.borrow(cs);
+            cell.set(Inner {
+                yielded: true,
+                ..cell.get()
+            });
+        })
+    }
+}
+
+/// Whether `task` must be polled after the other tasks of its batch, because it yielded or used up
+/// its poll budget.
+pub(crate) fn deprioritized(task: TaskRef) -> bool {
+    let header = task.header();
+    let Some(executor) = (unsafe { header.executor.get() }) else {
+        return false;
+    };
+    let round = executor.round();
+    let inner = critical_section::with(|cs| header.fairness.inner.borrow(cs).get());
+    inner.yielded || (inner.round == round && inner.polls >= POLL_BUDGET)
+}
+
+/// Yield to the other tasks of the executor.
+///
+/// Unlike a future that only wakes itself and returns `Pending` once, this guarantees that all the
+/// tasks of the executor that are ready to run when yielding are polled before the current task.
+/// Call it regularly in long computations, such as DSP loops, so that they don't delay I/O tasks.
+///
+/// # Panics
+///
+/// Panics if called outside of a task spawned in an embassy executor.
+pub async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        task_from_waker(cx.waker()).header().fairness.set_yielded();
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await
+}
@@ -0,0 +1,243 @@
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+use super::{SyncExecutor, TaskRef};
+
+/// All the tasks that have ever been spawned, linked through [`TaskIntrospection::next`].
+///
+/// Tasks are only ever added to the front of the list, so it can be walked while tasks are spawned.
+static TASKS: Mutex<Cell<Option<TaskRef>>> = Mutex::new(Cell::new(None));
+
+/// Debugging information about a task.
+pub(crate) struct TaskIntrospection {
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    name: Cell<Option<&'static str>>,
+    spawn_count: Cell<u32>,
+    next: Cell<Option<TaskRef>>,
+    #[cfg(feature = "task-wake-stats")]
+    wake_stats: Cell<WakeStats>,
+}
+
+impl TaskIntrospection {
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(Inner {
+                name: Cell::new(None),
+                spawn_count: Cell::new(0),
+                next: Cell::new(None),
+                #[cfg(feature = "task-wake-stats")]
+                wake_stats: Cell::new(WakeStats::NEW),
+            }),
+        }
+    }
+
+    /// Record that `task` was claimed to be spawned, adding it to the task list the first time.
+    pub fn claimed(&self, task: TaskRef) {
+        critical_section::with(|cs| {
+            let inner = self.inner.borrow(cs);
+            if inner.spawn_count.get() == 0 {
+                inner.next.set(TASKS.borrow(cs).replace(Some(task)));
+            }
+            inner.spawn_count.set(inner.spawn_count.get().wrapping_add(1));
+            inner.name.set(None);
+            #[cfg(feature = "task-wake-stats")]
+            inner.wake_stats.set(WakeStats::NEW);
+        })
+    }
+
+    /// Record that the task was woken by `source`, which `enqueued` it unless it was already queued.
+    #[cfg(feature = "task-wake-stats")]
+    pub fn woken(&self, source: WakeSource, enqueued: bool) {
+        critical_section::with(|cs| {
+            let cell = &self.inner.borrow(cs).wake_stats;
+            let mut stats = cell.get();
+            match source {
+                WakeSource::Spawn => {}
+                WakeSource::Waker => stats.waker_wakes = stats.waker_wakes.wrapping_add(1),
+                WakeSource::Timer => stats.timer_wakes = stats.timer_wakes.wrapping_add(1),
+            }
+            if enqueued {
+                stats.last_wake = Some(source);
+            } else {
+                stats.redundant_wakes = stats.redundant_wakes.wrapping_add(1);
+            }
+            cell.set(stats);
+        })
+    }
+
+    #[cfg(feature = "task-wake-stats")]
+    pub fn polled(&self) {
+        critical_section::with(|cs| {
+            let cell = &self.inner.borrow(cs).wake_stats;
+            let mut stats = cell.get();
+            stats.polls = stats.polls.wrapping_add(1);
+            cell.set(stats);
+        })
+    }
+
+    pub fn set_name(&self, name: &'static str) {
+        critical_section::with(|cs| self.inner.borrow(cs).name.set(Some(name)))
+    }
+
+    /// Send the name of `task` to the trace tool.
+    #[cfg(feature = "rtos-trace")]
+    pub fn trace_info(&self, task: TaskRef) {
+        if let Some(name) = critical_section::with(|cs| self.inner.borrow(cs).name.get()) {
+            let info = rtos_trace::TaskInfo {
+                name,
+                priority: 0,
+                stack_base: 0,
+                stack_size: 0,
+            };
+            rtos_trace::trace::task_send_info(task.as_ptr() as u32, info);
+        }
+    }
+}
+
+/// Send the names of the tasks running in all executors to the trace tool.
+#[cfg(feature = "rtos-trace")]
+pub(crate) fn trace_task_list() {
+    let mut next = critical_section::with(|cs| TASKS.borrow(cs).get());
+    while let Some(task) = next {
+        let header = task.header();
+        next = critical_section::with(|cs| header.introspection.inner.borrow(cs).next.get());
+        if header.state.is_spawned() {
+            header.introspection.trace_info(task);
+        }
+    }
+}
+
+/// Whether a task is waiting to be polled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TaskState {
+    /// The task was woken, and is waiting in the run queue to be polled.
+    Ready,
+    /// The task is waiting to be woken.
+    Waiting,
+}
+
+/// Information about a task running in an executor, returned by [`Tasks`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TaskInfo {
+    /// Name of the task, which is the name of the task function unless set with
+    /// `#[embassy_executor::task(name = "...")]`, or `None` for tasks spawned without the macro.
+    pub name: Option<&'static str>,
+    /// Whether the task is waiting to be polled.
+    pub state: TaskState,
+    /// Number of times the task storage has been spawned, including this time.
+    pub spawn_count: u32,
+}
+
+/// What woke a task, recorded with the `task-wake-stats` feature.
+#[cfg(feature = "task-wake-stats")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WakeSource {
+    /// The task was spawned.
+    Spawn,
+    /// The timer queue of the executor, for a timer of `embassy-time`.
+    Timer,
+    /// A `Waker` of the task, or [`wake_task()`](super::wake_task), for example from an interrupt
+    /// handler or a channel.
+    Waker,
+}
+
+/// Counts of the polls and wakeups of a task since it was spawned, returned by
+/// [`Tasks::with_wake_stats()`]. Counts wrap around on overflow.
+#[cfg(feature = "task-wake-stats")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeStats {
+    /// Number of times the task was polled.
+    pub polls: u32,
+    /// Number of times the task was woken with a `Waker`.
+    pub waker_wakes: u32,
+    /// Number of times the task was woken by the timer queue.
+    pub timer_wakes: u32,
+    /// Wakeups that had no effect, because the task was already waiting to be polled.
+    ///
+    /// A count growing much faster than `polls` points to a wakeup storm.
+    pub redundant_wakes: u32,
+    /// What woke the task last time it was queued to be polled.
+    pub last_wake: Option<WakeSource>,
+}
+
+#[cfg(feature = "task-wake-stats")]
+impl WakeStats {
+    const NEW: Self = Self {
+        polls: 0,
+        waker_wakes: 0,
+        timer_wakes: 0,
+        redundant_wakes: 0,
+        last_wake: None,
+    };
+}
+
+/// Iterator over the tasks running in an executor.
+///
+/// Obtained with [`Spawner::tasks()`](crate::Spawner::tasks), [`SendSpawner::tasks()`](crate::SendSpawner::tasks)
+/// or [`Executor::tasks()`](super::Executor::tasks).
+pub struct Tasks {
+    next: Option<TaskRef>,
+    executor: &'static SyncExecutor,
+}
+
+impl Tasks {
+    pub(crate) fn new(executor: &'static SyncExecutor) -> Self {
+        Self {
+            next: critical_section::with(|cs| TASKS.borrow(cs).get()),
+            executor,
+        }
+    }
+}
+
+impl Tasks {
+    /// Also return the poll and wakeup counts of each task.
+    #[cfg(feature = "task-wake-stats")]
+    pub fn with_wake_stats(self) -> impl Iterator<Item = (TaskInfo, WakeStats)> {
+        let mut tasks = self;
+        core::iter::from_fn(move || tasks.next_running(|info, inner| (info, inner.wake_stats.get())))
+    }
+
+    /// Return `f` applied to the next task running in the executor.
+    fn next_running<T>(&mut self, f: impl Fn(TaskInfo, &Inner) -> T) -> Option<T> {
+        loop {
+            let task = self.next?;
+            let header = task.header();
+            let item = critical_section::with(|cs| {
+                let inner = header.introspection.inner.borrow(cs);
+                self.next = inner.next.get();
+
+                let executor = unsafe { header.executor.get() };
+                let running = header.state.is_spawned() && executor.is_some_and(|e| core::ptr::eq(e, self.executor));
+                let info = TaskInfo {
+                    name: inner.name.get(),
+                    state: if header.state.is_run_queued() {
+                        TaskState::Ready
+                    } else {
+                        TaskState::Waiting
+                    },
+                    spawn_count: inner.spawn_count.get(),
+                };
+                running.then(|| f(info, inner))
+            });
+            if item.is_some() {
+                return item;
+            }
+        }
+    }
+}
+
+impl Iterator for Tasks {
+    type Item = TaskInfo;
+
+    fn next(&mut self) -> Option<TaskInfo> {
+        self.next_running(|info, _| info)
+    }
+}
@@ -16,12 +16,27 @@ mod run_queue;
 #[cfg_attr(not(target_has_atomic = "8"), path = "state_critical_section.rs")]
 mod state;
 
+pub(crate) mod available;
+#[cfg(feature = "task-handles")]
+mod control;
+#[cfg(feature = "fair-scheduling")]
+pub(crate) mod fairness;
+#[cfg(feature = "task-introspection")]
+mod introspection;
+#[cfg(feature = "executor-pause")]
+mod pause;
+#[cfg(feature = "scheduler-priority")]
+mod priority;
+#[cfg(feature = "executor-stats")]
+mod stats;
 #[cfg(feature = "integrated-timers")]
 mod timer_queue;
 pub(crate) mod util;
 #[cfg_attr(feature = "turbowakers", path = "waker_turbo.rs")]
 mod waker;
 
+#[cfg(feature = "fair-scheduling")]
+use core::cell::Cell;
 use core::future::Future;
 use core::marker::PhantomData;
 use core::mem;
@@ -29,13 +44,23 @@ use core::pin::Pin;
 use core::ptr::NonNull;
 use core::task::{Context, Poll};
 
+#[cfg(feature = "fair-scheduling")]
+use critical_section::Mutex;
 #[cfg(feature = "integrated-timers")]
 use embassy_time_driver::AlarmHandle;
 #[cfg(feature = "rtos-trace")]
 use rtos_trace::trace;
 
+#[cfg(feature = "task-introspection")]
+pub use self::introspection::{TaskInfo, TaskState, Tasks};
+#[cfg(feature = "task-wake-stats")]
+pub use self::introspection::{WakeSource, WakeStats};
+#[cfg(feature = "executor-pause")]
+pub use self::pause::{Pause, PauseGuard};
 use self::run_queue::{RunQueue, RunQueueItem};
 use self::state::State;
+#[cfg(feature = "executor-stats")]
+pub use self::stats::ExecutorStats;
 use self::util::{SyncUnsafeCell, UninitCell};
 pub use self::waker::task_from_waker;
 use super::SpawnToken;
@@ -46,6 +71,14 @@ pub(crate) struct TaskHeader {
     pub(crate) run_queue_item: RunQueueItem,
     pub(crate) executor: SyncUnsafeCell<Option<&'static SyncExecutor>>,
     poll_fn: SyncUnsafeCell<Option<unsafe fn(TaskRef)>>,
+    #[cfg(feature = "task-handles")]
+    pub(crate) control: control::TaskControl,
+    #[cfg(feature = "task-introspection")]
+    pub(crate) introspection: introspection::TaskIntrospection,
+    #[cfg(feature = "scheduler-priority")]
+    pub(crate) priority: priority::TaskPriority,
+    #[cfg(feature = "fair-scheduling")]
+    pub(crate) fairness: fairness::TaskFairness,
 
     #[cfg(feature = "integrated-timers")]
     pub(crate) expires_at: SyncUnsafeCell<u64>,
@@ -84,6 +117,37 @@ impl TaskRef {
     pub(crate) fn as_ptr(self) -> *const TaskHeader {
         self.ptr.as_ptr()
     }
+
+    /// Priority of the task. Among the tasks ready to run, those with a higher priority are polled first.
+    #[cfg(feature = "scheduler-priority")]
+    pub fn priority(self) -> u8 {
+        self.header().priority.priority()
+    }
+
+    /// Set the priority of the task, taking effect the next time it is woken.
+    ///
+    /// Tasks have priority 0 when spawned, unless set with [`SpawnToken::with_priority()`].
+    #[cfg(feature = "scheduler-priority")]
+    pub fn set_priority(self, priority: u8) {
+        self.header().priority.set_priority(priority)
+    }
+
+    /// Deadline of the task, in `embassy-time` ticks. Among the ready tasks with the same priority,
+    /// those with an earlier deadline are polled first.
+    #[cfg(feature = "scheduler-priority")]
+    pub fn deadline(self) -> u64 {
+        self.header().priority.deadline()
+    }
+
+    /// Set the deadline of the task, taking effect the next time it is woken.
+    ///
+    /// Tasks have no deadline (`u64::MAX`) when spawned, unless set with [`SpawnToken::with_deadline()`].
+    /// For earliest-deadline-first scheduling, a task can move its deadline forward each time it starts
+    /// a new job, getting its own `TaskRef` with [`task_from_waker()`].
+    #[cfg(feature = "scheduler-priority")]
+    pub fn set_deadline(self, deadline: u64) {
+        self.header().priority.set_deadline(deadline)
+    }
 }
 
 /// Raw storage in which a task can be spawned.
@@ -119,6 +183,14 @@ impl<F: Future + 'static> TaskStorage<F> {
                 executor: SyncUnsafeCell::new(None),
                 // Note: this is lazily initialized so that a static `TaskStorage` will go in `.bss`
                 poll_fn: SyncUnsafeCell::new(None),
+                #[cfg(feature = "task-handles")]
+                control: control::TaskControl::new(),
+                #[cfg(feature = "task-introspection")]
+                introspection: introspection::TaskIntrospection::new(),
+                #[cfg(feature = "scheduler-priority")]
+                priority: priority::TaskPriority::new(),
+                #[cfg(feature = "fair-scheduling")]
+                fairness: fairness::TaskFairness::new(),
 
                 #[cfg(feature = "integrated-timers")]
                 expires_at: SyncUnsafeCell::new(0),
@@ -153,17 +225,18 @@ impl<F: Future + 'static> TaskStorage<F> {
     unsafe fn poll(p: TaskRef) {
         let this = &*(p.as_ptr() as *const TaskStorage<F>);
 
+        // An aborted task is dropped at its next poll, instead of being polled.
+        #[cfg(feature = "task-handles")]
+        if this.raw.control.aborted() {
+            this.finish();
+            return;
+        }
+
         let future = Pin::new_unchecked(this.future.as_mut());
         let waker = waker::from_task(p);
         let mut cx = Context::from_waker(&waker);
         match future.poll(&mut cx) {
-            Poll::Ready(_) => {
-                this.future.drop_in_place();
-                this.raw.state.despawn();
-
-                #[cfg(feature = "integrated-timers")]
-                this.raw.expires_at.set(u64::MAX);
-            }
+            Poll::Ready(_) => this.finish(),
             Poll::Pending => {}
         }
 
@@ -172,6 +245,28 @@ impl<F: Future + 'static> TaskStorage<F> {
         mem::forget(waker);
     }
 
+    unsafe fn finish(&self) {
+        self.future.drop_in_place();
+
+        // The generation must change before the storage can be claimed again.
+        #[cfg(feature = "rtos-trace")]
+        trace::task_terminate(self as *const _ as u32);
+
+        #[cfg(feature = "task-handles")]
+        let join_waker = self.raw.control.finish();
+
+        self.raw.state.despawn();
+        available::task_finished();
+
+        #[cfg(feature = "integrated-timers")]
+        self.raw.expires_at.set(u64::MAX);
+
+        #[cfg(feature = "task-handles")]
+        if let Some(waker) = join_waker {
+            waker.wake();
+        }
+    }
+
     #[doc(hidden)]
     #[allow(dead_code)]
     fn _assert_sync(self) {
@@ -191,7 +286,19 @@ impl<F: Future + 'static> AvailableTask<F> {
     ///
     /// This function returns `None` if a task has already been spawned and has not finished running.
     pub fn claim(task: &'static TaskStorage<F>) -> Option<Self> {
-        task.raw.state.spawn().then(|| Self { task })
+        let claimed = task.raw.state.spawn();
+
+        #[cfg(feature = "task-introspection")]
+        if claimed {
+            task.raw.introspection.claimed(TaskRef::new(task));
+        }
+
+        #[cfg(feature = "scheduler-priority")]
+        if claimed {
+            task.raw.priority.reset();
+        }
+
+        claimed.then(|| Self { task })
     }
 
     fn initialize_impl<S>(self, future: impl FnOnce() -> F) -> SpawnToken<S> {
@@ -298,44 +405,106 @@ impl<F: Future + 'static, const N: usize> TaskPool<F, N> {
     }
 }
 
+/// Wakeup signaling of an executor, for executors created with [`Executor::new_with_pender()`].
+///
+/// The executor calls [`pend()`](Pender::pend) when it has work to do. You must then arrange for
+/// [`Executor::poll()`] to be called as soon as possible, from the thread owning the executor.
+///
+/// This allows driving an executor from anything able to wait for a signal, without exporting a
+/// global `__pender` function, and independently of the executors of the `arch-xx` features. For
+/// example, to run an executor as a sub-executor inside the thread of an RTOS:
+///
+/// ```rust,ignore
+/// struct SemaphorePender(rtos::Semaphore);
+///
+/// impl Pender for SemaphorePender {
+///     fn pend(&self) {
+///         self.0.give();
+///     }
+/// }
+///
+/// static PENDER: SemaphorePender = SemaphorePender(rtos::Semaphore::new());
+///
+/// fn rtos_thread() -> ! {
+///     let executor: &'static Executor = make_static!(Executor::new_with_pender(&PENDER));
+///     executor.spawner().must_spawn(main_task());
+///     loop {
+///         unsafe { executor.poll() };
+///         PENDER.0.take();
+///     }
+/// }
+/// ```
+///
+/// Like the pender function, `pend()` can be called from *any* context: any thread, any interrupt
+/// priority level, etc. It may be called synchronously from any `Executor` method call as well.
+/// In particular, it must NOT call `poll` directly.
+pub trait Pender: Sync {
+    /// Signal that the executor has work to do.
+    fn pend(&self);
+}
+
 #[derive(Clone, Copy)]
-pub(crate) struct Pender(*mut ());
+pub(crate) enum PendTarget {
+    /// Context passed to the global `__pender` function.
+    Context(*mut ()),
+    Pender(&'static dyn Pender),
+}
 
-unsafe impl Send for Pender {}
-unsafe impl Sync for Pender {}
+unsafe impl Send for PendTarget {}
+unsafe impl Sync for PendTarget {}
 
-impl Pender {
+impl PendTarget {
     pub(crate) fn pend(self) {
         extern "Rust" {
             fn __pender(context: *mut ());
         }
-        unsafe { __pender(self.0) };
+        match self {
+            PendTarget::Context(context) => unsafe { __pender(context) },
+            PendTarget::Pender(pender) => pender.pend(),
+        }
     }
 }
 
 pub(crate) struct SyncExecutor {
     run_queue: RunQueue,
-    pender: Pender,
+    pender: PendTarget,
+    // Incremented each time the executor drains its run queue.
+    #[cfg(feature = "fair-scheduling")]
+    round: Mutex<Cell<u32>>,
+    #[cfg(feature = "executor-pause")]
+    pause: pause::PauseState,
+    #[cfg(feature = "executor-stats")]
+    stats: stats::StatsRecorder,
 
     #[cfg(feature = "integrated-timers")]
     pub(crate) timer_queue: timer_queue::TimerQueue,
     #[cfg(feature = "integrated-timers")]
     alarm: AlarmHandle,
+    #[cfg(feature = "integrated-timers")]
+    next_expiration: SyncUnsafeCell<u64>,
 }
 
 impl SyncExecutor {
-    pub(crate) fn new(pender: Pender) -> Self {
+    pub(crate) fn new(pender: PendTarget) -> Self {
         #[cfg(feature = "integrated-timers")]
         let alarm = unsafe { unwrap!(embassy_time_driver::allocate_alarm()) };
 
         Self {
             run_queue: RunQueue::new(),
             pender,
+            #[cfg(feature = "fair-scheduling")]
+            round: Mutex::new(Cell::new(0)),
+            #[cfg(feature = "executor-pause")]
+            pause: pause::PauseState::new(),
+            #[cfg(feature = "executor-stats")]
+            stats: stats::StatsRecorder::new(),
 
             #[cfg(feature = "integrated-timers")]
             timer_queue: timer_queue::TimerQueue::new(),
             #[cfg(feature = "integrated-timers")]
             alarm,
+            #[cfg(feature = "integrated-timers")]
+            next_expiration: SyncUnsafeCell::new(u64::MAX),
         }
     }
 
@@ -355,6 +524,22 @@ impl SyncExecutor {
         }
     }
 
+    /// Whether the executor has no queued tasks and isn't polling any, as a hint for distributing
+    /// work between executors.
+    pub(crate) fn is_idle(&self) -> bool {
+        self.run_queue.is_idle()
+    }
+
+    #[cfg(feature = "fair-scheduling")]
+    pub(crate) fn round(&self) -> u32 {
+        critical_section::with(|cs| self.round.borrow(cs).get())
+    }
+
+    #[cfg(feature = "executor-stats")]
+    pub(crate) fn stats(&self) -> ExecutorStats {
+        self.stats.get()
+    }
+
     #[cfg(feature = "integrated-timers")]
     fn alarm_callback(ctx: *mut ()) {
         let this: &Self = unsafe { &*(ctx as *const Self) };
@@ -366,6 +551,10 @@ impl SyncExecutor {
 
         #[cfg(feature = "rtos-trace")]
         trace::task_new(task.as_ptr() as u32);
+        #[cfg(all(feature = "rtos-trace", feature = "task-introspection"))]
+        task.header().introspection.trace_info(task);
+        #[cfg(feature = "task-wake-stats")]
+        task.header().introspection.woken(WakeSource::Spawn, true);
 
         self.enqueue(task);
     }
@@ -374,18 +563,44 @@ impl SyncExecutor {
     ///
     /// Same as [`Executor::poll`], plus you must only call this on the thread this executor was created.
     pub(crate) unsafe fn poll(&'static self) {
+        // Tasks stay queued until the executor is resumed, which pends it.
+        #[cfg(feature = "executor-pause")]
+        if self.pause.is_paused() {
+            return;
+        }
+
+        // Tasks taken from the queue keep the executor busy until they are polled.
+        self.run_queue.set_polling(true);
+
         #[cfg(feature = "integrated-timers")]
         embassy_time_driver::set_alarm_callback(self.alarm, Self::alarm_callback, self as *const _ as *mut ());
 
+        #[cfg(feature = "executor-stats")]
+        let poll_start = embassy_time_driver::now();
+        #[cfg(feature = "fair-scheduling")]
+        let round = self.round();
+
         #[allow(clippy::never_loop)]
         loop {
             #[cfg(feature = "integrated-timers")]
-            self.timer_queue
-                .dequeue_expired(embassy_time_driver::now(), wake_task_no_pend);
+            self.timer_queue.dequeue_expired(embassy_time_driver::now(), |task| {
+                let enqueued = enqueue_no_pend(task);
+                #[cfg(feature = "task-wake-stats")]
+                task.header().introspection.woken(WakeSource::Timer, enqueued);
+                #[cfg(not(feature = "task-wake-stats"))]
+                let _ = enqueued;
+            });
 
             self.run_queue.dequeue_all(|p| {
                 let task = p.header();
 
+                // When paused while polling, the remaining tasks are queued again.
+                #[cfg(feature = "executor-pause")]
+                if !self.pause.begin_poll() {
+                    self.run_queue.enqueue(p);
+                    return;
+                }
+
                 #[cfg(feature = "integrated-timers")]
                 task.expires_at.set(u64::MAX);
 
@@ -395,9 +610,16 @@ impl SyncExecutor {
                     //   - While task is being polled, it gets woken. It gets placed in the queue.
                     //   - Task poll finishes, returning done=true
                     //   - RUNNING bit is cleared, but the task is already in the queue.
+                    #[cfg(feature = "executor-pause")]
+                    self.pause.end_poll();
                     return;
                 }
 
+                #[cfg(feature = "fair-scheduling")]
+                task.fairness.polled(round);
+                #[cfg(feature = "task-wake-stats")]
+                task.introspection.polled();
+
                 #[cfg(feature = "rtos-trace")]
                 trace::task_exec_begin(p.as_ptr() as u32);
 
@@ -407,6 +629,9 @@ impl SyncExecutor {
                 #[cfg(feature = "rtos-trace")]
                 trace::task_exec_end();
 
+                #[cfg(feature = "executor-pause")]
+                self.pause.end_poll();
+
                 // Enqueue or update into timer_queue
                 #[cfg(feature = "integrated-timers")]
                 self.timer_queue.update(p);
@@ -417,6 +642,7 @@ impl SyncExecutor {
                 // If this is already in the past, set_alarm might return false
                 // In that case do another poll loop iteration.
                 let next_expiration = self.timer_queue.next_expiration();
+                self.next_expiration.set(next_expiration);
                 if embassy_time_driver::set_alarm(self.alarm, next_expiration) {
                     break;
                 }
@@ -428,6 +654,20 @@ impl SyncExecutor {
             }
         }
 
+        #[cfg(feature = "executor-stats")]
+        self.stats.record_poll(poll_start, embassy_time_driver::now());
+
+        // Tasks woken while polling are left in the queue, for the next round.
+        #[cfg(feature = "fair-scheduling")]
+        critical_section::with(|cs| {
+            if self.run_queue.is_empty() {
+                let round = self.round.borrow(cs);
+                round.set(round.get().wrapping_add(1));
+            }
+        });
+
+        self.run_queue.set_polling(false);
+
         #[cfg(feature = "rtos-trace")]
         trace::system_idle();
     }
@@ -447,7 +687,8 @@ impl SyncExecutor {
 ///   it has work to do. You must arrange for `poll()` to be called as soon as possible.
 /// - Enabling `arch-xx` features will define a pender function for you. This means that you
 ///   are limited to using the executors provided to you by the architecture/platform
-///   implementation. If you need a different executor, you must not enable `arch-xx` features.
+///   implementation. If you need a different executor, you must not enable `arch-xx` features,
+///   or create it with [`Executor::new_with_pender()`] and a [`Pender`] instead.
 ///
 /// The pender can be called from *any* context: any thread, any interrupt priority
 /// level, etc. It may be called synchronously from any `Executor` method call as well.
@@ -468,6 +709,9 @@ impl SyncExecutor {
 /// The `context` argument is a piece of arbitrary data the executor will pass to the pender.
 /// You can set the `context` when calling [`Executor::new()`]. You can use it to, for example,
 /// differentiate between executors, or to pass a pointer to a callback that should be called.
+///
+/// The executor polls tasks spawned from any [`Future`], so it can drive futures produced by other
+/// crates, as long as they only rely on the [`Waker`](core::task::Waker) they are polled with.
 #[repr(transparent)]
 pub struct Executor {
     pub(crate) inner: SyncExecutor,
@@ -487,7 +731,18 @@ impl Executor {
     /// See [`Executor`] docs for details on the pender.
     pub fn new(context: *mut ()) -> Self {
         Self {
-            inner: SyncExecutor::new(Pender(context)),
+            inner: SyncExecutor::new(PendTarget::Context(context)),
+            _not_sync: PhantomData,
+        }
+    }
+
+    /// Create a new executor, calling `pender` when it has work to do.
+    ///
+    /// Unlike executors created with [`Executor::new()`], this doesn't call the global `__pender`
+    /// function. See [`Pender`] for details.
+    pub fn new_with_pender(pender: &'static dyn Pender) -> Self {
+        Self {
+            inner: SyncExecutor::new(PendTarget::Pender(pender)),
             _not_sync: PhantomData,
         }
     }
@@ -533,6 +788,42 @@ impl Executor {
     pub fn spawner(&'static self) -> super::Spawner {
         super::Spawner::new(self)
     }
+
+    /// Time at which the next timer expires, as of the last [`poll()`](Self::poll), in
+    /// `embassy-time` ticks, if any.
+    ///
+    /// # Safety
+    ///
+    /// You must call this on the thread this executor was created, and not during `poll`.
+    #[cfg(feature = "integrated-timers")]
+    pub unsafe fn next_expiration(&self) -> Option<u64> {
+        match self.inner.next_expiration.get() {
+            u64::MAX => None,
+            next => Some(next),
+        }
+    }
+
+    /// Iterate over the tasks running in this executor.
+    #[cfg(feature = "task-introspection")]
+    pub fn tasks(&'static self) -> Tasks {
+        Tasks::new(&self.inner)
+    }
+
+    /// Get the time this executor has spent polling tasks and sleeping.
+    #[cfg(feature = "executor-stats")]
+    pub fn stats(&self) -> ExecutorStats {
+        self.inner.stats()
+    }
+
+    /// Pause this executor.
+    ///
+    /// While paused, [`poll()`](Self::poll) returns without polling tasks. Awaiting the pause from
+    /// a task of this executor deadlocks. See [`SendSpawner::pause()`](crate::SendSpawner::pause)
+    /// for details.
+    #[cfg(feature = "executor-pause")]
+    pub fn pause(&'static self) -> Pause {
+        Pause::new(&self.inner)
+    }
 }
 
 /// Wake a task by `TaskRef`.
@@ -540,27 +831,66 @@ impl Executor {
 /// You can obtain a `TaskRef` from a `Waker` using [`task_from_waker`].
 pub fn wake_task(task: TaskRef) {
     let header = task.header();
-    if header.state.run_enqueue() {
+    let enqueued = header.state.run_enqueue();
+    if enqueued {
         // We have just marked the task as scheduled, so enqueue it.
         unsafe {
             let executor = header.executor.get().unwrap_unchecked();
             executor.enqueue(task);
         }
     }
+
+    #[cfg(feature = "task-wake-stats")]
+    header.introspection.woken(WakeSource::Waker, enqueued);
 }
 
 /// Wake a task by `TaskRef` without calling pend.
 ///
 /// You can obtain a `TaskRef` from a `Waker` using [`task_from_waker`].
 pub fn wake_task_no_pend(task: TaskRef) {
+    let enqueued = enqueue_no_pend(task);
+    #[cfg(feature = "task-wake-stats")]
+    task.header().introspection.woken(WakeSource::Waker, enqueued);
+    #[cfg(not(feature = "task-wake-stats"))]
+    let _ = enqueued;
+}
+
+/// Enqueue a task without calling pend, returning whether it wasn't already queued.
+#[inline(always)]
+fn enqueue_no_pend(task: TaskRef) -> bool {
     let header = task.header();
-    if header.state.run_enqueue() {
+    let enqueued = header.state.run_enqueue();
+    if enqueued {
         // We have just marked the task as scheduled, so enqueue it.
         unsafe {
             let executor = header.executor.get().unwrap_unchecked();
             executor.run_queue.enqueue(task);
         }
     }
+    enqueued
+}
+
+/// Wake a task by `TaskRef` at `at`, in `embassy-time` ticks, with the timer queue of its executor.
+///
+/// This is what `embassy-time` timers do when polled, without needing a `Timer` future per timeout,
+/// or going through the global `embassy-time` timer queue. A driver handling many timeouts, such
+/// as a protocol stack, can call it when polled with the earliest of its deadlines.
+///
+/// Each task has a single expiration time, which this only moves earlier. It is cleared every time
+/// the task is polled, so the wakeup must be scheduled again on each poll until the deadline is
+/// reached. The task may also be woken earlier, for example by another timer.
+///
+/// You can obtain a `TaskRef` from a `Waker` using [`task_from_waker`].
+///
+/// # Safety
+///
+/// You must call this on the thread running the executor of the task, for example while the
+/// task is being polled.
+#[cfg(feature = "integrated-timers")]
+pub unsafe fn wake_task_at(task: TaskRef, at: u64) {
+    let header = task.header();
+    let expires_at = header.expires_at.get();
+    header.expires_at.set(expires_at.min(at));
 }
 
 #[cfg(feature = "integrated-timers")]
@@ -569,19 +899,17 @@ struct TimerQueue;
 #[cfg(feature = "integrated-timers")]
 impl embassy_time_queue_driver::TimerQueue for TimerQueue {
     fn schedule_wake(&'static self, at: u64, waker: &core::task::Waker) {
-        let task = waker::task_from_waker(waker);
-        let task = task.header();
-        unsafe {
-            let expires_at = task.expires_at.get();
-            task.expires_at.set(expires_at.min(at));
-        }
+        unsafe { wake_task_at(waker::task_from_waker(waker), at) }
     }
 }
 
 #[cfg(feature = "integrated-timers")]
 embassy_time_queue_driver::timer_queue_impl!(static TIMER_QUEUE: TimerQueue = TimerQueue);
 
-#[cfg(all(feature = "rtos-trace", feature = "integrated-timers"))]
+#[cfg(all(
+    feature = "rtos-trace",
+    any(feature = "integrated-timers", feature = "executor-stats")
+))]
 const fn gcd(a: u64, b: u64) -> u64 {
     if b == 0 {
         a
@@ -592,15 +920,20 @@ const fn gcd(a: u64, b: u64) -> u64 {
 
 #[cfg(feature = "rtos-trace")]
 impl rtos_trace::RtosTraceOSCallbacks for Executor {
+    #[cfg(feature = "task-introspection")]
+    fn task_list() {
+        introspection::trace_task_list();
+    }
+    #[cfg(not(feature = "task-introspection"))]
     fn task_list() {
         // We don't know what tasks exist, so we can't send them.
     }
-    #[cfg(feature = "integrated-timers")]
+    #[cfg(any(feature = "integrated-timers", feature = "executor-stats"))]
     fn time() -> u64 {
         const GCD_1M: u64 = gcd(embassy_time_driver::TICK_HZ, 1_000_000);
         embassy_time_driver::now() * (1_000_000 / GCD_1M) / (embassy_time_driver::TICK_HZ / GCD_1M)
     }
-    #[cfg(not(feature = "integrated-timers"))]
+    #[cfg(not(any(feature = "integrated-timers", feature = "executor-stats")))]
     fn time() -> u64 {
         0
     }
@@ -0,0 +1,150 @@
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use critical_section::Mutex;
+
+use super::SyncExecutor;
+
+/// Pause requests of an executor.
+pub(crate) struct PauseState {
+    inner: Mutex<RefCell<Inner>>,
+}
+
+struct Inner {
+    /// Number of live pause requests, including granted ones.
+    requests: u32,
+    /// The executor is polling a task.
+    polling: bool,
+    /// Woken once the executor stops polling tasks.
+    waker: Option<Waker>,
+}
+
+impl PauseState {
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(Inner {
+                requests: 0,
+                polling: false,
+                waker: None,
+            })),
+        }
+    }
+
+    /// Called by the executor before polling a task. Return whether it may poll it.
+    pub fn begin_poll(&self) -> bool {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            inner.polling = inner.requests == 0;
+            inner.polling
+        })
+    }
+
+    /// Called by the executor when it has no task being polled, after polling tasks.
+    pub fn end_poll(&self) {
+        let waker = critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            inner.polling = false;
+            inner.waker.take()
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        critical_section::with(|cs| self.inner.borrow_ref(cs).requests != 0)
+    }
+}
+
+/// Future pausing an executor, returned by [`SendSpawner::pause()`](crate::SendSpawner::pause)
+/// or [`Executor::pause()`](super::Executor::pause).
+///
+/// The executor stops polling tasks as soon as the future is first polled. The future completes
+/// once the task the executor may be polling at that time returns, with a [`PauseGuard`] that
+/// resumes the executor when dropped.
+///
+/// Awaiting it from a task of the executor being paused deadlocks.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Pause {
+    executor: &'static SyncExecutor,
+    requested: bool,
+}
+
+impl Pause {
+    pub(crate) fn new(executor: &'static SyncExecutor) -> Self {
+        Self {
+            executor,
+            requested: false,
+        }
+    }
+}
+
+impl Future for Pause {
+    type Output = PauseGuard;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<PauseGuard> {
+        let executor = self.executor;
+        let requested = self.requested;
+        let (ready, previous) = critical_section::with(|cs| {
+            let mut inner = executor.pause.inner.borrow_ref_mut(cs);
+            if !requested {
+                inner.requests += 1;
+            }
+            if !inner.polling {
+                return (true, None);
+            }
+            // Like `WakerRegistration` in `embassy-sync`: a previous waiter is woken to register again.
+            let previous = match &inner.waker {
+                Some(waker) if waker.will_wake(cx.waker()) => None,
+                _ => inner.waker.replace(cx.waker().clone()),
+            };
+            (false, previous)
+        });
+        if let Some(waker) = previous {
+            waker.wake();
+        }
+
+        // Once ready, the guard takes over the request.
+        self.requested = !ready;
+        if ready {
+            Poll::Ready(PauseGuard { executor })
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Pause {
+    fn drop(&mut self) {
+        if self.requested {
+            resume(self.executor);
+        }
+    }
+}
+
+/// Keeps an executor paused, returned by [`Pause`]. The executor resumes polling tasks when all
+/// its guards are dropped.
+#[must_use = "the executor resumes when the guard is dropped"]
+pub struct PauseGuard {
+    executor: &'static SyncExecutor,
+}
+
+impl Drop for PauseGuard {
+    fn drop(&mut self) {
+        resume(self.executor);
+    }
+}
+
+fn resume(executor: &'static SyncExecutor) {
+    let resumed = critical_section::with(|cs| {
+        let mut inner = executor.pause.inner.borrow_ref_mut(cs);
+        inner.requests -= 1;
+        inner.requests == 0
+    });
+    // Tasks woken while paused are still queued.
+    if resumed {
+        executor.pender.pend();
+    }
+}
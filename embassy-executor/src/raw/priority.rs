@@ -0,0 +1,198 @@
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+use super::util::SyncUnsafeCell;
+use super::TaskRef;
+
+/// Sort key of a task in the run queue: tasks with a lower key are polled first.
+///
+/// With the `fair-scheduling` feature, deprioritized tasks are polled after the others, whatever
+/// their priority.
+type SortKey = (bool, u8, u64);
+
+/// Scheduling metadata of a task, deciding the order in which ready tasks are polled.
+pub(crate) struct TaskPriority {
+    inner: Mutex<Cell<Inner>>,
+    /// Only accessed by the executor polling the task.
+    queued: SyncUnsafeCell<Queued>,
+}
+
+#[derive(Clone, Copy)]
+struct Queued {
+    /// Key snapshotted when the task was taken from the run queue.
+    key: SortKey,
+    /// Batch of the run queue in which the task was last polled.
+    batch: u32,
+}
+
+#[derive(Clone, Copy)]
+struct Inner {
+    priority: u8,
+    deadline: u64,
+}
+
+impl Inner {
+    const DEFAULT: Self = Self {
+        priority: 0,
+        deadline: u64::MAX,
+    };
+}
+
+impl TaskPriority {
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(Cell::new(Inner::DEFAULT)),
+            queued: SyncUnsafeCell::new(Queued {
+                key: (false, 0, 0),
+                batch: 0,
+            }),
+        }
+    }
+
+    /// Reset the priority and deadline when the task storage is spawned again.
+    pub fn reset(&self) {
+        critical_section::with(|cs| self.inner.borrow(cs).set(Inner::DEFAULT))
+    }
+
+    pub fn set_priority(&self, priority: u8) {
+        critical_section::with(|cs| {
+            let cell = self.inner.borrow(cs);
+            cell.set(Inner { priority, ..cell.get() });
+        })
+    }
+
+    pub fn set_deadline(&self, deadline: u64) {
+        critical_section::with(|cs| {
+            let cell = self.inner.borrow(cs);
+            cell.set(Inner { deadline, ..cell.get() });
+        })
+    }
+
+    pub fn priority(&self) -> u8 {
+        critical_section::with(|cs| self.inner.borrow(cs).get().priority)
+    }
+
+    pub fn deadline(&self) -> u64 {
+        critical_section::with(|cs| self.inner.borrow(cs).get().deadline)
+    }
+
+    /// Record that the task is polled in `batch` of its run queue.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the executor polling the task.
+    pub unsafe fn set_polled(&self, batch: u32) {
+        let queued = self.queued.get();
+        self.queued.set(Queued { batch, ..queued });
+    }
+
+    /// Whether the task was polled in `batch` of its run queue.
+    ///
+    /// # Safety
+    ///
+    /// Must only be called by the executor polling the task.
+    pub unsafe fn polled_in(&self, batch: u32) -> bool {
+        self.queued.get().batch == batch
+    }
+}
+
+// Snapshot the sort key of a task taken from the run queue, in a critical section of its own.
+unsafe fn snapshot_key(task: TaskRef) {
+    #[cfg(feature = "fair-scheduling")]
+    let deprioritized = super::fairness::deprioritized(task);
+    #[cfg(not(feature = "fair-scheduling"))]
+    let deprioritized = false;
+
+    let priority = &task.header().priority;
+    let inner = critical_section::with(|cs| priority.inner.borrow(cs).get());
+    let queued = priority.queued.get();
+    priority.queued.set(Queued {
+        key: (deprioritized, u8::MAX - inner.priority, inner.deadline),
+        ..queued
+    });
+}
+
+unsafe fn key(task: TaskRef) -> SortKey {
+    task.header().priority.queued.get().key
+}
+
+unsafe fn next(task: TaskRef) -> Option<TaskRef> {
+    task.header().run_queue_item.next()
+}
+
+unsafe fn set_next(task: TaskRef, next: Option<TaskRef>) {
+    task.header().run_queue_item.set_next(next)
+}
+
+/// Sort a batch of tasks taken from the run queue by priority, then by deadline.
+///
+/// Tasks with the same key are kept in the order of the batch, so without priorities and deadlines
+/// the tasks are polled as they would be without sorting. The keys are snapshotted first, so that
+/// sorting doesn't need a critical section.
+///
+/// # Safety
+///
+/// There must be no concurrent accesses to the `next` pointers of the tasks in the batch.
+pub(crate) unsafe fn sort(batch: Option<TaskRef>) -> Option<TaskRef> {
+    let mut len = 0;
+    let mut cur = batch;
+    while let Some(task) = cur {
+        snapshot_key(task);
+        len += 1;
+        cur = next(task);
+    }
+    merge_sort(batch, len)
+}
+
+unsafe fn merge_sort(list: Option<TaskRef>, len: usize) -> Option<TaskRef> {
+    if len <= 1 {
+        return list;
+    }
+
+    // Split the list after its first half.
+    let half = len / 2;
+    let mut tail = unwrap!(list);
+    for _ in 1..half {
+        tail = unwrap!(next(tail));
+    }
+    let second = next(tail);
+    set_next(tail, None);
+
+    merge(merge_sort(list, half), merge_sort(second, len - half))
+}
+
+/// Merge two sorted lists of tasks, taking the tasks of `a` first among tasks with the same key.
+///
+/// # Safety
+///
+/// There must be no concurrent accesses to the `next` pointers of the tasks in the lists, and their
+/// keys must have been snapshotted by [`sort`].
+pub(crate) unsafe fn merge(mut a: Option<TaskRef>, mut b: Option<TaskRef>) -> Option<TaskRef> {
+    let mut head: Option<TaskRef> = None;
+    let mut tail: Option<TaskRef> = None;
+    loop {
+        let task = match (a, b) {
+            (Some(x), Some(y)) if key(y) < key(x) => {
+                b = next(y);
+                y
+            }
+            (Some(x), _) => {
+                a = next(x);
+                x
+            }
+            (None, Some(y)) => {
+                b = next(y);
+                y
+            }
+            (None, None) => break,
+        };
+        // The `next` pointer of the last task is left as is, which is `None` at the end of a list.
+        match tail {
+            Some(tail) => set_next(tail, Some(task)),
+            None => head = Some(task),
+        }
+        tail = Some(task);
+    }
+    head
+}
@@ -1,7 +1,9 @@
 use core::ptr;
 use core::ptr::NonNull;
-use core::sync::atomic::{AtomicPtr, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
 
+#[cfg(feature = "scheduler-priority")]
+use super::priority;
 use super::{TaskHeader, TaskRef};
 use crate::raw::util::SyncUnsafeCell;
 
@@ -15,6 +17,22 @@ impl RunQueueItem {
             next: SyncUnsafeCell::new(None),
         }
     }
+
+    /// # Safety
+    ///
+    /// There must be no concurrent accesses to `next`.
+    #[cfg(feature = "scheduler-priority")]
+    pub(crate) unsafe fn next(&self) -> Option<TaskRef> {
+        self.next.get()
+    }
+
+    /// # Safety
+    ///
+    /// There must be no concurrent accesses to `next`.
+    #[cfg(feature = "scheduler-priority")]
+    pub(crate) unsafe fn set_next(&self, next: Option<TaskRef>) {
+        self.next.set(next)
+    }
 }
 
 /// Atomic task queue using a very, very simple lock-free linked-list queue:
@@ -30,12 +48,19 @@ impl RunQueueItem {
 /// by waking its own waker) can't prevent other tasks from running.
 pub(crate) struct RunQueue {
     head: AtomicPtr<TaskHeader>,
+    polling: AtomicBool,
+    /// Incremented by each `dequeue_all`, only accessed by the executor.
+    #[cfg(feature = "scheduler-priority")]
+    batch: SyncUnsafeCell<u32>,
 }
 
 impl RunQueue {
     pub const fn new() -> Self {
         Self {
             head: AtomicPtr::new(ptr::null_mut()),
+            polling: AtomicBool::new(false),
+            #[cfg(feature = "scheduler-priority")]
+            batch: SyncUnsafeCell::new(0),
         }
     }
 
@@ -64,15 +89,55 @@ impl RunQueue {
         was_empty
     }
 
+    /// Returns true if the queue is empty.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.head.load(Ordering::SeqCst).is_null()
+    }
+
+    /// Set while the executor polls the tasks taken from the queue.
+    pub(crate) fn set_polling(&self, polling: bool) {
+        self.polling.store(polling, Ordering::SeqCst);
+    }
+
+    /// Returns true if the queue is empty and the executor isn't polling tasks.
+    pub(crate) fn is_idle(&self) -> bool {
+        !self.polling.load(Ordering::SeqCst) && self.is_empty()
+    }
+
+    // Atomically empty the queue, returning its tasks.
+    fn take(&self) -> Option<TaskRef> {
+        let ptr = self.head.swap(ptr::null_mut(), Ordering::AcqRel);
+
+        // safety: the pointer is either null or valid
+        unsafe { NonNull::new(ptr).map(|ptr| TaskRef::from_ptr(ptr.as_ptr())) }
+    }
+
     /// Empty the queue, then call `on_task` for each task that was in the queue.
     /// NOTE: It is OK for `on_task` to enqueue more tasks. In this case they're left in the queue
     /// and will be processed by the *next* call to `dequeue_all`, *not* the current one.
+    ///
+    /// With the `scheduler-priority` feature, tasks enqueued in the meantime are instead polled by
+    /// the current call, in order of priority with the remaining tasks, unless they were already
+    /// polled by it.
     pub(crate) fn dequeue_all(&self, on_task: impl Fn(TaskRef)) {
-        // Atomically empty the queue.
-        let ptr = self.head.swap(ptr::null_mut(), Ordering::AcqRel);
+        let mut next = self.take();
 
-        // safety: the pointer is either null or valid
-        let mut next = unsafe { NonNull::new(ptr).map(|ptr| TaskRef::from_ptr(ptr.as_ptr())) };
+        // Poll the highest priority tasks first.
+        #[cfg(feature = "scheduler-priority")]
+        let batch = unsafe {
+            let batch = self.batch.get().wrapping_add(1);
+            self.batch.set(batch);
+            next = priority::sort(next);
+            batch
+        };
+        #[cfg(feature = "scheduler-priority")]
+        let mut deferred = None;
+
+        // Poll the tasks that yielded or used up their poll budget last.
+        #[cfg(all(feature = "fair-scheduling", not(feature = "scheduler-priority")))]
+        {
+            next = unsafe { deprioritize(next) };
+        }
 
         // Iterate the linked list of tasks that were previously in the queue.
         while let Some(task) = next {
@@ -81,7 +146,94 @@ impl RunQueue {
             // safety: there are no concurrent accesses to `next`
             next = unsafe { task.header().run_queue_item.next.get() };
 
+            #[cfg(feature = "scheduler-priority")]
+            unsafe {
+                task.header().priority.set_polled(batch)
+            };
+
             on_task(task);
+
+            // Pick up the tasks woken in the meantime, to poll the highest priority one next.
+            #[cfg(feature = "scheduler-priority")]
+            if !self.is_empty() {
+                next = unsafe { self.join_woken(next, batch, &mut deferred) };
+            }
+        }
+
+        // Tasks woken again after being polled are left for the next call.
+        #[cfg(feature = "scheduler-priority")]
+        while let Some(task) = deferred {
+            // safety: there are no concurrent accesses to `next`, and the task isn't in the queue
+            unsafe {
+                deferred = task.header().run_queue_item.next.get();
+                self.enqueue(task);
+            }
+        }
+    }
+
+    /// Take the tasks from the queue, and merge the ones not polled in `batch` yet into `remaining`.
+    /// The others are added to `deferred`.
+    ///
+    /// # Safety
+    ///
+    /// There must be no concurrent accesses to the `next` pointers of the tasks in `remaining` and
+    /// `deferred`, and their keys must have been snapshotted.
+    #[cfg(feature = "scheduler-priority")]
+    unsafe fn join_woken(
+        &self,
+        remaining: Option<TaskRef>,
+        batch: u32,
+        deferred: &mut Option<TaskRef>,
+    ) -> Option<TaskRef> {
+        let mut woken = self.take();
+        let mut fresh = None;
+        while let Some(task) = woken {
+            let item = &task.header().run_queue_item;
+            woken = item.next.get();
+            if task.header().priority.polled_in(batch) {
+                item.next.set(*deferred);
+                *deferred = Some(task);
+            } else {
+                item.next.set(fresh);
+                fresh = Some(task);
+            }
+        }
+        priority::merge(remaining, priority::sort(fresh))
+    }
+}
+
+/// Move the tasks of a batch that must be deprioritized to its end, keeping the order of the tasks.
+///
+/// # Safety
+///
+/// There must be no concurrent accesses to the `next` pointers of the tasks in the batch.
+#[cfg(all(feature = "fair-scheduling", not(feature = "scheduler-priority")))]
+unsafe fn deprioritize(mut batch: Option<TaskRef>) -> Option<TaskRef> {
+    // Heads and tails of the lists of tasks polled first and last.
+    let mut first: (Option<TaskRef>, Option<TaskRef>) = (None, None);
+    let mut last: (Option<TaskRef>, Option<TaskRef>) = (None, None);
+    while let Some(task) = batch {
+        let item = &task.header().run_queue_item;
+        batch = item.next.get();
+        item.next.set(None);
+
+        let list = if super::fairness::deprioritized(task) {
+            &mut last
+        } else {
+            &mut first
+        };
+        match list.1 {
+            Some(tail) => tail.header().run_queue_item.next.set(Some(task)),
+            None => list.0 = Some(task),
+        }
+        list.1 = Some(task);
+    }
+
+    match first.1 {
+        Some(tail) => {
+            tail.header().run_queue_item.next.set(last.0);
+            first.0
         }
+        None => last.0,
     }
 }
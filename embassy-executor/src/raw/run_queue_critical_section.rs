@@ -2,6 +2,8 @@ use core::cell::Cell;
 
 use critical_section::{CriticalSection, Mutex};
 
+#[cfg(feature = "scheduler-priority")]
+use super::priority;
 use super::TaskRef;
 
 pub(crate) struct RunQueueItem {
@@ -14,6 +16,22 @@ impl RunQueueItem {
             next: Mutex::new(Cell::new(None)),
         }
     }
+
+    /// # Safety
+    ///
+    /// There must be no concurrent accesses to `next`.
+    #[cfg(feature = "scheduler-priority")]
+    pub(crate) unsafe fn next(&self) -> Option<TaskRef> {
+        self.next.borrow(CriticalSection::new()).get()
+    }
+
+    /// # Safety
+    ///
+    /// There must be no concurrent accesses to `next`.
+    #[cfg(feature = "scheduler-priority")]
+    pub(crate) unsafe fn set_next(&self, next: Option<TaskRef>) {
+        self.next.borrow(CriticalSection::new()).set(next)
+    }
 }
 
 /// Atomic task queue using a very, very simple lock-free linked-list queue:
@@ -29,12 +47,19 @@ impl RunQueueItem {
 /// by waking its own waker) can't prevent other tasks from running.
 pub(crate) struct RunQueue {
     head: Mutex<Cell<Option<TaskRef>>>,
+    polling: Mutex<Cell<bool>>,
+    /// Incremented by each `dequeue_all`.
+    #[cfg(feature = "scheduler-priority")]
+    batch: Mutex<Cell<u32>>,
 }
 
 impl RunQueue {
     pub const fn new() -> Self {
         Self {
             head: Mutex::new(Cell::new(None)),
+            polling: Mutex::new(Cell::new(false)),
+            #[cfg(feature = "scheduler-priority")]
+            batch: Mutex::new(Cell::new(0)),
         }
     }
 
@@ -53,12 +78,56 @@ impl RunQueue {
         })
     }
 
+    /// Returns true if the queue is empty.
+    pub(crate) fn is_empty(&self) -> bool {
+        critical_section::with(|cs| self.head.borrow(cs).get().is_none())
+    }
+
+    /// Set while the executor polls the tasks taken from the queue.
+    pub(crate) fn set_polling(&self, polling: bool) {
+        critical_section::with(|cs| self.polling.borrow(cs).set(polling));
+    }
+
+    /// Returns true if the queue is empty and the executor isn't polling tasks.
+    pub(crate) fn is_idle(&self) -> bool {
+        !critical_section::with(|cs| self.polling.borrow(cs).get()) && self.is_empty()
+    }
+
+    // Atomically empty the queue, returning its tasks.
+    fn take(&self) -> Option<TaskRef> {
+        critical_section::with(|cs| self.head.borrow(cs).take())
+    }
+
     /// Empty the queue, then call `on_task` for each task that was in the queue.
     /// NOTE: It is OK for `on_task` to enqueue more tasks. In this case they're left in the queue
     /// and will be processed by the *next* call to `dequeue_all`, *not* the current one.
+    ///
+    /// With the `scheduler-priority` feature, tasks enqueued in the meantime are instead polled by
+    /// the current call, in order of priority with the remaining tasks, unless they were already
+    /// polled by it.
     pub(crate) fn dequeue_all(&self, on_task: impl Fn(TaskRef)) {
-        // Atomically empty the queue.
-        let mut next = critical_section::with(|cs| self.head.borrow(cs).take());
+        let mut next = self.take();
+
+        // Poll the highest priority tasks first.
+        #[cfg(feature = "scheduler-priority")]
+        let batch = critical_section::with(|cs| {
+            let batch = self.batch.borrow(cs);
+            batch.set(batch.get().wrapping_add(1));
+            batch.get()
+        });
+        #[cfg(feature = "scheduler-priority")]
+        {
+            // safety: we know if the task is enqueued, no one else will touch the `next` pointer.
+            next = unsafe { priority::sort(next) };
+        }
+        #[cfg(feature = "scheduler-priority")]
+        let mut deferred = None;
+
+        // Poll the tasks that yielded or used up their poll budget last.
+        #[cfg(all(feature = "fair-scheduling", not(feature = "scheduler-priority")))]
+        {
+            next = deprioritize(next);
+        }
 
         // Iterate the linked list of tasks that were previously in the queue.
         while let Some(task) = next {
@@ -69,7 +138,89 @@ impl RunQueue {
             let cs = unsafe { CriticalSection::new() };
             next = task.header().run_queue_item.next.borrow(cs).get();
 
+            // safety: only the executor accesses the priority bookkeeping.
+            #[cfg(feature = "scheduler-priority")]
+            unsafe {
+                task.header().priority.set_polled(batch)
+            };
+
             on_task(task);
+
+            // Pick up the tasks woken in the meantime, to poll the highest priority one next.
+            #[cfg(feature = "scheduler-priority")]
+            if !self.is_empty() {
+                next = self.join_woken(next, batch, &mut deferred);
+            }
+        }
+
+        // Tasks woken again after being polled are left for the next call.
+        #[cfg(feature = "scheduler-priority")]
+        while let Some(task) = deferred {
+            let cs = unsafe { CriticalSection::new() };
+            deferred = task.header().run_queue_item.next.borrow(cs).get();
+            // safety: the task was taken from the queue, so it isn't in it.
+            unsafe { self.enqueue(task) };
+        }
+    }
+
+    /// Take the tasks from the queue, and merge the ones not polled in `batch` yet into `remaining`.
+    /// The others are added to `deferred`.
+    #[cfg(feature = "scheduler-priority")]
+    fn join_woken(&self, remaining: Option<TaskRef>, batch: u32, deferred: &mut Option<TaskRef>) -> Option<TaskRef> {
+        // safety: we know if the task is enqueued, no one else will touch the `next` pointer.
+        let cs = unsafe { CriticalSection::new() };
+
+        let mut woken = self.take();
+        let mut fresh = None;
+        while let Some(task) = woken {
+            let next = task.header().run_queue_item.next.borrow(cs);
+            woken = next.get();
+            // safety: only the executor accesses the priority bookkeeping.
+            if unsafe { task.header().priority.polled_in(batch) } {
+                next.set(*deferred);
+                *deferred = Some(task);
+            } else {
+                next.set(fresh);
+                fresh = Some(task);
+            }
+        }
+        // safety: the tasks were taken from the queue, so no one else touches their `next` pointers.
+        unsafe { priority::merge(remaining, priority::sort(fresh)) }
+    }
+}
+
+/// Move the tasks of a batch that must be deprioritized to its end, keeping the order of the tasks.
+#[cfg(all(feature = "fair-scheduling", not(feature = "scheduler-priority")))]
+fn deprioritize(mut batch: Option<TaskRef>) -> Option<TaskRef> {
+    // Deciding needs critical sections of its own, so the `next` pointers are accessed without one.
+    // safety: we know if the task is enqueued, no one else will touch the `next` pointer.
+    let cs = unsafe { CriticalSection::new() };
+
+    // Heads and tails of the lists of tasks polled first and last.
+    let mut first: (Option<TaskRef>, Option<TaskRef>) = (None, None);
+    let mut last: (Option<TaskRef>, Option<TaskRef>) = (None, None);
+    while let Some(task) = batch {
+        let next = task.header().run_queue_item.next.borrow(cs);
+        batch = next.get();
+        next.set(None);
+
+        let list = if super::fairness::deprioritized(task) {
+            &mut last
+        } else {
+            &mut first
+        };
+        match list.1 {
+            Some(tail) => tail.header().run_queue_item.next.borrow(cs).set(Some(task)),
+            None => list.0 = Some(task),
+        }
+        list.1 = Some(task);
+    }
+
+    match first.1 {
+        Some(tail) => {
+            tail.header().run_queue_item.next.borrow(cs).set(last.0);
+            first.0
         }
+        None => last.0,
     }
 }
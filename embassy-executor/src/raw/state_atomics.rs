@@ -56,6 +56,20 @@ impl State {
         state & STATE_SPAWNED != 0
     }
 
+    /// Return whether the task is spawned.
+    #[cfg(feature = "task-introspection")]
+    #[inline(always)]
+    pub fn is_spawned(&self) -> bool {
+        self.state.load(Ordering::Acquire) & STATE_SPAWNED != 0
+    }
+
+    /// Return whether the task is run-queued.
+    #[cfg(feature = "task-introspection")]
+    #[inline(always)]
+    pub fn is_run_queued(&self) -> bool {
+        self.state.load(Ordering::Acquire) & STATE_RUN_QUEUED != 0
+    }
+
     /// Mark the task as timer-queued. Return whether it was newly queued (i.e. not queued before)
     #[cfg(feature = "integrated-timers")]
     #[inline(always)]
@@ -87,6 +87,20 @@ impl State {
         r
     }
 
+    /// Return whether the task is spawned.
+    #[cfg(feature = "task-introspection")]
+    #[inline(always)]
+    pub fn is_spawned(&self) -> bool {
+        self.spawned.load(Ordering::Relaxed)
+    }
+
+    /// Return whether the task is run-queued.
+    #[cfg(feature = "task-introspection")]
+    #[inline(always)]
+    pub fn is_run_queued(&self) -> bool {
+        self.run_queued.load(Ordering::Relaxed)
+    }
+
     /// Mark the task as timer-queued. Return whether it was newly queued (i.e. not queued before)
     #[cfg(feature = "integrated-timers")]
     #[inline(always)]
@@ -0,0 +1,94 @@
+use core::cell::Cell;
+
+use critical_section::Mutex;
+
+/// Time spent by an executor polling tasks, returned by [`Executor::stats()`](super::Executor::stats).
+///
+/// Times are in ticks of the `embassy-time` driver, so they can be converted with
+/// `embassy_time::Duration::from_ticks()`. They are counted from the first time the executor
+/// polled its tasks, and keep accumulating: subtract two snapshots taken with
+/// [`since()`](Self::since) to measure the duty cycle over a period of time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ExecutorStats {
+    /// Ticks spent polling tasks.
+    pub busy_ticks: u64,
+    /// Ticks elapsed in total.
+    pub total_ticks: u64,
+    /// Number of times the executor woke up to poll tasks.
+    pub polls: u32,
+}
+
+impl ExecutorStats {
+    /// Ticks spent waiting for tasks to be woken, during which the executor sleeps.
+    pub fn idle_ticks(&self) -> u64 {
+        self.total_ticks - self.busy_ticks
+    }
+
+    /// Time spent polling tasks, in percent of the time elapsed.
+    pub fn busy_percent(&self) -> f32 {
+        if self.total_ticks == 0 {
+            return 0.0;
+        }
+        self.busy_ticks as f32 * 100.0 / self.total_ticks as f32
+    }
+
+    /// Statistics over the time elapsed since the `earlier` snapshot.
+    pub fn since(&self, earlier: &ExecutorStats) -> ExecutorStats {
+        ExecutorStats {
+            busy_ticks: self.busy_ticks - earlier.busy_ticks,
+            total_ticks: self.total_ticks - earlier.total_ticks,
+            polls: self.polls.wrapping_sub(earlier.polls),
+        }
+    }
+}
+
+/// Accumulates the time an executor spends polling.
+pub(crate) struct StatsRecorder {
+    inner: Mutex<Cell<Inner>>,
+}
+
+#[derive(Clone, Copy)]
+struct Inner {
+    /// Time of the first poll, if any.
+    start: Option<u64>,
+    busy_ticks: u64,
+    polls: u32,
+}
+
+impl StatsRecorder {
+    pub const fn new() -> Self {
+        Self {
+            inner: Mutex::new(Cell::new(Inner {
+                start: None,
+                busy_ticks: 0,
+                polls: 0,
+            })),
+        }
+    }
+
+    /// Record a poll of the executor, which lasted from `start` to `end`.
+    pub fn record_poll(&self, start: u64, end: u64) {
+        critical_section::with(|cs| {
+            let cell = self.inner.borrow(cs);
+            let mut inner = cell.get();
+            inner.start.get_or_insert(start);
+            inner.busy_ticks += end - start;
+            inner.polls = inner.polls.wrapping_add(1);
+            cell.set(inner);
+        })
+    }
+
+    pub fn get(&self) -> ExecutorStats {
+        let inner = critical_section::with(|cs| self.inner.borrow(cs).get());
+        let total_ticks = match inner.start {
+            Some(start) => embassy_time_driver::now() - start,
+            None => 0,
+        };
+        ExecutorStats {
+            busy_ticks: inner.busy_ticks,
+            total_ticks,
+            polls: inner.polls,
+        }
+    }
+}
@@ -1,8 +1,12 @@
-use core::cmp::min;
+use core::cmp::{max, min};
 
 use super::TaskRef;
 use crate::raw::util::SyncUnsafeCell;
 
+/// Window in ticks within which expiring timers share a single alarm.
+const COALESCE_WINDOW: u64 =
+    (embassy_time_queue_driver::COALESCE_WINDOW_US * embassy_time_driver::TICK_HZ).div_ceil(1_000_000);
+
 pub(crate) struct TimerQueueItem {
     next: SyncUnsafeCell<Option<TaskRef>>,
 }
@@ -44,6 +48,19 @@ impl TimerQueue {
             res = min(res, expires);
             expires != u64::MAX
         });
+
+        // Delay the alarm to the latest task expiring within the coalescing window, so that they
+        // are all woken by the same alarm.
+        if COALESCE_WINDOW != 0 && res != u64::MAX {
+            let limit = res.saturating_add(COALESCE_WINDOW);
+            self.retain(|p| {
+                let expires = p.header().expires_at.get();
+                if expires <= limit {
+                    res = max(res, expires);
+                }
+                true
+            });
+        }
         res
     }
 
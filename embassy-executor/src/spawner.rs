@@ -1,8 +1,11 @@
+use core::cell::Cell;
 use core::future::poll_fn;
 use core::marker::PhantomData;
 use core::mem;
 use core::task::Poll;
 
+use critical_section::Mutex;
+
 use super::raw;
 
 /// Token to spawn a newly-created task in an executor.
@@ -42,6 +45,48 @@ impl<S> SpawnToken<S> {
     }
 }
 
+#[cfg(feature = "scheduler-priority")]
+impl<S> SpawnToken<S> {
+    /// Set the priority of the task. Among the tasks ready to run, those with a higher priority are
+    /// polled first.
+    ///
+    /// See [`TaskRef::set_priority()`](raw::TaskRef::set_priority).
+    pub fn with_priority(self, priority: u8) -> Self {
+        if let Some(task) = self.raw_task {
+            task.set_priority(priority);
+        }
+        self
+    }
+
+    /// Set the deadline of the task, in `embassy-time` ticks. Among the ready tasks with the same
+    /// priority, those with an earlier deadline are polled first.
+    ///
+    /// See [`TaskRef::set_deadline()`](raw::TaskRef::set_deadline).
+    pub fn with_deadline(self, deadline: u64) -> Self {
+        if let Some(task) = self.raw_task {
+            task.set_deadline(deadline);
+        }
+        self
+    }
+}
+
+impl<S> SpawnToken<S> {
+    /// Set the name of the task, reported when iterating over the tasks of an executor.
+    ///
+    /// Not covered by semver guarantees. DO NOT call this directly. Intended to be used
+    /// by the Embassy macros ONLY.
+    #[doc(hidden)]
+    pub fn __with_name(self, name: &'static str) -> Self {
+        #[cfg(feature = "task-introspection")]
+        if let Some(task) = self.raw_task {
+            task.header().introspection.set_name(name);
+        }
+        #[cfg(not(feature = "task-introspection"))]
+        let _ = name;
+        self
+    }
+}
+
 impl<S> Drop for SpawnToken<S> {
     fn drop(&mut self) {
         // TODO deallocate the task instead.
@@ -61,6 +106,55 @@ pub enum SpawnError {
     Busy,
 }
 
+/// Handle to a spawned task, to abort it or wait for it to finish.
+///
+/// Obtained with [`Spawner::spawn_with_handle()`] or [`SendSpawner::spawn_with_handle()`]. The handle
+/// refers to the instance of the task it was returned for: once that instance has finished, the
+/// handle has no effect on the task storage being spawned again.
+///
+/// Dropping the handle doesn't abort the task.
+#[cfg(feature = "task-handles")]
+pub struct TaskHandle {
+    task: raw::TaskRef,
+    generation: u32,
+}
+
+#[cfg(feature = "task-handles")]
+impl TaskHandle {
+    fn new(task: raw::TaskRef) -> Self {
+        Self {
+            task,
+            generation: task.header().control.generation(),
+        }
+    }
+
+    /// Abort the task.
+    ///
+    /// The task's future is dropped by its executor the next time the task would be polled, that is
+    /// at the `.await` point where it is currently waiting. Use [`join()`](Self::join) to wait for
+    /// that to happen.
+    ///
+    /// Does nothing if the task has already finished.
+    pub fn abort(&self) {
+        if self.task.header().control.abort(self.generation) {
+            raw::wake_task(self.task);
+        }
+    }
+
+    /// Check whether the task has finished, either by completing or by being aborted.
+    pub fn is_finished(&self) -> bool {
+        self.task.header().control.generation() != self.generation
+    }
+
+    /// Wait for the task to finish, either by completing or by being aborted.
+    ///
+    /// Only one task may wait on a given task at a time: if several do, only the last one
+    /// to poll is woken.
+    pub async fn join(&self) {
+        poll_fn(|cx| self.task.header().control.poll_join(self.generation, cx)).await
+    }
+}
+
 /// Handle to spawn tasks into an executor.
 ///
 /// This Spawner can spawn any task (Send and non-Send ones), but it can
@@ -115,6 +209,40 @@ impl Spawner {
         }
     }
 
+    /// Spawn a task into an executor, returning a [`TaskHandle`] to abort it or wait for it to finish.
+    ///
+    /// You obtain the `token` by calling a task function (i.e. one marked with `#[embassy_executor::task]`).
+    #[cfg(feature = "task-handles")]
+    pub fn spawn_with_handle<S>(&self, token: SpawnToken<S>) -> Result<TaskHandle, SpawnError> {
+        let task = token.raw_task;
+        mem::forget(token);
+
+        match task {
+            Some(task) => {
+                let handle = TaskHandle::new(task);
+                unsafe { self.executor.spawn(task) };
+                Ok(handle)
+            }
+            None => Err(SpawnError::Busy),
+        }
+    }
+
+    /// Spawn a task into an executor, waiting for a task to finish when there are too many
+    /// instances of it running.
+    ///
+    /// `token` is called to obtain the `SpawnToken`, by calling a task function, and is called again
+    /// each time a task finishes until the task can be spawned, since the arguments passed to a task
+    /// function are dropped when spawning fails.
+    pub async fn spawn_when_available<S>(&self, mut token: impl FnMut() -> SpawnToken<S>) {
+        loop {
+            let finished = raw::available::finished_count();
+            if self.spawn(token()).is_ok() {
+                return;
+            }
+            raw::available::wait_task_finished(finished).await;
+        }
+    }
+
     // Used by the `embassy_executor_macros::main!` macro to throw an error when spawn
     // fails. This is here to allow conditional use of `defmt::unwrap!`
     // without introducing a `defmt` feature in the `embassy_executor_macros` package,
@@ -128,6 +256,18 @@ impl Spawner {
         unwrap!(self.spawn(token));
     }
 
+    /// Iterate over the tasks running in the executor.
+    #[cfg(feature = "task-introspection")]
+    pub fn tasks(&self) -> raw::Tasks {
+        raw::Tasks::new(&self.executor.inner)
+    }
+
+    /// Get the time the executor has spent polling tasks and sleeping.
+    #[cfg(feature = "executor-stats")]
+    pub fn stats(&self) -> raw::ExecutorStats {
+        self.executor.stats()
+    }
+
     /// Convert this Spawner to a SendSpawner. This allows you to send the
     /// spawner to other threads, but the spawner loses the ability to spawn
     /// non-Send tasks.
@@ -186,6 +326,38 @@ impl SendSpawner {
         }
     }
 
+    /// Spawn a task into an executor, returning a [`TaskHandle`] to abort it or wait for it to finish.
+    ///
+    /// You obtain the `token` by calling a task function (i.e. one marked with `#[embassy_executor::task]`).
+    #[cfg(feature = "task-handles")]
+    pub fn spawn_with_handle<S: Send>(&self, token: SpawnToken<S>) -> Result<TaskHandle, SpawnError> {
+        let header = token.raw_task;
+        mem::forget(token);
+
+        match header {
+            Some(header) => {
+                let handle = TaskHandle::new(header);
+                unsafe { self.executor.spawn(header) };
+                Ok(handle)
+            }
+            None => Err(SpawnError::Busy),
+        }
+    }
+
+    /// Spawn a task into an executor, waiting for a task to finish when there are too many
+    /// instances of it running.
+    ///
+    /// See [`Spawner::spawn_when_available()`] for details.
+    pub async fn spawn_when_available<S: Send>(&self, mut token: impl FnMut() -> SpawnToken<S>) {
+        loop {
+            let finished = raw::available::finished_count();
+            if self.spawn(token()).is_ok() {
+                return;
+            }
+            raw::available::wait_task_finished(finished).await;
+        }
+    }
+
     /// Spawn a task into an executor, panicking on failure.
     ///
     /// # Panics
@@ -194,4 +366,88 @@ impl SendSpawner {
     pub fn must_spawn<S: Send>(&self, token: SpawnToken<S>) {
         unwrap!(self.spawn(token));
     }
+
+    /// Iterate over the tasks running in the executor.
+    #[cfg(feature = "task-introspection")]
+    pub fn tasks(&self) -> raw::Tasks {
+        raw::Tasks::new(self.executor)
+    }
+
+    /// Get the time the executor has spent polling tasks and sleeping.
+    #[cfg(feature = "executor-stats")]
+    pub fn stats(&self) -> raw::ExecutorStats {
+        self.executor.stats()
+    }
+
+    /// Pause the executor, to run code that requires no task of the executor to be polling, such
+    /// as entering a low-power STOP or STANDBY mode, or programming the flash the code runs from.
+    ///
+    /// The executor stops polling tasks from the first poll of the returned future, which completes
+    /// once the task the executor may be polling at that time returns. Tasks woken in the meantime
+    /// are polled once all the returned [`PauseGuard`](raw::PauseGuard)s are dropped.
+    ///
+    /// This must be awaited outside of the executor, for example in a task of an interrupt executor
+    /// or of another thread. Awaiting it from a task of the executor deadlocks, as the task waits
+    /// forever for itself to return.
+    #[cfg(feature = "executor-pause")]
+    pub fn pause(&self) -> raw::Pause {
+        raw::Pause::new(self.executor)
+    }
+
+    /// Check whether the executor is idle, that is, whether it isn't polling tasks and has no woken
+    /// tasks waiting to be polled.
+    ///
+    /// This is only a hint, since tasks may be woken right after this returns.
+    pub fn is_idle(&self) -> bool {
+        self.executor.is_idle()
+    }
+}
+
+/// Distributes `Send` tasks between several executors, such as one per core.
+///
+/// Each task is spawned into the next executor that is idle, as reported by
+/// [`SendSpawner::is_idle`], going round-robin through the executors, or into the next executor if
+/// none of them is idle. Spawning wakes the chosen executor like any other cross-thread wakeup, so
+/// executors sleeping on another core are woken up.
+///
+/// Tasks keep running on the executor they were spawned into, so this balances compute-heavy work
+/// best when it is split into many short tasks.
+pub struct SendSpawnerPool<const N: usize> {
+    spawners: [SendSpawner; N],
+    next: Mutex<Cell<usize>>,
+}
+
+impl<const N: usize> SendSpawnerPool<N> {
+    /// Create a pool distributing tasks between the executors of `spawners`.
+    pub const fn new(spawners: [SendSpawner; N]) -> Self {
+        Self {
+            spawners,
+            next: Mutex::new(Cell::new(0)),
+        }
+    }
+
+    /// Spawn a task into one of the executors of the pool.
+    ///
+    /// You obtain the `token` by calling a task function (i.e. one marked with `#[embassy_executor::task]`).
+    pub fn spawn<S: Send>(&self, token: SpawnToken<S>) -> Result<(), SpawnError> {
+        let index = critical_section::with(|cs| {
+            let next = self.next.borrow(cs);
+            let index = (0..N)
+                .map(|i| (next.get() + i) % N)
+                .find(|&i| self.spawners[i].is_idle())
+                .unwrap_or(next.get());
+            next.set((index + 1) % N);
+            index
+        });
+        self.spawners[index].spawn(token)
+    }
+
+    /// Spawn a task into one of the executors of the pool, panicking on failure.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the spawning fails.
+    pub fn must_spawn<S: Send>(&self, token: SpawnToken<S>) {
+        unwrap!(self.spawn(token));
+    }
 }
@@ -0,0 +1,107 @@
+use core::future::{poll_fn, Future};
+use core::pin::{pin, Pin};
+use core::task::{Context, Poll};
+
+/// Delay before restarting a supervised task, doubling with each consecutive restart.
+///
+/// Delays are in ticks of the `embassy-time` driver, so they can be obtained with
+/// `embassy_time::Duration::as_ticks()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Backoff {
+    /// Delay before the first restart.
+    pub initial_ticks: u64,
+    /// Maximum delay between restarts.
+    ///
+    /// A task that ran for longer than this is considered to have recovered, so its next restart
+    /// waits for `initial_ticks` again.
+    pub max_ticks: u64,
+}
+
+impl Backoff {
+    /// Create a new `Backoff`, starting at `initial_ticks` and doubling up to `max_ticks`.
+    pub const fn new(initial_ticks: u64, max_ticks: u64) -> Self {
+        Self {
+            initial_ticks,
+            max_ticks,
+        }
+    }
+}
+
+/// Run a future, creating and running it again each time it completes.
+///
+/// This is meant to be the body of a long-running task, such as a driver task, which should never
+/// complete: instead of the task silently dying, the failure is logged and the future is restarted
+/// after the `backoff` delay.
+///
+/// ```rust,ignore
+/// #[embassy_executor::task]
+/// async fn radio_task(radio: &'static Radio) {
+///     let backoff = Backoff::new(Duration::from_millis(10).as_ticks(), Duration::from_secs(5).as_ticks());
+///     embassy_executor::supervise(backoff, || radio.run()).await
+/// }
+/// ```
+///
+/// With the `arch-std` feature, a panic of the future is caught and handled like its completion:
+/// the future is dropped and restarted. Other architectures usually abort on panic, so the panic
+/// handler is called as usual.
+///
+/// The delay uses the `embassy-time` timer queue, so either the `integrated-timers` feature or
+/// another timer queue implementation, such as the `generic-queue` feature of `embassy-time`,
+/// must be enabled.
+pub async fn supervise<F, Fut>(backoff: Backoff, mut f: F) -> !
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut delay = backoff.initial_ticks;
+    let mut restarts: u32 = 0;
+    loop {
+        let start = embassy_time_driver::now();
+        let panicked = run(f()).await;
+        let end = embassy_time_driver::now();
+
+        restarts = restarts.wrapping_add(1);
+        let exit = if panicked { "panicked" } else { "completed" };
+        warn!("supervised task {}, restarting it (restart {})", exit, restarts);
+
+        if end - start > backoff.max_ticks {
+            delay = backoff.initial_ticks;
+        }
+        Timer { at: end + delay }.await;
+        delay = delay.saturating_mul(2).min(backoff.max_ticks);
+    }
+}
+
+/// Run `future` to completion, returning whether it panicked.
+async fn run(future: impl Future<Output = ()>) -> bool {
+    let mut future = pin!(future);
+    poll_fn(|cx| {
+        #[cfg(feature = "arch-std")]
+        match std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+            Ok(poll) => poll.map(|()| false),
+            Err(_) => Poll::Ready(true),
+        }
+        #[cfg(not(feature = "arch-std"))]
+        future.as_mut().poll(cx).map(|()| false)
+    })
+    .await
+}
+
+/// Future completing once the `embassy-time` driver reaches `at`.
+struct Timer {
+    at: u64,
+}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if embassy_time_driver::now() >= self.at {
+            Poll::Ready(())
+        } else {
+            embassy_time_queue_driver::schedule_wake(self.at, cx.waker());
+            Poll::Pending
+        }
+    }
+}
@@ -6,7 +6,7 @@ use std::sync::{Arc, Mutex};
 use std::task::Poll;
 
 use embassy_executor::raw::Executor;
-use embassy_executor::task;
+use embassy_executor::{task, SendSpawner, SendSpawnerPool, Spawner};
 
 #[export_name = "__pender"]
 fn __pender(context: *mut ()) {
@@ -36,6 +36,129 @@ impl Trace {
     }
 }
 
+#[cfg(any(feature = "executor-stats", feature = "supervisor", feature = "integrated-timers"))]
+mod time_driver {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+
+    use embassy_time_driver::{AlarmHandle, Driver};
+
+    pub static NOW: AtomicU64 = AtomicU64::new(0);
+
+    /// Held by the tests setting the time, since they run in parallel.
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    pub fn lock() -> MutexGuard<'static, ()> {
+        LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    struct MockDriver;
+
+    impl Driver for MockDriver {
+        fn now(&self) -> u64 {
+            NOW.load(Ordering::Relaxed)
+        }
+
+        // Alarms are only set by the executors with `integrated-timers`, which the tests poll by hand.
+        unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+            Some(AlarmHandle::new(0))
+        }
+
+        fn set_alarm_callback(&self, _alarm: AlarmHandle, _callback: fn(*mut ()), _ctx: *mut ()) {}
+
+        fn set_alarm(&self, _alarm: AlarmHandle, timestamp: u64) -> bool {
+            timestamp > self.now()
+        }
+    }
+
+    embassy_time_driver::time_driver_impl!(static DRIVER: MockDriver = MockDriver);
+
+    // With `integrated-timers`, the executor provides the timer queue.
+    #[cfg(all(feature = "supervisor", not(feature = "integrated-timers")))]
+    pub mod queue {
+        use std::sync::Mutex;
+        use std::task::Waker;
+        use std::vec::Vec;
+
+        use embassy_time_queue_driver::TimerQueue;
+
+        /// Times at which wakeups were scheduled, and their wakers.
+        pub static SCHEDULED: Mutex<Vec<(u64, Waker)>> = Mutex::new(Vec::new());
+
+        struct MockQueue;
+
+        impl TimerQueue for MockQueue {
+            fn schedule_wake(&'static self, at: u64, waker: &Waker) {
+                SCHEDULED.lock().unwrap().push((at, waker.clone()));
+            }
+        }
+
+        embassy_time_queue_driver::timer_queue_impl!(static QUEUE: MockQueue = MockQueue);
+    }
+}
+
+#[cfg(feature = "rtos-trace")]
+mod trace_backend {
+    use std::cell::Cell;
+    use std::sync::Mutex;
+
+    use rtos_trace::{RtosTrace, TaskInfo};
+
+    static EVENTS: Mutex<Vec<(u32, &'static str)>> = Mutex::new(Vec::new());
+    static NAMES: Mutex<Vec<(u32, &'static str)>> = Mutex::new(Vec::new());
+
+    std::thread_local! {
+        static CURRENT_TASK: Cell<u32> = Cell::new(0);
+    }
+
+    fn push(id: u32, event: &'static str) {
+        EVENTS.lock().unwrap().push((id, event))
+    }
+
+    /// Events of the task named `name`.
+    pub fn events(name: &str) -> Vec<&'static str> {
+        let names = NAMES.lock().unwrap();
+        let (id, _) = names.iter().find(|(_, n)| *n == name).unwrap();
+        let events = EVENTS.lock().unwrap();
+        events.iter().filter(|(i, _)| i == id).map(|(_, e)| *e).collect()
+    }
+
+    struct Backend;
+
+    rtos_trace::global_trace! {Backend}
+
+    impl RtosTrace for Backend {
+        fn task_new(id: u32) {
+            push(id, "new")
+        }
+        fn task_send_info(id: u32, info: TaskInfo) {
+            NAMES.lock().unwrap().push((id, info.name));
+            push(id, "info")
+        }
+        fn task_terminate(id: u32) {
+            push(id, "terminate")
+        }
+        fn task_exec_begin(id: u32) {
+            CURRENT_TASK.with(|c| c.set(id));
+            push(id, "exec begin")
+        }
+        fn task_exec_end() {
+            push(CURRENT_TASK.with(|c| c.get()), "exec end")
+        }
+        fn task_ready_begin(id: u32) {
+            push(id, "ready")
+        }
+        fn task_ready_end(_id: u32) {}
+        fn system_idle() {}
+        fn isr_enter() {}
+        fn isr_exit() {}
+        fn isr_exit_to_scheduler() {}
+        fn marker(_id: u32) {}
+        fn marker_begin(_id: u32) {}
+        fn marker_end(_id: u32) {}
+    }
+}
+
 fn setup() -> (&'static Executor, Trace) {
     let trace = Trace::new();
     let context = Box::leak(Box::new(trace.clone())) as *mut _ as *mut ();
@@ -72,6 +195,38 @@ fn executor_task() {
     )
 }
 
+#[test]
+fn executor_custom_pender() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use embassy_executor::raw::Pender;
+
+    struct CountingPender(AtomicUsize);
+
+    impl Pender for CountingPender {
+        fn pend(&self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[task]
+    async fn task1(trace: Trace) {
+        trace.push("poll task1")
+    }
+
+    static PENDER: CountingPender = CountingPender(AtomicUsize::new(0));
+
+    let trace = Trace::new();
+    let executor = &*Box::leak(Box::new(Executor::new_with_pender(&PENDER)));
+    executor.spawner().spawn(task1(trace.clone())).unwrap();
+    assert_eq!(1, PENDER.0.load(Ordering::Relaxed));
+
+    unsafe { executor.poll() };
+
+    // The global pender isn't called.
+    assert_eq!(trace.get(), &["poll task1"]);
+}
+
 #[test]
 fn executor_task_self_wake() {
     #[task]
@@ -136,6 +291,64 @@ fn executor_task_self_wake_twice() {
     )
 }
 
+#[test]
+fn executor_spawn_when_available() {
+    #[task]
+    async fn task1(trace: Trace) {
+        let mut woken = false;
+        poll_fn(|cx| {
+            trace.push("poll task1");
+            if woken {
+                return Poll::Ready(());
+            }
+            woken = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+
+    #[task]
+    async fn task2(trace: Trace) {
+        let spawner = Spawner::for_current_executor().await;
+        spawner.spawn_when_available(|| task1(trace.clone())).await;
+        trace.push("task1 spawned")
+    }
+
+    let (executor, trace) = setup();
+    executor.spawner().spawn(task1(trace.clone())).unwrap();
+    executor.spawner().spawn(task2(trace.clone())).unwrap();
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+
+    // Tasks finishing in other tests can wake task2 too, so pends are left out.
+    let trace: Vec<_> = trace.get().into_iter().filter(|e| *e != "pend").collect();
+    #[cfg(not(feature = "scheduler-priority"))]
+    assert_eq!(
+        trace,
+        &[
+            "poll task1",    // task2 can't spawn task1 yet
+            "poll task1",    // task1 finishes, waking task2
+            "task1 spawned", //
+            "poll task1",    //
+        ]
+    );
+    // Woken tasks join the tasks being polled, so task2 spawns task1 right after it finishes.
+    #[cfg(feature = "scheduler-priority")]
+    assert_eq!(
+        trace,
+        &[
+            "poll task1",    // task2 can't spawn task1 yet
+            "poll task1",    // task1 finishes, waking task2
+            "task1 spawned", //
+            "poll task1",    //
+            "poll task1",    //
+        ]
+    );
+}
+
 #[test]
 fn executor_task_cfg_args() {
     // simulate cfg'ing away argument c
@@ -149,3 +362,649 @@ fn executor_task_cfg_args() {
         let (_, _, _) = (a, b, c);
     }
 }
+
+#[test]
+fn send_spawner_pool_prefers_idle_executors() {
+    #[task(pool_size = 4)]
+    async fn task1(trace: Trace, name: &'static str) {
+        trace.push(name)
+    }
+
+    let (executor0, _) = setup();
+    let (executor1, _) = setup();
+    let pool = SendSpawnerPool::new([executor0.spawner().make_send(), executor1.spawner().make_send()]);
+    let trace = Trace::new();
+
+    // Spawning pends an executor, so each one gets a task before any gets a second one.
+    pool.spawn(task1(trace.clone(), "task1 on executor0")).unwrap();
+    pool.spawn(task1(trace.clone(), "task2 on executor1")).unwrap();
+    pool.spawn(task1(trace.clone(), "task3 on executor0")).unwrap();
+
+    // Once polled, executor1 is idle again, while executor0 is still busy.
+    unsafe { executor1.poll() };
+    assert!(executor1.spawner().make_send().is_idle());
+    assert!(!executor0.spawner().make_send().is_idle());
+    pool.spawn(task1(trace.clone(), "task4 on executor1")).unwrap();
+
+    unsafe { executor0.poll() };
+    unsafe { executor1.poll() };
+
+    assert_eq!(
+        trace.get(),
+        &[
+            "task2 on executor1",
+            "task3 on executor0",
+            "task1 on executor0",
+            "task4 on executor1",
+        ]
+    )
+}
+
+#[test]
+fn send_spawner_busy_while_polling() {
+    // Checks whether its own executor is idle, once the run queue was drained.
+    #[task]
+    async fn task1(trace: Trace, spawner: SendSpawner) {
+        if spawner.is_idle() {
+            trace.push("idle")
+        } else {
+            trace.push("busy")
+        }
+    }
+
+    let (executor, trace) = setup();
+    let spawner = executor.spawner().make_send();
+    executor.spawner().spawn(task1(trace.clone(), spawner)).unwrap();
+    assert!(!spawner.is_idle());
+
+    unsafe { executor.poll() };
+    assert!(spawner.is_idle());
+    assert_eq!(trace.get(), &["pend", "busy"]);
+}
+
+#[cfg(feature = "arch-std")]
+#[test]
+fn executor_spawn_blocking() {
+    use std::time::Duration;
+
+    #[task]
+    async fn task1(spawner: Spawner, trace: Trace) {
+        let value = spawner
+            .spawn_blocking(|| {
+                std::thread::sleep(Duration::from_millis(10));
+                42
+            })
+            .await;
+        assert_eq!(42, value);
+        trace.push("done")
+    }
+
+    let (executor, trace) = setup();
+    executor
+        .spawner()
+        .spawn(task1(executor.spawner(), trace.clone()))
+        .unwrap();
+    unsafe { executor.poll() };
+    assert_eq!(trace.get(), &["pend"]);
+
+    // The task is woken once the closure returns on its thread.
+    for _ in 0..1000 {
+        if trace.get().len() > 1 {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    unsafe { executor.poll() };
+    assert_eq!(trace.get(), &["pend", "pend", "done"]);
+}
+
+#[cfg(feature = "task-handles")]
+#[test]
+fn executor_task_abort() {
+    use embassy_executor::TaskHandle;
+
+    struct DropGuard(Trace);
+
+    impl Drop for DropGuard {
+        fn drop(&mut self) {
+            self.0.push("drop task1")
+        }
+    }
+
+    #[task]
+    async fn task1(trace: Trace) {
+        let _guard = DropGuard(trace.clone());
+        poll_fn(|_| {
+            trace.push("poll task1");
+            Poll::<()>::Pending
+        })
+        .await
+    }
+
+    #[task]
+    async fn task2(trace: Trace, handle: TaskHandle) {
+        handle.abort();
+        handle.join().await;
+        trace.push("task1 joined")
+    }
+
+    let (executor, trace) = setup();
+    let handle = executor.spawner().spawn_with_handle(task1(trace.clone())).unwrap();
+    unsafe { executor.poll() };
+    assert!(!handle.is_finished());
+
+    let joiner = executor
+        .spawner()
+        .spawn_with_handle(task2(trace.clone(), handle))
+        .unwrap();
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+    assert!(joiner.is_finished());
+
+    // Handles to a finished task don't affect the next instance spawned in its storage.
+    let handle = executor.spawner().spawn_with_handle(task1(trace.clone())).unwrap();
+    joiner.abort();
+    unsafe { executor.poll() };
+    assert!(!handle.is_finished());
+
+    assert_eq!(
+        trace.get(),
+        &[
+            "pend",         // spawning a task pends the executor
+            "poll task1",   //
+            "pend",         // spawning task2
+            "pend",         // task2 aborts task1, which wakes it
+            "drop task1",   // task1 is dropped instead of polled
+            "pend",         // task1 finishing wakes task2
+            "task1 joined", //
+            "pend",         // spawning task1 again
+            "poll task1",   //
+        ]
+    )
+}
+
+#[cfg(feature = "task-introspection")]
+#[test]
+fn executor_task_introspection() {
+    use embassy_executor::raw::{TaskInfo, TaskState};
+
+    #[task]
+    async fn task1() {
+        poll_fn(|_| Poll::<()>::Pending).await
+    }
+
+    #[task(name = "second task")]
+    async fn task2() {}
+
+    let (executor, _) = setup();
+    let (other_executor, _) = setup();
+    executor.spawner().spawn(task1()).unwrap();
+    other_executor.spawner().spawn(task2()).unwrap();
+
+    let tasks: Vec<TaskInfo> = executor.tasks().collect();
+    assert_eq!(
+        tasks,
+        &[TaskInfo {
+            name: Some("task1"),
+            state: TaskState::Ready,
+            spawn_count: 1,
+        }]
+    );
+
+    unsafe { executor.poll() };
+    assert_eq!(TaskState::Waiting, executor.tasks().next().unwrap().state);
+
+    // Finished tasks are not reported, and spawning them again is counted.
+    assert_eq!(1, other_executor.tasks().count());
+    unsafe { other_executor.poll() };
+    assert_eq!(0, other_executor.tasks().count());
+    other_executor.spawner().spawn(task2()).unwrap();
+    let task = other_executor.tasks().next().unwrap();
+    assert_eq!((Some("second task"), 2), (task.name, task.spawn_count));
+}
+
+#[cfg(feature = "scheduler-priority")]
+#[test]
+fn executor_task_priority() {
+    #[task(pool_size = 4)]
+    async fn task1(trace: Trace, name: &'static str) {
+        trace.push(name)
+    }
+
+    let (executor, trace) = setup();
+    let spawner = executor.spawner();
+    spawner.spawn(task1(trace.clone(), "low")).unwrap();
+    spawner
+        .spawn(
+            task1(trace.clone(), "late deadline")
+                .with_priority(1)
+                .with_deadline(200),
+        )
+        .unwrap();
+    spawner
+        .spawn(
+            task1(trace.clone(), "early deadline")
+                .with_priority(1)
+                .with_deadline(100),
+        )
+        .unwrap();
+    spawner.spawn(task1(trace.clone(), "high").with_priority(2)).unwrap();
+
+    unsafe { executor.poll() };
+    assert_eq!(
+        trace.get(),
+        &[
+            "pend", // spawning the first task pends the executor
+            "high",
+            "early deadline",
+            "late deadline",
+            "low",
+        ]
+    )
+}
+
+#[cfg(feature = "scheduler-priority")]
+#[test]
+fn executor_task_priority_woken_while_polling() {
+    #[task(pool_size = 2)]
+    async fn low(spawner: Spawner, trace: Trace, name: &'static str) {
+        trace.push(name);
+        if name == "low 1" {
+            spawner.spawn(high(trace.clone()).with_priority(1)).unwrap();
+        }
+    }
+
+    #[task]
+    async fn high(trace: Trace) {
+        trace.push("high")
+    }
+
+    let (executor, trace) = setup();
+    let spawner = executor.spawner();
+    // Tasks spawned together are polled in the reverse order.
+    spawner.spawn(low(spawner, trace.clone(), "low 2")).unwrap();
+    spawner.spawn(low(spawner, trace.clone(), "low 1")).unwrap();
+
+    unsafe { executor.poll() };
+    assert_eq!(
+        trace.get(),
+        &[
+            "pend",  // spawning the first task pends the executor
+            "low 1", //
+            "pend",  // spawning `high` pends the executor
+            "high",  // polled before the rest of the tasks taken from the queue
+            "low 2", //
+        ]
+    )
+}
+
+#[cfg(feature = "fair-scheduling")]
+#[test]
+fn executor_fair_scheduling() {
+    use embassy_executor::yield_now;
+
+    #[task]
+    async fn io(trace: Trace) {
+        trace.push("io")
+    }
+
+    // Wakes itself 10 times, waking `io` just before waking itself on the 9th poll, once it used up
+    // its poll budget of 8.
+    #[task]
+    async fn hog(spawner: Spawner, trace: Trace) {
+        let mut polls = 0;
+        poll_fn(|cx| {
+            polls += 1;
+            trace.push("hog");
+            if polls == 9 {
+                spawner.spawn(io(trace.clone())).unwrap();
+            }
+            if polls == 10 {
+                return Poll::Ready(());
+            }
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+
+    #[task]
+    async fn other(trace: Trace) {
+        trace.push("other")
+    }
+
+    #[task]
+    async fn yielder(spawner: Spawner, trace: Trace) {
+        spawner.spawn(other(trace.clone())).unwrap();
+        yield_now().await;
+        trace.push("yielder")
+    }
+
+    let (executor, trace) = setup();
+    executor
+        .spawner()
+        .spawn(hog(executor.spawner(), trace.clone()))
+        .unwrap();
+    for _ in 0..10 {
+        unsafe { executor.poll() };
+    }
+    executor
+        .spawner()
+        .spawn(yielder(executor.spawner(), trace.clone()))
+        .unwrap();
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+
+    // Without fairness, the task woken last would be polled first.
+    let trace: Vec<_> = trace.get().into_iter().filter(|&t| t != "pend").collect();
+    assert_eq!(&trace[8..], &["hog", "io", "hog", "other", "yielder"]);
+}
+
+#[cfg(feature = "executor-pause")]
+#[test]
+fn executor_pause() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::task::{Context, Wake, Waker};
+
+    use embassy_executor::raw::Pause;
+
+    struct FlagWaker(AtomicBool);
+
+    impl Wake for FlagWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, Ordering::Relaxed)
+        }
+    }
+
+    static PAUSE: Mutex<Option<Pin<Box<Pause>>>> = Mutex::new(None);
+
+    // Pauses its own executor while being polled, which takes effect once it returns.
+    #[task]
+    async fn pauser(trace: Trace, waker: Waker) {
+        trace.push("poll pauser");
+        let mut pause = PAUSE.lock().unwrap();
+        let poll = pause.as_mut().unwrap().as_mut().poll(&mut Context::from_waker(&waker));
+        assert!(poll.is_pending());
+    }
+
+    #[task]
+    async fn task1(trace: Trace) {
+        trace.push("poll task1")
+    }
+
+    let (executor, trace) = setup();
+    let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+    let waker = Waker::from(flag.clone());
+    *PAUSE.lock().unwrap() = Some(Box::pin(executor.pause()));
+
+    executor.spawner().spawn(task1(trace.clone())).unwrap();
+    executor.spawner().spawn(pauser(trace.clone(), waker.clone())).unwrap();
+    unsafe { executor.poll() };
+
+    // task1 isn't polled, and the pause completes once the pauser returned.
+    assert!(flag.0.load(Ordering::Relaxed));
+    let mut pause = PAUSE.lock().unwrap().take().unwrap();
+    let Poll::Ready(guard) = pause.as_mut().poll(&mut Context::from_waker(&waker)) else {
+        panic!("executor not paused");
+    };
+    unsafe { executor.poll() };
+    assert_eq!(trace.get(), &["pend", "poll pauser"]);
+
+    // Resuming pends the executor.
+    drop(guard);
+    unsafe { executor.poll() };
+    assert_eq!(trace.get(), &["pend", "poll pauser", "pend", "poll task1"]);
+}
+
+#[cfg(feature = "executor-pause")]
+#[test]
+fn executor_pause_after_woken_task_finished() {
+    use std::future::Future;
+    use std::task::{Context, Wake, Waker};
+
+    struct NoopWaker;
+
+    impl Wake for NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    // Finishes after waking itself, which leaves it queued.
+    #[task]
+    async fn task1(trace: Trace) {
+        poll_fn(|cx| {
+            cx.waker().wake_by_ref();
+            Poll::Ready(())
+        })
+        .await;
+        trace.push("poll task1")
+    }
+
+    let (executor, trace) = setup();
+    executor.spawner().spawn(task1(trace.clone())).unwrap();
+    unsafe { executor.poll() };
+    // Only dequeues the finished task.
+    unsafe { executor.poll() };
+    assert_eq!(trace.get(), &["pend", "pend", "poll task1"]);
+
+    // The executor isn't polling a task, so it pauses right away.
+    let waker = Waker::from(Arc::new(NoopWaker));
+    let mut pause = Box::pin(executor.pause());
+    assert!(pause.as_mut().poll(&mut Context::from_waker(&waker)).is_ready());
+}
+
+#[cfg(feature = "task-wake-stats")]
+#[test]
+fn executor_task_wake_stats() {
+    use std::task::Waker;
+
+    use embassy_executor::raw::{WakeSource, WakeStats};
+
+    static WAKER: Mutex<Option<Waker>> = Mutex::new(None);
+
+    #[task]
+    async fn task1() {
+        poll_fn(|cx| {
+            *WAKER.lock().unwrap() = Some(cx.waker().clone());
+            Poll::<()>::Pending
+        })
+        .await
+    }
+
+    let (executor, _) = setup();
+    executor.spawner().spawn(task1()).unwrap();
+    unsafe { executor.poll() };
+
+    let (_, stats) = executor.tasks().with_wake_stats().next().unwrap();
+    assert_eq!((1, Some(WakeSource::Spawn)), (stats.polls, stats.last_wake));
+
+    // The second wakeup has no effect, since the task is already queued.
+    let waker = WAKER.lock().unwrap().clone().unwrap();
+    waker.wake_by_ref();
+    waker.wake_by_ref();
+    unsafe { executor.poll() };
+
+    let (info, stats) = executor.tasks().with_wake_stats().next().unwrap();
+    assert_eq!(Some("task1"), info.name);
+    assert_eq!(
+        WakeStats {
+            polls: 2,
+            waker_wakes: 2,
+            timer_wakes: 0,
+            redundant_wakes: 1,
+            last_wake: Some(WakeSource::Waker),
+        },
+        stats
+    );
+}
+
+#[cfg(feature = "executor-stats")]
+#[test]
+fn executor_stats() {
+    use std::sync::atomic::Ordering;
+
+    use embassy_executor::raw::ExecutorStats;
+    use time_driver::NOW;
+
+    let _lock = time_driver::lock();
+
+    // Each poll of the task takes 30 ticks.
+    #[task]
+    async fn task1() {
+        NOW.fetch_add(30, Ordering::Relaxed);
+    }
+
+    let (executor, _) = setup();
+    assert_eq!(ExecutorStats::default(), executor.stats());
+
+    NOW.store(100, Ordering::Relaxed);
+    executor.spawner().spawn(task1()).unwrap();
+    unsafe { executor.poll() };
+    NOW.store(200, Ordering::Relaxed);
+
+    let stats = executor.stats();
+    assert_eq!(
+        ExecutorStats {
+            busy_ticks: 30,
+            total_ticks: 100,
+            polls: 1,
+        },
+        stats
+    );
+    assert_eq!(70, stats.idle_ticks());
+    assert_eq!(30.0, stats.busy_percent());
+
+    executor.spawner().spawn(task1()).unwrap();
+    unsafe { executor.poll() };
+    NOW.store(400, Ordering::Relaxed);
+
+    let since = executor.stats().since(&stats);
+    assert_eq!((30, 200, 1), (since.busy_ticks, since.total_ticks, since.polls));
+}
+
+#[cfg(all(feature = "supervisor", not(feature = "integrated-timers")))]
+#[test]
+fn executor_supervisor() {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    use embassy_executor::{supervise, Backoff};
+    use time_driver::queue::SCHEDULED;
+    use time_driver::NOW;
+
+    // Ticks each run of the supervised future takes before completing.
+    static RUN_TICKS: AtomicU64 = AtomicU64::new(0);
+
+    #[task]
+    async fn task1(trace: Trace) {
+        supervise(Backoff::new(10, 30), || async {
+            trace.push("run");
+            NOW.fetch_add(RUN_TICKS.load(Ordering::Relaxed), Ordering::Relaxed);
+        })
+        .await
+    }
+
+    let _lock = time_driver::lock();
+    NOW.store(1000, Ordering::Relaxed);
+
+    let (executor, trace) = setup();
+    executor.spawner().spawn(task1(trace.clone())).unwrap();
+    unsafe { executor.poll() };
+
+    let mut restarts = Vec::new();
+    for run_ticks in [0, 0, 40, 0] {
+        let (at, waker) = SCHEDULED.lock().unwrap().pop().unwrap();
+        restarts.push(at);
+        RUN_TICKS.store(run_ticks, Ordering::Relaxed);
+        NOW.store(at, Ordering::Relaxed);
+        waker.wake();
+        unsafe { executor.poll() };
+    }
+
+    // The delay doubles up to the maximum, and is reset once the future ran for longer than it.
+    assert_eq!(restarts, &[1010, 1030, 1060, 1110]);
+    assert_eq!(5, trace.get().iter().filter(|&&t| t == "run").count());
+}
+
+#[cfg(feature = "integrated-timers")]
+#[test]
+fn executor_wake_task_at() {
+    use std::sync::atomic::Ordering;
+
+    use embassy_executor::raw::{task_from_waker, wake_task_at};
+    use time_driver::NOW;
+
+    // Schedules two timeouts on each poll, until the earliest one is reached.
+    #[task]
+    async fn task1(trace: Trace) {
+        poll_fn(|cx| {
+            trace.push("poll task1");
+            if NOW.load(Ordering::Relaxed) >= 1100 {
+                return Poll::Ready(());
+            }
+            let task = task_from_waker(cx.waker());
+            unsafe {
+                wake_task_at(task, 1200);
+                wake_task_at(task, 1100);
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    let _lock = time_driver::lock();
+    NOW.store(1000, Ordering::Relaxed);
+
+    let (executor, trace) = setup();
+    executor.spawner().spawn(task1(trace.clone())).unwrap();
+    unsafe { executor.poll() };
+    assert_eq!(Some(1100), unsafe { executor.next_expiration() });
+
+    // Not polled before the timeout.
+    NOW.store(1099, Ordering::Relaxed);
+    unsafe { executor.poll() };
+    NOW.store(1100, Ordering::Relaxed);
+    unsafe { executor.poll() };
+
+    assert_eq!(trace.get(), &["pend", "poll task1", "poll task1"]);
+    assert_eq!(None, unsafe { executor.next_expiration() });
+}
+
+#[cfg(all(feature = "rtos-trace", feature = "task-introspection"))]
+#[test]
+fn executor_rtos_trace() {
+    #[task(name = "traced task")]
+    async fn task1() {
+        let mut woken = false;
+        poll_fn(|cx| {
+            if woken {
+                return Poll::Ready(());
+            }
+            woken = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        })
+        .await
+    }
+
+    let (executor, _) = setup();
+    executor.spawner().spawn(task1()).unwrap();
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+
+    assert_eq!(
+        trace_backend::events("traced task"),
+        &[
+            "new",        // spawning a task sends its name and makes it ready
+            "info",       //
+            "ready",      //
+            "exec begin", //
+            "ready",      // task self-wakes
+            "exec end",   //
+            "exec begin", //
+            "terminate",  // task completes
+            "exec end",   //
+        ]
+    )
+}
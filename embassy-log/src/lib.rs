@@ -0,0 +1,111 @@
+#![no_std]
+#![doc = include_str!("../README.md")]
+#![warn(missing_docs)]
+
+#[cfg(all(feature = "defmt", feature = "log"))]
+compile_error!("You may not enable both `defmt` and `log` features.");
+
+#[cfg(feature = "time")]
+mod rate_limit;
+#[cfg(feature = "log")]
+mod ring;
+
+#[cfg(feature = "time")]
+pub use rate_limit::RateLimiter;
+#[cfg(feature = "log")]
+pub use ring::{RingLogger, MAX_LINE_LEN};
+
+#[doc(hidden)]
+pub mod __private {
+    #[cfg(feature = "log")]
+    pub use log;
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "defmt")]
+macro_rules! __log {
+    ($level:ident, $s:literal $(, $x:expr)* $(,)?) => {
+        ::defmt::$level!($s $(, $x)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(feature = "log")]
+macro_rules! __log {
+    ($level:ident, $s:literal $(, $x:expr)* $(,)?) => {
+        $crate::__private::log::$level!($s $(, $x)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+#[cfg(not(any(feature = "defmt", feature = "log")))]
+macro_rules! __log {
+    ($level:ident, $s:literal $(, $x:expr)* $(,)?) => {{
+        let _ = ($( & $x ),*);
+    }};
+}
+
+/// Log a message at trace level.
+#[macro_export]
+macro_rules! trace {
+    ($s:literal $(, $x:expr)* $(,)?) => {
+        $crate::__log!(trace, $s $(, $x)*)
+    };
+}
+
+/// Log a message at debug level.
+#[macro_export]
+macro_rules! debug {
+    ($s:literal $(, $x:expr)* $(,)?) => {
+        $crate::__log!(debug, $s $(, $x)*)
+    };
+}
+
+/// Log a message at info level.
+#[macro_export]
+macro_rules! info {
+    ($s:literal $(, $x:expr)* $(,)?) => {
+        $crate::__log!(info, $s $(, $x)*)
+    };
+}
+
+/// Log a message at warn level.
+#[macro_export]
+macro_rules! warn {
+    ($s:literal $(, $x:expr)* $(,)?) => {
+        $crate::__log!(warn, $s $(, $x)*)
+    };
+}
+
+/// Log a message at error level.
+#[macro_export]
+macro_rules! error {
+    ($s:literal $(, $x:expr)* $(,)?) => {
+        $crate::__log!(error, $s $(, $x)*)
+    };
+}
+
+/// Run a logging statement at most `max_per_second` times per second.
+///
+/// Each call site has its own limit. When messages were dropped, their number is logged at
+/// warn level before the next message that gets through.
+///
+/// ```rust,ignore
+/// embassy_log::ratelimit!(5, embassy_log::warn!("rx overrun on {}", channel));
+/// ```
+#[cfg(feature = "time")]
+#[macro_export]
+macro_rules! ratelimit {
+    ($max_per_second:expr, $($body:tt)*) => {{
+        static LIMITER: $crate::RateLimiter = $crate::RateLimiter::new($max_per_second);
+        if let Some(dropped) = LIMITER.check() {
+            if dropped > 0 {
+                $crate::warn!("{} messages dropped by rate limit", dropped);
+            }
+            $($body)*
+        }
+    }};
+}
@@ -0,0 +1,89 @@
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time::{Duration, Instant};
+
+#[derive(Copy, Clone)]
+struct State {
+    window_start: Option<Instant>,
+    passed: u32,
+    dropped: u32,
+}
+
+/// Limit on the number of events per second.
+///
+/// This is usually used through the [`ratelimit!`](crate::ratelimit) macro, which creates one
+/// limiter per call site.
+pub struct RateLimiter {
+    max_per_second: u32,
+    state: Mutex<CriticalSectionRawMutex, Cell<State>>,
+}
+
+impl RateLimiter {
+    /// Create a new limiter, allowing `max_per_second` events per second.
+    pub const fn new(max_per_second: u32) -> Self {
+        Self {
+            max_per_second,
+            state: Mutex::new(Cell::new(State {
+                window_start: None,
+                passed: 0,
+                dropped: 0,
+            })),
+        }
+    }
+
+    /// Check whether an event is allowed now.
+    ///
+    /// Returns `None` if the event must be dropped, or `Some` with the number of events dropped
+    /// since the last allowed one.
+    pub fn check(&self) -> Option<u32> {
+        let now = Instant::now();
+        self.state.lock(|state| {
+            let mut s = state.get();
+            let res = match s.window_start {
+                Some(start) if now - start < Duration::from_secs(1) && s.passed >= self.max_per_second => {
+                    s.dropped += 1;
+                    None
+                }
+                Some(start) if now - start < Duration::from_secs(1) => {
+                    s.passed += 1;
+                    Some(core::mem::take(&mut s.dropped))
+                }
+                _ => {
+                    s.window_start = Some(now);
+                    s.passed = 1;
+                    Some(core::mem::take(&mut s.dropped))
+                }
+            };
+            state.set(s);
+            res
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embassy_time::MockDriver;
+
+    use super::*;
+
+    #[test]
+    fn limits_per_second() {
+        let driver = MockDriver::get();
+        let limiter = RateLimiter::new(2);
+
+        assert_eq!(limiter.check(), Some(0));
+        assert_eq!(limiter.check(), Some(0));
+        assert_eq!(limiter.check(), None);
+        assert_eq!(limiter.check(), None);
+
+        driver.advance(Duration::from_millis(500));
+        assert_eq!(limiter.check(), None);
+
+        driver.advance(Duration::from_millis(500));
+        assert_eq!(limiter.check(), Some(3));
+        assert_eq!(limiter.check(), Some(0));
+        assert_eq!(limiter.check(), None);
+    }
+}
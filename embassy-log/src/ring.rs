@@ -0,0 +1,145 @@
+use core::cell::Cell;
+use core::fmt::Write as _;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::pipe::Pipe;
+use log::{Metadata, Record};
+
+/// Maximum length of a log line, longer lines are truncated.
+pub const MAX_LINE_LEN: usize = 128;
+
+/// `log` backend storing formatted lines in a RAM ring buffer of `N` bytes.
+///
+/// Lines are only stored whole: when the buffer doesn't have enough free space, the line is
+/// dropped and counted, so logging never blocks and never waits for the transport. The buffer
+/// is drained with [`read`](Self::read), and sent to wherever the logs should go.
+pub struct RingLogger<const N: usize> {
+    buffer: Pipe<CriticalSectionRawMutex, N>,
+    dropped: Mutex<CriticalSectionRawMutex, Cell<u32>>,
+}
+
+impl<const N: usize> RingLogger<N> {
+    /// Create a new logger.
+    pub const fn new() -> Self {
+        Self {
+            buffer: Pipe::new(),
+            dropped: Mutex::new(Cell::new(0)),
+        }
+    }
+
+    /// Read stored log data, waiting until some is available.
+    ///
+    /// Returns the number of bytes read. Lines may be split across reads.
+    pub async fn read(&self, buf: &mut [u8]) -> usize {
+        self.buffer.read(buf).await
+    }
+
+    /// Read stored log data, without waiting.
+    ///
+    /// Returns the number of bytes read, 0 if the buffer is empty.
+    pub fn try_read(&self, buf: &mut [u8]) -> usize {
+        self.buffer.try_read(buf).unwrap_or(0)
+    }
+
+    /// Get the number of lines dropped because the buffer was full, and reset it.
+    pub fn take_dropped(&self) -> u32 {
+        self.dropped.lock(|d| d.replace(0))
+    }
+
+    fn push(&self, line: &[u8]) {
+        let stored = self.buffer.free_capacity() >= line.len() && {
+            // The pipe can't write across the wraparound in one go, so this may take two writes.
+            let n = self.buffer.try_write(line).unwrap_or(0);
+            n == line.len() || self.buffer.try_write(&line[n..]).is_ok()
+        };
+        if !stored {
+            self.dropped.lock(|d| d.set(d.get().saturating_add(1)));
+        }
+    }
+}
+
+impl<const N: usize> Default for RingLogger<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> log::Log for RingLogger<N> {
+    fn enabled(&self, _metadata: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        let mut line = Line {
+            buf: [0; MAX_LINE_LEN],
+            len: 0,
+        };
+        let _ = write!(line, "{} {}", record.level(), record.args());
+        line.finish();
+        self.push(&line.buf[..line.len]);
+    }
+
+    fn flush(&self) {}
+}
+
+struct Line {
+    buf: [u8; MAX_LINE_LEN],
+    len: usize,
+}
+
+impl Line {
+    fn finish(&mut self) {
+        self.len = self.len.min(MAX_LINE_LEN - 2);
+        self.buf[self.len..self.len + 2].copy_from_slice(b"\r\n");
+        self.len += 2;
+    }
+}
+
+impl core::fmt::Write for Line {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        let n = s.len().min(MAX_LINE_LEN - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use log::{Level, Log};
+
+    use super::*;
+
+    fn log(logger: &impl Log, msg: &str) {
+        logger.log(
+            &Record::builder()
+                .level(Level::Info)
+                .args(format_args!("{}", msg))
+                .build(),
+        );
+    }
+
+    #[test]
+    fn stores_whole_lines() {
+        let logger = RingLogger::<32>::new();
+        let mut buf = [0; 32];
+
+        log(&logger, "hello");
+        log(&logger, "world");
+        assert_eq!(logger.try_read(&mut buf), 24);
+        assert_eq!(&buf[..24], b"INFO hello\r\nINFO world\r\n");
+
+        // Too long for the buffer, while the next one wraps around its end.
+        log(&logger, "this line is much too long");
+        log(&logger, "again");
+        assert_eq!(logger.take_dropped(), 1);
+        assert_eq!(logger.take_dropped(), 0);
+
+        let mut n = 0;
+        while let len @ 1.. = logger.try_read(&mut buf[n..]) {
+            n += len;
+        }
+        assert_eq!(&buf[..n], b"INFO again\r\n");
+    }
+}
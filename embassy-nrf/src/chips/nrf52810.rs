@@ -138,6 +138,10 @@ embassy_hal_internal::peripherals! {
 
     // Radio
     RADIO,
+
+    // EGU
+    EGU0,
+    EGU1,
 }
 
 impl_uarte!(UARTE0, UARTE0, UARTE0_UART0);
@@ -158,6 +162,9 @@ impl_qdec!(QDEC, QDEC, QDEC);
 
 impl_rng!(RNG, RNG, RNG);
 
+impl_egu!(EGU0, EGU0, SWI0_EGU0);
+impl_egu!(EGU1, EGU1, SWI1_EGU1);
+
 impl_timer!(TIMER0, TIMER0, TIMER0);
 impl_timer!(TIMER1, TIMER1, TIMER1);
 impl_timer!(TIMER2, TIMER2, TIMER2);
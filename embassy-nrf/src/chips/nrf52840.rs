@@ -168,6 +168,10 @@ embassy_hal_internal::peripherals! {
     // TEMP
     TEMP,
 
+    // Analog comparators
+    COMP,
+    LPCOMP,
+
     // PDM
     PDM,
 
@@ -176,6 +180,17 @@ embassy_hal_internal::peripherals! {
 
     // Radio
     RADIO,
+
+    // NFC tag
+    NFCT,
+
+    // EGU
+    EGU0,
+    EGU1,
+    EGU2,
+    EGU3,
+    EGU4,
+    EGU5,
 }
 
 impl_usb!(USBD, USBD, USBD);
@@ -203,6 +218,13 @@ impl_pwm!(PWM1, PWM1, PWM1);
 impl_pwm!(PWM2, PWM2, PWM2);
 impl_pwm!(PWM3, PWM3, PWM3);
 
+impl_egu!(EGU0, EGU0, SWI0_EGU0);
+impl_egu!(EGU1, EGU1, SWI1_EGU1);
+impl_egu!(EGU2, EGU2, SWI2_EGU2);
+impl_egu!(EGU3, EGU3, SWI3_EGU3);
+impl_egu!(EGU4, EGU4, SWI4_EGU4);
+impl_egu!(EGU5, EGU5, SWI5_EGU5);
+
 impl_timer!(TIMER0, TIMER0, TIMER0);
 impl_timer!(TIMER1, TIMER1, TIMER1);
 impl_timer!(TIMER2, TIMER2, TIMER2);
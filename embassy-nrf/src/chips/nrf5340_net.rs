@@ -251,6 +251,12 @@ embassy_hal_internal::peripherals! {
 
     // Radio
     RADIO,
+
+    // EGU
+    EGU0,
+
+    // IPC
+    IPC,
 }
 
 impl_uarte!(SERIAL0, UARTE0, SERIAL0);
@@ -259,6 +265,8 @@ impl_spis!(SERIAL0, SPIS0, SERIAL0);
 impl_twim!(SERIAL0, TWIM0, SERIAL0);
 impl_twis!(SERIAL0, TWIS0, SERIAL0);
 
+impl_egu!(EGU0, EGU0, EGU0);
+
 impl_timer!(TIMER0, TIMER0, TIMER0);
 impl_timer!(TIMER1, TIMER1, TIMER1);
 impl_timer!(TIMER2, TIMER2, TIMER2);
@@ -0,0 +1,325 @@
+//! Comparator (COMP) driver.
+//!
+//! COMP and LPCOMP share the same peripheral address space and interrupt,
+//! so only one of [`Comp`] and [`Lpcomp`](crate::lpcomp::Lpcomp) can be used at a time.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::interrupt::InterruptExt;
+use crate::peripherals::COMP;
+use crate::ppi::{Event, Task};
+use crate::saadc::{Input, InputChannel};
+use crate::{interrupt, pac, Peripheral};
+
+/// Interrupt handler.
+pub struct InterruptHandler {
+    _private: (),
+}
+
+impl interrupt::typelevel::Handler<interrupt::typelevel::COMP_LPCOMP> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        let r = unsafe { &*pac::COMP::PTR };
+        r.intenclr.write(|w| {
+            w.up().clear();
+            w.down().clear();
+            w.cross().clear();
+            w
+        });
+        WAKER.wake();
+    }
+}
+
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+enum Wait {
+    Up,
+    Down,
+    Cross,
+}
+
+/// Speed and power mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Speed {
+    /// Low power, slow response time.
+    Low,
+    /// Medium power, medium response time.
+    Normal,
+    /// High power, fast response time.
+    High,
+}
+
+/// Reference voltage for single-ended mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Reference {
+    /// 1.2V internal reference.
+    Int1V2,
+    /// 1.8V internal reference.
+    Int1V8,
+    /// 2.4V internal reference.
+    Int2V4,
+    /// VDD.
+    Vdd,
+}
+
+/// Direction of a threshold crossing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Crossing {
+    /// The input went from below to above the threshold.
+    Up,
+    /// The input went from above to below the threshold.
+    Down,
+}
+
+/// COMP config.
+#[non_exhaustive]
+pub struct Config {
+    /// Speed and power mode.
+    pub speed: Speed,
+    /// Reference voltage, only used in single-ended mode.
+    pub reference: Reference,
+    /// Upward crossing threshold in single-ended mode, in steps of 1/64 of the reference (0..=63).
+    ///
+    /// The input has to rise above `(threshold_up + 1) / 64 * reference` for an upward crossing.
+    pub threshold_up: u8,
+    /// Downward crossing threshold in single-ended mode, in steps of 1/64 of the reference (0..=63).
+    ///
+    /// The input has to fall below `(threshold_down + 1) / 64 * reference` for a downward crossing.
+    /// Setting this lower than `threshold_up` provides hysteresis.
+    pub threshold_down: u8,
+    /// Enable 50mV hysteresis, only used in differential mode.
+    pub hysteresis: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            speed: Speed::Normal,
+            reference: Reference::Vdd,
+            threshold_up: 31,
+            threshold_down: 31,
+            hysteresis: false,
+        }
+    }
+}
+
+/// Comparator driver.
+pub struct Comp<'d> {
+    _peri: PeripheralRef<'d, COMP>,
+}
+
+impl<'d> Comp<'d> {
+    /// Create a new comparator comparing `input` against a fraction of the configured reference.
+    ///
+    /// Panics if `input` is not an analog input pin, or if a threshold is out of range.
+    pub fn new_single_ended(
+        peri: impl Peripheral<P = COMP> + 'd,
+        _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::COMP_LPCOMP, InterruptHandler> + 'd,
+        input: impl Peripheral<P = impl Input> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(peri, input);
+
+        assert!(config.threshold_up < 64 && config.threshold_down < 64);
+
+        let r = Self::regs();
+        r.psel.write(|w| w.psel().bits(analog_input(&*input)));
+        r.refsel.write(|w| match config.reference {
+            Reference::Int1V2 => w.refsel().int1v2(),
+            Reference::Int1V8 => w.refsel().int1v8(),
+            Reference::Int2V4 => w.refsel().int2v4(),
+            Reference::Vdd => w.refsel().vdd(),
+        });
+        r.th.write(|w| unsafe {
+            w.thup().bits(config.threshold_up);
+            w.thdown().bits(config.threshold_down);
+            w
+        });
+        r.mode.write(|w| {
+            Self::set_speed(w, config.speed);
+            w.main().se()
+        });
+
+        Self::start(peri)
+    }
+
+    /// Create a new comparator comparing `p_input` against `n_input`.
+    ///
+    /// Panics if either input is not an analog input pin.
+    pub fn new_differential(
+        peri: impl Peripheral<P = COMP> + 'd,
+        _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::COMP_LPCOMP, InterruptHandler> + 'd,
+        p_input: impl Peripheral<P = impl Input> + 'd,
+        n_input: impl Peripheral<P = impl Input> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(peri, p_input, n_input);
+
+        let r = Self::regs();
+        r.psel.write(|w| w.psel().bits(analog_input(&*p_input)));
+        r.extrefsel.write(|w| w.extrefsel().bits(analog_input(&*n_input)));
+        r.hyst.write(|w| w.hyst().bit(config.hysteresis));
+        r.mode.write(|w| {
+            Self::set_speed(w, config.speed);
+            w.main().diff()
+        });
+
+        Self::start(peri)
+    }
+
+    fn set_speed(w: &mut pac::comp::mode::W, speed: Speed) {
+        match speed {
+            Speed::Low => w.sp().low(),
+            Speed::Normal => w.sp().normal(),
+            Speed::High => w.sp().high(),
+        };
+    }
+
+    fn start(peri: PeripheralRef<'d, COMP>) -> Self {
+        let r = Self::regs();
+
+        r.enable.write(|w| w.enable().enabled());
+
+        // The comparator is ready within microseconds, so just spin for it.
+        r.events_ready.reset();
+        r.tasks_start.write(|w| unsafe { w.bits(1) });
+        while r.events_ready.read().bits() == 0 {}
+        r.events_ready.reset();
+
+        interrupt::COMP_LPCOMP.unpend();
+        unsafe { interrupt::COMP_LPCOMP.enable() };
+
+        Self { _peri: peri }
+    }
+
+    /// Sample the comparator output, returning `true` if the input is above the threshold.
+    pub fn sample(&mut self) -> bool {
+        let r = Self::regs();
+        r.tasks_sample.write(|w| unsafe { w.bits(1) });
+        r.result.read().result().is_above()
+    }
+
+    /// Wait for the input to cross the threshold in either direction.
+    ///
+    /// The CPU can sleep while waiting, and is woken up by the crossing.
+    pub async fn wait_for_cross(&mut self) -> Crossing {
+        let r = Self::regs();
+        self.wait(Wait::Cross).await;
+
+        let up = r.events_up.read().bits() != 0;
+        let down = r.events_down.read().bits() != 0;
+        r.events_up.reset();
+        r.events_down.reset();
+
+        match (up, down) {
+            (true, false) => Crossing::Up,
+            (false, true) => Crossing::Down,
+            // Crossed back and forth, report the direction of the latest crossing.
+            _ => match self.sample() {
+                true => Crossing::Up,
+                false => Crossing::Down,
+            },
+        }
+    }
+
+    /// Wait for the input to rise above the threshold.
+    pub async fn wait_for_up(&mut self) {
+        self.wait(Wait::Up).await
+    }
+
+    /// Wait for the input to fall below the threshold.
+    pub async fn wait_for_down(&mut self) {
+        self.wait(Wait::Down).await
+    }
+
+    async fn wait(&mut self, wait: Wait) {
+        let r = Self::regs();
+
+        let on_drop = OnDrop::new(|| {
+            let r = Self::regs();
+            r.intenclr.write(|w| {
+                w.up().clear();
+                w.down().clear();
+                w.cross().clear();
+                w
+            });
+        });
+
+        r.events_up.reset();
+        r.events_down.reset();
+        r.events_cross.reset();
+        r.intenset.write(|w| match wait {
+            Wait::Up => w.up().set(),
+            Wait::Down => w.down().set(),
+            Wait::Cross => w.cross().set(),
+        });
+
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+
+            // Whichever event was enabled has fired once the interrupt disabled it again.
+            if r.intenset.read().bits() == 0 {
+                return Poll::Ready(());
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        on_drop.defuse();
+    }
+
+    /// Returns the UP event, for use with PPI.
+    pub fn event_up(&self) -> Event<'d> {
+        Event::from_reg(&Self::regs().events_up)
+    }
+
+    /// Returns the DOWN event, for use with PPI.
+    pub fn event_down(&self) -> Event<'d> {
+        Event::from_reg(&Self::regs().events_down)
+    }
+
+    /// Returns the CROSS event, for use with PPI.
+    pub fn event_cross(&self) -> Event<'d> {
+        Event::from_reg(&Self::regs().events_cross)
+    }
+
+    /// Returns the SAMPLE task, for use with PPI.
+    pub fn task_sample(&self) -> Task<'d> {
+        Task::from_reg(&Self::regs().tasks_sample)
+    }
+
+    fn regs() -> &'static pac::comp::RegisterBlock {
+        unsafe { &*pac::COMP::ptr() }
+    }
+}
+
+impl<'d> Drop for Comp<'d> {
+    fn drop(&mut self) {
+        let r = Self::regs();
+        r.tasks_stop.write(|w| unsafe { w.bits(1) });
+        r.enable.write(|w| w.enable().disabled());
+    }
+}
+
+/// Returns the AIN number of an analog input pin.
+pub(crate) fn analog_input(input: &impl Input) -> u8 {
+    match input.channel() {
+        InputChannel::ANALOG_INPUT0 => 0,
+        InputChannel::ANALOG_INPUT1 => 1,
+        InputChannel::ANALOG_INPUT2 => 2,
+        InputChannel::ANALOG_INPUT3 => 3,
+        InputChannel::ANALOG_INPUT4 => 4,
+        InputChannel::ANALOG_INPUT5 => 5,
+        InputChannel::ANALOG_INPUT6 => 6,
+        InputChannel::ANALOG_INPUT7 => 7,
+        _ => panic!("not an analog input pin"),
+    }
+}
@@ -0,0 +1,218 @@
+//! Event Generator Unit (EGU) driver.
+//!
+//! The EGU is a source of software triggered events and interrupts. Triggering one of its
+//! tasks from software or through PPI generates the corresponding event, which can in turn
+//! trigger other tasks through PPI, or fire the EGU interrupt. This makes it useful for
+//! pending an interrupt executor, or for signaling across interrupt priorities.
+//!
+//! Each EGU has 16 triggers. [`Egu::split`] hands them out as separate [`Trigger`]s,
+//! so each one can only be owned once.
+
+#![macro_use]
+
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::interrupt::typelevel::Interrupt;
+use crate::ppi::{Event, Task};
+use crate::{interrupt, pac, Peripheral};
+
+/// Number of triggers of each EGU.
+pub const TRIGGER_COUNT: usize = 16;
+
+/// Interrupt handler.
+///
+/// Only needed for [`Trigger::wait`]. Don't bind it if the EGU interrupt is used
+/// for something else, like an interrupt executor.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let r = T::regs();
+        let s = T::state();
+
+        for n in 0..TRIGGER_COUNT {
+            if r.events_triggered[n].read().bits() != 0 {
+                r.intenclr.write(|w| unsafe { w.bits(1 << n) });
+                s.wakers[n].wake();
+            }
+        }
+    }
+}
+
+/// EGU driver.
+pub struct Egu<'d, T: Instance> {
+    _p: PeripheralRef<'d, T>,
+}
+
+impl<'d, T: Instance> Egu<'d, T> {
+    /// Create a new EGU driver.
+    ///
+    /// The EGU interrupt is left alone, so it can be used by an interrupt executor
+    /// or a custom handler.
+    pub fn new(egu: impl Peripheral<P = T> + 'd) -> Self {
+        into_ref!(egu);
+
+        let r = T::regs();
+        r.intenclr.write(|w| unsafe { w.bits(0xFFFF) });
+        for n in 0..TRIGGER_COUNT {
+            r.events_triggered[n].reset();
+        }
+
+        Self { _p: egu }
+    }
+
+    /// Create a new EGU driver with the EGU interrupt bound to [`InterruptHandler`],
+    /// allowing to use [`Trigger::wait`].
+    pub fn new_with_interrupt(
+        egu: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+    ) -> Self {
+        let this = Self::new(egu);
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        this
+    }
+
+    /// Split the EGU into its triggers.
+    pub fn split(self) -> [Trigger<'d, T>; TRIGGER_COUNT] {
+        core::array::from_fn(|n| Trigger {
+            number: n,
+            _p: PhantomData,
+        })
+    }
+}
+
+/// A single trigger of an EGU, consisting of a task and the event it generates.
+pub struct Trigger<'d, T: Instance> {
+    number: usize,
+    _p: PhantomData<&'d T>,
+}
+
+impl<'d, T: Instance> Trigger<'d, T> {
+    /// Returns the number of this trigger.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Trigger the task, generating the event.
+    pub fn trigger(&mut self) {
+        T::regs().tasks_trigger[self.number].write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns whether the event has been generated since it was last cleared.
+    pub fn is_triggered(&self) -> bool {
+        T::regs().events_triggered[self.number].read().bits() != 0
+    }
+
+    /// Clear the event.
+    pub fn clear(&mut self) {
+        T::regs().events_triggered[self.number].reset();
+    }
+
+    /// Enable the EGU interrupt for this trigger's event.
+    ///
+    /// Use this when binding the EGU interrupt to an interrupt executor, or to a
+    /// custom handler. The event must be cleared by the handler.
+    pub fn enable_interrupt(&mut self) {
+        T::regs().intenset.write(|w| unsafe { w.bits(1 << self.number) });
+    }
+
+    /// Disable the EGU interrupt for this trigger's event.
+    pub fn disable_interrupt(&mut self) {
+        T::regs().intenclr.write(|w| unsafe { w.bits(1 << self.number) });
+    }
+
+    /// Wait for the event to be generated, for example by another priority level
+    /// calling [`Trigger::trigger`], or through PPI.
+    ///
+    /// This requires the EGU to be created with [`Egu::new_with_interrupt`].
+    pub async fn wait(&mut self) {
+        let r = T::regs();
+        let s = T::state();
+        let number = self.number;
+
+        let on_drop = OnDrop::new(|| {
+            T::regs().intenclr.write(|w| unsafe { w.bits(1 << number) });
+        });
+
+        r.events_triggered[number].reset();
+        r.intenset.write(|w| unsafe { w.bits(1 << number) });
+
+        poll_fn(|cx| {
+            s.wakers[number].register(cx.waker());
+
+            if r.events_triggered[number].read().bits() != 0 {
+                r.events_triggered[number].reset();
+                return Poll::Ready(());
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        drop(on_drop);
+    }
+
+    /// Returns the task, for use with PPI.
+    pub fn task(&self) -> Task<'d> {
+        Task::from_reg(&T::regs().tasks_trigger[self.number])
+    }
+
+    /// Returns the event, for use with PPI.
+    pub fn event(&self) -> Event<'d> {
+        Event::from_reg(&T::regs().events_triggered[self.number])
+    }
+}
+
+pub(crate) struct State {
+    wakers: [AtomicWaker; TRIGGER_COUNT],
+}
+
+impl State {
+    pub(crate) const fn new() -> Self {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const NEW_AW: AtomicWaker = AtomicWaker::new();
+        Self {
+            wakers: [NEW_AW; TRIGGER_COUNT],
+        }
+    }
+}
+
+pub(crate) trait SealedInstance {
+    fn regs() -> &'static pac::egu0::RegisterBlock;
+    fn state() -> &'static State;
+}
+
+/// EGU peripheral instance.
+#[allow(private_bounds)]
+pub trait Instance: Peripheral<P = Self> + SealedInstance + 'static + Send {
+    /// Interrupt for this peripheral.
+    type Interrupt: interrupt::typelevel::Interrupt;
+}
+
+macro_rules! impl_egu {
+    ($type:ident, $pac_type:ident, $irq:ident) => {
+        impl crate::egu::SealedInstance for peripherals::$type {
+            fn regs() -> &'static pac::egu0::RegisterBlock {
+                unsafe { &*(pac::$pac_type::ptr() as *const pac::egu0::RegisterBlock) }
+            }
+            fn state() -> &'static crate::egu::State {
+                static STATE: crate::egu::State = crate::egu::State::new();
+                &STATE
+            }
+        }
+        impl crate::egu::Instance for peripherals::$type {
+            type Interrupt = crate::interrupt::typelevel::$irq;
+        }
+    };
+}
@@ -83,6 +83,27 @@ impl MasterClock {
     pub fn sample_rate(&self) -> u32 {
         self.freq.to_frequency() / self.ratio.to_divisor()
     }
+
+    /// Find the clock configuration whose sample rate is closest to `sample_rate`.
+    ///
+    /// Use [`MasterClock::sample_rate`] on the result to get the actual sample rate.
+    pub fn closest(sample_rate: u32) -> Self {
+        let mut best = Self::new(MckFreq::_32MDiv8, Ratio::_32x);
+        let mut best_error = u32::MAX;
+
+        for &freq in MckFreq::ALL {
+            for &ratio in Ratio::ALL {
+                let candidate = Self::new(freq, ratio);
+                let error = candidate.sample_rate().abs_diff(sample_rate);
+                if error < best_error {
+                    best = candidate;
+                    best_error = error;
+                }
+            }
+        }
+
+        best
+    }
 }
 
 /// Master clock generator frequency.
@@ -117,6 +138,22 @@ pub enum MckFreq {
 }
 
 impl MckFreq {
+    const ALL: &'static [MckFreq] = &[
+        MckFreq::_32MDiv8,
+        MckFreq::_32MDiv10,
+        MckFreq::_32MDiv11,
+        MckFreq::_32MDiv15,
+        MckFreq::_32MDiv16,
+        MckFreq::_32MDiv21,
+        MckFreq::_32MDiv23,
+        MckFreq::_32MDiv30,
+        MckFreq::_32MDiv31,
+        MckFreq::_32MDiv32,
+        MckFreq::_32MDiv42,
+        MckFreq::_32MDiv63,
+        MckFreq::_32MDiv125,
+    ];
+
     const REGISTER_VALUES: &'static [u32] = &[
         0x20000000, 0x18000000, 0x16000000, 0x11000000, 0x10000000, 0x0C000000, 0x0B000000, 0x08800000, 0x08400000,
         0x08000000, 0x06000000, 0x04100000, 0x020C0000,
@@ -171,6 +208,18 @@ pub enum Ratio {
 }
 
 impl Ratio {
+    const ALL: &'static [Ratio] = &[
+        Ratio::_32x,
+        Ratio::_48x,
+        Ratio::_64x,
+        Ratio::_96x,
+        Ratio::_128x,
+        Ratio::_192x,
+        Ratio::_256x,
+        Ratio::_384x,
+        Ratio::_512x,
+    ];
+
     const RATIOS: &'static [u32] = &[32, 48, 64, 96, 128, 192, 256, 384, 512];
 
     /// Return the value that needs to be written to the register.
@@ -402,6 +451,7 @@ pub struct I2S<'d, T: Instance> {
     sdin: Option<PeripheralRef<'d, AnyPin>>,
     sdout: Option<PeripheralRef<'d, AnyPin>>,
     master_clock: Option<MasterClock>,
+    slave_mck: Option<MckFreq>,
     config: Config,
 }
 
@@ -425,6 +475,7 @@ impl<'d, T: Instance> I2S<'d, T> {
             sdin: None,
             sdout: None,
             master_clock: Some(master_clock),
+            slave_mck: None,
             config,
         }
     }
@@ -446,6 +497,33 @@ impl<'d, T: Instance> I2S<'d, T> {
             sdin: None,
             sdout: None,
             master_clock: None,
+            slave_mck: None,
+            config,
+        }
+    }
+
+    /// Create a new I2S in slave mode, generating the master clock for an external codec.
+    ///
+    /// SCK and LRCK are still driven by the external master, only MCK is output.
+    pub fn new_slave_with_mck(
+        i2s: impl Peripheral<P = T> + 'd,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        mck: impl Peripheral<P = impl GpioPin> + 'd,
+        sck: impl Peripheral<P = impl GpioPin> + 'd,
+        lrck: impl Peripheral<P = impl GpioPin> + 'd,
+        mck_freq: MckFreq,
+        config: Config,
+    ) -> Self {
+        into_ref!(i2s, mck, sck, lrck);
+        Self {
+            i2s,
+            mck: Some(mck.map_into()),
+            sck: sck.map_into(),
+            lrck: lrck.map_into(),
+            sdin: None,
+            sdout: None,
+            master_clock: None,
+            slave_mck: Some(mck_freq),
             config,
         }
     }
@@ -517,6 +595,14 @@ impl<'d, T: Instance> I2S<'d, T> {
             }
             None => {
                 c.mode.write(|w| w.mode().slave());
+                match &self.slave_mck {
+                    Some(freq) => {
+                        c.mcken.write(|w| w.mcken().enabled());
+                        c.mckfreq
+                            .write(|w| unsafe { w.mckfreq().bits(freq.to_register_value()) });
+                    }
+                    None => c.mcken.write(|w| w.mcken().disabled()),
+                }
             }
         };
 
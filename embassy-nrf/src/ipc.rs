@@ -0,0 +1,397 @@
+//! Interprocessor communication (IPC) driver, for the nRF5340 application and network cores.
+//!
+//! Each core has its own IPC peripheral, connected to the other through 16 shared channels.
+//! Triggering a [`Task`] sends on the channels it is configured for, and generates the
+//! [`Event`]s configured to receive on any of those channels, on both cores.
+//! By default, task `n` and event `n` use channel `n`, so a channel should only be used
+//! to send in one direction.
+//!
+//! IPC only carries notifications. Data is passed through memory shared by both cores, for
+//! example with a [`SharedQueue`] and its [`Sender`] and [`Receiver`] endpoints.
+//!
+//! On the application core, `start_network_core` releases the network core so it boots
+//! its own firmware, and `stop_network_core` holds it off again.
+
+use core::cell::UnsafeCell;
+use core::future::poll_fn;
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{compiler_fence, AtomicUsize, Ordering};
+use core::task::{Context, Poll};
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::blocking_mutex::raw::RawMutex;
+use embassy_sync::channel::Channel;
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::interrupt::InterruptExt;
+use crate::peripherals::IPC;
+use crate::{interrupt, pac, ppi, Peripheral};
+
+/// Number of IPC channels, and of tasks and events of the IPC peripheral.
+pub const CHANNEL_COUNT: usize = 16;
+
+/// Interrupt handler.
+pub struct InterruptHandler {
+    _private: (),
+}
+
+impl interrupt::typelevel::Handler<interrupt::typelevel::IPC> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        let r = regs();
+        for (n, waker) in WAKERS.iter().enumerate() {
+            if r.events_receive[n].read().bits() != 0 {
+                r.intenclr.write(|w| unsafe { w.bits(1 << n) });
+                waker.wake();
+            }
+        }
+    }
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NEW_AW: AtomicWaker = AtomicWaker::new();
+static WAKERS: [AtomicWaker; CHANNEL_COUNT] = [NEW_AW; CHANNEL_COUNT];
+
+fn regs() -> &'static pac::ipc::RegisterBlock {
+    unsafe { &*pac::IPC::ptr() }
+}
+
+/// IPC driver.
+///
+/// The tasks and events are public, so they can be moved out and used independently.
+pub struct Ipc<'d> {
+    /// Tasks, sending on channel `n` by default.
+    pub tasks: [Task<'d>; CHANNEL_COUNT],
+    /// Events, receiving on channel `n` by default.
+    pub events: [Event<'d>; CHANNEL_COUNT],
+    _p: PeripheralRef<'d, IPC>,
+}
+
+impl<'d> Ipc<'d> {
+    /// Create a new IPC driver, with task and event `n` configured for channel `n`.
+    pub fn new(
+        ipc: impl Peripheral<P = IPC> + 'd,
+        _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::IPC, InterruptHandler> + 'd,
+    ) -> Self {
+        into_ref!(ipc);
+
+        let r = regs();
+        r.intenclr.write(|w| unsafe { w.bits(0xFFFF) });
+        for n in 0..CHANNEL_COUNT {
+            r.send_cnf[n].write(|w| unsafe { w.bits(1 << n) });
+            r.receive_cnf[n].write(|w| unsafe { w.bits(1 << n) });
+            r.events_receive[n].reset();
+        }
+
+        interrupt::IPC.unpend();
+        unsafe { interrupt::IPC.enable() };
+
+        Self {
+            tasks: core::array::from_fn(|n| Task {
+                number: n,
+                _p: PhantomData,
+            }),
+            events: core::array::from_fn(|n| Event {
+                number: n,
+                _p: PhantomData,
+            }),
+            _p: ipc,
+        }
+    }
+}
+
+/// An IPC task, sending on one or more channels.
+pub struct Task<'d> {
+    number: usize,
+    _p: PhantomData<&'d ()>,
+}
+
+impl<'d> Task<'d> {
+    /// Returns the number of this task.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Set the channels this task sends on, as a bitmask.
+    pub fn configure_channels(&mut self, channels: u16) {
+        regs().send_cnf[self.number].write(|w| unsafe { w.bits(channels as u32) });
+    }
+
+    /// Trigger the task, sending on the configured channels.
+    pub fn trigger(&mut self) {
+        regs().tasks_send[self.number].write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Returns the task, for use with DPPI.
+    pub fn ppi_task(&self) -> ppi::Task<'d> {
+        ppi::Task::from_reg(&regs().tasks_send[self.number])
+    }
+}
+
+/// An IPC event, received on one or more channels.
+pub struct Event<'d> {
+    number: usize,
+    _p: PhantomData<&'d ()>,
+}
+
+impl<'d> Event<'d> {
+    /// Returns the number of this event.
+    pub fn number(&self) -> usize {
+        self.number
+    }
+
+    /// Set the channels this event is received on, as a bitmask.
+    pub fn configure_channels(&mut self, channels: u16) {
+        regs().receive_cnf[self.number].write(|w| unsafe { w.bits(channels as u32) });
+    }
+
+    /// Returns whether the event has been received since it was last cleared.
+    pub fn is_received(&self) -> bool {
+        regs().events_receive[self.number].read().bits() != 0
+    }
+
+    /// Clear the event.
+    pub fn clear(&mut self) {
+        regs().events_receive[self.number].reset();
+    }
+
+    /// Wait for the event to be received.
+    pub async fn wait(&mut self) {
+        let number = self.number;
+        let on_drop = OnDrop::new(|| regs().intenclr.write(|w| unsafe { w.bits(1 << number) }));
+
+        self.clear();
+        regs().intenset.write(|w| unsafe { w.bits(1 << number) });
+
+        poll_fn(|cx| {
+            WAKERS[number].register(cx.waker());
+
+            let r = regs();
+            if r.events_receive[number].read().bits() != 0 {
+                r.events_receive[number].reset();
+                return Poll::Ready(());
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        drop(on_drop);
+    }
+
+    // Clear the event and enable its interrupt, so the task is woken up by the next one.
+    fn arm(&mut self, cx: &mut Context<'_>) {
+        WAKERS[self.number].register(cx.waker());
+        self.clear();
+        regs().intenset.write(|w| unsafe { w.bits(1 << self.number) });
+    }
+
+    fn disarm(&mut self) {
+        regs().intenclr.write(|w| unsafe { w.bits(1 << self.number) });
+    }
+
+    /// Returns the event, for use with DPPI.
+    pub fn ppi_event(&self) -> ppi::Event<'d> {
+        ppi::Event::from_reg(&regs().events_receive[self.number])
+    }
+}
+
+/// Single-producer single-consumer message queue, in memory shared by both cores.
+///
+/// Both firmwares must place the queue at the same address, with the same `T` and `N`,
+/// typically in a dedicated RAM section that is not initialized at startup. One of the
+/// cores must [`reset`](Self::reset) it before the other one starts using it, for example
+/// the application core before calling `start_network_core`.
+#[repr(C)]
+pub struct SharedQueue<T: Copy, const N: usize> {
+    // Indices run from 0 to 2N, to tell a full queue from an empty one.
+    write: AtomicUsize,
+    read: AtomicUsize,
+    buf: UnsafeCell<[MaybeUninit<T>; N]>,
+}
+
+unsafe impl<T: Copy + Send, const N: usize> Sync for SharedQueue<T, N> {}
+
+impl<T: Copy, const N: usize> SharedQueue<T, N> {
+    /// Create a new, empty queue.
+    pub const fn new() -> Self {
+        Self {
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            buf: UnsafeCell::new([MaybeUninit::uninit(); N]),
+        }
+    }
+
+    /// Empty the queue.
+    pub fn reset(&mut self) {
+        self.write.store(0, Ordering::Relaxed);
+        self.read.store(0, Ordering::Relaxed);
+        compiler_fence(Ordering::SeqCst);
+    }
+
+    fn len(write: usize, read: usize) -> usize {
+        (write + 2 * N - read) % (2 * N)
+    }
+
+    fn try_push(&self, msg: T) -> Result<(), T> {
+        let write = self.write.load(Ordering::Relaxed);
+        let read = self.read.load(Ordering::Acquire);
+        if Self::len(write, read) >= N {
+            return Err(msg);
+        }
+
+        unsafe { (*self.buf.get())[write % N] = MaybeUninit::new(msg) };
+        self.write.store((write + 1) % (2 * N), Ordering::Release);
+        Ok(())
+    }
+
+    fn try_pop(&self) -> Option<T> {
+        let read = self.read.load(Ordering::Relaxed);
+        let write = self.write.load(Ordering::Acquire);
+        if Self::len(write, read) == 0 {
+            return None;
+        }
+
+        let msg = unsafe { (*self.buf.get())[read % N].assume_init() };
+        self.read.store((read + 1) % (2 * N), Ordering::Release);
+        Some(msg)
+    }
+}
+
+impl<T: Copy, const N: usize> Default for SharedQueue<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Sending end of a [`SharedQueue`].
+///
+/// `task` notifies the other core of new messages, and `event` is received when it
+/// frees up space. They must be configured on the channels used by the other core's
+/// [`Receiver`] event and task respectively.
+pub struct Sender<'d, T: Copy, const N: usize> {
+    queue: &'d SharedQueue<T, N>,
+    task: Task<'d>,
+    event: Event<'d>,
+}
+
+impl<'d, T: Copy, const N: usize> Sender<'d, T, N> {
+    /// Create a new sender.
+    pub fn new(queue: &'d mut SharedQueue<T, N>, task: Task<'d>, event: Event<'d>) -> Self {
+        Self { queue, task, event }
+    }
+
+    /// Try to send a message, returning it back if the queue is full.
+    pub fn try_send(&mut self, msg: T) -> Result<(), T> {
+        self.queue.try_push(msg)?;
+        self.task.trigger();
+        Ok(())
+    }
+
+    /// Send a message, waiting for space in the queue if it is full.
+    pub async fn send(&mut self, msg: T) {
+        let mut msg = Some(msg);
+        let number = self.event.number;
+        let on_drop = OnDrop::new(|| regs().intenclr.write(|w| unsafe { w.bits(1 << number) }));
+
+        poll_fn(|cx| {
+            self.event.arm(cx);
+            match self.try_send(unwrap!(msg.take())) {
+                Ok(()) => Poll::Ready(()),
+                Err(m) => {
+                    msg = Some(m);
+                    Poll::Pending
+                }
+            }
+        })
+        .await;
+
+        drop(on_drop);
+        self.event.disarm();
+    }
+
+    /// Forward all messages received from `channel` to the other core.
+    pub async fn forward_from<M: RawMutex, const C: usize>(&mut self, channel: &Channel<M, T, C>) -> ! {
+        loop {
+            let msg = channel.receive().await;
+            self.send(msg).await;
+        }
+    }
+}
+
+/// Receiving end of a [`SharedQueue`].
+///
+/// `event` is received when the other core sends messages, and `task` notifies it
+/// when space is freed up. They must be configured on the channels used by the other
+/// core's [`Sender`] task and event respectively.
+pub struct Receiver<'d, T: Copy, const N: usize> {
+    queue: &'d SharedQueue<T, N>,
+    event: Event<'d>,
+    task: Task<'d>,
+}
+
+impl<'d, T: Copy, const N: usize> Receiver<'d, T, N> {
+    /// Create a new receiver.
+    pub fn new(queue: &'d mut SharedQueue<T, N>, event: Event<'d>, task: Task<'d>) -> Self {
+        Self { queue, event, task }
+    }
+
+    /// Try to receive a message, returning `None` if the queue is empty.
+    pub fn try_receive(&mut self) -> Option<T> {
+        let msg = self.queue.try_pop()?;
+        self.task.trigger();
+        Some(msg)
+    }
+
+    /// Receive a message, waiting for one if the queue is empty.
+    pub async fn receive(&mut self) -> T {
+        let number = self.event.number;
+        let on_drop = OnDrop::new(|| regs().intenclr.write(|w| unsafe { w.bits(1 << number) }));
+
+        let msg = poll_fn(|cx| {
+            self.event.arm(cx);
+            match self.try_receive() {
+                Some(msg) => Poll::Ready(msg),
+                None => Poll::Pending,
+            }
+        })
+        .await;
+
+        drop(on_drop);
+        self.event.disarm();
+        msg
+    }
+
+    /// Forward all messages received from the other core to `channel`.
+    pub async fn forward_to<M: RawMutex, const C: usize>(&mut self, channel: &Channel<M, T, C>) -> ! {
+        loop {
+            let msg = self.receive().await;
+            channel.send(msg).await;
+        }
+    }
+}
+
+/// Release the network core, letting it boot.
+///
+/// The network core must be given access to shared RAM and peripherals through the SPU
+/// beforehand if needed.
+#[cfg(feature = "_nrf5340-app")]
+pub fn start_network_core() {
+    let r = unsafe { &*pac::RESET::ptr() };
+    r.network.forceoff.write(|w| w.forceoff().release());
+}
+
+/// Force the network core off, holding it in reset.
+#[cfg(feature = "_nrf5340-app")]
+pub fn stop_network_core() {
+    let r = unsafe { &*pac::RESET::ptr() };
+    r.network.forceoff.write(|w| w.forceoff().hold());
+}
+
+/// Returns `true` if the network core is released and running.
+#[cfg(feature = "_nrf5340-app")]
+pub fn is_network_core_running() -> bool {
+    let r = unsafe { &*pac::RESET::ptr() };
+    r.network.forceoff.read().forceoff().is_release()
+}
@@ -30,6 +30,9 @@ compile_error!("No chip feature activated. You must activate exactly one of the
 #[cfg(all(feature = "reset-pin-as-gpio", not(feature = "_nrf52")))]
 compile_error!("feature `reset-pin-as-gpio` is only valid for nRF52 series chips.");
 
+#[cfg(all(feature = "softdevice", not(feature = "_nrf52")))]
+compile_error!("feature `softdevice` is only valid for nRF52 series chips.");
+
 #[cfg(all(feature = "nfc-pins-as-gpio", not(any(feature = "_nrf52", feature = "_nrf5340-app"))))]
 compile_error!("feature `nfc-pins-as-gpio` is only valid for nRF52, or nRF53's application core.");
 
@@ -42,6 +45,10 @@ mod time_driver;
 
 #[cfg(not(feature = "nrf51"))]
 pub mod buffered_uarte;
+#[cfg(any(feature = "nrf52832", feature = "nrf52833", feature = "nrf52840"))]
+pub mod comp;
+#[cfg(not(feature = "nrf51"))]
+pub mod egu;
 pub mod gpio;
 #[cfg(feature = "gpiote")]
 pub mod gpiote;
@@ -52,6 +59,15 @@ pub mod radio;
 
 #[cfg(any(feature = "nrf52832", feature = "nrf52833", feature = "nrf52840"))]
 pub mod i2s;
+#[cfg(feature = "_nrf5340")]
+pub mod ipc;
+#[cfg(any(feature = "nrf52832", feature = "nrf52833", feature = "nrf52840"))]
+pub mod lpcomp;
+#[cfg(all(
+    any(feature = "nrf52832", feature = "nrf52833", feature = "nrf52840"),
+    not(feature = "nfc-pins-as-gpio")
+))]
+pub mod nfct;
 pub mod nvmc;
 #[cfg(any(
     feature = "nrf52810",
@@ -79,6 +95,8 @@ pub mod qspi;
 pub mod rng;
 #[cfg(not(any(feature = "nrf51", feature = "nrf52820", feature = "_nrf5340-net")))]
 pub mod saadc;
+#[cfg(feature = "softdevice")]
+pub mod softdevice;
 #[cfg(not(feature = "nrf51"))]
 pub mod spim;
 #[cfg(not(feature = "nrf51"))]
@@ -312,10 +330,14 @@ pub mod config {
                 },
                 #[cfg(feature = "_nrf9160")]
                 dcdc: DcdcConfig { regmain: false },
-                #[cfg(feature = "gpiote")]
+                #[cfg(all(feature = "gpiote", not(feature = "softdevice")))]
                 gpiote_interrupt_priority: crate::interrupt::Priority::P0,
-                #[cfg(feature = "_time-driver")]
+                #[cfg(all(feature = "gpiote", feature = "softdevice"))]
+                gpiote_interrupt_priority: crate::softdevice::DEFAULT_PRIORITY,
+                #[cfg(all(feature = "_time-driver", not(feature = "softdevice")))]
                 time_interrupt_priority: crate::interrupt::Priority::P0,
+                #[cfg(all(feature = "_time-driver", feature = "softdevice"))]
+                time_interrupt_priority: crate::softdevice::DEFAULT_PRIORITY,
 
                 // In NS mode, default to NotConfigured, assuming the S firmware will do it.
                 #[cfg(feature = "_ns")]
@@ -415,6 +437,10 @@ pub fn init(config: config::Config) -> Peripherals {
     // before doing anything important.
     let peripherals = Peripherals::take();
 
+    // Set up interrupt priorities usable alongside the SoftDevice
+    #[cfg(feature = "softdevice")]
+    softdevice::init(&config);
+
     #[allow(unused_mut)]
     let mut needs_reset = false;
 
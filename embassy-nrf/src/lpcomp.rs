@@ -0,0 +1,270 @@
+//! Low-power comparator (LPCOMP) driver.
+//!
+//! LPCOMP and COMP share the same peripheral address space and interrupt,
+//! so only one of [`Lpcomp`] and [`Comp`](crate::comp::Comp) can be used at a time.
+
+use core::future::poll_fn;
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::comp::analog_input;
+pub use crate::comp::Crossing;
+use crate::interrupt::InterruptExt;
+use crate::peripherals::LPCOMP;
+use crate::ppi::{Event, Task};
+use crate::saadc::Input;
+use crate::{interrupt, pac, Peripheral};
+
+/// Interrupt handler.
+pub struct InterruptHandler {
+    _private: (),
+}
+
+impl interrupt::typelevel::Handler<interrupt::typelevel::COMP_LPCOMP> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        let r = unsafe { &*pac::LPCOMP::PTR };
+        r.intenclr.write(|w| {
+            w.up().clear();
+            w.down().clear();
+            w.cross().clear();
+            w
+        });
+        WAKER.wake();
+    }
+}
+
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+enum Wait {
+    Up,
+    Down,
+    Cross,
+}
+
+/// Reference voltage, as a fraction of VDD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[allow(missing_docs)]
+pub enum Reference {
+    Vdd1_16,
+    Vdd1_8,
+    Vdd3_16,
+    Vdd2_8,
+    Vdd5_16,
+    Vdd3_8,
+    Vdd7_16,
+    Vdd4_8,
+    Vdd9_16,
+    Vdd5_8,
+    Vdd11_16,
+    Vdd6_8,
+    Vdd13_16,
+    Vdd7_8,
+    Vdd15_16,
+}
+
+/// Crossing to detect for waking up from System OFF.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Detect {
+    /// Upward or downward crossing.
+    Cross,
+    /// Upward crossing.
+    Up,
+    /// Downward crossing.
+    Down,
+}
+
+/// LPCOMP config.
+#[non_exhaustive]
+pub struct Config {
+    /// Reference voltage.
+    pub reference: Reference,
+    /// Enable 50mV hysteresis.
+    pub hysteresis: bool,
+    /// Crossing that wakes the system up from System OFF.
+    pub detect: Detect,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            reference: Reference::Vdd4_8,
+            hysteresis: false,
+            detect: Detect::Cross,
+        }
+    }
+}
+
+/// Low-power comparator driver.
+///
+/// The comparator keeps running in System OFF, so it can also be used to wake
+/// the system up, as selected with [`Config::detect`].
+pub struct Lpcomp<'d> {
+    _peri: PeripheralRef<'d, LPCOMP>,
+}
+
+impl<'d> Lpcomp<'d> {
+    /// Create a new low-power comparator comparing `input` against a fraction of VDD.
+    ///
+    /// Panics if `input` is not an analog input pin.
+    pub fn new(
+        peri: impl Peripheral<P = LPCOMP> + 'd,
+        _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::COMP_LPCOMP, InterruptHandler> + 'd,
+        input: impl Peripheral<P = impl Input> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(peri, input);
+
+        let r = Self::regs();
+        r.psel.write(|w| w.psel().bits(analog_input(&*input)));
+        r.refsel.write(|w| match config.reference {
+            Reference::Vdd1_16 => w.refsel().ref1_16vdd(),
+            Reference::Vdd1_8 => w.refsel().ref1_8vdd(),
+            Reference::Vdd3_16 => w.refsel().ref3_16vdd(),
+            Reference::Vdd2_8 => w.refsel().ref2_8vdd(),
+            Reference::Vdd5_16 => w.refsel().ref5_16vdd(),
+            Reference::Vdd3_8 => w.refsel().ref3_8vdd(),
+            Reference::Vdd7_16 => w.refsel().ref7_16vdd(),
+            Reference::Vdd4_8 => w.refsel().ref4_8vdd(),
+            Reference::Vdd9_16 => w.refsel().ref9_16vdd(),
+            Reference::Vdd5_8 => w.refsel().ref5_8vdd(),
+            Reference::Vdd11_16 => w.refsel().ref11_16vdd(),
+            Reference::Vdd6_8 => w.refsel().ref6_8vdd(),
+            Reference::Vdd13_16 => w.refsel().ref13_16vdd(),
+            Reference::Vdd7_8 => w.refsel().ref7_8vdd(),
+            Reference::Vdd15_16 => w.refsel().ref15_16vdd(),
+        });
+        r.hyst.write(|w| w.hyst().bit(config.hysteresis));
+        r.anadetect.write(|w| match config.detect {
+            Detect::Cross => w.anadetect().cross(),
+            Detect::Up => w.anadetect().up(),
+            Detect::Down => w.anadetect().down(),
+        });
+
+        r.enable.write(|w| w.enable().enabled());
+
+        // The comparator is ready within microseconds, so just spin for it.
+        r.events_ready.reset();
+        r.tasks_start.write(|w| unsafe { w.bits(1) });
+        while r.events_ready.read().bits() == 0 {}
+        r.events_ready.reset();
+
+        interrupt::COMP_LPCOMP.unpend();
+        unsafe { interrupt::COMP_LPCOMP.enable() };
+
+        Self { _peri: peri }
+    }
+
+    /// Sample the comparator output, returning `true` if the input is above the reference.
+    pub fn sample(&mut self) -> bool {
+        let r = Self::regs();
+        r.tasks_sample.write(|w| unsafe { w.bits(1) });
+        r.result.read().result().is_above()
+    }
+
+    /// Wait for the input to cross the reference in either direction.
+    ///
+    /// The CPU can sleep while waiting, and is woken up by the crossing.
+    pub async fn wait_for_cross(&mut self) -> Crossing {
+        let r = Self::regs();
+        self.wait(Wait::Cross).await;
+
+        let up = r.events_up.read().bits() != 0;
+        let down = r.events_down.read().bits() != 0;
+        r.events_up.reset();
+        r.events_down.reset();
+
+        match (up, down) {
+            (true, false) => Crossing::Up,
+            (false, true) => Crossing::Down,
+            // Crossed back and forth, report the direction of the latest crossing.
+            _ => match self.sample() {
+                true => Crossing::Up,
+                false => Crossing::Down,
+            },
+        }
+    }
+
+    /// Wait for the input to rise above the reference.
+    pub async fn wait_for_up(&mut self) {
+        self.wait(Wait::Up).await
+    }
+
+    /// Wait for the input to fall below the reference.
+    pub async fn wait_for_down(&mut self) {
+        self.wait(Wait::Down).await
+    }
+
+    async fn wait(&mut self, wait: Wait) {
+        let r = Self::regs();
+
+        let on_drop = OnDrop::new(|| {
+            let r = Self::regs();
+            r.intenclr.write(|w| {
+                w.up().clear();
+                w.down().clear();
+                w.cross().clear();
+                w
+            });
+        });
+
+        r.events_up.reset();
+        r.events_down.reset();
+        r.events_cross.reset();
+        r.intenset.write(|w| match wait {
+            Wait::Up => w.up().set(),
+            Wait::Down => w.down().set(),
+            Wait::Cross => w.cross().set(),
+        });
+
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+
+            // Whichever event was enabled has fired once the interrupt disabled it again.
+            if r.intenset.read().bits() == 0 {
+                return Poll::Ready(());
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        on_drop.defuse();
+    }
+
+    /// Returns the UP event, for use with PPI.
+    pub fn event_up(&self) -> Event<'d> {
+        Event::from_reg(&Self::regs().events_up)
+    }
+
+    /// Returns the DOWN event, for use with PPI.
+    pub fn event_down(&self) -> Event<'d> {
+        Event::from_reg(&Self::regs().events_down)
+    }
+
+    /// Returns the CROSS event, for use with PPI.
+    pub fn event_cross(&self) -> Event<'d> {
+        Event::from_reg(&Self::regs().events_cross)
+    }
+
+    /// Returns the SAMPLE task, for use with PPI.
+    pub fn task_sample(&self) -> Task<'d> {
+        Task::from_reg(&Self::regs().tasks_sample)
+    }
+
+    fn regs() -> &'static pac::lpcomp::RegisterBlock {
+        unsafe { &*pac::LPCOMP::ptr() }
+    }
+}
+
+impl<'d> Drop for Lpcomp<'d> {
+    fn drop(&mut self) {
+        let r = Self::regs();
+        r.tasks_stop.write(|w| unsafe { w.bits(1) });
+        r.enable.write(|w| w.enable().disabled());
+    }
+}
@@ -0,0 +1,438 @@
+//! NFC tag (NFCT) driver.
+//!
+//! The NFCT peripheral implements the NFC-A listen mode: it detects an external field,
+//! handles the anticollision and selection procedure in hardware, and then exchanges
+//! frames with the reader (poller). Higher level protocols, such as the NFC Forum
+//! Type 2 and Type 4 tag command sets, are implemented on top of [`NfcT::receive`]
+//! and [`NfcT::transmit`].
+//!
+//! The NFCT peripheral requires the high frequency crystal oscillator to be running
+//! while activated, so set `config.hfclk_source` to
+//! [`HfclkSource::ExternalXtal`](crate::config::HfclkSource::ExternalXtal).
+
+use core::future::poll_fn;
+use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::interrupt::InterruptExt;
+use crate::peripherals::NFCT;
+use crate::util::slice_in_ram_or;
+use crate::{interrupt, pac, Peripheral};
+
+/// Maximum frame size that can be sent or received, in bytes.
+pub const MAX_FRAME_LEN: usize = 257;
+
+/// NFCT error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// The external field was lost, the tag has to be activated again.
+    FieldLost,
+    /// The received frame had a CRC error.
+    Crc,
+    /// The received frame had a parity error.
+    Parity,
+    /// The received frame did not fit in the buffer.
+    Overrun,
+    /// The response was not sent within the maximum frame delay.
+    FrameDelayTimeout,
+    /// The buffer is longer than [`MAX_FRAME_LEN`].
+    BufferTooLong,
+    /// The buffer is empty.
+    BufferZeroLength,
+    /// The buffer is not in data RAM. It's most likely in flash, and nRF's DMA cannot access flash.
+    BufferNotInRAM,
+}
+
+/// NFCID1 of the tag, used during anticollision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NfcId {
+    /// Single size (4 bytes) NFCID1.
+    SingleSize([u8; 4]),
+    /// Double size (7 bytes) NFCID1.
+    DoubleSize([u8; 7]),
+    /// Triple size (10 bytes) NFCID1.
+    TripleSize([u8; 10]),
+}
+
+/// Protocol advertised in the SEL_RES response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Protocol {
+    /// NFC Forum Type 2 tag.
+    Type2Tag,
+    /// NFC Forum Type 4A tag (ISO-DEP).
+    Type4Tag,
+}
+
+/// NFCT configuration.
+#[derive(Clone)]
+#[non_exhaustive]
+pub struct Config {
+    /// NFCID1 used during anticollision.
+    pub nfcid1: NfcId,
+    /// Protocol advertised to the reader once selected.
+    pub protocol: Protocol,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            // Value of the FICR NFC tag header is a better choice, but needs to be read by the user.
+            nfcid1: NfcId::SingleSize([0x08, 0x00, 0x00, 0x00]),
+            protocol: Protocol::Type2Tag,
+        }
+    }
+}
+
+/// Interrupt handler.
+pub struct InterruptHandler {
+    _private: (),
+}
+
+impl interrupt::typelevel::Handler<interrupt::typelevel::NFCT> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        let r = regs();
+
+        if r.events_fielddetected.read().bits() != 0 {
+            r.intenclr.write(|w| w.fielddetected().clear());
+        }
+        if r.events_fieldlost.read().bits() != 0 {
+            r.intenclr.write(|w| w.fieldlost().clear());
+        }
+        if r.events_selected.read().bits() != 0 {
+            r.intenclr.write(|w| w.selected().clear());
+        }
+        if r.events_rxframeend.read().bits() != 0 {
+            r.intenclr.write(|w| w.rxframeend().clear());
+        }
+        if r.events_txframeend.read().bits() != 0 {
+            r.intenclr.write(|w| w.txframeend().clear());
+        }
+        if r.events_error.read().bits() != 0 {
+            r.intenclr.write(|w| w.error().clear());
+        }
+
+        WAKER.wake();
+    }
+}
+
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+/// NFC tag driver.
+pub struct NfcT<'d> {
+    _p: PeripheralRef<'d, NFCT>,
+}
+
+impl<'d> NfcT<'d> {
+    /// Create a new NFC tag driver.
+    pub fn new(
+        nfct: impl Peripheral<P = NFCT> + 'd,
+        _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::NFCT, InterruptHandler> + 'd,
+        config: Config,
+    ) -> Self {
+        into_ref!(nfct);
+
+        let r = regs();
+
+        r.tasks_disable.write(|w| unsafe { w.bits(1) });
+
+        // Anticollision is configured before the peripheral is activated.
+        // On nRF52832 it is always enabled.
+        #[cfg(not(feature = "nrf52832"))]
+        r.autocolresconfig.write(|w| w.mode().enabled());
+
+        let (size, last, second, third) = match config.nfcid1 {
+            NfcId::SingleSize(id) => (0, u32::from_be_bytes(id), 0, 0),
+            NfcId::DoubleSize(id) => (
+                1,
+                u32::from_be_bytes([id[3], id[4], id[5], id[6]]),
+                u32::from_be_bytes([0, id[0], id[1], id[2]]),
+                0,
+            ),
+            NfcId::TripleSize(id) => (
+                2,
+                u32::from_be_bytes([id[6], id[7], id[8], id[9]]),
+                u32::from_be_bytes([0, id[3], id[4], id[5]]),
+                u32::from_be_bytes([0, id[0], id[1], id[2]]),
+            ),
+        };
+        r.nfcid1_last.write(|w| unsafe { w.bits(last) });
+        r.nfcid1_2nd_last.write(|w| unsafe { w.bits(second) });
+        r.nfcid1_3rd_last.write(|w| unsafe { w.bits(third) });
+        r.sensres.write(|w| unsafe {
+            // SDD pattern 00100, as required by the NFC Forum digital protocol.
+            w.bitframesdd().bits(0b00100);
+            w.nfcidsize().bits(size)
+        });
+        r.selres.write(|w| unsafe {
+            w.protocol().bits(match config.protocol {
+                Protocol::Type2Tag => 0b00,
+                Protocol::Type4Tag => 0b01,
+            })
+        });
+
+        // Go back to sensing the field when it is lost.
+        r.shorts.write(|w| w.fieldlost_sense().enabled());
+
+        interrupt::NFCT.unpend();
+        unsafe { interrupt::NFCT.enable() };
+
+        Self { _p: nfct }
+    }
+
+    /// Returns true if an external field is currently present.
+    pub fn is_field_present(&self) -> bool {
+        regs().fieldpresent.read().fieldpresent().is_field_present()
+    }
+
+    /// Wait for an external field and for a reader to select this tag.
+    ///
+    /// Once this returns, frames can be exchanged with [`receive`](Self::receive)
+    /// and [`transmit`](Self::transmit).
+    pub async fn activate(&mut self) {
+        let r = regs();
+
+        loop {
+            r.events_fielddetected.reset();
+            r.intenset.write(|w| w.fielddetected().set());
+            r.tasks_sense.write(|w| unsafe { w.bits(1) });
+
+            poll_fn(|cx| {
+                WAKER.register(cx.waker());
+                if r.events_fielddetected.read().bits() != 0 {
+                    r.events_fielddetected.reset();
+                    return Poll::Ready(());
+                }
+                Poll::Pending
+            })
+            .await;
+
+            r.events_fieldlost.reset();
+            r.tasks_activate.write(|w| unsafe { w.bits(1) });
+
+            if self.wait_for_selection().await.is_ok() {
+                return;
+            }
+
+            trace!("nfct: field lost before selection");
+        }
+    }
+
+    /// Put the tag to sleep, for example after receiving a HLTA command.
+    ///
+    /// The tag ignores everything but a WUPA command until it is selected again,
+    /// which can be awaited with [`wait_for_selection`](Self::wait_for_selection).
+    pub fn sleep(&mut self) {
+        regs().tasks_gosleep.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Wait for the reader to complete anticollision and select this tag.
+    pub async fn wait_for_selection(&mut self) -> Result<(), Error> {
+        let r = regs();
+
+        let on_drop = OnDrop::new(|| {
+            let r = regs();
+            r.intenclr.write(|w| w.selected().clear().fieldlost().clear());
+        });
+
+        r.events_selected.reset();
+        r.intenset.write(|w| w.selected().set().fieldlost().set());
+
+        let res = poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if r.events_fieldlost.read().bits() != 0 {
+                r.events_fieldlost.reset();
+                return Poll::Ready(Err(Error::FieldLost));
+            }
+            if r.events_selected.read().bits() != 0 {
+                r.events_selected.reset();
+                return Poll::Ready(Ok(()));
+            }
+            Poll::Pending
+        })
+        .await;
+
+        drop(on_drop);
+        res
+    }
+
+    /// Receive a frame from the reader, returning its length in bytes.
+    ///
+    /// The CRC is checked and stripped by the hardware.
+    pub async fn receive(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Err(Error::BufferZeroLength);
+        }
+        if buf.len() > MAX_FRAME_LEN {
+            return Err(Error::BufferTooLong);
+        }
+
+        let r = regs();
+
+        // In case the future is dropped, go back to idle so the DMA stops writing to the buffer.
+        let on_drop = OnDrop::new(|| {
+            let r = regs();
+            r.intenclr.write(|w| w.rxframeend().clear().fieldlost().clear());
+            r.tasks_goidle.write(|w| unsafe { w.bits(1) });
+        });
+
+        r.packetptr.write(|w| unsafe { w.bits(buf.as_mut_ptr() as u32) });
+        r.maxlen.write(|w| unsafe { w.bits(buf.len() as u32) });
+        r.rxd
+            .frameconfig
+            .write(|w| w.parity().parity().sof().so_f().crcmoderx().crc16rx());
+
+        r.events_rxframeend.reset();
+        r.intenset.write(|w| w.rxframeend().set().fieldlost().set());
+
+        compiler_fence(Ordering::SeqCst);
+        r.tasks_enablerxdata.write(|w| unsafe { w.bits(1) });
+
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if r.events_fieldlost.read().bits() != 0 {
+                r.events_fieldlost.reset();
+                return Poll::Ready(Err(Error::FieldLost));
+            }
+            if r.events_rxframeend.read().bits() != 0 {
+                r.events_rxframeend.reset();
+                return Poll::Ready(Ok(()));
+            }
+            Poll::Pending
+        })
+        .await?;
+
+        compiler_fence(Ordering::SeqCst);
+        on_drop.defuse();
+        r.intenclr.write(|w| w.rxframeend().clear().fieldlost().clear());
+
+        let status = r.framestatus.rx.read();
+        r.framestatus.rx.write(|w| {
+            w.crcerror()
+                .clear_bit_by_one()
+                .paritystatus()
+                .clear_bit_by_one()
+                .overrun()
+                .clear_bit_by_one()
+        });
+        r.events_rxerror.reset();
+
+        if status.overrun().is_overrun() {
+            return Err(Error::Overrun);
+        }
+        if status.crcerror().is_crcerror() {
+            return Err(Error::Crc);
+        }
+        if status.paritystatus().is_parity_error() {
+            return Err(Error::Parity);
+        }
+
+        Ok(r.rxd.amount.read().rxdatabytes().bits() as usize)
+    }
+
+    /// Transmit a frame to the reader, with CRC appended by the hardware.
+    pub async fn transmit(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.transmit_inner(buf, buf.len() * 8, true).await
+    }
+
+    /// Transmit a frame of `bits` bits to the reader, without CRC.
+    ///
+    /// This is used for short frames such as the 4-bit ACK/NAK responses of Type 2 tags.
+    pub async fn transmit_bits(&mut self, buf: &[u8], bits: usize) -> Result<(), Error> {
+        assert!(bits <= buf.len() * 8);
+        self.transmit_inner(buf, bits, false).await
+    }
+
+    async fn transmit_inner(&mut self, buf: &[u8], bits: usize, crc: bool) -> Result<(), Error> {
+        if buf.is_empty() {
+            return Err(Error::BufferZeroLength);
+        }
+        if buf.len() > MAX_FRAME_LEN {
+            return Err(Error::BufferTooLong);
+        }
+        slice_in_ram_or(buf, Error::BufferNotInRAM)?;
+
+        let r = regs();
+
+        let on_drop = OnDrop::new(|| {
+            let r = regs();
+            r.intenclr
+                .write(|w| w.txframeend().clear().fieldlost().clear().error().clear());
+            r.tasks_goidle.write(|w| unsafe { w.bits(1) });
+        });
+
+        r.packetptr.write(|w| unsafe { w.bits(buf.as_ptr() as u32) });
+        r.maxlen.write(|w| unsafe { w.bits(buf.len() as u32) });
+        r.txd.amount.write(|w| unsafe {
+            w.txdatabytes().bits((bits / 8) as u16);
+            w.txdatabits().bits((bits % 8) as u8)
+        });
+        r.txd.frameconfig.write(|w| {
+            w.parity().parity();
+            w.discardmode().discard_start();
+            w.sof().so_f();
+            if crc {
+                w.crcmodetx().crc16tx()
+            } else {
+                w.crcmodetx().no_crctx()
+            }
+        });
+
+        r.events_txframeend.reset();
+        r.events_error.reset();
+        r.intenset
+            .write(|w| w.txframeend().set().fieldlost().set().error().set());
+
+        compiler_fence(Ordering::SeqCst);
+        r.tasks_starttx.write(|w| unsafe { w.bits(1) });
+
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if r.events_fieldlost.read().bits() != 0 {
+                r.events_fieldlost.reset();
+                return Poll::Ready(Err(Error::FieldLost));
+            }
+            if r.events_error.read().bits() != 0 {
+                r.events_error.reset();
+                let status = r.errorstatus.read();
+                r.errorstatus.write(|w| unsafe { w.bits(status.bits()) });
+                if status.framedelaytimeout().bit_is_set() {
+                    return Poll::Ready(Err(Error::FrameDelayTimeout));
+                }
+            }
+            if r.events_txframeend.read().bits() != 0 {
+                r.events_txframeend.reset();
+                return Poll::Ready(Ok(()));
+            }
+            Poll::Pending
+        })
+        .await?;
+
+        compiler_fence(Ordering::SeqCst);
+        on_drop.defuse();
+        r.intenclr
+            .write(|w| w.txframeend().clear().fieldlost().clear().error().clear());
+
+        Ok(())
+    }
+}
+
+impl<'d> Drop for NfcT<'d> {
+    fn drop(&mut self) {
+        let r = regs();
+        r.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        r.shorts.reset();
+        r.tasks_disable.write(|w| unsafe { w.bits(1) });
+    }
+}
+
+fn regs() -> &'static pac::nfct::RegisterBlock {
+    unsafe { &*pac::NFCT::ptr() }
+}
@@ -2,12 +2,16 @@
 
 use core::{ptr, slice};
 
+use embassy_futures::yield_now;
+use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::{into_ref, PeripheralRef};
 use embedded_storage::nor_flash::{
     ErrorType, MultiwriteNorFlash, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
 };
 
 use crate::peripherals::NVMC;
+#[cfg(feature = "softdevice")]
+use crate::softdevice;
 use crate::{pac, Peripheral};
 
 #[cfg(not(feature = "_nrf5340-net"))]
@@ -20,6 +24,17 @@ pub const PAGE_SIZE: usize = 2048;
 /// Size of NVMC flash in bytes.
 pub const FLASH_SIZE: usize = crate::chip::FLASH_SIZE;
 
+/// Total time needed to erase a page using partial erase, in milliseconds.
+#[cfg(any(
+    feature = "nrf52805",
+    feature = "nrf52810",
+    feature = "nrf52811",
+    feature = "nrf52820",
+    feature = "nrf52833",
+    feature = "nrf52840"
+))]
+const PAGE_ERASE_DURATION_MS: u8 = 85;
+
 /// Error type for NVMC operations.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -28,6 +43,16 @@ pub enum Error {
     OutOfBounds,
     /// Unaligned operation or using unaligned buffers.
     Unaligned,
+    /// Flash operation through the SoftDevice failed.
+    #[cfg(feature = "softdevice")]
+    SoftDevice(crate::softdevice::FlashError),
+}
+
+#[cfg(feature = "softdevice")]
+impl From<crate::softdevice::FlashError> for Error {
+    fn from(e: crate::softdevice::FlashError) -> Self {
+        Self::SoftDevice(e)
+    }
 }
 
 impl NorFlashError for Error {
@@ -35,20 +60,67 @@ impl NorFlashError for Error {
         match self {
             Self::OutOfBounds => NorFlashErrorKind::OutOfBounds,
             Self::Unaligned => NorFlashErrorKind::NotAligned,
+            #[cfg(feature = "softdevice")]
+            Self::SoftDevice(_) => NorFlashErrorKind::Other,
         }
     }
 }
 
 /// Non-Volatile Memory Controller (NVMC) that implements the `embedded-storage` traits.
+///
+/// Both the blocking and async traits are implemented. The CPU is halted while flash
+/// is being erased, so the async erase yields between pages to let other tasks run.
+/// On chips supporting it, pages are erased in several partial erase steps, bounding
+/// the time spent with the CPU halted to the duration of a single step, see
+/// `Nvmc::set_partial_erase_duration`.
 pub struct Nvmc<'d> {
     _p: PeripheralRef<'d, NVMC>,
+    #[cfg(any(
+        feature = "nrf52805",
+        feature = "nrf52810",
+        feature = "nrf52811",
+        feature = "nrf52820",
+        feature = "nrf52833",
+        feature = "nrf52840"
+    ))]
+    partial_erase_ms: u8,
 }
 
 impl<'d> Nvmc<'d> {
     /// Create Nvmc driver.
     pub fn new(_p: impl Peripheral<P = NVMC> + 'd) -> Self {
         into_ref!(_p);
-        Self { _p }
+        Self {
+            _p,
+            #[cfg(any(
+                feature = "nrf52805",
+                feature = "nrf52810",
+                feature = "nrf52811",
+                feature = "nrf52820",
+                feature = "nrf52833",
+                feature = "nrf52840"
+            ))]
+            partial_erase_ms: 10,
+        }
+    }
+
+    /// Set the duration of a single partial erase step used by the async erase, in milliseconds.
+    ///
+    /// Shorter steps reduce the time the CPU is halted at once, at the cost of a longer total
+    /// erase time. Defaults to 10ms.
+    ///
+    /// Panics if `ms` is zero or larger than 127.
+    #[cfg(any(
+        feature = "nrf52805",
+        feature = "nrf52810",
+        feature = "nrf52811",
+        feature = "nrf52820",
+        feature = "nrf52833",
+        feature = "nrf52840"
+    ))]
+    pub fn set_partial_erase_duration(&mut self, ms: u8) {
+        assert!(ms > 0 && ms < 128);
+        self.partial_erase_ms = ms;
     }
 
     fn regs() -> &'static pac::nvmc::RegisterBlock {
@@ -84,6 +156,63 @@ impl<'d> Nvmc<'d> {
         }
     }
 
+    #[cfg(any(
+        feature = "nrf52805",
+        feature = "nrf52810",
+        feature = "nrf52811",
+        feature = "nrf52820",
+        feature = "nrf52833",
+        feature = "nrf52840"
+    ))]
+    async fn erase_page_async(&mut self, page_addr: u32) {
+        let p = Self::regs();
+        p.erasepagepartialcfg
+            .write(|w| unsafe { w.duration().bits(self.partial_erase_ms) });
+
+        let mut elapsed = 0;
+        while elapsed < PAGE_ERASE_DURATION_MS {
+            p.erasepagepartial
+                .write(|w| unsafe { w.erasepagepartial().bits(page_addr) });
+            self.wait_ready();
+            elapsed = elapsed.saturating_add(self.partial_erase_ms);
+            yield_now().await;
+        }
+    }
+
+    #[cfg(not(any(
+        feature = "nrf52805",
+        feature = "nrf52810",
+        feature = "nrf52811",
+        feature = "nrf52820",
+        feature = "nrf52833",
+        feature = "nrf52840"
+    )))]
+    async fn erase_page_async(&mut self, page_addr: u32) {
+        self.erase_page(page_addr);
+        self.wait_ready();
+        yield_now().await;
+    }
+
+    fn check_erase(from: u32, to: u32) -> Result<(), Error> {
+        if to < from || to as usize > FLASH_SIZE {
+            return Err(Error::OutOfBounds);
+        }
+        if from as usize % PAGE_SIZE != 0 || to as usize % PAGE_SIZE != 0 {
+            return Err(Error::Unaligned);
+        }
+        Ok(())
+    }
+
+    fn check_write(offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        if offset as usize + bytes.len() > FLASH_SIZE {
+            return Err(Error::OutOfBounds);
+        }
+        if offset as usize % 4 != 0 || bytes.len() % 4 != 0 {
+            return Err(Error::Unaligned);
+        }
+        Ok(())
+    }
+
     fn enable_erase(&self) {
         #[cfg(not(feature = "_ns"))]
         Self::regs().config.write(|w| w.wen().een());
@@ -91,7 +220,7 @@ impl<'d> Nvmc<'d> {
         Self::regs().configns.write(|w| w.wen().een());
     }
 
-    fn enable_read(&self) {
+    fn enable_read() {
         #[cfg(not(feature = "_ns"))]
         Self::regs().config.write(|w| w.wen().ren());
         #[cfg(feature = "_ns")]
@@ -135,11 +264,14 @@ impl<'d> NorFlash for Nvmc<'d> {
     const ERASE_SIZE: usize = PAGE_SIZE;
 
     fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
-        if to < from || to as usize > FLASH_SIZE {
-            return Err(Error::OutOfBounds);
-        }
-        if from as usize % PAGE_SIZE != 0 || to as usize % PAGE_SIZE != 0 {
-            return Err(Error::Unaligned);
+        Self::check_erase(from, to)?;
+
+        #[cfg(feature = "softdevice")]
+        if let Some(hooks) = softdevice::flash_hooks() {
+            for page_addr in (from..to).step_by(PAGE_SIZE) {
+                softdevice::blocking_flash_op(|| unsafe { (hooks.erase)(page_addr / PAGE_SIZE as u32) })?;
+            }
+            return Ok(());
         }
 
         self.enable_erase();
@@ -150,18 +282,24 @@ impl<'d> NorFlash for Nvmc<'d> {
             self.wait_ready();
         }
 
-        self.enable_read();
+        Self::enable_read();
         self.wait_ready();
 
         Ok(())
     }
 
     fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
-        if offset as usize + bytes.len() > FLASH_SIZE {
-            return Err(Error::OutOfBounds);
-        }
-        if offset as usize % 4 != 0 || bytes.len() % 4 != 0 {
-            return Err(Error::Unaligned);
+        Self::check_write(offset, bytes)?;
+
+        #[cfg(feature = "softdevice")]
+        if let Some(hooks) = softdevice::flash_hooks() {
+            let mut buf = [0u32; SD_WRITE_WORDS];
+            for (i, chunk) in bytes.chunks(SD_WRITE_WORDS * 4).enumerate() {
+                let dst = offset + (i * SD_WRITE_WORDS * 4) as u32;
+                let words = copy_words(&mut buf, chunk);
+                softdevice::blocking_flash_op(|| unsafe { (hooks.write)(dst as *mut u32, buf.as_ptr(), words) })?;
+            }
+            return Ok(());
         }
 
         self.enable_write();
@@ -178,9 +316,93 @@ impl<'d> NorFlash for Nvmc<'d> {
             }
         }
 
-        self.enable_read();
+        Self::enable_read();
         self.wait_ready();
 
         Ok(())
     }
 }
+
+// Number of words written per SoftDevice flash operation. The data is copied
+// to a word-aligned buffer first, as the SoftDevice requires.
+#[cfg(feature = "softdevice")]
+const SD_WRITE_WORDS: usize = 64;
+
+#[cfg(feature = "softdevice")]
+fn copy_words(buf: &mut [u32; SD_WRITE_WORDS], bytes: &[u8]) -> u32 {
+    for (word, b) in buf.iter_mut().zip(bytes.chunks_exact(4)) {
+        *word = u32::from_ne_bytes([b[0], b[1], b[2], b[3]]);
+    }
+    (bytes.len() / 4) as u32
+}
+
+mod _eh1 {
+    use embedded_storage_async::nor_flash::{NorFlash as AsyncNorFlash, ReadNorFlash as AsyncReadNorFlash};
+
+    use super::*;
+
+    impl<'d> AsyncNorFlash for Nvmc<'d> {
+        const WRITE_SIZE: usize = <Self as NorFlash>::WRITE_SIZE;
+        const ERASE_SIZE: usize = <Self as NorFlash>::ERASE_SIZE;
+
+        async fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Self::Error> {
+            #[cfg(feature = "softdevice")]
+            if let Some(hooks) = softdevice::flash_hooks() {
+                Self::check_write(offset, data)?;
+                let mut buf = [0u32; SD_WRITE_WORDS];
+                for (i, chunk) in data.chunks(SD_WRITE_WORDS * 4).enumerate() {
+                    let dst = offset + (i * SD_WRITE_WORDS * 4) as u32;
+                    let words = copy_words(&mut buf, chunk);
+                    softdevice::flash_op(|| unsafe { (hooks.write)(dst as *mut u32, buf.as_ptr(), words) }).await?;
+                }
+                return Ok(());
+            }
+
+            NorFlash::write(self, offset, data)
+        }
+
+        async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+            Self::check_erase(from, to)?;
+
+            #[cfg(feature = "softdevice")]
+            if let Some(hooks) = softdevice::flash_hooks() {
+                for page_addr in (from..to).step_by(PAGE_SIZE) {
+                    softdevice::flash_op(|| unsafe { (hooks.erase)(page_addr / PAGE_SIZE as u32) }).await?;
+                }
+                return Ok(());
+            }
+
+            self.enable_erase();
+            self.wait_ready();
+
+            // Pages are only erased while polled, so flash is ready for reading
+            // again once erase is disabled, even if the erase is cancelled.
+            let on_drop = OnDrop::new(|| {
+                Self::enable_read();
+                while Self::regs().ready.read().ready().is_busy() {}
+            });
+
+            for page_addr in (from..to).step_by(PAGE_SIZE) {
+                self.erase_page_async(page_addr).await;
+            }
+
+            drop(on_drop);
+
+            Ok(())
+        }
+    }
+
+    impl<'d> AsyncReadNorFlash for Nvmc<'d> {
+        const READ_SIZE: usize = <Self as ReadNorFlash>::READ_SIZE;
+
+        async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+            ReadNorFlash::read(self, offset, bytes)
+        }
+
+        fn capacity(&self) -> usize {
+            FLASH_SIZE
+        }
+    }
+
+    impl<'d> embedded_storage_async::nor_flash::MultiwriteNorFlash for Nvmc<'d> {}
+}
@@ -2,11 +2,18 @@
 
 #![macro_use]
 
+use core::future::poll_fn;
+use core::marker::PhantomData;
 use core::sync::atomic::{compiler_fence, Ordering};
+use core::task::Poll;
 
+use embassy_embedded_hal::smart_led::{Rgb8, SmartLedWrite};
+use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::{into_ref, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
 
 use crate::gpio::{AnyPin, Pin as GpioPin, PselBits, SealedPin as _};
+use crate::interrupt::typelevel::Interrupt;
 use crate::ppi::{Event, Task};
 use crate::util::slice_in_ram_or;
 use crate::{interrupt, pac, Peripheral};
@@ -30,6 +37,29 @@ pub struct SequencePwm<'d, T: Instance> {
     ch1: Option<PeripheralRef<'d, AnyPin>>,
     ch2: Option<PeripheralRef<'d, AnyPin>>,
     ch3: Option<PeripheralRef<'d, AnyPin>>,
+    irq_bound: bool,
+}
+
+/// Interrupt handler.
+///
+/// Only needed for waiting for the end of a sequence playback.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let r = T::regs();
+
+        if r.events_loopsdone.read().bits() != 0 {
+            r.intenclr.write(|w| w.loopsdone().clear());
+            T::state().end_waker.wake();
+        }
+        if r.events_stopped.read().bits() != 0 {
+            r.intenclr.write(|w| w.stopped().clear());
+            T::state().end_waker.wake();
+        }
+    }
 }
 
 /// PWM error
@@ -43,6 +73,8 @@ pub enum Error {
     SequenceTimesAtLeastOne,
     /// EasyDMA can only read from data memory, read only buffers in flash will fail.
     BufferNotInRAM,
+    /// The buffer is too small for the number of LEDs.
+    BufferTooSmall,
 }
 
 const MAX_SEQUENCE_LEN: usize = 32767;
@@ -177,9 +209,18 @@ impl<'d, T: Instance> SequencePwm<'d, T> {
             ch1,
             ch2,
             ch3,
+            irq_bound: false,
         })
     }
 
+    /// Enable the PWM interrupt, allowing to wait for the end of playback with
+    /// [`Sequencer::wait`] and [`SingleSequencer::wait`].
+    pub fn bind_interrupt(&mut self, _irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd) {
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+        self.irq_bound = true;
+    }
+
     /// Returns reference to `Stopped` event endpoint for PPI.
     #[inline(always)]
     pub fn event_stopped(&self) -> Event<'d> {
@@ -401,6 +442,11 @@ impl<'d, 's, T: Instance> SingleSequencer<'d, 's, T> {
     pub fn stop(&self) {
         self.sequencer.stop();
     }
+
+    /// Wait for playback to end. See [`Sequencer::wait`].
+    pub async fn wait(&self) {
+        self.sequencer.wait().await
+    }
 }
 
 /// A composition of sequences that can be started and stopped.
@@ -410,7 +456,7 @@ impl<'d, 's, T: Instance> SingleSequencer<'d, 's, T> {
 /// is used.
 #[non_exhaustive]
 pub struct Sequencer<'d, 's, T: Instance> {
-    _pwm: &'s mut SequencePwm<'d, T>,
+    pwm: &'s mut SequencePwm<'d, T>,
     sequence0: Sequence<'s>,
     sequence1: Option<Sequence<'s>>,
 }
@@ -420,7 +466,7 @@ impl<'d, 's, T: Instance> Sequencer<'d, 's, T> {
     /// will be used twice in the one loop.
     pub fn new(pwm: &'s mut SequencePwm<'d, T>, sequence0: Sequence<'s>, sequence1: Option<Sequence<'s>>) -> Self {
         Sequencer {
-            _pwm: pwm,
+            pwm,
             sequence0,
             sequence1,
         }
@@ -463,6 +509,9 @@ impl<'d, 's, T: Instance> Sequencer<'d, 's, T> {
 
         r.enable.write(|w| w.enable().enabled());
 
+        r.events_loopsdone.reset();
+        r.events_stopped.reset();
+
         // defensive before seqstart
         compiler_fence(Ordering::SeqCst);
 
@@ -502,6 +551,48 @@ impl<'d, 's, T: Instance> Sequencer<'d, 's, T> {
 
         r.enable.write(|w| w.enable().disabled());
     }
+
+    /// Wait for playback to end, once all loops are done or it is stopped.
+    ///
+    /// Playback started with [`SequenceMode::Infinite`] only ends when stopped,
+    /// for example through PPI.
+    ///
+    /// Panics if the interrupt wasn't bound with [`SequencePwm::bind_interrupt`].
+    pub async fn wait(&self) {
+        assert!(self.pwm.irq_bound, "PWM interrupt not bound");
+
+        let r = T::regs();
+
+        let on_drop = OnDrop::new(|| {
+            T::regs().intenclr.write(|w| w.loopsdone().clear().stopped().clear());
+        });
+
+        // Infinite playback restarts on LOOPSDONE, so only STOPPED ends it.
+        let infinite = r.shorts.read().loopsdone_seqstart0().is_enabled();
+        r.intenset.write(|w| {
+            if !infinite {
+                w.loopsdone().set();
+            }
+            w.stopped().set()
+        });
+
+        poll_fn(|cx| {
+            T::state().end_waker.register(cx.waker());
+
+            // Playback stopped with `stop` also disables the peripheral.
+            if (!infinite && r.events_loopsdone.read().bits() != 0)
+                || r.events_stopped.read().bits() != 0
+                || r.enable.read().enable().is_disabled()
+            {
+                return Poll::Ready(());
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        drop(on_drop);
+    }
 }
 
 impl<'d, 's, T: Instance> Drop for Sequencer<'d, 's, T> {
@@ -510,6 +601,81 @@ impl<'d, 's, T: Instance> Drop for Sequencer<'d, 's, T> {
     }
 }
 
+// Setting the high bit reverses the polarity, so the period starts high.
+const WS2812_T0H: u16 = 0x8000 | 7; // 0.4us
+const WS2812_T1H: u16 = 0x8000 | 13; // 0.8us
+const WS2812_RES: u16 = 0x8000;
+
+/// Number of sequence words per LED for [`Ws2812`].
+pub const WS2812_WORDS_PER_LED: usize = 24;
+
+/// WS2812 addressable LED driver, using a PWM sequence.
+///
+/// Each data bit is one PWM period of 1.25us. The buffer must be in RAM, and hold
+/// [`WS2812_WORDS_PER_LED`] words per LED plus one.
+pub struct Ws2812<'d, 'a, T: Instance> {
+    pwm: SequencePwm<'d, T>,
+    buf: &'a mut [u16],
+}
+
+impl<'d, 'a, T: Instance> Ws2812<'d, 'a, T> {
+    /// Create a new WS2812 driver.
+    pub fn new(
+        pwm: impl Peripheral<P = T> + 'd,
+        pin: impl Peripheral<P = impl GpioPin> + 'd,
+        irq: impl interrupt::typelevel::Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        buf: &'a mut [u16],
+    ) -> Result<Self, Error> {
+        let config = Config {
+            prescaler: Prescaler::Div1,
+            max_duty: 20,
+            ..Default::default()
+        };
+        let mut pwm = SequencePwm::new_1ch(pwm, pin, config)?;
+        pwm.bind_interrupt(irq);
+        Ok(Self { pwm, buf })
+    }
+
+    /// Write the colors of the LEDs.
+    pub async fn write(&mut self, colors: &[Rgb8]) -> Result<(), Error> {
+        let len = colors.len() * WS2812_WORDS_PER_LED + 1;
+        if self.buf.len() < len {
+            return Err(Error::BufferTooSmall);
+        }
+
+        for (color, out) in colors.iter().zip(self.buf.chunks_exact_mut(WS2812_WORDS_PER_LED)) {
+            // WS2812 expects green first.
+            let bits = (u32::from(color.g) << 16) | (u32::from(color.r) << 8) | u32::from(color.b);
+            for (i, out) in out.iter_mut().enumerate() {
+                *out = if bits & (1 << (23 - i)) != 0 {
+                    WS2812_T1H
+                } else {
+                    WS2812_T0H
+                };
+            }
+        }
+        self.buf[len - 1] = WS2812_RES;
+
+        // Keep the line low for 300us after the data, so the LEDs latch the colors.
+        let config = SequenceConfig {
+            end_delay: 240,
+            ..Default::default()
+        };
+        let sequencer = SingleSequencer::new(&mut self.pwm, &self.buf[..len], config);
+        sequencer.start(SingleSequenceMode::Times(1))?;
+        sequencer.wait().await;
+        Ok(())
+    }
+}
+
+impl<'d, 'a, T: Instance> SmartLedWrite for Ws2812<'d, 'a, T> {
+    type Error = Error;
+
+    async fn write(&mut self, colors: &[Rgb8]) -> Result<(), Self::Error> {
+        Ws2812::write(self, colors).await
+    }
+}
+
 /// How many times to run a single sequence
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 pub enum SingleSequenceMode {
@@ -846,8 +1012,21 @@ impl<'a, T: Instance> Drop for SimplePwm<'a, T> {
     }
 }
 
+pub(crate) struct State {
+    end_waker: AtomicWaker,
+}
+
+impl State {
+    pub(crate) const fn new() -> Self {
+        Self {
+            end_waker: AtomicWaker::new(),
+        }
+    }
+}
+
 pub(crate) trait SealedInstance {
     fn regs() -> &'static pac::pwm0::RegisterBlock;
+    fn state() -> &'static State;
 }
 
 /// PWM peripheral instance.
@@ -863,6 +1042,10 @@ macro_rules! impl_pwm {
             fn regs() -> &'static pac::pwm0::RegisterBlock {
                 unsafe { &*pac::$pac_type::ptr() }
             }
+            fn state() -> &'static crate::pwm::State {
+                static STATE: crate::pwm::State = crate::pwm::State::new();
+                &STATE
+            }
         }
         impl crate::pwm::Instance for peripherals::$type {
             type Interrupt = crate::interrupt::typelevel::$irq;
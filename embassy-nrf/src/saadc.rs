@@ -17,7 +17,7 @@ use saadc::oversample::OVERSAMPLE_A;
 use saadc::resolution::VAL_A;
 
 use crate::interrupt::InterruptExt;
-use crate::ppi::{ConfigurableChannel, Event, Ppi, Task};
+use crate::ppi::{AnyConfigurableChannel, ConfigurableChannel, Event, Ppi, Task};
 use crate::timer::{Frequency, Instance as TimerInstance, Timer};
 use crate::{interrupt, pac, peripherals, Peripheral};
 
@@ -123,6 +123,45 @@ impl<'d> ChannelConfig<'d> {
             n_channel: Some(n_input.map_into()),
         }
     }
+
+    /// Configuration for measuring the supply voltage, VDD (VDDGPIO on the nRF9160).
+    ///
+    /// Samples can be converted with [`to_millivolts`].
+    pub fn vdd() -> Self {
+        Self::single_ended(VddInput)
+    }
+
+    /// Configuration for measuring VDDH / 5, for example when powered from USB or a battery
+    /// connected to VDDH.
+    ///
+    /// Samples can be converted with [`to_millivolts`], and multiplied by 5 to get VDDH.
+    #[cfg(any(feature = "_nrf5340-app", feature = "nrf52833", feature = "nrf52840"))]
+    pub fn vddh_div5() -> Self {
+        Self::single_ended(VddhDiv5Input)
+    }
+}
+
+/// Convert a single-ended sample taken with the internal 0.6V reference to millivolts.
+///
+/// The full scale of the SAADC is `0.6V / gain`, for example 3.6V with [`Gain::GAIN1_6`].
+pub fn to_millivolts(sample: i16, gain: Gain, resolution: Resolution) -> i32 {
+    let (num, den) = match gain {
+        Gain::GAIN1_6 => (1, 6),
+        Gain::GAIN1_5 => (1, 5),
+        Gain::GAIN1_4 => (1, 4),
+        Gain::GAIN1_3 => (1, 3),
+        Gain::GAIN1_2 => (1, 2),
+        Gain::GAIN1 => (1, 1),
+        Gain::GAIN2 => (2, 1),
+        Gain::GAIN4 => (4, 1),
+    };
+    let bits = match resolution {
+        Resolution::_8BIT => 8,
+        Resolution::_10BIT => 10,
+        Resolution::_12BIT => 12,
+        Resolution::_14BIT => 14,
+    };
+    sample as i32 * 600 * den / num / (1 << bits)
 }
 
 /// Value returned by the SAADC callback, deciding what happens next.
@@ -134,6 +173,34 @@ pub enum CallbackResult {
     Stop,
 }
 
+/// Sample rate used for continuous sampling driven by a TIMER.
+pub struct SampleRate {
+    frequency: Frequency,
+    counter: u32,
+}
+
+impl SampleRate {
+    /// Sample rate in Hz.
+    ///
+    /// The TIMER is clocked at 16MHz, so the actual rate is `16_000_000 / (16_000_000 / hz)`.
+    ///
+    /// Panics if `hz` is zero or exceeds the 200kHz maximum of the SAADC.
+    pub const fn hz(hz: u32) -> Self {
+        core::assert!(hz != 0 && hz <= 200_000);
+        Self {
+            frequency: Frequency::F16MHz,
+            counter: 16_000_000 / hz,
+        }
+    }
+
+    /// Sample rate expressed as a timer clock frequency and a counter threshold.
+    /// For example, 1KHz can be achieved using a frequency of 1MHz and a counter
+    /// threshold of 1000.
+    pub const fn from_timer(frequency: Frequency, counter: u32) -> Self {
+        Self { frequency, counter }
+    }
+}
+
 /// One-shot and continuous SAADC.
 pub struct Saadc<'d, const N: usize> {
     _p: PeripheralRef<'d, peripherals::SAADC>,
@@ -336,6 +403,65 @@ impl<'d, const N: usize> Saadc<'d, N> {
         .await;
     }
 
+    /// Start continuous sampling with double buffers, returning a [`ContinuousSampler`]
+    /// from which filled buffers are awaited.
+    ///
+    /// As with [`Saadc::run_task_sampler`], a TIMER and two PPI channels are used to
+    /// trigger sampling of all channels at the given `sample_rate`, and the SAADC
+    /// is restarted on the next buffer as soon as one is filled so that no samples
+    /// are missed.
+    ///
+    /// NOTE: [`ContinuousSampler::next`] must be called again before the other
+    /// buffer has been filled. Otherwise that buffer is overwritten and its samples
+    /// are dropped.
+    ///
+    /// Sampling is stopped when the returned [`ContinuousSampler`] is dropped.
+    pub fn start_continuous<'a, T: TimerInstance, const N0: usize>(
+        &'a mut self,
+        timer: impl Peripheral<P = T> + 'a,
+        ppi_ch1: impl Peripheral<P = impl ConfigurableChannel> + 'a,
+        ppi_ch2: impl Peripheral<P = impl ConfigurableChannel> + 'a,
+        sample_rate: SampleRate,
+        bufs: &'a mut [[[i16; N]; N0]; 2],
+    ) -> ContinuousSampler<'a, 'd, T, N, N0> {
+        into_ref!(ppi_ch1, ppi_ch2);
+
+        let r = Self::regs();
+
+        let mut start_ppi = Ppi::new_one_to_one(
+            ppi_ch1.map_into(),
+            Event::from_reg(&r.events_end),
+            Task::from_reg(&r.tasks_start),
+        );
+        start_ppi.enable();
+
+        let timer = Timer::new(timer);
+        timer.set_frequency(sample_rate.frequency);
+        timer.cc(0).write(sample_rate.counter);
+        timer.cc(0).short_compare_clear();
+
+        let sample_ppi = Ppi::new_one_to_one(
+            ppi_ch2.map_into(),
+            timer.cc(0).event_compare(),
+            Task::from_reg(&r.tasks_sample),
+        );
+
+        timer.start();
+
+        Self::start_sampler(bufs, None);
+
+        ContinuousSampler {
+            _saadc: self,
+            timer,
+            _start_ppi: start_ppi,
+            sample_ppi,
+            bufs,
+            current: 0,
+            started: false,
+            sampling: false,
+        }
+    }
+
     async fn run_sampler<I, F, const N0: usize>(
         &mut self,
         bufs: &mut [[[i16; N]; N0]; 2],
@@ -349,45 +475,7 @@ impl<'d, const N: usize> Saadc<'d, N> {
         // In case the future is dropped, stop the task and wait for it to end.
         let on_drop = OnDrop::new(Self::stop_sampling_immediately);
 
-        let r = Self::regs();
-
-        // Establish mode and sample rate
-        match sample_rate_divisor {
-            Some(sr) => {
-                r.samplerate.write(|w| unsafe {
-                    w.cc().bits(sr);
-                    w.mode().timers();
-                    w
-                });
-                r.tasks_sample.write(|w| unsafe { w.bits(1) }); // Need to kick-start the internal timer
-            }
-            None => r.samplerate.write(|w| unsafe {
-                w.cc().bits(0);
-                w.mode().task();
-                w
-            }),
-        }
-
-        // Set up the initial DMA
-        r.result
-            .ptr
-            .write(|w| unsafe { w.ptr().bits(bufs[0].as_mut_ptr() as u32) });
-        r.result.maxcnt.write(|w| unsafe { w.maxcnt().bits((N0 * N) as _) });
-
-        // Reset and enable the events
-        r.events_end.reset();
-        r.events_started.reset();
-        r.intenset.write(|w| {
-            w.end().set();
-            w.started().set();
-            w
-        });
-
-        // Don't reorder the ADC start event before the previous writes. Hopefully self
-        // wouldn't happen anyway.
-        compiler_fence(Ordering::SeqCst);
-
-        r.tasks_start.write(|w| unsafe { w.bits(1) });
+        Self::start_sampler(bufs, sample_rate_divisor);
 
         let mut inited = false;
 
@@ -440,6 +528,49 @@ impl<'d, const N: usize> Saadc<'d, N> {
         r
     }
 
+    // Set up double buffered sampling into the first buffer and start the SAADC.
+    fn start_sampler<const N0: usize>(bufs: &mut [[[i16; N]; N0]; 2], sample_rate_divisor: Option<u16>) {
+        let r = Self::regs();
+
+        // Establish mode and sample rate
+        match sample_rate_divisor {
+            Some(sr) => {
+                r.samplerate.write(|w| unsafe {
+                    w.cc().bits(sr);
+                    w.mode().timers();
+                    w
+                });
+                r.tasks_sample.write(|w| unsafe { w.bits(1) }); // Need to kick-start the internal timer
+            }
+            None => r.samplerate.write(|w| unsafe {
+                w.cc().bits(0);
+                w.mode().task();
+                w
+            }),
+        }
+
+        // Set up the initial DMA
+        r.result
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(bufs[0].as_mut_ptr() as u32) });
+        r.result.maxcnt.write(|w| unsafe { w.maxcnt().bits((N0 * N) as _) });
+
+        // Reset and enable the events
+        r.events_end.reset();
+        r.events_started.reset();
+        r.intenset.write(|w| {
+            w.end().set();
+            w.started().set();
+            w
+        });
+
+        // Don't reorder the ADC start event before the previous writes. Hopefully self
+        // wouldn't happen anyway.
+        compiler_fence(Ordering::SeqCst);
+
+        r.tasks_start.write(|w| unsafe { w.bits(1) });
+    }
+
     // Stop sampling and wait for it to stop in a blocking fashion
     fn stop_sampling_immediately() {
         let r = Self::regs();
@@ -477,6 +608,78 @@ impl<'d> Saadc<'d, 1> {
     }
 }
 
+/// Continuous sampler started by [`Saadc::start_continuous`].
+pub struct ContinuousSampler<'a, 'd, T: TimerInstance, const N: usize, const N0: usize> {
+    _saadc: &'a mut Saadc<'d, N>,
+    timer: Timer<'a, T>,
+    _start_ppi: Ppi<'a, AnyConfigurableChannel, 1, 1>,
+    sample_ppi: Ppi<'a, AnyConfigurableChannel, 1, 1>,
+    bufs: &'a mut [[[i16; N]; N0]; 2],
+    // Buffer currently being filled by the SAADC.
+    current: usize,
+    // Whether the STARTED event of the current buffer has been handled.
+    started: bool,
+    // Whether the TIMER has been connected to the sample task.
+    sampling: bool,
+}
+
+impl<'a, 'd, T: TimerInstance, const N: usize, const N0: usize> ContinuousSampler<'a, 'd, T, N, N0> {
+    /// Wait for the next buffer of samples to be filled, and return it.
+    ///
+    /// The SAADC keeps filling the other buffer while the returned one is borrowed.
+    pub async fn next(&mut self) -> &[[i16; N]] {
+        let done = poll_fn(|cx| {
+            let r = Saadc::<N>::regs();
+
+            WAKER.register(cx.waker());
+
+            // The STARTED event of the buffer following the current one is only handled
+            // once the current buffer has been returned and released by the caller, so
+            // that the SAADC never gets to write into a buffer that is still borrowed.
+            if !self.started && r.events_started.read().bits() != 0 {
+                r.events_started.reset();
+                r.intenset.write(|w| w.started().set());
+
+                if !self.sampling {
+                    self.sample_ppi.enable();
+                    self.sampling = true;
+                }
+
+                let next_buffer = 1 - self.current;
+                r.result
+                    .ptr
+                    .write(|w| unsafe { w.ptr().bits(self.bufs[next_buffer].as_mut_ptr() as u32) });
+                self.started = true;
+            }
+
+            if self.started && r.events_end.read().bits() != 0 {
+                compiler_fence(Ordering::SeqCst);
+
+                r.events_end.reset();
+                r.intenset.write(|w| w.end().set());
+
+                let done = self.current;
+                self.current = 1 - self.current;
+                self.started = false;
+                return Poll::Ready(done);
+            }
+
+            Poll::Pending
+        })
+        .await;
+
+        &self.bufs[done]
+    }
+}
+
+impl<'a, 'd, T: TimerInstance, const N: usize, const N0: usize> Drop for ContinuousSampler<'a, 'd, T, N, N0> {
+    fn drop(&mut self) {
+        self.sample_ppi.disable();
+        self.timer.stop();
+        Saadc::<N>::stop_sampling_immediately();
+    }
+}
+
 impl<'d, const N: usize> Drop for Saadc<'d, N> {
     fn drop(&mut self) {
         let r = Self::regs();
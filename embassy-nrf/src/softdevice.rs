@@ -0,0 +1,203 @@
+//! Coexistence with the Nordic SoftDevice.
+//!
+//! When a SoftDevice is enabled, it owns some interrupt priority levels, the flash controller,
+//! and peripherals like RADIO and TIMER0. This module provides the glue needed for
+//! `embassy-nrf` to run alongside it:
+//!
+//! - [`init`](crate::init) gives every interrupt a priority the SoftDevice allows
+//!   applications to use, and checks the configured ones.
+//! - [`Nvmc`](crate::nvmc::Nvmc) routes flash operations through the [`FlashHooks`] set with
+//!   [`set_flash_hooks`], which should call `sd_flash_write` and `sd_flash_page_erase`.
+//!   Completion must be reported from the SoftDevice SoC event handler with [`on_flash_operation`].
+//! - RADIO and TIMER0 are only available to the application while the SoftDevice is disabled
+//!   or during a radio timeslot, which is tracked by [`set_enabled`], [`on_timeslot_start`] and
+//!   [`on_timeslot_end`], and can be waited for with [`wait_for_radio_access`].
+
+use core::cell::Cell;
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+
+use critical_section::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::interrupt::Priority;
+
+/// Interrupt priority levels reserved by the SoftDevice.
+pub const RESERVED_PRIORITIES: [Priority; 3] = [Priority::P0, Priority::P1, Priority::P4];
+
+/// Priority given to all interrupts at initialization.
+pub const DEFAULT_PRIORITY: Priority = Priority::P7;
+
+/// Returns `true` if the application is allowed to use `priority`.
+pub fn is_priority_allowed(priority: Priority) -> bool {
+    !RESERVED_PRIORITIES.contains(&priority)
+}
+
+pub(crate) fn init(config: &crate::config::Config) {
+    #[cfg(feature = "gpiote")]
+    assert!(
+        is_priority_allowed(config.gpiote_interrupt_priority),
+        "GPIOTE interrupt priority is reserved by the SoftDevice"
+    );
+    #[cfg(feature = "_time-driver")]
+    assert!(
+        is_priority_allowed(config.time_interrupt_priority),
+        "time driver interrupt priority is reserved by the SoftDevice"
+    );
+    #[cfg(not(any(feature = "gpiote", feature = "_time-driver")))]
+    let _ = config;
+
+    // All interrupts default to priority 0, which the SoftDevice refuses to enable.
+    // The SoftDevice sets the priorities of its own interrupts when enabled.
+    let nvic = unsafe { &*cortex_m::peripheral::NVIC::PTR };
+    for ipr in nvic.ipr.iter() {
+        unsafe { ipr.write(DEFAULT_PRIORITY as u8) };
+    }
+}
+
+/// Functions starting SoftDevice flash operations.
+///
+/// Both return the SoftDevice error code, 0 meaning the operation was started. The
+/// operation then completes in the background, which must be reported with [`on_flash_operation`].
+#[derive(Clone, Copy)]
+pub struct FlashHooks {
+    /// Write `words` words from `src` to `dst`, like `sd_flash_write`.
+    pub write: unsafe fn(dst: *mut u32, src: *const u32, words: u32) -> u32,
+    /// Erase the page with number `page`, like `sd_flash_page_erase`.
+    pub erase: unsafe fn(page: u32) -> u32,
+}
+
+/// Error of a SoftDevice flash operation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FlashError {
+    /// The SoftDevice refused to start the operation, with the given error code.
+    Rejected(u32),
+    /// The SoftDevice reported the operation as failed, for example because of a timeout.
+    Failed,
+}
+
+const NRF_ERROR_BUSY: u32 = 17;
+
+static FLASH_HOOKS: Mutex<Cell<Option<FlashHooks>>> = Mutex::new(Cell::new(None));
+static FLASH_DONE: Signal<CriticalSectionRawMutex, bool> = Signal::new();
+
+/// Route flash operations through the SoftDevice.
+///
+/// Call this once the SoftDevice is enabled, and [`clear_flash_hooks`] before disabling it.
+pub fn set_flash_hooks(hooks: FlashHooks) {
+    critical_section::with(|cs| FLASH_HOOKS.borrow(cs).set(Some(hooks)));
+}
+
+/// Access the flash controller directly again.
+pub fn clear_flash_hooks() {
+    critical_section::with(|cs| FLASH_HOOKS.borrow(cs).set(None));
+}
+
+/// Report completion of a flash operation.
+///
+/// Call this from the SoftDevice SoC event handler on `NRF_EVT_FLASH_OPERATION_SUCCESS`
+/// (`success = true`) and `NRF_EVT_FLASH_OPERATION_ERROR` (`success = false`).
+pub fn on_flash_operation(success: bool) {
+    FLASH_DONE.signal(success);
+}
+
+pub(crate) fn flash_hooks() -> Option<FlashHooks> {
+    critical_section::with(|cs| FLASH_HOOKS.borrow(cs).get())
+}
+
+// Start a flash operation, retrying while the SoftDevice is busy, and wait for it to complete.
+pub(crate) fn blocking_flash_op(mut start: impl FnMut() -> u32) -> Result<(), FlashError> {
+    FLASH_DONE.reset();
+    loop {
+        match start() {
+            0 => break,
+            NRF_ERROR_BUSY => continue,
+            err => return Err(FlashError::Rejected(err)),
+        }
+    }
+
+    loop {
+        if let Some(success) = FLASH_DONE.try_take() {
+            return if success { Ok(()) } else { Err(FlashError::Failed) };
+        }
+    }
+}
+
+// Async version of `blocking_flash_op`.
+//
+// The SoftDevice keeps reading the source buffer until the operation completes,
+// so it must not be cancelled: dropping the future blocks until completion.
+pub(crate) async fn flash_op(mut start: impl FnMut() -> u32) -> Result<(), FlashError> {
+    FLASH_DONE.reset();
+    loop {
+        match start() {
+            0 => break,
+            NRF_ERROR_BUSY => embassy_futures::yield_now().await,
+            err => return Err(FlashError::Rejected(err)),
+        }
+    }
+
+    let on_drop = embassy_hal_internal::drop::OnDrop::new(|| while FLASH_DONE.try_take().is_none() {});
+    let success = FLASH_DONE.wait().await;
+    on_drop.defuse();
+
+    if success {
+        Ok(())
+    } else {
+        Err(FlashError::Failed)
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static TIMESLOT: AtomicBool = AtomicBool::new(false);
+static RADIO_WAKER: AtomicWaker = AtomicWaker::new();
+
+/// Record whether the SoftDevice is enabled.
+///
+/// Call this right after `sd_softdevice_enable` and `sd_softdevice_disable`. While the
+/// SoftDevice is enabled, it owns RADIO and TIMER0 outside of radio timeslots.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Release);
+    if !enabled {
+        TIMESLOT.store(false, Ordering::Release);
+    }
+    RADIO_WAKER.wake();
+}
+
+/// Returns `true` if the SoftDevice was recorded as enabled with [`set_enabled`].
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Acquire)
+}
+
+/// Report the start of a radio timeslot, from the `NRF_RADIO_CALLBACK_SIGNAL_TYPE_START` signal.
+pub fn on_timeslot_start() {
+    TIMESLOT.store(true, Ordering::Release);
+    RADIO_WAKER.wake();
+}
+
+/// Report the end of a radio timeslot, right before returning `NRF_RADIO_SIGNAL_CALLBACK_ACTION_END`.
+pub fn on_timeslot_end() {
+    TIMESLOT.store(false, Ordering::Release);
+}
+
+/// Returns `true` if the application may currently use RADIO and TIMER0.
+pub fn has_radio_access() -> bool {
+    !is_enabled() || TIMESLOT.load(Ordering::Acquire)
+}
+
+/// Wait until the application may use RADIO and TIMER0.
+pub async fn wait_for_radio_access() {
+    poll_fn(|cx| {
+        RADIO_WAKER.register(cx.waker());
+        if has_radio_access() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
@@ -40,6 +40,9 @@ impl<'d> Temp<'d> {
     ) -> Self {
         into_ref!(_peri);
 
+        #[cfg(any(feature = "nrf52810", feature = "nrf52832"))]
+        Self::apply_calibration();
+
         // Enable interrupt that signals temperature values
         interrupt::TEMP.unpend();
         unsafe { interrupt::TEMP.enable() };
@@ -95,6 +98,22 @@ impl<'d> Temp<'d> {
         value
     }
 
+    /// Load the factory calibration of the linearity coefficients.
+    ///
+    /// Workaround for errata 66, the default values don't meet the linearity specification.
+    #[cfg(any(feature = "nrf52810", feature = "nrf52832"))]
+    fn apply_calibration() {
+        let t = Self::regs();
+        let f = unsafe { &*pac::FICR::ptr() };
+
+        macro_rules! copy {
+            ($($reg:ident),*) => {
+                $(t.$reg.write(|w| unsafe { w.bits(f.temp.$reg.read().bits()) });)*
+            };
+        }
+        copy!(a0, a1, a2, a3, a4, a5, b0, b1, b2, b3, b4, b5, t0, t1, t2, t3, t4);
+    }
+
     fn regs() -> &'static pac::temp::RegisterBlock {
         unsafe { &*pac::TEMP::ptr() }
     }
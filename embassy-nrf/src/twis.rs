@@ -1,4 +1,4 @@
-//! I2C-compatible Two Wire Interface in slave mode (TWIM) driver.
+//! I2C-compatible Two Wire Interface in slave mode (TWIS) driver.
 
 #![macro_use]
 
@@ -8,10 +8,11 @@ use core::sync::atomic::compiler_fence;
 use core::sync::atomic::Ordering::SeqCst;
 use core::task::Poll;
 
+use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::{into_ref, PeripheralRef};
 use embassy_sync::waitqueue::AtomicWaker;
 #[cfg(feature = "time")]
-use embassy_time::{Duration, Instant};
+use embassy_time::{with_timeout, Duration, Instant};
 
 use crate::chip::{EASY_DMA_SIZE, FORCE_COPY_BUFFER_SIZE};
 use crate::gpio::Pin as GpioPin;
@@ -545,6 +546,23 @@ impl<'d, T: Instance> Twis<'d, T> {
         })
     }
 
+    /// Abort an ongoing or prepared transfer, so EasyDMA no longer accesses the buffers.
+    fn abort() {
+        let r = T::regs();
+        r.intenclr.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+        r.tasks_stop.write(|w| unsafe { w.bits(1) });
+
+        // Disabling the peripheral releases the prepared buffers, even when no
+        // transaction has started yet and STOPPED would never be generated.
+        r.enable.write(|w| w.enable().disabled());
+        r.enable.write(|w| w.enable().enabled());
+
+        r.events_read.reset();
+        r.events_write.reset();
+        r.events_stopped.reset();
+        r.events_error.reset();
+    }
+
     fn setup_respond_from_ram(&mut self, buffer: &[u8], inten: bool) -> Result<(), Error> {
         let r = T::regs();
 
@@ -710,8 +728,17 @@ impl<'d, T: Instance> Twis<'d, T> {
     /// The buffer must have a length of at most 255 bytes on the nRF52832
     /// and at most 65535 bytes on the nRF52840.
     /// To know which one of the addresses were matched, call `address_match` or `address_match_index`
+    ///
+    /// The future can be dropped to stop listening, which aborts any ongoing transfer.
     pub async fn listen(&mut self, buffer: &mut [u8]) -> Result<Command, Error> {
         self.setup_listen(buffer, true)?;
+        let on_drop = OnDrop::new(Self::abort);
+        let res = self.async_listen().await;
+        on_drop.defuse();
+        res
+    }
+
+    async fn async_listen(&mut self) -> Result<Command, Error> {
         let status = self.async_listen_wait().await?;
         if status == Status::Write {
             self.setup_listen_end(true)?;
@@ -725,15 +752,56 @@ impl<'d, T: Instance> Twis<'d, T> {
     /// Returns the number of bytes written.
     /// The buffer must have a length of at most 255 bytes on the nRF52832
     /// and at most 65535 bytes on the nRF52840.
+    ///
+    /// The future can be dropped to abort the transfer.
     pub async fn respond_to_read(&mut self, buffer: &[u8]) -> Result<usize, Error> {
-        self.setup_respond(buffer, true)?;
-        self.async_wait().await
+        match self.respond_to_read_from_ram(buffer).await {
+            Err(Error::BufferNotInRAM) => {
+                trace!("Copying TWIS tx buffer into RAM for DMA");
+                let tx_ram_buf = &mut [0; FORCE_COPY_BUFFER_SIZE][..buffer.len()];
+                tx_ram_buf.copy_from_slice(buffer);
+                self.respond_to_read_from_ram(tx_ram_buf).await
+            }
+            res => res,
+        }
     }
 
     /// Same as [`respond_to_read`](Twis::respond_to_read) but will fail instead of copying data into RAM. Consult the module level documentation to learn more.
     pub async fn respond_to_read_from_ram(&mut self, buffer: &[u8]) -> Result<usize, Error> {
         self.setup_respond_from_ram(buffer, true)?;
-        self.async_wait().await
+        let on_drop = OnDrop::new(Self::abort);
+        let res = self.async_wait().await;
+        on_drop.defuse();
+        res
+    }
+
+    // ===========================================
+
+    /// Wait asynchronously for commands from an I2C master, with timeout.
+    /// See [`listen`](Twis::listen).
+    #[cfg(feature = "time")]
+    pub async fn listen_timeout(&mut self, buffer: &mut [u8], timeout: Duration) -> Result<Command, Error> {
+        with_timeout(timeout, self.listen(buffer))
+            .await
+            .unwrap_or(Err(Error::Timeout))
+    }
+
+    /// Respond to an I2C master READ command asynchronously, with timeout.
+    /// See [`respond_to_read`](Twis::respond_to_read).
+    #[cfg(feature = "time")]
+    pub async fn respond_to_read_timeout(&mut self, buffer: &[u8], timeout: Duration) -> Result<usize, Error> {
+        with_timeout(timeout, self.respond_to_read(buffer))
+            .await
+            .unwrap_or(Err(Error::Timeout))
+    }
+
+    /// Same as [`respond_to_read_timeout`](Twis::respond_to_read_timeout) but will fail instead of copying data into RAM.
+    /// Consult the module level documentation to learn more.
+    #[cfg(feature = "time")]
+    pub async fn respond_to_read_from_ram_timeout(&mut self, buffer: &[u8], timeout: Duration) -> Result<usize, Error> {
+        with_timeout(timeout, self.respond_to_read_from_ram(buffer))
+            .await
+            .unwrap_or(Err(Error::Timeout))
     }
 }
 
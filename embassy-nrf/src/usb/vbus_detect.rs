@@ -4,6 +4,8 @@ use core::future::poll_fn;
 use core::sync::atomic::{AtomicBool, Ordering};
 use core::task::Poll;
 
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
 use embassy_sync::waitqueue::AtomicWaker;
 
 use super::BUS_WAKER;
@@ -28,6 +30,26 @@ pub trait VbusDetect {
     async fn wait_power_ready(&mut self) -> Result<(), ()>;
 }
 
+/// USB cable attach or detach, as reported by a [`VbusDetect`] implementation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VbusEvent {
+    /// USB power was detected.
+    Attached,
+    /// USB power was removed.
+    Detached,
+}
+
+static VBUS_EVENT: Signal<CriticalSectionRawMutex, VbusEvent> = Signal::new();
+
+/// Wait for the USB cable to be attached or detached.
+///
+/// Events are reported by [`HardwareVbusDetect`] and [`SoftwareVbusDetect`]. Only the latest
+/// event is kept, so events happening while nobody is waiting are coalesced.
+pub async fn wait_for_event() -> VbusEvent {
+    VBUS_EVENT.wait().await
+}
+
 #[cfg(not(feature = "_nrf5340"))]
 type UsbRegIrq = interrupt::typelevel::POWER_CLOCK;
 #[cfg(feature = "_nrf5340")]
@@ -50,12 +72,14 @@ impl interrupt::typelevel::Handler<UsbRegIrq> for InterruptHandler {
         if regs.events_usbdetected.read().bits() != 0 {
             regs.events_usbdetected.reset();
             BUS_WAKER.wake();
+            VBUS_EVENT.signal(VbusEvent::Attached);
         }
 
         if regs.events_usbremoved.read().bits() != 0 {
             regs.events_usbremoved.reset();
             BUS_WAKER.wake();
             POWER_WAKER.wake();
+            VBUS_EVENT.signal(VbusEvent::Detached);
         }
 
         if regs.events_usbpwrrdy.read().bits() != 0 {
@@ -68,7 +92,7 @@ impl interrupt::typelevel::Handler<UsbRegIrq> for InterruptHandler {
 /// [`VbusDetect`] implementation using the native hardware POWER peripheral.
 ///
 /// Unsuitable for usage with the nRF softdevice, since it reserves exclusive acces
-/// to POWER. In that case, use [`VbusDetectSignal`].
+/// to POWER. In that case, use [`SoftwareVbusDetect`].
 pub struct HardwareVbusDetect {
     _private: (),
 }
@@ -119,7 +143,11 @@ impl VbusDetect for HardwareVbusDetect {
 /// to notify the power events by calling functions instead.
 ///
 /// This is suitable for use with the nRF softdevice, by calling the functions
-/// when the softdevice reports power-related events.
+/// when the softdevice reports power-related events: [`detected`](Self::detected) on
+/// `NRF_EVT_POWER_USB_DETECTED` and `NRF_EVT_POWER_USB_REMOVED`, and [`ready`](Self::ready)
+/// on `NRF_EVT_POWER_USB_POWER_READY`. The SoftDevice only reports these events once
+/// enabled with `sd_power_usbdetected_enable`, `sd_power_usbremoved_enable` and
+/// `sd_power_usbpwrrdy_enable`.
 pub struct SoftwareVbusDetect {
     usb_detected: AtomicBool,
     power_ready: AtomicBool,
@@ -140,10 +168,16 @@ impl SoftwareVbusDetect {
     ///
     /// Equivalent to the `USBDETECTED`, `USBREMOVED` events from the `POWER` peripheral.
     pub fn detected(&self, detected: bool) {
-        self.usb_detected.store(detected, Ordering::Relaxed);
+        let was_detected = self.usb_detected.swap(detected, Ordering::Relaxed);
         self.power_ready.store(false, Ordering::Relaxed);
         BUS_WAKER.wake();
         POWER_WAKER.wake();
+        if detected != was_detected {
+            VBUS_EVENT.signal(match detected {
+                true => VbusEvent::Attached,
+                false => VbusEvent::Detached,
+            });
+        }
     }
 
     /// Report when USB power is ready.
@@ -221,16 +221,24 @@ impl<'d> Adc<'d, Async> {
 
     async fn read_many_inner<W: dma::Word>(
         &mut self,
-        ch: &mut Channel<'_>,
+        ch: &mut [Channel<'_>],
         buf: &mut [W],
         fcs_err: bool,
         div: u16,
         dma: impl Peripheral<P = impl dma::Channel>,
     ) -> Result<(), Error> {
         let r = Self::regs();
+        // round robin moves on to the next enabled channel in ascending order after each
+        // conversion, so start at the lowest one to keep the samples in a predictable order.
+        let first = unwrap!(ch.iter().map(|c| c.channel()).min());
+        let rrobin = match ch.len() {
+            1 => 0,
+            _ => ch.iter().fold(0, |mask, c| mask | (1 << c.channel())),
+        };
         // clear previous errors and set channel
         r.cs().modify(|w| {
-            w.set_ainsel(ch.channel());
+            w.set_ainsel(first);
+            w.set_rrobin(rrobin);
             w.set_err_sticky(true); // clear previous errors
             w.set_start_many(false);
         });
@@ -257,6 +265,7 @@ impl<'d> Adc<'d, Async> {
             fn drop(&mut self) {
                 pac::ADC.cs().write_clear(|w| w.set_start_many(true));
                 while !pac::ADC.cs().read().ready() {}
+                pac::ADC.cs().modify(|w| w.set_rrobin(0));
                 pac::ADC.fcs().write_clear(|w| {
                     w.set_dreq_en(true);
                     w.set_shift(true);
@@ -291,10 +300,46 @@ impl<'d> Adc<'d, Async> {
         buf: &mut [S],
         div: u16,
         dma: impl Peripheral<P = impl dma::Channel>,
+    ) -> Result<(), Error> {
+        self.read_many_inner(core::slice::from_mut(ch), buf, false, div, dma)
+            .await
+    }
+
+    /// Sample multiple channels in round robin using DMA.
+    ///
+    /// Samples are interleaved in `buf` in ascending channel order, regardless of the
+    /// order of `ch`: GPIO 26 to 29, then the temperature sensor. Each channel is sampled
+    /// `buf.len() / ch.len()` times, with `div` setting the time between individual samples.
+    ///
+    /// Panics if `ch` is empty.
+    #[inline]
+    pub async fn read_many_multichannel<S: AdcSample>(
+        &mut self,
+        ch: &mut [Channel<'_>],
+        buf: &mut [S],
+        div: u16,
+        dma: impl Peripheral<P = impl dma::Channel>,
     ) -> Result<(), Error> {
         self.read_many_inner(ch, buf, false, div, dma).await
     }
 
+    /// Sample multiple channels in round robin using DMA with errors inlined in samples.
+    ///
+    /// See [`read_many_multichannel`](Self::read_many_multichannel) for the sample order.
+    #[inline]
+    pub async fn read_many_multichannel_raw(
+        &mut self,
+        ch: &mut [Channel<'_>],
+        buf: &mut [Sample],
+        div: u16,
+        dma: impl Peripheral<P = impl dma::Channel>,
+    ) {
+        // errors are reported in individual samples
+        let _ = self
+            .read_many_inner(ch, unsafe { mem::transmute::<_, &mut [u16]>(buf) }, true, div, dma)
+            .await;
+    }
+
     /// Sample multiple values from a channel using DMA with errors inlined in samples.
     #[inline]
     pub async fn read_many_raw(
@@ -306,7 +351,13 @@ impl<'d> Adc<'d, Async> {
     ) {
         // errors are reported in individual samples
         let _ = self
-            .read_many_inner(ch, unsafe { mem::transmute::<_, &mut [u16]>(buf) }, true, div, dma)
+            .read_many_inner(
+                core::slice::from_mut(ch),
+                unsafe { mem::transmute::<_, &mut [u16]>(buf) },
+                true,
+                div,
+                dma,
+            )
             .await;
     }
 }
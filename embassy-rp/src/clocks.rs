@@ -991,6 +991,9 @@ impl rand_core::RngCore for RoscRng {
 /// and can only be exited through resets, dormant-wake GPIO interrupts,
 /// and RTC interrupts. If RTC is clocked from an internal clock source
 /// it will be stopped and not function as a wakeup source.
+///
+/// The timer stops as well. With the `time-driver` feature, the time can be
+/// caught up afterwards with `time_driver::advance`.
 #[cfg(target_arch = "arm")]
 pub fn dormant_sleep() {
     struct Set<T: Copy, F: Fn()>(Reg<T, RW>, T, F);
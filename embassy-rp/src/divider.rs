@@ -0,0 +1,35 @@
+//! Hardware divider
+//!
+//! Each core has an 8-cycle hardware divider for 32-bit integers. With the `intrinsics`
+//! feature, it is used for all integer division automatically. These functions use it
+//! regardless, and return both the quotient and the remainder of a single division.
+//!
+//! The divider state is saved and restored when an interrupt handler uses it while it is
+//! busy, so it can be used from any context and is not owned like a peripheral.
+
+/// Result of a division.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DivResult<T> {
+    /// The quotient of the division.
+    pub quotient: T,
+    /// The remainder of the division.
+    pub remainder: T,
+}
+
+/// Divide unsigned `n` by `d`.
+///
+/// Dividing by zero doesn't panic: the quotient is `u32::MAX` and the remainder is `n`.
+#[inline]
+pub fn unsigned_divmod(n: u32, d: u32) -> DivResult<u32> {
+    crate::intrinsics::divider_unsigned(n, d)
+}
+
+/// Divide signed `n` by `d`.
+///
+/// Dividing by zero doesn't panic: the quotient is -1 for `n >= 0` and 1 for `n < 0`,
+/// and the remainder is `n`.
+#[inline]
+pub fn signed_divmod(n: i32, d: i32) -> DivResult<i32> {
+    crate::intrinsics::divider_signed(n, d)
+}
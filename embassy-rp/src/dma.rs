@@ -1,7 +1,8 @@
 //! Direct Memory Access (DMA)
-use core::future::Future;
+use core::future::{poll_fn, Future};
+use core::marker::PhantomData;
 use core::pin::Pin;
-use core::sync::atomic::{compiler_fence, Ordering};
+use core::sync::atomic::{compiler_fence, AtomicU32, Ordering};
 use core::task::{Context, Poll};
 
 use embassy_hal_internal::{impl_peripheral, into_ref, Peripheral, PeripheralRef};
@@ -23,6 +24,9 @@ fn DMA_IRQ_0() {
         }
 
         if ints0 & (1 << channel) == (1 << channel) {
+            // Only this handler writes the counters, so this doesn't need to be atomic.
+            let completions = &CHANNEL_COMPLETIONS[channel];
+            completions.store(completions.load(Ordering::Relaxed).wrapping_add(1), Ordering::Relaxed);
             CHANNEL_WAKERS[channel].wake();
         }
     }
@@ -204,9 +208,208 @@ impl<'a, C: Channel> Future for Transfer<'a, C> {
     }
 }
 
+/// Error returned when a ring buffer was not read fast enough, and data was overwritten.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OverrunError;
+
+/// Continuous DMA transfer from a peripheral into a ring buffer.
+///
+/// The data channel fills the ring buffer, and chains to the control channel at the end of
+/// each half of it, which restarts the data channel right away. This way, no data is lost
+/// while the buffer is read from, as long as it is read from fast enough.
+///
+/// Progress is tracked with the DMA interrupt, so this requires the `rt` feature.
+pub struct ReadableRingBuffer<'a, W: Word> {
+    data: PeripheralRef<'a, AnyChannel>,
+    control: PeripheralRef<'a, AnyChannel>,
+    ring: *mut W,
+    len: usize,
+    read_total: u32,
+    phantom: PhantomData<&'a mut [W]>,
+}
+
+impl<'a, W: Word> ReadableRingBuffer<'a, W> {
+    /// Start transferring from `from` into `ring`, paced by `dreq`.
+    ///
+    /// The ring buffer is filled with a DMA ring, so its size in bytes must be a power of
+    /// two between 4 and 32768, and it must be aligned to its size. Panics otherwise.
+    ///
+    /// SAFETY: `from` must point to a valid location reachable by DMA.
+    pub unsafe fn new(
+        data: impl Peripheral<P = impl Channel> + 'a,
+        control: impl Peripheral<P = impl Channel> + 'a,
+        from: *const W,
+        ring: &'a mut [W],
+        dreq: u8,
+    ) -> Self {
+        into_ref!(data, control);
+        let data: PeripheralRef<'a, AnyChannel> = data.map_into();
+        let control: PeripheralRef<'a, AnyChannel> = control.map_into();
+
+        let len = ring.len();
+        let size = core::mem::size_of_val(ring);
+        assert!(size.is_power_of_two() && (4..=32768).contains(&size));
+        assert!(ring.as_ptr() as usize % size == 0);
+
+        let d = data.regs();
+        let c = control.regs();
+        let chunk = (len / 2) as u32;
+
+        // Written by the control channel to restart the data channel.
+        RELOAD_COUNTS[data.number() as usize].store(chunk, Ordering::Relaxed);
+        CHANNEL_COMPLETIONS[data.number() as usize].store(0, Ordering::Relaxed);
+        pac::DMA.intr().write(|w| w.0 = 1 << data.number());
+
+        c.read_addr()
+            .write_value(RELOAD_COUNTS[data.number() as usize].as_ptr() as u32);
+        c.write_addr().write_value(d.al1_trans_count_trig().as_ptr() as u32);
+        c.trans_count().write_value(1);
+        let mut ctrl = pac::dma::regs::CtrlTrig(0);
+        ctrl.set_treq_sel(vals::TreqSel::PERMANENT);
+        ctrl.set_data_size(DataSize::SIZE_WORD);
+        ctrl.set_irq_quiet(true);
+        ctrl.set_chain_to(control.number());
+        ctrl.set_en(true);
+        c.al1_ctrl().write_value(ctrl.0);
+
+        d.read_addr().write_value(from as u32);
+        d.write_addr().write_value(ring.as_mut_ptr() as u32);
+        d.trans_count().write_value(chunk);
+
+        compiler_fence(Ordering::SeqCst);
+
+        d.ctrl_trig().write(|w| {
+            // TODO: Add all DREQ options to pac vals::TreqSel, and use
+            // `set_treq:sel`
+            w.0 = ((dreq as u32) & 0x3f) << 15usize;
+            w.set_data_size(W::size());
+            w.set_incr_read(false);
+            w.set_incr_write(true);
+            w.set_ring_sel(true);
+            w.set_ring_size(size.trailing_zeros() as u8);
+            w.set_chain_to(control.number());
+            w.set_en(true);
+        });
+
+        compiler_fence(Ordering::SeqCst);
+
+        Self {
+            data,
+            control,
+            ring: ring.as_mut_ptr(),
+            len,
+            read_total: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the capacity of the ring buffer.
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+
+    // Total number of words written, wrapping around at 2^32.
+    fn write_total(&self) -> u32 {
+        let number = self.data.number() as usize;
+        let bit = 1 << number;
+        let chunk = (self.len / 2) as u32;
+        let d = self.data.regs();
+
+        critical_section::with(|_| loop {
+            // A chunk completing while reading would make the counts inconsistent, so retry then.
+            let completions = CHANNEL_COMPLETIONS[number].load(Ordering::Relaxed);
+            let pending = pac::DMA.intr().read().intr() & bit != 0;
+            let remaining = d.trans_count().read();
+            if pending != (pac::DMA.intr().read().intr() & bit != 0) {
+                continue;
+            }
+
+            let completions = completions.wrapping_add(pending as u32);
+            let in_chunk = if remaining == 0 { 0 } else { chunk - remaining };
+            break completions.wrapping_mul(chunk).wrapping_add(in_chunk);
+        })
+    }
+
+    /// Returns the number of words available to read.
+    pub fn len(&self) -> Result<usize, OverrunError> {
+        let available = self.write_total().wrapping_sub(self.read_total) as usize;
+        if available > self.len {
+            Err(OverrunError)
+        } else {
+            Ok(available)
+        }
+    }
+
+    /// Returns `true` if no words are available to read.
+    pub fn is_empty(&self) -> Result<bool, OverrunError> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Drop all data in the ring buffer, recovering from an [`OverrunError`].
+    pub fn clear(&mut self) {
+        self.read_total = self.write_total();
+    }
+
+    /// Read the available words, up to the length of `buf`, without waiting.
+    ///
+    /// Returns the number of words read.
+    pub fn read(&mut self, buf: &mut [W]) -> Result<usize, OverrunError> {
+        let n = self.len()?.min(buf.len());
+
+        compiler_fence(Ordering::SeqCst);
+        for (i, w) in buf[..n].iter_mut().enumerate() {
+            let index = (self.read_total as usize + i) % self.len;
+            *w = unsafe { core::ptr::read_volatile(self.ring.add(index)) };
+        }
+        compiler_fence(Ordering::SeqCst);
+
+        // The words may have been overwritten while copying them out.
+        if self.write_total().wrapping_sub(self.read_total) as usize > self.len {
+            return Err(OverrunError);
+        }
+
+        self.read_total = self.read_total.wrapping_add(n as u32);
+        Ok(n)
+    }
+
+    /// Read exactly `buf.len()` words, waiting for them as needed.
+    ///
+    /// The DMA interrupt fires every time half of the ring buffer was filled, so `buf`
+    /// should not be longer than half of the capacity to avoid overruns.
+    pub async fn read_exact(&mut self, buf: &mut [W]) -> Result<(), OverrunError> {
+        let mut read = 0;
+        poll_fn(|cx| {
+            CHANNEL_WAKERS[self.data.number() as usize].register(cx.waker());
+
+            read += self.read(&mut buf[read..])?;
+            if read == buf.len() {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl<'a, W: Word> Drop for ReadableRingBuffer<'a, W> {
+    fn drop(&mut self) {
+        pac::DMA
+            .chan_abort()
+            .modify(|m| m.set_chan_abort((1 << self.data.number()) | (1 << self.control.number())));
+        while self.data.regs().ctrl_trig().read().busy() || self.control.regs().ctrl_trig().read().busy() {}
+    }
+}
+
 pub(crate) const CHANNEL_COUNT: usize = 12;
 const NEW_AW: AtomicWaker = AtomicWaker::new();
 static CHANNEL_WAKERS: [AtomicWaker; CHANNEL_COUNT] = [NEW_AW; CHANNEL_COUNT];
+#[allow(clippy::declare_interior_mutable_const)]
+const NEW_COUNT: AtomicU32 = AtomicU32::new(0);
+static CHANNEL_COMPLETIONS: [AtomicU32; CHANNEL_COUNT] = [NEW_COUNT; CHANNEL_COUNT];
+// Transfer counts reloaded into ring buffer data channels, read by DMA.
+static RELOAD_COUNTS: [AtomicU32; CHANNEL_COUNT] = [NEW_COUNT; CHANNEL_COUNT];
 
 trait SealedChannel {}
 trait SealedWord {}
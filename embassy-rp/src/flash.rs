@@ -148,9 +148,11 @@ impl<'d, T: Instance, M: Mode, const FLASH_SIZE: usize> Flash<'d, T, M, FLASH_SI
             FLASH_BASE as u32 + to
         );
 
-        let len = to - from;
-
-        unsafe { in_ram(|| ram_helpers::flash_range_erase(from, len))? };
+        // Erase one sector at a time, so interrupts and the other core only
+        // stall for the duration of a single sector erase.
+        for sector in (from..to).step_by(ERASE_SIZE) {
+            unsafe { in_ram(|| ram_helpers::flash_range_erase(sector, ERASE_SIZE as u32))? };
+        }
 
         Ok(())
     }
@@ -195,17 +197,17 @@ impl<'d, T: Instance, M: Mode, const FLASH_SIZE: usize> Flash<'d, T, M, FLASH_SI
                 offset as usize
             };
 
-            if bytes.as_ptr() as usize >= 0x2000_0000 {
-                let aligned_data = &bytes[start_padding..end_padding];
-
-                unsafe { in_ram(|| ram_helpers::flash_range_program(aligned_offset as u32, aligned_data))? }
-            } else {
-                for chunk in bytes[start_padding..end_padding].chunks_exact(PAGE_SIZE) {
+            // Program one page at a time, so interrupts and the other core only
+            // stall for the duration of a single page program.
+            for chunk in bytes[start_padding..end_padding].chunks_exact(PAGE_SIZE) {
+                if chunk.as_ptr() as usize >= 0x2000_0000 {
+                    unsafe { in_ram(|| ram_helpers::flash_range_program(aligned_offset as u32, chunk))? }
+                } else {
                     let mut ram_buf = [0xFF_u8; PAGE_SIZE];
                     ram_buf.copy_from_slice(chunk);
                     unsafe { in_ram(|| ram_helpers::flash_range_program(aligned_offset as u32, &ram_buf))? }
-                    aligned_offset += PAGE_SIZE;
                 }
+                aligned_offset += PAGE_SIZE;
             }
         }
 
@@ -875,14 +877,8 @@ mod ram_helpers {
 /// - interrupts must be disabled
 /// - DMA must not access flash memory
 pub(crate) unsafe fn in_ram(operation: impl FnOnce()) -> Result<(), Error> {
-    // Make sure we're running on CORE0
-    let core_id: u32 = pac::SIO.cpuid().read();
-    if core_id != 0 {
-        return Err(Error::InvalidCore);
-    }
-
-    // Make sure CORE1 is paused during the entire duration of the RAM function
-    crate::multicore::pause_core1();
+    // Make sure the other core is paused during the entire duration of the RAM function
+    crate::multicore::pause_other_core();
 
     critical_section::with(|_| {
         // Wait for all DMA channels in flash to finish before ram operation
@@ -898,8 +894,8 @@ pub(crate) unsafe fn in_ram(operation: impl FnOnce()) -> Result<(), Error> {
         operation();
     });
 
-    // Resume CORE1 execution
-    crate::multicore::resume_core1();
+    // Resume the other core's execution
+    crate::multicore::resume_other_core();
     Ok(())
 }
 
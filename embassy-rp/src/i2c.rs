@@ -3,6 +3,7 @@ use core::future;
 use core::marker::PhantomData;
 use core::task::Poll;
 
+use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::{into_ref, PeripheralRef};
 use embassy_sync::waitqueue::AtomicWaker;
 use pac::i2c;
@@ -138,6 +139,7 @@ impl<'d, T: Instance> I2c<'d, T, Async> {
         }
 
         let p = T::regs();
+        let on_drop = OnDrop::new(Self::abort_transfer);
 
         let mut remaining = buffer.len();
         let mut remaining_queue = buffer.len();
@@ -211,7 +213,9 @@ impl<'d, T: Instance> I2c<'d, T, Async> {
             };
         }
 
-        self.wait_stop_det(abort_reason, send_stop).await
+        let res = self.wait_stop_det(abort_reason, send_stop).await;
+        on_drop.defuse();
+        res
     }
 
     async fn write_async_internal(
@@ -222,6 +226,11 @@ impl<'d, T: Instance> I2c<'d, T, Async> {
         let p = T::regs();
 
         let mut bytes = bytes.into_iter().peekable();
+        if bytes.peek().is_none() {
+            return Err(Error::InvalidWriteBufferLength);
+        }
+
+        let on_drop = OnDrop::new(Self::abort_transfer);
 
         let res = 'xmit: loop {
             let tx_fifo_space = Self::tx_fifo_capacity();
@@ -270,7 +279,22 @@ impl<'d, T: Instance> I2c<'d, T, Async> {
             }
         };
 
-        self.wait_stop_det(res, send_stop).await
+        let res = self.wait_stop_det(res, send_stop).await;
+        on_drop.defuse();
+        res
+    }
+
+    /// Abort a transfer that was cancelled by dropping its future.
+    ///
+    /// The hardware sends a STOP and flushes the FIFOs, leaving the bus and
+    /// the driver ready for the next transfer.
+    fn abort_transfer() {
+        let p = T::regs();
+        p.ic_intr_mask().write_value(i2c::regs::IcIntrMask::default());
+        p.ic_enable().modify(|w| w.set_abort(true));
+        while p.ic_enable().read().abort() {}
+        p.ic_clr_tx_abrt().read();
+        p.ic_clr_stop_det().read();
     }
 
     /// Helper to wait for a stop bit, for both tx and rx. If we had an abort,
@@ -0,0 +1,266 @@
+//! Interpolators
+//!
+//! Each core has two interpolators in its SIO, which accelerate tasks like texture mapping,
+//! fixed-point math and address calculation. Both lanes of an interpolator shift, mask and
+//! add their accumulator to a base, and the results can be read in a single cycle.
+//!
+//! The interpolators are part of each core, so a driver always uses the interpolator of the
+//! core it runs on. For this reason, [`Interp`] can't be sent to another core.
+
+use core::marker::PhantomData;
+
+use embassy_hal_internal::{into_ref, Peripheral, PeripheralRef};
+
+use crate::pac::sio::regs::{Interp0ctrlLane0, Interp1ctrlLane0, Interp1ctrlLane1};
+use crate::{pac, peripherals};
+
+/// Interpolator lane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Lane {
+    /// Lane 0.
+    Lane0,
+    /// Lane 1.
+    Lane1,
+}
+
+/// Lane configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy)]
+pub struct LaneConfig {
+    /// Right rotation applied to the accumulator, 0..=31.
+    pub shift: u8,
+    /// Least significant bit kept after shifting, 0..=31.
+    pub mask_lsb: u8,
+    /// Most significant bit kept after shifting, 0..=31.
+    pub mask_msb: u8,
+    /// Sign-extend the masked value to 32 bits, before adding the base.
+    pub signed: bool,
+    /// Use the accumulator of the other lane as input.
+    pub cross_input: bool,
+    /// Feed the result of the other lane back into this lane's accumulator on pop.
+    pub cross_result: bool,
+    /// Add the raw accumulator to the base for the lane result, instead of the shifted and masked value.
+    pub add_raw: bool,
+    /// Value ORed into bits 29:28 of the lane result, for generating addresses in a memory region.
+    pub force_msb: u8,
+}
+
+impl Default for LaneConfig {
+    fn default() -> Self {
+        Self {
+            shift: 0,
+            mask_lsb: 0,
+            mask_msb: 31,
+            signed: false,
+            cross_input: false,
+            cross_result: false,
+            add_raw: false,
+            force_msb: 0,
+        }
+    }
+}
+
+/// Interpolator configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy, Default)]
+pub struct Config {
+    /// Lane 0 configuration.
+    pub lane0: LaneConfig,
+    /// Lane 1 configuration.
+    pub lane1: LaneConfig,
+    /// Blend mode, only available on INTERP0.
+    ///
+    /// Linearly interpolates between base 0 and base 1 in the full result, using the
+    /// 8 least significant bits of the lane 1 shift and mask value as the fraction.
+    pub blend: bool,
+    /// Clamp mode, only available on INTERP1.
+    ///
+    /// Clamps the lane 0 shift and mask value between base 0 and base 1.
+    pub clamp: bool,
+}
+
+/// Interpolator driver.
+pub struct Interp<'d, T: Instance> {
+    _inner: PeripheralRef<'d, T>,
+    // The interpolators are per-core, so keep the driver on the core it was configured on.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl<'d, T: Instance> Interp<'d, T> {
+    /// Create a new interpolator driver.
+    ///
+    /// Panics if `blend` is set for INTERP1, or `clamp` for INTERP0.
+    pub fn new(inner: impl Peripheral<P = T> + 'd, config: Config) -> Self {
+        into_ref!(inner);
+        let mut this = Self {
+            _inner: inner,
+            _not_send: PhantomData,
+        };
+        this.set_config(&config);
+        this
+    }
+
+    /// Set the configuration.
+    ///
+    /// Panics if `blend` is set for INTERP1, or `clamp` for INTERP0.
+    pub fn set_config(&mut self, config: &Config) {
+        assert!(
+            !config.blend || T::NUMBER == 0,
+            "blend mode is only available on INTERP0"
+        );
+        assert!(
+            !config.clamp || T::NUMBER == 1,
+            "clamp mode is only available on INTERP1"
+        );
+
+        let r = Self::regs();
+
+        let mut lane0 = Interp1ctrlLane0(0);
+        let c = &config.lane0;
+        lane0.set_shift(c.shift);
+        lane0.set_mask_lsb(c.mask_lsb);
+        lane0.set_mask_msb(c.mask_msb);
+        lane0.set_signed(c.signed);
+        lane0.set_cross_input(c.cross_input);
+        lane0.set_cross_result(c.cross_result);
+        lane0.set_add_raw(c.add_raw);
+        lane0.set_force_msb(c.force_msb);
+        lane0.set_clamp(config.clamp);
+        // The blend bit doesn't exist on INTERP1, where the register type comes from.
+        let mut blend = Interp0ctrlLane0(0);
+        blend.set_blend(config.blend);
+        lane0.0 |= blend.0;
+        r.ctrl_lane0().write_value(lane0);
+
+        let mut lane1 = Interp1ctrlLane1(0);
+        let c = &config.lane1;
+        lane1.set_shift(c.shift);
+        lane1.set_mask_lsb(c.mask_lsb);
+        lane1.set_mask_msb(c.mask_msb);
+        lane1.set_signed(c.signed);
+        lane1.set_cross_input(c.cross_input);
+        lane1.set_cross_result(c.cross_result);
+        lane1.set_add_raw(c.add_raw);
+        lane1.set_force_msb(c.force_msb);
+        r.ctrl_lane1().write_value(lane1);
+    }
+
+    /// Read the accumulator of a lane.
+    #[inline]
+    pub fn accum(&self, lane: Lane) -> u32 {
+        let r = Self::regs();
+        match lane {
+            Lane::Lane0 => r.accum0().read(),
+            Lane::Lane1 => r.accum1().read(),
+        }
+    }
+
+    /// Write the accumulator of a lane.
+    #[inline]
+    pub fn set_accum(&mut self, lane: Lane, value: u32) {
+        let r = Self::regs();
+        match lane {
+            Lane::Lane0 => r.accum0().write_value(value),
+            Lane::Lane1 => r.accum1().write_value(value),
+        }
+    }
+
+    /// Read the base added to the result of a lane.
+    #[inline]
+    pub fn base(&self, lane: Lane) -> u32 {
+        let r = Self::regs();
+        match lane {
+            Lane::Lane0 => r.base0().read(),
+            Lane::Lane1 => r.base1().read(),
+        }
+    }
+
+    /// Write the base added to the result of a lane.
+    #[inline]
+    pub fn set_base(&mut self, lane: Lane, value: u32) {
+        let r = Self::regs();
+        match lane {
+            Lane::Lane0 => r.base0().write_value(value),
+            Lane::Lane1 => r.base1().write_value(value),
+        }
+    }
+
+    /// Read the base added to the full result.
+    #[inline]
+    pub fn base2(&self) -> u32 {
+        Self::regs().base2().read()
+    }
+
+    /// Write the base added to the full result.
+    #[inline]
+    pub fn set_base2(&mut self, value: u32) {
+        Self::regs().base2().write_value(value)
+    }
+
+    /// Read the result of a lane without changing the state.
+    #[inline]
+    pub fn peek(&self, lane: Lane) -> u32 {
+        let r = Self::regs();
+        match lane {
+            Lane::Lane0 => r.peek_lane0().read(),
+            Lane::Lane1 => r.peek_lane1().read(),
+        }
+    }
+
+    /// Read the full result without changing the state.
+    #[inline]
+    pub fn peek_full(&self) -> u32 {
+        Self::regs().peek_full().read()
+    }
+
+    /// Read the result of a lane, and write the lane results back into the accumulators.
+    #[inline]
+    pub fn pop(&mut self, lane: Lane) -> u32 {
+        let r = Self::regs();
+        match lane {
+            Lane::Lane0 => r.pop_lane0().read(),
+            Lane::Lane1 => r.pop_lane1().read(),
+        }
+    }
+
+    /// Read the full result, and write the lane results back into the accumulators.
+    #[inline]
+    pub fn pop_full(&mut self) -> u32 {
+        Self::regs().pop_full().read()
+    }
+
+    /// Add `value` to the accumulator of a lane, in a single bus access.
+    #[inline]
+    pub fn add_accum(&mut self, lane: Lane, value: u32) {
+        let r = Self::regs();
+        match lane {
+            Lane::Lane0 => r.accum0_add().write(|w| w.0 = value),
+            Lane::Lane1 => r.accum1_add().write(|w| w.0 = value),
+        }
+    }
+
+    fn regs() -> pac::sio::Interp {
+        pac::SIO.interp(T::NUMBER)
+    }
+}
+
+trait SealedInstance {
+    const NUMBER: usize;
+}
+
+/// Interpolator instance.
+#[allow(private_bounds)]
+pub trait Instance: Peripheral<P = Self> + SealedInstance + 'static {}
+
+macro_rules! impl_interp {
+    ($name:ident, $num:expr) => {
+        impl SealedInstance for peripherals::$name {
+            const NUMBER: usize = $num;
+        }
+        impl Instance for peripherals::$name {}
+    };
+}
+
+impl_interp!(INTERP0, 0);
+impl_interp!(INTERP1, 1);
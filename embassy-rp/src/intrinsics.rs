@@ -3,6 +3,8 @@
 // Credit: taken from `rp-hal` (also licensed Apache+MIT)
 // https://github.com/rp-rs/rp-hal/blob/main/rp2040-hal/src/intrinsics.rs
 
+use crate::divider::DivResult;
+
 /// Generate a series of aliases for an intrinsic function.
 macro_rules! intrinsics_aliases {
     (
@@ -419,7 +421,7 @@ division_function! {
     }
 }
 
-fn divider_unsigned(n: u32, d: u32) -> DivResult<u32> {
+pub(crate) fn divider_unsigned(n: u32, d: u32) -> DivResult<u32> {
     let packed = unsafe { unsigned_divmod(n, d) };
     DivResult {
         quotient: packed as u32,
@@ -427,7 +429,7 @@ fn divider_unsigned(n: u32, d: u32) -> DivResult<u32> {
     }
 }
 
-fn divider_signed(n: i32, d: i32) -> DivResult<i32> {
+pub(crate) fn divider_signed(n: i32, d: i32) -> DivResult<i32> {
     let packed = unsafe { signed_divmod(n, d) };
     // Double casts to avoid sign extension
     DivResult {
@@ -436,14 +438,6 @@ fn divider_signed(n: i32, d: i32) -> DivResult<i32> {
     }
 }
 
-/// Result of divide/modulo operation
-struct DivResult<T> {
-    /// The quotient of divide/modulo operation
-    pub quotient: T,
-    /// The remainder of divide/modulo operation
-    pub remainder: T,
-}
-
 intrinsics! {
     extern "C" fn __udivsi3(n: u32, d: u32) -> u32 {
         divider_unsigned(n, d).quotient
@@ -17,12 +17,14 @@ mod intrinsics;
 pub mod adc;
 pub mod bootsel;
 pub mod clocks;
+pub mod divider;
 pub mod dma;
 pub mod flash;
 mod float;
 pub mod gpio;
 pub mod i2c;
 pub mod i2c_slave;
+pub mod interp;
 pub mod multicore;
 pub mod pwm;
 mod reset;
@@ -206,6 +208,9 @@ embassy_hal_internal::peripherals! {
     PIO0,
     PIO1,
 
+    INTERP0,
+    INTERP1,
+
     WATCHDOG,
     BOOTSEL,
 }
@@ -8,6 +8,15 @@
 //! Enable the `critical-section-impl` feature in embassy-rp when sharing data across cores using
 //! the `embassy-sync` primitives and `CriticalSectionRawMutex`.
 //!
+//! Once core1 is started, the inter-core FIFOs are used by embassy-rp to pause the other core while
+//! flash is being written, from either core. This requires the `SIO_IRQ_PROC0` and `SIO_IRQ_PROC1`
+//! interrupts, which are handled by embassy-rp: with the `rt` feature, applications must not define
+//! handlers for them, or linking fails with duplicate symbols. Spinlock 30 is reserved as well, to
+//! keep both cores from pausing each other at the same time.
+//!
+//! With the `executor` feature, [`spawn_core1_executor`] starts an executor on core1 and returns
+//! a spawner to spawn tasks on it from core0.
+//!
 //! # Usage
 //!
 //! ```no_run
@@ -55,6 +64,8 @@ use crate::{gpio, install_stack_guard, interrupt, pac};
 
 const PAUSE_TOKEN: u32 = 0xDEADBEEF;
 const RESUME_TOKEN: u32 = !0xDEADBEEF;
+/// Hardware spinlock held by the core pausing the other one, until it resumes it.
+const PAUSE_SPINLOCK: usize = 30;
 static IS_CORE1_INIT: AtomicBool = AtomicBool::new(false);
 
 #[inline(always)]
@@ -84,26 +95,40 @@ impl<const SIZE: usize> Stack<SIZE> {
     }
 }
 
+#[cfg(feature = "rt")]
+#[interrupt]
+#[link_section = ".data.ram_func"]
+unsafe fn SIO_IRQ_PROC0() {
+    handle_pause();
+}
+
 #[cfg(feature = "rt")]
 #[interrupt]
 #[link_section = ".data.ram_func"]
 unsafe fn SIO_IRQ_PROC1() {
+    handle_pause();
+}
+
+// Inlined into the interrupt handlers, so it runs from RAM while the other core writes flash.
+#[cfg(feature = "rt")]
+#[inline(always)]
+unsafe fn handle_pause() {
     let sio = pac::SIO;
     // Clear IRQ
     sio.fifo().st().write(|w| w.set_wof(false));
 
     while sio.fifo().st().read().vld() {
-        // Pause CORE1 execution and disable interrupts
+        // Pause execution and disable interrupts
         if fifo_read_wfe() == PAUSE_TOKEN {
             cortex_m::interrupt::disable();
-            // Signal to CORE0 that execution is paused
+            // Signal to the other core that execution is paused
             fifo_write(PAUSE_TOKEN);
-            // Wait for `resume` signal from CORE0
+            // Wait for `resume` signal from the other core
             while fifo_read_wfe() != RESUME_TOKEN {
                 cortex_m::asm::nop();
             }
             cortex_m::interrupt::enable();
-            // Signal to CORE0 that execution is resumed
+            // Signal to the other core that execution is resumed
             fifo_write(RESUME_TOKEN);
         }
     }
@@ -223,23 +248,129 @@ where
 
     // Wait until the other core has copied `entry` before returning.
     fifo_read();
+
+    // Enable fifo interrupt on CORE0, so CORE1 can pause it too.
+    interrupt::SIO_IRQ_PROC0.unpend();
+    unsafe { interrupt::SIO_IRQ_PROC0.enable() };
+}
+
+/// Start an executor on CORE1, returning a spawner to spawn tasks on it from CORE0.
+///
+/// `init` is called on CORE1 with the executor's [`Spawner`](embassy_executor::Spawner), to spawn
+/// the tasks that aren't `Send`. Tasks spawned from CORE0 with the returned
+/// [`SendSpawner`](embassy_executor::SendSpawner) are woken with `SEV`, like any task woken from
+/// the other core. Enable the `critical-section-impl` feature so that the run queue of the
+/// executor is safe to use from both cores.
+///
+/// Requires the `executor` feature.
+///
+/// ```no_run
+/// use embassy_rp::multicore::Stack;
+///
+/// static mut CORE1_STACK: Stack<4096> = Stack::new();
+///
+/// #[embassy_executor::task]
+/// async fn core1_task() {
+///     // ...
+/// }
+///
+/// # fn main() {
+/// let p = embassy_rp::init(Default::default());
+/// let core1_spawner = embassy_rp::multicore::spawn_core1_executor(p.CORE1, unsafe { &mut CORE1_STACK }, |_| {});
+/// core1_spawner.spawn(core1_task()).unwrap();
+/// # }
+/// ```
+#[cfg(feature = "executor")]
+pub fn spawn_core1_executor<F, const SIZE: usize>(
+    core1: CORE1,
+    stack: &'static mut Stack<SIZE>,
+    init: F,
+) -> embassy_executor::SendSpawner
+where
+    F: FnOnce(embassy_executor::Spawner) + Send + 'static,
+{
+    use core::mem::MaybeUninit;
+
+    use embassy_executor::{Executor, SendSpawner};
+
+    // CORE1 can only be started once, so these are only written once.
+    static mut EXECUTOR: MaybeUninit<Executor> = MaybeUninit::uninit();
+    static mut SPAWNER: MaybeUninit<SendSpawner> = MaybeUninit::uninit();
+    static SPAWNER_READY: AtomicBool = AtomicBool::new(false);
+
+    spawn_core1(core1, stack, move || {
+        let executor = unsafe { EXECUTOR.write(Executor::new()) };
+        executor.run(|spawner| {
+            unsafe { SPAWNER.write(spawner.make_send()) };
+            SPAWNER_READY.store(true, Ordering::Release);
+            cortex_m::asm::sev();
+            init(spawner);
+        })
+    });
+
+    while !SPAWNER_READY.load(Ordering::Acquire) {
+        cortex_m::asm::wfe();
+    }
+    unsafe { SPAWNER.assume_init() }
 }
 
 /// Pause execution on CORE1.
+///
+/// Does nothing when called from CORE1, use [`pause_other_core`] to pause CORE0 from there.
 pub fn pause_core1() {
-    if IS_CORE1_INIT.load(Ordering::Acquire) {
-        fifo_write(PAUSE_TOKEN);
-        // Wait for CORE1 to signal it has paused execution.
-        while fifo_read() != PAUSE_TOKEN {}
+    if pac::SIO.cpuid().read() == 0 {
+        pause_other_core();
     }
 }
 
 /// Resume CORE1 execution.
+///
+/// Does nothing when called from CORE1.
 pub fn resume_core1() {
+    if pac::SIO.cpuid().read() == 0 {
+        resume_other_core();
+    }
+}
+
+/// Pause execution on the other core, until [`resume_other_core`] is called.
+///
+/// The other core keeps running from RAM with interrupts disabled, so this is safe
+/// to use around flash operations.
+///
+/// If the other core is pausing this one, this waits until it resumes it. Interrupts must be enabled
+/// then, to let this core pause.
+pub fn pause_other_core() {
     if IS_CORE1_INIT.load(Ordering::Acquire) {
-        fifo_write(RESUME_TOKEN);
-        // Wait for CORE1 to signal it has resumed execution.
-        while fifo_read() != RESUME_TOKEN {}
+        // Only one core pauses the other at a time. Waiting for the lock with interrupts enabled lets
+        // the other core, holding it, pause this one meanwhile.
+        while pac::SIO.spinlock(PAUSE_SPINLOCK).read() == 0 {
+            cortex_m::asm::nop();
+        }
+        compiler_fence(Ordering::SeqCst);
+
+        // Keep our own fifo interrupt from taking the acknowledgement.
+        cortex_m::interrupt::free(|_| {
+            fifo_write(PAUSE_TOKEN);
+            // Wait for the other core to signal it has paused execution.
+            while fifo_read() != PAUSE_TOKEN {}
+        });
+    }
+}
+
+/// Resume execution on the other core.
+///
+/// Must only be called by the core that paused it with [`pause_other_core`].
+pub fn resume_other_core() {
+    if IS_CORE1_INIT.load(Ordering::Acquire) {
+        cortex_m::interrupt::free(|_| {
+            fifo_write(RESUME_TOKEN);
+            // Wait for the other core to signal it has resumed execution.
+            while fifo_read() != RESUME_TOKEN {}
+        });
+
+        compiler_fence(Ordering::SeqCst);
+        // Release the lock taken by `pause_other_core`.
+        pac::SIO.spinlock(PAUSE_SPINLOCK).write_value(1);
     }
 }
 
@@ -22,6 +22,7 @@ use crate::relocate::RelocatedProgram;
 use crate::{pac, peripherals, RegExt};
 
 pub mod instr;
+pub mod ws2812;
 
 /// Wakers for interrupts and FIFOs.
 pub struct Wakers([AtomicWaker; 12]);
@@ -365,6 +366,37 @@ impl<'d, PIO: Instance, const SM: usize> StateMachineRx<'d, PIO, SM> {
         compiler_fence(Ordering::SeqCst);
         Transfer::new(ch)
     }
+
+    /// Prepare a DMA transfer pulling `len` words from the RX FIFO and discarding them.
+    ///
+    /// This keeps a state machine that produces data which isn't needed from
+    /// stalling, without CPU involvement.
+    pub fn dma_pull_repeated<'a, C: Channel, W: Word>(
+        &'a mut self,
+        ch: PeripheralRef<'a, C>,
+        len: usize,
+    ) -> Transfer<'a, C> {
+        static mut DUMMY: u32 = 0;
+
+        let pio_no = PIO::PIO_NO;
+        let p = ch.regs();
+        p.write_addr()
+            .write_value(unsafe { core::ptr::addr_of_mut!(DUMMY) } as u32);
+        p.read_addr().write_value(PIO::PIO.rxf(SM).as_ptr() as u32);
+        p.trans_count().write_value(len as u32);
+        compiler_fence(Ordering::SeqCst);
+        p.ctrl_trig().write(|w| {
+            // Set RX DREQ for this statemachine
+            w.set_treq_sel(TreqSel(pio_no * 8 + SM as u8 + 4));
+            w.set_data_size(W::size());
+            w.set_chain_to(ch.number());
+            w.set_incr_read(false);
+            w.set_incr_write(false);
+            w.set_en(true);
+        });
+        compiler_fence(Ordering::SeqCst);
+        Transfer::new(ch)
+    }
 }
 
 /// Type representing a state machine TX FIFO.
@@ -447,6 +479,34 @@ impl<'d, PIO: Instance, const SM: usize> StateMachineTx<'d, PIO, SM> {
         compiler_fence(Ordering::SeqCst);
         Transfer::new(ch)
     }
+
+    /// Prepare a DMA transfer pushing `value` to the TX FIFO `len` times.
+    ///
+    /// Useful to clock out padding or idle patterns without a buffer of the full length.
+    pub fn dma_push_repeated<'a, C: Channel, W: Word>(
+        &'a mut self,
+        ch: PeripheralRef<'a, C>,
+        value: &'a W,
+        len: usize,
+    ) -> Transfer<'a, C> {
+        let pio_no = PIO::PIO_NO;
+        let p = ch.regs();
+        p.read_addr().write_value(value as *const W as u32);
+        p.write_addr().write_value(PIO::PIO.txf(SM).as_ptr() as u32);
+        p.trans_count().write_value(len as u32);
+        compiler_fence(Ordering::SeqCst);
+        p.ctrl_trig().write(|w| {
+            // Set TX DREQ for this statemachine
+            w.set_treq_sel(TreqSel(pio_no * 8 + SM as u8));
+            w.set_data_size(W::size());
+            w.set_chain_to(ch.number());
+            w.set_incr_read(false);
+            w.set_incr_write(false);
+            w.set_en(true);
+        });
+        compiler_fence(Ordering::SeqCst);
+        Transfer::new(ch)
+    }
 }
 
 /// A type representing a single PIO state machine.
@@ -0,0 +1,114 @@
+//! WS2812 addressable LED driver, using a PIO state machine.
+
+use core::convert::Infallible;
+
+use embassy_embedded_hal::smart_led::{Rgb8, SmartLedWrite};
+use embassy_hal_internal::{into_ref, Peripheral, PeripheralRef};
+use embassy_time::Timer;
+use fixed::types::U24F8;
+
+use crate::clocks::clk_sys_freq;
+use crate::dma::{AnyChannel, Channel};
+use crate::pio::{Common, Config, FifoJoin, Instance, PioPin, ShiftConfig, ShiftDirection, StateMachine};
+
+const T1: u8 = 2; // start bit
+const T2: u8 = 5; // data bit
+const T3: u8 = 3; // stop bit
+const CYCLES_PER_BIT: u32 = (T1 + T2 + T3) as u32;
+
+/// WS2812 driver for a chain of `N` LEDs.
+///
+/// The colors are encoded into a buffer owned by the driver, and sent with DMA, so the caller's
+/// frame can be updated as soon as `write` is called again.
+pub struct PioWs2812<'d, P: Instance, const S: usize, const N: usize> {
+    dma: PeripheralRef<'d, AnyChannel>,
+    sm: StateMachine<'d, P, S>,
+    words: [u32; N],
+}
+
+impl<'d, P: Instance, const S: usize, const N: usize> PioWs2812<'d, P, S, N> {
+    /// Create a new WS2812 driver, loading its program into the PIO.
+    pub fn new(
+        pio: &mut Common<'d, P>,
+        mut sm: StateMachine<'d, P, S>,
+        dma: impl Peripheral<P = impl Channel> + 'd,
+        pin: impl PioPin,
+    ) -> Self {
+        into_ref!(dma);
+
+        let side_set = pio::SideSet::new(false, 1, false);
+        let mut a: pio::Assembler<32> = pio::Assembler::new_with_side_set(side_set);
+
+        let mut wrap_target = a.label();
+        let mut wrap_source = a.label();
+        let mut do_zero = a.label();
+        a.set_with_side_set(pio::SetDestination::PINDIRS, 1, 0);
+        a.bind(&mut wrap_target);
+        // Do stop bit
+        a.out_with_delay_and_side_set(pio::OutDestination::X, 1, T3 - 1, 0);
+        // Do start bit
+        a.jmp_with_delay_and_side_set(pio::JmpCondition::XIsZero, &mut do_zero, T1 - 1, 1);
+        // Do data bit = 1
+        a.jmp_with_delay_and_side_set(pio::JmpCondition::Always, &mut wrap_target, T2 - 1, 1);
+        a.bind(&mut do_zero);
+        // Do data bit = 0
+        a.nop_with_delay_and_side_set(T2 - 1, 0);
+        a.bind(&mut wrap_source);
+
+        let prg = a.assemble_with_wrap(wrap_source, wrap_target);
+        let mut cfg = Config::default();
+
+        let out_pin = pio.make_pio_pin(pin);
+        cfg.set_out_pins(&[&out_pin]);
+        cfg.set_set_pins(&[&out_pin]);
+        cfg.use_program(&pio.load_program(&prg), &[&out_pin]);
+
+        // Measured in kHz to avoid overflows.
+        let clock_freq = U24F8::from_num(clk_sys_freq() / 1000);
+        let bit_freq = U24F8::from_num(800 * CYCLES_PER_BIT);
+        cfg.clock_divider = clock_freq / bit_freq;
+
+        cfg.fifo_join = FifoJoin::TxOnly;
+        cfg.shift_out = ShiftConfig {
+            auto_fill: true,
+            threshold: 24,
+            direction: ShiftDirection::Left,
+        };
+
+        sm.set_config(&cfg);
+        sm.set_enable(true);
+
+        Self {
+            dma: dma.map_into(),
+            sm,
+            words: [0; N],
+        }
+    }
+
+    /// Write the colors of the LEDs.
+    ///
+    /// Panics if there are more than `N` colors.
+    pub async fn write(&mut self, colors: &[Rgb8]) {
+        assert!(colors.len() <= N, "too many colors");
+
+        for (word, color) in self.words.iter_mut().zip(colors) {
+            *word = (u32::from(color.g) << 24) | (u32::from(color.r) << 16) | (u32::from(color.b) << 8);
+        }
+        self.sm
+            .tx()
+            .dma_push(self.dma.reborrow(), &self.words[..colors.len()])
+            .await;
+
+        // The last bits are still being shifted out, then the line must stay low for the LEDs to latch.
+        Timer::after_micros(55).await;
+    }
+}
+
+impl<'d, P: Instance, const S: usize, const N: usize> SmartLedWrite for PioWs2812<'d, P, S, N> {
+    type Error = Infallible;
+
+    async fn write(&mut self, colors: &[Rgb8]) -> Result<(), Self::Error> {
+        PioWs2812::write(self, colors).await;
+        Ok(())
+    }
+}
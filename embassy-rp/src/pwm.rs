@@ -1,13 +1,25 @@
 //! Pulse Width Modulation (PWM)
 
+use core::future::poll_fn;
+use core::task::Poll;
+
 use embassy_hal_internal::{into_ref, Peripheral, PeripheralRef};
+use embassy_sync::waitqueue::AtomicWaker;
 use fixed::traits::ToFixed;
 use fixed::FixedU16;
 use pac::pwm::regs::{ChDiv, Intr};
 use pac::pwm::vals::Divmode;
 
 use crate::gpio::{AnyPin, Pin as GpioPin, Pull, SealedPin as _};
-use crate::{pac, peripherals, RegExt};
+use crate::interrupt::typelevel::Binding;
+use crate::interrupt::InterruptExt;
+use crate::{interrupt, pac, peripherals, RegExt};
+
+const SLICE_COUNT: usize = 8;
+
+#[allow(clippy::declare_interior_mutable_const)]
+const NEW_AW: AtomicWaker = AtomicWaker::new();
+static WAKERS: [AtomicWaker; SLICE_COUNT] = [NEW_AW; SLICE_COUNT];
 
 /// The configuration of a PWM slice.
 /// Note the period in clock cycles of a slice can be computed as:
@@ -61,12 +73,20 @@ impl Default for Config {
 }
 
 /// PWM input mode.
+///
+/// In the input modes, the counter is gated by the 'b' pin instead of free-running,
+/// which allows measuring the frequency or duty cycle of the signal on that pin.
 pub enum InputMode {
-    /// Level mode.
+    /// Level mode: the counter advances while the 'b' pin is high.
+    ///
+    /// Comparing the count over a known number of system clock cycles to that
+    /// number of cycles gives the duty cycle of the input.
     Level,
-    /// Rising edge mode.
+    /// Rising edge mode: the counter advances on each rising edge of the 'b' pin.
+    ///
+    /// The count over a known time gives the frequency of the input.
     RisingEdge,
-    /// Falling edge mode.
+    /// Falling edge mode: the counter advances on each falling edge of the 'b' pin.
     FallingEdge,
 }
 
@@ -267,6 +287,40 @@ impl<'d, T: Slice> Pwm<'d, T> {
         self.clear_wrapped();
     }
 
+    /// Enable the wrap interrupt, allowing to use [`Pwm::wait_for_wrap_async`].
+    pub fn bind_wrap_interrupt(
+        &mut self,
+        _irq: impl Binding<interrupt::typelevel::PWM_IRQ_WRAP, InterruptHandler> + 'd,
+    ) {
+        interrupt::PWM_IRQ_WRAP.unpend();
+        unsafe { interrupt::PWM_IRQ_WRAP.enable() };
+    }
+
+    /// Wait for the counter to wrap, without blocking.
+    ///
+    /// A wrap that happened before this is called, and was not cleared yet with
+    /// [`Pwm::clear_wrapped`], completes the wait immediately. This makes it possible to
+    /// update the config once per period, for example to play back a waveform.
+    ///
+    /// This requires the wrap interrupt to be enabled with [`Pwm::bind_wrap_interrupt`].
+    pub async fn wait_for_wrap_async(&mut self) {
+        let bit = self.bit();
+        let waker = &WAKERS[self.inner.number() as usize];
+
+        pac::PWM.inte().write_set(|w| w.0 = bit);
+        poll_fn(|cx| {
+            waker.register(cx.waker());
+            if pac::PWM.intr().read().0 & bit != 0 {
+                pac::PWM.inte().write_clear(|w| w.0 = bit);
+                pac::PWM.intr().write_value(Intr(bit));
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
     /// Check if interrupt for channel is set.
     #[inline]
     pub fn wrapped(&mut self) -> bool {
@@ -285,6 +339,23 @@ impl<'d, T: Slice> Pwm<'d, T> {
     }
 }
 
+/// Interrupt handler for the PWM wrap interrupt.
+pub struct InterruptHandler {
+    _empty: (),
+}
+
+impl interrupt::typelevel::Handler<interrupt::typelevel::PWM_IRQ_WRAP> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        let ints = pac::PWM.ints().read().0;
+        pac::PWM.inte().write_clear(|w| w.0 = ints);
+        for (n, waker) in WAKERS.iter().enumerate() {
+            if ints & (1 << n) != 0 {
+                waker.wake();
+            }
+        }
+    }
+}
+
 /// Batch representation of PWM slices.
 pub struct PwmBatch(u32);
 
@@ -310,6 +381,7 @@ impl PwmBatch {
 
 impl<'d, T: Slice> Drop for Pwm<'d, T> {
     fn drop(&mut self) {
+        pac::PWM.inte().write_clear(|w| w.0 = self.bit());
         self.inner.regs().csr().write_clear(|w| w.set_en(false));
         if let Some(pin) = &self.pin_a {
             pin.gpio().ctrl().write(|w| w.set_funcsel(31));
@@ -315,21 +315,25 @@ impl<'d, T: Instance> Spi<'d, T, Async> {
     }
 
     /// Create an SPI driver in async mode supporting DMA read operations only.
+    ///
+    /// The controller only clocks in data while it is clocking out data, so a TX
+    /// DMA channel is still needed to feed it dummy bytes.
     pub fn new_rxonly(
         inner: impl Peripheral<P = T> + 'd,
         clk: impl Peripheral<P = impl ClkPin<T> + 'd> + 'd,
         miso: impl Peripheral<P = impl MisoPin<T> + 'd> + 'd,
+        tx_dma: impl Peripheral<P = impl Channel> + 'd,
         rx_dma: impl Peripheral<P = impl Channel> + 'd,
         config: Config,
     ) -> Self {
-        into_ref!(rx_dma, clk, miso);
+        into_ref!(tx_dma, rx_dma, clk, miso);
         Self::new_inner(
             inner,
             Some(clk.map_into()),
             None,
             Some(miso.map_into()),
             None,
-            None,
+            Some(tx_dma.map_into()),
             Some(rx_dma.map_into()),
             config,
         )
@@ -567,7 +571,7 @@ impl<'d, T: Instance, M: Mode> embedded_hal_1::spi::ErrorType for Spi<'d, T, M>
 
 impl<'d, T: Instance, M: Mode> embedded_hal_1::spi::SpiBus<u8> for Spi<'d, T, M> {
     fn flush(&mut self) -> Result<(), Self::Error> {
-        Ok(())
+        self.flush()
     }
 
     fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
@@ -615,6 +619,9 @@ impl<'d, T: Instance, M: Mode> SetConfig for Spi<'d, T, M> {
     fn set_config(&mut self, config: &Self::Config) -> Result<(), ()> {
         let p = self.inner.regs();
         let (presc, postdiv) = calc_prescs(config.frequency);
+        // disable
+        p.cr1().write(|w| w.set_sse(false));
+
         p.cpsr().write(|w| w.set_cpsdvsr(presc));
         p.cr0().write(|w| {
             w.set_dss(0b0111); // 8bit
@@ -623,6 +630,9 @@ impl<'d, T: Instance, M: Mode> SetConfig for Spi<'d, T, M> {
             w.set_scr(postdiv);
         });
 
+        // enable
+        p.cr1().write(|w| w.set_sse(true));
+
         Ok(())
     }
 }
@@ -54,7 +54,12 @@ impl Driver for TimerDriver {
         });
 
         match id {
-            Ok(id) => Some(AlarmHandle::new(id)),
+            Ok(id) => {
+                // The NVIC is per core: the alarm fires on the core allocating it, usually the one
+                // running the executor using it.
+                alarm_interrupt(id as usize).enable();
+                Some(AlarmHandle::new(id))
+            }
             Err(_) => None,
         }
     }
@@ -127,6 +132,33 @@ impl TimerDriver {
     }
 }
 
+/// Advance the time by `us` microseconds.
+///
+/// The timer is stopped in DORMANT, since its tick is derived from `clk_ref`. Call this after
+/// waking up from `clocks::dormant_sleep` with the time spent asleep, measured with a clock that
+/// kept running (like the RTC clocked from a GPIN), so that the time and pending timers catch up.
+/// Alarms that became due fire right away.
+pub fn advance(us: u64) {
+    critical_section::with(|cs| {
+        let now = DRIVER.now() + us;
+        pac::TIMER.timelw().write_value(now as u32);
+        pac::TIMER.timehw().write_value((now >> 32) as u32);
+
+        // Alarms only fire when the low half of the time matches exactly, which was just skipped.
+        for n in 0..ALARM_COUNT {
+            let timestamp = DRIVER.alarms.borrow(cs)[n].timestamp.get();
+            if timestamp == u64::MAX {
+                continue;
+            }
+            if timestamp <= now {
+                DRIVER.trigger_alarm(n, cs);
+            } else {
+                pac::TIMER.alarm(n).write_value(timestamp as u32);
+            }
+        }
+    });
+}
+
 /// safety: must be called exactly once at bootup
 pub unsafe fn init() {
     // init alarms
@@ -137,17 +169,22 @@ pub unsafe fn init() {
         }
     });
 
-    // enable all irqs
+    // enable all irqs, they are enabled in the NVIC of a core when allocating the alarm.
     pac::TIMER.inte().write(|w| {
         w.set_alarm(0, true);
         w.set_alarm(1, true);
         w.set_alarm(2, true);
         w.set_alarm(3, true);
     });
-    interrupt::TIMER_IRQ_0.enable();
-    interrupt::TIMER_IRQ_1.enable();
-    interrupt::TIMER_IRQ_2.enable();
-    interrupt::TIMER_IRQ_3.enable();
+}
+
+fn alarm_interrupt(n: usize) -> interrupt::Interrupt {
+    match n {
+        0 => interrupt::TIMER_IRQ_0,
+        1 => interrupt::TIMER_IRQ_1,
+        2 => interrupt::TIMER_IRQ_2,
+        _ => interrupt::TIMER_IRQ_3,
+    }
 }
 
 #[cfg(feature = "rt")]
@@ -10,8 +10,18 @@ use core::marker::PhantomData;
 
 use embassy_time::Duration;
 
-use crate::pac;
 use crate::peripherals::WATCHDOG;
+use crate::{pac, rom_data};
+
+/// The reason for a system reset from the watchdog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ResetReason {
+    /// The reset was forced, for example with [`Watchdog::trigger_reset`].
+    Forced,
+    /// The watchdog was not fed in time.
+    TimedOut,
+}
 
 /// Watchdog peripheral
 pub struct Watchdog {
@@ -108,6 +118,32 @@ impl Watchdog {
         })
     }
 
+    /// Reset the chip into BOOTSEL mode, using the watchdog.
+    ///
+    /// * `activity_pin_mask` selects a single GPIO, driven high on USB mass storage activity,
+    ///   or is 0 to use no activity pin.
+    /// * `disable_interface_mask` disables the USB mass storage interface with bit 0,
+    ///   and the PICOBOOT interface with bit 1.
+    pub fn reset_to_usb_boot(&mut self, activity_pin_mask: u32, disable_interface_mask: u32) -> ! {
+        rom_data::reset_to_usb_boot(activity_pin_mask, disable_interface_mask);
+        // The ROM function resets the chip and never returns
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    /// Returns the reason for the last system reset, if it was caused by the watchdog.
+    pub fn reset_reason(&self) -> Option<ResetReason> {
+        let reason = pac::WATCHDOG.reason().read();
+        if reason.force() {
+            Some(ResetReason::Forced)
+        } else if reason.timer() {
+            Some(ResetReason::TimedOut)
+        } else {
+            None
+        }
+    }
+
     /// Store data in scratch register
     pub fn set_scratch(&mut self, index: usize, value: u32) {
         let watchdog = pac::WATCHDOG;
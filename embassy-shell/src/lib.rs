@@ -0,0 +1,357 @@
+#![no_std]
+#![allow(async_fn_in_trait)]
+#![doc = include_str!("../README.md")]
+#![warn(missing_docs)]
+
+use core::fmt::{self, Write as _};
+use core::str::FromStr;
+
+use embedded_io_async::{Read, Write};
+
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+const CTRL_C: u8 = 0x03;
+const ESCAPE: u8 = 0x1b;
+const BELL: u8 = 0x07;
+
+/// Description of a command, used to generate the help.
+#[derive(Copy, Clone, Debug)]
+pub struct CommandInfo {
+    /// Name of the command, typed to run it.
+    pub name: &'static str,
+    /// Arguments of the command, like `<count> [period]`.
+    pub args: &'static str,
+    /// One line description of the command.
+    pub help: &'static str,
+}
+
+impl CommandInfo {
+    /// Create a new command description.
+    pub const fn new(name: &'static str, args: &'static str, help: &'static str) -> Self {
+        Self { name, args, help }
+    }
+}
+
+/// Error parsing a command argument.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ArgError {
+    /// The argument is missing.
+    Missing,
+    /// The argument can't be parsed into the requested type.
+    Invalid,
+}
+
+/// Command entered by the user.
+pub struct Command<'l> {
+    info: &'static CommandInfo,
+    args: core::str::SplitAsciiWhitespace<'l>,
+}
+
+impl<'l> Command<'l> {
+    /// Get the name of the command.
+    pub fn name(&self) -> &'static str {
+        self.info.name
+    }
+
+    /// Get the description of the command.
+    pub fn info(&self) -> &'static CommandInfo {
+        self.info
+    }
+
+    /// Get the next argument.
+    pub fn next_arg(&mut self) -> Option<&'l str> {
+        self.args.next()
+    }
+
+    /// Parse the next argument.
+    pub fn parse_arg<T: FromStr>(&mut self) -> Result<T, ArgError> {
+        self.next_arg()
+            .ok_or(ArgError::Missing)?
+            .parse()
+            .map_err(|_| ArgError::Invalid)
+    }
+
+    /// Get the remaining arguments.
+    pub fn remaining_args(&mut self) -> impl Iterator<Item = &'l str> + '_ {
+        &mut self.args
+    }
+}
+
+/// Command shell over an `embedded-io-async` transport.
+///
+/// Commands are read with [`read_command`](Self::read_command), which only returns the commands
+/// from the list given at creation, with their arguments. Unknown commands and `help` are handled
+/// by the shell itself.
+pub struct Shell<'a, IO> {
+    io: IO,
+    commands: &'static [CommandInfo],
+    out: &'a mut [u8],
+    prompt: &'a str,
+    last: u8,
+}
+
+impl<'a, IO: Read + Write> Shell<'a, IO> {
+    /// Create a new shell.
+    ///
+    /// `out` is used to format the output of [`println`](Self::println), longer lines are truncated.
+    pub fn new(io: IO, commands: &'static [CommandInfo], out: &'a mut [u8]) -> Self {
+        Self {
+            io,
+            commands,
+            out,
+            prompt: "> ",
+            last: 0,
+        }
+    }
+
+    /// Set the prompt printed before each command, `"> "` by default.
+    pub fn set_prompt(&mut self, prompt: &'a str) {
+        self.prompt = prompt;
+    }
+
+    /// Release the transport.
+    pub fn into_inner(self) -> IO {
+        self.io
+    }
+
+    /// Print the prompt and read a command, with line editing.
+    ///
+    /// `line` holds the characters typed, a command can't be longer.
+    pub async fn read_command<'l>(&mut self, line: &'l mut [u8]) -> Result<Command<'l>, IO::Error> {
+        loop {
+            let len = self.read_line(line).await?;
+            // Only ASCII is accepted in the line.
+            let text = core::str::from_utf8(&line[..len]).unwrap();
+            let mut args = text.split_ascii_whitespace();
+            let Some(name) = args.next() else {
+                continue;
+            };
+
+            if let Some(info) = self.commands.iter().find(|c| c.name == name) {
+                // Reborrow the line for the returned lifetime.
+                let text = core::str::from_utf8(&line[..len]).unwrap();
+                let mut args = text.split_ascii_whitespace();
+                args.next();
+                return Ok(Command { info, args });
+            }
+
+            if name == "help" {
+                self.help().await?;
+            } else {
+                self.println(format_args!("unknown command: {}, type help for a list", name))
+                    .await?;
+            }
+        }
+    }
+
+    /// Print the list of commands.
+    pub async fn help(&mut self) -> Result<(), IO::Error> {
+        let width = self
+            .commands
+            .iter()
+            .map(|c| c.name.len() + 1 + c.args.len())
+            .max()
+            .unwrap_or(0);
+        for c in self.commands {
+            let pad = width - (c.name.len() + 1 + c.args.len());
+            self.println(format_args!(
+                "  {} {}{:pad$}  {}",
+                c.name,
+                c.args,
+                "",
+                c.help,
+                pad = pad
+            ))
+            .await?;
+        }
+        self.println(format_args!(
+            "  help{:pad$}  Show this list",
+            "",
+            pad = width.saturating_sub(4)
+        ))
+        .await
+    }
+
+    /// Print the usage of a command, typically after an invalid argument.
+    pub async fn usage(&mut self, command: &Command<'_>) -> Result<(), IO::Error> {
+        let info = command.info;
+        self.println(format_args!("usage: {} {}", info.name, info.args)).await
+    }
+
+    /// Print a formatted line.
+    pub async fn println(&mut self, args: fmt::Arguments<'_>) -> Result<(), IO::Error> {
+        let mut w = BufWriter { buf: self.out, len: 0 };
+        let _ = w.write_fmt(args);
+        let len = w.len;
+        self.io.write_all(&self.out[..len]).await?;
+        self.io.write_all(b"\r\n").await
+    }
+
+    /// Write a string as is.
+    pub async fn write_str(&mut self, s: &str) -> Result<(), IO::Error> {
+        self.io.write_all(s.as_bytes()).await
+    }
+
+    async fn read_line(&mut self, line: &mut [u8]) -> Result<usize, IO::Error> {
+        self.io.write_all(self.prompt.as_bytes()).await?;
+        self.io.flush().await?;
+
+        let mut len = 0;
+        let mut escape = 0;
+        loop {
+            let mut b = [0; 1];
+            if self.io.read(&mut b).await? == 0 {
+                continue;
+            }
+            let b = b[0];
+            let last = core::mem::replace(&mut self.last, b);
+
+            // Skip escape sequences, like arrow keys: ESC [ A.
+            if escape > 0 {
+                escape = match (escape, b) {
+                    (1, b'[') => 2,
+                    (2, b'0'..=b'9' | b';') => 2,
+                    _ => 0,
+                };
+                continue;
+            }
+
+            match b {
+                // Accept CR, LF and CRLF line endings.
+                b'\n' if last == b'\r' => {}
+                b'\r' | b'\n' => {
+                    self.io.write_all(b"\r\n").await?;
+                    return Ok(len);
+                }
+                BACKSPACE | DELETE => {
+                    if len > 0 {
+                        len -= 1;
+                        self.io.write_all(b"\x08 \x08").await?;
+                    }
+                }
+                CTRL_C => {
+                    len = 0;
+                    self.io.write_all(b"^C\r\n").await?;
+                    self.io.write_all(self.prompt.as_bytes()).await?;
+                }
+                ESCAPE => escape = 1,
+                0x20..=0x7e if len < line.len() => {
+                    line[len] = b;
+                    len += 1;
+                    self.io.write_all(&[b]).await?;
+                }
+                _ => self.io.write_all(&[BELL]).await?,
+            }
+            self.io.flush().await?;
+        }
+    }
+}
+
+struct BufWriter<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl fmt::Write for BufWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let n = core::cmp::min(s.len(), self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+        self.len += n;
+        if n < s.len() {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate alloc;
+
+    use alloc::vec::Vec;
+    use core::convert::Infallible;
+
+    use embedded_io_async::ErrorType;
+
+    use super::*;
+
+    struct Term<'a> {
+        input: &'a [u8],
+        output: Vec<u8>,
+    }
+
+    impl ErrorType for Term<'_> {
+        type Error = Infallible;
+    }
+
+    impl Read for Term<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Infallible> {
+            let n = core::cmp::min(buf.len(), self.input.len());
+            assert!(n > 0, "input exhausted");
+            buf[..n].copy_from_slice(&self.input[..n]);
+            self.input = &self.input[n..];
+            Ok(n)
+        }
+    }
+
+    impl Write for Term<'_> {
+        async fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+            self.output.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+    }
+
+    const COMMANDS: &[CommandInfo] = &[
+        CommandInfo::new("led", "<on|off>", "Switch the LED"),
+        CommandInfo::new("blink", "<count> <period_ms>", "Blink the LED"),
+    ];
+
+    #[futures_test::test]
+    async fn can_read_commands() {
+        let term = Term {
+            input: b"blonk\r\nblink 3\x7f5  x\x1b[D 10\r\n\nled\x03led on\n",
+            output: Vec::new(),
+        };
+        let mut out = [0; 64];
+        let mut shell = Shell::new(term, COMMANDS, &mut out);
+        let mut line = [0; 32];
+
+        let mut cmd = shell.read_command(&mut line).await.unwrap();
+        assert_eq!(cmd.name(), "blink");
+        assert_eq!(cmd.parse_arg::<u32>(), Ok(5));
+        assert_eq!(cmd.parse_arg::<u32>(), Err(ArgError::Invalid));
+        assert_eq!(cmd.parse_arg::<u32>(), Ok(10));
+        assert_eq!(cmd.parse_arg::<u32>(), Err(ArgError::Missing));
+
+        let mut cmd = shell.read_command(&mut line).await.unwrap();
+        assert_eq!(cmd.name(), "led");
+        assert_eq!(cmd.next_arg(), Some("on"));
+
+        let term = shell.into_inner();
+        let output = core::str::from_utf8(&term.output).unwrap();
+        assert!(output
+            .starts_with("> blonk\r\nunknown command: blonk, type help for a list\r\n> blink 3\x08 \x085  x 10\r\n"));
+        assert!(output.ends_with("> led^C\r\n> led on\r\n"));
+    }
+
+    #[futures_test::test]
+    async fn can_print_help() {
+        let term = Term {
+            input: b"help\rled\r",
+            output: Vec::new(),
+        };
+        let mut out = [0; 64];
+        let mut shell = Shell::new(term, COMMANDS, &mut out);
+        let mut line = [0; 32];
+
+        shell.read_command(&mut line).await.unwrap();
+
+        let term = shell.into_inner();
+        let output = core::str::from_utf8(&term.output).unwrap();
+        assert_eq!(
+            output,
+            "> help\r\n  led <on|off>               Switch the LED\r\n  blink <count> <period_ms>  Blink the LED\r\n  help                       Show this list\r\n> led\r\n"
+        );
+    }
+}
@@ -0,0 +1,276 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash};
+
+/// Error returned by [`SimFlash`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimFlashError {
+    /// The offset or length is not a multiple of the read, write or erase size.
+    NotAligned,
+    /// The operation goes past the end of the flash.
+    OutOfBounds,
+    /// The operation tried to program a bit from 0 to 1 without erasing it first.
+    NotErased,
+    /// The simulated power loss configured with [`SimFlash::fail_after`] happened.
+    PowerLoss,
+}
+
+impl NorFlashError for SimFlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Self::NotAligned => NorFlashErrorKind::NotAligned,
+            Self::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Self::NotErased | Self::PowerLoss => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// Simulated NOR flash.
+///
+/// Erasing sets the bytes to `0xFF`, and writing can only clear bits, like on real NOR flash:
+/// writing to memory that wasn't erased fails with [`SimFlashError::NotErased`] rather than
+/// silently corrupting the data. Offsets and lengths must be aligned to `WRITE_SIZE` and
+/// `ERASE_SIZE`.
+///
+/// The contents are kept in memory, and optionally saved to a file so they survive a restart of
+/// the simulated device.
+pub struct SimFlash<const WRITE_SIZE: usize, const ERASE_SIZE: usize> {
+    data: Vec<u8>,
+    file: Option<File>,
+    remaining_ops: Option<usize>,
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> SimFlash<WRITE_SIZE, ERASE_SIZE> {
+    /// Create an erased flash of `size` bytes, kept in memory.
+    pub fn new(size: usize) -> Self {
+        assert!(size % ERASE_SIZE == 0);
+        Self {
+            data: vec![0xFF; size],
+            file: None,
+            remaining_ops: None,
+        }
+    }
+
+    /// Open a flash of `size` bytes backed by the file at `path`.
+    ///
+    /// The file is created erased if it doesn't exist, and every write and erase is saved to it.
+    pub fn open(path: impl AsRef<Path>, size: usize) -> std::io::Result<Self> {
+        assert!(size % ERASE_SIZE == 0);
+        let mut file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        let mut data = Vec::with_capacity(size);
+        file.read_to_end(&mut data)?;
+        if data.len() != size {
+            data.resize(size, 0xFF);
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(&data)?;
+            file.set_len(size as u64)?;
+        }
+        Ok(Self {
+            data,
+            file: Some(file),
+            remaining_ops: None,
+        })
+    }
+
+    /// Simulate a power loss after `ops` more writes or erases.
+    ///
+    /// The operation that hits the power loss is left half done: half of its bytes are written or
+    /// erased, and [`SimFlashError::PowerLoss`] is returned. All the following operations fail the
+    /// same way until [`SimFlash::power_on`] is called.
+    pub fn fail_after(&mut self, ops: usize) {
+        self.remaining_ops = Some(ops);
+    }
+
+    /// Restore the power after a simulated power loss.
+    pub fn power_on(&mut self) {
+        self.remaining_ops = None;
+    }
+
+    /// Access the raw contents of the flash.
+    pub fn contents(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Access the raw contents of the flash mutably, bypassing the NOR flash rules.
+    pub fn contents_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    fn check(&self, offset: u32, len: usize, align: usize) -> Result<usize, SimFlashError> {
+        let offset = offset as usize;
+        if offset % align != 0 || len % align != 0 {
+            return Err(SimFlashError::NotAligned);
+        }
+        if offset + len > self.data.len() {
+            return Err(SimFlashError::OutOfBounds);
+        }
+        Ok(offset)
+    }
+
+    /// Returns the number of bytes the operation is allowed to change before the power is lost.
+    fn power(&mut self, len: usize) -> Result<(), usize> {
+        match &mut self.remaining_ops {
+            None => Ok(()),
+            Some(0) => Err(0),
+            Some(n) => {
+                *n -= 1;
+                if *n == 0 {
+                    Err(len / 2)
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+
+    fn save(&mut self, offset: usize, len: usize) {
+        if let Some(file) = &mut self.file {
+            file.seek(SeekFrom::Start(offset as u64))
+                .and_then(|_| file.write_all(&self.data[offset..offset + len]))
+                .expect("failed to save flash contents");
+        }
+    }
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> ErrorType for SimFlash<WRITE_SIZE, ERASE_SIZE> {
+    type Error = SimFlashError;
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> ReadNorFlash for SimFlash<WRITE_SIZE, ERASE_SIZE> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = self.check(offset, bytes.len(), Self::READ_SIZE)?;
+        bytes.copy_from_slice(&self.data[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> NorFlash for SimFlash<WRITE_SIZE, ERASE_SIZE> {
+    const WRITE_SIZE: usize = WRITE_SIZE;
+    const ERASE_SIZE: usize = ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if to < from {
+            return Err(SimFlashError::OutOfBounds);
+        }
+        let offset = self.check(from, (to - from) as usize, ERASE_SIZE)?;
+        let len = (to - from) as usize;
+        let (done, res) = match self.power(len) {
+            Ok(()) => (len, Ok(())),
+            Err(done) => (done, Err(SimFlashError::PowerLoss)),
+        };
+        self.data[offset..offset + done].fill(0xFF);
+        self.save(offset, done);
+        res
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let offset = self.check(offset, bytes.len(), WRITE_SIZE)?;
+        let target = &self.data[offset..offset + bytes.len()];
+        if target.iter().zip(bytes).any(|(old, new)| !old & new != 0) {
+            return Err(SimFlashError::NotErased);
+        }
+        let (done, res) = match self.power(bytes.len()) {
+            Ok(()) => (bytes.len(), Ok(())),
+            Err(done) => (done, Err(SimFlashError::PowerLoss)),
+        };
+        self.data[offset..offset + done].copy_from_slice(&bytes[..done]);
+        self.save(offset, done);
+        res
+    }
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> embedded_storage_async::nor_flash::ReadNorFlash
+    for SimFlash<WRITE_SIZE, ERASE_SIZE>
+{
+    const READ_SIZE: usize = 1;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        ReadNorFlash::read(self, offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl<const WRITE_SIZE: usize, const ERASE_SIZE: usize> embedded_storage_async::nor_flash::NorFlash
+    for SimFlash<WRITE_SIZE, ERASE_SIZE>
+{
+    const WRITE_SIZE: usize = WRITE_SIZE;
+    const ERASE_SIZE: usize = ERASE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        NorFlash::erase(self, from, to)
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        NorFlash::write(self, offset, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nor_semantics() {
+        let mut flash = SimFlash::<4, 256>::new(1024);
+
+        flash.write(0, &[0x0F, 0xF0, 0xAA, 0x55]).unwrap();
+        // Clearing more bits is fine, setting them isn't.
+        flash.write(0, &[0x0E, 0x00, 0xAA, 0x55]).unwrap();
+        assert_eq!(flash.write(0, &[0xFF; 4]), Err(SimFlashError::NotErased));
+        assert_eq!(flash.write(2, &[0; 4]), Err(SimFlashError::NotAligned));
+        assert_eq!(flash.write(1024, &[0; 4]), Err(SimFlashError::OutOfBounds));
+        assert_eq!(flash.erase(0, 100), Err(SimFlashError::NotAligned));
+
+        flash.erase(0, 256).unwrap();
+        let mut buf = [0; 4];
+        ReadNorFlash::read(&mut flash, 0, &mut buf).unwrap();
+        assert_eq!(buf, [0xFF; 4]);
+    }
+
+    #[test]
+    fn power_loss() {
+        let mut flash = SimFlash::<4, 256>::new(1024);
+        flash.fail_after(2);
+
+        flash.write(0, &[0; 8]).unwrap();
+        assert_eq!(flash.write(8, &[0; 8]), Err(SimFlashError::PowerLoss));
+        assert_eq!(&flash.contents()[8..16], &[0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        assert_eq!(flash.erase(0, 256), Err(SimFlashError::PowerLoss));
+        assert_eq!(flash.contents()[0], 0);
+
+        flash.power_on();
+        flash.erase(0, 256).unwrap();
+        assert_eq!(flash.contents()[0], 0xFF);
+    }
+
+    #[test]
+    fn file_backed() {
+        let path = std::env::temp_dir().join(format!("embassy-sim-flash-{}.bin", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut flash = SimFlash::<4, 256>::open(&path, 1024).unwrap();
+        flash.write(256, &[1, 2, 3, 4]).unwrap();
+        drop(flash);
+
+        let flash = SimFlash::<4, 256>::open(&path, 1024).unwrap();
+        assert_eq!(&flash.contents()[256..260], &[1, 2, 3, 4]);
+        assert_eq!(flash.contents()[0], 0xFF);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
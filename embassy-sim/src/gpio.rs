@@ -0,0 +1,153 @@
+use std::convert::Infallible;
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex};
+use std::task::{Poll, Waker};
+
+use embedded_hal_1::digital::{ErrorType, InputPin, OutputPin, PinState, StatefulOutputPin};
+
+struct State {
+    level: bool,
+    wakers: Vec<Waker>,
+}
+
+/// Simulated GPIO pin.
+///
+/// Clones of a pin share the same level: give one to the firmware and keep another in the test
+/// to drive an input or check an output. Any clone can change the level, and tasks waiting on an
+/// edge with [`Wait`](embedded_hal_async::digital::Wait) are woken when it does.
+#[derive(Clone)]
+pub struct SimPin {
+    state: Arc<Mutex<State>>,
+}
+
+impl SimPin {
+    /// Create a pin with the given initial level.
+    pub fn new(initial: PinState) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State {
+                level: initial == PinState::High,
+                wakers: Vec::new(),
+            })),
+        }
+    }
+
+    /// Get the current level.
+    pub fn is_high(&self) -> bool {
+        self.state.lock().unwrap().level
+    }
+
+    /// Set the level, waking the tasks waiting for an edge if it changed.
+    pub fn set_level(&self, level: PinState) {
+        let mut state = self.state.lock().unwrap();
+        let level = level == PinState::High;
+        if state.level != level {
+            state.level = level;
+            state.wakers.drain(..).for_each(Waker::wake);
+        }
+    }
+
+    /// Wait until `done` returns true, calling it with the level before and after each change.
+    async fn wait(&self, mut done: impl FnMut(bool, bool) -> bool) {
+        let mut last = self.is_high();
+        poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if done(last, state.level) {
+                return Poll::Ready(());
+            }
+            last = state.level;
+            if !state.wakers.iter().any(|w| w.will_wake(cx.waker())) {
+                state.wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl ErrorType for SimPin {
+    type Error = Infallible;
+}
+
+impl InputPin for SimPin {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(SimPin::is_high(self))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!SimPin::is_high(self))
+    }
+}
+
+impl OutputPin for SimPin {
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set_level(PinState::Low);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set_level(PinState::High);
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for SimPin {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(SimPin::is_high(self))
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(!SimPin::is_high(self))
+    }
+}
+
+impl embedded_hal_async::digital::Wait for SimPin {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        self.wait(|_, now| now).await;
+        Ok(())
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        self.wait(|_, now| !now).await;
+        Ok(())
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait(|before, now| !before && now).await;
+        Ok(())
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait(|before, now| before && !now).await;
+        Ok(())
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        self.wait(|before, now| before != now).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use embedded_hal_async::digital::Wait;
+
+    use super::*;
+
+    #[futures_test::test]
+    async fn edges() {
+        let mut input = SimPin::new(PinState::Low);
+        let driver = input.clone();
+
+        let thread = std::thread::spawn(move || {
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            driver.set_level(PinState::High);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+            driver.set_level(PinState::Low);
+        });
+
+        input.wait_for_rising_edge().await.unwrap();
+        input.wait_for_falling_edge().await.unwrap();
+        assert!(InputPin::is_low(&mut input).unwrap());
+        thread.join().unwrap();
+    }
+}
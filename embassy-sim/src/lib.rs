@@ -0,0 +1,10 @@
+#![doc = include_str!("../README.md")]
+#![warn(missing_docs)]
+
+mod flash;
+mod gpio;
+mod uart;
+
+pub use flash::{SimFlash, SimFlashError};
+pub use gpio::SimPin;
+pub use uart::TcpUart;
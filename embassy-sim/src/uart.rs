@@ -0,0 +1,110 @@
+use std::io::{self, Read as _, Write as _};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+use std::thread;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::pipe::Pipe;
+use embedded_io_async::{ErrorType, Read, Write};
+
+const RX_BUFFER_SIZE: usize = 256;
+
+/// Simulated UART over a TCP connection.
+///
+/// Received bytes are read from the socket by a background thread into a buffer, like the RX
+/// buffer of a buffered UART driver, so reading never blocks the executor. Writes go straight to
+/// the socket.
+///
+/// If the other side closes the connection, reads wait forever, like on a UART with nothing
+/// connected.
+pub struct TcpUart {
+    stream: TcpStream,
+    rx: Arc<Pipe<CriticalSectionRawMutex, RX_BUFFER_SIZE>>,
+}
+
+impl TcpUart {
+    /// Connect to a TCP server, for example one started with `nc -l 1234`.
+    pub fn connect(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::new(TcpStream::connect(addr)?)
+    }
+
+    /// Listen on `addr` and wait for one client to connect, for example with `telnet localhost 1234`.
+    pub fn listen(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        Self::new(stream)
+    }
+
+    /// Create a UART using an existing connection.
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        let rx = Arc::new(Pipe::new());
+
+        let mut reader = stream.try_clone()?;
+        let pipe = rx.clone();
+        thread::spawn(move || {
+            let mut buf = [0; RX_BUFFER_SIZE];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => {
+                        let mut data = &buf[..n];
+                        while !data.is_empty() {
+                            match pipe.try_write(data) {
+                                Ok(written) => data = &data[written..],
+                                // The firmware isn't reading fast enough, wait for room like
+                                // a UART with hardware flow control.
+                                Err(_) => thread::sleep(std::time::Duration::from_millis(1)),
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { stream, rx })
+    }
+}
+
+impl ErrorType for TcpUart {
+    type Error = io::Error;
+}
+
+impl Read for TcpUart {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        Ok(self.rx.read(buf).await)
+    }
+}
+
+impl Write for TcpUart {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        self.stream.write(buf)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        self.stream.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[futures_test::test]
+    async fn echo() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let mut uart = TcpUart::new(listener.accept().unwrap().0).unwrap();
+
+        client.write_all(b"hello").unwrap();
+        let mut buf = [0; 5];
+        uart.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"hello");
+
+        uart.write_all(b"world").await.unwrap();
+        client.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"world");
+    }
+}
@@ -16,6 +16,16 @@
 //! low-power executor will only attempt to enter when the next timer event is at least
 //! [`time_driver::MIN_STOP_PAUSE`] in the future.
 //!
+//! The application can also keep the executor out of a stop mode, for example while a USB cable
+//! is attached or a latency-critical operation is in progress, by holding a [`StopBlocker`].
+//! [`stats()`] reports how the executor spent its idle periods, and [`stop_blockers()`] what
+//! currently blocks stop modes, to debug why the device doesn't reach the expected stop mode.
+//!
+//! Currently, blockers and statistics are specific to this executor: there is no HAL-independent
+//! way for drivers to declare low-power constraints, or for other executors to pick a sleep state
+//! from them. Blockers and wake-ups are also only counted, not attributed to a driver or a wake
+//! source.
+//!
 //! Currently there is no macro analogous to `embassy_executor::main` for this executor;
 //! consequently one must define their entrypoint manually. Moveover, you must relinquish control
 //! of the `RTC` peripheral to the executor. This will typically look like
@@ -67,6 +77,20 @@ use crate::rtc::Rtc;
 
 static mut EXECUTOR: Option<Executor> = None;
 
+/// Must be accessed within a critical section.
+static mut STATS: Stats = Stats {
+    sleep: 0,
+    time_not_paused: 0,
+    stop1: 0,
+    stop2: 0,
+    wakeup_irqs: 0,
+};
+
+/// Must be written within a critical section.
+///
+/// May be read without a critical section.
+static mut APP_BLOCKERS: u32 = 0;
+
 foreach_interrupt! {
     (RTC, rtc, $block:ident, WKUP, $irq:ident) => {
         #[interrupt]
@@ -101,7 +125,8 @@ pub fn stop_ready(stop_mode: StopMode) -> bool {
 
 /// Available stop modes.
 #[non_exhaustive]
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum StopMode {
     /// STOP 1
     Stop1,
@@ -109,6 +134,82 @@ pub enum StopMode {
     Stop2,
 }
 
+/// Keeps the executor out of a stop mode while held.
+///
+/// Holding a blocker for [`StopMode::Stop1`] prevents entering any stop mode, while a blocker for
+/// [`StopMode::Stop2`] still allows entering STOP 1. The executor enters regular sleep instead.
+/// This works the same as the peripheral drivers, which block stop modes their clocks don't run in.
+pub struct StopBlocker {
+    stop_mode: StopMode,
+}
+
+impl StopBlocker {
+    /// Block entering `stop_mode` and deeper modes until the blocker is dropped.
+    pub fn new(stop_mode: StopMode) -> Self {
+        critical_section::with(|_| unsafe {
+            match stop_mode {
+                StopMode::Stop1 => crate::rcc::REFCOUNT_STOP1 += 1,
+                StopMode::Stop2 => crate::rcc::REFCOUNT_STOP2 += 1,
+            }
+            APP_BLOCKERS += 1;
+        });
+        Self { stop_mode }
+    }
+}
+
+impl Drop for StopBlocker {
+    fn drop(&mut self) {
+        critical_section::with(|_| unsafe {
+            match self.stop_mode {
+                StopMode::Stop1 => crate::rcc::REFCOUNT_STOP1 -= 1,
+                StopMode::Stop2 => crate::rcc::REFCOUNT_STOP2 -= 1,
+            }
+            APP_BLOCKERS -= 1;
+        });
+    }
+}
+
+/// Low-power statistics, to find out why the device doesn't enter the expected stop mode.
+///
+/// The counters are incremented each time the executor goes idle, according to what it did.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stats {
+    /// Times the executor only slept, because a peripheral or a [`StopBlocker`] blocked all stop modes.
+    pub sleep: u32,
+    /// Times the executor only slept, because the next timer was too close to stop.
+    pub time_not_paused: u32,
+    /// Times the executor entered STOP 1.
+    pub stop1: u32,
+    /// Times the executor entered STOP 2.
+    pub stop2: u32,
+    /// Times the core was woken from stop by the RTC or an EXTI line.
+    pub wakeup_irqs: u32,
+}
+
+/// Get the low-power statistics since the last call, and reset them.
+pub fn stats() -> Stats {
+    critical_section::with(|_| unsafe {
+        let stats = STATS;
+        STATS = Stats {
+            sleep: 0,
+            time_not_paused: 0,
+            stop1: 0,
+            stop2: 0,
+            wakeup_irqs: 0,
+        };
+        stats
+    })
+}
+
+/// Get the current number of holders blocking stop modes, as `(stop1, stop2, application)`.
+///
+/// `stop1` and `stop2` count both peripheral drivers and [`StopBlocker`]s, the `application` count
+/// only the latter. When a count is non-zero, the corresponding stop mode can't be entered.
+pub fn stop_blockers() -> (u32, u32, u32) {
+    unsafe { (crate::rcc::REFCOUNT_STOP1, crate::rcc::REFCOUNT_STOP2, APP_BLOCKERS) }
+}
+
 #[cfg(stm32l5)]
 use stm32_metapac::pwr::vals::Lpms;
 
@@ -157,6 +258,7 @@ impl Executor {
     }
 
     unsafe fn on_wakeup_irq(&mut self) {
+        critical_section::with(|_| STATS.wakeup_irqs += 1);
         self.time_driver.resume_time();
         trace!("low power: resume");
     }
@@ -193,14 +295,22 @@ impl Executor {
         let stop_mode = self.stop_mode();
         if stop_mode.is_none() {
             trace!("low power: not ready to stop");
+            critical_section::with(|_| unsafe { STATS.sleep += 1 });
         } else if self.time_driver.pause_time().is_err() {
             trace!("low power: failed to pause time");
+            critical_section::with(|_| unsafe { STATS.time_not_paused += 1 });
         } else {
             let stop_mode = stop_mode.unwrap();
             match stop_mode {
                 StopMode::Stop1 => trace!("low power: stop 1"),
                 StopMode::Stop2 => trace!("low power: stop 2"),
             }
+            critical_section::with(|_| unsafe {
+                match stop_mode {
+                    StopMode::Stop1 => STATS.stop1 += 1,
+                    StopMode::Stop2 => STATS.stop2 += 1,
+                }
+            });
             self.configure_stop(stop_mode);
 
             #[cfg(not(feature = "low-power-debug-with-sleep"))]
@@ -4,7 +4,6 @@
 use core::default::Default;
 use core::future::poll_fn;
 use core::marker::PhantomData;
-use core::ops::{Deref, DerefMut};
 use core::task::Poll;
 
 use embassy_hal_internal::drop::OnDrop;
@@ -73,24 +72,7 @@ impl Default for Signalling {
 /// Aligned data block for SDMMC transfers.
 ///
 /// This is a 512-byte array, aligned to 4 bytes to satisfy DMA requirements.
-#[repr(align(4))]
-#[derive(Debug, Clone, PartialEq, Eq)]
-#[cfg_attr(feature = "defmt", derive(defmt::Format))]
-pub struct DataBlock(pub [u8; 512]);
-
-impl Deref for DataBlock {
-    type Target = [u8; 512];
-
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl DerefMut for DataBlock {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
+pub use embassy_embedded_hal::block::Block as DataBlock;
 
 /// Errors
 #[non_exhaustive]
@@ -1306,6 +1288,35 @@ impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Sdmmc<'d, T, Dma> {
     }
 }
 
+impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> embassy_embedded_hal::block::BlockDevice for Sdmmc<'d, T, Dma> {
+    type Error = Error;
+
+    async fn read(&mut self, block_idx: u32, blocks: &mut [DataBlock]) -> Result<(), Self::Error> {
+        // TODO: use multi-block reads (CMD18)
+        for (i, block) in blocks.iter_mut().enumerate() {
+            self.read_block(block_idx + i as u32, block).await?;
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, block_idx: u32, blocks: &[DataBlock]) -> Result<(), Self::Error> {
+        // TODO: use multi-block writes (CMD25)
+        for (i, block) in blocks.iter().enumerate() {
+            self.write_block(block_idx + i as u32, block).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        // `write_block` only returns once the card has finished programming.
+        Ok(())
+    }
+
+    async fn block_count(&mut self) -> Result<u32, Self::Error> {
+        Ok(self.card()?.csd.block_count())
+    }
+}
+
 impl<'d, T: Instance, Dma: SdmmcDma<T> + 'd> Drop for Sdmmc<'d, T, Dma> {
     fn drop(&mut self) {
         T::Interrupt::disable();
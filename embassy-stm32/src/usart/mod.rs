@@ -41,6 +41,23 @@ impl<T: BasicInstance> interrupt::typelevel::Handler<T::Interrupt> for Interrupt
 
         let (sr, cr1, cr3) = (sr(r).read(), r.cr1().read(), r.cr3().read());
 
+        #[cfg(any(usart_v3, usart_v4))]
+        if cr3.wufie() && sr.wuf() {
+            // Woken from STOP mode by a start bit: the time driver may have been paused
+            #[cfg(feature = "low-power")]
+            crate::low_power::on_wakeup_irq();
+
+            r.icr().write(|w| w.set_wuf(true));
+            r.cr3().modify(|w| {
+                // disable wakeup interrupt, it is re-armed by the next waiter
+                w.set_wufie(false);
+            });
+
+            compiler_fence(Ordering::SeqCst);
+            s.rx_waker.wake();
+            return;
+        }
+
         let has_errors = (sr.pe() && cr1.peie()) || ((sr.fe() || sr.ne() || sr.ore()) && cr3.eie());
         if has_errors {
             // clear all interrupts and DMA Rx Request
@@ -162,6 +179,15 @@ pub struct Config {
     /// Set this to true to invert RX pin signal values (V<sub>DD</sub> =0/mark, Gnd = 1/idle).
     #[cfg(any(usart_v3, usart_v4))]
     pub invert_rx: bool,
+
+    /// Set this to true to allow a start bit on RX to wake the MCU from STOP mode.
+    ///
+    /// This only works if the peripheral kernel clock keeps running in STOP mode,
+    /// so the instance must be clocked from LSE or HSI (see `rcc::Config::mux`).
+    /// At 9600 baud and below, LSE allows an LPUART to receive while the rest of the
+    /// chip is stopped.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub wakeup_from_stop: bool,
 }
 
 impl Default for Config {
@@ -181,6 +207,8 @@ impl Default for Config {
             invert_tx: false,
             #[cfg(any(usart_v3, usart_v4))]
             invert_rx: false,
+            #[cfg(any(usart_v3, usart_v4))]
+            wakeup_from_stop: false,
         }
     }
 }
@@ -551,6 +579,39 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
         self.inner_read(buffer, true).await
     }
 
+    /// Wait until a start bit is detected on RX while the MCU is in STOP mode.
+    ///
+    /// Requires [`Config::wakeup_from_stop`]. The incoming character is not consumed
+    /// and is available to a subsequent read.
+    #[cfg(any(usart_v3, usart_v4))]
+    pub async fn wait_for_wakeup(&mut self) {
+        let r = T::regs();
+
+        // make sure the wakeup interrupt is disabled when this future is dropped
+        let on_drop = OnDrop::new(move || {
+            r.cr3().modify(|w| w.set_wufie(false));
+        });
+
+        r.icr().write(|w| w.set_wuf(true));
+        r.cr3().modify(|w| w.set_wufie(true));
+
+        compiler_fence(Ordering::SeqCst);
+
+        poll_fn(|cx| {
+            T::state().rx_waker.register(cx.waker());
+
+            // the interrupt handler disables the wakeup interrupt once it fired
+            if r.cr3().read().wufie() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+
+        on_drop.defuse();
+    }
+
     async fn inner_read_run(
         &mut self,
         buffer: &mut [u8],
@@ -578,6 +639,9 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
                 w.set_eie(false);
                 // disable DMA Rx Request
                 w.set_dmar(false);
+                // disable wakeup from STOP interrupt
+                #[cfg(any(usart_v3, usart_v4))]
+                w.set_wufie(false);
             });
         });
 
@@ -613,6 +677,14 @@ impl<'d, T: BasicInstance, RxDma> UartRx<'d, T, RxDma> {
             w.set_dmar(true);
         });
 
+        // If the first byte arrives while in STOP mode, the wakeup interrupt gets the core
+        // (and the DMA) running again before the receiver overruns.
+        #[cfg(any(usart_v3, usart_v4))]
+        if r.cr1().read().uesm() {
+            r.icr().write(|w| w.set_wuf(true));
+            r.cr3().modify(|w| w.set_wufie(true));
+        }
+
         compiler_fence(Ordering::SeqCst);
 
         // In case of errors already pending when reception started, interrupts may have already been raised
@@ -1008,6 +1080,14 @@ impl<'d, T: BasicInstance, TxDma, RxDma> Uart<'d, T, TxDma, RxDma> {
         self.rx.read_until_idle(buffer).await
     }
 
+    /// Wait until a start bit is detected on RX while the MCU is in STOP mode.
+    ///
+    /// See [`UartRx::wait_for_wakeup`].
+    #[cfg(any(usart_v3, usart_v4))]
+    pub async fn wait_for_wakeup(&mut self) {
+        self.rx.wait_for_wakeup().await
+    }
+
     /// Split the Uart into a transmitter and receiver, which is
     /// particularly useful when having two tasks correlating to
     /// transmitting and receiving.
@@ -1153,6 +1233,9 @@ fn configure(
     #[cfg(not(usart_v1))]
     r.cr3().modify(|w| {
         w.set_onebit(config.assume_noise_free);
+        // wakeup from STOP mode on start bit detection, only effective if UESM is set
+        #[cfg(any(usart_v3, usart_v4))]
+        w.set_wus(vals::Wus::START);
     });
 
     r.cr1().write(|w| {
@@ -1180,6 +1263,8 @@ fn configure(
         w.set_over8(vals::Over8::from_bits(over8 as _));
         #[cfg(usart_v4)]
         w.set_fifoen(true);
+        #[cfg(any(usart_v3, usart_v4))]
+        w.set_uesm(config.wakeup_from_stop);
     });
 
     Ok(())
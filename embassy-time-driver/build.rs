@@ -1 +1,31 @@
-fn main() {}
+use std::path::PathBuf;
+use std::{env, fs};
+
+fn main() {
+    // only rebuild if build.rs changed. Otherwise Cargo will rebuild if any
+    // other file changed.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=EMBASSY_TIME_DRIVER_TICK_HZ");
+
+    // Arbitrary tick rate, for drivers whose timer clock has no matching `tick-hz-*` feature.
+    let Ok(value) = env::var("EMBASSY_TIME_DRIVER_TICK_HZ") else {
+        return;
+    };
+
+    let tick_hz = match value.replace('_', "").parse::<u64>() {
+        Ok(tick_hz) if tick_hz != 0 => tick_hz,
+        _ => panic!("Invalid value for env var EMBASSY_TIME_DRIVER_TICK_HZ: {value}"),
+    };
+
+    if env::vars().any(|(var, _)| var.starts_with("CARGO_FEATURE_TICK_HZ_")) {
+        panic!("EMBASSY_TIME_DRIVER_TICK_HZ is set, but a `tick-hz-*` feature is also enabled");
+    }
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(
+        out_dir.join("tick_hz.rs"),
+        format!("pub const TICK_HZ: u64 = {tick_hz};\n"),
+    )
+    .unwrap();
+    println!("cargo:rustc-cfg=tick_hz_env");
+}
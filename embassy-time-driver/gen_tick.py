@@ -22,6 +22,10 @@ for i in range(1, 30):
     ticks.append(10 * i * 1_000_000)
 for i in range(15, 50):
     ticks.append(20 * i * 1_000_000)
+for i in range(1, 101):
+    ticks.append(i * 1_000_000)
+# Low-speed internal oscillators.
+ticks.extend([32_000, 37_000, 40_000])
 
 seen = set()
 ticks = sorted([x for x in ticks if not (x in seen or seen.add(x))])
@@ -72,7 +76,9 @@ with open('src/tick.rs', 'w') as f:
     for hz in ticks:
         f.write(
             f'#[cfg(feature = "tick-hz-{hz:_}")] pub const TICK_HZ: u64 = {hz:_};\n')
+    f.write('#[cfg(tick_hz_env)] include!(concat!(env!("OUT_DIR"), "/tick_hz.rs"));\n')
     f.write('#[cfg(not(any(\n')
+    f.write('tick_hz_env,\n')
     for hz in ticks:
         f.write(f'feature = "tick-hz-{hz:_}",\n')
     f.write(')))] pub const TICK_HZ: u64 = 1_000_000;')
@@ -17,6 +17,10 @@
 //! Otherwise, don’t enable any `tick-hz-*` feature to let the user configure the tick rate themselves by
 //! enabling a feature on `embassy-time`.
 //!
+//! If your driver has a set tick rate that no `tick-hz-*` feature matches, document that users must set
+//! the `EMBASSY_TIME_DRIVER_TICK_HZ` environment variable to it, and check [`TICK_HZ`] in your driver, for
+//! example in a `const` assertion.
+//!
 //! # Linkage details
 //!
 //! Instead of the usual "trait + generic params" approach, calls from embassy to the driver are done via `extern` functions.
@@ -67,7 +71,8 @@ mod tick;
 
 /// Ticks per second of the global timebase.
 ///
-/// This value is specified by the [`tick-*` Cargo features](crate#tick-rate)
+/// This value is specified by the [`tick-*` Cargo features](crate#tick-rate), or the
+/// `EMBASSY_TIME_DRIVER_TICK_HZ` environment variable.
 pub const TICK_HZ: u64 = tick::TICK_HZ;
 
 /// Alarm handle, assigned by the driver.
@@ -109,6 +114,11 @@ pub trait Driver: Send + Sync + 'static {
     /// Try allocating an alarm handle. Returns None if no alarms left.
     /// Initially the alarm has no callback set, and a null `ctx` pointer.
     ///
+    /// Alarms are independent of each other, so that each timer queue can have its own. On multi-core
+    /// chips, an executor running on each core allocates its own alarm for its integrated timer queue:
+    /// drivers should then call the alarm callback on the core that allocated the alarm, so that
+    /// the timers of each core are handled locally, without contention between cores.
+    ///
     /// # Safety
     /// It is UB to make the alarm fire before setting a callback.
     unsafe fn allocate_alarm(&self) -> Option<AlarmHandle>;
@@ -52,6 +52,8 @@ pub const TICK_HZ: u64 = 20_000;
 pub const TICK_HZ: u64 = 32_000;
 #[cfg(feature = "tick-hz-32_768")]
 pub const TICK_HZ: u64 = 32_768;
+#[cfg(feature = "tick-hz-37_000")]
+pub const TICK_HZ: u64 = 37_000;
 #[cfg(feature = "tick-hz-40_000")]
 pub const TICK_HZ: u64 = 40_000;
 #[cfg(feature = "tick-hz-64_000")]
@@ -104,10 +106,14 @@ pub const TICK_HZ: u64 = 4_000_000;
 pub const TICK_HZ: u64 = 4_096_000;
 #[cfg(feature = "tick-hz-4_194_304")]
 pub const TICK_HZ: u64 = 4_194_304;
+#[cfg(feature = "tick-hz-5_000_000")]
+pub const TICK_HZ: u64 = 5_000_000;
 #[cfg(feature = "tick-hz-5_120_000")]
 pub const TICK_HZ: u64 = 5_120_000;
 #[cfg(feature = "tick-hz-6_000_000")]
 pub const TICK_HZ: u64 = 6_000_000;
+#[cfg(feature = "tick-hz-7_000_000")]
+pub const TICK_HZ: u64 = 7_000_000;
 #[cfg(feature = "tick-hz-8_000_000")]
 pub const TICK_HZ: u64 = 8_000_000;
 #[cfg(feature = "tick-hz-8_192_000")]
@@ -120,56 +126,198 @@ pub const TICK_HZ: u64 = 9_000_000;
 pub const TICK_HZ: u64 = 10_000_000;
 #[cfg(feature = "tick-hz-10_240_000")]
 pub const TICK_HZ: u64 = 10_240_000;
+#[cfg(feature = "tick-hz-11_000_000")]
+pub const TICK_HZ: u64 = 11_000_000;
 #[cfg(feature = "tick-hz-12_000_000")]
 pub const TICK_HZ: u64 = 12_000_000;
+#[cfg(feature = "tick-hz-13_000_000")]
+pub const TICK_HZ: u64 = 13_000_000;
+#[cfg(feature = "tick-hz-14_000_000")]
+pub const TICK_HZ: u64 = 14_000_000;
+#[cfg(feature = "tick-hz-15_000_000")]
+pub const TICK_HZ: u64 = 15_000_000;
 #[cfg(feature = "tick-hz-16_000_000")]
 pub const TICK_HZ: u64 = 16_000_000;
 #[cfg(feature = "tick-hz-16_384_000")]
 pub const TICK_HZ: u64 = 16_384_000;
 #[cfg(feature = "tick-hz-16_777_216")]
 pub const TICK_HZ: u64 = 16_777_216;
+#[cfg(feature = "tick-hz-17_000_000")]
+pub const TICK_HZ: u64 = 17_000_000;
 #[cfg(feature = "tick-hz-18_000_000")]
 pub const TICK_HZ: u64 = 18_000_000;
+#[cfg(feature = "tick-hz-19_000_000")]
+pub const TICK_HZ: u64 = 19_000_000;
 #[cfg(feature = "tick-hz-20_000_000")]
 pub const TICK_HZ: u64 = 20_000_000;
 #[cfg(feature = "tick-hz-20_480_000")]
 pub const TICK_HZ: u64 = 20_480_000;
+#[cfg(feature = "tick-hz-21_000_000")]
+pub const TICK_HZ: u64 = 21_000_000;
+#[cfg(feature = "tick-hz-22_000_000")]
+pub const TICK_HZ: u64 = 22_000_000;
+#[cfg(feature = "tick-hz-23_000_000")]
+pub const TICK_HZ: u64 = 23_000_000;
 #[cfg(feature = "tick-hz-24_000_000")]
 pub const TICK_HZ: u64 = 24_000_000;
+#[cfg(feature = "tick-hz-25_000_000")]
+pub const TICK_HZ: u64 = 25_000_000;
+#[cfg(feature = "tick-hz-26_000_000")]
+pub const TICK_HZ: u64 = 26_000_000;
+#[cfg(feature = "tick-hz-27_000_000")]
+pub const TICK_HZ: u64 = 27_000_000;
+#[cfg(feature = "tick-hz-28_000_000")]
+pub const TICK_HZ: u64 = 28_000_000;
+#[cfg(feature = "tick-hz-29_000_000")]
+pub const TICK_HZ: u64 = 29_000_000;
 #[cfg(feature = "tick-hz-30_000_000")]
 pub const TICK_HZ: u64 = 30_000_000;
+#[cfg(feature = "tick-hz-31_000_000")]
+pub const TICK_HZ: u64 = 31_000_000;
 #[cfg(feature = "tick-hz-32_000_000")]
 pub const TICK_HZ: u64 = 32_000_000;
 #[cfg(feature = "tick-hz-32_768_000")]
 pub const TICK_HZ: u64 = 32_768_000;
+#[cfg(feature = "tick-hz-33_000_000")]
+pub const TICK_HZ: u64 = 33_000_000;
+#[cfg(feature = "tick-hz-34_000_000")]
+pub const TICK_HZ: u64 = 34_000_000;
+#[cfg(feature = "tick-hz-35_000_000")]
+pub const TICK_HZ: u64 = 35_000_000;
 #[cfg(feature = "tick-hz-36_000_000")]
 pub const TICK_HZ: u64 = 36_000_000;
+#[cfg(feature = "tick-hz-37_000_000")]
+pub const TICK_HZ: u64 = 37_000_000;
+#[cfg(feature = "tick-hz-38_000_000")]
+pub const TICK_HZ: u64 = 38_000_000;
+#[cfg(feature = "tick-hz-39_000_000")]
+pub const TICK_HZ: u64 = 39_000_000;
 #[cfg(feature = "tick-hz-40_000_000")]
 pub const TICK_HZ: u64 = 40_000_000;
 #[cfg(feature = "tick-hz-40_960_000")]
 pub const TICK_HZ: u64 = 40_960_000;
+#[cfg(feature = "tick-hz-41_000_000")]
+pub const TICK_HZ: u64 = 41_000_000;
+#[cfg(feature = "tick-hz-42_000_000")]
+pub const TICK_HZ: u64 = 42_000_000;
+#[cfg(feature = "tick-hz-43_000_000")]
+pub const TICK_HZ: u64 = 43_000_000;
+#[cfg(feature = "tick-hz-44_000_000")]
+pub const TICK_HZ: u64 = 44_000_000;
+#[cfg(feature = "tick-hz-45_000_000")]
+pub const TICK_HZ: u64 = 45_000_000;
+#[cfg(feature = "tick-hz-46_000_000")]
+pub const TICK_HZ: u64 = 46_000_000;
+#[cfg(feature = "tick-hz-47_000_000")]
+pub const TICK_HZ: u64 = 47_000_000;
 #[cfg(feature = "tick-hz-48_000_000")]
 pub const TICK_HZ: u64 = 48_000_000;
+#[cfg(feature = "tick-hz-49_000_000")]
+pub const TICK_HZ: u64 = 49_000_000;
 #[cfg(feature = "tick-hz-50_000_000")]
 pub const TICK_HZ: u64 = 50_000_000;
+#[cfg(feature = "tick-hz-51_000_000")]
+pub const TICK_HZ: u64 = 51_000_000;
+#[cfg(feature = "tick-hz-52_000_000")]
+pub const TICK_HZ: u64 = 52_000_000;
+#[cfg(feature = "tick-hz-53_000_000")]
+pub const TICK_HZ: u64 = 53_000_000;
+#[cfg(feature = "tick-hz-54_000_000")]
+pub const TICK_HZ: u64 = 54_000_000;
+#[cfg(feature = "tick-hz-55_000_000")]
+pub const TICK_HZ: u64 = 55_000_000;
+#[cfg(feature = "tick-hz-56_000_000")]
+pub const TICK_HZ: u64 = 56_000_000;
+#[cfg(feature = "tick-hz-57_000_000")]
+pub const TICK_HZ: u64 = 57_000_000;
+#[cfg(feature = "tick-hz-58_000_000")]
+pub const TICK_HZ: u64 = 58_000_000;
+#[cfg(feature = "tick-hz-59_000_000")]
+pub const TICK_HZ: u64 = 59_000_000;
 #[cfg(feature = "tick-hz-60_000_000")]
 pub const TICK_HZ: u64 = 60_000_000;
+#[cfg(feature = "tick-hz-61_000_000")]
+pub const TICK_HZ: u64 = 61_000_000;
+#[cfg(feature = "tick-hz-62_000_000")]
+pub const TICK_HZ: u64 = 62_000_000;
+#[cfg(feature = "tick-hz-63_000_000")]
+pub const TICK_HZ: u64 = 63_000_000;
 #[cfg(feature = "tick-hz-64_000_000")]
 pub const TICK_HZ: u64 = 64_000_000;
+#[cfg(feature = "tick-hz-65_000_000")]
+pub const TICK_HZ: u64 = 65_000_000;
 #[cfg(feature = "tick-hz-65_536_000")]
 pub const TICK_HZ: u64 = 65_536_000;
+#[cfg(feature = "tick-hz-66_000_000")]
+pub const TICK_HZ: u64 = 66_000_000;
+#[cfg(feature = "tick-hz-67_000_000")]
+pub const TICK_HZ: u64 = 67_000_000;
+#[cfg(feature = "tick-hz-68_000_000")]
+pub const TICK_HZ: u64 = 68_000_000;
+#[cfg(feature = "tick-hz-69_000_000")]
+pub const TICK_HZ: u64 = 69_000_000;
 #[cfg(feature = "tick-hz-70_000_000")]
 pub const TICK_HZ: u64 = 70_000_000;
+#[cfg(feature = "tick-hz-71_000_000")]
+pub const TICK_HZ: u64 = 71_000_000;
 #[cfg(feature = "tick-hz-72_000_000")]
 pub const TICK_HZ: u64 = 72_000_000;
+#[cfg(feature = "tick-hz-73_000_000")]
+pub const TICK_HZ: u64 = 73_000_000;
+#[cfg(feature = "tick-hz-74_000_000")]
+pub const TICK_HZ: u64 = 74_000_000;
+#[cfg(feature = "tick-hz-75_000_000")]
+pub const TICK_HZ: u64 = 75_000_000;
+#[cfg(feature = "tick-hz-76_000_000")]
+pub const TICK_HZ: u64 = 76_000_000;
+#[cfg(feature = "tick-hz-77_000_000")]
+pub const TICK_HZ: u64 = 77_000_000;
+#[cfg(feature = "tick-hz-78_000_000")]
+pub const TICK_HZ: u64 = 78_000_000;
+#[cfg(feature = "tick-hz-79_000_000")]
+pub const TICK_HZ: u64 = 79_000_000;
 #[cfg(feature = "tick-hz-80_000_000")]
 pub const TICK_HZ: u64 = 80_000_000;
+#[cfg(feature = "tick-hz-81_000_000")]
+pub const TICK_HZ: u64 = 81_000_000;
 #[cfg(feature = "tick-hz-81_920_000")]
 pub const TICK_HZ: u64 = 81_920_000;
+#[cfg(feature = "tick-hz-82_000_000")]
+pub const TICK_HZ: u64 = 82_000_000;
+#[cfg(feature = "tick-hz-83_000_000")]
+pub const TICK_HZ: u64 = 83_000_000;
+#[cfg(feature = "tick-hz-84_000_000")]
+pub const TICK_HZ: u64 = 84_000_000;
+#[cfg(feature = "tick-hz-85_000_000")]
+pub const TICK_HZ: u64 = 85_000_000;
+#[cfg(feature = "tick-hz-86_000_000")]
+pub const TICK_HZ: u64 = 86_000_000;
+#[cfg(feature = "tick-hz-87_000_000")]
+pub const TICK_HZ: u64 = 87_000_000;
+#[cfg(feature = "tick-hz-88_000_000")]
+pub const TICK_HZ: u64 = 88_000_000;
+#[cfg(feature = "tick-hz-89_000_000")]
+pub const TICK_HZ: u64 = 89_000_000;
 #[cfg(feature = "tick-hz-90_000_000")]
 pub const TICK_HZ: u64 = 90_000_000;
+#[cfg(feature = "tick-hz-91_000_000")]
+pub const TICK_HZ: u64 = 91_000_000;
+#[cfg(feature = "tick-hz-92_000_000")]
+pub const TICK_HZ: u64 = 92_000_000;
+#[cfg(feature = "tick-hz-93_000_000")]
+pub const TICK_HZ: u64 = 93_000_000;
+#[cfg(feature = "tick-hz-94_000_000")]
+pub const TICK_HZ: u64 = 94_000_000;
+#[cfg(feature = "tick-hz-95_000_000")]
+pub const TICK_HZ: u64 = 95_000_000;
 #[cfg(feature = "tick-hz-96_000_000")]
 pub const TICK_HZ: u64 = 96_000_000;
+#[cfg(feature = "tick-hz-97_000_000")]
+pub const TICK_HZ: u64 = 97_000_000;
+#[cfg(feature = "tick-hz-98_000_000")]
+pub const TICK_HZ: u64 = 98_000_000;
+#[cfg(feature = "tick-hz-99_000_000")]
+pub const TICK_HZ: u64 = 99_000_000;
 #[cfg(feature = "tick-hz-100_000_000")]
 pub const TICK_HZ: u64 = 100_000_000;
 #[cfg(feature = "tick-hz-110_000_000")]
@@ -318,7 +466,10 @@ pub const TICK_HZ: u64 = 1_310_720_000;
 pub const TICK_HZ: u64 = 2_621_440_000;
 #[cfg(feature = "tick-hz-5_242_880_000")]
 pub const TICK_HZ: u64 = 5_242_880_000;
+#[cfg(tick_hz_env)]
+include!(concat!(env!("OUT_DIR"), "/tick_hz.rs"));
 #[cfg(not(any(
+    tick_hz_env,
     feature = "tick-hz-1",
     feature = "tick-hz-2",
     feature = "tick-hz-4",
@@ -345,6 +496,7 @@ pub const TICK_HZ: u64 = 5_242_880_000;
     feature = "tick-hz-20_000",
     feature = "tick-hz-32_000",
     feature = "tick-hz-32_768",
+    feature = "tick-hz-37_000",
     feature = "tick-hz-40_000",
     feature = "tick-hz-64_000",
     feature = "tick-hz-65_536",
@@ -371,39 +523,112 @@ pub const TICK_HZ: u64 = 5_242_880_000;
     feature = "tick-hz-4_000_000",
     feature = "tick-hz-4_096_000",
     feature = "tick-hz-4_194_304",
+    feature = "tick-hz-5_000_000",
     feature = "tick-hz-5_120_000",
     feature = "tick-hz-6_000_000",
+    feature = "tick-hz-7_000_000",
     feature = "tick-hz-8_000_000",
     feature = "tick-hz-8_192_000",
     feature = "tick-hz-8_388_608",
     feature = "tick-hz-9_000_000",
     feature = "tick-hz-10_000_000",
     feature = "tick-hz-10_240_000",
+    feature = "tick-hz-11_000_000",
     feature = "tick-hz-12_000_000",
+    feature = "tick-hz-13_000_000",
+    feature = "tick-hz-14_000_000",
+    feature = "tick-hz-15_000_000",
     feature = "tick-hz-16_000_000",
     feature = "tick-hz-16_384_000",
     feature = "tick-hz-16_777_216",
+    feature = "tick-hz-17_000_000",
     feature = "tick-hz-18_000_000",
+    feature = "tick-hz-19_000_000",
     feature = "tick-hz-20_000_000",
     feature = "tick-hz-20_480_000",
+    feature = "tick-hz-21_000_000",
+    feature = "tick-hz-22_000_000",
+    feature = "tick-hz-23_000_000",
     feature = "tick-hz-24_000_000",
+    feature = "tick-hz-25_000_000",
+    feature = "tick-hz-26_000_000",
+    feature = "tick-hz-27_000_000",
+    feature = "tick-hz-28_000_000",
+    feature = "tick-hz-29_000_000",
     feature = "tick-hz-30_000_000",
+    feature = "tick-hz-31_000_000",
     feature = "tick-hz-32_000_000",
     feature = "tick-hz-32_768_000",
+    feature = "tick-hz-33_000_000",
+    feature = "tick-hz-34_000_000",
+    feature = "tick-hz-35_000_000",
     feature = "tick-hz-36_000_000",
+    feature = "tick-hz-37_000_000",
+    feature = "tick-hz-38_000_000",
+    feature = "tick-hz-39_000_000",
     feature = "tick-hz-40_000_000",
     feature = "tick-hz-40_960_000",
+    feature = "tick-hz-41_000_000",
+    feature = "tick-hz-42_000_000",
+    feature = "tick-hz-43_000_000",
+    feature = "tick-hz-44_000_000",
+    feature = "tick-hz-45_000_000",
+    feature = "tick-hz-46_000_000",
+    feature = "tick-hz-47_000_000",
     feature = "tick-hz-48_000_000",
+    feature = "tick-hz-49_000_000",
     feature = "tick-hz-50_000_000",
+    feature = "tick-hz-51_000_000",
+    feature = "tick-hz-52_000_000",
+    feature = "tick-hz-53_000_000",
+    feature = "tick-hz-54_000_000",
+    feature = "tick-hz-55_000_000",
+    feature = "tick-hz-56_000_000",
+    feature = "tick-hz-57_000_000",
+    feature = "tick-hz-58_000_000",
+    feature = "tick-hz-59_000_000",
     feature = "tick-hz-60_000_000",
+    feature = "tick-hz-61_000_000",
+    feature = "tick-hz-62_000_000",
+    feature = "tick-hz-63_000_000",
     feature = "tick-hz-64_000_000",
+    feature = "tick-hz-65_000_000",
     feature = "tick-hz-65_536_000",
+    feature = "tick-hz-66_000_000",
+    feature = "tick-hz-67_000_000",
+    feature = "tick-hz-68_000_000",
+    feature = "tick-hz-69_000_000",
     feature = "tick-hz-70_000_000",
+    feature = "tick-hz-71_000_000",
     feature = "tick-hz-72_000_000",
+    feature = "tick-hz-73_000_000",
+    feature = "tick-hz-74_000_000",
+    feature = "tick-hz-75_000_000",
+    feature = "tick-hz-76_000_000",
+    feature = "tick-hz-77_000_000",
+    feature = "tick-hz-78_000_000",
+    feature = "tick-hz-79_000_000",
     feature = "tick-hz-80_000_000",
+    feature = "tick-hz-81_000_000",
     feature = "tick-hz-81_920_000",
+    feature = "tick-hz-82_000_000",
+    feature = "tick-hz-83_000_000",
+    feature = "tick-hz-84_000_000",
+    feature = "tick-hz-85_000_000",
+    feature = "tick-hz-86_000_000",
+    feature = "tick-hz-87_000_000",
+    feature = "tick-hz-88_000_000",
+    feature = "tick-hz-89_000_000",
     feature = "tick-hz-90_000_000",
+    feature = "tick-hz-91_000_000",
+    feature = "tick-hz-92_000_000",
+    feature = "tick-hz-93_000_000",
+    feature = "tick-hz-94_000_000",
+    feature = "tick-hz-95_000_000",
     feature = "tick-hz-96_000_000",
+    feature = "tick-hz-97_000_000",
+    feature = "tick-hz-98_000_000",
+    feature = "tick-hz-99_000_000",
     feature = "tick-hz-100_000_000",
     feature = "tick-hz-110_000_000",
     feature = "tick-hz-120_000_000",
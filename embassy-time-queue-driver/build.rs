@@ -1 +1,24 @@
-fn main() {}
+use std::path::PathBuf;
+use std::{env, fs};
+
+fn main() {
+    // only rebuild if build.rs changed. Otherwise Cargo will rebuild if any
+    // other file changed.
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=EMBASSY_TIME_QUEUE_DRIVER_COALESCE_WINDOW_US");
+
+    let coalesce_window_us = match env::var("EMBASSY_TIME_QUEUE_DRIVER_COALESCE_WINDOW_US") {
+        Ok(value) => match value.replace('_', "").parse::<u64>() {
+            Ok(value) => value,
+            Err(_) => panic!("Invalid value for env var EMBASSY_TIME_QUEUE_DRIVER_COALESCE_WINDOW_US: {value}"),
+        },
+        Err(_) => 0,
+    };
+
+    let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+    fs::write(
+        out_dir.join("config.rs"),
+        format!("pub const COALESCE_WINDOW_US: u64 = {coalesce_window_us};\n"),
+    )
+    .unwrap();
+}
@@ -26,8 +26,28 @@
 //!
 //! embassy_time_queue_driver::timer_queue_impl!(static QUEUE: MyTimerQueue = MyTimerQueue{});
 //! ```
+//!
+//! ## Alarm coalescing
+//!
+//! Each expiring timer wakes the device up from sleep. On battery-powered devices with many
+//! loosely-timed tasks, timers expiring close to each other can share a single wakeup instead, at the
+//! cost of firing them a bit late. Set the `EMBASSY_TIME_QUEUE_DRIVER_COALESCE_WINDOW_US`
+//! environment variable, for example in the `[env]` section of your `.cargo/config.toml`, to a
+//! window in microseconds: the timer queue then sets its alarm to the latest timer expiring within
+//! the window after the earliest one, so a timer fires at most this late.
+//!
+//! Timer queue implementations should honor [`COALESCE_WINDOW_US`]. It defaults to zero, which
+//! disables coalescing.
 use core::task::Waker;
 
+mod config {
+    include!(concat!(env!("OUT_DIR"), "/config.rs"));
+}
+
+/// Window in microseconds within which expiring timers share a single alarm, see
+/// [alarm coalescing](crate#alarm-coalescing).
+pub const COALESCE_WINDOW_US: u64 = config::COALESCE_WINDOW_US;
+
 /// Timer queue
 pub trait TimerQueue {
     /// Schedules a waker in the queue to be awoken at moment `at`.
@@ -0,0 +1,254 @@
+//! 32-bit tick time types.
+//!
+//! [`Instant`] and [`Duration`] count ticks in a `u64`, so that they never overflow. On cores without
+//! 64-bit arithmetic such as Cortex-M0, every comparison and addition on them takes several
+//! instructions, and every stored timestamp takes 8 bytes. [`Instant32`] and [`Duration32`] keep the
+//! low 32 bits of the tick count instead, for timestamps that are stored in large numbers or handled
+//! in hot paths.
+//!
+//! An `Instant32` wraps around every 2<sup>32</sup> ticks (about 71 minutes at 1MHz, or 36 hours at
+//! 32.768kHz). Comparisons and subtractions are wrap-aware, so they are correct as long as the
+//! instants involved are less than 2<sup>31</sup> ticks apart. Convert back to an [`Instant`] with
+//! [`Instant32::to_instant()`] to use it with a [`Timer`](crate::Timer).
+
+use core::cmp::Ordering;
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+
+use crate::{Duration, Instant};
+
+/// An [`Instant`] truncated to 32 bits, see the [module documentation](self).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Instant32 {
+    ticks: u32,
+}
+
+impl Instant32 {
+    /// Returns an Instant32 representing the current time.
+    pub fn now() -> Self {
+        Self {
+            ticks: embassy_time_driver::now() as u32,
+        }
+    }
+
+    /// Create an Instant32 from the low 32 bits of a tick count since system boot.
+    pub const fn from_ticks(ticks: u32) -> Self {
+        Self { ticks }
+    }
+
+    /// Low 32 bits of the tick count since system boot.
+    pub const fn as_ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// Duration between this Instant32 and an earlier one.
+    ///
+    /// If `earlier` is in fact later, the result is meaningless.
+    pub const fn duration_since(&self, earlier: Instant32) -> Duration32 {
+        Duration32 {
+            ticks: self.ticks.wrapping_sub(earlier.ticks),
+        }
+    }
+
+    /// Duration elapsed since this Instant32.
+    pub fn elapsed(&self) -> Duration32 {
+        Self::now().duration_since(*self)
+    }
+
+    /// The full [`Instant`], assuming that it is less than 2<sup>31</sup> ticks away from now.
+    pub fn to_instant(&self) -> Instant {
+        let now = embassy_time_driver::now();
+        let offset = self.ticks.wrapping_sub(now as u32) as i32;
+        Instant::from_ticks(now.wrapping_add_signed(offset as i64))
+    }
+}
+
+impl From<Instant> for Instant32 {
+    fn from(instant: Instant) -> Self {
+        Self {
+            ticks: instant.as_ticks() as u32,
+        }
+    }
+}
+
+impl PartialOrd for Instant32 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Instant32 {
+    /// Wrap-aware comparison: `self` is later than `other` if it is less than 2<sup>31</sup> ticks
+    /// after it.
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.ticks.wrapping_sub(other.ticks) as i32).cmp(&0)
+    }
+}
+
+impl Add<Duration32> for Instant32 {
+    type Output = Instant32;
+
+    fn add(self, other: Duration32) -> Instant32 {
+        Instant32 {
+            ticks: self.ticks.wrapping_add(other.ticks),
+        }
+    }
+}
+
+impl AddAssign<Duration32> for Instant32 {
+    fn add_assign(&mut self, other: Duration32) {
+        *self = *self + other;
+    }
+}
+
+impl Sub<Duration32> for Instant32 {
+    type Output = Instant32;
+
+    fn sub(self, other: Duration32) -> Instant32 {
+        Instant32 {
+            ticks: self.ticks.wrapping_sub(other.ticks),
+        }
+    }
+}
+
+impl SubAssign<Duration32> for Instant32 {
+    fn sub_assign(&mut self, other: Duration32) {
+        *self = *self - other;
+    }
+}
+
+impl Sub<Instant32> for Instant32 {
+    type Output = Duration32;
+
+    fn sub(self, other: Instant32) -> Duration32 {
+        self.duration_since(other)
+    }
+}
+
+/// A [`Duration`] of at most `u32::MAX` ticks, see the [module documentation](self).
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Duration32 {
+    ticks: u32,
+}
+
+impl Duration32 {
+    /// The smallest value that can be represented by the `Duration32` type.
+    pub const MIN: Duration32 = Duration32 { ticks: u32::MIN };
+    /// The largest value that can be represented by the `Duration32` type.
+    pub const MAX: Duration32 = Duration32 { ticks: u32::MAX };
+
+    /// Creates a duration from the specified number of clock ticks.
+    pub const fn from_ticks(ticks: u32) -> Self {
+        Self { ticks }
+    }
+
+    /// Tick count of the `Duration32`.
+    pub const fn as_ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    /// Converts a [`Duration`], returning `None` if it is longer than `u32::MAX` ticks.
+    ///
+    /// Being `const`, it can convert durations at compile time, so that no 64-bit arithmetic is
+    /// left at runtime.
+    pub const fn from_duration(duration: Duration) -> Option<Self> {
+        let ticks = duration.as_ticks();
+        if ticks > u32::MAX as u64 {
+            None
+        } else {
+            Some(Self { ticks: ticks as u32 })
+        }
+    }
+
+    /// Adds one Duration32 to another, returning a new Duration32 or None in the event of an overflow.
+    pub fn checked_add(self, rhs: Duration32) -> Option<Duration32> {
+        self.ticks.checked_add(rhs.ticks).map(|ticks| Duration32 { ticks })
+    }
+
+    /// Subtracts one Duration32 to another, returning a new Duration32 or None in the event of an overflow.
+    pub fn checked_sub(self, rhs: Duration32) -> Option<Duration32> {
+        self.ticks.checked_sub(rhs.ticks).map(|ticks| Duration32 { ticks })
+    }
+}
+
+impl TryFrom<Duration> for Duration32 {
+    type Error = Duration;
+
+    /// Converts a [`Duration`], returning it back if it is longer than `u32::MAX` ticks.
+    fn try_from(duration: Duration) -> Result<Self, Duration> {
+        Self::from_duration(duration).ok_or(duration)
+    }
+}
+
+impl From<Duration32> for Duration {
+    fn from(duration: Duration32) -> Self {
+        Duration::from_ticks(duration.ticks as u64)
+    }
+}
+
+impl Add for Duration32 {
+    type Output = Duration32;
+
+    fn add(self, rhs: Duration32) -> Duration32 {
+        self.checked_add(rhs).expect("overflow when adding durations")
+    }
+}
+
+impl Sub for Duration32 {
+    type Output = Duration32;
+
+    fn sub(self, rhs: Duration32) -> Duration32 {
+        self.checked_sub(rhs).expect("overflow when subtracting durations")
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "mock-driver")]
+mod tests {
+    use serial_test::serial;
+
+    use super::*;
+    use crate::MockDriver;
+
+    #[test]
+    fn test_wrapping_order() {
+        let before = Instant32::from_ticks(u32::MAX - 10);
+        let after = before + Duration32::from_ticks(20);
+
+        assert_eq!(after.as_ticks(), 9);
+        assert!(before < after);
+        assert_eq!(after - before, Duration32::from_ticks(20));
+    }
+
+    #[test]
+    fn test_from_duration() {
+        assert_eq!(
+            Duration32::from_duration(Duration::from_ticks(42)),
+            Some(Duration32::from_ticks(42))
+        );
+        assert_eq!(Duration32::from_duration(Duration::from_ticks(1 << 32)), None);
+        assert_eq!(Duration::from(Duration32::MAX), Duration::from_ticks(u32::MAX as u64));
+    }
+
+    #[test]
+    #[serial]
+    fn test_to_instant() {
+        MockDriver::get().reset();
+
+        MockDriver::get().advance(Duration::from_ticks(3 << 32));
+        let now = Instant::now();
+        let now32 = Instant32::now();
+
+        assert_eq!(now32.to_instant(), now);
+        assert_eq!(
+            (now32 + Duration32::from_ticks(100)).to_instant(),
+            now + Duration::from_ticks(100)
+        );
+        assert_eq!(
+            (now32 - Duration32::from_ticks(100)).to_instant(),
+            now - Duration::from_ticks(100)
+        );
+        assert_eq!(Instant32::from(now), now32);
+    }
+}
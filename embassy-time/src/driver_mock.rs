@@ -26,6 +26,17 @@ use crate::{Duration, Instant};
 ///     assert_eq!(true, has_a_second_passed(reference));
 /// }
 /// ```
+///
+/// Together with the `generic-queue` feature, advancing the driver wakes the [`Timer`](crate::Timer)s
+/// that expired, so code using [`Timer::after`](crate::Timer::after) or
+/// [`with_timeout`](crate::with_timeout) can be tested without waiting in real time:
+///
+/// ```ignore
+/// let mut fut = pin!(with_timeout(Duration::from_secs(5), protocol.receive()));
+/// assert!(fut.as_mut().poll(&mut cx).is_pending());
+/// embassy_time::MockDriver::get().advance(Duration::from_secs(5));
+/// assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(Err(TimeoutError)));
+/// ```
 pub struct MockDriver(CsMutex<RefCell<InnerMockDriver>>);
 
 embassy_time_driver::time_driver_impl!(static DRIVER: MockDriver = MockDriver::new());
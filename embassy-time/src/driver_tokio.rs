@@ -0,0 +1,148 @@
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration as StdDuration;
+
+use embassy_time_driver::{AlarmHandle, Driver};
+use tokio::time::Instant as TokioInstant;
+
+#[cfg(feature = "std")]
+compile_error!("You may not enable both `std` and `tokio` features.");
+
+const ALARM_COUNT: usize = 4;
+
+type AlarmCallback = (fn(*mut ()), *mut ());
+
+struct AlarmState {
+    timestamp: u64,
+    callback: Option<AlarmCallback>,
+}
+
+unsafe impl Send for AlarmState {}
+
+const ALARM_NEW: AlarmState = AlarmState {
+    timestamp: u64::MAX,
+    callback: None,
+};
+
+/// Time driver on the clock of the current tokio runtime.
+///
+/// Each armed alarm is a tokio task sleeping until the alarm timestamp, so a runtime with a paused
+/// clock auto-advances to the next alarm when it is idle.
+struct TimeDriver {
+    alarm_count: AtomicU8,
+    /// Tokio instant of tick 0, taken on the first call to `now()`.
+    start: OnceLock<TokioInstant>,
+    alarms: Mutex<[AlarmState; ALARM_COUNT]>,
+}
+
+embassy_time_driver::time_driver_impl!(static DRIVER: TimeDriver = TimeDriver {
+    alarm_count: AtomicU8::new(0),
+    start: OnceLock::new(),
+    alarms: Mutex::new([ALARM_NEW; ALARM_COUNT]),
+});
+
+impl TimeDriver {
+    fn start(&self) -> TokioInstant {
+        *self.start.get_or_init(TokioInstant::now)
+    }
+
+    fn check_alarm(&self, n: usize) {
+        let now = self.now();
+        let callback = {
+            let mut alarms = self.alarms.lock().unwrap();
+            let alarm = &mut alarms[n];
+            if alarm.timestamp > now {
+                // Superseded by a later `set_alarm`.
+                return;
+            }
+            alarm.timestamp = u64::MAX;
+            alarm.callback
+        };
+
+        // Call after releasing the lock, so the callback can set another alarm.
+        if let Some((f, ctx)) = callback {
+            f(ctx);
+        }
+    }
+}
+
+impl Driver for TimeDriver {
+    fn now(&self) -> u64 {
+        TokioInstant::now().saturating_duration_since(self.start()).as_micros() as u64
+    }
+
+    unsafe fn allocate_alarm(&self) -> Option<AlarmHandle> {
+        let id = self.alarm_count.fetch_update(Ordering::AcqRel, Ordering::Acquire, |x| {
+            if x < ALARM_COUNT as u8 {
+                Some(x + 1)
+            } else {
+                None
+            }
+        });
+
+        match id {
+            Ok(id) => Some(AlarmHandle::new(id)),
+            Err(_) => None,
+        }
+    }
+
+    fn set_alarm_callback(&self, alarm: AlarmHandle, callback: fn(*mut ()), ctx: *mut ()) {
+        let mut alarms = self.alarms.lock().unwrap();
+        alarms[alarm.id() as usize].callback = Some((callback, ctx));
+    }
+
+    fn set_alarm(&self, alarm: AlarmHandle, timestamp: u64) -> bool {
+        let n = alarm.id() as usize;
+        if timestamp <= self.now() {
+            self.alarms.lock().unwrap()[n].timestamp = u64::MAX;
+            return false;
+        }
+        self.alarms.lock().unwrap()[n].timestamp = timestamp;
+
+        // An alarm too far in the future to be represented never fires.
+        if let Some(deadline) = self.start().checked_add(StdDuration::from_micros(timestamp)) {
+            let handle = tokio::runtime::Handle::try_current()
+                .expect("the `tokio` time driver must be used from within a tokio runtime");
+            handle.spawn(async move {
+                tokio::time::sleep_until(deadline).await;
+                DRIVER.check_alarm(n);
+            });
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "generic-queue")]
+mod tests {
+    use core::future::pending;
+    use std::time::Duration as StdDuration;
+
+    use crate::{with_timeout, Duration, Instant, Timer};
+
+    #[test]
+    fn test_paused_clock() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+
+        runtime.block_on(async {
+            let real_start = std::time::Instant::now();
+            let start = Instant::now();
+
+            Timer::after(Duration::from_secs(3600)).await;
+            assert!(Instant::now() - start >= Duration::from_secs(3600));
+
+            tokio::time::advance(StdDuration::from_secs(60)).await;
+            assert!(Instant::now() - start >= Duration::from_secs(3660));
+
+            let result = with_timeout(Duration::from_secs(10), pending::<()>()).await;
+            assert!(result.is_err());
+
+            // The clock was advanced by tokio, without waiting in real time.
+            assert!(real_start.elapsed() < StdDuration::from_secs(60));
+        });
+    }
+}
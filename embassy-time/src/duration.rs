@@ -37,6 +37,11 @@ impl Duration {
         self.ticks * (1_000_000 / GCD_1M) / (TICK_HZ / GCD_1M)
     }
 
+    /// Convert the `Duration` to nanoseconds, rounding down.
+    pub const fn as_nanos(&self) -> u64 {
+        self.ticks * (1_000_000_000 / GCD_1G) / (TICK_HZ / GCD_1G)
+    }
+
     /// Creates a duration from the specified number of clock ticks
     pub const fn from_ticks(ticks: u64) -> Duration {
         Duration { ticks }
@@ -64,9 +69,9 @@ impl Duration {
 
     /// Creates a duration from the specified number of nanoseconds, rounding up.
     /// NOTE: Delays this small may be inaccurate.
-    pub const fn from_nanos(micros: u64) -> Duration {
+    pub const fn from_nanos(nanos: u64) -> Duration {
         Duration {
-            ticks: div_ceil(micros * (TICK_HZ / GCD_1G), 1_000_000_000 / GCD_1G),
+            ticks: div_ceil(nanos * (TICK_HZ / GCD_1G), 1_000_000_000 / GCD_1G),
         }
     }
 
@@ -90,6 +95,14 @@ impl Duration {
         }
     }
 
+    /// Creates a duration from the specified number of nanoseconds, rounding down.
+    /// NOTE: Delays this small may be inaccurate.
+    pub const fn from_nanos_floor(nanos: u64) -> Duration {
+        Duration {
+            ticks: nanos * (TICK_HZ / GCD_1G) / (1_000_000_000 / GCD_1G),
+        }
+    }
+
     /// Creates a duration corresponding to the specified Hz.
     /// NOTE: Giving this function a hz >= the TICK_HZ of your platform will clamp the Duration to 1
     /// tick. Doing so will not deadlock, but will certainly not produce the desired output.
@@ -217,3 +230,29 @@ impl From<Duration> for core::time::Duration {
         core::time::Duration::from_micros(value.as_micros())
     }
 }
+
+#[cfg(test)]
+#[cfg(feature = "mock-driver")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nanos_round_trip() {
+        assert_eq!(Duration::from_nanos(0).as_nanos(), 0);
+        assert_eq!(Duration::from_nanos(3_000).as_nanos(), 3_000);
+        assert_eq!(Duration::from_nanos_floor(3_000).as_nanos(), 3_000);
+        assert_eq!(Duration::from_micros(7).as_nanos(), 7_000);
+        assert_eq!(Duration::from_secs(2).as_nanos(), 2_000_000_000);
+    }
+
+    #[test]
+    fn test_nanos_rounding() {
+        // A tick is 1000ns with the mock driver.
+        assert_eq!(Duration::from_nanos(1), Duration::from_ticks(1));
+        assert_eq!(Duration::from_nanos(1_001), Duration::from_ticks(2));
+        assert_eq!(Duration::from_nanos_floor(1), Duration::from_ticks(0));
+        assert_eq!(Duration::from_nanos_floor(1_999), Duration::from_ticks(1));
+        assert_eq!(Duration::from_nanos(1_500).as_nanos(), 2_000);
+        assert_eq!(Duration::from_nanos_floor(1_500).as_nanos(), 1_000);
+    }
+}
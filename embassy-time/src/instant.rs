@@ -1,7 +1,7 @@
 use core::fmt;
 use core::ops::{Add, AddAssign, Sub, SubAssign};
 
-use super::{Duration, GCD_1K, GCD_1M, TICK_HZ};
+use super::{Duration, GCD_1G, GCD_1K, GCD_1M, TICK_HZ};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -28,6 +28,13 @@ impl Instant {
         Self { ticks }
     }
 
+    /// Create an Instant from a nanosecond count since system boot.
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self {
+            ticks: nanos * (TICK_HZ / GCD_1G) / (1_000_000_000 / GCD_1G),
+        }
+    }
+
     /// Create an Instant from a microsecond count since system boot.
     pub const fn from_micros(micros: u64) -> Self {
         Self {
@@ -69,6 +76,11 @@ impl Instant {
         self.ticks * (1_000_000 / GCD_1M) / (TICK_HZ / GCD_1M)
     }
 
+    /// Nanoseconds since system boot.
+    pub const fn as_nanos(&self) -> u64 {
+        self.ticks * (1_000_000_000 / GCD_1G) / (TICK_HZ / GCD_1G)
+    }
+
     /// Duration between this Instant and another Instant
     /// Panics on over/underflow.
     pub fn duration_since(&self, earlier: Instant) -> Duration {
@@ -159,3 +171,25 @@ impl<'a> fmt::Display for Instant {
         write!(f, "{} ticks", self.ticks)
     }
 }
+
+#[cfg(test)]
+#[cfg(feature = "mock-driver")]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nanos_round_trip() {
+        assert_eq!(Instant::from_nanos(0).as_nanos(), 0);
+        assert_eq!(Instant::from_nanos(5_000).as_nanos(), 5_000);
+        assert_eq!(Instant::from_micros(42).as_nanos(), 42_000);
+        assert_eq!(Instant::from_secs(3).as_nanos(), 3_000_000_000);
+    }
+
+    #[test]
+    fn test_nanos_rounding() {
+        // A tick is 1000ns with the mock driver, and instants round down.
+        assert_eq!(Instant::from_nanos(999), Instant::from_ticks(0));
+        assert_eq!(Instant::from_nanos(1_999), Instant::from_ticks(1));
+        assert_eq!(Instant::from_nanos(2_500).as_nanos(), 2_000);
+    }
+}
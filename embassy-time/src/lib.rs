@@ -1,4 +1,4 @@
-#![cfg_attr(not(any(feature = "std", feature = "wasm", test)), no_std)]
+#![cfg_attr(not(any(feature = "std", feature = "wasm", feature = "tokio", test)), no_std)]
 #![allow(async_fn_in_trait)]
 #![doc = include_str!("../README.md")]
 #![allow(clippy::new_without_default)]
@@ -10,10 +10,14 @@
 // This mod MUST go first, so that the others see its macros.
 pub(crate) mod fmt;
 
+pub mod compact;
 mod delay;
 mod duration;
 mod instant;
+mod rate_limiter;
+pub mod stopwatch;
 mod timer;
+pub mod wallclock;
 
 #[cfg(feature = "mock-driver")]
 mod driver_mock;
@@ -23,6 +27,8 @@ pub use driver_mock::MockDriver;
 
 #[cfg(feature = "std")]
 mod driver_std;
+#[cfg(feature = "tokio")]
+mod driver_tokio;
 #[cfg(feature = "wasm")]
 mod driver_wasm;
 #[cfg(feature = "generic-queue")]
@@ -32,7 +38,16 @@ pub use delay::{block_for, Delay};
 pub use duration::Duration;
 pub use embassy_time_driver::TICK_HZ;
 pub use instant::Instant;
-pub use timer::{with_deadline, with_timeout, Ticker, TimeoutError, Timer};
+pub use rate_limiter::RateLimiter;
+pub use timer::{with_deadline, with_timeout, Deadline, MissedTickBehavior, Ticker, TimeoutError, Timer};
+
+/// Ticks per second of the global timebase, the same as [`TICK_HZ`].
+///
+/// This is useful to libraries that want to check or log the tick rate at runtime, for instance to
+/// pick a resolution for their own timeouts.
+pub const fn tick_hz() -> u64 {
+    TICK_HZ
+}
 
 const fn gcd(a: u64, b: u64) -> u64 {
     if b == 0 {
@@ -7,7 +7,7 @@ use embassy_time_driver::{allocate_alarm, set_alarm, set_alarm_callback, AlarmHa
 use embassy_time_queue_driver::TimerQueue;
 use heapless::Vec;
 
-use crate::Instant;
+use crate::{Duration, Instant};
 
 #[cfg(feature = "generic-queue-8")]
 const QUEUE_SIZE: usize = 8;
@@ -28,6 +28,9 @@ const QUEUE_SIZE: usize = 128;
 )))]
 const QUEUE_SIZE: usize = 64;
 
+/// Window in ticks within which expiring timers share a single alarm.
+const COALESCE_WINDOW: u64 = Duration::from_micros(embassy_time_queue_driver::COALESCE_WINDOW_US).as_ticks();
+
 #[derive(Debug)]
 struct Timer {
     at: Instant,
@@ -108,12 +111,29 @@ impl InnerQueue {
                 }
             }
 
+            let next_alarm = self.coalesce(next_alarm, COALESCE_WINDOW);
+
             if self.update_alarm(next_alarm) {
                 break;
             }
         }
     }
 
+    /// Delay the alarm for the `first` timer to the latest timer expiring within `window` ticks of it,
+    /// so that they all fire on the same alarm.
+    fn coalesce(&self, first: Instant, window: u64) -> Instant {
+        if window == 0 || first == Instant::MAX {
+            return first;
+        }
+        let limit = Instant::from_ticks(first.as_ticks().saturating_add(window));
+        self.queue
+            .iter()
+            .map(|timer| timer.at)
+            .filter(|at| *at <= limit)
+            .max()
+            .unwrap_or(first)
+    }
+
     fn update_alarm(&mut self, next_alarm: Instant) -> bool {
         if next_alarm == Instant::MAX {
             true
@@ -174,18 +194,27 @@ impl TimerQueue for Queue {
 
 embassy_time_queue_driver::timer_queue_impl!(static QUEUE: Queue = Queue::new());
 
+/// Forget the queue and its alarm, to be called along with `MockDriver::reset`.
+#[cfg(test)]
+#[cfg(feature = "mock-driver")]
+pub(crate) fn reset() {
+    critical_section::with(|cs| *QUEUE.inner.borrow_ref_mut(cs) = None);
+}
+
 #[cfg(test)]
 #[cfg(feature = "mock-driver")]
 mod tests {
     use core::cell::Cell;
-    use core::task::{RawWaker, RawWakerVTable, Waker};
+    use core::future::{pending, Future};
+    use core::pin::pin;
+    use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
     use std::rc::Rc;
 
     use serial_test::serial;
 
     use crate::driver_mock::MockDriver;
     use crate::queue_generic::QUEUE;
-    use crate::{Duration, Instant};
+    use crate::{with_timeout, Deadline, Duration, Instant, MissedTickBehavior, Ticker, TimeoutError, Timer};
 
     struct TestWaker {
         pub awoken: Rc<Cell<bool>>,
@@ -228,7 +257,7 @@ mod tests {
 
     fn setup() {
         MockDriver::get().reset();
-        critical_section::with(|cs| *QUEUE.inner.borrow_ref_mut(cs) = None);
+        super::reset();
     }
 
     fn queue_len() -> usize {
@@ -359,4 +388,141 @@ mod tests {
         assert_eq!(queue_len(), super::QUEUE_SIZE);
         assert!(second_waker.awoken.get());
     }
+
+    #[test]
+    #[serial]
+    fn test_timer_after() {
+        setup();
+
+        let waker = TestWaker::new();
+        let mut cx = Context::from_waker(&waker.waker);
+        let mut timer = pin!(Timer::after(Duration::from_secs(10)));
+
+        assert_eq!(timer.as_mut().poll(&mut cx), Poll::Pending);
+
+        MockDriver::get().advance(Duration::from_secs(9));
+
+        assert!(!waker.awoken.get());
+
+        MockDriver::get().advance(Duration::from_secs(1));
+
+        assert!(waker.awoken.get());
+        assert_eq!(timer.as_mut().poll(&mut cx), Poll::Ready(()));
+    }
+
+    #[test]
+    #[serial]
+    fn test_with_timeout() {
+        setup();
+
+        let waker = TestWaker::new();
+        let mut cx = Context::from_waker(&waker.waker);
+        let mut fut = pin!(with_timeout(Duration::from_secs(5), pending::<()>()));
+
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Pending);
+
+        MockDriver::get().advance(Duration::from_secs(5));
+
+        assert!(waker.awoken.get());
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(Err(TimeoutError)));
+    }
+
+    fn ticker_ticks(ticker: &mut Ticker, cx: &mut Context<'_>) -> usize {
+        let mut ticks = 0;
+        while pin!(ticker.next()).poll(cx).is_ready() {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    #[serial]
+    fn test_ticker_burst() {
+        setup();
+
+        let waker = TestWaker::new();
+        let mut cx = Context::from_waker(&waker.waker);
+        let mut ticker = Ticker::every(Duration::from_secs(10));
+
+        MockDriver::get().advance(Duration::from_secs(10));
+        assert_eq!(ticker_ticks(&mut ticker, &mut cx), 1);
+
+        // Late by 1.5 periods: the missed ticks fire at once, and the schedule doesn't drift.
+        MockDriver::get().advance(Duration::from_secs(25));
+        assert_eq!(ticker_ticks(&mut ticker, &mut cx), 2);
+        assert_eq!(ticker.next_deadline(), Instant::from_secs(40));
+    }
+
+    #[test]
+    #[serial]
+    fn test_ticker_skip() {
+        setup();
+
+        let waker = TestWaker::new();
+        let mut cx = Context::from_waker(&waker.waker);
+        let mut ticker = Ticker::every(Duration::from_secs(10));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        MockDriver::get().advance(Duration::from_secs(10));
+        assert_eq!(ticker_ticks(&mut ticker, &mut cx), 1);
+
+        // Late by 1.5 periods: a single tick fires, and the next one stays on the original schedule.
+        MockDriver::get().advance(Duration::from_secs(25));
+        assert_eq!(ticker_ticks(&mut ticker, &mut cx), 1);
+        assert_eq!(ticker.next_deadline(), Instant::from_secs(40));
+
+        MockDriver::get().advance(Duration::from_secs(5));
+        assert!(waker.awoken.get());
+        assert_eq!(ticker_ticks(&mut ticker, &mut cx), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn test_deadline() {
+        setup();
+
+        let waker = TestWaker::new();
+        let mut cx = Context::from_waker(&waker.waker);
+        let deadline = Deadline::after(Duration::from_secs(5));
+
+        let mut first = pin!(deadline.run(Timer::after(Duration::from_secs(3))));
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Pending);
+        MockDriver::get().advance(Duration::from_secs(3));
+        assert_eq!(first.as_mut().poll(&mut cx), Poll::Ready(Ok(())));
+
+        // The second step only gets what is left of the budget.
+        assert_eq!(deadline.remaining(), Duration::from_secs(2));
+        let mut second = pin!(deadline.run(Timer::after(Duration::from_secs(3))));
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Pending);
+        MockDriver::get().advance(Duration::from_secs(2));
+        assert!(deadline.is_expired());
+        assert_eq!(second.as_mut().poll(&mut cx), Poll::Ready(Err(TimeoutError)));
+    }
+
+    #[test]
+    #[serial]
+    fn test_coalesce() {
+        setup();
+
+        let wakers = [TestWaker::new(), TestWaker::new(), TestWaker::new()];
+        QUEUE.schedule_wake(Instant::from_millis(10_000), &wakers[0].waker);
+        QUEUE.schedule_wake(Instant::from_millis(10_500), &wakers[1].waker);
+        QUEUE.schedule_wake(Instant::from_millis(20_000), &wakers[2].waker);
+
+        critical_section::with(|cs| {
+            let inner = QUEUE.inner.borrow_ref(cs);
+            let inner = inner.as_ref().unwrap();
+            let first = Instant::from_millis(10_000);
+
+            assert_eq!(inner.coalesce(first, 0), first);
+            assert_eq!(
+                inner.coalesce(first, Duration::from_secs(1).as_ticks()),
+                Instant::from_millis(10_500)
+            );
+            assert_eq!(
+                inner.coalesce(first, Duration::from_secs(10).as_ticks()),
+                Instant::from_millis(20_000)
+            );
+        });
+    }
 }
@@ -0,0 +1,218 @@
+use crate::{Duration, Instant, Timer};
+
+/// Limits operations to a number of operations per period, with a token bucket.
+///
+/// The bucket holds up to `count` tokens and starts full, so that a burst of `count` operations is
+/// allowed at once. It is refilled continuously, at a rate of `count` tokens per `period`.
+///
+/// ```ignore
+/// // At most 10 requests per second.
+/// let mut limiter = RateLimiter::new(10, Duration::from_secs(1));
+/// loop {
+///     limiter.acquire().await;
+///     client.send_request().await;
+/// }
+/// ```
+///
+/// A duty cycle limit, such as the 1% of LoRaWAN, is a rate limit on airtime: with one token per
+/// millisecond of airtime, 36 seconds of airtime are allowed per hour.
+///
+/// ```ignore
+/// let mut duty_cycle = RateLimiter::new(36_000, Duration::from_secs(3600));
+/// duty_cycle.acquire_n(airtime.as_millis() as u32).await;
+/// radio.transmit(&frame).await;
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RateLimiter {
+    count: u32,
+    period: u64,
+    // Tokens are counted in units of `1 / period`, refilled at `count` units per tick, so that the
+    // refill is exact.
+    units: u64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter allowing `count` operations per `period`, with a full bucket.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `count` or `period` is zero.
+    pub const fn new(count: u32, period: Duration) -> Self {
+        core::assert!(count > 0);
+        core::assert!(period.as_ticks() > 0);
+        Self {
+            count,
+            period: period.as_ticks(),
+            units: (count as u64).saturating_mul(period.as_ticks()),
+            refilled_at: Instant::MIN,
+        }
+    }
+
+    /// Number of tokens available right now.
+    pub fn available(&mut self) -> u32 {
+        self.refill();
+        (self.units / self.period) as u32
+    }
+
+    /// Take a token if one is available, returning whether it was.
+    pub fn try_acquire(&mut self) -> bool {
+        self.try_acquire_n(1)
+    }
+
+    /// Take `n` tokens if they are available, returning whether they were.
+    ///
+    /// Always fails if `n` is more than the `count` of the limiter.
+    pub fn try_acquire_n(&mut self, n: u32) -> bool {
+        if n > self.count {
+            return false;
+        }
+        self.refill();
+        let cost = self.cost(n);
+        if self.units >= cost {
+            self.units -= cost;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Wait for a token, and take it.
+    pub async fn acquire(&mut self) {
+        self.acquire_n(1).await
+    }
+
+    /// Wait for `n` tokens, and take them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is more than the `count` of the limiter, since the bucket can never hold that
+    /// many tokens.
+    pub async fn acquire_n(&mut self, n: u32) {
+        assert!(n <= self.count, "cannot acquire more tokens than the bucket holds");
+        while !self.try_acquire_n(n) {
+            Timer::after(self.wait_time(n)).await;
+        }
+    }
+
+    /// Time until `n` tokens are available, zero if they already are.
+    pub fn wait_time(&mut self, n: u32) -> Duration {
+        self.refill();
+        let missing = self.cost(n).saturating_sub(self.units);
+        Duration::from_ticks(missing.div_ceil(self.count as u64))
+    }
+
+    fn cost(&self, n: u32) -> u64 {
+        (n as u64).saturating_mul(self.period)
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.as_ticks().saturating_sub(self.refilled_at.as_ticks());
+        let capacity = self.cost(self.count);
+        self.units = self
+            .units
+            .saturating_add(elapsed.saturating_mul(self.count as u64))
+            .min(capacity);
+        self.refilled_at = now;
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "mock-driver")]
+mod tests {
+    use serial_test::serial;
+
+    use super::*;
+    use crate::MockDriver;
+
+    #[test]
+    #[serial]
+    fn test_burst_and_refill() {
+        MockDriver::get().reset();
+        MockDriver::get().advance(Duration::from_secs(1));
+
+        let mut limiter = RateLimiter::new(3, Duration::from_millis(300));
+        assert_eq!(limiter.available(), 3);
+        assert!(limiter.try_acquire_n(3));
+        assert!(!limiter.try_acquire());
+        assert_eq!(limiter.wait_time(1), Duration::from_millis(100));
+        assert_eq!(limiter.wait_time(2), Duration::from_millis(200));
+
+        MockDriver::get().advance(Duration::from_millis(99));
+        assert!(!limiter.try_acquire());
+        MockDriver::get().advance(Duration::from_millis(1));
+        assert!(limiter.try_acquire());
+        assert!(!limiter.try_acquire());
+
+        // The bucket doesn't fill over its capacity.
+        MockDriver::get().advance(Duration::from_secs(10));
+        assert_eq!(limiter.available(), 3);
+        assert_eq!(limiter.wait_time(3), Duration::from_ticks(0));
+    }
+
+    #[test]
+    #[serial]
+    fn test_exact_refill() {
+        MockDriver::get().reset();
+
+        // A token every 333.33 ticks: no rounding error accumulates.
+        let mut limiter = RateLimiter::new(3, Duration::from_ticks(1000));
+        assert!(limiter.try_acquire_n(3));
+        for _ in 0..10 {
+            MockDriver::get().advance(Duration::from_ticks(1000));
+            assert!(limiter.try_acquire_n(3));
+            assert!(!limiter.try_acquire());
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_acquire_more_than_count() {
+        MockDriver::get().reset();
+
+        let mut limiter = RateLimiter::new(u32::MAX, Duration::from_ticks(u64::MAX));
+        assert!(limiter.try_acquire_n(u32::MAX));
+
+        let mut limiter = RateLimiter::new(3, Duration::from_millis(300));
+        assert!(!limiter.try_acquire_n(4));
+        assert_eq!(limiter.available(), 3);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg(feature = "generic-queue")]
+    fn test_acquire() {
+        use core::future::Future;
+        use core::pin::pin;
+        use core::task::{Context, Poll, Waker};
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::Arc;
+        use std::task::Wake;
+
+        struct FlagWaker(AtomicBool);
+
+        impl Wake for FlagWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.store(true, Ordering::Relaxed);
+            }
+        }
+
+        MockDriver::get().reset();
+        crate::queue_generic::reset();
+
+        let flag = Arc::new(FlagWaker(AtomicBool::new(false)));
+        let waker = Waker::from(flag.clone());
+        let mut cx = Context::from_waker(&waker);
+        let mut limiter = RateLimiter::new(2, Duration::from_secs(2));
+
+        assert!(pin!(limiter.acquire_n(2)).poll(&mut cx).is_ready());
+
+        let mut acquire = pin!(limiter.acquire());
+        assert_eq!(acquire.as_mut().poll(&mut cx), Poll::Pending);
+        MockDriver::get().advance(Duration::from_secs(1));
+        assert!(flag.0.load(Ordering::Relaxed));
+        assert_eq!(acquire.as_mut().poll(&mut cx), Poll::Ready(()));
+    }
+}
@@ -0,0 +1,244 @@
+//! Measuring elapsed time and latencies.
+//!
+//! ```ignore
+//! use embassy_time::stopwatch::{self, LatencyHistogram, Stopwatch};
+//!
+//! stopwatch::calibrate();
+//! let mut histogram = LatencyHistogram::<16>::new(Duration::from_micros(10));
+//! loop {
+//!     let stopwatch = Stopwatch::start();
+//!     handle_request().await;
+//!     histogram.record(stopwatch.elapsed());
+//!
+//!     if histogram.count() == 1000 {
+//!         info!("min {} mean {} max {}", histogram.min(), histogram.mean(), histogram.max());
+//!         histogram.reset();
+//!     }
+//! }
+//! ```
+
+use core::cell::Cell;
+use core::future::Future;
+
+use critical_section::Mutex as CsMutex;
+
+use crate::{Duration, Instant};
+
+/// Overhead of a measurement in ticks, subtracted from measured durations.
+static OVERHEAD: CsMutex<Cell<u64>> = CsMutex::new(Cell::new(0));
+
+/// Measure the overhead of reading the time, and subtract it from the durations measured by
+/// [`Stopwatch`]es from now on. Returns the overhead.
+///
+/// Reading the time of the driver takes a few ticks with high tick rates, which would otherwise be
+/// added to every measurement. Call it once at startup, with interrupts enabled as usual: the
+/// smallest of several measurements is kept.
+pub fn calibrate() -> Duration {
+    let mut overhead = u64::MAX;
+    for _ in 0..16 {
+        let start = Instant::now();
+        let end = Instant::now();
+        overhead = overhead.min(end.as_ticks() - start.as_ticks());
+    }
+    critical_section::with(|cs| OVERHEAD.borrow(cs).set(overhead));
+    Duration::from_ticks(overhead)
+}
+
+fn overhead() -> u64 {
+    critical_section::with(|cs| OVERHEAD.borrow(cs).get())
+}
+
+/// Measures the time elapsed since it was started, corrected for the overhead measured by
+/// [`calibrate()`].
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Stopwatch {
+    start: Instant,
+}
+
+impl Stopwatch {
+    /// Start a new stopwatch.
+    pub fn start() -> Self {
+        Self { start: Instant::now() }
+    }
+
+    /// The instant the stopwatch was started.
+    pub fn start_instant(&self) -> Instant {
+        self.start
+    }
+
+    /// Time elapsed since the stopwatch was started.
+    pub fn elapsed(&self) -> Duration {
+        let ticks = Instant::now().as_ticks() - self.start.as_ticks();
+        Duration::from_ticks(ticks.saturating_sub(overhead()))
+    }
+
+    /// Time elapsed since the stopwatch was started, restarting it: successive calls measure
+    /// consecutive laps.
+    pub fn lap(&mut self) -> Duration {
+        let now = Instant::now();
+        let ticks = now.as_ticks() - self.start.as_ticks();
+        self.start = now;
+        Duration::from_ticks(ticks.saturating_sub(overhead()))
+    }
+}
+
+/// Distribution of latencies, in `N` buckets of equal width.
+///
+/// The last bucket also counts all the latencies above the range of the histogram.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LatencyHistogram<const N: usize> {
+    bucket_width: u64,
+    buckets: [u32; N],
+    count: u32,
+    min: u64,
+    max: u64,
+    sum: u64,
+}
+
+impl<const N: usize> LatencyHistogram<N> {
+    /// Create an empty histogram with buckets of `bucket_width`.
+    pub const fn new(bucket_width: Duration) -> Self {
+        core::assert!(N > 0);
+        core::assert!(bucket_width.as_ticks() > 0);
+        Self {
+            bucket_width: bucket_width.as_ticks(),
+            buckets: [0; N],
+            count: 0,
+            min: u64::MAX,
+            max: 0,
+            sum: 0,
+        }
+    }
+
+    /// Record a latency.
+    pub fn record(&mut self, latency: Duration) {
+        let ticks = latency.as_ticks();
+        let bucket = ((ticks / self.bucket_width) as usize).min(N - 1);
+        self.buckets[bucket] = self.buckets[bucket].saturating_add(1);
+        self.count = self.count.saturating_add(1);
+        self.min = self.min.min(ticks);
+        self.max = self.max.max(ticks);
+        self.sum = self.sum.saturating_add(ticks);
+    }
+
+    /// Run a closure, recording how long it took.
+    pub fn time<R>(&mut self, f: impl FnOnce() -> R) -> R {
+        let stopwatch = Stopwatch::start();
+        let result = f();
+        self.record(stopwatch.elapsed());
+        result
+    }
+
+    /// Run a future to completion, recording how long it took.
+    pub async fn measure<F: Future>(&mut self, fut: F) -> F::Output {
+        let stopwatch = Stopwatch::start();
+        let result = fut.await;
+        self.record(stopwatch.elapsed());
+        result
+    }
+
+    /// Forget all the recorded latencies.
+    pub fn reset(&mut self) {
+        *self = Self::new(Duration::from_ticks(self.bucket_width));
+    }
+
+    /// Number of recorded latencies.
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Smallest recorded latency, or zero if none was recorded.
+    pub fn min(&self) -> Duration {
+        Duration::from_ticks(if self.count == 0 { 0 } else { self.min })
+    }
+
+    /// Largest recorded latency, or zero if none was recorded.
+    pub fn max(&self) -> Duration {
+        Duration::from_ticks(self.max)
+    }
+
+    /// Mean of the recorded latencies, or zero if none was recorded.
+    pub fn mean(&self) -> Duration {
+        Duration::from_ticks(self.sum.checked_div(self.count as u64).unwrap_or(0))
+    }
+
+    /// Upper bound of the bucket containing the `percent`th percentile of the recorded latencies,
+    /// capped to the largest recorded latency. Zero if none was recorded.
+    pub fn percentile(&self, percent: u8) -> Duration {
+        if self.count == 0 {
+            return Duration::from_ticks(0);
+        }
+        let rank = (self.count as u64 * percent.min(100) as u64).div_ceil(100).max(1);
+        let mut seen = 0;
+        for (i, &count) in self.buckets.iter().enumerate() {
+            seen += count as u64;
+            // The last bucket has no upper bound.
+            if seen >= rank && i < N - 1 {
+                let upper = (i as u64 + 1).saturating_mul(self.bucket_width);
+                return Duration::from_ticks(upper.min(self.max));
+            }
+        }
+        self.max()
+    }
+
+    /// Count of recorded latencies in each bucket: bucket `i` counts latencies in
+    /// `[i * bucket_width, (i + 1) * bucket_width)`.
+    pub fn buckets(&self) -> &[u32; N] {
+        &self.buckets
+    }
+
+    /// Width of the buckets.
+    pub fn bucket_width(&self) -> Duration {
+        Duration::from_ticks(self.bucket_width)
+    }
+}
+
+#[cfg(test)]
+#[cfg(feature = "mock-driver")]
+mod tests {
+    use serial_test::serial;
+
+    use super::*;
+    use crate::MockDriver;
+
+    #[test]
+    #[serial]
+    fn test_stopwatch() {
+        MockDriver::get().reset();
+
+        let mut stopwatch = Stopwatch::start();
+        MockDriver::get().advance(Duration::from_millis(3));
+        assert_eq!(stopwatch.elapsed(), Duration::from_millis(3));
+        assert_eq!(stopwatch.lap(), Duration::from_millis(3));
+        MockDriver::get().advance(Duration::from_millis(2));
+        assert_eq!(stopwatch.lap(), Duration::from_millis(2));
+
+        // The mock driver doesn't advance between reads.
+        assert_eq!(calibrate(), Duration::from_ticks(0));
+    }
+
+    #[test]
+    fn test_histogram() {
+        let mut histogram = LatencyHistogram::<4>::new(Duration::from_ticks(10));
+        assert_eq!(histogram.mean(), Duration::from_ticks(0));
+        assert_eq!(histogram.percentile(50), Duration::from_ticks(0));
+
+        for ticks in [1, 5, 12, 18, 100] {
+            histogram.record(Duration::from_ticks(ticks));
+        }
+
+        assert_eq!(histogram.count(), 5);
+        assert_eq!(histogram.buckets(), &[2, 2, 0, 1]);
+        assert_eq!(histogram.min(), Duration::from_ticks(1));
+        assert_eq!(histogram.max(), Duration::from_ticks(100));
+        assert_eq!(histogram.mean(), Duration::from_ticks(27));
+        assert_eq!(histogram.percentile(50), Duration::from_ticks(20));
+        assert_eq!(histogram.percentile(100), Duration::from_ticks(100));
+
+        histogram.reset();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.bucket_width(), Duration::from_ticks(10));
+    }
+}
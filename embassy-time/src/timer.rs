@@ -39,6 +39,67 @@ pub async fn with_deadline<F: Future>(at: Instant, fut: F) -> Result<F::Output,
     }
 }
 
+/// A single time budget for a multi-step operation.
+///
+/// Unlike calling [`with_timeout`] for each step, which restarts the timeout every time, a `Deadline`
+/// is fixed once created, and can be copied to every step of the operation so that they all share the
+/// same end-to-end budget.
+///
+/// ```ignore
+/// let deadline = Deadline::after(Duration::from_secs(2));
+/// deadline.run(socket.connect(endpoint)).await??;
+/// deadline.run(socket.write_all(&request)).await??;
+/// let len = deadline.run(socket.read(&mut response)).await??;
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Deadline {
+    at: Instant,
+}
+
+impl Deadline {
+    /// A deadline at the specified [`Instant`].
+    pub const fn at(at: Instant) -> Self {
+        Self { at }
+    }
+
+    /// A deadline the specified [`Duration`] from now.
+    pub fn after(duration: Duration) -> Self {
+        Self::at(Instant::now() + duration)
+    }
+
+    /// The [`Instant`] of the deadline.
+    pub const fn instant(&self) -> Instant {
+        self.at
+    }
+
+    /// Time left until the deadline, or zero if it has passed.
+    pub fn remaining(&self) -> Duration {
+        self.at.saturating_duration_since(Instant::now())
+    }
+
+    /// Whether the deadline has passed.
+    pub fn is_expired(&self) -> bool {
+        self.at <= Instant::now()
+    }
+
+    /// Runs a given future until the deadline, see [`with_deadline`].
+    pub async fn run<F: Future>(&self, fut: F) -> Result<F::Output, TimeoutError> {
+        with_deadline(self.at, fut).await
+    }
+
+    /// A [`Timer`] expiring at the deadline.
+    pub fn timer(&self) -> Timer {
+        Timer::at(self.at)
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(at: Instant) -> Self {
+        Self::at(at)
+    }
+}
+
 /// A future that completes at a specified [Instant](struct.Instant.html).
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Timer {
@@ -172,48 +233,99 @@ impl Future for Timer {
 ///     }
 /// }
 /// ```
+///
+/// Tick deadlines are computed from the instant the ticker was started, `start + n * duration`,
+/// so they don't drift even if the task is occasionally late. What happens to the ticks missed
+/// while the task was late is selected with [`MissedTickBehavior`].
 pub struct Ticker {
-    expires_at: Instant,
+    start: Instant,
+    ticks: u64,
     duration: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+}
+
+/// What a [`Ticker`] does with the ticks that were missed because it wasn't polled in time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MissedTickBehavior {
+    /// Fire all the missed ticks immediately, one after the other, until the ticker catches up.
+    ///
+    /// The number of ticks is kept, which is useful when each tick accounts for a period of time.
+    #[default]
+    Burst,
+    /// Fire a single tick for all the missed ones, then resume at the next deadline of the
+    /// original schedule.
+    ///
+    /// This is usually what control loops and sensor sampling want: the late tick is handled
+    /// once, and the following ones stay aligned to the original schedule.
+    Skip,
 }
 
 impl Ticker {
     /// Creates a new ticker that ticks at the specified duration interval.
     pub fn every(duration: Duration) -> Self {
-        let expires_at = Instant::now() + duration;
-        Self { expires_at, duration }
+        Self {
+            start: Instant::now(),
+            ticks: 1,
+            duration,
+            missed_tick_behavior: MissedTickBehavior::Burst,
+        }
+    }
+
+    /// Sets what the ticker does with missed ticks.
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    /// Returns what the ticker does with missed ticks.
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// Returns the deadline of the next tick.
+    pub fn next_deadline(&self) -> Instant {
+        Instant::from_ticks(self.start.as_ticks() + self.duration.as_ticks() * self.ticks)
     }
 
     /// Resets the ticker back to its original state.
     /// This causes the ticker to go back to zero, even if the current tick isn't over yet.
     pub fn reset(&mut self) {
-        self.expires_at = Instant::now() + self.duration;
+        self.reset_at(Instant::now());
     }
 
     /// Reset the ticker at the deadline.
     /// If the deadline is in the past, the ticker will fire instantly.
     pub fn reset_at(&mut self, deadline: Instant) {
-        self.expires_at = deadline + self.duration;
+        self.start = deadline;
+        self.ticks = 1;
     }
 
     /// Resets the ticker, after the specified duration has passed.
     /// If the specified duration is zero, the next tick will be after the duration of the ticker.
     pub fn reset_after(&mut self, after: Duration) {
-        self.expires_at = Instant::now() + after + self.duration;
+        self.reset_at(Instant::now() + after);
     }
 
     /// Waits for the next tick.
     pub fn next(&mut self) -> impl Future<Output = ()> + Send + Sync + '_ {
-        poll_fn(|cx| {
-            if self.expires_at <= Instant::now() {
-                let dur = self.duration;
-                self.expires_at += dur;
-                Poll::Ready(())
-            } else {
-                embassy_time_queue_driver::schedule_wake(self.expires_at.as_ticks(), cx.waker());
-                Poll::Pending
-            }
-        })
+        poll_fn(|cx| self.poll_tick(cx))
+    }
+
+    fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let now = Instant::now();
+        let expires_at = self.next_deadline();
+        if expires_at <= now {
+            let period = self.duration.as_ticks();
+            self.ticks = match self.missed_tick_behavior {
+                // First tick of the schedule strictly after `now`.
+                MissedTickBehavior::Skip if period != 0 => (now.as_ticks() - self.start.as_ticks()) / period + 1,
+                _ => self.ticks + 1,
+            };
+            Poll::Ready(())
+        } else {
+            embassy_time_queue_driver::schedule_wake(expires_at.as_ticks(), cx.waker());
+            Poll::Pending
+        }
     }
 }
 
@@ -222,14 +334,7 @@ impl Unpin for Ticker {}
 impl Stream for Ticker {
     type Item = ();
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if self.expires_at <= Instant::now() {
-            let dur = self.duration;
-            self.expires_at += dur;
-            Poll::Ready(Some(()))
-        } else {
-            embassy_time_queue_driver::schedule_wake(self.expires_at.as_ticks(), cx.waker());
-            Poll::Pending
-        }
+        self.poll_tick(cx).map(Some)
     }
 }
 
@@ -0,0 +1,330 @@
+//! Wall-clock time, on top of [`Instant`].
+//!
+//! [`Instant`]s count ticks since boot, which is all timers need, but not what a log timestamp,
+//! a TLS certificate check or a calendar needs. This module keeps the offset between boot and the
+//! UNIX epoch, so that once it has been set from a time source (SNTP, GPS, an RTC...), the current
+//! time can be read with [`now_utc()`] and converted to and from calendar time.
+//!
+//! ```ignore
+//! // Received from an SNTP server.
+//! wallclock::set_now_utc(UtcTime::from_unix_secs(sntp_secs));
+//!
+//! let now = wallclock::now_utc().unwrap().to_datetime();
+//! info!("{}-{}-{} {}:{}:{}", now.year, now.month, now.day, now.hour, now.minute, now.second);
+//! ```
+//!
+//! The offset is lost on reset. A battery-backed RTC can keep it across resets by implementing
+//! [`BackupRtc`]: call [`persist()`] after setting the time, and [`restore()`] at startup.
+
+use core::cell::Cell;
+
+use critical_section::Mutex as CsMutex;
+
+use crate::{Duration, Instant};
+
+/// UNIX time in microseconds of the instant the time driver started, if set.
+static BOOT_UTC: CsMutex<Cell<Option<u64>>> = CsMutex::new(Cell::new(None));
+
+/// A point in UTC wall-clock time, with microsecond resolution.
+///
+/// It is stored as the number of microseconds since the UNIX epoch, 1970-01-01 00:00:00 UTC, ignoring
+/// leap seconds like UNIX time does.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UtcTime {
+    micros: u64,
+}
+
+impl UtcTime {
+    /// The UNIX epoch, 1970-01-01 00:00:00 UTC.
+    pub const UNIX_EPOCH: UtcTime = UtcTime { micros: 0 };
+
+    /// Create an `UtcTime` from the number of microseconds since the UNIX epoch.
+    pub const fn from_unix_micros(micros: u64) -> Self {
+        Self { micros }
+    }
+
+    /// Create an `UtcTime` from the number of milliseconds since the UNIX epoch.
+    pub const fn from_unix_millis(millis: u64) -> Self {
+        Self { micros: millis * 1_000 }
+    }
+
+    /// Create an `UtcTime` from the number of seconds since the UNIX epoch.
+    pub const fn from_unix_secs(secs: u64) -> Self {
+        Self {
+            micros: secs * 1_000_000,
+        }
+    }
+
+    /// Microseconds since the UNIX epoch.
+    pub const fn as_unix_micros(&self) -> u64 {
+        self.micros
+    }
+
+    /// Milliseconds since the UNIX epoch.
+    pub const fn as_unix_millis(&self) -> u64 {
+        self.micros / 1_000
+    }
+
+    /// Seconds since the UNIX epoch.
+    pub const fn as_unix_secs(&self) -> u64 {
+        self.micros / 1_000_000
+    }
+
+    /// Create an `UtcTime` from a calendar date and time.
+    ///
+    /// Returns `None` if a field is out of range, for example February 30th, or if the date is before
+    /// the UNIX epoch.
+    pub fn from_datetime(datetime: &DateTime) -> Option<Self> {
+        let DateTime {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second,
+            microsecond,
+        } = *datetime;
+        if year < 1970
+            || !(1..=12).contains(&month)
+            || day == 0
+            || day > days_in_month(year, month)
+            || hour > 23
+            || minute > 59
+            || second > 59
+            || microsecond > 999_999
+        {
+            return None;
+        }
+
+        let days = days_from_civil(year, month, day);
+        let secs = ((days * 24 + hour as u64) * 60 + minute as u64) * 60 + second as u64;
+        Some(Self {
+            micros: secs * 1_000_000 + microsecond as u64,
+        })
+    }
+
+    /// Convert to a calendar date and time.
+    pub fn to_datetime(&self) -> DateTime {
+        let secs = self.micros / 1_000_000;
+        let (year, month, day) = civil_from_days(secs / 86_400);
+        let secs_of_day = secs % 86_400;
+        DateTime {
+            year,
+            month,
+            day,
+            hour: (secs_of_day / 3_600) as u8,
+            minute: (secs_of_day / 60 % 60) as u8,
+            second: (secs_of_day % 60) as u8,
+            microsecond: (self.micros % 1_000_000) as u32,
+        }
+    }
+
+    /// Adds a Duration to self. In case of overflow, the maximum value is returned.
+    pub fn saturating_add(self, duration: Duration) -> Self {
+        Self {
+            micros: self.micros.saturating_add(duration.as_micros()),
+        }
+    }
+
+    /// Duration between this time and an earlier one, or zero if `earlier` is later.
+    pub fn saturating_duration_since(&self, earlier: UtcTime) -> Duration {
+        Duration::from_micros(self.micros.saturating_sub(earlier.micros))
+    }
+}
+
+/// A calendar date and time in UTC.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DateTime {
+    /// Year, 1970 or later.
+    pub year: u16,
+    /// Month, from 1 to 12.
+    pub month: u8,
+    /// Day of the month, from 1 to 31.
+    pub day: u8,
+    /// Hour, from 0 to 23.
+    pub hour: u8,
+    /// Minute, from 0 to 59.
+    pub minute: u8,
+    /// Second, from 0 to 59.
+    pub second: u8,
+    /// Microsecond, from 0 to 999_999.
+    pub microsecond: u32,
+}
+
+/// Set the current wall-clock time.
+///
+/// Setting it again, for example after each SNTP synchronization, corrects the drift between the time
+/// driver and the time source.
+pub fn set_now_utc(now: UtcTime) {
+    let uptime = Instant::now().as_micros();
+    let boot = now.micros.saturating_sub(uptime);
+    critical_section::with(|cs| BOOT_UTC.borrow(cs).set(Some(boot)));
+}
+
+/// Forget the wall-clock time, so that [`now_utc()`] returns `None` until it is set again.
+pub fn clear() {
+    critical_section::with(|cs| BOOT_UTC.borrow(cs).set(None));
+}
+
+/// Whether the wall-clock time has been set.
+pub fn is_set() -> bool {
+    boot_utc().is_some()
+}
+
+/// The current wall-clock time, or `None` if it hasn't been set yet.
+pub fn now_utc() -> Option<UtcTime> {
+    instant_to_utc(Instant::now())
+}
+
+/// The wall-clock time at `instant`, or `None` if the wall-clock time hasn't been set yet.
+pub fn instant_to_utc(instant: Instant) -> Option<UtcTime> {
+    let boot = boot_utc()?;
+    Some(UtcTime {
+        micros: boot.saturating_add(instant.as_micros()),
+    })
+}
+
+/// The [`Instant`] at wall-clock time `time`, or `None` if the wall-clock time hasn't been set yet, or
+/// `time` is before the time driver started.
+pub fn utc_to_instant(time: UtcTime) -> Option<Instant> {
+    let boot = boot_utc()?;
+    let uptime = time.micros.checked_sub(boot)?;
+    Some(Instant::from_micros(uptime))
+}
+
+fn boot_utc() -> Option<u64> {
+    critical_section::with(|cs| BOOT_UTC.borrow(cs).get())
+}
+
+/// A battery-backed clock keeping the wall-clock time across resets, such as the backup domain RTC of
+/// a microcontroller or an external I2C RTC.
+pub trait BackupRtc {
+    /// Error type.
+    type Error;
+
+    /// Read the current time from the RTC.
+    async fn read(&mut self) -> Result<UtcTime, Self::Error>;
+
+    /// Set the current time of the RTC.
+    async fn write(&mut self, now: UtcTime) -> Result<(), Self::Error>;
+}
+
+/// Set the wall-clock time from `rtc`, returning it.
+pub async fn restore<R: BackupRtc>(rtc: &mut R) -> Result<UtcTime, R::Error> {
+    let now = rtc.read().await?;
+    set_now_utc(now);
+    Ok(now)
+}
+
+/// Write the wall-clock time to `rtc`.
+///
+/// Does nothing if the wall-clock time hasn't been set yet.
+pub async fn persist<R: BackupRtc>(rtc: &mut R) -> Result<(), R::Error> {
+    match now_utc() {
+        Some(now) => rtc.write(now).await,
+        None => Ok(()),
+    }
+}
+
+fn is_leap_year(year: u16) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Conversions between days since the UNIX epoch and civil dates, from
+// http://howardhinnant.github.io/date_algorithms.html, restricted to dates after the epoch.
+
+fn days_from_civil(year: u16, month: u8, day: u8) -> u64 {
+    let (year, month, day) = (year as u64, month as u64, day as u64);
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: u64) -> (u16, u8, u8) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as u64;
+    (year as u16, month as u8, day as u8)
+}
+
+#[cfg(test)]
+#[cfg(feature = "mock-driver")]
+mod tests {
+    use serial_test::serial;
+
+    use super::*;
+    use crate::MockDriver;
+
+    fn setup() {
+        MockDriver::get().reset();
+        clear();
+    }
+
+    #[test]
+    fn test_datetime() {
+        let datetime = DateTime {
+            year: 2024,
+            month: 2,
+            day: 29,
+            hour: 13,
+            minute: 37,
+            second: 42,
+            microsecond: 123_456,
+        };
+        let time = UtcTime::from_datetime(&datetime).unwrap();
+        assert_eq!(time.as_unix_secs(), 1_709_213_862);
+        assert_eq!(time.to_datetime(), datetime);
+
+        assert_eq!(UtcTime::UNIX_EPOCH.to_datetime().year, 1970);
+        assert_eq!(UtcTime::from_unix_secs(951_868_800).to_datetime().day, 1); // 2000-03-01
+
+        let invalid = DateTime { day: 30, ..datetime };
+        assert_eq!(UtcTime::from_datetime(&invalid), None);
+        let invalid = DateTime { year: 2023, ..datetime };
+        assert_eq!(UtcTime::from_datetime(&invalid), None);
+    }
+
+    #[test]
+    #[serial]
+    fn test_now_utc() {
+        setup();
+
+        MockDriver::get().advance(Duration::from_secs(10));
+        assert_eq!(now_utc(), None);
+
+        set_now_utc(UtcTime::from_unix_secs(1_000_000));
+        MockDriver::get().advance(Duration::from_secs(5));
+        assert_eq!(now_utc(), Some(UtcTime::from_unix_secs(1_000_005)));
+
+        assert_eq!(
+            instant_to_utc(Instant::from_secs(0)),
+            Some(UtcTime::from_unix_secs(999_990))
+        );
+        assert_eq!(
+            utc_to_instant(UtcTime::from_unix_secs(1_000_000)),
+            Some(Instant::from_secs(10))
+        );
+        assert_eq!(utc_to_instant(UtcTime::from_unix_secs(1)), None);
+    }
+}
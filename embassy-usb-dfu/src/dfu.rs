@@ -81,8 +81,12 @@ impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Ha
                                     NorFlashErrorKind::OutOfBounds => self.status = Status::ErrAddress,
                                     _ => self.status = Status::ErrUnknown,
                                 },
-                                embassy_boot::FirmwareUpdaterError::Signature(_) => self.status = Status::ErrVerify,
+                                embassy_boot::FirmwareUpdaterError::Signature(_)
+                                | embassy_boot::FirmwareUpdaterError::DigestMismatch
+                                | embassy_boot::FirmwareUpdaterError::Rollback => self.status = Status::ErrVerify,
                                 embassy_boot::FirmwareUpdaterError::BadState => self.status = Status::ErrUnknown,
+                                embassy_boot::FirmwareUpdaterError::InvalidPatch
+                                | embassy_boot::FirmwareUpdaterError::InvalidImage => self.status = Status::ErrFile,
                             }
                         }
                     }
@@ -107,8 +111,12 @@ impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Ha
                                     NorFlashErrorKind::OutOfBounds => self.status = Status::ErrAddress,
                                     _ => self.status = Status::ErrUnknown,
                                 },
-                                embassy_boot::FirmwareUpdaterError::Signature(_) => self.status = Status::ErrVerify,
+                                embassy_boot::FirmwareUpdaterError::Signature(_)
+                                | embassy_boot::FirmwareUpdaterError::DigestMismatch
+                                | embassy_boot::FirmwareUpdaterError::Rollback => self.status = Status::ErrVerify,
                                 embassy_boot::FirmwareUpdaterError::BadState => self.status = Status::ErrUnknown,
+                                embassy_boot::FirmwareUpdaterError::InvalidPatch
+                                | embassy_boot::FirmwareUpdaterError::InvalidImage => self.status = Status::ErrFile,
                             }
                         }
                     }
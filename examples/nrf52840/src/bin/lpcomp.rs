@@ -0,0 +1,29 @@
+#![no_std]
+#![no_main]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_nrf::bind_interrupts;
+use embassy_nrf::lpcomp::{self, Lpcomp, Reference};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    COMP_LPCOMP => lpcomp::InterruptHandler;
+});
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_nrf::init(Default::default());
+
+    let mut config = lpcomp::Config::default();
+    config.reference = Reference::Vdd4_8;
+    config.hysteresis = true;
+    let mut comp = Lpcomp::new(p.LPCOMP, Irqs, p.P0_02, config);
+
+    info!("input above VDD/2: {}", comp.sample());
+
+    loop {
+        let crossing = comp.wait_for_cross().await;
+        info!("crossed: {:?}", crossing);
+    }
+}
@@ -0,0 +1,93 @@
+#![no_std]
+#![no_main]
+
+use defmt::{info, warn};
+use embassy_executor::Spawner;
+use embassy_nrf::bind_interrupts;
+use embassy_nrf::config::HfclkSource;
+use embassy_nrf::nfct::{self, NfcId, NfcT, Protocol};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    NFCT => nfct::InterruptHandler;
+});
+
+const UID: [u8; 7] = [0x5f, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+
+// NDEF message with a single URI record pointing to https://embassy.dev
+const NDEF: &[u8] = &[
+    0xd1, 0x01, 0x0c, 0x55, 0x04, b'e', b'm', b'b', b'a', b's', b's', b'y', b'.', b'd', b'e', b'v',
+];
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let mut config = embassy_nrf::config::Config::default();
+    config.hfclk_source = HfclkSource::ExternalXtal;
+    let p = embassy_nrf::init(config);
+
+    // Type 2 tag memory: 4 header pages followed by 12 data pages.
+    let mut memory = [0u8; 64];
+    memory[0..3].copy_from_slice(&UID[0..3]);
+    memory[3] = 0x88 ^ UID[0] ^ UID[1] ^ UID[2];
+    memory[4..8].copy_from_slice(&UID[3..7]);
+    memory[8] = UID[3] ^ UID[4] ^ UID[5] ^ UID[6];
+    // Capability container: NDEF v1.0, 48 bytes of data, read-only.
+    memory[12..16].copy_from_slice(&[0xe1, 0x10, 0x06, 0x0f]);
+    // NDEF message TLV followed by a terminator TLV.
+    memory[16] = 0x03;
+    memory[17] = NDEF.len() as u8;
+    memory[18..18 + NDEF.len()].copy_from_slice(NDEF);
+    memory[18 + NDEF.len()] = 0xfe;
+
+    let mut config = nfct::Config::default();
+    config.nfcid1 = NfcId::DoubleSize(UID);
+    config.protocol = Protocol::Type2Tag;
+    let mut tag = NfcT::new(p.NFCT, Irqs, config);
+
+    let mut rx = [0u8; 16];
+    let mut tx = [0u8; 16];
+
+    'outer: loop {
+        info!("waiting for reader");
+        tag.activate().await;
+        info!("selected");
+
+        loop {
+            let n = match tag.receive(&mut rx).await {
+                Ok(n) => n,
+                Err(nfct::Error::FieldLost) => continue 'outer,
+                Err(e) => {
+                    warn!("rx error: {:?}", e);
+                    continue;
+                }
+            };
+
+            let res = match &rx[..n] {
+                // READ: return 4 pages starting at the requested one.
+                [0x30, page] => {
+                    for (i, b) in tx.iter_mut().enumerate() {
+                        *b = memory[(*page as usize * 4 + i) % memory.len()];
+                    }
+                    tag.transmit(&tx).await
+                }
+                // HLTA: sleep until woken up and selected again.
+                [0x50, 0x00] => {
+                    tag.sleep();
+                    tag.wait_for_selection().await
+                }
+                // Everything else gets a NAK.
+                _ => {
+                    tx[0] = 0x0;
+                    tag.transmit_bits(&tx[..1], 4).await
+                }
+            };
+
+            if let Err(e) = res {
+                warn!("error: {:?}", e);
+                if e == nfct::Error::FieldLost {
+                    continue 'outer;
+                }
+            }
+        }
+    }
+}
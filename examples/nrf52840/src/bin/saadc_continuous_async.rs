@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+use defmt::info;
+use embassy_executor::Spawner;
+use embassy_nrf::saadc::{ChannelConfig, Config, Saadc, SampleRate};
+use embassy_nrf::{bind_interrupts, saadc};
+use {defmt_rtt as _, panic_probe as _};
+
+// Demonstrates continuous sampling of multiple channels driven by a PPI linked timer,
+// awaiting each filled buffer instead of processing it in a callback.
+
+bind_interrupts!(struct Irqs {
+    SAADC => saadc::InterruptHandler;
+});
+
+#[embassy_executor::main]
+async fn main(_p: Spawner) {
+    let mut p = embassy_nrf::init(Default::default());
+    let config = Config::default();
+    let channel_1_config = ChannelConfig::single_ended(&mut p.P0_02);
+    let channel_2_config = ChannelConfig::single_ended(&mut p.P0_03);
+    let mut saadc = Saadc::new(p.SAADC, Irqs, config, [channel_1_config, channel_2_config]);
+
+    saadc.calibrate().await;
+
+    let mut bufs = [[[0; 2]; 250]; 2];
+
+    let mut sampler = saadc.start_continuous(
+        &mut p.TIMER0,
+        &mut p.PPI_CH0,
+        &mut p.PPI_CH1,
+        SampleRate::hz(500),
+        &mut bufs,
+    );
+
+    loop {
+        // The other buffer is filled while this one is processed, which gives
+        // us 500ms before the next call to `next` is due.
+        let buf = sampler.next().await;
+
+        let mut a = [0i32; 2];
+        for b in buf {
+            a[0] += b[0] as i32;
+            a[1] += b[1] as i32;
+        }
+        let len = buf.len() as i32;
+        info!("channel 1: {=i32}, channel 2: {=i32}", a[0] / len, a[1] / len);
+    }
+}
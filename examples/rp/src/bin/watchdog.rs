@@ -19,6 +19,7 @@ async fn main(_spawner: Spawner) {
     info!("Hello world!");
 
     let mut watchdog = Watchdog::new(p.WATCHDOG);
+    info!("Last reset caused by the watchdog: {:?}", watchdog.reset_reason());
     let mut led = Output::new(p.PIN_25, Level::Low);
 
     // Set the LED high for 2 seconds so we know when we're about to start the watchdog
@@ -0,0 +1,26 @@
+PROVIDE(POWER_CLOCK = DefaultHandler);
+PROVIDE(RADIO = DefaultHandler);
+PROVIDE(UART0 = DefaultHandler);
+PROVIDE(SPI0_TWI0 = DefaultHandler);
+PROVIDE(SPI1_TWI1 = DefaultHandler);
+PROVIDE(GPIOTE = DefaultHandler);
+PROVIDE(ADC = DefaultHandler);
+PROVIDE(TIMER0 = DefaultHandler);
+PROVIDE(TIMER1 = DefaultHandler);
+PROVIDE(TIMER2 = DefaultHandler);
+PROVIDE(RTC0 = DefaultHandler);
+PROVIDE(TEMP = DefaultHandler);
+PROVIDE(RNG = DefaultHandler);
+PROVIDE(ECB = DefaultHandler);
+PROVIDE(CCM_AAR = DefaultHandler);
+PROVIDE(WDT = DefaultHandler);
+PROVIDE(RTC1 = DefaultHandler);
+PROVIDE(QDEC = DefaultHandler);
+PROVIDE(LPCOMP = DefaultHandler);
+PROVIDE(SWI0 = DefaultHandler);
+PROVIDE(SWI1 = DefaultHandler);
+PROVIDE(SWI2 = DefaultHandler);
+PROVIDE(SWI3 = DefaultHandler);
+PROVIDE(SWI4 = DefaultHandler);
+PROVIDE(SWI5 = DefaultHandler);
+
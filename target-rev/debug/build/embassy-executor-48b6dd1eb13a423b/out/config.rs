@@ -0,0 +1,2 @@
+pub const POLL_BUDGET: usize = 8;
+pub const TASK_ARENA_SIZE: usize = 4096;
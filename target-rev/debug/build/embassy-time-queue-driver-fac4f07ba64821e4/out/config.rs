@@ -0,0 +1 @@
+pub const COALESCE_WINDOW_US: u64 = 0;
@@ -130,6 +130,25 @@ async fn main(_spawner: Spawner) {
         defmt::assert!(temp.iter().all(|t| *t > 0.0));
         defmt::assert!(temp.iter().all(|t| *t < 60.0));
     }
+    {
+        // round robin between a pulled down pin and the temp sensor,
+        // samples are interleaved in channel order.
+        let mut multi = [0u16; 16];
+        adc.read_many_multichannel(
+            &mut [
+                Channel::new_temp_sensor(&mut p.ADC_TEMP_SENSOR),
+                Channel::new_pin(&mut p.PIN_28, Pull::Down),
+            ],
+            &mut multi,
+            1,
+            &mut p.DMA_CH0,
+        )
+        .await
+        .unwrap();
+        defmt::assert!(multi.iter().step_by(2).all(|s| *s < 0b01_0000_0000));
+        let mut temp = multi.iter().skip(1).step_by(2).map(|s| convert_to_celsius(*s));
+        defmt::assert!(temp.all(|t| t > 0.0 && t < 60.0));
+    }
 
     info!("Test OK");
     cortex_m::asm::bkpt();
@@ -0,0 +1,63 @@
+#![no_std]
+#![no_main]
+teleprobe_meta::target!(b"rpi-pico");
+
+use defmt::{assert, *};
+use embassy_executor::Spawner;
+use embassy_rp::dma::ReadableRingBuffer;
+use embassy_rp::pac;
+use embassy_time::Timer;
+use {defmt_rtt as _, panic_probe as _};
+
+#[repr(align(256))]
+struct Ring([u32; 64]);
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+    info!("Hello World!");
+
+    // Pace the transfers with DMA timer 0 at 125MHz / 12500 = 10kHz.
+    pac::DMA.timer(0).write(|w| {
+        w.set_x(1);
+        w.set_y(12500);
+    });
+
+    // Sample the free-running microsecond timer, so every word is 100 more than the previous one.
+    let mut ring = Ring([0; 64]);
+    let mut rb = unsafe {
+        ReadableRingBuffer::new(
+            p.DMA_CH0,
+            p.DMA_CH1,
+            pac::TIMER.timerawl().as_ptr() as *const u32,
+            &mut ring.0,
+            pac::dma::vals::TreqSel::TIMER0.0,
+        )
+    };
+
+    // Read across several wraps of the ring.
+    let mut last = None;
+    for _ in 0..20 {
+        let mut buf = [0u32; 16];
+        rb.read_exact(&mut buf).await.unwrap();
+        for w in buf {
+            if let Some(last) = last {
+                let delta = w.wrapping_sub(last);
+                assert!((95..=105).contains(&delta), "delta {}", delta);
+            }
+            last = Some(w);
+        }
+    }
+
+    // Not reading for longer than it takes to fill the ring overruns it.
+    Timer::after_millis(10).await;
+    assert!(rb.len().is_err());
+    let mut buf = [0u32; 16];
+    assert!(rb.read(&mut buf).is_err());
+
+    rb.clear();
+    rb.read_exact(&mut buf).await.unwrap();
+
+    info!("Test OK");
+    cortex_m::asm::bkpt();
+}
@@ -0,0 +1,55 @@
+#![no_std]
+#![no_main]
+teleprobe_meta::target!(b"rpi-pico");
+
+use defmt::{assert_eq, *};
+use embassy_executor::Spawner;
+use embassy_rp::divider::{signed_divmod, unsigned_divmod};
+use embassy_rp::interp::{self, Interp, Lane};
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    let p = embassy_rp::init(Default::default());
+    info!("Hello World!");
+
+    // Divider
+    {
+        let res = unsigned_divmod(123, 10);
+        assert_eq!((res.quotient, res.remainder), (12, 3));
+        let res = signed_divmod(-7, 2);
+        assert_eq!((res.quotient, res.remainder), (-3, -1));
+    }
+
+    // Lane 0 as a counter: each pop writes accum0 + base0 back into accum0.
+    {
+        let mut interp = Interp::new(p.INTERP0, interp::Config::default());
+        interp.set_accum(Lane::Lane0, 0);
+        interp.set_base(Lane::Lane0, 3);
+        assert_eq!(interp.peek(Lane::Lane0), 3);
+        assert_eq!(interp.pop(Lane::Lane0), 3);
+        assert_eq!(interp.pop(Lane::Lane0), 6);
+        assert_eq!(interp.pop(Lane::Lane0), 9);
+        interp.add_accum(Lane::Lane0, 1);
+        assert_eq!(interp.peek(Lane::Lane0), 13);
+    }
+
+    // Clamp on INTERP1
+    {
+        let mut config = interp::Config::default();
+        config.clamp = true;
+        config.lane0.signed = true;
+        let mut interp = Interp::new(p.INTERP1, config);
+        interp.set_base(Lane::Lane0, 0);
+        interp.set_base(Lane::Lane1, 255);
+        interp.set_accum(Lane::Lane0, 1000);
+        assert_eq!(interp.peek(Lane::Lane0), 255);
+        interp.set_accum(Lane::Lane0, -5i32 as u32);
+        assert_eq!(interp.peek(Lane::Lane0), 0);
+        interp.set_accum(Lane::Lane0, 42);
+        assert_eq!(interp.peek(Lane::Lane0), 42);
+    }
+
+    info!("Test OK");
+    cortex_m::asm::bkpt();
+}
@@ -4,11 +4,16 @@ teleprobe_meta::target!(b"rpi-pico");
 
 use defmt::{assert, assert_eq, assert_ne, *};
 use embassy_executor::Spawner;
+use embassy_rp::bind_interrupts;
 use embassy_rp::gpio::{Input, Level, Output, Pull};
-use embassy_rp::pwm::{Config, InputMode, Pwm};
-use embassy_time::Timer;
+use embassy_rp::pwm::{Config, InputMode, InterruptHandler, Pwm};
+use embassy_time::{Instant, Timer};
 use {defmt_rtt as _, panic_probe as _};
 
+bind_interrupts!(struct Irqs {
+    PWM_IRQ_WRAP => InterruptHandler;
+});
+
 #[embassy_executor::main]
 async fn main(_spawner: Spawner) {
     let mut p = embassy_rp::init(Default::default());
@@ -37,6 +42,21 @@ async fn main(_spawner: Spawner) {
         assert!(ctr < pwm.counter());
     }
 
+    // Test async wrap
+    {
+        let mut pwm = Pwm::new_free(&mut p.PWM_SLICE3, cfg.clone());
+        pwm.bind_wrap_interrupt(Irqs);
+        pwm.wait_for_wrap_async().await;
+        let start = Instant::now();
+        for _ in 0..5 {
+            pwm.wait_for_wrap_async().await;
+        }
+        // 1µs ticks, 10001 ticks per period
+        let elapsed = start.elapsed().as_micros();
+        assert!(elapsed > 49_000);
+        assert!(elapsed < 51_000);
+    }
+
     for invert_a in [false, true] {
         info!("free-running, invert A: {}", invert_a);
         let mut cfg = cfg.clone();
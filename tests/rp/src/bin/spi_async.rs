@@ -79,6 +79,19 @@ async fn main(_spawner: Spawner) {
         defmt::info!("buffer rx length = tx length - OK");
     }
 
+    // transfers much larger than the FIFO
+    {
+        let mut tx_buf = [0_u8; 1024];
+        for (i, b) in tx_buf.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        let mut rx_buf = [0_u8; 1024];
+        spi.transfer(&mut rx_buf, &tx_buf).await.unwrap();
+
+        assert_eq!(rx_buf, tx_buf);
+        defmt::info!("long transfer - OK");
+    }
+
     info!("Test OK");
     cortex_m::asm::bkpt();
 }
@@ -0,0 +1,46 @@
+#![no_std]
+#![no_main]
+teleprobe_meta::target!(b"rpi-pico");
+
+use defmt::{assert, *};
+use embassy_executor::Spawner;
+use embassy_futures::join::join;
+use embassy_rp::time_driver;
+use embassy_time::{Duration, Instant, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+#[embassy_executor::main]
+async fn main(_spawner: Spawner) {
+    embassy_rp::init(Default::default());
+    info!("Hello World!");
+
+    info!("test advancing the time");
+    let start = Instant::now();
+    time_driver::advance(1_000_000);
+    assert!(start.elapsed() >= Duration::from_secs(1));
+
+    info!("test alarms that became due fire right away");
+    let start = Instant::now();
+    join(Timer::after_millis(500), async {
+        Timer::after_millis(10).await;
+        time_driver::advance(1_000_000);
+    })
+    .await;
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(1010));
+    assert!(elapsed < Duration::from_millis(1100));
+
+    info!("test alarms still ahead are re-armed");
+    let start = Instant::now();
+    join(Timer::after_millis(500), async {
+        Timer::after_millis(10).await;
+        time_driver::advance(200_000);
+    })
+    .await;
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(500));
+    assert!(elapsed < Duration::from_millis(550));
+
+    info!("Test OK");
+    cortex_m::asm::bkpt();
+}